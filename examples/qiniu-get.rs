@@ -0,0 +1,96 @@
+//! 使用与服务相同的配置文件和代码路径下载对象，用于验证配置和复现问题
+//!
+//! ```text
+//! cargo run --example qiniu-get --features cli -- --config qiniu.toml stat <key>
+//! ```
+
+use positioned_io::ReadAt;
+use qiniu_download::RangeReader;
+use std::{
+    env,
+    fs::File,
+    io::{stdout, Error as IoError, ErrorKind as IoErrorKind, Result as IoResult, Write},
+    process::exit,
+};
+
+const USAGE: &str = "Usage: qiniu-get [--config <path>] <command> [args]
+
+Commands:
+    get <key> [<output>]                 Download the whole object to <output> or stdout
+    stat <key>                           Print whether the object exists and its size
+    range <key> <from> <len> [<output>]  Download a range of the object to <output> or stdout
+
+The config file is loaded in the same way as services do, from the path given
+by --config or the QINIU environment variable.";
+
+fn main() {
+    env_logger::init();
+    if let Err(err) = run(env::args().skip(1).collect()) {
+        eprintln!("{}", err);
+        exit(1);
+    }
+}
+
+fn run(mut args: Vec<String>) -> IoResult<()> {
+    if args.len() >= 2 && args[0] == "--config" {
+        env::set_var("QINIU", &args[1]);
+        args.drain(..2);
+    }
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    match args.as_slice() {
+        ["get", key] => get(key, None),
+        ["get", key, output] => get(key, Some(output)),
+        ["stat", key] => stat(key),
+        ["range", key, from, len] => range(key, from, len, None),
+        ["range", key, from, len, output] => range(key, from, len, Some(output)),
+        _ => Err(invalid_input(USAGE)),
+    }
+}
+
+fn get(key: &str, output: Option<&str>) -> IoResult<()> {
+    let reader = reader_of(key)?;
+    match output {
+        Some(output) => {
+            let size = reader.download_to(&mut File::create(output)?)?;
+            eprintln!("{} bytes are written to {}", size, output);
+        }
+        None => stdout().write_all(&reader.download()?)?,
+    }
+    Ok(())
+}
+
+fn stat(key: &str) -> IoResult<()> {
+    let reader = reader_of(key)?;
+    if reader.exist()? {
+        println!("key: {}\nexist: true\nsize: {}", key, reader.file_size()?);
+    } else {
+        println!("key: {}\nexist: false", key);
+    }
+    Ok(())
+}
+
+fn range(key: &str, from: &str, len: &str, output: Option<&str>) -> IoResult<()> {
+    let from: u64 = from
+        .parse()
+        .map_err(|_| invalid_input("<from> must be an integer"))?;
+    let len: usize = len
+        .parse()
+        .map_err(|_| invalid_input("<len> must be an integer"))?;
+    let mut buf = vec![0u8; len];
+    let size = reader_of(key)?.read_at(from, &mut buf)?;
+    buf.truncate(size);
+    match output {
+        Some(output) => File::create(output)?.write_all(&buf)?,
+        None => stdout().write_all(&buf)?,
+    }
+    Ok(())
+}
+
+fn reader_of(key: &str) -> IoResult<RangeReader> {
+    RangeReader::from_env(key)
+        .ok_or_else(|| invalid_input("Failed to load config, please specify --config or QINIU env"))
+}
+
+fn invalid_input(message: &str) -> IoError {
+    IoError::new(IoErrorKind::InvalidInput, message)
+}
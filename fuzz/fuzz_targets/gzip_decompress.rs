@@ -0,0 +1,23 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use qiniu_download::gzip_decompress;
+
+// 限制解压后的尺寸，避免解压炸弹耗尽内存
+const MAX_SIZE: u64 = 16 << 20;
+
+// 将数据作为 gzip 响应体解压，不能崩溃，且解压结果不能超过尺寸限制
+fuzz_target!(|data: &[u8]| {
+    let output = match gzip_decompress(data, Some(MAX_SIZE)) {
+        Ok(output) => output,
+        Err(_) => return,
+    };
+    assert!(output.len() as u64 <= MAX_SIZE);
+
+    // 尺寸限制恰好为解压后的尺寸时必须成功，且结果一致；小于解压后的尺寸时必须失败
+    let size = output.len() as u64;
+    assert_eq!(gzip_decompress(data, Some(size)).unwrap(), output);
+    if size > 0 {
+        assert!(gzip_decompress(data, Some(size - 1)).is_err());
+    }
+});
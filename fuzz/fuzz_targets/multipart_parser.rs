@@ -0,0 +1,43 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use qiniu_download::MultipartParser;
+
+const BOUNDARY: &str = "3d6b6a416f9b5";
+
+// 第一个字节决定分块大小，其余数据作为响应体，按分块依次输入解析器
+fuzz_target!(|data: &[u8]| {
+    let (chunk_size, body) = match data.split_first() {
+        Some((&chunk_size, body)) => (chunk_size as usize + 1, body),
+        None => return,
+    };
+    let mut parser = MultipartParser::new(BOUNDARY).unwrap();
+    for chunk in body.chunks(chunk_size) {
+        if parser.feed(chunk).is_err() {
+            break;
+        }
+    }
+    parser.finish().ok();
+
+    // 将其余数据作为分段数据构造合法的响应体，即使数据中包含分隔符，解析结果也必须与其完全一致
+    if body.is_empty() {
+        return;
+    }
+    let mut multipart = format!(
+        "--{}\r\nContent-Range: bytes 0-{}/{}\r\n\r\n",
+        BOUNDARY,
+        body.len() - 1,
+        body.len()
+    )
+    .into_bytes();
+    multipart.extend_from_slice(body);
+    multipart.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+    let mut parser = MultipartParser::new(BOUNDARY).unwrap();
+    let mut parts = Vec::new();
+    for chunk in multipart.chunks(chunk_size) {
+        parts.extend(parser.feed(chunk).unwrap());
+    }
+    parser.finish().unwrap();
+    assert_eq!(parts.len(), 1);
+    assert_eq!(parts[0].data, body);
+});
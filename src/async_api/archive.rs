@@ -0,0 +1,835 @@
+use super::{
+    super::{
+        base::download::RangeReaderBuilder as BaseRangeReaderBuilder,
+        config::{build_range_reader_builder_from_config, Config},
+    },
+    retrier::AsyncRangeReaderWithRangeReader,
+    streaming::{chunks, read_chunk},
+    transform::{StreamTransformer, TransformerChain, TransformerFactory},
+};
+use futures::{pin_mut, stream, StreamExt, TryStreamExt};
+use std::{
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::Semaphore,
+};
+
+const DEFAULT_CONCURRENCY: usize = 4;
+const DEFAULT_MAX_BUFFERED_BYTES: u64 = 256 << 20;
+
+/// 每次读取的最大分块尺寸
+const MAX_CHUNK_SIZE: u64 = 1 << 20;
+
+/// 归档格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArchiveFormat {
+    /// tar 格式
+    Tar,
+    /// zip 格式，对象数据不压缩
+    #[cfg(feature = "zip")]
+    Zip,
+}
+
+/// 归档下载进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveProgress {
+    /// 已经写入归档的对象数
+    pub finished_objects: usize,
+    /// 对象总数
+    pub total_objects: usize,
+    /// 已经写入输出流的字节数
+    pub written_bytes: u64,
+}
+
+/// 多对象归档下载器
+///
+/// 并行下载多个对象，并按照输入顺序将其组装为一个归档流写入指定输出流中，无需服务端参与
+#[derive(Debug, Clone)]
+pub struct AsyncArchiveDownloader {
+    inner: AsyncRangeReaderWithRangeReader,
+    format: ArchiveFormat,
+    concurrency: usize,
+    max_buffered_bytes: u64,
+    transformer: Option<TransformerFactory>,
+}
+
+impl AsyncArchiveDownloader {
+    pub(crate) fn from_base_builder(builder: BaseRangeReaderBuilder) -> Self {
+        Self {
+            inner: AsyncRangeReaderWithRangeReader::from_base_builder(builder).0,
+            format: ArchiveFormat::Tar,
+            concurrency: DEFAULT_CONCURRENCY,
+            max_buffered_bytes: DEFAULT_MAX_BUFFERED_BYTES,
+            transformer: None,
+        }
+    }
+
+    /// 从配置创建多对象归档下载器
+    /// # Arguments
+    ///
+    /// * `config` - 下载配置
+    pub fn from_config(config: &Config) -> Self {
+        Self::from_base_builder(build_range_reader_builder_from_config(
+            String::new(),
+            config,
+        ))
+    }
+
+    /// 设置归档格式
+    ///
+    /// 默认为 tar 格式
+    pub fn format(mut self, format: ArchiveFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// 设置并行下载的分块数
+    ///
+    /// 默认为 4，为 0 时将被视为 1。对象被切分为不超过 1 MiB 的分块下载，获取对象尺寸的 HEAD 请求也按该值并发
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// 设置在内存中缓存的已下载但尚未写入归档的数据总尺寸上限
+    ///
+    /// 默认为 256 MB，为 0 时将被视为 1，最大为 4 GB。每个分块在开始下载前按其尺寸占用额度，写入归档后释放，
+    /// 分块尺寸不超过该上限
+    pub fn max_buffered_bytes(mut self, max_buffered_bytes: u64) -> Self {
+        self.max_buffered_bytes = max_buffered_bytes.clamp(1, u32::MAX as u64);
+        self
+    }
+
+    /// 设置对象数据的转换器
+    ///
+    /// 每个对象都会经过一个由 `factory` 新创建的转换器链，各个分块下载后立即被转换，归档中记录的是转换后的数据。
+    /// tar 格式需要在数据前写入文件尺寸，因此使用转换器时，每个对象转换后的数据将在内存中缓存，转换完成后再写入归档
+    pub fn transformer(
+        mut self,
+        factory: impl Fn() -> TransformerChain + Send + Sync + 'static,
+    ) -> Self {
+        self.transformer = Some(TransformerFactory::new(factory));
+        self
+    }
+
+    /// 下载多个对象并组装为归档写入指定输出流中，返回写入的字节数
+    ///
+    /// 归档内的文件名即为对象名称，文件顺序与输入顺序一致。每写入一个对象都会调用一次进度回调函数。
+    /// 对象名称为空、为绝对路径或包含 `..` 时，在开始下载前返回 [`IoErrorKind::InvalidInput`] 错误，以免解压时写入目标目录之外
+    pub async fn download_to(
+        &self,
+        keys: &[String],
+        writer: &mut (dyn AsyncWrite + Unpin + Send),
+        mut on_progress: impl FnMut(&ArchiveProgress) + Send,
+    ) -> IoResult<u64> {
+        for key in keys {
+            check_entry_name(key)?;
+        }
+        let mut archive = ArchiveWriter::new(self.format, writer);
+        let mut progress = ArchiveProgress {
+            finished_objects: 0,
+            total_objects: keys.len(),
+            written_bytes: 0,
+        };
+        let chunk_size = self.max_buffered_bytes.min(MAX_CHUNK_SIZE);
+        let buffered_bytes = Semaphore::new(self.max_buffered_bytes as usize);
+        let pieces =
+            stream::iter(keys)
+                .map(|key| async move { Ok::<_, IoError>((key, self.inner.file_size(key).await?)) })
+                .buffered(self.concurrency)
+                .map_ok(|(key, size)| {
+                    stream::iter(entry_chunks(size, chunk_size).into_iter().map(
+                        move |(pos, len)| {
+                            Ok(Piece {
+                                key,
+                                size,
+                                pos,
+                                len,
+                            })
+                        },
+                    ))
+                })
+                .try_flatten();
+        // 按输入顺序依次占用额度，保证排在前面的分块总能先获得额度，避免与按顺序输出的归档互相等待
+        let downloads = pieces
+            .and_then(|piece| {
+                let buffered_bytes = &buffered_bytes;
+                async move {
+                    let permit = buffered_bytes
+                        .acquire_many(piece.len.max(1) as u32)
+                        .await
+                        .map_err(|err| IoError::new(IoErrorKind::Other, err))?;
+                    Ok((piece, permit))
+                }
+            })
+            .map_ok(|(piece, permit)| async move {
+                let data = if piece.len > 0 {
+                    read_chunk(&self.inner, piece.key, piece.pos, piece.len).await?
+                } else {
+                    Vec::new()
+                };
+                Ok::<_, IoError>((piece, data, permit))
+            })
+            .try_buffered(self.concurrency);
+        pin_mut!(downloads);
+        while let Some((piece, data, _permit)) = downloads.try_next().await? {
+            if piece.pos == 0 {
+                let transformer = self.transformer.as_ref().map(|factory| factory.make());
+                archive
+                    .begin_entry(piece.key, piece.size, transformer)
+                    .await?;
+            }
+            archive.write_entry_data(&data).await?;
+            if piece.pos + piece.len == piece.size {
+                progress.written_bytes = archive.finish_entry().await?;
+                progress.finished_objects += 1;
+                on_progress(&progress);
+            }
+        }
+        archive.finish().await
+    }
+}
+
+/// 对象的一个分块
+struct Piece<'k> {
+    key: &'k str,
+    size: u64,
+    pos: u64,
+    len: u64,
+}
+
+/// 将对象切分为分块，空对象对应一个空分块，以便为其写入归档条目
+fn entry_chunks(size: u64, chunk_size: u64) -> Vec<(u64, u64)> {
+    if size == 0 {
+        vec![(0, 0)]
+    } else {
+        chunks(size, chunk_size)
+    }
+}
+
+/// 检查归档内的文件名，不能为空、不能为绝对路径，也不能包含 `..`
+fn check_entry_name(name: &str) -> IoResult<()> {
+    let is_absolute = name.starts_with(['/', '\\'])
+        || (name.as_bytes().first().is_some_and(u8::is_ascii_alphabetic)
+            && name.as_bytes().get(1) == Some(&b':'));
+    if name.is_empty() || is_absolute || name.split(['/', '\\']).any(|part| part == "..") {
+        return Err(IoError::new(
+            IoErrorKind::InvalidInput,
+            format!("{:?} is not a safe archive entry name", name),
+        ));
+    }
+    Ok(())
+}
+
+/// 正在写入的归档条目
+struct Entry {
+    name: String,
+    size: u64,
+    transformer: Option<TransformerChain>,
+    /// tar 格式使用转换器时缓存的转换后的数据
+    buffer: Vec<u8>,
+    /// 已经写入的数据尺寸
+    written: u64,
+    /// zip 格式中条目的本地文件头的位置
+    #[cfg(feature = "zip")]
+    offset: u64,
+    #[cfg(feature = "zip")]
+    crc: crc32fast::Hasher,
+}
+
+struct ArchiveWriter<'w> {
+    format: ArchiveFormat,
+    writer: &'w mut (dyn AsyncWrite + Unpin + Send),
+    mtime: u64,
+    written: u64,
+    entry: Option<Entry>,
+    #[cfg(feature = "zip")]
+    central_directory: Vec<u8>,
+    #[cfg(feature = "zip")]
+    entries: usize,
+}
+
+impl<'w> ArchiveWriter<'w> {
+    fn new(format: ArchiveFormat, writer: &'w mut (dyn AsyncWrite + Unpin + Send)) -> Self {
+        Self {
+            format,
+            writer,
+            mtime: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            written: 0,
+            entry: None,
+            #[cfg(feature = "zip")]
+            central_directory: Vec::new(),
+            #[cfg(feature = "zip")]
+            entries: 0,
+        }
+    }
+
+    /// 开始写入一个条目，`size` 为对象尺寸，使用转换器时归档中记录的是转换后的尺寸
+    async fn begin_entry(
+        &mut self,
+        name: &str,
+        size: u64,
+        transformer: Option<TransformerChain>,
+    ) -> IoResult<()> {
+        let entry = Entry {
+            name: name.to_owned(),
+            size,
+            transformer,
+            buffer: Vec::new(),
+            written: 0,
+            #[cfg(feature = "zip")]
+            offset: self.written,
+            #[cfg(feature = "zip")]
+            crc: Default::default(),
+        };
+        match self.format {
+            ArchiveFormat::Tar => {
+                if entry.transformer.is_none() {
+                    self.write(&tar::entry_header(name, size, self.mtime))
+                        .await?;
+                }
+            }
+            #[cfg(feature = "zip")]
+            ArchiveFormat::Zip => {
+                if entry.transformer.is_none() {
+                    // 提前检查对象尺寸，以免写入数据后才发现超出限制
+                    zip::to_u32(size, name)?;
+                }
+                self.write(&zip::local_header(name, self.mtime)?).await?;
+            }
+        }
+        self.entry = Some(entry);
+        Ok(())
+    }
+
+    async fn write_entry_data(&mut self, data: &[u8]) -> IoResult<()> {
+        let mut entry = self.entry.take().expect("archive entry is not begun");
+        let result = match entry.transformer.as_mut() {
+            Some(transformer) => match transformer.transform(data) {
+                Ok(output) => self.write_transformed(&mut entry, &output).await,
+                Err(err) => Err(err),
+            },
+            None => self.write_transformed(&mut entry, data).await,
+        };
+        self.entry = Some(entry);
+        result
+    }
+
+    /// 结束当前条目，返回已经写入输出流的字节数
+    async fn finish_entry(&mut self) -> IoResult<u64> {
+        let mut entry = self.entry.take().expect("archive entry is not begun");
+        if let Some(transformer) = entry.transformer.as_mut() {
+            let output = transformer.finish()?;
+            self.write_transformed(&mut entry, &output).await?;
+        } else if entry.written != entry.size {
+            return Err(IoError::new(
+                IoErrorKind::UnexpectedEof,
+                format!(
+                    "Expected {} bytes of {:?}, got {} bytes",
+                    entry.size, entry.name, entry.written
+                ),
+            ));
+        }
+        match self.format {
+            ArchiveFormat::Tar => {
+                if entry.transformer.is_some() {
+                    let header = tar::entry_header(&entry.name, entry.written, self.mtime);
+                    self.write(&header).await?;
+                    self.write(&entry.buffer).await?;
+                }
+                self.write(&tar::padding(entry.written)).await?;
+            }
+            #[cfg(feature = "zip")]
+            ArchiveFormat::Zip => {
+                let crc = entry.crc.finalize();
+                self.write(&zip::data_descriptor(&entry.name, crc, entry.written)?)
+                    .await?;
+                let central_header =
+                    zip::central_header(&entry.name, crc, entry.written, entry.offset, self.mtime)?;
+                self.central_directory.extend_from_slice(&central_header);
+                self.entries += 1;
+            }
+        }
+        Ok(self.written)
+    }
+
+    /// 写入条目的数据，数据已经经过转换
+    async fn write_transformed(&mut self, entry: &mut Entry, data: &[u8]) -> IoResult<()> {
+        entry.written += data.len() as u64;
+        match self.format {
+            ArchiveFormat::Tar if entry.transformer.is_some() => {
+                entry.buffer.extend_from_slice(data);
+                Ok(())
+            }
+            ArchiveFormat::Tar => self.write(data).await,
+            #[cfg(feature = "zip")]
+            ArchiveFormat::Zip => {
+                entry.crc.update(data);
+                self.write(data).await
+            }
+        }
+    }
+
+    async fn finish(mut self) -> IoResult<u64> {
+        match self.format {
+            ArchiveFormat::Tar => {
+                self.write(&[0u8; tar::BLOCK_SIZE * 2]).await?;
+            }
+            #[cfg(feature = "zip")]
+            ArchiveFormat::Zip => {
+                let central_directory = std::mem::take(&mut self.central_directory);
+                let end = zip::end_of_central_directory(
+                    self.entries,
+                    central_directory.len() as u64,
+                    self.written,
+                )?;
+                self.write(&central_directory).await?;
+                self.write(&end).await?;
+            }
+        }
+        self.writer.flush().await?;
+        Ok(self.written)
+    }
+
+    async fn write(&mut self, data: &[u8]) -> IoResult<()> {
+        self.writer.write_all(data).await?;
+        self.written += data.len() as u64;
+        Ok(())
+    }
+}
+
+mod tar {
+    pub(super) const BLOCK_SIZE: usize = 512;
+    const MAX_OCTAL_SIZE: u64 = 0o77777777777;
+
+    pub(super) fn entry_header(name: &str, size: u64, mtime: u64) -> Vec<u8> {
+        let mut header = Vec::with_capacity(BLOCK_SIZE * 3);
+        if name.len() > 100 {
+            // 使用 GNU 长文件名扩展
+            header.extend_from_slice(&block("././@LongLink", name.len() as u64 + 1, 0, b'L'));
+            header.extend_from_slice(name.as_bytes());
+            header.push(0);
+            header.extend_from_slice(&padding(name.len() as u64 + 1));
+        }
+        header.extend_from_slice(&block(name, size, mtime, b'0'));
+        header
+    }
+
+    pub(super) fn padding(size: u64) -> Vec<u8> {
+        let remainder = (size % BLOCK_SIZE as u64) as usize;
+        if remainder == 0 {
+            Vec::new()
+        } else {
+            vec![0u8; BLOCK_SIZE - remainder]
+        }
+    }
+
+    fn block(name: &str, size: u64, mtime: u64, type_flag: u8) -> [u8; BLOCK_SIZE] {
+        let mut block = [0u8; BLOCK_SIZE];
+        let name = name.as_bytes();
+        let name_len = name.len().min(100);
+        block[..name_len].copy_from_slice(&name[..name_len]);
+        write_octal(&mut block[100..108], 0o644);
+        write_octal(&mut block[108..116], 0);
+        write_octal(&mut block[116..124], 0);
+        write_size(&mut block[124..136], size);
+        write_octal(&mut block[136..148], mtime);
+        block[148..156].copy_from_slice(b"        ");
+        block[156] = type_flag;
+        block[257..263].copy_from_slice(b"ustar\0");
+        block[263..265].copy_from_slice(b"00");
+        let checksum: u32 = block.iter().map(|&b| b as u32).sum();
+        write_octal(&mut block[148..155], checksum as u64);
+        block
+    }
+
+    /// 超过八进制字段表示范围的尺寸使用 GNU 的 base-256 编码，即首字节最高位为 1，其余字节为大端序的数值
+    fn write_size(field: &mut [u8], size: u64) {
+        if size <= MAX_OCTAL_SIZE {
+            write_octal(field, size);
+        } else {
+            let (flag, value) = field.split_at_mut(field.len() - 8);
+            flag.fill(0);
+            flag[0] = 0x80;
+            value.copy_from_slice(&size.to_be_bytes());
+        }
+    }
+
+    fn write_octal(field: &mut [u8], value: u64) {
+        let digits = format!("{:0width$o}", value, width = field.len() - 1);
+        let digits = &digits.as_bytes()[digits.len() - (field.len() - 1)..];
+        field[..digits.len()].copy_from_slice(digits);
+        field[digits.len()] = 0;
+    }
+}
+
+#[cfg(feature = "zip")]
+mod zip {
+    use super::*;
+    use std::convert::TryFrom;
+
+    const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+    const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+    const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+    const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+    const VERSION: u16 = 20;
+    /// 文件名使用 UTF-8 编码，CRC 和尺寸记录在数据之后的数据描述符中
+    const FLAGS: u16 = (1 << 11) | (1 << 3);
+
+    /// 本地文件头，数据的 CRC 和尺寸在写入数据前未知，置为 0 并由数据之后的数据描述符记录
+    pub(super) fn local_header(name: &str, mtime: u64) -> IoResult<Vec<u8>> {
+        let name_len = name_len(name)?;
+        let (dos_time, dos_date) = dos_date_time(mtime);
+        let mut local = Vec::with_capacity(30 + name.len());
+        local.extend_from_slice(&LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes());
+        local.extend_from_slice(&VERSION.to_le_bytes());
+        local.extend_from_slice(&FLAGS.to_le_bytes());
+        local.extend_from_slice(&0u16.to_le_bytes());
+        local.extend_from_slice(&dos_time.to_le_bytes());
+        local.extend_from_slice(&dos_date.to_le_bytes());
+        local.extend_from_slice(&[0u8; 12]);
+        local.extend_from_slice(&name_len.to_le_bytes());
+        local.extend_from_slice(&0u16.to_le_bytes());
+        local.extend_from_slice(name.as_bytes());
+        Ok(local)
+    }
+
+    pub(super) fn data_descriptor(name: &str, crc: u32, size: u64) -> IoResult<Vec<u8>> {
+        let size = to_u32(size, name)?;
+        let mut descriptor = Vec::with_capacity(16);
+        descriptor.extend_from_slice(&DATA_DESCRIPTOR_SIGNATURE.to_le_bytes());
+        descriptor.extend_from_slice(&crc.to_le_bytes());
+        descriptor.extend_from_slice(&size.to_le_bytes());
+        descriptor.extend_from_slice(&size.to_le_bytes());
+        Ok(descriptor)
+    }
+
+    pub(super) fn central_header(
+        name: &str,
+        crc: u32,
+        size: u64,
+        offset: u64,
+        mtime: u64,
+    ) -> IoResult<Vec<u8>> {
+        let size = to_u32(size, name)?;
+        let offset = to_u32(offset, name)?;
+        let name_len = name_len(name)?;
+        let (dos_time, dos_date) = dos_date_time(mtime);
+        let mut central = Vec::with_capacity(46 + name.len());
+        central.extend_from_slice(&CENTRAL_DIRECTORY_HEADER_SIGNATURE.to_le_bytes());
+        central.extend_from_slice(&VERSION.to_le_bytes());
+        central.extend_from_slice(&VERSION.to_le_bytes());
+        central.extend_from_slice(&FLAGS.to_le_bytes());
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&dos_time.to_le_bytes());
+        central.extend_from_slice(&dos_date.to_le_bytes());
+        central.extend_from_slice(&crc.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&name_len.to_le_bytes());
+        central.extend_from_slice(&[0u8; 8]);
+        central.extend_from_slice(&(0o100644u32 << 16).to_le_bytes());
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+        Ok(central)
+    }
+
+    pub(super) fn end_of_central_directory(
+        entries: usize,
+        size: u64,
+        offset: u64,
+    ) -> IoResult<Vec<u8>> {
+        let entries = u16::try_from(entries)
+            .map_err(|_| IoError::new(IoErrorKind::InvalidInput, "too many objects for zip"))?;
+        let size = to_u32(size, "central directory")?;
+        let offset = to_u32(offset, "central directory")?;
+        let mut end = Vec::with_capacity(22);
+        end.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        end.extend_from_slice(&[0u8; 4]);
+        end.extend_from_slice(&entries.to_le_bytes());
+        end.extend_from_slice(&entries.to_le_bytes());
+        end.extend_from_slice(&size.to_le_bytes());
+        end.extend_from_slice(&offset.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        Ok(end)
+    }
+
+    fn name_len(name: &str) -> IoResult<u16> {
+        u16::try_from(name.len())
+            .map_err(|_| IoError::new(IoErrorKind::InvalidInput, "object name is too long"))
+    }
+
+    pub(super) fn to_u32(value: u64, name: &str) -> IoResult<u32> {
+        u32::try_from(value).map_err(|_| {
+            IoError::new(
+                IoErrorKind::InvalidInput,
+                format!("{} exceeds the 4 GB limit of zip", name),
+            )
+        })
+    }
+
+    fn dos_date_time(mtime: u64) -> (u16, u16) {
+        let days = mtime / 86400;
+        let secs = mtime % 86400;
+        let time = ((secs / 3600) << 11) | (((secs % 3600) / 60) << 5) | ((secs % 60) / 2);
+
+        // 将 Unix 天数转换为公历日期
+        let z = days as i64 + 719468;
+        let era = z / 146097;
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        let date = (((year - 1980).max(0) as u64) << 9) | ((month as u64) << 5) | day as u64;
+        (time as u16, date as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::super::Credential, *};
+    use hyper::header::HeaderValue;
+    use tokio::{spawn, sync::oneshot::channel};
+    use warp::{header, path, reply::Response, Filter};
+
+    /// 对象内容为 `content of <key>`，按照 `Range` 头返回其中的部分
+    fn ranged_content(key: &str, range: Option<String>) -> Vec<u8> {
+        let content = format!("content of {}", key).into_bytes();
+        let range = range.as_ref().and_then(|range| {
+            let (from, to) = range.strip_prefix("bytes=")?.split_once('-')?;
+            Some((from.parse::<usize>().ok()?, to.parse::<usize>().ok()?))
+        });
+        match range {
+            Some((from, to)) => content[from..=to.min(content.len() - 1)].to_vec(),
+            None => content,
+        }
+    }
+
+    struct Uppercase;
+
+    impl StreamTransformer for Uppercase {
+        fn transform(&mut self, input: &[u8]) -> IoResult<Vec<u8>> {
+            Ok(input.to_ascii_uppercase())
+        }
+    }
+
+    macro_rules! starts_with_server {
+        ($addr:ident, $routes:ident, $code:block) => {{
+            let (tx, rx) = channel();
+            let ($addr, server) =
+                warp::serve($routes).bind_with_graceful_shutdown(([127, 0, 0, 1], 0), async move {
+                    rx.await.unwrap();
+                });
+            spawn(server);
+            $code;
+            tx.send(()).unwrap();
+        }};
+    }
+
+    #[tokio::test]
+    async fn test_download_tar_archive() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!(String).and(header::optional("Range")).map(
+            |key: String, range: Option<String>| {
+                let content = ranged_content(&key, range);
+                let mut resp = Response::new(content.into());
+                resp.headers_mut()
+                    .insert("Content-Type", HeaderValue::from_static("text/plain"));
+                resp
+            },
+        );
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncArchiveDownloader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    String::new(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            )
+            .concurrency(2);
+            let keys = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+            let mut progresses = Vec::new();
+            let mut archive = Vec::new();
+            let written = downloader
+                .download_to(&keys, &mut archive, |progress| {
+                    progresses.push(progress.finished_objects)
+                })
+                .await?;
+            assert_eq!(written, archive.len() as u64);
+            assert_eq!(progresses, [1, 2, 3]);
+            assert_eq!(archive.len(), 512 * 3 * 2 + 512 * 2);
+
+            for (i, key) in keys.iter().enumerate() {
+                let header = &archive[i * 1024..i * 1024 + 512];
+                assert_eq!(&header[..1], key.as_bytes());
+                assert_eq!(&header[257..262], b"ustar");
+                assert_eq!(&header[124..135], b"00000000014");
+                let content = format!("content of {}", key);
+                assert_eq!(
+                    &archive[i * 1024 + 512..i * 1024 + 512 + content.len()],
+                    content.as_bytes()
+                );
+            }
+            assert!(archive[3 * 1024..].iter().all(|&b| b == 0));
+
+            // 额度仅够缓存一个对象时逐个下载
+            let mut progresses = Vec::new();
+            let mut limited = Vec::new();
+            downloader
+                .to_owned()
+                .max_buffered_bytes(12)
+                .download_to(&keys, &mut limited, |progress| {
+                    progresses.push(progress.finished_objects)
+                })
+                .await?;
+            assert_eq!(progresses, [1, 2, 3]);
+            assert_eq!(limited, archive);
+
+            // 额度小于对象尺寸时按分块下载
+            let mut chunked = Vec::new();
+            downloader
+                .to_owned()
+                .max_buffered_bytes(5)
+                .download_to(&keys, &mut chunked, |_| {})
+                .await?;
+            assert_eq!(chunked, archive);
+
+            let mut transformed = Vec::new();
+            downloader
+                .max_buffered_bytes(5)
+                .transformer(|| TransformerChain::new().then(Uppercase))
+                .download_to(&keys, &mut transformed, |_| {})
+                .await?;
+            assert_eq!(transformed.len(), archive.len());
+            assert_eq!(&transformed[512..512 + 12], b"CONTENT OF A");
+        });
+
+        Ok(())
+    }
+
+    #[cfg(feature = "zip")]
+    #[tokio::test]
+    async fn test_download_zip_archive() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!(String).and(header::optional("Range")).map(
+            |key: String, range: Option<String>| Response::new(ranged_content(&key, range).into()),
+        );
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncArchiveDownloader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    String::new(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            )
+            .format(ArchiveFormat::Zip)
+            .max_buffered_bytes(5);
+            let keys = vec!["a".to_owned(), "b".to_owned()];
+            let mut archive = Vec::new();
+            let written = downloader.download_to(&keys, &mut archive, |_| {}).await?;
+            assert_eq!(written, archive.len() as u64);
+
+            let u16_at = |pos: usize| u16::from_le_bytes([archive[pos], archive[pos + 1]]);
+            let u32_at = |pos: usize| {
+                u32::from_le_bytes([
+                    archive[pos],
+                    archive[pos + 1],
+                    archive[pos + 2],
+                    archive[pos + 3],
+                ])
+            };
+            // 每个条目为 31 字节的本地文件头、12 字节的数据和 16 字节的数据描述符
+            let entry_size = 31 + 12 + 16;
+            for (i, key) in keys.iter().enumerate() {
+                let local = i * entry_size;
+                let content = format!("content of {}", key);
+                assert_eq!(u32_at(local), 0x0403_4b50);
+                assert_eq!(u16_at(local + 6) & (1 << 3), 1 << 3);
+                assert_eq!(&archive[local + 30..local + 31], key.as_bytes());
+                assert_eq!(&archive[local + 31..local + 43], content.as_bytes());
+                assert_eq!(u32_at(local + 43), 0x0807_4b50);
+                assert_eq!(u32_at(local + 47), crc32fast::hash(content.as_bytes()));
+                assert_eq!(u32_at(local + 51), 12);
+                assert_eq!(u32_at(local + 55), 12);
+
+                let central = 2 * entry_size + i * 47;
+                assert_eq!(u32_at(central), 0x0201_4b50);
+                assert_eq!(u32_at(central + 16), crc32fast::hash(content.as_bytes()));
+                assert_eq!(u32_at(central + 20), 12);
+                assert_eq!(u32_at(central + 42), local as u32);
+            }
+            let end = 2 * entry_size + 2 * 47;
+            assert_eq!(u32_at(end), 0x0605_4b50);
+            assert_eq!(u16_at(end + 10), 2);
+            assert_eq!(u32_at(end + 16), 2 * entry_size as u32);
+            assert_eq!(archive.len(), end + 22);
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reject_unsafe_entry_names() -> anyhow::Result<()> {
+        let downloader = AsyncArchiveDownloader::from_base_builder(BaseRangeReaderBuilder::new(
+            "bucket".to_owned(),
+            String::new(),
+            Credential::new("1234567890", "abcdefghijk"),
+            vec!["http://127.0.0.1:1".to_owned()],
+        ));
+        for name in [
+            "",
+            "/etc/passwd",
+            "\\a",
+            "C:a",
+            "a/../../b",
+            "..",
+            "a\\..\\b",
+        ]
+        .iter()
+        {
+            let mut archive = Vec::new();
+            let err = downloader
+                .download_to(&["a".to_owned(), name.to_string()], &mut archive, |_| {})
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), IoErrorKind::InvalidInput, "{:?}", name);
+            assert!(archive.is_empty());
+        }
+        for name in ["a..b", "..a/b", "a/b/", "ab:c"].iter() {
+            check_entry_name(name)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_tar_entry_header_with_large_size() {
+        let header = tar::entry_header("a", 0o77777777777, 0);
+        assert_eq!(&header[124..136], b"77777777777\0");
+
+        let size = 10u64 << 30;
+        let header = tar::entry_header("a", size, 0);
+        assert_eq!(header[124], 0x80);
+        assert!(header[125..128].iter().all(|&b| b == 0));
+        assert_eq!(&header[128..136], &size.to_be_bytes());
+    }
+}
@@ -0,0 +1,476 @@
+use super::{
+    super::{
+        base::download::status_code_of,
+        events::{emit_event, DownloadEvent},
+        settings::global_settings,
+    },
+    dot::ApiName,
+    health::record_slow_request,
+};
+use log::warn;
+use once_cell::sync::Lazy;
+use reqwest::header::HeaderValue;
+use serde::Serialize;
+use serde_json::to_vec as json_to_vec;
+use std::{
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    mem::take,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Mutex, RwLock,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    fs::{create_dir_all, remove_file, rename, File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex as AsyncMutex,
+};
+
+const AUDIT_LOG_FILE_NAME: &str = "audit.log";
+const DEFAULT_MAX_FILE_SIZE: u64 = 100 << 20;
+const DEFAULT_MAX_FILES: usize = 10;
+
+/// 慢请求日志的日志目标，可以单独为其设置日志级别
+const SLOW_REQUEST_LOG_TARGET: &str = "qiniu_download::slow_request";
+
+static AUDIT_LOG_CONFIG: Lazy<RwLock<Option<AuditLogConfig>>> = Lazy::new(Default::default);
+static AUDIT_LOG_WRITER: Lazy<AsyncMutex<Option<AuditLogWriter>>> = Lazy::new(Default::default);
+
+/// 审计日志配置
+///
+/// 审计日志独立于监控打点，每个完成的 API 调用都会以一行 JSON 的形式写入指定目录下的 `audit.log` 文件，
+/// 文件超过指定大小后滚动为 `audit.log.1`、`audit.log.2` 等文件
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AuditLogConfig {
+    dir: PathBuf,
+    max_file_size: u64,
+    max_files: usize,
+}
+
+impl AuditLogConfig {
+    /// 创建审计日志配置
+    /// # Arguments
+    ///
+    /// * `dir` - 审计日志所在目录，不存在时将自动创建
+    #[inline]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_files: DEFAULT_MAX_FILES,
+        }
+    }
+
+    /// 设置单个审计日志文件的最大尺寸，默认为 100 MiB
+    #[inline]
+    pub fn max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// 设置最多保留的审计日志文件数，包括正在写入的文件，默认为 10
+    #[inline]
+    pub fn max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files.max(1);
+        self
+    }
+
+    fn file_path(&self, index: usize) -> PathBuf {
+        if index == 0 {
+            self.dir.join(AUDIT_LOG_FILE_NAME)
+        } else {
+            self.dir.join(format!("{}.{}", AUDIT_LOG_FILE_NAME, index))
+        }
+    }
+}
+
+/// 设置审计日志，设置为 None 表示关闭审计日志
+pub fn set_audit_log(config: Option<AuditLogConfig>) {
+    *AUDIT_LOG_CONFIG.write().unwrap() = config;
+}
+
+fn audit_log_config() -> Option<AuditLogConfig> {
+    AUDIT_LOG_CONFIG.read().unwrap().to_owned()
+}
+
+/// 记录一次 API 调用中发出的请求次数，以及最后一次请求的域名、请求 ID 和各阶段的耗时
+#[derive(Debug, Default)]
+pub(super) struct AuditSlot {
+    request: Mutex<Option<(String, String)>>,
+    stages: Mutex<StageState>,
+    tries: AtomicUsize,
+}
+
+#[derive(Debug, Default)]
+struct StageState {
+    host_selection: Duration,
+    requested_at: Option<Instant>,
+    ttfb: Option<Duration>,
+    parse: Option<Duration>,
+    cache_write: Option<Duration>,
+}
+
+/// 最后一次请求各阶段的耗时
+///
+/// 由于 HTTP 客户端不提供 DNS 解析和建立连接的耗时，二者包含在首字节耗时中
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct StageTimings {
+    /// 选择域名的耗时
+    pub(super) host_selection_ms: u64,
+    /// 从发出请求到收到响应头的耗时，请求失败时为 None
+    pub(super) ttfb_ms: Option<u64>,
+    /// 从收到响应头到 API 调用完成的耗时，不包括解析响应体和写入缓存的耗时
+    pub(super) transfer_ms: Option<u64>,
+    /// 解析响应体的耗时，例如解析多范围响应体，不需要解析时为 None
+    pub(super) parse_ms: Option<u64>,
+    /// 将结果写入内存缓存的耗时，未写入缓存时为 None
+    pub(super) cache_write_ms: Option<u64>,
+}
+
+impl AuditSlot {
+    pub(super) fn record(&self, host: &str, req_id: &HeaderValue) {
+        *self.request.lock().unwrap() = Some((
+            host.to_owned(),
+            String::from_utf8_lossy(req_id.as_bytes()).into_owned(),
+        ));
+        let mut stages = self.stages.lock().unwrap();
+        stages.requested_at = Some(Instant::now());
+        stages.ttfb = None;
+        stages.parse = None;
+        self.tries.fetch_add(1, Relaxed);
+    }
+
+    pub(super) fn record_host_selection(&self, elapsed: Duration) {
+        self.stages.lock().unwrap().host_selection = elapsed;
+    }
+
+    pub(super) fn record_response(&self) {
+        let mut stages = self.stages.lock().unwrap();
+        stages.ttfb = stages
+            .requested_at
+            .map(|requested_at| requested_at.elapsed());
+    }
+
+    /// 累加最后一次请求解析响应体的耗时
+    pub(super) fn record_parse(&self, elapsed: Duration) {
+        *self
+            .stages
+            .lock()
+            .unwrap()
+            .parse
+            .get_or_insert_with(Default::default) += elapsed;
+    }
+
+    /// 累加写入缓存的耗时
+    pub(super) fn record_cache_write(&self, elapsed: Duration) {
+        *self
+            .stages
+            .lock()
+            .unwrap()
+            .cache_write
+            .get_or_insert_with(Default::default) += elapsed;
+    }
+
+    /// 获取最后一次请求各阶段的耗时，尚未发出请求时返回 None
+    pub(super) fn stages(&self) -> Option<StageTimings> {
+        let stages = self.stages.lock().unwrap();
+        let requested_at = stages.requested_at?;
+        let parse = stages.parse.unwrap_or_default();
+        let cache_write = stages.cache_write.unwrap_or_default();
+        Some(StageTimings {
+            host_selection_ms: stages.host_selection.as_millis() as u64,
+            ttfb_ms: stages.ttfb.map(|ttfb| ttfb.as_millis() as u64),
+            transfer_ms: stages.ttfb.map(|ttfb| {
+                requested_at
+                    .elapsed()
+                    .saturating_sub(ttfb)
+                    .saturating_sub(parse)
+                    .saturating_sub(cache_write)
+                    .as_millis() as u64
+            }),
+            parse_ms: stages.parse.map(|parse| parse.as_millis() as u64),
+            cache_write_ms: stages
+                .cache_write
+                .map(|cache_write| cache_write.as_millis() as u64),
+        })
+    }
+
+    fn take(&self) -> (Option<String>, Option<String>) {
+        self.request
+            .lock()
+            .unwrap()
+            .take()
+            .map_or((None, None), |(host, req_id)| (Some(host), Some(req_id)))
+    }
+
+    fn take_tries(&self) -> usize {
+        self.tries.swap(0, Relaxed)
+    }
+
+    fn take_stages(&self) -> Option<StageTimings> {
+        let stages = self.stages();
+        take(&mut *self.stages.lock().unwrap());
+        stages
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct AuditRecord<'a> {
+    timestamp_ms: u64,
+    api: String,
+    key: &'a str,
+    range: Option<String>,
+    bytes: Option<u64>,
+    host: Option<String>,
+    req_id: Option<String>,
+    status: &'static str,
+    status_code: Option<u16>,
+    error: Option<String>,
+    tries: usize,
+    duration_ms: u64,
+    stages: Option<StageTimings>,
+}
+
+/// 生成审计日志中的区域描述，格式与 HTTP Range 请求头相同
+pub(super) fn range_of(ranges: &[(u64, u64)]) -> Option<String> {
+    let ranges = ranges
+        .iter()
+        .filter(|(_, size)| *size > 0)
+        .map(|(from, size)| format!("{}-{}", from, from + size - 1))
+        .collect::<Vec<_>>();
+    if ranges.is_empty() {
+        None
+    } else {
+        Some(format!("bytes={}", ranges.join(",")))
+    }
+}
+
+/// 写入一条 API 调用的审计日志，耗时超过慢请求阈值时同时记录慢请求日志，二者均未启用时直接返回
+///
+/// `outcome` 为成功时表示下载的字节数，不涉及下载数据的 API 为 None
+pub(super) async fn audit_api_call(
+    api: ApiName,
+    key: &str,
+    range: Option<String>,
+    outcome: Result<Option<u64>, &IoError>,
+    slot: &AuditSlot,
+    elapsed: Duration,
+) {
+    let config = audit_log_config();
+    let slow = global_settings()
+        .slow_request_threshold()
+        .is_some_and(|threshold| elapsed >= threshold);
+    if config.is_none() && !slow {
+        return;
+    }
+    let (host, req_id) = slot.take();
+    let stages = slot.take_stages();
+    let (bytes, status, status_code, error) = match outcome {
+        Ok(bytes) => (bytes, "success", None, None),
+        Err(err) => (None, "failed", status_code_of(err), Some(err.to_string())),
+    };
+    let record = AuditRecord {
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or_default(),
+        api: api.to_string(),
+        key,
+        range,
+        bytes,
+        host,
+        req_id,
+        status,
+        status_code,
+        error,
+        tries: slot.take_tries(),
+        duration_ms: elapsed.as_millis() as u64,
+        stages,
+    };
+    if slow {
+        log_slow_request(&record, elapsed);
+    }
+    if let Some(config) = config {
+        if let Err(err) = write_record(config, &record).await {
+            warn!("Failed to write audit log: {}", err);
+        }
+    }
+}
+
+/// 记录一次慢请求，日志以 JSON 格式输出到独立的日志目标，同时累加慢请求计数并产生慢请求事件
+fn log_slow_request(record: &AuditRecord<'_>, elapsed: Duration) {
+    record_slow_request();
+    match serde_json::to_string(record) {
+        Ok(json) => warn!(target: SLOW_REQUEST_LOG_TARGET, "Slow API call: {}", json),
+        Err(err) => warn!(target: SLOW_REQUEST_LOG_TARGET, "Slow API call: {:?}, {}", record, err),
+    }
+    emit_event(|| DownloadEvent::SlowRequest {
+        api: record.api.to_owned(),
+        key: record.key.to_owned(),
+        range: record.range.to_owned(),
+        host: record.host.to_owned(),
+        tries: record.tries,
+        elapsed,
+    });
+}
+
+async fn write_record(config: AuditLogConfig, record: &AuditRecord<'_>) -> IoResult<()> {
+    let mut line = json_to_vec(record)?;
+    line.push(b'\n');
+    let mut writer = AUDIT_LOG_WRITER.lock().await;
+    let reopen = writer
+        .as_ref()
+        .map_or(true, |writer| writer.config != config);
+    if reopen {
+        *writer = Some(AuditLogWriter::open(config).await?);
+    }
+    writer.as_mut().unwrap().write_line(&line).await
+}
+
+#[derive(Debug)]
+struct AuditLogWriter {
+    config: AuditLogConfig,
+    file: File,
+    size: u64,
+}
+
+impl AuditLogWriter {
+    async fn open(config: AuditLogConfig) -> IoResult<Self> {
+        create_dir_all(&config.dir).await?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(config.file_path(0))
+            .await?;
+        let size = file.metadata().await?.len();
+        Ok(Self { config, file, size })
+    }
+
+    async fn write_line(&mut self, line: &[u8]) -> IoResult<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.config.max_file_size {
+            self.rotate().await?;
+        }
+        self.file.write_all(line).await?;
+        self.file.flush().await?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    async fn rotate(&mut self) -> IoResult<()> {
+        self.file.flush().await?;
+        if self.config.max_files > 1 {
+            for index in (1..self.config.max_files).rev() {
+                match rename(
+                    self.config.file_path(index - 1),
+                    self.config.file_path(index),
+                )
+                .await
+                {
+                    Err(err) if err.kind() != IoErrorKind::NotFound => return Err(err),
+                    _ => {}
+                }
+            }
+        } else {
+            remove_file(self.config.file_path(0)).await?;
+        }
+        *self = Self::open(self.config.to_owned()).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value as JsonValue;
+    use tempfile::tempdir;
+    use tokio::fs::{read_dir, read_to_string};
+
+    #[test]
+    fn test_range_of() {
+        assert_eq!(range_of(&[(5, 6)]), Some("bytes=5-10".to_owned()));
+        assert_eq!(
+            range_of(&[(0, 5), (5, 0), (10, 5)]),
+            Some("bytes=0-4,10-14".to_owned())
+        );
+        assert_eq!(range_of(&[(0, 0)]), None);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_rotation() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let config = AuditLogConfig::new(dir.path())
+            .max_file_size(300)
+            .max_files(2);
+        let slot = AuditSlot::default();
+        for i in 0..5 {
+            slot.record("http://io.example.com", &HeaderValue::from(i));
+            let record = AuditRecord {
+                timestamp_ms: 0,
+                api: ApiName::RangeReaderReadAt.to_string(),
+                key: "file",
+                range: range_of(&[(0, 10)]),
+                bytes: Some(10),
+                host: slot.take().0,
+                req_id: Some(i.to_string()),
+                status: "success",
+                status_code: None,
+                error: None,
+                tries: slot.take_tries(),
+                duration_ms: 1,
+                stages: slot.take_stages(),
+            };
+            write_record(config.to_owned(), &record).await?;
+        }
+
+        let mut files = read_dir(dir.path()).await?;
+        let mut count = 0;
+        while files.next_entry().await?.is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 2);
+
+        let content = read_to_string(config.file_path(0)).await?;
+        let last: JsonValue = serde_json::from_str(content.lines().last().unwrap())?;
+        assert_eq!(last["req_id"], "4");
+        assert_eq!(last["host"], "http://io.example.com");
+        assert_eq!(last["range"], "bytes=0-9");
+        assert_eq!(last["status"], "success");
+        assert_eq!(last["tries"], 1);
+        assert!(last["stages"]["ttfb_ms"].is_null());
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_slot_stages() {
+        let slot = AuditSlot::default();
+        assert_eq!(slot.take_stages(), None);
+
+        slot.record_host_selection(Duration::from_millis(3));
+        slot.record("http://io.example.com", &HeaderValue::from_static("req-id"));
+        slot.record_response();
+        let stages = slot.take_stages().unwrap();
+        assert_eq!(stages.host_selection_ms, 3);
+        assert!(stages.ttfb_ms.is_some());
+        assert!(stages.transfer_ms.is_some());
+        assert_eq!(stages.parse_ms, None);
+        assert_eq!(stages.cache_write_ms, None);
+        assert_eq!(slot.take_stages(), None);
+
+        slot.record("http://io.example.com", &HeaderValue::from_static("req-id"));
+        slot.record_response();
+        slot.record_parse(Duration::from_millis(2));
+        slot.record_parse(Duration::from_millis(3));
+        slot.record_cache_write(Duration::from_millis(1));
+        let stages = slot.take_stages().unwrap();
+        assert_eq!(stages.parse_ms, Some(5));
+        assert_eq!(stages.cache_write_ms, Some(1));
+
+        slot.record("http://io.example.com", &HeaderValue::from_static("req-id"));
+        let stages = slot.take_stages().unwrap();
+        assert_eq!(stages.host_selection_ms, 0);
+        assert_eq!(stages.ttfb_ms, None);
+        assert_eq!(stages.transfer_ms, None);
+    }
+}
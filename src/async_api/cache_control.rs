@@ -0,0 +1,162 @@
+use reqwest::header::{HeaderMap, AGE, CACHE_CONTROL, DATE, EXPIRES};
+use std::time::{Duration, SystemTime};
+
+/// 响应头中与缓存相关的指令
+///
+/// 用于限制本地缓存的有效期，使其在 CDN 之后也不会比上游缓存保留更久
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct CacheDirectives {
+    no_store: bool,
+    freshness: Option<Duration>,
+}
+
+impl CacheDirectives {
+    /// 解析 Cache-Control、Expires 和 Age 头，Cache-Control 中的 max-age 优先于 Expires
+    pub(super) fn from_headers(headers: &HeaderMap) -> Self {
+        Self::from_headers_at(headers, SystemTime::now())
+    }
+
+    /// 根据缓存指令限制本地缓存的有效期，返回 None 表示不应缓存
+    ///
+    /// 本地缓存无法再验证，因此 no-cache 与 no-store 同样处理
+    pub(super) fn limit_ttl(&self, ttl: Duration) -> Option<Duration> {
+        if self.no_store {
+            return None;
+        }
+        match self.freshness {
+            Some(freshness) if freshness.is_zero() => None,
+            Some(freshness) => Some(ttl.min(freshness)),
+            None => Some(ttl),
+        }
+    }
+
+    fn from_headers_at(headers: &HeaderMap, now: SystemTime) -> Self {
+        let mut no_store = false;
+        let mut max_age = None;
+        for value in headers.get_all(CACHE_CONTROL) {
+            let value = match value.to_str() {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+            for directive in value.split(',') {
+                let (name, argument) = match directive.split_once('=') {
+                    Some((name, argument)) => {
+                        (name.trim(), Some(argument.trim().trim_matches('"')))
+                    }
+                    None => (directive.trim(), None),
+                };
+                if name.eq_ignore_ascii_case("no-store") || name.eq_ignore_ascii_case("no-cache") {
+                    no_store = true;
+                } else if name.eq_ignore_ascii_case("max-age") {
+                    // 无法解析的 max-age 视为已过期
+                    max_age = Some(argument.and_then(|s| s.parse().ok()).unwrap_or(0));
+                }
+            }
+        }
+
+        let age = headers
+            .get(AGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(0u64);
+        let lifetime = max_age.map(Duration::from_secs).or_else(|| {
+            let expires = headers.get(EXPIRES)?;
+            // 无法解析的 Expires 同样视为已过期
+            let expires = match expires.to_str().ok().and_then(parse_http_date) {
+                Some(expires) => expires,
+                None => return Some(Duration::from_secs(0)),
+            };
+            let date = headers
+                .get(DATE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_http_date)
+                .unwrap_or(now);
+            Some(expires.duration_since(date).unwrap_or_default())
+        });
+
+        Self {
+            no_store,
+            freshness: lifetime.map(|lifetime| lifetime.saturating_sub(Duration::from_secs(age))),
+        }
+    }
+}
+
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    httpdate::parse_http_date(value.trim()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn directives_of(headers: &[(&'static str, &'static str)]) -> CacheDirectives {
+        let mut map = HeaderMap::new();
+        for &(name, value) in headers {
+            map.append(name, HeaderValue::from_static(value));
+        }
+        CacheDirectives::from_headers_at(
+            &map,
+            httpdate::parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_cache_directives() {
+        let ttl = Duration::from_secs(60);
+        assert_eq!(directives_of(&[]).limit_ttl(ttl), Some(ttl));
+        assert_eq!(
+            directives_of(&[("cache-control", "public, max-age=30")]).limit_ttl(ttl),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            directives_of(&[("cache-control", "max-age=\"3600\"")]).limit_ttl(ttl),
+            Some(ttl)
+        );
+        assert_eq!(
+            directives_of(&[("cache-control", "max-age=30"), ("age", "10")]).limit_ttl(ttl),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(
+            directives_of(&[("cache-control", "max-age=30"), ("age", "40")]).limit_ttl(ttl),
+            None
+        );
+        assert_eq!(
+            directives_of(&[
+                ("cache-control", "max-age=30"),
+                ("cache-control", "No-Store")
+            ])
+            .limit_ttl(ttl),
+            None
+        );
+        assert_eq!(
+            directives_of(&[("cache-control", "no-cache")]).limit_ttl(ttl),
+            None
+        );
+        assert_eq!(
+            directives_of(&[("cache-control", "max-age=abc")]).limit_ttl(ttl),
+            None
+        );
+        assert_eq!(
+            directives_of(&[("expires", "Sun, 06 Nov 1994 08:49:47 GMT")]).limit_ttl(ttl),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            directives_of(&[
+                ("date", "Sun, 06 Nov 1994 08:49:00 GMT"),
+                ("expires", "Sun, 06 Nov 1994 08:49:47 GMT"),
+            ])
+            .limit_ttl(ttl),
+            Some(Duration::from_secs(47))
+        );
+        assert_eq!(
+            directives_of(&[
+                ("cache-control", "max-age=5"),
+                ("expires", "Sun, 06 Nov 1994 08:49:47 GMT"),
+            ])
+            .limit_ttl(ttl),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(directives_of(&[("expires", "0")]).limit_ttl(ttl), None);
+    }
+}
@@ -0,0 +1,515 @@
+use super::{
+    super::{
+        base::{
+            download::RangeReaderBuilder as BaseRangeReaderBuilder, upload_policy::UploadPolicy,
+            upload_token::sign_upload_token,
+        },
+        config::{
+            build_range_reader_builder_from_config, check_config_file, with_current_qiniu_config,
+            Config, HttpClientTimeouts,
+        },
+    },
+    cache_dir::cache_dir_path_of,
+    dot::DotRecords,
+    download::{make_download_url, sign_download_url_with_lifetime},
+};
+use futures::future::join_all;
+use reqwest::{
+    header::AUTHORIZATION, Client as HttpClient, Error as ReqwestError, Response as HttpResponse,
+    StatusCode, Url,
+};
+use serde::Serialize;
+use std::{
+    net::SocketAddr,
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    fs::{remove_file, write},
+    net::{lookup_host, TcpStream},
+    time::timeout,
+};
+
+const DIAGNOSE_TIMEOUT: Duration = Duration::from_secs(5);
+const DIAGNOSE_KEY: &str = "qiniu-download-diagnose";
+pub(super) const DIAGNOSE_FILE_NAME: &str = "diagnose.tmp";
+
+/// 单项诊断检查的结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "detail", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum CheckOutcome {
+    /// 检查通过
+    Passed(String),
+    /// 检查失败
+    Failed(String),
+    /// 由于前置检查失败或不适用而跳过
+    Skipped(String),
+}
+
+impl CheckOutcome {
+    /// 检查是否失败
+    #[inline]
+    pub fn is_failed(&self) -> bool {
+        matches!(self, Self::Failed(_))
+    }
+
+    /// 获取检查结果的详细信息
+    #[inline]
+    pub fn detail(&self) -> &str {
+        match self {
+            Self::Passed(detail) | Self::Failed(detail) | Self::Skipped(detail) => detail,
+        }
+    }
+}
+
+/// 被诊断的域名用途
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum HostRole {
+    /// IO 域名
+    Io,
+    /// UC 域名
+    Uc,
+    /// 打点监控域名
+    Monitor,
+}
+
+/// 单个域名的诊断结果
+#[derive(Debug, Clone, Serialize)]
+pub struct HostReport {
+    role: HostRole,
+    url: String,
+    dns: CheckOutcome,
+    tcp: CheckOutcome,
+    request: CheckOutcome,
+}
+
+impl HostReport {
+    /// 获取域名用途
+    #[inline]
+    pub fn role(&self) -> HostRole {
+        self.role
+    }
+
+    /// 获取域名 URL
+    #[inline]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// 获取 DNS 解析结果
+    #[inline]
+    pub fn dns(&self) -> &CheckOutcome {
+        &self.dns
+    }
+
+    /// 获取 TCP 连接结果
+    #[inline]
+    pub fn tcp(&self) -> &CheckOutcome {
+        &self.tcp
+    }
+
+    /// 获取示例请求的结果，HTTPS 域名的 TLS 握手也在此项中检查
+    #[inline]
+    pub fn request(&self) -> &CheckOutcome {
+        &self.request
+    }
+
+    fn is_healthy(&self) -> bool {
+        !self.dns.is_failed() && !self.tcp.is_failed() && !self.request.is_failed()
+    }
+}
+
+/// 诊断报告
+///
+/// 可以通过 `serde_json` 序列化后附在工单中
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticReport {
+    config: CheckOutcome,
+    cache_dir: CheckOutcome,
+    hosts: Vec<HostReport>,
+}
+
+impl DiagnosticReport {
+    /// 获取配置文件解析结果
+    #[inline]
+    pub fn config(&self) -> &CheckOutcome {
+        &self.config
+    }
+
+    /// 获取缓存目录可写性检查结果
+    #[inline]
+    pub fn cache_dir(&self) -> &CheckOutcome {
+        &self.cache_dir
+    }
+
+    /// 获取所有域名的诊断结果
+    #[inline]
+    pub fn hosts(&self) -> &[HostReport] {
+        &self.hosts
+    }
+
+    /// 所有检查项是否都没有失败
+    pub fn is_healthy(&self) -> bool {
+        !self.config.is_failed()
+            && !self.cache_dir.is_failed()
+            && self.hosts.iter().all(|host| host.is_healthy())
+    }
+}
+
+/// 诊断当前七牛环境配置
+///
+/// 重新解析 QINIU 或 QINIU_MULTI_CLUSTER 环境变量指定的配置文件，检查缓存目录是否可写，
+/// 并对 `key` 所在集群的每个 IO，UC 和打点监控域名检查 DNS 解析，TCP 连接以及一次签名请求
+pub async fn diagnose(key: &str) -> DiagnosticReport {
+    let config_outcome = match check_config_file() {
+        Ok(path) => CheckOutcome::Passed(path.display().to_string()),
+        Err(err) => CheckOutcome::Failed(err),
+    };
+    match current_config(key) {
+        Some(config) => diagnose_with(config_outcome, &config).await,
+        None => DiagnosticReport {
+            config: config_outcome,
+            cache_dir: check_cache_dir().await,
+            hosts: vec![],
+        },
+    }
+}
+
+pub(super) fn current_config(key: &str) -> Option<Config> {
+    with_current_qiniu_config(|configurable| {
+        configurable.and_then(|configurable| configurable.with_key(key, |config| config.to_owned()))
+    })
+}
+
+/// 诊断指定的七牛配置
+///
+/// 与 [`diagnose`] 相同，但不会解析环境变量指定的配置文件
+pub async fn diagnose_config(config: &Config) -> DiagnosticReport {
+    diagnose_with(
+        CheckOutcome::Skipped("config is given directly".to_owned()),
+        config,
+    )
+    .await
+}
+
+async fn diagnose_with(config_outcome: CheckOutcome, config: &Config) -> DiagnosticReport {
+    let builder = build_range_reader_builder_from_config(DIAGNOSE_KEY.to_owned(), config);
+    let http_client = HttpClientTimeouts::new(builder.base_timeout, builder.dial_timeout)
+        .with_dns_cache(builder.dns_cache_min_ttl, builder.dns_cache_max_ttl)
+        .with_redirect_policy(builder.redirect_policy)
+        .async_http_client();
+    let io_hosts = builder
+        .io_urls
+        .iter()
+        .map(|url| diagnose_host(HostRole::Io, url, &builder, &http_client));
+    let uc_hosts = builder
+        .uc_urls
+        .iter()
+        .map(|url| diagnose_host(HostRole::Uc, url, &builder, &http_client));
+    let monitor_hosts = builder
+        .monitor_urls
+        .iter()
+        .map(|url| diagnose_host(HostRole::Monitor, url, &builder, &http_client));
+    let (cache_dir, io_hosts, uc_hosts, monitor_hosts) = futures::join!(
+        check_cache_dir(),
+        join_all(io_hosts),
+        join_all(uc_hosts),
+        join_all(monitor_hosts)
+    );
+    DiagnosticReport {
+        config: config_outcome,
+        cache_dir,
+        hosts: io_hosts
+            .into_iter()
+            .chain(uc_hosts)
+            .chain(monitor_hosts)
+            .collect(),
+    }
+}
+
+async fn check_cache_dir() -> CheckOutcome {
+    let path = match cache_dir_path_of(DIAGNOSE_FILE_NAME).await {
+        Ok(path) => path,
+        Err(err) => return CheckOutcome::Failed(format!("cannot create cache dir: {}", err)),
+    };
+    if let Err(err) = write(&path, b"").await {
+        return CheckOutcome::Failed(format!("cannot write {}: {}", path.display(), err));
+    }
+    remove_file(&path).await.ok();
+    CheckOutcome::Passed(
+        path.parent()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default(),
+    )
+}
+
+async fn diagnose_host(
+    role: HostRole,
+    url: &str,
+    builder: &BaseRangeReaderBuilder,
+    http_client: &HttpClient,
+) -> HostReport {
+    let mut report = HostReport {
+        role,
+        url: url.to_owned(),
+        dns: CheckOutcome::Skipped("invalid url".to_owned()),
+        tcp: CheckOutcome::Skipped("dns resolution failed".to_owned()),
+        request: CheckOutcome::Skipped("tcp connection failed".to_owned()),
+    };
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            report.dns = CheckOutcome::Failed(format!("invalid url: {}", err));
+            return report;
+        }
+    };
+    let (host, port) = match (parsed.host_str(), parsed.port_or_known_default()) {
+        (Some(host), Some(port)) => (host.to_owned(), port),
+        _ => {
+            report.dns = CheckOutcome::Failed("url has no host or port".to_owned());
+            return report;
+        }
+    };
+
+    let addrs = match resolve(&host, port).await {
+        Ok(addrs) => addrs,
+        Err(err) => {
+            report.dns = CheckOutcome::Failed(err);
+            return report;
+        }
+    };
+    report.dns = CheckOutcome::Passed(
+        addrs
+            .iter()
+            .map(|addr| addr.ip().to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+
+    report.tcp = connect(&addrs).await;
+    if report.tcp.is_failed() {
+        return report;
+    }
+
+    report.request = match role {
+        HostRole::Io => request_io(url, builder, http_client).await,
+        HostRole::Uc => request_uc(url, builder, http_client).await,
+        HostRole::Monitor => request_monitor(url, builder, http_client).await,
+    };
+    report
+}
+
+async fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+    match timeout(DIAGNOSE_TIMEOUT, lookup_host((host, port))).await {
+        Ok(Ok(addrs)) => {
+            let addrs: Vec<_> = addrs.collect();
+            if addrs.is_empty() {
+                Err(format!("{} resolves to no address", host))
+            } else {
+                Ok(addrs)
+            }
+        }
+        Ok(Err(err)) => Err(format!("cannot resolve {}: {}", host, err)),
+        Err(_) => Err(format!("resolving {} timed out", host)),
+    }
+}
+
+async fn connect(addrs: &[SocketAddr]) -> CheckOutcome {
+    let mut errors = Vec::with_capacity(addrs.len());
+    for addr in addrs {
+        match timeout(DIAGNOSE_TIMEOUT, TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => return CheckOutcome::Passed(format!("connected to {}", addr)),
+            Ok(Err(err)) => errors.push(format!("{}: {}", addr, err)),
+            Err(_) => errors.push(format!("{}: timed out", addr)),
+        }
+    }
+    CheckOutcome::Failed(errors.join("; "))
+}
+
+async fn request_io(
+    io_url: &str,
+    builder: &BaseRangeReaderBuilder,
+    http_client: &HttpClient,
+) -> CheckOutcome {
+    let url = make_download_url(
+        &builder.url_options.base_url(io_url),
+        builder.credential.access_key(),
+        &builder.bucket,
+        DIAGNOSE_KEY,
+        builder.use_getfile_api,
+        true,
+    );
+    let signed_url = match Url::parse(&url)
+        .map_err(|err| err.to_string())
+        .and_then(|url| {
+            sign_download_url_with_lifetime(
+                &builder.credential,
+                url,
+                builder
+                    .private_url_lifetime
+                    .unwrap_or_else(|| Duration::from_secs(3600)),
+            )
+            .map_err(|err| err.to_string())
+        }) {
+        Ok(signed_url) => signed_url,
+        Err(err) => return CheckOutcome::Failed(format!("cannot sign {}: {}", url, err)),
+    };
+    // 探测对象通常不存在，404 同样说明域名和签名都是可用的
+    check_response(
+        http_client
+            .head(signed_url)
+            .timeout(DIAGNOSE_TIMEOUT)
+            .send()
+            .await,
+        |status| status.is_success() || status == StatusCode::NOT_FOUND,
+    )
+}
+
+async fn request_uc(
+    uc_url: &str,
+    builder: &BaseRangeReaderBuilder,
+    http_client: &HttpClient,
+) -> CheckOutcome {
+    let url = match Url::parse_with_params(
+        &format!("{}/v4/query", uc_url),
+        &[
+            ("ak", builder.credential.access_key()),
+            ("bucket", &builder.bucket),
+        ],
+    ) {
+        Ok(url) => url,
+        Err(err) => return CheckOutcome::Failed(format!("invalid url: {}", err)),
+    };
+    check_response(
+        http_client.get(url).timeout(DIAGNOSE_TIMEOUT).send().await,
+        |status| status == StatusCode::OK,
+    )
+}
+
+async fn request_monitor(
+    monitor_url: &str,
+    builder: &BaseRangeReaderBuilder,
+    http_client: &HttpClient,
+) -> CheckOutcome {
+    let credential = builder
+        .dot_credential
+        .as_ref()
+        .unwrap_or(&builder.credential);
+    let uptoken = sign_upload_token(
+        credential,
+        &UploadPolicy::new_for_bucket(
+            builder
+                .dot_bucket
+                .to_owned()
+                .unwrap_or_else(|| builder.bucket.to_owned()),
+            SystemTime::now() + DIAGNOSE_TIMEOUT,
+        ),
+    );
+    check_response(
+        http_client
+            .post(format!("{}/v1/stat", monitor_url))
+            .header(AUTHORIZATION, format!("UpToken {}", uptoken))
+            .json(&DotRecords::default())
+            .timeout(DIAGNOSE_TIMEOUT)
+            .send()
+            .await,
+        |status| status == StatusCode::OK,
+    )
+}
+
+fn check_response(
+    result: Result<HttpResponse, ReqwestError>,
+    is_expected: impl FnOnce(StatusCode) -> bool,
+) -> CheckOutcome {
+    match result {
+        Ok(resp) if is_expected(resp.status()) => {
+            CheckOutcome::Passed(format!("status code {}", resp.status().as_u16()))
+        }
+        Ok(resp) => match resp.status() {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => CheckOutcome::Failed(format!(
+                "credential is rejected, status code {}",
+                resp.status().as_u16()
+            )),
+            status => CheckOutcome::Failed(format!("unexpected status code {}", status.as_u16())),
+        },
+        Err(err) if err.is_timeout() => CheckOutcome::Failed(format!("request timed out: {}", err)),
+        Err(err) => CheckOutcome::Failed(format!("request failed: {}", err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, TcpListener as StdTcpListener};
+    use tokio::{sync::oneshot::channel, task::spawn};
+    use warp::{path, Filter};
+
+    macro_rules! starts_with_server {
+        ($addr:ident, $routes:ident, $code:block) => {{
+            let (tx, rx) = channel();
+            let ($addr, server) =
+                warp::serve($routes).bind_with_graceful_shutdown(([127, 0, 0, 1], 0), async move {
+                    rx.await.unwrap();
+                });
+            spawn(server);
+            $code;
+            tx.send(()).unwrap();
+        }};
+    }
+
+    #[tokio::test]
+    async fn test_diagnose_config() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!("qiniu-download-diagnose")
+            .and(warp::head())
+            .map(|| StatusCode::NOT_FOUND);
+        let uc_routes = path!("v4" / "query").map(|| StatusCode::UNAUTHORIZED);
+        let routes = io_routes.or(uc_routes);
+
+        // 先占用端口再释放，保证该端口上没有服务在监听
+        let closed_port = StdTcpListener::bind((Ipv4Addr::LOCALHOST, 0))?
+            .local_addr()?
+            .port();
+
+        starts_with_server!(addr, routes, {
+            let config =
+                Config::builder("ak", "sk", "bucket", Some(vec![format!("http://{}", addr)]))
+                    .uc_urls(Some(vec![format!("http://{}", addr)]))
+                    .monitor_urls(Some(vec![format!("http://127.0.0.1:{}", closed_port)]))
+                    .use_getfile_api(Some(false))
+                    .build();
+            let report = diagnose_config(&config).await;
+
+            assert!(matches!(report.config(), CheckOutcome::Skipped(_)));
+            assert!(!report.cache_dir().is_failed());
+            assert_eq!(report.hosts().len(), 3);
+
+            let io = &report.hosts()[0];
+            assert_eq!(io.role(), HostRole::Io);
+            assert!(matches!(io.dns(), CheckOutcome::Passed(_)));
+            assert!(matches!(io.tcp(), CheckOutcome::Passed(_)));
+            assert_eq!(
+                io.request(),
+                &CheckOutcome::Passed("status code 404".to_owned())
+            );
+
+            let uc = &report.hosts()[1];
+            assert_eq!(uc.role(), HostRole::Uc);
+            assert!(uc.request().is_failed());
+            assert!(uc.request().detail().contains("credential is rejected"));
+
+            let monitor = &report.hosts()[2];
+            assert_eq!(monitor.role(), HostRole::Monitor);
+            assert!(monitor.tcp().is_failed());
+            assert!(matches!(monitor.request(), CheckOutcome::Skipped(_)));
+
+            assert!(!report.is_healthy());
+            let json = serde_json::to_value(&report)?;
+            assert_eq!(json["hosts"][0]["request"]["status"], "passed");
+        });
+        Ok(())
+    }
+}
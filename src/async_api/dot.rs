@@ -1,27 +1,35 @@
 use super::{
-    super::base::{
-        credential::Credential, upload_policy::UploadPolicy, upload_token::sign_upload_token,
+    super::{
+        base::{
+            credential::Credential, dot_sampler::DotSampler, upload_policy::UploadPolicy,
+            upload_token::sign_upload_token,
+        },
+        events::{emit_event, DownloadEvent},
     },
+    audit::StageTimings,
     cache_dir::cache_dir_path_of,
+    health::{record_dot_backlog_cleared, record_dot_records_flushed, record_dot_upload},
     host_selector::{HostInfo, HostSelector, PunishResult},
 };
 use fd_lock::RwLock as FdRwLock;
 use futures::future::join_all;
 use log::{debug, info, warn};
+use once_cell::sync::Lazy;
 use reqwest::{header::AUTHORIZATION, Client as HttpClient, StatusCode};
 use scc::HashMap;
 use serde::{de::Error as DeserializeError, Deserialize, Serialize};
 use serde_json::Value as JSONValue;
 use std::{
-    collections::HashMap as StdHashMap,
+    collections::{HashMap as StdHashMap, HashSet},
     convert::TryFrom,
+    env::consts::{ARCH, OS},
     fmt::{self, Debug},
     future::Future,
     io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult, SeekFrom},
     ops::Deref,
     sync::{
         atomic::{AtomicBool, Ordering::Relaxed},
-        Arc,
+        Arc, Mutex as StdMutex,
     },
     time::{Duration, Instant, SystemTime},
 };
@@ -29,26 +37,27 @@ use tap::prelude::*;
 use tokio::{
     fs::{File, OpenOptions},
     io::{AsyncBufReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter},
+    runtime::Handle as RuntimeHandle,
     spawn,
     sync::Mutex,
+    task::JoinHandle,
+    time::sleep,
 };
 
 static DOTTING_DISABLED: AtomicBool = AtomicBool::new(false);
+static SDK_STARTED_HOSTS: Lazy<StdMutex<HashSet<u32>>> = Lazy::new(Default::default);
 
 /// 禁止打点功能
-
 pub fn disable_dotting() {
     DOTTING_DISABLED.store(true, Relaxed)
 }
 
 /// 启用打点功能
-
 pub fn enable_dotting() {
     DOTTING_DISABLED.store(false, Relaxed)
 }
 
 /// 打点功能是否启用
-
 pub fn is_dotting_disabled() -> bool {
     DOTTING_DISABLED.load(Relaxed)
 }
@@ -56,19 +65,16 @@ pub fn is_dotting_disabled() -> bool {
 static DOT_UPLOADING_DISABLED: AtomicBool = AtomicBool::new(false);
 
 /// 禁止打点上传功能
-
 pub fn disable_dot_uploading() {
     DOT_UPLOADING_DISABLED.store(true, Relaxed)
 }
 
 /// 启用打点上传功能
-
 pub fn enable_dot_uploading() {
     DOT_UPLOADING_DISABLED.store(false, Relaxed)
 }
 
 /// 打点上传功能是否启用
-
 pub fn is_dot_uploading_disabled() -> bool {
     DOT_UPLOADING_DISABLED.load(Relaxed)
 }
@@ -93,28 +99,38 @@ impl fmt::Display for DotType {
 #[serde(rename_all = "snake_case")]
 pub(super) enum ApiName {
     IoGetfile,
+    CdnGetfile,
     MonitorV1Stat,
     UcV4Query,
+    UpdateHosts,
+    RejectHostsUpdate,
     RangeReaderReadAt,
     RangeReaderReadMultiRanges,
     RangeReaderExist,
     RangeReaderFileSize,
+    RangeReaderStat,
     RangeReaderDownloadTo,
     RangeReaderReadLastBytes,
+    RangeReaderChecksumRange,
 }
 
 impl fmt::Display for ApiName {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::IoGetfile => write!(f, "io_getfile"),
+            Self::CdnGetfile => write!(f, "cdn_getfile"),
             Self::MonitorV1Stat => write!(f, "monitor_v1_stat"),
             Self::UcV4Query => write!(f, "uc_v4_query"),
+            Self::UpdateHosts => write!(f, "update_hosts"),
+            Self::RejectHostsUpdate => write!(f, "reject_hosts_update"),
             Self::RangeReaderReadAt => write!(f, "range_reader_read_at"),
             Self::RangeReaderReadMultiRanges => write!(f, "range_reader_read_multi_ranges"),
             Self::RangeReaderExist => write!(f, "range_reader_exist"),
             Self::RangeReaderFileSize => write!(f, "range_reader_file_size"),
+            Self::RangeReaderStat => write!(f, "range_reader_stat"),
             Self::RangeReaderDownloadTo => write!(f, "range_reader_download_to"),
             Self::RangeReaderReadLastBytes => write!(f, "range_reader_read_last_bytes"),
+            Self::RangeReaderChecksumRange => write!(f, "range_reader_checksum_range"),
         }
     }
 }
@@ -122,11 +138,25 @@ impl fmt::Display for ApiName {
 #[derive(Clone, Debug, Default)]
 pub(super) struct Dotter {
     inner: Option<Arc<DotterInner>>,
+    sampler: Arc<DotSampler>,
+    latency_buckets_ms: Option<Arc<[u64]>>,
+    _ticker: Option<Arc<DotterTicker>>,
+}
+
+/// 后台定时上传打点记录的任务，最后一个 [`Dotter`] 被释放时取消
+#[derive(Debug)]
+struct DotterTicker(JoinHandle<()>);
+
+impl Drop for DotterTicker {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
 }
 
 struct DotterInner {
     credential: Credential,
     bucket: String,
+    token_lifetime: Duration,
     monitor_selector: HostSelector,
     buffered_records: AsyncDotRecordsMap,
     buffered_file: Mutex<FdRwLock<File>>,
@@ -142,6 +172,7 @@ impl Debug for DotterInner {
         f.debug_struct("DotterInner")
             .field("credential", &self.credential)
             .field("bucket", &self.bucket)
+            .field("token_lifetime", &self.token_lifetime)
             .field("monitor_selector", &self.monitor_selector)
             .field("buffered_file", &self.buffered_file)
             .field("interval", &self.interval)
@@ -169,6 +200,7 @@ impl Dotter {
         max_punished_times: Option<usize>,
         max_punished_hosts_percent: Option<u8>,
         base_timeout: Option<Duration>,
+        token_lifetime: Option<Duration>,
     ) -> Dotter {
         if !monitor_urls.is_empty() {
             if let Ok(buffered_file_path) = cache_dir_path_of(DOT_FILE_NAME).await {
@@ -186,24 +218,47 @@ impl Dotter {
                         .base_timeout(base_timeout.unwrap_or_else(|| Duration::from_secs(1)))
                         .build()
                         .await;
+                    let inner = Arc::new(DotterInner {
+                        credential,
+                        bucket,
+                        token_lifetime: token_lifetime.unwrap_or_else(|| Duration::from_secs(30)),
+                        monitor_selector,
+                        http_client,
+                        buffered_records: Default::default(),
+                        buffered_file: Mutex::new(FdRwLock::new(buffer_file)),
+                        interval: interval.unwrap_or_else(|| Duration::from_secs(10)),
+                        uploaded_at: Instant::now(),
+                        max_buffer_size: max_buffer_size.unwrap_or(1 << 20),
+                        tries: tries.unwrap_or(10),
+                    });
                     return Self {
-                        inner: Some(Arc::new(DotterInner {
-                            credential,
-                            bucket,
-                            monitor_selector,
-                            http_client,
-                            buffered_records: Default::default(),
-                            buffered_file: Mutex::new(FdRwLock::new(buffer_file)),
-                            interval: interval.unwrap_or_else(|| Duration::from_secs(10)),
-                            uploaded_at: Instant::now(),
-                            max_buffer_size: max_buffer_size.unwrap_or(1 << 20),
-                            tries: tries.unwrap_or(10),
-                        })),
+                        _ticker: DotterTicker::spawn(&inner),
+                        inner: Some(inner),
+                        sampler: Default::default(),
+                        latency_buckets_ms: None,
                     };
                 }
             }
         }
-        Self { inner: None }
+        Default::default()
+    }
+
+    pub(super) fn with_sampler(mut self, sampler: DotSampler) -> Self {
+        self.sampler = Arc::new(sampler);
+        self
+    }
+
+    /// 设置耗时直方图的分桶边界，设置后打点记录中将包含耗时直方图
+    pub(super) fn with_latency_buckets(mut self, latency_buckets_ms: Option<Vec<u64>>) -> Self {
+        self.latency_buckets_ms = latency_buckets_ms
+            .map(|mut buckets| {
+                buckets.sort_unstable();
+                buckets.dedup();
+                buckets
+            })
+            .filter(|buckets| !buckets.is_empty())
+            .map(Arc::from);
+        self
     }
 
     pub(super) async fn dot(
@@ -216,12 +271,27 @@ impl Dotter {
         if is_dotting_disabled() {
             debug!("dotting is disabled")
         } else if let Some(inner) = self.inner.as_ref() {
+            let count = match self.sampler.sample() {
+                Some(count) => count,
+                None => return Ok(()),
+            };
             inner
-                .fast_dot(dot_type, api_name, successful, elapsed_duration)
+                .fast_dot(
+                    dot_type,
+                    api_name,
+                    successful,
+                    elapsed_duration,
+                    count,
+                    self.latency_buckets_ms.as_deref(),
+                )
                 .await;
             inner
                 .lock_buffered_file(|mut buffered_file| async move {
                     inner.flush_to_file(&mut buffered_file).await?;
+                    if self.sampler.escalates() {
+                        self.sampler
+                            .observe_buffer_size(buffered_file.metadata().await?.len());
+                    }
                     if inner.is_time_to_upload(&buffered_file).await? {
                         self.async_upload();
                     }
@@ -250,6 +320,108 @@ impl Dotter {
         Ok(())
     }
 
+    /// 记录一次从两个域名读取的数据不一致
+    pub(super) async fn diverged(&self) -> IoResult<()> {
+        if is_dotting_disabled() {
+            debug!("dotting is disabled")
+        } else if let Some(inner) = self.inner.as_ref() {
+            inner
+                .buffered_records
+                .merge_with_record(DotRecord::diverged())
+                .await;
+            inner
+                .lock_buffered_file(|mut buffered_file| async move {
+                    inner.flush_to_file(&mut buffered_file).await?;
+                    if inner.is_time_to_upload(&buffered_file).await? {
+                        self.async_upload();
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 记录同步接口内部运行时的请求并发数和排队请求数，上传前只保留峰值
+    pub(super) async fn sync_runtime_load(
+        &self,
+        in_flight_requests: usize,
+        queued_requests: usize,
+    ) -> IoResult<()> {
+        if is_dotting_disabled() {
+            debug!("dotting is disabled")
+        } else if let Some(inner) = self.inner.as_ref() {
+            inner
+                .buffered_records
+                .merge_with_record(DotRecord::sync_runtime_load(
+                    in_flight_requests,
+                    queued_requests,
+                ))
+                .await;
+            inner
+                .lock_buffered_file(|mut buffered_file| async move {
+                    inner.flush_to_file(&mut buffered_file).await?;
+                    if inner.is_time_to_upload(&buffered_file).await? {
+                        self.async_upload();
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 记录 API 调用最后一次请求各阶段的耗时，上传前只保留各阶段的平均耗时
+    pub(super) async fn stage_timings(
+        &self,
+        api_name: ApiName,
+        stages: &StageTimings,
+    ) -> IoResult<()> {
+        if is_dotting_disabled() {
+            debug!("dotting is disabled")
+        } else if let Some(inner) = self.inner.as_ref() {
+            inner
+                .buffered_records
+                .merge_with_record(DotRecord::stage_timings(api_name, stages))
+                .await;
+            inner
+                .lock_buffered_file(|mut buffered_file| async move {
+                    inner.flush_to_file(&mut buffered_file).await?;
+                    if inner.is_time_to_upload(&buffered_file).await? {
+                        self.async_upload();
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 记录 SDK 版本、操作系统和域名列表的 CRC32，每个进程对每组域名只记录一次
+    pub(super) async fn sdk_started(&self, hosts_crc32: u32) -> IoResult<()> {
+        if is_dotting_disabled() {
+            debug!("dotting is disabled")
+        } else if let Some(inner) = self.inner.as_ref() {
+            if !SDK_STARTED_HOSTS.lock().unwrap().insert(hosts_crc32) {
+                return Ok(());
+            }
+            inner
+                .buffered_records
+                .merge_with_record(DotRecord::sdk_started(hosts_crc32))
+                .await;
+            inner
+                .lock_buffered_file(|mut buffered_file| async move {
+                    inner.flush_to_file(&mut buffered_file).await?;
+                    if inner.is_time_to_upload(&buffered_file).await? {
+                        self.async_upload();
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
     fn async_upload(&self) {
         if let Some(inner) = self.inner.as_ref() {
             let inner = inner.to_owned();
@@ -268,6 +440,32 @@ impl Dotter {
     }
 }
 
+impl DotterTicker {
+    fn spawn(inner: &Arc<DotterInner>) -> Option<Arc<Self>> {
+        let interval = inner.interval;
+        if interval == Duration::from_millis(0) {
+            return None;
+        }
+        let runtime = RuntimeHandle::try_current().ok()?;
+        let inner = Arc::downgrade(inner);
+        Some(Arc::new(Self(runtime.spawn(async move {
+            loop {
+                sleep(interval).await;
+                match inner.upgrade() {
+                    Some(inner) => {
+                        inner
+                            .upload_pending()
+                            .await
+                            .tap_err(|err| warn!("failed to upload pending dots: {:?}", err))
+                            .ok();
+                    }
+                    None => break,
+                }
+            }
+        }))))
+    }
+}
+
 impl DotterInner {
     async fn fast_dot(
         &self,
@@ -275,24 +473,31 @@ impl DotterInner {
         api_name: ApiName,
         successful: bool,
         elapsed_duration: Duration,
+        count: usize,
+        latency_buckets_ms: Option<&[u64]>,
     ) {
+        let latency_histogram = latency_buckets_ms.map(|buckets_ms| {
+            LatencyHistogram::new(buckets_ms, successful, elapsed_duration.as_millis(), count)
+        });
         let record = if successful {
             DotRecord::new(
                 dot_type,
                 api_name,
-                1,
+                count,
                 Default::default(),
                 elapsed_duration.as_millis(),
                 Default::default(),
+                latency_histogram,
             )
         } else {
             DotRecord::new(
                 dot_type,
                 api_name,
                 Default::default(),
-                1,
+                count,
                 Default::default(),
                 elapsed_duration.as_millis(),
+                latency_histogram,
             )
         };
         self.buffered_records.merge_with_record(record).await;
@@ -306,6 +511,7 @@ impl DotterInner {
 
     async fn flush_to_file(&self, buffered_file: &mut File) -> IoResult<()> {
         let buffered_file = Arc::new(Mutex::new(BufWriter::new(buffered_file)));
+        let mut records = 0;
         {
             let mut futures = vec![];
             self.buffered_records
@@ -327,14 +533,16 @@ impl DotterInner {
                 .await;
             for key in join_all(futures).await.into_iter().flatten() {
                 self.buffered_records.remove_async(&key).await;
+                records += 1;
             }
         }
 
-        Arc::try_unwrap(buffered_file)
-            .unwrap()
-            .into_inner()
-            .flush()
-            .await?;
+        let mut buffered_file = Arc::try_unwrap(buffered_file).unwrap().into_inner();
+        buffered_file.flush().await?;
+        if records > 0 {
+            let backlog_size = buffered_file.into_inner().metadata().await?.len();
+            record_dot_records_flushed(records, backlog_size);
+        }
 
         return Ok(());
 
@@ -369,6 +577,20 @@ impl DotterInner {
         Ok(result)
     }
 
+    async fn upload_pending(&self) -> IoResult<()> {
+        if is_dotting_disabled() || is_dot_uploading_disabled() {
+            return Ok(());
+        }
+        self.lock_buffered_file(|mut buffered_file| async move {
+            self.flush_to_file(&mut buffered_file).await?;
+            if buffered_file.metadata().await?.len() > 0 {
+                self.do_upload().await?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
     async fn do_upload(&self) -> IoResult<()> {
         self.upload_with_retry(|host_info| async move {
             let mut buffered_file = OpenOptions::new()
@@ -382,7 +604,7 @@ impl DotterInner {
                 &self.credential,
                 &UploadPolicy::new_for_bucket(
                     self.bucket.to_owned(),
-                    SystemTime::now() + Duration::from_secs(30),
+                    SystemTime::now() + self.token_lifetime,
                 ),
             );
             let begin_at = Instant::now();
@@ -418,12 +640,25 @@ impl DotterInner {
                 ApiName::MonitorV1Stat,
                 response_result.is_ok(),
                 begin_at.elapsed(),
+                1,
+                None,
             )
             .await;
             response_result
-                .tap_ok(|_| info!("upload dots succeed"))
-                .tap_err(|err| warn!("failed to upload dots: {:?}", err))?;
+                .tap_ok(|_| {
+                    info!("upload dots succeed");
+                    record_dot_upload(None);
+                    emit_event(|| DownloadEvent::DotUploadSucceeded);
+                })
+                .tap_err(|err| {
+                    warn!("failed to upload dots: {:?}", err);
+                    record_dot_upload(Some(err));
+                    emit_event(|| DownloadEvent::DotUploadFailed {
+                        error: err.to_string(),
+                    });
+                })?;
             buffered_file.set_len(0).await?;
+            record_dot_backlog_cleared();
             Ok(())
         })
         .await?;
@@ -546,7 +781,15 @@ pub(super) enum DotRecordKey {
         dot_type: DotType,
         api_name: ApiName,
     },
+    SdkStarted {
+        hosts_crc32: u32,
+    },
     PunishedCount,
+    SyncRuntimeLoad,
+    DivergedCount,
+    StageTimings {
+        api_name: ApiName,
+    },
 }
 
 impl DotRecordKey {
@@ -557,13 +800,33 @@ impl DotRecordKey {
     pub(super) fn punished() -> Self {
         Self::PunishedCount
     }
+
+    pub(super) fn sdk_started(hosts_crc32: u32) -> Self {
+        Self::SdkStarted { hosts_crc32 }
+    }
+
+    pub(super) fn sync_runtime_load() -> Self {
+        Self::SyncRuntimeLoad
+    }
+
+    pub(super) fn diverged() -> Self {
+        Self::DivergedCount
+    }
+
+    pub(super) fn stage_timings(api_name: ApiName) -> Self {
+        Self::StageTimings { api_name }
+    }
 }
 
 #[derive(Serialize, Clone, Debug)]
 #[serde(untagged)]
 pub(super) enum DotRecord {
     APICalls(APICallsDotRecord),
+    SdkStarted(SdkStartedDotRecord),
     PunishedCount(PunishedCountDotRecord),
+    SyncRuntimeLoad(SyncRuntimeLoadDotRecord),
+    DivergedCount(DivergedCountDotRecord),
+    StageTimings(StageTimingsDotRecord),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -576,6 +839,80 @@ pub(super) struct APICallsDotRecord {
     success_avg_elapsed_duration: u128,
     failed_count: usize,
     failed_avg_elapsed_duration: u128,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    latency_histogram: Option<LatencyHistogram>,
+}
+
+/// 耗时直方图，仅在配置了分桶边界时记录
+///
+/// 第 i 个桶统计耗时不超过 `buckets_ms[i]` 毫秒的调用次数，最后一个桶统计超过所有边界的调用次数
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub(super) struct LatencyHistogram {
+    buckets_ms: Vec<u64>,
+    success_counts: Vec<usize>,
+    failed_counts: Vec<usize>,
+}
+
+impl LatencyHistogram {
+    fn new(buckets_ms: &[u64], successful: bool, elapsed_ms: u128, count: usize) -> Self {
+        let mut success_counts = vec![0; buckets_ms.len() + 1];
+        let mut failed_counts = vec![0; buckets_ms.len() + 1];
+        let index = buckets_ms
+            .iter()
+            .position(|&bucket_ms| elapsed_ms <= u128::from(bucket_ms))
+            .unwrap_or(buckets_ms.len());
+        if successful {
+            success_counts[index] = count;
+        } else {
+            failed_counts[index] = count;
+        }
+        Self {
+            buckets_ms: buckets_ms.to_owned(),
+            success_counts,
+            failed_counts,
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.success_counts.len() == self.buckets_ms.len() + 1
+            && self.failed_counts.len() == self.buckets_ms.len() + 1
+    }
+
+    /// 合并相同分桶边界的直方图，边界不同时（例如配置被修改）以新的直方图为准
+    fn merge(&mut self, other: &Self) {
+        if self.buckets_ms == other.buckets_ms && self.is_valid() && other.is_valid() {
+            for (count, other_count) in self.success_counts.iter_mut().zip(&other.success_counts) {
+                *count += other_count;
+            }
+            for (count, other_count) in self.failed_counts.iter_mut().zip(&other.failed_counts) {
+                *count += other_count;
+            }
+        } else {
+            *self = other.to_owned();
+        }
+    }
+
+    #[cfg(test)]
+    pub(super) fn success_counts(&self) -> &[usize] {
+        &self.success_counts
+    }
+
+    #[cfg(test)]
+    pub(super) fn failed_counts(&self) -> &[usize] {
+        &self.failed_counts
+    }
+}
+
+fn merge_latency_histograms(
+    merged: &mut Option<LatencyHistogram>,
+    other: &Option<LatencyHistogram>,
+) {
+    match (merged.as_mut(), other) {
+        (_, None) => {}
+        (Some(merged), Some(other)) => merged.merge(other),
+        (None, Some(other)) => *merged = Some(other.to_owned()),
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -583,6 +920,65 @@ pub(super) struct PunishedCountDotRecord {
     punished_count: usize,
 }
 
+/// 从两个域名读取的数据不一致的次数
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(super) struct DivergedCountDotRecord {
+    diverged_count: usize,
+}
+
+/// 同步接口内部运行时的负载记录，合并时保留峰值
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(super) struct SyncRuntimeLoadDotRecord {
+    sync_runtime_peak_in_flight: usize,
+    sync_runtime_peak_queue_depth: usize,
+}
+
+/// API 调用最后一次请求各阶段的耗时记录，合并时按调用次数计算各阶段的平均耗时
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(super) struct StageTimingsDotRecord {
+    api_name: ApiName,
+    stages_count: usize,
+    host_selection_avg_ms: u64,
+    ttfb_avg_ms: u64,
+    transfer_avg_ms: u64,
+    parse_avg_ms: u64,
+    cache_write_avg_ms: u64,
+}
+
+impl StageTimingsDotRecord {
+    fn merge(&mut self, record: &Self) {
+        let (count, other_count) = (self.stages_count, record.stages_count);
+        let merge_avg = |avg: &mut u64, other_avg: u64| {
+            let total =
+                u128::from(*avg) * to_u128(count) + u128::from(other_avg) * to_u128(other_count);
+            *avg = u64::try_from(total / to_u128(count + other_count).max(1)).unwrap_or(u64::MAX);
+        };
+        merge_avg(
+            &mut self.host_selection_avg_ms,
+            record.host_selection_avg_ms,
+        );
+        merge_avg(&mut self.ttfb_avg_ms, record.ttfb_avg_ms);
+        merge_avg(&mut self.transfer_avg_ms, record.transfer_avg_ms);
+        merge_avg(&mut self.parse_avg_ms, record.parse_avg_ms);
+        merge_avg(&mut self.cache_write_avg_ms, record.cache_write_avg_ms);
+        self.stages_count += other_count;
+
+        fn to_u128(v: usize) -> u128 {
+            u128::try_from(v).unwrap_or(u128::MAX)
+        }
+    }
+}
+
+/// SDK 启动记录，用于统计各个版本的 SDK 和域名列表的使用情况
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(super) struct SdkStartedDotRecord {
+    sdk_started_count: usize,
+    sdk_version: String,
+    os: String,
+    arch: String,
+    hosts_crc32: u32,
+}
+
 impl DotRecord {
     fn new(
         dot_type: DotType,
@@ -591,6 +987,7 @@ impl DotRecord {
         failed_count: usize,
         success_avg_elapsed_duration: u128,
         failed_avg_elapsed_duration: u128,
+        latency_histogram: Option<LatencyHistogram>,
     ) -> Self {
         Self::APICalls(APICallsDotRecord {
             dot_type,
@@ -599,6 +996,7 @@ impl DotRecord {
             success_avg_elapsed_duration,
             failed_count,
             failed_avg_elapsed_duration,
+            latency_histogram,
         })
     }
 
@@ -606,15 +1004,51 @@ impl DotRecord {
         Self::PunishedCount(PunishedCountDotRecord { punished_count: 1 })
     }
 
+    fn sdk_started(hosts_crc32: u32) -> Self {
+        Self::SdkStarted(SdkStartedDotRecord {
+            sdk_started_count: 1,
+            sdk_version: env!("CARGO_PKG_VERSION").to_owned(),
+            os: OS.to_owned(),
+            arch: ARCH.to_owned(),
+            hosts_crc32,
+        })
+    }
+
+    fn diverged() -> Self {
+        Self::DivergedCount(DivergedCountDotRecord { diverged_count: 1 })
+    }
+
+    fn sync_runtime_load(in_flight_requests: usize, queued_requests: usize) -> Self {
+        Self::SyncRuntimeLoad(SyncRuntimeLoadDotRecord {
+            sync_runtime_peak_in_flight: in_flight_requests,
+            sync_runtime_peak_queue_depth: queued_requests,
+        })
+    }
+
+    fn stage_timings(api_name: ApiName, stages: &StageTimings) -> Self {
+        Self::StageTimings(StageTimingsDotRecord {
+            api_name,
+            stages_count: 1,
+            host_selection_avg_ms: stages.host_selection_ms,
+            ttfb_avg_ms: stages.ttfb_ms.unwrap_or_default(),
+            transfer_avg_ms: stages.transfer_ms.unwrap_or_default(),
+            parse_avg_ms: stages.parse_ms.unwrap_or_default(),
+            cache_write_avg_ms: stages.cache_write_ms.unwrap_or_default(),
+        })
+    }
+
     pub(super) fn key(&self) -> DotRecordKey {
         match self {
             Self::APICalls(record) => DotRecordKey::new(record.dot_type, record.api_name),
+            Self::SdkStarted(record) => DotRecordKey::sdk_started(record.hosts_crc32),
             Self::PunishedCount(_) => DotRecordKey::punished(),
+            Self::SyncRuntimeLoad(_) => DotRecordKey::sync_runtime_load(),
+            Self::DivergedCount(_) => DotRecordKey::diverged(),
+            Self::StageTimings(record) => DotRecordKey::stage_timings(record.api_name),
         }
     }
 
     #[cfg(test)]
-
     pub(super) fn dot_type(&self) -> Option<DotType> {
         match self {
             Self::APICalls(record) => Some(record.dot_type),
@@ -623,7 +1057,6 @@ impl DotRecord {
     }
 
     #[cfg(test)]
-
     pub(super) fn api_name(&self) -> Option<ApiName> {
         match self {
             Self::APICalls(record) => Some(record.api_name),
@@ -632,7 +1065,6 @@ impl DotRecord {
     }
 
     #[cfg(test)]
-
     pub(super) fn success_count(&self) -> Option<usize> {
         match self {
             Self::APICalls(record) => Some(record.success_count),
@@ -641,7 +1073,6 @@ impl DotRecord {
     }
 
     #[cfg(test)]
-
     pub(super) fn success_avg_elapsed_duration_ms(&self) -> Option<u128> {
         match self {
             Self::APICalls(record) => Some(record.success_avg_elapsed_duration),
@@ -650,7 +1081,6 @@ impl DotRecord {
     }
 
     #[cfg(test)]
-
     pub(super) fn failed_count(&self) -> Option<usize> {
         match self {
             Self::APICalls(record) => Some(record.failed_count),
@@ -659,7 +1089,6 @@ impl DotRecord {
     }
 
     #[cfg(test)]
-
     pub(super) fn failed_avg_elapsed_duration_ms(&self) -> Option<u128> {
         match self {
             Self::APICalls(record) => Some(record.failed_avg_elapsed_duration),
@@ -668,13 +1097,62 @@ impl DotRecord {
     }
 
     #[cfg(test)]
+    pub(super) fn latency_histogram(&self) -> Option<&LatencyHistogram> {
+        match self {
+            Self::APICalls(record) => record.latency_histogram.as_ref(),
+            _ => None,
+        }
+    }
 
+    #[cfg(test)]
+    pub(super) fn sdk_started_count(&self) -> Option<usize> {
+        match self {
+            Self::SdkStarted(record) => Some(record.sdk_started_count),
+            _ => None,
+        }
+    }
+
+    #[cfg(test)]
     pub(super) fn punished_count(&self) -> Option<usize> {
         match self {
             Self::PunishedCount(record) => Some(record.punished_count),
             _ => None,
         }
     }
+
+    #[cfg(test)]
+    pub(super) fn diverged_count(&self) -> Option<usize> {
+        match self {
+            Self::DivergedCount(record) => Some(record.diverged_count),
+            _ => None,
+        }
+    }
+
+    #[cfg(test)]
+    pub(super) fn stage_timings_avg_ms(&self) -> Option<(usize, u64, u64, u64, u64, u64)> {
+        match self {
+            Self::StageTimings(record) => Some((
+                record.stages_count,
+                record.host_selection_avg_ms,
+                record.ttfb_avg_ms,
+                record.transfer_avg_ms,
+                record.parse_avg_ms,
+                record.cache_write_avg_ms,
+            )),
+            _ => None,
+        }
+    }
+
+    #[cfg(test)]
+    pub(super) fn sync_runtime_peaks(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::SyncRuntimeLoad(record) => Some((
+                record.sync_runtime_peak_in_flight,
+                record.sync_runtime_peak_queue_depth,
+            )),
+            _ => None,
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for DotRecord {
@@ -685,6 +1163,14 @@ impl<'de> Deserialize<'de> for DotRecord {
         let value = JSONValue::deserialize(deserializer)?;
         if let Ok(record) = APICallsDotRecord::deserialize(&value) {
             Ok(Self::APICalls(record))
+        } else if let Ok(record) = SdkStartedDotRecord::deserialize(&value) {
+            Ok(Self::SdkStarted(record))
+        } else if let Ok(record) = SyncRuntimeLoadDotRecord::deserialize(&value) {
+            Ok(Self::SyncRuntimeLoad(record))
+        } else if let Ok(record) = DivergedCountDotRecord::deserialize(&value) {
+            Ok(Self::DivergedCount(record))
+        } else if let Ok(record) = StageTimingsDotRecord::deserialize(&value) {
+            Ok(Self::StageTimings(record))
         } else {
             PunishedCountDotRecord::deserialize(&value)
                 .map(Self::PunishedCount)
@@ -701,7 +1187,6 @@ pub(super) struct DotRecords {
 
 impl DotRecords {
     #[cfg(test)]
-
     pub(super) fn records(&self) -> &[DotRecord] {
         self.records.as_ref()
     }
@@ -735,10 +1220,31 @@ impl DotRecordsMap {
                     } else {
                         0
                     };
+                    merge_latency_histograms(&mut r.latency_histogram, &record.latency_histogram);
+                }
+                (DotRecord::SdkStarted(r), DotRecord::SdkStarted(record)) => {
+                    r.sdk_started_count += record.sdk_started_count;
+                    r.sdk_version = record.sdk_version.to_owned();
+                    r.os = record.os.to_owned();
+                    r.arch = record.arch.to_owned();
                 }
                 (DotRecord::PunishedCount(r), DotRecord::PunishedCount(record)) => {
                     r.punished_count += record.punished_count;
                 }
+                (DotRecord::DivergedCount(r), DotRecord::DivergedCount(record)) => {
+                    r.diverged_count += record.diverged_count;
+                }
+                (DotRecord::SyncRuntimeLoad(r), DotRecord::SyncRuntimeLoad(record)) => {
+                    r.sync_runtime_peak_in_flight = r
+                        .sync_runtime_peak_in_flight
+                        .max(record.sync_runtime_peak_in_flight);
+                    r.sync_runtime_peak_queue_depth = r
+                        .sync_runtime_peak_queue_depth
+                        .max(record.sync_runtime_peak_queue_depth);
+                }
+                (DotRecord::StageTimings(r), DotRecord::StageTimings(record)) => {
+                    r.merge(record);
+                }
                 _ => panic!("Impossible merge with {:?} and {:?}", r, record),
             })
             .or_insert(record);
@@ -800,10 +1306,31 @@ impl AsyncDotRecordsMap {
                     } else {
                         0
                     };
+                    merge_latency_histograms(&mut r.latency_histogram, &record.latency_histogram);
+                }
+                (DotRecord::SdkStarted(r), DotRecord::SdkStarted(record)) => {
+                    r.sdk_started_count += record.sdk_started_count;
+                    r.sdk_version = record.sdk_version.to_owned();
+                    r.os = record.os.to_owned();
+                    r.arch = record.arch.to_owned();
                 }
                 (DotRecord::PunishedCount(r), DotRecord::PunishedCount(record)) => {
                     r.punished_count += record.punished_count;
                 }
+                (DotRecord::DivergedCount(r), DotRecord::DivergedCount(record)) => {
+                    r.diverged_count += record.diverged_count;
+                }
+                (DotRecord::SyncRuntimeLoad(r), DotRecord::SyncRuntimeLoad(record)) => {
+                    r.sync_runtime_peak_in_flight = r
+                        .sync_runtime_peak_in_flight
+                        .max(record.sync_runtime_peak_in_flight);
+                    r.sync_runtime_peak_queue_depth = r
+                        .sync_runtime_peak_queue_depth
+                        .max(record.sync_runtime_peak_queue_depth);
+                }
+                (DotRecord::StageTimings(r), DotRecord::StageTimings(record)) => {
+                    r.merge(record);
+                }
                 _ => panic!("Impossible merge with {:?} and {:?}", r, record),
             })
             .or_insert_with(|| record.to_owned());
@@ -843,9 +1370,10 @@ impl Deref for AsyncDotRecordsMap {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Timeouts;
+    use crate::config::HttpClientTimeouts;
     use futures::channel::oneshot::channel;
     use futures::future::join_all;
+    use serde_json::{from_str as json_from_str, to_string as json_to_string};
     use std::{error::Error, sync::atomic::AtomicUsize};
     use tokio::{fs::remove_file, task::spawn, time::sleep};
     use warp::{http::HeaderValue, hyper::Body, path, reply::Response, Filter};
@@ -916,7 +1444,7 @@ mod tests {
 
         starts_with_server!(addr, routes, {
             let dotter = Dotter::new(
-                Timeouts::default_async_http_client(),
+                HttpClientTimeouts::default_async_http_client(),
                 get_credential(),
                 BUCKET_NAME.to_owned(),
                 vec![],
@@ -927,6 +1455,7 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
             )
             .await;
             assert!(dotter.inner.is_none());
@@ -944,7 +1473,7 @@ mod tests {
 
             let urls = vec!["http://".to_owned() + &addr.to_string()];
             let dotter = Dotter::new(
-                Timeouts::default_async_http_client(),
+                HttpClientTimeouts::default_async_http_client(),
                 get_credential(),
                 BUCKET_NAME.to_owned(),
                 urls,
@@ -955,6 +1484,7 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
             )
             .await;
             assert!(dotter.inner.is_some());
@@ -1006,7 +1536,7 @@ mod tests {
                 "http://".to_owned() + &addr.to_string(),
             ];
             let dotter = Dotter::new(
-                Timeouts::default_async_http_client(),
+                HttpClientTimeouts::default_async_http_client(),
                 get_credential(),
                 BUCKET_NAME.to_owned(),
                 urls,
@@ -1017,6 +1547,7 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
             )
             .await;
 
@@ -1284,7 +1815,7 @@ mod tests {
         starts_with_server!(addr, routes, {
             let urls = vec!["http://".to_owned() + &addr.to_string()];
             let dotter = Dotter::new(
-                Timeouts::default_async_http_client(),
+                HttpClientTimeouts::default_async_http_client(),
                 get_credential(),
                 BUCKET_NAME.to_owned(),
                 urls,
@@ -1295,6 +1826,7 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
             )
             .await;
 
@@ -1345,6 +1877,173 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_merge_latency_histograms() -> Result<(), Box<dyn Error>> {
+        let buckets_ms = [10, 100];
+        let record_of = |successful: bool, elapsed_ms: u128| {
+            let histogram = LatencyHistogram::new(&buckets_ms, successful, elapsed_ms, 1);
+            if successful {
+                DotRecord::new(
+                    DotType::Http,
+                    ApiName::IoGetfile,
+                    1,
+                    0,
+                    elapsed_ms,
+                    0,
+                    Some(histogram),
+                )
+            } else {
+                DotRecord::new(
+                    DotType::Http,
+                    ApiName::IoGetfile,
+                    0,
+                    1,
+                    0,
+                    elapsed_ms,
+                    Some(histogram),
+                )
+            }
+        };
+
+        let mut map = DotRecordsMap::default();
+        map.merge_with_record(DotRecord::new(
+            DotType::Http,
+            ApiName::IoGetfile,
+            1,
+            0,
+            5,
+            0,
+            None,
+        ));
+        map.merge_with_record(record_of(true, 10));
+        map.merge_with_record(record_of(true, 50));
+        map.merge_with_record(record_of(true, 500));
+        map.merge_with_record(record_of(false, 100));
+
+        let record = map
+            .get(&DotRecordKey::new(DotType::Http, ApiName::IoGetfile))
+            .unwrap();
+        assert_eq!(record.success_count(), Some(4));
+        let histogram = record.latency_histogram().unwrap();
+        assert_eq!(histogram.success_counts(), [1, 1, 1]);
+        assert_eq!(histogram.failed_counts(), [0, 1, 0]);
+
+        let records: DotRecords = json_from_str(&json_to_string(&map.to_owned().into_records())?)?;
+        assert_eq!(records.records()[0].latency_histogram(), Some(histogram));
+
+        map.merge_with_record(DotRecord::new(
+            DotType::Http,
+            ApiName::IoGetfile,
+            1,
+            0,
+            5,
+            0,
+            Some(LatencyHistogram::new(&[1000], true, 5, 1)),
+        ));
+        let record = map
+            .get(&DotRecordKey::new(DotType::Http, ApiName::IoGetfile))
+            .unwrap();
+        assert_eq!(record.latency_histogram().unwrap().success_counts(), [1, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_sdk_started_records() -> Result<(), Box<dyn Error>> {
+        let mut map = DotRecordsMap::default();
+        map.merge_with_record(DotRecord::sdk_started(1));
+        map.merge_with_record(DotRecord::sdk_started(1));
+        map.merge_with_record(DotRecord::sdk_started(2));
+        assert_eq!(
+            map.get(&DotRecordKey::sdk_started(1))
+                .and_then(|record| record.sdk_started_count()),
+            Some(2)
+        );
+
+        let records: DotRecords = json_from_str(&json_to_string(&map.into_records())?)?;
+        assert_eq!(records.records().len(), 2);
+        assert!(records
+            .records()
+            .iter()
+            .all(|record| record.sdk_started_count().is_some()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_sync_runtime_load_records() -> Result<(), Box<dyn Error>> {
+        let mut map = DotRecordsMap::default();
+        map.merge_with_record(DotRecord::sync_runtime_load(3, 0));
+        map.merge_with_record(DotRecord::sync_runtime_load(1, 5));
+        map.merge_with_record(DotRecord::sync_runtime_load(2, 2));
+        assert_eq!(
+            map.get(&DotRecordKey::sync_runtime_load())
+                .and_then(|record| record.sync_runtime_peaks()),
+            Some((3, 5))
+        );
+
+        let records: DotRecords = json_from_str(&json_to_string(&map.into_records())?)?;
+        assert_eq!(records.records().len(), 1);
+        assert_eq!(records.records()[0].sync_runtime_peaks(), Some((3, 5)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_stage_timings_records() -> Result<(), Box<dyn Error>> {
+        let stages = |ttfb_ms, parse_ms| StageTimings {
+            host_selection_ms: 1,
+            ttfb_ms: Some(ttfb_ms),
+            transfer_ms: Some(10),
+            parse_ms,
+            cache_write_ms: None,
+        };
+        let mut map = DotRecordsMap::default();
+        map.merge_with_record(DotRecord::stage_timings(
+            ApiName::RangeReaderReadAt,
+            &stages(20, Some(6)),
+        ));
+        map.merge_with_record(DotRecord::stage_timings(
+            ApiName::RangeReaderReadAt,
+            &stages(40, None),
+        ));
+        map.merge_with_record(DotRecord::stage_timings(
+            ApiName::RangeReaderReadAt,
+            &stages(60, Some(3)),
+        ));
+        map.merge_with_record(DotRecord::stage_timings(
+            ApiName::RangeReaderExist,
+            &stages(5, None),
+        ));
+        assert_eq!(
+            map.get(&DotRecordKey::stage_timings(ApiName::RangeReaderReadAt))
+                .and_then(|record| record.stage_timings_avg_ms()),
+            Some((3, 1, 40, 10, 3, 0))
+        );
+
+        let records: DotRecords = json_from_str(&json_to_string(&map.into_records())?)?;
+        assert_eq!(records.records().len(), 2);
+        assert!(records
+            .records()
+            .iter()
+            .all(|record| record.stage_timings_avg_ms().is_some()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_diverged_records() -> Result<(), Box<dyn Error>> {
+        let mut map = DotRecordsMap::default();
+        map.merge_with_record(DotRecord::diverged());
+        map.merge_with_record(DotRecord::diverged());
+        assert_eq!(
+            map.get(&DotRecordKey::diverged())
+                .and_then(|record| record.diverged_count()),
+            Some(2)
+        );
+
+        let records: DotRecords = json_from_str(&json_to_string(&map.into_records())?)?;
+        assert_eq!(records.records().len(), 1);
+        assert_eq!(records.records()[0].diverged_count(), Some(2));
+        Ok(())
+    }
+
     async fn clear_cache() -> IoResult<()> {
         let cache_file_path = cache_dir_path_of(DOT_FILE_NAME).await?;
         remove_file(&cache_file_path).await.or_else(|err| {
@@ -2,36 +2,67 @@
 
 use super::{
     super::{
-        base::{credential::Credential, download::RangeReaderBuilder as BaseRangeReaderBuilder},
-        config::{build_range_reader_builder_from_config, Config, Timeouts},
+        base::{
+            checksum::{ChecksumAlgorithm, RangeHasher},
+            credential::Credential,
+            dot_sampler::DotSampler,
+            download::{
+                check_body_size, check_object_size, check_response_headers, content_encoded_error,
+                content_encoding_of, is_not_found_status_code, is_object_level_error,
+                protocol_error_of, range_in_body, redirected_host_of, should_retry,
+                unexpected_status_code_error, DownloadUrlOptions, ObjectMetadata,
+                RangeReaderBuilder as BaseRangeReaderBuilder,
+            },
+            gzip,
+            region_hosts::RegionHosts,
+            rng::SharedRng,
+        },
+        config::{
+            build_range_reader_builder_from_config, rewrite_key, Config, ConnectionFailureKind,
+            FailoverBackoff, HttpClientTimeouts, KeyRewriteRule, RangeRequestEncoding,
+        },
+        error_code::ErrorCode,
+        events::{emit_event, DownloadEvent},
     },
+    audit::{AuditSlot, StageTimings},
+    cache_control::CacheDirectives,
     dot::{ApiName, DotType, Dotter},
+    head_cache::HeadCache,
+    health::record_divergent_read,
     host_selector::{HostInfo, HostSelector, HostSelectorBuilder},
+    host_stats::record_host_stats,
+    multipart::{MultipartError, MultipartParser},
+    negative_cache::NegativeCache,
+    preload::{PreloadCache, DEFAULT_PRELOAD_CACHE_SIZE, DEFAULT_PRELOAD_CACHE_TTL},
     query::HostsQuerier,
     req_id::{get_req_id2, REQUEST_ID_HEADER},
+    session::ObjectSession,
+    stats::StatsRecorder,
+    trace::{response_url, send_request, SendError},
 };
 use async_once_cell::Lazy as AsyncLazy;
 use futures::{AsyncReadExt, TryStreamExt};
 use hyper::HeaderMap;
 use log::{debug, info, warn};
 use mime::{Mime, BOUNDARY};
-use multer::Multipart;
 use reqwest::{
-    header::{HeaderValue, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE},
-    Client as HttpClient, Error as ReqwestError, Method, RequestBuilder as HttpRequestBuilder,
-    Response as HttpResponse, StatusCode, Url,
+    header::{HeaderValue, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, IF_MATCH},
+    Error as ReqwestError, Method, RequestBuilder as HttpRequestBuilder, Response as HttpResponse,
+    StatusCode, Url,
 };
 use std::{
-    collections::HashSet,
+    borrow::Cow,
+    collections::{HashMap, HashSet},
     error::Error as StdError,
     fmt::{self, Debug},
     future::Future,
     io::{Cursor, Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    iter::once,
     mem::take,
     ops::Deref,
     sync::{
         atomic::{AtomicUsize, Ordering::Relaxed},
-        Arc,
+        Arc, Mutex as StdMutex,
     },
     time::{Duration, Instant, SystemTime, SystemTimeError, UNIX_EPOCH},
 };
@@ -41,9 +72,13 @@ use tokio::{
     io::{copy as io_copy, AsyncWrite},
     spawn,
     sync::Mutex,
+    time::sleep,
 };
 use tokio_util::{compat::FuturesAsyncReadCompatExt, either::Either};
 
+const DOWNLOAD_CHUNK_SIZE: u64 = 4 << 20;
+const MIN_CONNECT_FAILURES_TO_PURGE: usize = 3;
+
 /// 为私有空间签发对象下载 URL
 /// # Arguments
 ///
@@ -106,6 +141,26 @@ impl AsyncRangeReaderBuilder {
         take(&mut self.0.key)
     }
 
+    pub(super) fn bucket(&self) -> &str {
+        &self.0.bucket
+    }
+
+    pub(super) fn cluster_name(&self) -> Option<&str> {
+        self.0.cluster_name.as_deref()
+    }
+
+    pub(super) fn random_seed(&self) -> Option<u64> {
+        self.0.random_seed
+    }
+
+    pub(super) fn sync_request_timeout(&self) -> Option<Duration> {
+        self.0.sync_request_timeout
+    }
+
+    pub(super) fn sync_worker_threads(&self) -> usize {
+        self.0.sync_worker_threads
+    }
+
     pub(super) fn build(self) -> AsyncRangeReader {
         AsyncRangeReader(Arc::new(AsyncLazy::new(Box::pin(async move {
             self.build_inner().await
@@ -114,12 +169,21 @@ impl AsyncRangeReaderBuilder {
 
     async fn build_inner(self) -> Arc<AsyncRangeReaderInner> {
         let builder = self.0;
-        let http_client =
-            Timeouts::new(builder.base_timeout, builder.dial_timeout).async_http_client();
+        let http_client_timeouts =
+            HttpClientTimeouts::new(builder.base_timeout, builder.dial_timeout)
+                .with_dns_cache(builder.dns_cache_min_ttl, builder.dns_cache_max_ttl)
+                .with_redirect_policy(builder.redirect_policy);
+        let http_client = http_client_timeouts.async_http_client();
         let dotter = Dotter::new(
             http_client.to_owned(),
-            builder.credential.to_owned(),
-            builder.bucket.to_owned(),
+            builder
+                .dot_credential
+                .clone()
+                .unwrap_or_else(|| builder.credential.to_owned()),
+            builder
+                .dot_bucket
+                .clone()
+                .unwrap_or_else(|| builder.bucket.to_owned()),
             builder.monitor_urls,
             builder.dot_interval,
             builder.max_dot_buffer_size,
@@ -128,15 +192,29 @@ impl AsyncRangeReaderBuilder {
             builder.max_punished_times,
             builder.max_punished_hosts_percent,
             builder.base_timeout,
+            builder.dot_token_lifetime,
         )
-        .await;
+        .await
+        .with_sampler(DotSampler::new(
+            builder.dot_sample_rate,
+            builder.dot_sampling_threshold,
+        ))
+        .with_latency_buckets(builder.dot_latency_buckets_ms.to_owned());
 
+        let rng = SharedRng::new(builder.random_seed);
         let params = HostSelectorParams {
             update_interval: builder.update_interval,
             punish_duration: builder.punish_duration,
             max_punished_times: builder.max_punished_times,
             max_punished_hosts_percent: builder.max_punished_hosts_percent,
             base_timeout: builder.base_timeout,
+            max_timeout: builder.max_timeout,
+            host_base_timeouts: builder.host_base_timeouts.to_owned(),
+            connection_failure_kinds: builder.connection_failure_kinds.to_owned(),
+            min_hosts_on_update: builder.min_hosts_on_update,
+            max_host_change_ratio: builder.max_host_change_ratio,
+            force_https: builder.force_https,
+            rng: rng.to_owned(),
         };
 
         let io_querier = if builder.uc_urls.is_empty() {
@@ -149,26 +227,91 @@ impl AsyncRangeReaderBuilder {
                 http_client.to_owned(),
             ))
         };
+        let cdn_selector = if builder.cdn_urls.is_empty() {
+            None
+        } else {
+            Some(
+                make_cdn_selector(
+                    builder.cdn_urls,
+                    builder.retryable_status_codes.to_owned(),
+                    builder.object_level_status_codes.to_owned(),
+                    &params,
+                )
+                .await,
+            )
+        };
         let io_selector = make_io_selector(
             builder.io_urls,
-            io_querier,
+            io_querier.to_owned(),
             builder.credential.access_key().to_owned(),
             builder.bucket.to_owned(),
             builder.use_https,
+            builder.preferred_idc.to_owned(),
+            builder.retryable_status_codes,
+            builder.object_level_status_codes.to_owned(),
+            builder.update_hosts_tries,
+            dotter.to_owned(),
             &params,
         )
         .await;
+        dotter
+            .sdk_started(io_selector.all_hosts_crc32().await)
+            .await
+            .tap_err(|err| warn!("Failed to dot sdk started: {}", err))
+            .ok();
 
+        let preload_cache_ttl = builder
+            .preload_cache_ttl
+            .unwrap_or(DEFAULT_PRELOAD_CACHE_TTL);
         return Arc::new(AsyncRangeReaderInner {
             io_selector,
+            cdn_selector,
+            io_querier,
             dotter,
-            http_client,
+            http_client_timeouts,
+            connect_failures: Default::default(),
             credential: builder.credential,
             bucket: builder.bucket,
             use_getfile_api: builder.use_getfile_api,
             normalize_key: builder.normalize_key,
+            key_rewrite_rules: builder.key_rewrite_rules,
             use_https: builder.use_https,
             private_url_lifetime: builder.private_url_lifetime,
+            url_options: builder.url_options,
+            strict_body_size: builder.strict_body_size,
+            max_response_header_size: builder.max_response_header_size,
+            strict_header_validation: builder.strict_header_validation,
+            disable_read_multi_ranges: builder.disable_read_multi_ranges,
+            range_request_encoding: builder.range_request_encoding,
+            disable_download: builder.disable_download,
+            decompress_content_encoding: builder.decompress_content_encoding,
+            max_object_size: builder.max_object_size,
+            failover_backoff: builder.failover_backoff,
+            object_level_status_codes: builder.object_level_status_codes,
+            force_update_interval: builder
+                .force_update_interval
+                .unwrap_or_else(|| Duration::from_secs(30)),
+            last_forced_update_at: Default::default(),
+            negative_cache: builder
+                .negative_cache_ttl
+                .filter(|ttl| !ttl.is_zero())
+                .map(NegativeCache::new),
+            head_cache: builder
+                .head_cache_window
+                .filter(|window| !window.is_zero())
+                .map(HeadCache::new),
+            preload_cache: Some(
+                builder
+                    .preload_cache_size
+                    .unwrap_or(DEFAULT_PRELOAD_CACHE_SIZE),
+            )
+            .filter(|&size| size > 0)
+            .map(|size| PreloadCache::new(size, preload_cache_ttl)),
+            respect_cache_control: builder.respect_cache_control,
+            dot_sync_runtime_load: builder.dot_sync_runtime_load,
+            dot_stage_timings: builder.dot_stage_timings,
+            verify_with_second_host: builder.verify_with_second_host,
+            rng,
         });
 
         #[derive(Clone, Debug)]
@@ -178,6 +321,13 @@ impl AsyncRangeReaderBuilder {
             max_punished_times: Option<usize>,
             max_punished_hosts_percent: Option<u8>,
             base_timeout: Option<Duration>,
+            max_timeout: Option<Duration>,
+            host_base_timeouts: HashMap<String, Duration>,
+            connection_failure_kinds: Vec<ConnectionFailureKind>,
+            min_hosts_on_update: Option<usize>,
+            max_host_change_ratio: Option<f64>,
+            force_https: bool,
+            rng: SharedRng,
         }
 
         impl HostSelectorParams {
@@ -197,7 +347,21 @@ impl AsyncRangeReaderBuilder {
                 if let Some(base_timeout) = self.base_timeout {
                     builder = builder.base_timeout(base_timeout);
                 }
+                if let Some(max_timeout) = self.max_timeout {
+                    builder = builder.max_timeout(max_timeout);
+                }
+                builder = builder.host_base_timeouts(self.host_base_timeouts.to_owned());
+                builder =
+                    builder.connection_failure_kinds(self.connection_failure_kinds.to_owned());
+                if let Some(min_hosts_on_update) = self.min_hosts_on_update {
+                    builder = builder.min_hosts_on_update(min_hosts_on_update);
+                }
+                if let Some(max_host_change_ratio) = self.max_host_change_ratio {
+                    builder = builder.max_host_change_ratio(max_host_change_ratio);
+                }
                 builder
+                    .force_https(self.force_https)
+                    .rng(self.rng.to_owned())
             }
         }
 
@@ -217,26 +381,63 @@ impl AsyncRangeReaderBuilder {
             access_key: String,
             bucket: String,
             use_https: bool,
+            preferred_idc: Option<String>,
+            retryable_status_codes: Option<Vec<u16>>,
+            object_level_status_codes: Vec<u16>,
+            update_tries: usize,
+            dotter: Dotter,
             params: &HostSelectorParams,
         ) -> HostSelector {
             let builder = HostSelector::builder(io_urls)
+                .use_persisted_stats(true)
+                .update_tries(update_tries)
+                .update_dotter(dotter)
                 .update_callback(Some(Box::new(move || {
                     let io_querier = io_querier.to_owned();
                     let access_key = access_key.to_owned();
                     let bucket = bucket.to_owned();
+                    let preferred_idc = preferred_idc.to_owned();
                     Box::pin(async move {
                         if let Some(io_querier) = io_querier.as_ref() {
                             io_querier
-                                .query_for_io_urls(&access_key, &bucket, use_https)
+                                .query_for_io_urls(
+                                    &access_key,
+                                    &bucket,
+                                    use_https,
+                                    preferred_idc.as_deref(),
+                                )
                                 .await
                         } else {
                             Ok(vec![])
                         }
                     })
                 })))
-                .should_punish_callback(Some(Box::new(|error| {
-                    let kind = error.kind();
-                    Box::pin(async move { !matches!(kind, IoErrorKind::InvalidData) })
+                .should_punish_callback(Some(Box::new(move |error| {
+                    let retryable = should_retry(
+                        error,
+                        retryable_status_codes.as_deref(),
+                        &object_level_status_codes,
+                    );
+                    Box::pin(async move { retryable })
+                })));
+            params.set_builder(builder).build().await
+        }
+
+        async fn make_cdn_selector(
+            cdn_urls: Vec<String>,
+            retryable_status_codes: Option<Vec<u16>>,
+            object_level_status_codes: Vec<u16>,
+            params: &HostSelectorParams,
+        ) -> HostSelector {
+            let builder = HostSelector::builder(cdn_urls)
+                .use_persisted_stats(true)
+                .should_punish_callback(Some(Box::new(move |error| {
+                    let retryable = should_retry(
+                        error,
+                        retryable_status_codes.as_deref(),
+                        &object_level_status_codes,
+                    );
+                    Box::pin(async move { retryable })
                 })));
             params.set_builder(builder).build().await
         }
@@ -253,14 +454,108 @@ pub(super) struct AsyncRangeReader(Arc<AsyncLazy<Arc<AsyncRangeReaderInner>>>);
 #[derive(Debug)]
 struct AsyncRangeReaderInner {
     io_selector: HostSelector,
+    cdn_selector: Option<HostSelector>,
+    io_querier: Option<HostsQuerier>,
     dotter: Dotter,
     credential: Credential,
-    http_client: Arc<HttpClient>,
+    http_client_timeouts: HttpClientTimeouts,
+    connect_failures: AtomicUsize,
     bucket: String,
     use_getfile_api: bool,
     normalize_key: bool,
+    key_rewrite_rules: Vec<KeyRewriteRule>,
     use_https: bool,
     private_url_lifetime: Option<Duration>,
+    url_options: DownloadUrlOptions,
+    strict_body_size: bool,
+    max_response_header_size: Option<usize>,
+    strict_header_validation: bool,
+    disable_read_multi_ranges: bool,
+    range_request_encoding: RangeRequestEncoding,
+    disable_download: bool,
+    decompress_content_encoding: bool,
+    max_object_size: Option<u64>,
+    failover_backoff: FailoverBackoff,
+    object_level_status_codes: Vec<u16>,
+    force_update_interval: Duration,
+    last_forced_update_at: Mutex<Option<Instant>>,
+    negative_cache: Option<NegativeCache>,
+    head_cache: Option<HeadCache>,
+    preload_cache: Option<PreloadCache>,
+    respect_cache_control: bool,
+    dot_sync_runtime_load: bool,
+    dot_stage_timings: bool,
+    verify_with_second_host: f64,
+    rng: SharedRng,
+}
+
+impl AsyncRangeReaderInner {
+    async fn find_io_host(&self, url: &str) -> Option<String> {
+        self.io_selector
+            .all_hosts()
+            .await
+            .into_iter()
+            .find(|host| host == url || normalize_host(host, self.use_https) == url)
+    }
+
+    /// 生成下载地址，`bucket` 为 None 时使用下载器的存储空间
+    fn download_url(
+        &self,
+        host: &str,
+        key: &str,
+        use_getfile_api: bool,
+        bucket: Option<&str>,
+    ) -> Url {
+        sign_download_url_if_needed(
+            &self.url_options.apply_to(make_download_url(
+                &self.url_options.base_url(host),
+                self.credential.access_key(),
+                bucket.unwrap_or(&self.bucket),
+                &rewrite_key(&self.key_rewrite_rules, key),
+                use_getfile_api,
+                self.normalize_key,
+            )),
+            self.private_url_lifetime,
+            &self.credential,
+        )
+    }
+
+    /// 生成缓存使用的键，`bucket` 与下载器的存储空间不同时在对象名称前加上存储空间名称
+    fn cache_key<'k>(&self, key: &'k str, bucket: Option<&str>) -> Cow<'k, str> {
+        match bucket {
+            Some(bucket) if bucket != self.bucket => Cow::Owned(format!("{}:{}", bucket, key)),
+            _ => Cow::Borrowed(key),
+        }
+    }
+
+    fn request_builder(
+        &self,
+        method: &Method,
+        download_url: &Url,
+        req_id: &HeaderValue,
+        tries_info: TriesInfo<'_>,
+    ) -> HttpRequestBuilder {
+        let mut request_builder = self
+            .http_client_timeouts
+            .async_http_client()
+            .request(method.to_owned(), download_url.to_owned())
+            .header(REQUEST_ID_HEADER, req_id.to_owned());
+        tries_info.record_request(download_url, req_id);
+        if let Some(etag) = tries_info.session.and_then(|session| session.etag()) {
+            request_builder = request_builder.header(IF_MATCH, etag);
+        }
+        request_builder
+    }
+}
+
+fn normalize_host(host: &str, use_https: bool) -> String {
+    if host.contains("://") {
+        host.to_string()
+    } else if use_https {
+        "https://".to_owned() + host
+    } else {
+        "http://".to_owned() + host
+    }
 }
 
 impl AsyncRangeReader {
@@ -278,35 +573,250 @@ impl AsyncRangeReader {
             .await
     }
 
-    pub(super) async fn update_urls(&self) -> bool {
+    /// 记录同步接口内部运行时的负载打点，未启用时不做任何事
+    pub(super) async fn dot_sync_runtime_load(
+        &self,
+        in_flight_requests: usize,
+        queued_requests: usize,
+    ) -> IoResult<()> {
+        let inner = self.inner().await;
+        if inner.dot_sync_runtime_load {
+            inner
+                .dotter
+                .sync_runtime_load(in_flight_requests, queued_requests)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 记录 API 调用各阶段耗时的打点，未启用或请求未收到响应时不做任何事
+    pub(super) async fn dot_stage_timings(
+        &self,
+        api_name: ApiName,
+        stages: &StageTimings,
+    ) -> IoResult<()> {
+        let inner = self.inner().await;
+        if inner.dot_stage_timings && stages.ttfb_ms.is_some() {
+            inner.dotter.stage_timings(api_name, stages).await?;
+        }
+        Ok(())
+    }
+
+    pub(super) async fn is_object_level_error(&self, err: &IoError) -> bool {
+        is_object_level_error(err, &self.inner().await.object_level_status_codes)
+    }
+
+    pub(super) async fn update_urls(&self) -> IoResult<bool> {
         self.inner().await.io_selector.update_hosts().await
     }
 
+    /// 当一次调用中所有尝试都失败时强制更新域名列表，每个间隔内最多更新一次，返回域名列表是否被更新
+    pub(super) async fn force_update_urls(&self) -> bool {
+        let inner = self.inner().await;
+        {
+            let mut last_forced_update_at = inner.last_forced_update_at.lock().await;
+            if let Some(last_forced_update_at) = *last_forced_update_at {
+                if last_forced_update_at.elapsed() < inner.force_update_interval {
+                    return false;
+                }
+            }
+            *last_forced_update_at = Some(Instant::now());
+        }
+        match inner.io_selector.update_hosts().await {
+            Ok(true) => {
+                info!("Force update hosts successfully after all tries failed");
+                true
+            }
+            Ok(false) => false,
+            Err(err) => {
+                warn!("Failed to force update hosts: {}", err);
+                false
+            }
+        }
+    }
+
     pub(super) async fn io_urls(&self) -> Vec<String> {
         let inner = self.inner().await;
-        return inner
+        inner
             .io_selector
             .hosts()
             .await
             .iter()
             .map(|host| normalize_host(host, inner.use_https))
-            .collect();
+            .collect()
+    }
+
+    /// 获取按区域分组的域名列表，未配置 UC 服务器时仅返回包含当前 IO 域名的一个分组
+    pub(super) async fn region_hosts(&self, bucket: Option<&str>) -> IoResult<Vec<RegionHosts>> {
+        let inner = self.inner().await;
+        if let Some(io_querier) = inner.io_querier.as_ref() {
+            io_querier
+                .query_for_region_hosts(
+                    inner.credential.access_key(),
+                    bucket.unwrap_or(&inner.bucket),
+                    inner.use_https,
+                )
+                .await
+        } else {
+            Ok(vec![RegionHosts::new(
+                None,
+                None,
+                self.io_urls().await,
+                vec![],
+            )])
+        }
+    }
+
+    /// 获取可用于分片下载的 IO 域名，不包括被冻结的域名
+    pub(super) async fn sharding_hosts(&self) -> Vec<String> {
+        self.inner().await.io_selector.hosts().await
+    }
+
+    pub(super) async fn report_external_failure(&self, url: &str, error: &IoError) -> bool {
+        let inner = self.inner().await;
+        if let Some(host) = inner.find_io_host(url).await {
+            inner.io_selector.punish(&host, error, &inner.dotter).await
+        } else {
+            false
+        }
+    }
+
+    pub(super) async fn report_external_success(&self, url: &str) -> bool {
+        let inner = self.inner().await;
+        if let Some(host) = inner.find_io_host(url).await {
+            inner.io_selector.reward(&host).await;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(super) async fn ban_host(&self, url: &str, duration: Duration) -> bool {
+        let inner = self.inner().await;
+        if let Some(host) = inner.find_io_host(url).await {
+            inner.io_selector.ban_host(&host, duration).await;
+            true
+        } else {
+            false
+        }
+    }
 
-        fn normalize_host(host: &str, use_https: bool) -> String {
-            if host.contains("://") {
-                host.to_string()
-            } else if use_https {
-                "https://".to_owned() + host
+    pub(super) async fn set_host_base_timeout(
+        &self,
+        url: &str,
+        base_timeout: Option<Duration>,
+    ) -> bool {
+        let inner = self.inner().await;
+        if let Some(host) = inner.find_io_host(url).await {
+            inner
+                .io_selector
+                .set_host_base_timeout(&host, base_timeout)
+                .await;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(super) async fn pin_hosts(&self, urls: &[String]) -> bool {
+        let inner = self.inner().await;
+        let mut hosts = Vec::with_capacity(urls.len());
+        for url in urls {
+            if let Some(host) = inner.find_io_host(url).await {
+                hosts.push(host);
             } else {
-                "http://".to_owned() + host
+                return false;
             }
         }
+        inner.io_selector.pin_hosts(hosts).await;
+        true
+    }
+
+    /// 删除对象不存在的缓存，包括 HEAD 请求结果缓存中对象不存在的记录
+    pub(super) async fn invalidate_negative_cache(&self, key: &str, bucket: Option<&str>) -> bool {
+        let inner = self.inner().await;
+        let cache_key = inner.cache_key(key, bucket);
+        let invalidated = inner
+            .negative_cache
+            .as_ref()
+            .is_some_and(|negative_cache| negative_cache.invalidate(&cache_key));
+        let head_invalidated = inner
+            .head_cache
+            .as_ref()
+            .is_some_and(|head_cache| head_cache.invalidate_missing(&cache_key));
+        invalidated || head_invalidated
+    }
+
+    /// 读取预加载缓存中完整覆盖指定区域的数据
+    pub(super) async fn preloaded_range(
+        &self,
+        key: &str,
+        bucket: Option<&str>,
+        pos: u64,
+        size: u64,
+    ) -> Option<Vec<u8>> {
+        let inner = self.inner().await;
+        inner
+            .preload_cache
+            .as_ref()
+            .and_then(|preload_cache| preload_cache.get(&inner.cache_key(key, bucket), pos, size))
+    }
+
+    /// 将区域数据载入预加载缓存，如果载入成功则返回 true
+    pub(super) async fn preload_range(
+        &self,
+        key: &str,
+        bucket: Option<&str>,
+        pos: u64,
+        data: Vec<u8>,
+    ) -> bool {
+        let inner = self.inner().await;
+        inner.preload_cache.as_ref().is_some_and(|preload_cache| {
+            preload_cache.insert(&inner.cache_key(key, bucket), pos, data)
+        })
+    }
+
+    /// 丢弃 HTTP 客户端及其连接池，后续请求将使用新建的客户端，如果客户端存在则返回 true
+    pub(super) async fn purge_connections(&self) -> bool {
+        let inner = self.inner().await;
+        inner.connect_failures.store(0, Relaxed);
+        inner.http_client_timeouts.purge_async_http_client()
     }
 
     pub(super) async fn base_timeout(&self) -> Duration {
         self.inner().await.io_selector.base_timeout()
     }
 
+    pub(super) async fn rng(&self) -> SharedRng {
+        self.inner().await.rng.to_owned()
+    }
+
+    #[cfg(feature = "opendal")]
+    pub(super) async fn sign_download_url(
+        &self,
+        key: &str,
+        bucket: Option<&str>,
+        lifetime: Duration,
+    ) -> Option<Url> {
+        let inner = self.inner().await;
+        let chosen_io_info = inner.io_selector.select_host(&Default::default()).await?;
+        let download_url = inner.url_options.apply_to(make_download_url(
+            &inner.url_options.base_url(chosen_io_info.host()),
+            inner.credential.access_key(),
+            bucket.unwrap_or(&inner.bucket),
+            &rewrite_key(&inner.key_rewrite_rules, key),
+            inner.use_getfile_api,
+            inner.normalize_key,
+        ));
+        sign_download_url_with_lifetime(
+            &inner.credential,
+            Url::parse(&download_url).ok()?,
+            lifetime,
+        )
+        .ok()
+        .and_then(|url| Url::parse(&url).ok())
+    }
+
     pub(super) async fn increase_timeout_power_by(&self, host: &str, timeout_power: usize) {
         self.inner()
             .await
@@ -328,9 +838,13 @@ impl AsyncRangeReader {
         if size == 0 {
             return Ok(Default::default()).into();
         }
-        return self.with_retries(
+        let strict_body_size = self.inner().await.strict_body_size;
+        let encoding = self.inner().await.range_request_encoding;
+        let served_by = StdMutex::new(None);
+        let served_by_ref = &served_by;
+        let result = self.with_retries(
             key,
-            Method::GET,
+            encoding.method(),
             async_task_id,
             tries_info,
             trying_hosts,
@@ -343,15 +857,11 @@ impl AsyncRangeReader {
                         async_task_id, tries, download_url, req_id, &range
                     );
                     let begin_at = Instant::now();
-                    let result = request_builder
-                        .header(RANGE, &range)
-                        .send()
-                        .await;
-                        if let Err(err) = &result {
-                            self.punish_if_needed(host_info.host(), host_info.timeout_power(), err).await;
-                        }
-                    let result = result
-                        .map_err(io_error_from(IoErrorKind::ConnectionAborted))
+                    let result = self
+                        .send_request(encoding.encode(request_builder, &range), &host_info, tries_info)
+                        .await
+                        .and_then(|resp| tries_info.check_response(resp))
+                        .and_then(reject_content_encoded)
                         .and_then(|resp| {
                             if resp.status() != StatusCode::PARTIAL_CONTENT && resp.status() != StatusCode::OK {
                                 return Err(unexpected_status_code(&resp));
@@ -359,12 +869,15 @@ impl AsyncRangeReader {
                             Ok(resp)
                         })
                         .map(|resp| {
-                            let max_size = parse_content_length(&resp).min(size);
-                            (resp, max_size)
+                            let content_length = parse_content_length(&resp);
+                            (resp, content_length)
                         });
                     match result {
-                        Ok((resp, max_size)) => {
-                            read_response_body(resp, Some(max_size)).await
+                        Ok((resp, content_length)) if strict_body_size => {
+                            read_response_body_strictly(resp, content_length, size).await
+                        }
+                        Ok((resp, content_length)) => {
+                            read_response_body(resp, Some(content_length.min(size))).await
                         }
                         Err(err) => Err(err),
                     }
@@ -378,23 +891,264 @@ impl AsyncRangeReader {
                                 req_id,
                                 begin_at.elapsed(),
                             );
+                            *served_by_ref.lock().unwrap() = Some(host_info.host().to_owned());
                         })
                         .tap_err(|err| {
                             warn!(
-                                "{{{}}} [{}] read_at error url: {}, range: {}, error: {}, req_id: {:?}, elapsed: {:?}",
-                                async_task_id, tries, download_url, range, err, req_id, begin_at.elapsed(),
+                                "{{{}}} [{}] read_at error url: {}, range: {}, error: {}, code: {}, req_id: {:?}, elapsed: {:?}",
+                                async_task_id, tries, download_url, range, err, ErrorCode::of(err), req_id, begin_at.elapsed(),
                             );
                         })
                 }
             },
         )
         .await;
+        let served_by = served_by.into_inner().unwrap();
+        if let (Result3::Ok(data), Some(first_host)) = (&result, served_by) {
+            self.verify_with_second_host(key, pos, data, &first_host, tries_info)
+                .await;
+        }
+        return result;
 
         fn generate_range_header(pos: u64, size: u64) -> String {
             format!("bytes={}-{}", pos, pos + size - 1)
         }
     }
 
+    /// 按配置的概率从另一个 IO 域名再次读取相同区域并比较校验和
+    ///
+    /// 不一致时输出警告日志、发出事件并记录打点，验证失败或没有其他可用域名时仅输出日志，不影响首次读取的结果
+    async fn verify_with_second_host(
+        &self,
+        key: &str,
+        pos: u64,
+        data: &[u8],
+        first_host: &str,
+        tries_info: TriesInfo<'_>,
+    ) {
+        let inner = self.inner().await;
+        if data.is_empty()
+            || inner.verify_with_second_host <= 0.0
+            || !inner.rng.gen_bool(inner.verify_with_second_host)
+        {
+            return;
+        }
+        let tried = once(first_host.to_owned()).collect::<HashSet<_>>();
+        let host_info = match inner.io_selector.select_host(&tried).await {
+            Some(host_info) if host_info.host() != first_host => host_info,
+            _ => {
+                debug!(
+                    "No second host is available to verify {}, first host: {}",
+                    key, first_host
+                );
+                return;
+            }
+        };
+        let have_tried = AtomicUsize::new(0);
+        let verify_tries_info = TriesInfo::new(&have_tried, 1)
+            .with_session(tries_info.session)
+            .with_bucket(tries_info.bucket);
+        let encoding = inner.range_request_encoding;
+        let range = format!("bytes={}-{}", pos, pos + data.len() as u64 - 1);
+        let download_url = inner.download_url(
+            host_info.host(),
+            key,
+            inner.use_getfile_api,
+            tries_info.bucket,
+        );
+        let req_id = get_req_id2(SystemTime::now(), 0, 0, host_info.timeout());
+        let request_builder = inner.request_builder(
+            &encoding.method(),
+            &download_url,
+            &req_id,
+            verify_tries_info,
+        );
+        let result = match self
+            .send_request(
+                encoding.encode(request_builder, &range),
+                &host_info,
+                verify_tries_info,
+            )
+            .await
+            .and_then(|resp| verify_tries_info.check_response(resp))
+            .and_then(reject_content_encoded)
+        {
+            Ok(resp)
+                if resp.status() == StatusCode::PARTIAL_CONTENT
+                    || resp.status() == StatusCode::OK =>
+            {
+                read_response_body(resp, Some(data.len() as u64)).await
+            }
+            Ok(resp) => Err(unexpected_status_code(&resp)),
+            Err(err) => Err(err),
+        };
+        let second = match result {
+            Ok(second) => second,
+            Err(err) => {
+                warn!(
+                    "Failed to verify {} with second host {}, range: {}, error: {}",
+                    key,
+                    host_info.host(),
+                    range,
+                    err
+                );
+                return;
+            }
+        };
+        let (first_crc32, second_crc32) = (crc32fast::hash(data), crc32fast::hash(&second));
+        if first_crc32 == second_crc32 && data.len() == second.len() {
+            debug!(
+                "{} is verified with second host {}, range: {}",
+                key,
+                host_info.host(),
+                range
+            );
+            return;
+        }
+        warn!(
+            "Data of {} diverges between hosts, range: {}, {}: {:08x} ({} bytes), {}: {:08x} ({} bytes)",
+            key,
+            range,
+            first_host,
+            first_crc32,
+            data.len(),
+            host_info.host(),
+            second_crc32,
+            second.len(),
+        );
+        record_divergent_read();
+        emit_event(|| DownloadEvent::HostDivergence {
+            key: key.to_owned(),
+            range: range.to_owned(),
+            first_host: first_host.to_owned(),
+            second_host: host_info.host().to_owned(),
+            first_crc32,
+            second_crc32,
+        });
+        inner
+            .dotter
+            .diverged()
+            .await
+            .tap_err(|err| warn!("Failed to dot divergence: {}", err))
+            .ok();
+    }
+
+    /// 流式计算指定范围数据的校验和，重试时从已经计算的位置继续下载
+    pub(super) async fn checksum_range<F: FnMut(HostInfo) -> Fut, Fut: Future<Output = ()>>(
+        &self,
+        pos: u64,
+        size: u64,
+        algorithm: ChecksumAlgorithm,
+        key: &str,
+        async_task_id: u32,
+        tries_info: TriesInfo<'_>,
+        trying_hosts: &TryingHosts,
+        on_host_selected: F,
+    ) -> IoResult3<Vec<u8>> {
+        let mut hasher = RangeHasher::new(algorithm);
+        if size == 0 {
+            return Ok(hasher.finalize()).into();
+        }
+        let mut hashed = 0u64;
+        let state = Arc::new(Mutex::new((&mut hasher, &mut hashed)));
+        let encoding = self.inner().await.range_request_encoding;
+        let result = self
+            .with_retries(
+                key,
+                encoding.method(),
+                async_task_id,
+                tries_info,
+                trying_hosts,
+                on_host_selected,
+                move |tries, request_builder, req_id, download_url, host_info| {
+                    let state = state.to_owned();
+                    async move {
+                        let mut state = state.lock().await;
+                        let (hasher, hashed) = &mut *state;
+                        let start_from = pos + **hashed;
+                        let range = format!("bytes={}-{}", start_from, pos + size - 1);
+                        debug!(
+                            "{{{}}} [{}] checksum_range url: {}, req_id: {:?}, range: {}",
+                            async_task_id, tries, download_url, req_id, range
+                        );
+                        let begin_at = Instant::now();
+                        let result = self
+                            .send_request(encoding.encode(request_builder, &range), &host_info, tries_info)
+                            .await
+                            .and_then(|resp| tries_info.check_response(resp))
+                            .and_then(reject_content_encoded);
+                        match result {
+                            Ok(resp) => {
+                                hash_response_body(resp, start_from, pos + size - start_from, hasher, hashed).await
+                            }
+                            Err(err) => Err(err),
+                        }
+                        .tap_ok(|_| {
+                            info!(
+                                "{{{}}} [{}] checksum_range ok url: {}, range: {}, req_id: {:?}, elapsed: {:?}",
+                                async_task_id, tries, download_url, range, req_id, begin_at.elapsed(),
+                            );
+                        })
+                        .tap_err(|err| {
+                            warn!(
+                                "{{{}}} [{}] checksum_range error url: {}, range: {}, error: {}, code: {}, req_id: {:?}, elapsed: {:?}",
+                                async_task_id, tries, download_url, range, err, ErrorCode::of(err), req_id, begin_at.elapsed(),
+                            );
+                        })
+                    }
+                },
+            )
+            .await;
+        return match result {
+            Result3::Ok(()) => Ok(hasher.finalize()).into(),
+            Result3::Err(err) => Result3::Err(err),
+            Result3::NoMoreTries(err) => Result3::NoMoreTries(err),
+        };
+
+        /// 计算响应体的校验和，超出对象尾部的部分将被忽略，响应体提前结束时返回错误以便继续下载
+        async fn hash_response_body(
+            resp: HttpResponse,
+            start_from: u64,
+            size: u64,
+            hasher: &mut RangeHasher,
+            hashed: &mut u64,
+        ) -> IoResult<()> {
+            let mut remaining = match resp.status() {
+                StatusCode::PARTIAL_CONTENT => {
+                    let (from, to, _) = extract_range_header(resp.headers())?;
+                    if from != start_from {
+                        return Err(IoError::new(
+                            IoErrorKind::InvalidInput,
+                            format!(
+                                "Unexpected Content-Range from {}, expected {}",
+                                from, start_from
+                            ),
+                        ));
+                    }
+                    size.min(to.saturating_sub(from).saturating_add(1))
+                }
+                StatusCode::OK if start_from == 0 => size.min(parse_content_length(&resp)),
+                _ => return Err(unexpected_status_code(&resp)),
+            };
+            let mut body = resp.bytes_stream();
+            while remaining > 0 {
+                let chunk = body
+                    .try_next()
+                    .await
+                    .map_err(io_error_from(IoErrorKind::BrokenPipe))?
+                    .ok_or_else(new_io_error(
+                        IoErrorKind::UnexpectedEof,
+                        "Response body is shorter than expected",
+                    ))?;
+                let chunk = &chunk[..remaining.min(chunk.len() as u64) as usize];
+                hasher.update(chunk);
+                *hashed += chunk.len() as u64;
+                remaining -= chunk.len() as u64;
+            }
+            Ok(())
+        }
+    }
+
     pub(super) async fn read_multi_ranges<F: FnMut(HostInfo) -> Fut, Fut: Future<Output = ()>>(
         &self,
         ranges: &[(u64, u64)],
@@ -404,10 +1158,24 @@ impl AsyncRangeReader {
         trying_hosts: &TryingHosts,
         on_host_selected: F,
     ) -> IoResult3<Vec<RangePart>> {
+        let inner = self.inner().await;
+        let encoding = inner.range_request_encoding;
+        if inner.disable_read_multi_ranges {
+            return self
+                .read_ranges_sequentially(
+                    ranges,
+                    key,
+                    async_task_id,
+                    tries_info,
+                    trying_hosts,
+                    on_host_selected,
+                )
+                .await;
+        }
         return self
             .with_retries(
                 key,
-                Method::GET,
+                encoding.method(),
                 async_task_id,
                 tries_info,
                 trying_hosts,
@@ -423,14 +1191,11 @@ impl AsyncRangeReader {
                     );
                     let range = generate_range_header(ranges);
                     let begin_at = Instant::now();
-                    let result = request_builder
-                        .header(RANGE, &range)
-                        .send()
-                        .await;
-                    if let Err(err) = &result {
-                        self.punish_if_needed(host_info.host(), host_info.timeout_power(), err).await;
-                    }
-                    let result = result.map_err(io_error_from(IoErrorKind::ConnectionAborted));
+                    let result = self
+                        .send_request(encoding.encode(request_builder, &range), &host_info, tries_info)
+                        .await
+                        .and_then(|resp| tries_info.check_response(resp))
+                        .and_then(reject_content_encoded);
                     match result {
                         Ok(resp) => {
                             let mut parts = Vec::with_capacity(ranges.len());
@@ -438,12 +1203,11 @@ impl AsyncRangeReader {
                                 StatusCode::OK => {
                                     let body = read_response_body(resp, None).await?;
                                     for &(from, len) in ranges.iter() {
-                                        let from = (from as usize).min(body.len());
-                                        let len = (len as usize).min(body.len() - from);
-                                        if len > 0 {
+                                        if let Some(range) = range_in_body(body.len(), from, len) {
                                             parts.push(RangePart {
-                                                data: body[from..(from + len)].to_vec(),
-                                                range: (from as u64, len as u64),
+                                                range: (range.start as u64, range.len() as u64),
+                                                data: body[range].to_vec(),
+                                                requested: (from, len),
                                             });
                                         }
                                     }
@@ -461,25 +1225,21 @@ impl AsyncRangeReader {
                                         .map_err(io_error_from(IoErrorKind::InvalidInput))?
                                         .parse()
                                         .map_err(io_error_from(IoErrorKind::InvalidInput))?;
-                                    let boundary = content_type.get_param(BOUNDARY).unwrap();
-                                    let mut multipart =
-                                        Multipart::new(resp.bytes_stream(), boundary.as_str());
-                                    while let Some(field) = multipart
-                                        .next_field()
+                                    let boundary = content_type
+                                        .get_param(BOUNDARY)
+                                        .ok_or(MultipartError::InvalidBoundary)?;
+                                    let mut parser = MultipartParser::new(boundary.as_str())?;
+                                    let mut body = resp.bytes_stream();
+                                    while let Some(chunk) = body
+                                        .try_next()
                                         .await
                                         .map_err(io_error_from(IoErrorKind::BrokenPipe))?
                                     {
-                                        let (from, to, _) = extract_range_header(field.headers())?;
-                                        let len = to - from + 1;
-                                        parts.push(RangePart {
-                                            data: field
-                                                .bytes()
-                                                .await
-                                                .map(|b| b.to_vec())
-                                                .map_err(io_error_from(IoErrorKind::BrokenPipe))?,
-                                            range: (from, len),
-                                        });
+                                        let parse_begin_at = Instant::now();
+                                        parts.extend(parser.feed(&chunk)?);
+                                        tries_info.record_parse(parse_begin_at.elapsed());
                                     }
+                                    parser.finish()?;
                                 }
                                 StatusCode::PARTIAL_CONTENT => {
                                     let (from, to, _) = extract_range_header(resp.headers())?;
@@ -487,13 +1247,14 @@ impl AsyncRangeReader {
                                     parts.push(RangePart {
                                         data: read_response_body(resp, None).await?,
                                         range: (from, len),
+                                        requested: (from, len),
                                     });
                                 }
                                 _ => {
                                     return Err(unexpected_status_code(&resp));
                                 }
                             }
-                            Ok(parts)
+                            Ok(sort_range_parts(ranges, parts))
                         }
                         Err(err) => Err(err),
                     }
@@ -505,8 +1266,8 @@ impl AsyncRangeReader {
                     })
                     .tap_err(|err| {
                         warn!(
-                            "{{{}}} [{}] read_multi_ranges error url: {}, error: {}, req_id: {:?}, elapsed: {:?}",
-                            async_task_id, tries, download_url, err, req_id, begin_at.elapsed(),
+                            "{{{}}} [{}] read_multi_ranges error url: {}, error: {}, code: {}, req_id: {:?}, elapsed: {:?}",
+                            async_task_id, tries, download_url, err, ErrorCode::of(err), req_id, begin_at.elapsed(),
                         );
                     })
                 },
@@ -527,15 +1288,71 @@ impl AsyncRangeReader {
         }
     }
 
-    pub(super) async fn exist<F: FnMut(HostInfo) -> Fut, Fut: Future<Output = ()>>(
+    /// 禁用多范围读取时，依次对每个范围调用 read_at
+    async fn read_ranges_sequentially<F: FnMut(HostInfo) -> Fut, Fut: Future<Output = ()>>(
         &self,
+        ranges: &[(u64, u64)],
         key: &str,
         async_task_id: u32,
         tries_info: TriesInfo<'_>,
         trying_hosts: &TryingHosts,
-        on_host_selected: F,
+        mut on_host_selected: F,
+    ) -> IoResult3<Vec<RangePart>> {
+        let mut parts = Vec::with_capacity(ranges.len());
+        for &(from, len) in ranges.iter() {
+            let have_tried = AtomicUsize::new(0);
+            let data = match self
+                .read_at(
+                    from,
+                    len,
+                    key,
+                    async_task_id,
+                    tries_info.with_have_tried(&have_tried),
+                    trying_hosts,
+                    &mut on_host_selected,
+                )
+                .await
+            {
+                Result3::Ok(data) => data,
+                Result3::Err(err) => return Result3::Err(err),
+                Result3::NoMoreTries(err) => return Result3::NoMoreTries(err),
+            };
+            if !data.is_empty() {
+                parts.push(RangePart {
+                    range: (from, data.len() as u64),
+                    data,
+                    requested: (from, len),
+                });
+            }
+        }
+        Ok(parts).into()
+    }
+
+    pub(super) async fn exist<F: FnMut(HostInfo) -> Fut, Fut: Future<Output = ()>>(
+        &self,
+        key: &str,
+        async_task_id: u32,
+        tries_info: TriesInfo<'_>,
+        trying_hosts: &TryingHosts,
+        on_host_selected: F,
     ) -> IoResult3<bool> {
-        self.with_retries(
+        let inner = self.inner().await;
+        let cache_key = inner.cache_key(key, tries_info.bucket);
+        if let Some(metadata) = inner
+            .head_cache
+            .as_ref()
+            .and_then(|cache| cache.get(&cache_key))
+        {
+            tries_info.record_cache_hit();
+            return Ok(metadata.is_some()).into();
+        }
+        let negative_cache = inner.negative_cache.as_ref();
+        if negative_cache.is_some_and(|negative_cache| negative_cache.contains(&cache_key)) {
+            tries_info.record_cache_hit();
+            return Ok(false).into();
+        }
+        let respect_cache_control = inner.respect_cache_control;
+        let result = self._with_retries(
             key,
             Method::HEAD,
             async_task_id,
@@ -548,17 +1365,29 @@ impl AsyncRangeReader {
                     async_task_id, tries, download_url, req_id
                 );
                 let begin_at = Instant::now();
-                let result = request_builder.send().await;
-                if let Err(err) = &result {
-                    self.punish_if_needed(host_info.host(), host_info.timeout_power(), err)
-                        .await;
-                }
-                result
-                    .map_err(io_error_from(IoErrorKind::ConnectionAborted))
-                    .and_then(|resp| match resp.status() {
-                        StatusCode::OK => Ok(true),
-                        StatusCode::NOT_FOUND => Ok(false),
-                        _ => Err(unexpected_status_code(&resp)),
+                self.send_request(request_builder, &host_info, tries_info)
+                    .await
+                    .and_then(|resp| tries_info.check_response(resp))
+                    .and_then(|resp| {
+                        let directives = cache_directives_of(&resp, respect_cache_control);
+                        match resp.status() {
+                            // 经过内容编码的响应无法得到对象尺寸，对象存在但不缓存其元信息
+                            StatusCode::OK => match reject_content_encoded(resp) {
+                                Ok(resp) => Ok((
+                                    true,
+                                    Some(Some(ObjectMetadata::from_headers(
+                                        parse_content_length(&resp),
+                                        resp.headers(),
+                                    ))),
+                                    directives,
+                                )),
+                                Err(_) => Ok((true, None, directives)),
+                            },
+                            status if is_not_found_status_code(status.as_u16()) => {
+                                Ok((false, Some(None), directives))
+                            }
+                            _ => Err(unexpected_status_code(&resp)),
+                        }
                     })
                     .tap_ok(|_| {
                         info!(
@@ -572,18 +1401,34 @@ impl AsyncRangeReader {
                     })
                     .tap_err(|err| {
                         warn!(
-                            "{{{}}} [{}] exist error url: {}, error: {}, req_id: {:?}, elapsed: {:?}",
+                            "{{{}}} [{}] exist error url: {}, error: {}, code: {}, req_id: {:?}, elapsed: {:?}",
                             async_task_id,
                             tries,
                             download_url,
                             err,
+                            ErrorCode::of(err),
                             req_id,
                             begin_at.elapsed(),
                         );
                     })
             },
         )
-        .await
+        .await;
+        match result {
+            Result3::Ok((existed, metadata, directives)) => {
+                let cache_write_begin_at = Instant::now();
+                if let (Some(head_cache), Some(metadata)) = (inner.head_cache.as_ref(), metadata) {
+                    head_cache.insert(&cache_key, metadata, &directives);
+                }
+                if let (Some(negative_cache), false) = (negative_cache, existed) {
+                    negative_cache.insert(&cache_key, &directives);
+                }
+                tries_info.record_cache_write(cache_write_begin_at.elapsed());
+                Ok(existed).into()
+            }
+            Result3::Err(err) => Result3::Err(err),
+            Result3::NoMoreTries(err) => Result3::NoMoreTries(err),
+        }
     }
 
     pub(super) async fn file_size<F: FnMut(HostInfo) -> Fut, Fut: Future<Output = ()>>(
@@ -594,7 +1439,49 @@ impl AsyncRangeReader {
         trying_hosts: &TryingHosts,
         on_host_selected: F,
     ) -> IoResult3<u64> {
-        self.with_retries(
+        match self
+            .stat(
+                key,
+                async_task_id,
+                tries_info,
+                trying_hosts,
+                on_host_selected,
+            )
+            .await
+        {
+            Result3::Ok(metadata) => Ok(metadata.size()).into(),
+            Result3::Err(err) => Result3::Err(err),
+            Result3::NoMoreTries(err) => Result3::NoMoreTries(err),
+        }
+    }
+
+    pub(super) async fn stat<F: FnMut(HostInfo) -> Fut, Fut: Future<Output = ()>>(
+        &self,
+        key: &str,
+        async_task_id: u32,
+        tries_info: TriesInfo<'_>,
+        trying_hosts: &TryingHosts,
+        on_host_selected: F,
+    ) -> IoResult3<ObjectMetadata> {
+        let inner = self.inner().await;
+        let cache_key = inner.cache_key(key, tries_info.bucket);
+        match inner
+            .head_cache
+            .as_ref()
+            .and_then(|cache| cache.get(&cache_key))
+        {
+            Some(Some(metadata)) => {
+                tries_info.record_cache_hit();
+                return Ok(metadata).into();
+            }
+            Some(None) => {
+                tries_info.record_cache_hit();
+                return Result3::Err(unexpected_status_code_error(404, None));
+            }
+            None => {}
+        }
+        let respect_cache_control = inner.respect_cache_control;
+        let result = self.with_retries(
             key,
             Method::HEAD,
             async_task_id,
@@ -603,27 +1490,30 @@ impl AsyncRangeReader {
             on_host_selected,
             |tries, request_builder, req_id, download_url, host_info| async move {
                 debug!(
-                    "{{{}}} [{}] file_size url: {}, req_id: {:?}",
+                    "{{{}}} [{}] stat url: {}, req_id: {:?}",
                     async_task_id, tries, download_url, req_id
                 );
                 let begin_at = Instant::now();
-                let result = request_builder.send().await;
-                if let Err(err) = &result {
-                    self.punish_if_needed(host_info.host(), host_info.timeout_power(), err)
-                        .await;
-                }
-                result
-                    .map_err(io_error_from(IoErrorKind::ConnectionAborted))
+                self.send_request(request_builder, &host_info, tries_info)
+                    .await
+                    .and_then(|resp| tries_info.check_response(resp))
+                    .and_then(reject_content_encoded)
                     .and_then(|resp| {
                         if resp.status() == StatusCode::OK {
-                            Ok(parse_content_length(&resp))
+                            Ok((
+                                ObjectMetadata::from_headers(
+                                    parse_content_length(&resp),
+                                    resp.headers(),
+                                ),
+                                cache_directives_of(&resp, respect_cache_control),
+                            ))
                         } else {
                             Err(unexpected_status_code(&resp))
                         }
                     })
                     .tap_ok(|_| {
                         info!(
-                            "{{{}}} [{}] file_size ok url: {}, req_id: {:?}, elapsed: {:?}",
+                            "{{{}}} [{}] stat ok url: {}, req_id: {:?}, elapsed: {:?}",
                             async_task_id,
                             tries,
                             download_url,
@@ -633,18 +1523,31 @@ impl AsyncRangeReader {
                     })
                     .tap_err(|err| {
                         warn!(
-                            "{{{}}} [{}] file_size error url: {}, error: {}, req_id: {:?}, elapsed: {:?}",
+                            "{{{}}} [{}] stat error url: {}, error: {}, code: {}, req_id: {:?}, elapsed: {:?}",
                             async_task_id,
                             tries,
                             download_url,
                             err,
+                            ErrorCode::of(err),
                             req_id,
                             begin_at.elapsed(),
                         );
                     })
             },
         )
-        .await
+        .await;
+        match result {
+            Result3::Ok((metadata, directives)) => {
+                if let Some(head_cache) = inner.head_cache.as_ref() {
+                    let cache_write_begin_at = Instant::now();
+                    head_cache.insert(&cache_key, Some(metadata.to_owned()), &directives);
+                    tries_info.record_cache_write(cache_write_begin_at.elapsed());
+                }
+                Ok(metadata).into()
+            }
+            Result3::Err(err) => Result3::Err(err),
+            Result3::NoMoreTries(err) => Result3::NoMoreTries(err),
+        }
     }
 
     pub(super) async fn download<F: FnMut(HostInfo) -> Fut, Fut: Future<Output = ()>>(
@@ -655,9 +1558,21 @@ impl AsyncRangeReader {
         trying_hosts: &TryingHosts,
         mut on_host_selected: F,
     ) -> IoResult3<Vec<u8>> {
+        if self.inner().await.disable_download {
+            return self
+                .download_by_chunks(
+                    key,
+                    async_task_id,
+                    tries_info,
+                    trying_hosts,
+                    on_host_selected,
+                )
+                .await;
+        }
         let mut result = Vec::new();
+        let mut content_encoding = None;
         loop {
-            let (chunk, mut completed) = match self
+            let (chunk, mut completed, chunk_content_encoding) = match self
                 ._download(
                     key,
                     async_task_id,
@@ -672,6 +1587,7 @@ impl AsyncRangeReader {
                 Result3::Err(err) => return Result3::Err(err),
                 Result3::NoMoreTries(err) => return Result3::NoMoreTries(err),
             };
+            content_encoding = content_encoding.or(chunk_content_encoding);
             if result.is_empty() {
                 result = chunk;
             } else if chunk.is_empty() {
@@ -680,13 +1596,91 @@ impl AsyncRangeReader {
                 result.extend(chunk);
             }
             if completed {
-                return Result3::Ok(result);
+                return self.decode_body(result, content_encoding).await.into();
             } else {
                 info!("Early EOF Response Body is detected in {}::download(), will start a new GET request for the rest body", module_path!());
             }
         }
     }
 
+    /// 处理以压缩编码存储的对象
+    ///
+    /// 启用解压时解压 gzip 编码的数据，无法解压的压缩编码返回 [`crate::ContentEncodedError`] 错误，
+    /// 未启用解压时原样返回压缩后的数据
+    async fn decode_body(
+        &self,
+        body: Vec<u8>,
+        content_encoding: Option<String>,
+    ) -> IoResult<Vec<u8>> {
+        let inner = self.inner().await;
+        match content_encoding {
+            None => Ok(body),
+            Some(content_encoding) if inner.decompress_content_encoding => {
+                if content_encoding == "gzip" {
+                    gzip::decompress(&body, inner.max_object_size)
+                } else {
+                    Err(content_encoded_error(&content_encoding))
+                }
+            }
+            Some(_) => {
+                check_object_size(body.len() as u64, inner.max_object_size)?;
+                Ok(body)
+            }
+        }
+    }
+
+    /// 禁用下载整个文件时，先获取文件尺寸，再分块调用 read_at
+    async fn download_by_chunks<F: FnMut(HostInfo) -> Fut, Fut: Future<Output = ()>>(
+        &self,
+        key: &str,
+        async_task_id: u32,
+        tries_info: TriesInfo<'_>,
+        trying_hosts: &TryingHosts,
+        mut on_host_selected: F,
+    ) -> IoResult3<Vec<u8>> {
+        let have_tried = AtomicUsize::new(0);
+        let file_size = match self
+            .file_size(
+                key,
+                async_task_id,
+                tries_info.with_have_tried(&have_tried),
+                trying_hosts,
+                &mut on_host_selected,
+            )
+            .await
+        {
+            Result3::Ok(file_size) => file_size,
+            Result3::Err(err) => return Result3::Err(err),
+            Result3::NoMoreTries(err) => return Result3::NoMoreTries(err),
+        };
+        let mut result = Vec::with_capacity(file_size as usize);
+        while (result.len() as u64) < file_size {
+            let pos = result.len() as u64;
+            let have_tried = AtomicUsize::new(0);
+            let chunk = match self
+                .read_at(
+                    pos,
+                    DOWNLOAD_CHUNK_SIZE.min(file_size - pos),
+                    key,
+                    async_task_id,
+                    tries_info.with_have_tried(&have_tried),
+                    trying_hosts,
+                    &mut on_host_selected,
+                )
+                .await
+            {
+                Result3::Ok(chunk) => chunk,
+                Result3::Err(err) => return Result3::Err(err),
+                Result3::NoMoreTries(err) => return Result3::NoMoreTries(err),
+            };
+            if chunk.is_empty() {
+                break;
+            }
+            result.extend(chunk);
+        }
+        Ok(result).into()
+    }
+
     async fn _download<F: FnMut(HostInfo) -> Fut, Fut: Future<Output = ()>>(
         &self,
         key: &str,
@@ -695,13 +1689,14 @@ impl AsyncRangeReader {
         tries_info: TriesInfo<'_>,
         trying_hosts: &TryingHosts,
         on_host_selected: F,
-    ) -> IoResult3<(Vec<u8>, bool)> {
+    ) -> IoResult3<(Vec<u8>, bool, Option<String>)> {
         let mut buf = Vec::new();
         let buf_cursor = Arc::new(Mutex::new(Cursor::new(&mut buf)));
+        let encoding = self.inner().await.range_request_encoding;
         let result = self
             .with_retries(
                 key,
-                Method::GET,
+                encoding.method(),
                 async_task_id,
                 tries_info,
                 trying_hosts,
@@ -717,34 +1712,28 @@ impl AsyncRangeReader {
                         );
                         let begin_at = Instant::now();
                         if start_from > 0 {
-                            request_builder =
-                                request_builder.header(RANGE, format!("bytes={}-", start_from));
+                            request_builder = encoding
+                                .encode(request_builder, &format!("bytes={}-", start_from));
                         }
-                        let result = request_builder
-                            .send()
-                            .await;
-                        if let Err(err) = &result {
-                            self.punish_if_needed(
-                                host_info.host(),
-                                host_info.timeout_power(),
-                                err,
-                            ).await;
-                        }
-                        let result = result.map_err(io_error_from(IoErrorKind::ConnectionAborted));
+                        let result = self
+                            .send_request(request_builder, &host_info, tries_info)
+                            .await
+                            .and_then(|resp| tries_info.check_response(resp));
                         match result {
                             Ok(resp) => {
                                 let content_length = parse_content_length(&resp);
+                                let content_encoding = content_encoding_of(resp.headers());
                                 write_to_writer(resp,  &mut *buf_cursor).await.map(|actually_downloaded| {
                                     if let Some(actually_downloaded) = actually_downloaded {
-                                        (actually_downloaded, actually_downloaded < content_length)
+                                        (actually_downloaded, actually_downloaded < content_length, content_encoding)
                                     } else {
-                                        (0, false)
+                                        (0, false, None)
                                     }
                                 })
                             },
                             Err(err) => Err(err),
                         }
-                        .tap_ok(|(downloaded, incompleted)| {
+                        .tap_ok(|(downloaded, incompleted, _)| {
                             info!(
                                 "{{{}}} [{}] download ok url: {}, start_from: {}, downloaded: {}, completed: {:?}, req_id: {:?}, elapsed: {:?}",
                                 async_task_id, tries, download_url, start_from, downloaded, !incompleted, req_id, begin_at.elapsed(),
@@ -752,8 +1741,8 @@ impl AsyncRangeReader {
                         })
                         .tap_err(|err| {
                             warn!(
-                                "{{{}}} [{}] download error url: {}, start_from: {}, error: {}, req_id: {:?}, elapsed: {:?}",
-                                async_task_id, tries, download_url, start_from, err, req_id, begin_at.elapsed(),
+                                "{{{}}} [{}] download error url: {}, start_from: {}, error: {}, code: {}, req_id: {:?}, elapsed: {:?}",
+                                async_task_id, tries, download_url, start_from, err, ErrorCode::of(err), req_id, begin_at.elapsed(),
                             );
                         })
                     }
@@ -761,7 +1750,9 @@ impl AsyncRangeReader {
             )
             .await;
         return match result {
-            Result3::Ok((_, incompleted)) => Ok((buf, !incompleted)).into(),
+            Result3::Ok((_, incompleted, content_encoding)) => {
+                Ok((buf, !incompleted, content_encoding)).into()
+            }
             Result3::Err(err) => Result3::Err(err),
             Result3::NoMoreTries(err) => Result3::NoMoreTries(err),
         };
@@ -796,9 +1787,10 @@ impl AsyncRangeReader {
         trying_hosts: &TryingHosts,
         on_host_selected: F,
     ) -> IoResult3<(Vec<u8>, u64)> {
+        let encoding = self.inner().await.range_request_encoding;
         return self.with_retries(
             key,
-            Method::GET,
+            encoding.method(),
             async_task_id,
             tries_info,
             trying_hosts,
@@ -809,14 +1801,11 @@ impl AsyncRangeReader {
                     async_task_id, tries, download_url, req_id, size,
                 );
                 let begin_at = Instant::now();
-                let result = request_builder
-                    .header(RANGE, format!("bytes=-{}", size))
-                    .send()
-                    .await;
-                    if let Err(err) = &result {
-                        self.punish_if_needed(host_info.host(), host_info.timeout_power(), err).await;
-                    }
-                    let result = result.map_err(io_error_from(IoErrorKind::ConnectionAborted))
+                let result = self
+                    .send_request(encoding.encode(request_builder, &format!("bytes=-{}", size)), &host_info, tries_info)
+                    .await
+                    .and_then(|resp| tries_info.check_response(resp))
+                    .and_then(reject_content_encoded)
                     .and_then(|resp| {
                         if resp.status() == StatusCode::PARTIAL_CONTENT {
                             Ok(resp)
@@ -836,8 +1825,8 @@ impl AsyncRangeReader {
                 })
                 .tap_err(|err| {
                     warn!(
-                        "{{{}}} [{}] download error url: {}, len: {}, error: {}, req_id: {:?}, elapsed: {:?}",
-                        async_task_id, tries, download_url, size, err, req_id, begin_at.elapsed(),
+                        "{{{}}} [{}] download error url: {}, len: {}, error: {}, code: {}, req_id: {:?}, elapsed: {:?}",
+                        async_task_id, tries, download_url, size, err, ErrorCode::of(err), req_id, begin_at.elapsed(),
                     );
                 })
             }
@@ -854,16 +1843,134 @@ impl AsyncRangeReader {
         }
     }
 
+    /// 获取通过 `attname` 指定的下载文件名
+    pub(super) async fn attname(&self) -> Option<String> {
+        self.inner().await.url_options.attname.to_owned()
+    }
+
     async fn inner(&self) -> &Arc<AsyncRangeReaderInner> {
         self.0.get().await
     }
 
+    /// 启用了对象不存在的缓存时，在有效期内直接返回不存在的错误，并记录新的不存在的对象
     async fn with_retries<
         T,
         F: FnMut(usize, HttpRequestBuilder, HeaderValue, Url, HostInfo) -> Fut,
         Fut: Future<Output = IoResult<T>>,
         F2: FnMut(HostInfo) -> Fut2,
         Fut2: Future<Output = ()>,
+    >(
+        &self,
+        key: &str,
+        method: Method,
+        async_task_id: u32,
+        tries_info: TriesInfo<'_>,
+        trying_hosts: &TryingHosts,
+        on_host_selected: F2,
+        for_each_url: F,
+    ) -> IoResult3<T> {
+        let inner = self.inner().await;
+        let negative_cache = if let Some(negative_cache) = inner.negative_cache.as_ref() {
+            negative_cache
+        } else {
+            return self
+                ._with_retries(
+                    key,
+                    method,
+                    async_task_id,
+                    tries_info,
+                    trying_hosts,
+                    on_host_selected,
+                    for_each_url,
+                )
+                .await;
+        };
+        let cache_key = inner.cache_key(key, tries_info.bucket);
+        if negative_cache.contains(&cache_key) {
+            tries_info.record_cache_hit();
+            return Result3::Err(unexpected_status_code_error(404, None));
+        }
+        let result = self
+            ._with_retries(
+                key,
+                method,
+                async_task_id,
+                tries_info,
+                trying_hosts,
+                on_host_selected,
+                for_each_url,
+            )
+            .await;
+        if let Result3::Err(err) | Result3::NoMoreTries(Some(err)) = &result {
+            if err.kind() == IoErrorKind::NotFound {
+                let cache_write_begin_at = Instant::now();
+                negative_cache.insert(&cache_key, &Default::default());
+                tries_info.record_cache_write(cache_write_begin_at.elapsed());
+            }
+        }
+        result
+    }
+
+    /// 所有尝试都因域名级别的错误失败时，强制更新域名列表，更新成功则在新的域名上再尝试一次
+    async fn _with_retries<
+        T,
+        F: FnMut(usize, HttpRequestBuilder, HeaderValue, Url, HostInfo) -> Fut,
+        Fut: Future<Output = IoResult<T>>,
+        F2: FnMut(HostInfo) -> Fut2,
+        Fut2: Future<Output = ()>,
+    >(
+        &self,
+        key: &str,
+        method: Method,
+        async_task_id: u32,
+        tries_info: TriesInfo<'_>,
+        trying_hosts: &TryingHosts,
+        mut on_host_selected: F2,
+        mut for_each_url: F,
+    ) -> IoResult3<T> {
+        let result = self
+            .try_each_host(
+                key,
+                method.to_owned(),
+                async_task_id,
+                tries_info,
+                trying_hosts,
+                &mut on_host_selected,
+                &mut for_each_url,
+            )
+            .await;
+        if !matches!(result, Result3::NoMoreTries(Some(_))) || !self.force_update_urls().await {
+            return result;
+        }
+        let have_tried = AtomicUsize::new(0);
+        let tries_info = TriesInfo {
+            total_tries: 1,
+            preferred_host: None,
+            ..tries_info.with_have_tried(&have_tried)
+        };
+        match self
+            .try_each_host(
+                key,
+                method,
+                async_task_id,
+                tries_info,
+                &Default::default(),
+                on_host_selected,
+                for_each_url,
+            )
+            .await
+        {
+            Result3::NoMoreTries(None) => result,
+            retried => retried,
+        }
+    }
+
+    async fn try_each_host<
+        T,
+        F: FnMut(usize, HttpRequestBuilder, HeaderValue, Url, HostInfo) -> Fut,
+        Fut: Future<Output = IoResult<T>>,
+        F2: FnMut(HostInfo) -> Fut2,
+        Fut2: Future<Output = ()>,
     >(
         &self,
         key: &str,
@@ -878,15 +1985,89 @@ impl AsyncRangeReader {
         let mut last_error: Option<IoError> = None;
         let inner = self.inner().await;
 
+        if let Some(cdn_selector) = inner.cdn_selector.as_ref() {
+            let select_begin_at = Instant::now();
+            if let Some(cdn_info) = cdn_selector.select_host(&Default::default()).await {
+                tries_info.record_host_selection(select_begin_at.elapsed());
+                on_host_selected(cdn_info.to_owned()).await;
+                let download_url =
+                    inner.download_url(cdn_info.host(), key, false, tries_info.bucket);
+                let req_id = get_req_id2(begin_at, 0, async_task_id, cdn_info.timeout());
+                let request_begin_at_instant = Instant::now();
+                let request_builder =
+                    inner.request_builder(&method, &download_url, &req_id, tries_info);
+                let result = for_each_url(
+                    0,
+                    request_builder,
+                    req_id,
+                    download_url,
+                    cdn_info.to_owned(),
+                )
+                .await;
+                inner
+                    .dotter
+                    .dot(
+                        DotType::Http,
+                        ApiName::CdnGetfile,
+                        result.is_ok(),
+                        request_begin_at_instant.elapsed(),
+                    )
+                    .await
+                    .ok();
+                match result {
+                    Ok(result) => {
+                        cdn_selector.reward(cdn_info.host()).await;
+                        record_host_stats(
+                            cdn_info.host(),
+                            true,
+                            request_begin_at_instant.elapsed(),
+                        )
+                        .await;
+                        return Ok(result).into();
+                    }
+                    Err(err) => {
+                        if cdn_selector
+                            .punish(cdn_info.host(), &err, &inner.dotter)
+                            .await
+                        {
+                            record_host_stats(
+                                cdn_info.host(),
+                                false,
+                                request_begin_at_instant.elapsed(),
+                            )
+                            .await;
+                        }
+                        warn!(
+                            "CDN host {} responds error, fall back to io hosts: {}",
+                            cdn_info.host(),
+                            err
+                        );
+                        tries_info.record_failover();
+                    }
+                }
+            }
+        }
+
         loop {
             let tries = tries_info.have_tried.fetch_add(1, Relaxed);
             if tries >= tries_info.total_tries {
                 return IoResult3::NoMoreTries(last_error);
+            } else if tries > 0 {
+                tries_info.record_retry();
             }
 
+            let select_begin_at = Instant::now();
             let chosen_io_info = {
                 let mut guard = trying_hosts.lock().await;
-                if let Some(chosen) = inner.io_selector.select_host(&guard).await {
+                let preferred = match tries_info.preferred_host.filter(|_| tries == 0) {
+                    Some(host) => inner.io_selector.select_preferred_host(host, &guard).await,
+                    None => None,
+                };
+                let chosen = match preferred {
+                    Some(chosen) => Some(chosen),
+                    None => inner.io_selector.select_host(&guard).await,
+                };
+                if let Some(chosen) = chosen {
                     guard.insert(chosen.host().to_owned());
                     drop(guard);
                     TryingHostInfo {
@@ -897,18 +2078,13 @@ impl AsyncRangeReader {
                     return IoResult3::NoMoreTries(last_error);
                 }
             };
+            tries_info.record_host_selection(select_begin_at.elapsed());
             on_host_selected(chosen_io_info.to_owned()).await;
-            let download_url = sign_download_url_if_needed(
-                &make_download_url(
-                    chosen_io_info.host(),
-                    inner.credential.access_key(),
-                    &inner.bucket,
-                    key,
-                    inner.use_getfile_api,
-                    inner.normalize_key,
-                ),
-                inner.private_url_lifetime,
-                &inner.credential,
+            let download_url = inner.download_url(
+                chosen_io_info.host(),
+                key,
+                inner.use_getfile_api,
+                tries_info.bucket,
             );
             let req_id = get_req_id2(
                 begin_at,
@@ -917,10 +2093,8 @@ impl AsyncRangeReader {
                 chosen_io_info.host_info.timeout(),
             );
             let request_begin_at_instant = Instant::now();
-            let request_builder = inner
-                .http_client
-                .request(method.to_owned(), download_url.to_owned())
-                .header(REQUEST_ID_HEADER, req_id.to_owned());
+            let request_builder =
+                inner.request_builder(&method, &download_url, &req_id, tries_info);
             match for_each_url(
                 tries,
                 request_builder,
@@ -932,6 +2106,12 @@ impl AsyncRangeReader {
             {
                 Ok(result) => {
                     inner.io_selector.reward(chosen_io_info.host()).await;
+                    record_host_stats(
+                        chosen_io_info.host(),
+                        true,
+                        request_begin_at_instant.elapsed(),
+                    )
+                    .await;
                     inner
                         .dotter
                         .dot(
@@ -944,7 +2124,35 @@ impl AsyncRangeReader {
                         .ok();
                     return Ok(result).into();
                 }
+                Err(err) if is_object_level_error(&err, &inner.object_level_status_codes) => {
+                    inner.io_selector.reward(chosen_io_info.host()).await;
+                    record_host_stats(
+                        chosen_io_info.host(),
+                        true,
+                        request_begin_at_instant.elapsed(),
+                    )
+                    .await;
+                    inner
+                        .dotter
+                        .dot(
+                            DotType::Http,
+                            ApiName::IoGetfile,
+                            true,
+                            request_begin_at_instant.elapsed(),
+                        )
+                        .await
+                        .ok();
+                    return Err(err).into();
+                }
                 Err(err) => {
+                    if let Some(final_host) = redirected_host_of(&err, chosen_io_info.host()) {
+                        warn!(
+                            "{} is redirected to {}, which responds error: {}",
+                            chosen_io_info.host(),
+                            final_host,
+                            err
+                        );
+                    }
                     let punished = inner
                         .io_selector
                         .punish(chosen_io_info.host(), &err, &inner.dotter)
@@ -960,74 +2168,137 @@ impl AsyncRangeReader {
                         .await
                         .ok();
                     if punished {
+                        record_host_stats(
+                            chosen_io_info.host(),
+                            false,
+                            request_begin_at_instant.elapsed(),
+                        )
+                        .await;
+                        emit_event(|| DownloadEvent::RetryScheduled {
+                            api: ApiName::IoGetfile.to_string(),
+                            host: chosen_io_info.host().to_owned(),
+                            tries: tries + 1,
+                        });
                         last_error = Some(err);
+                        if tries + 1 < tries_info.total_tries {
+                            tries_info.record_failover();
+                        }
+                        let delay = inner.failover_backoff.delay_with_rng(tries + 1, &inner.rng);
+                        if delay > Duration::from_millis(0) && tries + 1 < tries_info.total_tries {
+                            sleep(delay).await;
+                        }
                     } else {
                         return Err(err).into();
                     }
                 }
             }
         }
+    }
 
-        fn make_download_url(
-            io_url: &str,
-            access_key: &str,
-            bucket: &str,
-            key: &str,
-            use_getfile_api: bool,
-            normalize_key: bool,
-        ) -> String {
-            let mut url = if use_getfile_api {
-                format!("{}/getfile/{}/{}", io_url, access_key, bucket)
-            } else {
-                io_url.to_owned()
-            };
-            if normalize_key {
-                if url.ends_with('/') && key.starts_with('/') {
-                    url.truncate(url.len() - 1);
-                } else if !url.ends_with('/') && !key.starts_with('/') {
-                    url.push('/');
-                }
+    async fn send_request(
+        &self,
+        request_builder: HttpRequestBuilder,
+        host_info: &HostInfo,
+        tries_info: TriesInfo<'_>,
+    ) -> IoResult<HttpResponse> {
+        match send_request(request_builder).await {
+            Ok(resp) => {
+                tries_info.record_response();
+                let inner = self.inner().await;
+                inner.connect_failures.store(0, Relaxed);
+                check_response_headers(
+                    resp.headers(),
+                    inner.max_response_header_size,
+                    inner.strict_header_validation,
+                )?;
+                Ok(resp)
             }
-            url.push_str(key);
-            url
-        }
-
-        fn sign_download_url_if_needed(
-            url: &str,
-            private_url_lifetime: Option<Duration>,
-            credential: &Credential,
-        ) -> Url {
-            if let Some(private_url_lifetime) = private_url_lifetime {
-                Url::parse(
-                    &sign_download_url_with_lifetime(
-                        credential,
-                        Url::parse(url).unwrap(),
-                        private_url_lifetime,
-                    )
-                    .unwrap(),
-                )
-                .unwrap()
-            } else {
-                Url::parse(url).unwrap()
+            Err(SendError::Reqwest(err)) => {
+                self.punish_if_needed(host_info.host(), host_info.timeout_power(), &err)
+                    .await;
+                Err(protocol_error_of(&err)
+                    .unwrap_or_else(|| io_error_from(IoErrorKind::ConnectionAborted)(err)))
             }
+            Err(SendError::Replayed(err)) => Err(err),
         }
     }
 
     async fn punish_if_needed(&self, host: &str, timeout_power: usize, err: &ReqwestError) {
-        if err.is_timeout() {
-            self.inner()
-                .await
-                .io_selector
-                .increase_timeout_power_by(host, timeout_power)
-                .await
-        } else if err.is_connect() {
-            self.inner()
-                .await
+        let inner = self.inner().await;
+        let selectors = once(&inner.io_selector).chain(inner.cdn_selector.as_ref());
+        for selector in selectors {
+            if err.is_timeout() {
+                selector
+                    .increase_timeout_power_by(host, timeout_power)
+                    .await
+            } else if selector.is_connection_failure(err) {
+                selector.mark_connection_as_failed(host).await
+            }
+        }
+        if err.is_connect() {
+            // 连续的连接失败覆盖了所有 IO 节点时，连接池中可能全是指向旧路由的连接
+            let connect_failures = inner.connect_failures.fetch_add(1, Relaxed) + 1;
+            let threshold = inner
                 .io_selector
-                .mark_connection_as_failed(host)
+                .hosts()
                 .await
+                .len()
+                .max(MIN_CONNECT_FAILURES_TO_PURGE);
+            if connect_failures >= threshold {
+                inner.connect_failures.store(0, Relaxed);
+                if inner.http_client_timeouts.purge_async_http_client() {
+                    warn!(
+                        "Purge connections after {} consecutive connect failures",
+                        connect_failures
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub(super) fn make_download_url(
+    io_url: &str,
+    access_key: &str,
+    bucket: &str,
+    key: &str,
+    use_getfile_api: bool,
+    normalize_key: bool,
+) -> String {
+    let mut url = if use_getfile_api {
+        format!("{}/getfile/{}/{}", io_url, access_key, bucket)
+    } else {
+        io_url.to_owned()
+    };
+    if normalize_key {
+        if url.ends_with('/') && key.starts_with('/') {
+            url.truncate(url.len() - 1);
+        } else if !url.ends_with('/') && !key.starts_with('/') {
+            url.push('/');
         }
     }
+    url.push_str(key);
+    url
+}
+
+fn sign_download_url_if_needed(
+    url: &str,
+    private_url_lifetime: Option<Duration>,
+    credential: &Credential,
+) -> Url {
+    if let Some(private_url_lifetime) = private_url_lifetime {
+        Url::parse(
+            &sign_download_url_with_lifetime(
+                credential,
+                Url::parse(url).unwrap(),
+                private_url_lifetime,
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    } else {
+        Url::parse(url).unwrap()
+    }
 }
 
 impl Debug for AsyncRangeReader {
@@ -1039,12 +2310,61 @@ impl Debug for AsyncRangeReader {
 }
 
 /// 通过 RangeReader::read_multi_ranges() 获取文件的区域以及对应的数据
+///
+/// 返回的区域顺序与请求的区域顺序一致
 #[derive(Debug, Clone)]
 pub struct RangePart {
     /// 区域对应的数据
     pub data: Vec<u8>,
     /// 区域的开始偏移量和区域长度
     pub range: (u64, u64),
+    /// 该区域对应的请求区域，由开始偏移量和区域长度组成
+    ///
+    /// 区域超出文件末尾时，实际读取的区域可能短于请求的区域
+    pub requested: (u64, u64),
+}
+
+/// 按照请求的区域顺序排列读取到的区域，并记录每个区域对应的请求区域
+///
+/// 优先匹配开始偏移量相同的请求区域，其次匹配包含该区域开始偏移量的请求区域，无法匹配的区域排在最后
+pub(crate) fn sort_range_parts(ranges: &[(u64, u64)], parts: Vec<RangePart>) -> Vec<RangePart> {
+    let mut matched = vec![false; ranges.len()];
+    let mut indexes = vec![None; parts.len()];
+    for (index, part) in indexes.iter_mut().zip(parts.iter()) {
+        *index = match_range(ranges, &mut matched, |&(from, _)| from == part.range.0);
+    }
+    for (index, part) in indexes.iter_mut().zip(parts.iter()) {
+        if index.is_none() {
+            *index = match_range(ranges, &mut matched, |&(from, len)| {
+                from <= part.range.0 && part.range.0 < from.saturating_add(len)
+            });
+        }
+    }
+    let mut parts = indexes
+        .into_iter()
+        .zip(parts)
+        .map(|(index, mut part)| {
+            if let Some(index) = index {
+                part.requested = ranges[index];
+            }
+            (index.unwrap_or(usize::MAX), part)
+        })
+        .collect::<Vec<_>>();
+    parts.sort_by_key(|(index, _)| *index);
+    return parts.into_iter().map(|(_, part)| part).collect();
+
+    fn match_range(
+        ranges: &[(u64, u64)],
+        matched: &mut [bool],
+        predicate: impl Fn(&(u64, u64)) -> bool,
+    ) -> Option<usize> {
+        let index = ranges
+            .iter()
+            .enumerate()
+            .position(|(index, range)| !matched[index] && predicate(range))?;
+        matched[index] = true;
+        Some(index)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -1098,6 +2418,11 @@ impl Drop for TryingHostInfo {
 pub(super) struct TriesInfo<'a> {
     have_tried: &'a AtomicUsize,
     total_tries: usize,
+    session: Option<&'a ObjectSession>,
+    audit_slot: Option<&'a AuditSlot>,
+    stats: Option<&'a StatsRecorder>,
+    preferred_host: Option<&'a str>,
+    bucket: Option<&'a str>,
 }
 
 impl<'a> TriesInfo<'a> {
@@ -1105,22 +2430,137 @@ impl<'a> TriesInfo<'a> {
         Self {
             have_tried,
             total_tries,
+            session: None,
+            audit_slot: None,
+            stats: None,
+            preferred_host: None,
+            bucket: None,
+        }
+    }
+
+    pub(super) fn with_session(mut self, session: Option<&'a ObjectSession>) -> Self {
+        self.session = session;
+        self
+    }
+
+    pub(super) fn with_audit_slot(mut self, audit_slot: Option<&'a AuditSlot>) -> Self {
+        self.audit_slot = audit_slot;
+        self
+    }
+
+    pub(super) fn with_stats(mut self, stats: Option<&'a StatsRecorder>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// 访问指定的存储空间，为 None 时访问下载器的存储空间
+    pub(super) fn with_bucket(mut self, bucket: Option<&'a str>) -> Self {
+        self.bucket = bucket;
+        self
+    }
+
+    /// 首次尝试时优先使用指定域名，该域名不可用时仍按常规方式选择域名
+    pub(super) fn with_preferred_host(mut self, preferred_host: Option<&'a str>) -> Self {
+        self.preferred_host = preferred_host;
+        self
+    }
+
+    /// 使用新的计数器，使拆分后的每个请求独立计算尝试次数
+    fn with_have_tried<'b>(&self, have_tried: &'b AtomicUsize) -> TriesInfo<'b>
+    where
+        'a: 'b,
+    {
+        TriesInfo {
+            have_tried,
+            total_tries: self.total_tries,
+            session: self.session,
+            audit_slot: self.audit_slot,
+            stats: self.stats,
+            preferred_host: self.preferred_host,
+            bucket: self.bucket,
+        }
+    }
+
+    fn record_request(&self, download_url: &Url, req_id: &HeaderValue) {
+        if let Some(audit_slot) = self.audit_slot {
+            audit_slot.record(&download_url.origin().ascii_serialization(), req_id);
+        }
+    }
+
+    fn record_host_selection(&self, elapsed: Duration) {
+        if let Some(audit_slot) = self.audit_slot {
+            audit_slot.record_host_selection(elapsed);
+        }
+    }
+
+    fn record_response(&self) {
+        if let Some(audit_slot) = self.audit_slot {
+            audit_slot.record_response();
+        }
+    }
+
+    fn record_parse(&self, elapsed: Duration) {
+        if let Some(audit_slot) = self.audit_slot {
+            audit_slot.record_parse(elapsed);
+        }
+    }
+
+    fn record_cache_write(&self, elapsed: Duration) {
+        if let Some(audit_slot) = self.audit_slot {
+            audit_slot.record_cache_write(elapsed);
+        }
+    }
+
+    fn record_retry(&self) {
+        if let Some(stats) = self.stats {
+            stats.record_retry();
+        }
+    }
+
+    fn record_failover(&self) {
+        if let Some(stats) = self.stats {
+            stats.record_failover();
+        }
+    }
+
+    fn record_cache_hit(&self) {
+        if let Some(stats) = self.stats {
+            stats.record_cache_hit();
+        }
+    }
+
+    fn check_response(&self, resp: HttpResponse) -> IoResult<HttpResponse> {
+        match self.session {
+            Some(session) => session.check_response(resp),
+            None => Ok(resp),
         }
     }
 }
 
-fn unexpected_status_code(resp: &HttpResponse) -> IoError {
-    let error_kind = if resp.status().is_client_error() {
-        IoErrorKind::InvalidData
+/// 仅在启用时解析响应的缓存指令，否则返回不限制本地缓存的默认指令
+fn cache_directives_of(resp: &HttpResponse, respect_cache_control: bool) -> CacheDirectives {
+    if respect_cache_control {
+        CacheDirectives::from_headers(resp.headers())
     } else {
-        IoErrorKind::Other
-    };
-    IoError::new(
-        error_kind,
-        format!("Unexpected status code {}", resp.status().as_u16()),
+        Default::default()
+    }
+}
+
+fn unexpected_status_code(resp: &HttpResponse) -> IoError {
+    unexpected_status_code_error(
+        resp.status().as_u16(),
+        Some(response_url(resp).origin().ascii_serialization()),
     )
 }
 
+/// 以压缩编码存储的对象不支持范围读取和获取尺寸，直接返回 [`crate::ContentEncodedError`] 错误
+fn reject_content_encoded(resp: HttpResponse) -> IoResult<HttpResponse> {
+    match content_encoding_of(resp.headers()) {
+        Some(content_encoding) => Err(content_encoded_error(&content_encoding)),
+        None => Ok(resp),
+    }
+}
+
 fn parse_content_length(resp: &HttpResponse) -> u64 {
     resp.content_length()
         .and_then(|s| if s > 0 { Some(s) } else { None })
@@ -1170,6 +2610,19 @@ async fn read_response_body(resp: HttpResponse, limit: Option<u64>) -> IoResult<
     Ok(buf_cursor.into_inner())
 }
 
+async fn read_response_body_strictly(
+    resp: HttpResponse,
+    content_length: u64,
+    size: u64,
+) -> IoResult<Vec<u8>> {
+    if content_length > size {
+        check_body_size(content_length, size)?;
+    }
+    let body = read_response_body(resp, Some(content_length.saturating_add(1))).await?;
+    check_body_size(body.len() as u64, content_length)?;
+    Ok(body)
+}
+
 fn io_error_from<E: Into<Box<dyn StdError + Send + Sync>>>(
     kind: IoErrorKind,
 ) -> impl Fn(E) -> IoError {
@@ -1189,10 +2642,17 @@ mod tests {
         super::{
             cache_dir::cache_dir_path_of,
             dot::{AsyncDotRecordsMap, DotRecordKey, DotRecords, DOT_FILE_NAME},
+            health::health_snapshot,
             query::CACHE_FILE_NAME,
         },
         *,
     };
+    use crate::{
+        base::download::{
+            ContentEncodedError, ObjectChangedError, ProtocolError, ProtocolErrorKind,
+        },
+        config::RedirectPolicy,
+    };
     use futures::channel::oneshot::channel;
     use multipart::client::lazy::Multipart as LazyMultipart;
     use serde_json::{json, to_vec as json_to_vec};
@@ -1203,10 +2663,18 @@ mod tests {
             Arc,
         },
     };
-    use tokio::{fs::remove_file, task::spawn, time::sleep};
+    use tokio::{
+        fs::remove_file,
+        io::{AsyncReadExt as _, AsyncWriteExt as _},
+        task::spawn,
+        time::sleep,
+    };
     use warp::{
         header,
-        http::{header::AUTHORIZATION, HeaderValue, StatusCode},
+        http::{
+            header::{AUTHORIZATION, CACHE_CONTROL, RANGE},
+            HeaderValue, StatusCode,
+        },
         hyper::Body,
         path,
         reply::Response,
@@ -1413,48 +2881,635 @@ mod tests {
     }
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-    async fn test_read_at_2() -> anyhow::Result<()> {
+    async fn test_read_at_with_redirect() -> anyhow::Result<()> {
         env_logger::try_init().ok();
         clear_cache().await?;
 
-        let io_called = Arc::new(AtomicUsize::new(0));
+        let routes = {
+            let action_1 = path!("file").map(|| {
+                let mut resp = Response::new(Body::empty());
+                *resp.status_mut() = StatusCode::FOUND;
+                resp.headers_mut()
+                    .insert("Location", HeaderValue::from_static("/edge/file"));
+                resp
+            });
+            let action_2 = path!("edge" / "file")
+                .and(header::value(RANGE.as_str()))
+                .map(|range: HeaderValue| {
+                    assert_eq!(range.to_str().unwrap(), "bytes=5-10");
+                    Response::new("123456".into())
+                });
+            action_1.or(action_2)
+        };
+        starts_with_server!(addr, routes, {
+            for (redirect_policy, followed) in [
+                (RedirectPolicy::default(), true),
+                (RedirectPolicy::same_host(1), true),
+                (RedirectPolicy::None, false),
+            ] {
+                let downloader = AsyncRangeReaderBuilder::from(
+                    BaseRangeReaderBuilder::new(
+                        "bucket".to_owned(),
+                        "file".to_owned(),
+                        get_credential(),
+                        vec![format!("http://{}", addr)],
+                    )
+                    .use_getfile_api(false)
+                    .normalize_key(true)
+                    .redirect_policy(redirect_policy),
+                )
+                .build();
+
+                let have_tried = AtomicUsize::new(0);
+                match downloader
+                    .read_at(
+                        5,
+                        6,
+                        "file",
+                        0,
+                        TriesInfo::new(&have_tried, 1),
+                        &Default::default(),
+                        |_| async {},
+                    )
+                    .await
+                {
+                    Result3::Ok(buf) => {
+                        assert!(followed);
+                        assert_eq!(&buf, b"123456");
+                    }
+                    _ => assert!(!followed),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_read_at_with_range_request_encoding() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let routes = {
+            let action_1 = warp::post().and(path!("file")).and(warp::body::json()).map(
+                |body: serde_json::Value| {
+                    assert_eq!(body, json!({"ranges": ["5-10"]}));
+                    Response::new("123456".into())
+                },
+            );
+            let action_2 = warp::get()
+                .and(path!("file"))
+                .and(warp::query::<HashMap<String, String>>())
+                .map(|query: HashMap<String, String>| {
+                    assert_eq!(query.get("range").unwrap(), "bytes=5-10");
+                    Response::new("123456".into())
+                });
+            action_1.or(action_2)
+        };
+        starts_with_server!(addr, routes, {
+            for encoding in [
+                RangeRequestEncoding::JsonBody,
+                RangeRequestEncoding::QueryParam,
+            ] {
+                let downloader = AsyncRangeReaderBuilder::from(
+                    BaseRangeReaderBuilder::new(
+                        "bucket".to_owned(),
+                        "file".to_owned(),
+                        get_credential(),
+                        vec![format!("http://{}", addr)],
+                    )
+                    .use_getfile_api(false)
+                    .normalize_key(true)
+                    .range_request_encoding(encoding),
+                )
+                .build();
+
+                let have_tried = AtomicUsize::new(0);
+                match downloader
+                    .read_at(
+                        5,
+                        6,
+                        "file",
+                        0,
+                        TriesInfo::new(&have_tried, 1),
+                        &Default::default(),
+                        |_| async {},
+                    )
+                    .await
+                {
+                    Result3::Ok(buf) => assert_eq!(&buf, b"123456"),
+                    _ => unreachable!(),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_read_at_through_cdn() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let cdn_counter = Arc::new(AtomicUsize::new(0));
+        let io_counter = Arc::new(AtomicUsize::new(0));
+        let cdn_routes = {
+            let cdn_counter = cdn_counter.to_owned();
+            path!("file").map(move || {
+                cdn_counter.fetch_add(1, Relaxed);
+                let mut resp = Response::new(Body::empty());
+                *resp.status_mut() = StatusCode::BAD_GATEWAY;
+                resp
+            })
+        };
         let io_routes = {
-            let io_called = io_called.to_owned();
+            let io_counter = io_counter.to_owned();
             path!("file")
                 .and(header::value(RANGE.as_str()))
                 .map(move |range: HeaderValue| {
-                    assert_eq!(range.to_str().unwrap(), "bytes=1-5");
-                    io_called.fetch_add(1, Relaxed);
-                    let mut resp = Response::new("12345".into());
-                    *resp.status_mut() = StatusCode::NOT_IMPLEMENTED;
+                    io_counter.fetch_add(1, Relaxed);
+                    assert_eq!(range.to_str().unwrap(), "bytes=5-10");
+                    Response::new("123456".into())
+                })
+        };
+        starts_with_server!(cdn_addr, cdn_routes, {
+            starts_with_server!(io_addr, io_routes, {
+                let read_at = |cdn_urls: Vec<String>, io_urls: Vec<String>| async move {
+                    let downloader = AsyncRangeReaderBuilder::from(
+                        BaseRangeReaderBuilder::new(
+                            "bucket".to_owned(),
+                            "file".to_owned(),
+                            get_credential(),
+                            io_urls,
+                        )
+                        .cdn_urls(cdn_urls)
+                        .use_getfile_api(false)
+                        .normalize_key(true),
+                    )
+                    .build();
+                    let have_tried = AtomicUsize::new(0);
+                    downloader
+                        .read_at(
+                            5,
+                            6,
+                            "file",
+                            0,
+                            TriesInfo::new(&have_tried, 1),
+                            &Default::default(),
+                            |_| async {},
+                        )
+                        .await
+                };
+
+                // CDN 返回错误时回源到 IO 服务器
+                match read_at(
+                    vec![format!("http://{}", cdn_addr)],
+                    vec![format!("http://{}", io_addr)],
+                )
+                .await
+                {
+                    Result3::Ok(buf) => assert_eq!(&buf, b"123456"),
+                    _ => unreachable!(),
+                }
+                assert_eq!(cdn_counter.load(Relaxed), 1);
+                assert_eq!(io_counter.load(Relaxed), 1);
+
+                // CDN 正常时不访问 IO 服务器
+                match read_at(
+                    vec![format!("http://{}", io_addr)],
+                    vec!["http://127.0.0.1:1".to_owned()],
+                )
+                .await
+                {
+                    Result3::Ok(buf) => assert_eq!(&buf, b"123456"),
+                    _ => unreachable!(),
+                }
+                assert_eq!(io_counter.load(Relaxed), 2);
+            });
+        });
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_read_at_with_second_host_verification() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let make_routes = |body: &'static str, counter: Arc<AtomicUsize>| {
+            path!("file")
+                .and(header::value(RANGE.as_str()))
+                .map(move |range: HeaderValue| {
+                    counter.fetch_add(1, Relaxed);
+                    assert_eq!(range.to_str().unwrap(), "bytes=5-10");
+                    Response::new(body.into())
+                })
+        };
+        let io_counter_1 = Arc::new(AtomicUsize::new(0));
+        let io_counter_2 = Arc::new(AtomicUsize::new(0));
+        let io_routes_1 = make_routes("123456", io_counter_1.to_owned());
+        let io_routes_2 = make_routes("123457", io_counter_2.to_owned());
+        starts_with_server!(io_addr_1, io_routes_1, {
+            starts_with_server!(io_addr_2, io_routes_2, {
+                let read_at = |probability: f64| {
+                    let io_urls = vec![
+                        format!("http://{}", io_addr_1),
+                        format!("http://{}", io_addr_2),
+                    ];
+                    async move {
+                        let downloader = AsyncRangeReaderBuilder::from(
+                            BaseRangeReaderBuilder::new(
+                                "bucket".to_owned(),
+                                "file".to_owned(),
+                                get_credential(),
+                                io_urls,
+                            )
+                            .use_getfile_api(false)
+                            .normalize_key(true)
+                            .verify_with_second_host(probability),
+                        )
+                        .build();
+                        let have_tried = AtomicUsize::new(0);
+                        match downloader
+                            .read_at(
+                                5,
+                                6,
+                                "file",
+                                0,
+                                TriesInfo::new(&have_tried, 1),
+                                &Default::default(),
+                                |_| async {},
+                            )
+                            .await
+                        {
+                            Result3::Ok(buf) => buf,
+                            _ => unreachable!(),
+                        }
+                    }
+                };
+
+                read_at(0.0).await;
+                assert_eq!(io_counter_1.load(Relaxed) + io_counter_2.load(Relaxed), 1);
+
+                let divergent_reads = health_snapshot().await.divergent_reads();
+                let buf = read_at(1.0).await;
+                assert!(buf == b"123456" || buf == b"123457");
+                assert_eq!(io_counter_1.load(Relaxed) + io_counter_2.load(Relaxed), 3);
+                assert!(io_counter_1.load(Relaxed) >= 1);
+                assert!(io_counter_2.load(Relaxed) >= 1);
+                assert!(health_snapshot().await.divergent_reads() > divergent_reads);
+            });
+        });
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_read_at_with_session() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let routes = {
+            let counter = counter.to_owned();
+            path!("file")
+                .and(header::optional::<String>(IF_MATCH.as_str()))
+                .map(move |if_match: Option<String>| {
+                    let mut resp = match counter.fetch_add(1, Relaxed) {
+                        0 => {
+                            assert_eq!(if_match, None);
+                            Response::new("123456".into())
+                        }
+                        _ => {
+                            assert_eq!(if_match.as_deref(), Some("\"v1\""));
+                            let mut resp = Response::new(Body::empty());
+                            *resp.status_mut() = StatusCode::PRECONDITION_FAILED;
+                            resp
+                        }
+                    };
+                    resp.headers_mut()
+                        .insert("ETag", HeaderValue::from_static("\"v1\""));
                     resp
                 })
         };
-        starts_with_server!(io_addr, monitor_addr, io_routes, records_map, {
-            let have_tried = AtomicUsize::new(0);
-            let io_urls = vec![format!("http://{}", io_addr)];
+        starts_with_server!(addr, routes, {
             let downloader = AsyncRangeReaderBuilder::from(
                 BaseRangeReaderBuilder::new(
                     "bucket".to_owned(),
                     "file".to_owned(),
                     get_credential(),
-                    io_urls,
+                    vec![format!("http://{}", addr)],
                 )
                 .use_getfile_api(false)
-                .normalize_key(true)
-                .monitor_urls(vec!["http://".to_owned() + &monitor_addr.to_string()])
-                .dot_interval(Duration::from_millis(0))
-                .max_dot_buffer_size(1),
+                .normalize_key(true),
             )
             .build();
+            let session = ObjectSession::default();
 
+            let have_tried = AtomicUsize::new(0);
             match downloader
                 .read_at(
-                    1,
                     5,
+                    6,
                     "file",
                     0,
-                    TriesInfo::new(&have_tried, 3),
+                    TriesInfo::new(&have_tried, 3).with_session(Some(&session)),
+                    &Default::default(),
+                    |_| async {},
+                )
+                .await
+            {
+                Result3::Ok(buf) => assert_eq!(&buf, b"123456"),
+                _ => unreachable!(),
+            }
+            assert_eq!(session.etag(), Some(HeaderValue::from_static("\"v1\"")));
+
+            let have_tried = AtomicUsize::new(0);
+            match downloader
+                .read_at(
+                    5,
+                    6,
+                    "file",
+                    0,
+                    TriesInfo::new(&have_tried, 3).with_session(Some(&session)),
+                    &Default::default(),
+                    |_| async {},
+                )
+                .await
+            {
+                Result3::Err(err) => {
+                    let err = err
+                        .get_ref()
+                        .and_then(|err| err.downcast_ref::<ObjectChangedError>())
+                        .unwrap();
+                    assert_eq!(err.expected_etag(), "\"v1\"");
+                    assert_eq!(err.actual_etag(), None);
+                }
+                _ => unreachable!(),
+            }
+            assert_eq!(counter.load(Relaxed), 2);
+        });
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_read_at_with_strict_body_size() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let io_routes =
+            path!("file")
+                .and(header::value(RANGE.as_str()))
+                .map(|range: HeaderValue| {
+                    assert_eq!(range.to_str().unwrap(), "bytes=5-10");
+                    Response::new("1234567890".into())
+                });
+
+        starts_with_server!(io_addr, io_routes, {
+            let io_urls = vec![format!("http://{}", io_addr)];
+            for &strict_body_size in &[false, true] {
+                let have_tried = AtomicUsize::new(0);
+                let downloader = AsyncRangeReaderBuilder::from(
+                    BaseRangeReaderBuilder::new(
+                        "bucket".to_owned(),
+                        "file".to_owned(),
+                        get_credential(),
+                        io_urls.to_owned(),
+                    )
+                    .use_getfile_api(false)
+                    .normalize_key(true)
+                    .strict_body_size(strict_body_size),
+                )
+                .build();
+
+                match downloader
+                    .read_at(
+                        5,
+                        6,
+                        "file",
+                        0,
+                        TriesInfo::new(&have_tried, 1),
+                        &Default::default(),
+                        |_| async {},
+                    )
+                    .await
+                {
+                    Result3::Ok(buf) => {
+                        assert!(!strict_body_size);
+                        assert_eq!(&buf, b"123456");
+                    }
+                    Result3::NoMoreTries(Some(err)) => {
+                        assert!(strict_body_size);
+                        assert_eq!(err.kind(), IoErrorKind::Other);
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_content_encoded_object() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        const GZIPPED: &[u8] = &[
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9,
+            0xc9, 0x57, 0xc8, 0x40, 0x27, 0xb9, 0x00, 0x00, 0x88, 0x59, 0x0b, 0x18, 0x00, 0x00,
+            0x00,
+        ];
+        let io_routes = path!("file").map(|| {
+            let mut response = Response::new(GZIPPED.into());
+            response
+                .headers_mut()
+                .insert("content-encoding", HeaderValue::from_static("gzip"));
+            response
+        });
+
+        starts_with_server!(io_addr, io_routes, {
+            let io_urls = vec![format!("http://{}", io_addr)];
+            for &decompress in &[false, true] {
+                let downloader = AsyncRangeReaderBuilder::from(
+                    BaseRangeReaderBuilder::new(
+                        "bucket".to_owned(),
+                        "file".to_owned(),
+                        get_credential(),
+                        io_urls.to_owned(),
+                    )
+                    .use_getfile_api(false)
+                    .normalize_key(true)
+                    .decompress_content_encoding(decompress),
+                )
+                .build();
+
+                let have_tried = AtomicUsize::new(0);
+                match downloader
+                    .read_at(
+                        0,
+                        5,
+                        "file",
+                        0,
+                        TriesInfo::new(&have_tried, 3),
+                        &Default::default(),
+                        |_| async {},
+                    )
+                    .await
+                {
+                    Result3::Err(err) => {
+                        assert_eq!(
+                            err.get_ref()
+                                .and_then(|err| err.downcast_ref::<ContentEncodedError>())
+                                .map(|err| err.content_encoding()),
+                            Some("gzip")
+                        );
+                        assert_eq!(ErrorCode::of(&err), ErrorCode::ContentEncoded);
+                    }
+                    _ => unreachable!(),
+                }
+                assert_eq!(have_tried.load(Relaxed), 1);
+
+                let have_tried = AtomicUsize::new(0);
+                match downloader
+                    .download(
+                        "file",
+                        0,
+                        TriesInfo::new(&have_tried, 3),
+                        &Default::default(),
+                        |_| async {},
+                    )
+                    .await
+                {
+                    Result3::Ok(data) if decompress => {
+                        assert_eq!(&data, b"hello hello hello hello\n");
+                    }
+                    Result3::Ok(data) => assert_eq!(&data, GZIPPED),
+                    _ => unreachable!(),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// 对每个连接都返回固定的原始响应，用于模拟不符合协议的网关
+    async fn start_raw_server(response: Vec<u8>) -> IoResult<std::net::SocketAddr> {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let response = Arc::new(response);
+        spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let response = response.to_owned();
+                spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    if matches!(stream.read(&mut buf).await, Ok(size) if size > 0) {
+                        stream.write_all(&response).await.ok();
+                    }
+                    stream.shutdown().await.ok();
+                });
+            }
+        });
+        Ok(addr)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_read_at_with_protocol_error() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let too_large = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nX-Padding: {}\r\n\r\n12345",
+            "a".repeat(2048)
+        )
+        .into_bytes();
+        let invalid =
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nX-Bad: caf\xe9\r\n\r\n12345".to_vec();
+        let malformed = b"HTTP/1.1 abc\r\n\r\n".to_vec();
+
+        for (response, expected_kind) in [
+            (too_large, ProtocolErrorKind::HeaderTooLarge),
+            (invalid, ProtocolErrorKind::InvalidHeader),
+            (malformed, ProtocolErrorKind::MalformedResponse),
+        ] {
+            let addr = start_raw_server(response).await?;
+            let downloader = AsyncRangeReaderBuilder::from(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    get_credential(),
+                    vec![format!("http://{}", addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true)
+                .max_response_header_size(1024)
+                .strict_header_validation(true),
+            )
+            .build();
+
+            let have_tried = AtomicUsize::new(0);
+            match downloader
+                .read_at(
+                    0,
+                    5,
+                    "file",
+                    0,
+                    TriesInfo::new(&have_tried, 1),
+                    &Default::default(),
+                    |_| async {},
+                )
+                .await
+            {
+                Result3::NoMoreTries(Some(err)) => {
+                    let err = err
+                        .get_ref()
+                        .and_then(|err| err.downcast_ref::<ProtocolError>())
+                        .unwrap();
+                    assert_eq!(err.kind(), expected_kind);
+                }
+                _ => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_read_at_2() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let io_called = Arc::new(AtomicUsize::new(0));
+        let io_routes = {
+            let io_called = io_called.to_owned();
+            path!("file")
+                .and(header::value(RANGE.as_str()))
+                .map(move |range: HeaderValue| {
+                    assert_eq!(range.to_str().unwrap(), "bytes=1-5");
+                    io_called.fetch_add(1, Relaxed);
+                    let mut resp = Response::new("12345".into());
+                    *resp.status_mut() = StatusCode::NOT_IMPLEMENTED;
+                    resp
+                })
+        };
+        starts_with_server!(io_addr, monitor_addr, io_routes, records_map, {
+            let have_tried = AtomicUsize::new(0);
+            let io_urls = vec![format!("http://{}", io_addr)];
+            let downloader = AsyncRangeReaderBuilder::from(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    get_credential(),
+                    io_urls,
+                )
+                .use_getfile_api(false)
+                .normalize_key(true)
+                .monitor_urls(vec!["http://".to_owned() + &monitor_addr.to_string()])
+                .dot_interval(Duration::from_millis(0))
+                .max_dot_buffer_size(1),
+            )
+            .build();
+
+            match downloader
+                .read_at(
+                    1,
+                    5,
+                    "file",
+                    0,
+                    TriesInfo::new(&have_tried, 3),
                     &Default::default(),
                     |_| async {},
                 )
@@ -1620,6 +3675,71 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_checksum_range() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let io_routes =
+            path!("file")
+                .and(header::value(RANGE.as_str()))
+                .map(|range: HeaderValue| {
+                    assert_eq!(range.to_str().unwrap(), "bytes=6-10");
+                    let mut resp = Response::new("world".into());
+                    *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+                    resp.headers_mut()
+                        .insert(CONTENT_RANGE, "bytes 6-10/11".parse().unwrap());
+                    resp
+                });
+        starts_with_server!(io_addr, io_routes, {
+            let io_urls = vec![format!("http://{}", io_addr)];
+            let downloader = AsyncRangeReaderBuilder::from(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    get_credential(),
+                    io_urls,
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            )
+            .build();
+
+            for (algorithm, expected) in [
+                (
+                    ChecksumAlgorithm::Crc32,
+                    0x3a771143u32.to_be_bytes().to_vec(),
+                ),
+                (
+                    ChecksumAlgorithm::Md5,
+                    vec![
+                        0x7d, 0x79, 0x30, 0x37, 0xa0, 0x76, 0x01, 0x86, 0x57, 0x4b, 0x02, 0x82,
+                        0xf2, 0xf4, 0x35, 0xe7,
+                    ],
+                ),
+            ] {
+                let have_tried = AtomicUsize::new(0);
+                match downloader
+                    .checksum_range(
+                        6,
+                        5,
+                        algorithm,
+                        "file",
+                        0,
+                        TriesInfo::new(&have_tried, 1),
+                        &Default::default(),
+                        |_| async {},
+                    )
+                    .await
+                {
+                    Result3::Ok(checksum) => assert_eq!(checksum, expected),
+                    _ => unreachable!(),
+                }
+            }
+        });
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn test_download_file() -> anyhow::Result<()> {
         env_logger::try_init().ok();
@@ -1814,7 +3934,303 @@ mod tests {
     }
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-    async fn test_download_file_3() -> anyhow::Result<()> {
+    async fn test_download_file_3() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let routes = {
+            let counter = counter.to_owned();
+            path!("file").map(move || {
+                counter.fetch_add(1, Relaxed);
+                let mut resp = Response::new("12345".into());
+                *resp.status_mut() = StatusCode::BAD_REQUEST;
+                resp
+            })
+        };
+        starts_with_server!(addr, routes, {
+            let io_urls = vec![format!("http://{}", addr)];
+
+            let downloader = AsyncRangeReaderBuilder::from(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    get_credential(),
+                    io_urls,
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            )
+            .build();
+
+            let have_tried = AtomicUsize::new(0);
+            match downloader
+                .exist(
+                    "file",
+                    0,
+                    TriesInfo::new(&have_tried, 3),
+                    &Default::default(),
+                    |_| async {},
+                )
+                .await
+            {
+                Result3::Err(_) => {}
+                _ => unreachable!(),
+            }
+
+            let have_tried = AtomicUsize::new(0);
+            match downloader
+                .file_size(
+                    "file",
+                    0,
+                    TriesInfo::new(&have_tried, 3),
+                    &Default::default(),
+                    |_| async {},
+                )
+                .await
+            {
+                Result3::Err(_) => {}
+                _ => unreachable!(),
+            }
+
+            let have_tried = AtomicUsize::new(0);
+            match downloader
+                .download(
+                    "file",
+                    0,
+                    TriesInfo::new(&have_tried, 3),
+                    &Default::default(),
+                    |_| async {},
+                )
+                .await
+            {
+                Result3::Err(_) => {}
+                _ => unreachable!(),
+            }
+            assert_eq!(counter.load(Relaxed), 3);
+        });
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_download_file_not_found() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let routes = {
+            let counter = counter.to_owned();
+            path!("file").map(move || {
+                counter.fetch_add(1, Relaxed);
+                let mut resp = Response::new("".into());
+                *resp.status_mut() = StatusCode::from_u16(612).unwrap();
+                resp
+            })
+        };
+        starts_with_server!(addr, routes, {
+            let io_urls = vec![format!("http://{}", addr)];
+
+            let downloader = AsyncRangeReaderBuilder::from(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    get_credential(),
+                    io_urls,
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            )
+            .build();
+
+            let have_tried = AtomicUsize::new(0);
+            match downloader
+                .exist(
+                    "file",
+                    0,
+                    TriesInfo::new(&have_tried, 3),
+                    &Default::default(),
+                    |_| async {},
+                )
+                .await
+            {
+                Result3::Ok(existed) => assert!(!existed),
+                _ => unreachable!(),
+            }
+
+            let have_tried = AtomicUsize::new(0);
+            match downloader
+                .file_size(
+                    "file",
+                    0,
+                    TriesInfo::new(&have_tried, 3),
+                    &Default::default(),
+                    |_| async {},
+                )
+                .await
+            {
+                Result3::Err(err) => {
+                    assert_eq!(err.kind(), IoErrorKind::NotFound);
+                    assert!(downloader.is_object_level_error(&err).await);
+                }
+                _ => unreachable!(),
+            }
+            assert_eq!(counter.load(Relaxed), 2);
+        });
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_negative_cache() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let routes = {
+            let counter = counter.to_owned();
+            path!("file").map(move || {
+                counter.fetch_add(1, Relaxed);
+                let mut resp = Response::new("".into());
+                *resp.status_mut() = StatusCode::NOT_FOUND;
+                resp
+            })
+        };
+        starts_with_server!(addr, routes, {
+            let io_urls = vec![format!("http://{}", addr)];
+
+            let downloader = AsyncRangeReaderBuilder::from(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    get_credential(),
+                    io_urls,
+                )
+                .use_getfile_api(false)
+                .normalize_key(true)
+                .negative_cache_ttl(Duration::from_secs(60)),
+            )
+            .build();
+
+            for _ in 0..2 {
+                let have_tried = AtomicUsize::new(0);
+                match downloader
+                    .exist(
+                        "file",
+                        0,
+                        TriesInfo::new(&have_tried, 3),
+                        &Default::default(),
+                        |_| async {},
+                    )
+                    .await
+                {
+                    Result3::Ok(existed) => assert!(!existed),
+                    _ => unreachable!(),
+                }
+
+                let have_tried = AtomicUsize::new(0);
+                match downloader
+                    .read_at(
+                        0,
+                        5,
+                        "file",
+                        0,
+                        TriesInfo::new(&have_tried, 3),
+                        &Default::default(),
+                        |_| async {},
+                    )
+                    .await
+                {
+                    Result3::Err(err) => assert_eq!(err.kind(), IoErrorKind::NotFound),
+                    _ => unreachable!(),
+                }
+            }
+            assert_eq!(counter.load(Relaxed), 1);
+
+            assert!(downloader.invalidate_negative_cache("file", None).await);
+            assert!(!downloader.invalidate_negative_cache("file", None).await);
+            let have_tried = AtomicUsize::new(0);
+            match downloader
+                .file_size(
+                    "file",
+                    0,
+                    TriesInfo::new(&have_tried, 3),
+                    &Default::default(),
+                    |_| async {},
+                )
+                .await
+            {
+                Result3::Err(err) => assert_eq!(err.kind(), IoErrorKind::NotFound),
+                _ => unreachable!(),
+            }
+            assert_eq!(counter.load(Relaxed), 2);
+        });
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_purge_connections() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        // 先占用端口再释放，保证该端口上没有服务在监听
+        let closed_port = std::net::TcpListener::bind(("127.0.0.1", 0))?
+            .local_addr()?
+            .port();
+        let io_url = format!("http://127.0.0.1:{}", closed_port);
+        // 使用独立的超时配置，避免与其他测试共享客户端
+        let downloader = AsyncRangeReaderBuilder::from(
+            BaseRangeReaderBuilder::new(
+                "bucket".to_owned(),
+                "file".to_owned(),
+                get_credential(),
+                vec![io_url.to_owned()],
+            )
+            .use_getfile_api(false)
+            .normalize_key(true)
+            .base_timeout(Duration::from_millis(2718)),
+        )
+        .build();
+
+        let client = downloader
+            .inner()
+            .await
+            .http_client_timeouts
+            .async_http_client();
+        assert!(downloader.purge_connections().await);
+        assert!(!downloader.purge_connections().await);
+        let purged_client = downloader
+            .inner()
+            .await
+            .http_client_timeouts
+            .async_http_client();
+        assert!(!Arc::ptr_eq(&client, &purged_client));
+
+        let err = purged_client.get(&io_url).send().await.unwrap_err();
+        assert!(err.is_connect());
+        for _ in 0..MIN_CONNECT_FAILURES_TO_PURGE - 1 {
+            downloader.punish_if_needed(&io_url, 0, &err).await;
+        }
+        assert!(Arc::ptr_eq(
+            &purged_client,
+            &downloader
+                .inner()
+                .await
+                .http_client_timeouts
+                .async_http_client()
+        ));
+        downloader.punish_if_needed(&io_url, 0, &err).await;
+        assert!(!Arc::ptr_eq(
+            &purged_client,
+            &downloader
+                .inner()
+                .await
+                .http_client_timeouts
+                .async_http_client()
+        ));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_head_cache() -> anyhow::Result<()> {
         env_logger::try_init().ok();
         clear_cache().await?;
 
@@ -1823,9 +4239,7 @@ mod tests {
             let counter = counter.to_owned();
             path!("file").map(move || {
                 counter.fetch_add(1, Relaxed);
-                let mut resp = Response::new("12345".into());
-                *resp.status_mut() = StatusCode::BAD_REQUEST;
-                resp
+                Response::new("1234567890".into())
             })
         };
         starts_with_server!(addr, routes, {
@@ -1839,7 +4253,8 @@ mod tests {
                     io_urls,
                 )
                 .use_getfile_api(false)
-                .normalize_key(true),
+                .normalize_key(true)
+                .head_cache_window(Duration::from_secs(60)),
             )
             .build();
 
@@ -1854,13 +4269,30 @@ mod tests {
                 )
                 .await
             {
-                Result3::Err(_) => {}
+                Result3::Ok(existed) => assert!(existed),
                 _ => unreachable!(),
             }
 
+            for _ in 0..2 {
+                let have_tried = AtomicUsize::new(0);
+                match downloader
+                    .file_size(
+                        "file",
+                        0,
+                        TriesInfo::new(&have_tried, 3),
+                        &Default::default(),
+                        |_| async {},
+                    )
+                    .await
+                {
+                    Result3::Ok(file_size) => assert_eq!(file_size, 10),
+                    _ => unreachable!(),
+                }
+            }
+
             let have_tried = AtomicUsize::new(0);
             match downloader
-                .file_size(
+                .stat(
                     "file",
                     0,
                     TriesInfo::new(&have_tried, 3),
@@ -1869,25 +4301,66 @@ mod tests {
                 )
                 .await
             {
-                Result3::Err(_) => {}
+                Result3::Ok(metadata) => assert_eq!(metadata.size(), 10),
                 _ => unreachable!(),
             }
+            assert_eq!(counter.load(Relaxed), 1);
+        });
+        Ok(())
+    }
 
-            let have_tried = AtomicUsize::new(0);
-            match downloader
-                .download(
-                    "file",
-                    0,
-                    TriesInfo::new(&have_tried, 3),
-                    &Default::default(),
-                    |_| async {},
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_head_cache_with_cache_control() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let routes = {
+            let counter = counter.to_owned();
+            path!("file").map(move || {
+                counter.fetch_add(1, Relaxed);
+                let mut resp = Response::new("1234567890".into());
+                resp.headers_mut()
+                    .insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+                resp
+            })
+        };
+        starts_with_server!(addr, routes, {
+            for (respect_cache_control, expected_requests) in [(false, 1), (true, 3)] {
+                counter.store(0, Relaxed);
+                let io_urls = vec![format!("http://{}", addr)];
+                let downloader = AsyncRangeReaderBuilder::from(
+                    BaseRangeReaderBuilder::new(
+                        "bucket".to_owned(),
+                        "file".to_owned(),
+                        get_credential(),
+                        io_urls,
+                    )
+                    .use_getfile_api(false)
+                    .normalize_key(true)
+                    .head_cache_window(Duration::from_secs(60))
+                    .respect_cache_control(respect_cache_control),
                 )
-                .await
-            {
-                Result3::Err(_) => {}
-                _ => unreachable!(),
+                .build();
+
+                for _ in 0..3 {
+                    let have_tried = AtomicUsize::new(0);
+                    match downloader
+                        .file_size(
+                            "file",
+                            0,
+                            TriesInfo::new(&have_tried, 3),
+                            &Default::default(),
+                            |_| async {},
+                        )
+                        .await
+                    {
+                        Result3::Ok(file_size) => assert_eq!(file_size, 10),
+                        _ => unreachable!(),
+                    }
+                }
+                assert_eq!(counter.load(Relaxed), expected_requests);
             }
-            assert_eq!(counter.load(Relaxed), 3);
         });
         Ok(())
     }
@@ -2036,10 +4509,121 @@ mod tests {
             {
                 Result3::Ok(parts) => {
                     assert_eq!(parts.len(), 2);
-                    assert_eq!(&parts.get(1).unwrap().data, b"12345");
-                    assert_eq!(parts.get(1).unwrap().range, (0, 5));
-                    assert_eq!(&parts.first().unwrap().data, b"67890");
-                    assert_eq!(parts.first().unwrap().range, (5, 5));
+                    assert_eq!(&parts[0].data, b"12345");
+                    assert_eq!(parts[0].range, (0, 5));
+                    assert_eq!(&parts[1].data, b"67890");
+                    assert_eq!(parts[1].range, (5, 5));
+                    assert_eq!(parts[0].requested, (0, 5));
+                    assert_eq!(parts[1].requested, (5, 5));
+                }
+                _ => unreachable!(),
+            }
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_range_parts() {
+        let part = |from, len| RangePart {
+            data: vec![0; len as usize],
+            range: (from, len),
+            requested: (from, len),
+        };
+        let parts = sort_range_parts(
+            &[(10, 5), (0, 5), (20, 10), (10, 5)],
+            vec![
+                part(0, 5),
+                part(22, 3),
+                part(10, 5),
+                part(10, 5),
+                part(40, 1),
+            ],
+        );
+        assert_eq!(
+            parts.iter().map(|part| part.range).collect::<Vec<_>>(),
+            vec![(10, 5), (0, 5), (22, 3), (10, 5), (40, 1)]
+        );
+        assert_eq!(parts[2].requested, (20, 10));
+        assert_eq!(parts[4].requested, (40, 1));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_disabled_apis() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let routes = {
+            path!("file")
+                .and(header::optional::<String>(RANGE.as_str()))
+                .map(move |range: Option<String>| {
+                    const BODY: &[u8] = b"1234567890";
+                    if let Some(range) = range {
+                        assert!(!range.contains(','));
+                        let (from, to) =
+                            range.trim_start_matches("bytes=").split_once('-').unwrap();
+                        let (from, to): (usize, usize) =
+                            (from.parse().unwrap(), to.parse().unwrap());
+                        let mut response = Response::new(BODY[from..=to].to_vec().into());
+                        *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+                        response
+                    } else {
+                        Response::new(BODY.into())
+                    }
+                })
+        };
+
+        starts_with_server!(addr, routes, {
+            let io_urls = vec![format!("http://{}", addr)];
+            let downloader = AsyncRangeReaderBuilder::from(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    get_credential(),
+                    io_urls,
+                )
+                .use_getfile_api(false)
+                .normalize_key(true)
+                .disable_read_multi_ranges(true)
+                .disable_download(true),
+            )
+            .build();
+
+            let ranges = [(0, 5), (5, 5)];
+            let have_tried = AtomicUsize::new(0);
+            match downloader
+                .read_multi_ranges(
+                    &ranges,
+                    "file",
+                    0,
+                    TriesInfo::new(&have_tried, 1),
+                    &Default::default(),
+                    |_| async {},
+                )
+                .await
+            {
+                Result3::Ok(parts) => {
+                    assert_eq!(parts.len(), 2);
+                    assert_eq!(&parts[0].data, b"12345");
+                    assert_eq!(parts[0].range, (0, 5));
+                    assert_eq!(&parts[1].data, b"67890");
+                    assert_eq!(parts[1].range, (5, 5));
+                }
+                _ => unreachable!(),
+            }
+
+            let have_tried = AtomicUsize::new(0);
+            match downloader
+                .download(
+                    "file",
+                    0,
+                    TriesInfo::new(&have_tried, 1),
+                    &Default::default(),
+                    |_| async {},
+                )
+                .await
+            {
+                Result3::Ok(buf) => {
+                    assert_eq!(&buf, b"1234567890");
                 }
                 _ => unreachable!(),
             }
@@ -2264,10 +4848,11 @@ mod tests {
             {
                 Result3::Ok(parts) => {
                     assert_eq!(parts.len(), 2);
-                    assert_eq!(&parts.get(1).unwrap().data, b"12345");
-                    assert_eq!(parts.get(1).unwrap().range, (0, 5));
-                    assert_eq!(&parts.first().unwrap().data, b"6");
-                    assert_eq!(parts.first().unwrap().range, (5, 1));
+                    assert_eq!(&parts[0].data, b"12345");
+                    assert_eq!(parts[0].range, (0, 5));
+                    assert_eq!(&parts[1].data, b"6");
+                    assert_eq!(parts[1].range, (5, 1));
+                    assert_eq!(parts[1].requested, (5, 5));
                 }
                 _ => unreachable!(),
             }
@@ -2409,7 +4994,7 @@ mod tests {
             .build();
 
             assert_eq!(downloader.io_urls().await, io_urls);
-            assert!(downloader.update_urls().await);
+            assert!(downloader.update_urls().await?);
             assert_eq!(
                 downloader.io_urls().await,
                 vec![format!("http://{}", io_addr)]
@@ -2434,6 +5019,156 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_force_update_hosts() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let routes = { path!("file").map(move || Response::new("12345".into())) };
+        starts_with_server!(io_addr, uc_addr, routes, {
+            let io_urls = vec!["http://fakedomain:12345".to_owned()];
+            let uc_urls = vec![format!("http://{}", uc_addr)];
+            let downloader = AsyncRangeReaderBuilder::from(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    get_credential(),
+                    io_urls.to_owned(),
+                )
+                .uc_urls(uc_urls)
+                .force_update_interval(Duration::from_secs(3600))
+                .use_getfile_api(false)
+                .normalize_key(true),
+            )
+            .build();
+
+            assert_eq!(downloader.io_urls().await, io_urls);
+            downloader.force_update_urls().await;
+            assert_eq!(
+                downloader.io_urls().await,
+                vec![format!("http://{}", io_addr)]
+            );
+            let last_forced_update_at = downloader
+                .inner()
+                .await
+                .last_forced_update_at
+                .lock()
+                .await
+                .to_owned();
+            assert!(last_forced_update_at.is_some());
+
+            downloader.force_update_urls().await;
+            assert_eq!(
+                *downloader.inner().await.last_forced_update_at.lock().await,
+                last_forced_update_at
+            );
+        });
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_force_update_hosts_after_all_tries_failed() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+        clear_cache().await?;
+
+        let routes = {
+            path!("file")
+                .map(move || Response::new("12345".into()))
+                .or(path!("missing").map(move || {
+                    let mut resp = Response::default();
+                    *resp.status_mut() = StatusCode::NOT_FOUND;
+                    resp
+                }))
+        };
+        starts_with_server!(io_addr, uc_addr, routes, {
+            let uc_urls = vec![format!("http://{}", uc_addr)];
+            {
+                let have_tried = AtomicUsize::new(0);
+                let downloader = AsyncRangeReaderBuilder::from(
+                    BaseRangeReaderBuilder::new(
+                        "bucket".to_owned(),
+                        "file".to_owned(),
+                        get_credential(),
+                        vec!["http://fakedomain:12345".to_owned()],
+                    )
+                    .uc_urls(uc_urls.to_owned())
+                    .force_update_interval(Duration::from_secs(3600))
+                    .use_getfile_api(false)
+                    .normalize_key(true),
+                )
+                .build();
+
+                match downloader
+                    .read_at(
+                        0,
+                        5,
+                        "file",
+                        0,
+                        TriesInfo::new(&have_tried, 1),
+                        &Default::default(),
+                        |_| async {},
+                    )
+                    .await
+                {
+                    Result3::Ok(buf) => assert_eq!(&buf, b"12345"),
+                    _ => unreachable!(),
+                }
+                assert_eq!(
+                    downloader.io_urls().await,
+                    vec![format!("http://{}", io_addr)]
+                );
+                assert!(downloader
+                    .inner()
+                    .await
+                    .last_forced_update_at
+                    .lock()
+                    .await
+                    .is_some());
+            }
+            {
+                let have_tried = AtomicUsize::new(0);
+                let io_urls = vec![format!("http://{}", io_addr)];
+                let downloader = AsyncRangeReaderBuilder::from(
+                    BaseRangeReaderBuilder::new(
+                        "bucket".to_owned(),
+                        "missing".to_owned(),
+                        get_credential(),
+                        io_urls,
+                    )
+                    .uc_urls(uc_urls)
+                    .force_update_interval(Duration::from_secs(3600))
+                    .use_getfile_api(false)
+                    .normalize_key(true),
+                )
+                .build();
+
+                match downloader
+                    .read_at(
+                        0,
+                        5,
+                        "missing",
+                        0,
+                        TriesInfo::new(&have_tried, 3),
+                        &Default::default(),
+                        |_| async {},
+                    )
+                    .await
+                {
+                    Result3::Err(err) => assert_eq!(err.kind(), IoErrorKind::NotFound),
+                    _ => unreachable!(),
+                }
+                assert!(downloader
+                    .inner()
+                    .await
+                    .last_forced_update_at
+                    .lock()
+                    .await
+                    .is_none());
+            }
+        });
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_sign_download_url_with_deadline() -> anyhow::Result<()> {
         env_logger::try_init().ok();
@@ -0,0 +1,459 @@
+use super::cache_dir::cache_dir_path_of;
+use fd_lock::RwLock as FdRwLock;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_slice as json_from_slice, to_vec as json_to_vec};
+use std::{
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult, SeekFrom},
+    sync::{Mutex, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+use tokio::{
+    fs::OpenOptions,
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+    sync::Mutex as AsyncMutex,
+    time::sleep,
+};
+
+pub(super) const EGRESS_USAGE_FILE_NAME: &str = "egress-usage.json";
+
+const SECONDS_PER_HOUR: u64 = 3600;
+const SECONDS_PER_DAY: u64 = 86400;
+
+/// 用量文件被其他进程加锁时重试的间隔
+const FILE_LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+static EGRESS_BUDGET: Lazy<RwLock<Option<EgressBudget>>> = Lazy::new(Default::default);
+static EGRESS_USAGE: Lazy<Mutex<Option<EgressUsage>>> = Lazy::new(Default::default);
+static EGRESS_USAGE_FILE_LOCK: Lazy<AsyncMutex<()>> = Lazy::new(Default::default);
+
+/// 超出出口流量预算时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EgressBudgetPolicy {
+    /// 直接返回 [`EgressBudgetExceededError`] 错误
+    Reject,
+    /// 等待到统计窗口重置后再发起请求
+    Delay,
+}
+
+impl Default for EgressBudgetPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::Reject
+    }
+}
+
+/// 出口流量预算的统计窗口
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EgressWindow {
+    /// 自然小时
+    Hour,
+    /// 自然日（UTC）
+    Day,
+}
+
+/// 进程级出口流量预算
+///
+/// 通过 [`set_egress_budget`] 设置，对所有异步下载器及基于其实现的同步下载器生效。
+/// 用量按自然小时和自然日统计并保存在缓存目录中，同一主机上的多个进程共享同一份用量
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct EgressBudget {
+    bytes_per_hour: Option<u64>,
+    bytes_per_day: Option<u64>,
+    policy: EgressBudgetPolicy,
+}
+
+impl EgressBudget {
+    /// 创建出口流量预算，默认不限制任何窗口
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// 设置每小时最多下载的字节数
+    #[inline]
+    pub fn bytes_per_hour(mut self, bytes: u64) -> Self {
+        self.bytes_per_hour = Some(bytes);
+        self
+    }
+
+    /// 设置每天最多下载的字节数
+    #[inline]
+    pub fn bytes_per_day(mut self, bytes: u64) -> Self {
+        self.bytes_per_day = Some(bytes);
+        self
+    }
+
+    /// 设置超出预算时的处理方式，默认直接返回错误
+    #[inline]
+    pub fn policy(mut self, policy: EgressBudgetPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+/// 超出出口流量预算
+///
+/// 作为 [`std::io::Error`] 的内部错误返回，可以通过 `get_ref()` 和 `downcast_ref()` 获取
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Egress budget of {limit} bytes per {window:?} is exceeded, retry after {retry_after:?}")]
+pub struct EgressBudgetExceededError {
+    window: EgressWindow,
+    limit: u64,
+    retry_after: Duration,
+}
+
+impl EgressBudgetExceededError {
+    /// 获取超出预算的统计窗口
+    #[inline]
+    pub fn window(&self) -> EgressWindow {
+        self.window
+    }
+
+    /// 获取该窗口的预算字节数
+    #[inline]
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// 获取距离该窗口重置的时长
+    #[inline]
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+}
+
+/// 剩余的出口流量预算，未设置预算的窗口为 None
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EgressBudgetRemaining {
+    /// 当前小时剩余的字节数
+    pub hourly: Option<u64>,
+    /// 当天剩余的字节数
+    pub daily: Option<u64>,
+}
+
+/// 设置进程级出口流量预算，设置为 None 表示不限制
+pub fn set_egress_budget(budget: Option<EgressBudget>) {
+    *EGRESS_BUDGET.write().unwrap() = budget;
+}
+
+/// 获取当前的出口流量预算
+pub fn egress_budget() -> Option<EgressBudget> {
+    *EGRESS_BUDGET.read().unwrap()
+}
+
+/// 获取剩余的出口流量预算
+///
+/// 基于本进程最近一次同步的用量计算，未设置预算时返回 None
+pub fn remaining_egress_budget() -> Option<EgressBudgetRemaining> {
+    let budget = egress_budget()?;
+    let usage = EGRESS_USAGE.lock().unwrap().unwrap_or_default();
+    Some(usage.remaining(&budget, now_secs()))
+}
+
+/// 在发起下载请求前检查出口流量预算，并预留预计下载的字节数
+///
+/// 检查和预留在持有用量文件锁时完成，因此并发的请求（包括其他进程的请求）都能看到彼此预留的用量，
+/// 不会在同时通过检查后一起超出预算。无法预知尺寸的请求预留 0 字节，下载完成后再记录实际用量
+pub(super) async fn reserve_egress_budget(bytes: u64) -> IoResult<EgressReservation> {
+    loop {
+        let budget = if let Some(budget) = egress_budget() {
+            budget
+        } else {
+            return Ok(EgressReservation::default());
+        };
+        let now = now_secs();
+        let reserved = update_usage(|usage| {
+            usage.check(&budget, now)?;
+            Ok(usage.consumed(bytes))
+        })
+        .await;
+        match reserved {
+            Ok(usage) => {
+                return Ok(EgressReservation {
+                    bytes,
+                    hour_start: usage.hour_start,
+                    day_start: usage.day_start,
+                })
+            }
+            Err(err) if budget.policy == EgressBudgetPolicy::Delay => {
+                info!("{}, wait for the budget window to reset", err);
+                sleep(err.retry_after()).await;
+            }
+            Err(err) => return Err(IoError::new(IoErrorKind::Other, err)),
+        }
+    }
+}
+
+/// 预留的出口流量预算
+///
+/// 请求结束后应当调用 [`EgressReservation::settle`] 按实际下载的字节数结算，
+/// 未结算即被丢弃时（例如 Future 被取消）预留的用量不会被释放
+#[derive(Debug, Default)]
+#[must_use]
+pub(super) struct EgressReservation {
+    bytes: u64,
+    hour_start: u64,
+    day_start: u64,
+}
+
+impl EgressReservation {
+    /// 按实际下载的字节数结算，请求失败时传入 0 以释放预留的用量
+    pub(super) async fn settle(self, bytes: u64) {
+        if bytes == self.bytes || (self.bytes == 0 && egress_budget().is_none()) {
+            return;
+        }
+        let result = update_usage(|usage| {
+            Ok(if bytes > self.bytes {
+                usage.consumed(bytes - self.bytes)
+            } else {
+                usage.released(self.bytes - bytes, self.hour_start, self.day_start)
+            })
+        })
+        .await;
+        if let Err(err) = result {
+            warn!("Failed to settle egress usage: {}", err);
+        }
+    }
+}
+
+/// 在持有用量文件锁时更新用量，用量文件无法访问时仅更新本进程的用量
+async fn update_usage(
+    update: impl Fn(EgressUsage) -> Result<EgressUsage, EgressBudgetExceededError>,
+) -> Result<EgressUsage, EgressBudgetExceededError> {
+    let now = now_secs();
+    let usage = match update_usage_file(|usage| update(usage.rolled(now))).await {
+        Ok(usage) => usage,
+        Err(err) => {
+            warn!("Failed to update egress usage file: {}", err);
+            let mut cached = EGRESS_USAGE.lock().unwrap();
+            let usage = update(cached.unwrap_or_default().rolled(now))?;
+            *cached = Some(usage);
+            return Ok(usage);
+        }
+    };
+    let usage = usage?;
+    *EGRESS_USAGE.lock().unwrap() = Some(usage);
+    Ok(usage)
+}
+
+/// 在持有用量文件锁时读取并更新用量，`update` 返回错误时不写入用量文件
+async fn update_usage_file(
+    update: impl FnOnce(EgressUsage) -> Result<EgressUsage, EgressBudgetExceededError>,
+) -> IoResult<Result<EgressUsage, EgressBudgetExceededError>> {
+    let _guard = EGRESS_USAGE_FILE_LOCK.lock().await;
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(cache_dir_path_of(EGRESS_USAGE_FILE_NAME).await?)
+        .await?;
+    let mut file = FdRwLock::new(file);
+    // 加锁失败时异步等待，避免在其他进程持有锁期间阻塞运行时的工作线程
+    let mut file = loop {
+        match file.try_write() {
+            Ok(file) => break file,
+            Err(err) if err.kind() == IoErrorKind::WouldBlock => {
+                sleep(FILE_LOCK_RETRY_INTERVAL).await
+            }
+            Err(err) => return Err(err),
+        }
+    };
+    let mut content = Vec::new();
+    file.read_to_end(&mut content).await?;
+    let usage = match update(parse_usage(&content).unwrap_or_default()) {
+        Ok(usage) => usage,
+        Err(err) => return Ok(Err(err)),
+    };
+    file.seek(SeekFrom::Start(0)).await?;
+    file.set_len(0).await?;
+    file.write_all(&json_to_vec(&usage)?).await?;
+    file.flush().await?;
+    Ok(Ok(usage))
+}
+
+fn parse_usage(content: &[u8]) -> IoResult<EgressUsage> {
+    if content.is_empty() {
+        Ok(Default::default())
+    } else {
+        json_from_slice(content).map_err(|err| IoError::new(IoErrorKind::InvalidData, err))
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct EgressUsage {
+    hour_start: u64,
+    hour_bytes: u64,
+    day_start: u64,
+    day_bytes: u64,
+}
+
+impl EgressUsage {
+    fn rolled(mut self, now: u64) -> Self {
+        let hour_start = now - now % SECONDS_PER_HOUR;
+        if self.hour_start != hour_start {
+            self.hour_start = hour_start;
+            self.hour_bytes = 0;
+        }
+        let day_start = now - now % SECONDS_PER_DAY;
+        if self.day_start != day_start {
+            self.day_start = day_start;
+            self.day_bytes = 0;
+        }
+        self
+    }
+
+    fn consumed(mut self, bytes: u64) -> Self {
+        self.hour_bytes = self.hour_bytes.saturating_add(bytes);
+        self.day_bytes = self.day_bytes.saturating_add(bytes);
+        self
+    }
+
+    /// 释放在指定窗口中预留的用量，窗口已经重置时无需释放
+    fn released(mut self, bytes: u64, hour_start: u64, day_start: u64) -> Self {
+        if self.hour_start == hour_start {
+            self.hour_bytes = self.hour_bytes.saturating_sub(bytes);
+        }
+        if self.day_start == day_start {
+            self.day_bytes = self.day_bytes.saturating_sub(bytes);
+        }
+        self
+    }
+
+    fn check(&self, budget: &EgressBudget, now: u64) -> Result<(), EgressBudgetExceededError> {
+        let usage = self.rolled(now);
+        // 优先检查天级窗口，以便两个窗口均超出时等待更长的时间
+        if let Some(limit) = budget.bytes_per_day {
+            if usage.day_bytes >= limit {
+                return Err(EgressBudgetExceededError {
+                    window: EgressWindow::Day,
+                    limit,
+                    retry_after: Duration::from_secs(usage.day_start + SECONDS_PER_DAY - now),
+                });
+            }
+        }
+        if let Some(limit) = budget.bytes_per_hour {
+            if usage.hour_bytes >= limit {
+                return Err(EgressBudgetExceededError {
+                    window: EgressWindow::Hour,
+                    limit,
+                    retry_after: Duration::from_secs(usage.hour_start + SECONDS_PER_HOUR - now),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn remaining(&self, budget: &EgressBudget, now: u64) -> EgressBudgetRemaining {
+        let usage = self.rolled(now);
+        EgressBudgetRemaining {
+            hourly: budget
+                .bytes_per_hour
+                .map(|limit| limit.saturating_sub(usage.hour_bytes)),
+            daily: budget
+                .bytes_per_day
+                .map(|limit| limit.saturating_sub(usage.day_bytes)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_egress_usage() {
+        let budget = EgressBudget::new()
+            .bytes_per_hour(100)
+            .bytes_per_day(150)
+            .policy(EgressBudgetPolicy::Delay);
+        let now = 1_700_000_000;
+        let usage = EgressUsage::default().rolled(now).consumed(60);
+        assert_eq!(usage.check(&budget, now), Ok(()));
+        assert_eq!(
+            usage.remaining(&budget, now),
+            EgressBudgetRemaining {
+                hourly: Some(40),
+                daily: Some(90),
+            }
+        );
+
+        let usage = usage.consumed(40);
+        let err = usage.check(&budget, now).unwrap_err();
+        assert_eq!(err.window(), EgressWindow::Hour);
+        assert_eq!(err.limit(), 100);
+        assert_eq!(
+            err.retry_after(),
+            Duration::from_secs(SECONDS_PER_HOUR - now % SECONDS_PER_HOUR)
+        );
+
+        let released = usage.released(30, usage.hour_start, usage.day_start);
+        assert_eq!((released.hour_bytes, released.day_bytes), (70, 70));
+        let released = usage.released(30, usage.hour_start - SECONDS_PER_HOUR, usage.day_start);
+        assert_eq!((released.hour_bytes, released.day_bytes), (100, 70));
+
+        let next_hour = now + SECONDS_PER_HOUR;
+        assert_eq!(usage.check(&budget, next_hour), Ok(()));
+        let usage = usage.rolled(next_hour).consumed(50);
+        assert_eq!(usage.hour_bytes, 50);
+        assert_eq!(usage.day_bytes, 150);
+        assert_eq!(
+            usage.check(&budget, next_hour).unwrap_err().window(),
+            EgressWindow::Day
+        );
+        assert_eq!(
+            usage.remaining(&budget, next_hour + SECONDS_PER_DAY),
+            EgressBudgetRemaining {
+                hourly: Some(100),
+                daily: Some(150),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_egress_usage_written_by_other_processes() -> anyhow::Result<()> {
+        let path = cache_dir_path_of(EGRESS_USAGE_FILE_NAME).await?;
+        let now = now_secs();
+        let usage = EgressUsage::default().rolled(now).consumed(42);
+        tokio::fs::write(&path, json_to_vec(&usage)?).await?;
+        assert_eq!(update_usage(Ok).await?, usage);
+
+        // 其他进程更新了用量文件后，检查时应当读到新的用量，而不是本进程缓存的用量
+        let usage = usage.consumed(100);
+        tokio::fs::write(&path, json_to_vec(&usage)?).await?;
+        assert_eq!(update_usage(Ok).await?, usage);
+
+        let file = std::fs::File::open(&path)?;
+        let mut other_process = FdRwLock::new(file);
+        let guard = other_process.write()?;
+        let update = tokio::spawn(update_usage_file(|usage| Ok(usage.consumed(8))));
+        sleep(FILE_LOCK_RETRY_INTERVAL * 5).await;
+        assert!(!update.is_finished());
+        drop(guard);
+        assert_eq!(update.await???, usage.consumed(8));
+
+        tokio::fs::remove_file(&path).await?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_egress_usage() -> IoResult<()> {
+        assert_eq!(parse_usage(b"")?, EgressUsage::default());
+        let usage = EgressUsage::default().rolled(1_700_000_000).consumed(42);
+        assert_eq!(parse_usage(&json_to_vec(&usage)?)?, usage);
+        assert!(parse_usage(b"{").is_err());
+        Ok(())
+    }
+}
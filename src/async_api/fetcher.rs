@@ -0,0 +1,162 @@
+use super::{
+    super::{
+        base::download::RangeReaderBuilder as BaseRangeReaderBuilder,
+        config::{build_range_reader_builder_from_config, Config},
+    },
+    retrier::AsyncRangeReaderWithRangeReader,
+};
+use futures::{stream, Stream, StreamExt};
+use std::io::Result as IoResult;
+
+const DEFAULT_CONCURRENCY: usize = 16;
+
+/// 多对象并发下载器
+///
+/// 并发下载大量小对象，所有对象共享同一个域名选择器、缓存和打点记录，
+/// 适用于缩略图、小文件等单次调用开销占主导的场景
+#[derive(Debug, Clone)]
+pub struct AsyncMultiFetcher {
+    inner: AsyncRangeReaderWithRangeReader,
+    concurrency: usize,
+    ordered: bool,
+}
+
+impl AsyncMultiFetcher {
+    pub(crate) fn from_base_builder(builder: BaseRangeReaderBuilder) -> Self {
+        Self {
+            inner: AsyncRangeReaderWithRangeReader::from_base_builder(builder).0,
+            concurrency: DEFAULT_CONCURRENCY,
+            ordered: false,
+        }
+    }
+
+    /// 从配置创建多对象并发下载器
+    /// # Arguments
+    ///
+    /// * `config` - 下载配置
+    pub fn from_config(config: &Config) -> Self {
+        Self::from_base_builder(build_range_reader_builder_from_config(
+            String::new(),
+            config,
+        ))
+    }
+
+    /// 设置并发下载的对象数
+    ///
+    /// 默认为 16，为 0 时将被视为 1。同时在内存中缓存的对象数不会超过该值
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// 设置是否按照输入顺序返回结果
+    ///
+    /// 默认为 false，即按照下载完成的顺序返回结果。按照输入顺序返回时，
+    /// 下载较慢的对象将阻塞其后已经下载完成的对象
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    /// 并发下载多个对象，返回由对象名称和下载结果组成的流
+    ///
+    /// 每个对象单独重试，一个对象下载失败不影响其他对象，流中总是包含全部输入的对象名称
+    pub fn fetch_many<'a>(
+        &'a self,
+        keys: impl IntoIterator<Item = String> + 'a,
+    ) -> impl Stream<Item = (String, IoResult<Vec<u8>>)> + 'a {
+        let fetches = stream::iter(keys).map(move |key| async move {
+            let result = self.inner.download(&key).await;
+            (key, result)
+        });
+        if self.ordered {
+            fetches.buffered(self.concurrency).left_stream()
+        } else {
+            fetches.buffer_unordered(self.concurrency).right_stream()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::super::Credential, *};
+    use hyper::StatusCode;
+    use std::{convert::Infallible, io::ErrorKind as IoErrorKind, time::Duration};
+    use tokio::{spawn, sync::oneshot::channel, time::sleep};
+    use warp::{path, reply::Response, Filter};
+
+    macro_rules! starts_with_server {
+        ($addr:ident, $routes:ident, $code:block) => {{
+            let (tx, rx) = channel();
+            let ($addr, server) =
+                warp::serve($routes).bind_with_graceful_shutdown(([127, 0, 0, 1], 0), async move {
+                    rx.await.unwrap();
+                });
+            spawn(server);
+            $code;
+            tx.send(()).unwrap();
+        }};
+    }
+
+    #[tokio::test]
+    async fn test_fetch_many() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!(String).and_then(|key: String| async move {
+            match key.as_str() {
+                "slow" => sleep(Duration::from_millis(300)).await,
+                "missing" => {
+                    let mut resp = Response::new(Default::default());
+                    *resp.status_mut() = StatusCode::NOT_FOUND;
+                    return Ok::<_, Infallible>(resp);
+                }
+                _ => {}
+            }
+            Ok(Response::new(format!("content of {}", key).into()))
+        });
+
+        starts_with_server!(io_addr, io_routes, {
+            let fetcher = AsyncMultiFetcher::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    String::new(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            )
+            .concurrency(4);
+            let keys = || {
+                ["slow", "a", "missing", "b"]
+                    .iter()
+                    .map(|&key| key.to_owned())
+                    .collect::<Vec<_>>()
+            };
+
+            let results: Vec<_> = fetcher.fetch_many(keys()).collect().await;
+            assert_eq!(results.len(), 4);
+            assert_eq!(results.last().unwrap().0, "slow");
+            for (key, result) in results {
+                match key.as_str() {
+                    "missing" => assert_eq!(result.unwrap_err().kind(), IoErrorKind::NotFound),
+                    _ => assert_eq!(result?, format!("content of {}", key).into_bytes()),
+                }
+            }
+
+            let fetcher = fetcher.ordered(true);
+            let results: Vec<_> = fetcher.fetch_many(keys()).collect().await;
+            assert_eq!(
+                results
+                    .iter()
+                    .map(|(key, _)| key.to_owned())
+                    .collect::<Vec<_>>(),
+                keys()
+            );
+            assert_eq!(results[0].1.as_ref().unwrap(), b"content of slow");
+            assert!(results[2].1.is_err());
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,329 @@
+use super::reader::AsyncRangeReader;
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult, SeekFrom},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::{
+    io::{AsyncBufRead, AsyncRead, AsyncSeek, ReadBuf},
+    spawn,
+    task::JoinHandle,
+};
+
+const DEFAULT_BLOCK_SIZE: usize = 1 << 22;
+const DEFAULT_READ_AHEAD_BLOCKS: usize = 2;
+const DEFAULT_CACHE_BLOCKS: usize = 4;
+
+/// 异步对象文件
+///
+/// 实现了 `AsyncRead`，`AsyncSeek` 和 `AsyncBufRead`，可以像本地文件一样被异步解析器使用。
+/// 对象按块下载并缓存，顺序读取时会在后台预读后续的块
+#[derive(Debug)]
+pub struct AsyncObjectFile {
+    reader: AsyncRangeReader,
+    file_size: u64,
+    pos: u64,
+    block_size: usize,
+    read_ahead_blocks: usize,
+    cache_blocks: usize,
+    cache: VecDeque<(u64, Vec<u8>)>,
+    inflight: HashMap<u64, JoinHandle<IoResult<Vec<u8>>>>,
+}
+
+impl AsyncObjectFile {
+    /// 打开异步对象文件
+    ///
+    /// 打开时将获取一次对象的文件大小
+    pub async fn open(reader: AsyncRangeReader) -> IoResult<Self> {
+        let file_size = reader.file_size().await?;
+        Ok(Self {
+            reader,
+            file_size,
+            pos: 0,
+            block_size: DEFAULT_BLOCK_SIZE,
+            read_ahead_blocks: DEFAULT_READ_AHEAD_BLOCKS,
+            cache_blocks: DEFAULT_CACHE_BLOCKS,
+            cache: Default::default(),
+            inflight: Default::default(),
+        })
+    }
+
+    /// 设置块大小
+    ///
+    /// 默认为 4 MB，为 0 时将被视为 1
+    pub fn block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size.max(1);
+        self.clear();
+        self
+    }
+
+    /// 设置预读块数
+    ///
+    /// 默认为 2，为 0 时表示不预读
+    pub fn read_ahead_blocks(mut self, read_ahead_blocks: usize) -> Self {
+        self.read_ahead_blocks = read_ahead_blocks;
+        self
+    }
+
+    /// 设置缓存块数
+    ///
+    /// 默认为 4，为 0 时将被视为 1
+    pub fn cache_blocks(mut self, cache_blocks: usize) -> Self {
+        self.cache_blocks = cache_blocks.max(1);
+        self
+    }
+
+    /// 获取对象的文件大小
+    #[inline]
+    pub fn file_size(&self) -> u64 {
+        self.file_size
+    }
+
+    /// 获取当前读取位置
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    fn clear(&mut self) {
+        self.cache.clear();
+        self.inflight.drain().for_each(|(_, handle)| handle.abort());
+    }
+
+    fn last_block_index(&self) -> u64 {
+        self.file_size.saturating_sub(1) / self.block_size as u64
+    }
+
+    fn fetch_block(&mut self, index: u64) {
+        if self.inflight.contains_key(&index) || self.cache.iter().any(|(i, _)| *i == index) {
+            return;
+        }
+        let reader = self.reader.clone();
+        let pos = index * self.block_size as u64;
+        let size = (self.file_size - pos).min(self.block_size as u64) as usize;
+        self.inflight.insert(
+            index,
+            spawn(async move {
+                let mut buf = vec![0u8; size];
+                let mut have_read = 0;
+                while have_read < size {
+                    let n = reader
+                        .read_at(pos + have_read as u64, &mut buf[have_read..])
+                        .await?;
+                    if n == 0 {
+                        return Err(IoError::new(
+                            IoErrorKind::UnexpectedEof,
+                            "object is shorter than expected",
+                        ));
+                    }
+                    have_read += n;
+                }
+                Ok(buf)
+            }),
+        );
+    }
+
+    fn poll_block(&mut self, cx: &mut Context<'_>, index: u64) -> Poll<IoResult<usize>> {
+        if let Some(found) = self.cache.iter().position(|(i, _)| *i == index) {
+            return Poll::Ready(Ok(found));
+        }
+
+        let last_index = index.saturating_add(self.read_ahead_blocks as u64);
+        self.inflight.retain(|&i, handle| {
+            let keep = i >= index && i <= last_index;
+            if !keep {
+                handle.abort();
+            }
+            keep
+        });
+        for i in index..=last_index.min(self.last_block_index()) {
+            self.fetch_block(i);
+        }
+
+        let handle = self.inflight.get_mut(&index).unwrap();
+        let result = match Pin::new(handle).poll(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(result) => result,
+        };
+        self.inflight.remove(&index);
+        let block = result??;
+        while self.cache.len() >= self.cache_blocks {
+            self.cache.pop_front();
+        }
+        self.cache.push_back((index, block));
+        Poll::Ready(Ok(self.cache.len() - 1))
+    }
+}
+
+impl Drop for AsyncObjectFile {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl AsyncBufRead for AsyncObjectFile {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<&[u8]>> {
+        let this = self.get_mut();
+        if this.pos >= this.file_size {
+            return Poll::Ready(Ok(&[]));
+        }
+        let index = this.pos / this.block_size as u64;
+        let offset = (this.pos % this.block_size as u64) as usize;
+        match this.poll_block(cx, index) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Ready(Ok(found)) => Poll::Ready(Ok(&this.cache[found].1[offset..])),
+        }
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        this.pos = this.pos.saturating_add(amt as u64).min(this.file_size);
+    }
+}
+
+impl AsyncRead for AsyncObjectFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        let size = match self.as_mut().poll_fill_buf(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            Poll::Ready(Ok(data)) => {
+                let size = data.len().min(buf.remaining());
+                buf.put_slice(&data[..size]);
+                size
+            }
+        };
+        self.consume(size);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for AsyncObjectFile {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> IoResult<()> {
+        let this = self.get_mut();
+        let new_pos = match position {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => add_offset(this.file_size, offset),
+            SeekFrom::Current(offset) => add_offset(this.pos, offset),
+        };
+        this.pos = new_pos.ok_or_else(|| {
+            IoError::new(
+                IoErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<IoResult<u64>> {
+        Poll::Ready(Ok(self.pos))
+    }
+}
+
+fn add_offset(base: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        base.checked_add(offset as u64)
+    } else {
+        base.checked_sub(offset.unsigned_abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::super::{base::download::RangeReaderBuilder as BaseRangeReaderBuilder, Credential},
+        *,
+    };
+    use hyper::header::{HeaderValue, CONTENT_LENGTH, RANGE};
+    use text_io::scan as scan_text;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt},
+        sync::oneshot::channel,
+    };
+    use warp::{header, http::StatusCode, path, reply::Response, Filter};
+
+    macro_rules! starts_with_server {
+        ($addr:ident, $routes:ident, $code:block) => {{
+            let (tx, rx) = channel();
+            let ($addr, server) =
+                warp::serve($routes).bind_with_graceful_shutdown(([127, 0, 0, 1], 0), async move {
+                    rx.await.unwrap();
+                });
+            spawn(server);
+            $code;
+            tx.send(()).unwrap();
+        }};
+    }
+
+    fn data() -> Vec<u8> {
+        (0..100u8).collect()
+    }
+
+    #[tokio::test]
+    async fn test_async_object_file_read_and_seek() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!("file")
+            .and(warp::head())
+            .map(|| {
+                let mut resp = Response::default();
+                resp.headers_mut()
+                    .insert(CONTENT_LENGTH, HeaderValue::from_static("100"));
+                resp
+            })
+            .or(path!("file")
+                .and(header::value(RANGE.as_str()))
+                .map(|range: HeaderValue| {
+                    let from: usize;
+                    let to: usize;
+                    scan_text!(range.to_str().unwrap().bytes() => "bytes={}-{}", from, to);
+                    let mut resp = Response::new(data()[from..=to.min(99)].to_vec().into());
+                    *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+                    resp
+                }));
+
+        starts_with_server!(io_addr, io_routes, {
+            let reader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            );
+            let mut file = reader
+                .into_file()
+                .await?
+                .block_size(16)
+                .read_ahead_blocks(1)
+                .cache_blocks(2);
+            assert_eq!(file.file_size(), 100);
+
+            let mut buf = Vec::new();
+            file.read_to_end(&mut buf).await?;
+            assert_eq!(buf, data());
+
+            assert_eq!(file.seek(SeekFrom::Start(30)).await?, 30);
+            let mut buf = [0u8; 4];
+            file.read_exact(&mut buf).await?;
+            assert_eq!(buf, [30, 31, 32, 33]);
+
+            assert_eq!(file.seek(SeekFrom::End(-3)).await?, 97);
+            assert_eq!(file.fill_buf().await?, &[97, 98, 99]);
+            file.consume(3);
+            assert!(file.fill_buf().await?.is_empty());
+
+            assert!(file.seek(SeekFrom::Current(-101)).await.is_err());
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use super::{super::base::download::ObjectMetadata, cache_control::CacheDirectives};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+const MAX_ENTRIES: usize = 4096;
+
+/// 在时间窗口内复用 HEAD 请求的结果，使相继调用的 exist、file_size 和 stat 只发出一次请求
+///
+/// 记录的结果为对象元信息，对象不存在时为 None
+#[derive(Debug)]
+pub(super) struct HeadCache {
+    window: Duration,
+    entries: Mutex<HashMap<String, (Instant, Option<ObjectMetadata>)>>,
+}
+
+impl HeadCache {
+    pub(super) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            entries: Default::default(),
+        }
+    }
+
+    /// 获取时间窗口内的 HEAD 请求结果，外层的 None 表示没有可以复用的结果
+    pub(super) fn get(&self, key: &str) -> Option<Option<ObjectMetadata>> {
+        self.get_at(key, Instant::now())
+    }
+
+    /// 记录 HEAD 请求的结果，时间窗口受响应的缓存指令限制
+    pub(super) fn insert(
+        &self,
+        key: &str,
+        metadata: Option<ObjectMetadata>,
+        directives: &CacheDirectives,
+    ) {
+        if let Some(window) = directives.limit_ttl(self.window) {
+            self.insert_at(key, metadata, window, Instant::now())
+        }
+    }
+
+    /// 删除对象不存在的记录，如果记录存在则返回 true
+    pub(super) fn invalidate_missing(&self, key: &str) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        if matches!(entries.get(key), Some((_, None))) {
+            entries.remove(key);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn get_at(&self, key: &str, now: Instant) -> Option<Option<ObjectMetadata>> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((expires_at, metadata)) if *expires_at > now => Some(metadata.to_owned()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert_at(
+        &self,
+        key: &str,
+        metadata: Option<ObjectMetadata>,
+        window: Duration,
+        now: Instant,
+    ) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ENTRIES && !entries.contains_key(key) {
+            entries.retain(|_, &mut (expires_at, _)| expires_at > now);
+            if entries.len() >= MAX_ENTRIES {
+                entries.clear();
+            }
+        }
+        entries.insert(key.to_owned(), (now + window, metadata));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_head_cache() {
+        let cache = HeadCache::new(Duration::from_secs(1));
+        let now = Instant::now();
+        assert_eq!(cache.get_at("file", now), None);
+
+        let metadata = ObjectMetadata::from_headers(10, &Default::default());
+        cache.insert_at("file", Some(metadata.to_owned()), cache.window, now);
+        cache.insert_at("missing", None, cache.window, now);
+        assert_eq!(
+            cache.get_at("file", now + Duration::from_millis(500)),
+            Some(Some(metadata))
+        );
+        assert_eq!(
+            cache.get_at("missing", now + Duration::from_millis(500)),
+            Some(None)
+        );
+        assert!(!cache.invalidate_missing("file"));
+        assert!(cache.invalidate_missing("missing"));
+        assert_eq!(
+            cache.get_at("missing", now + Duration::from_millis(500)),
+            None
+        );
+        assert_eq!(cache.get_at("file", now + Duration::from_secs(1)), None);
+        assert!(cache.entries.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,424 @@
+use super::{
+    super::events::{emit_event, DownloadEvent},
+    cache_dir::cache_dir_path_of,
+    dot::DOT_FILE_NAME,
+};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    io::Error as IoError,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::fs::metadata;
+
+const RECENT_WINDOW: Duration = Duration::from_secs(60);
+
+static HEALTH_STATS: Lazy<Mutex<HealthStats>> = Lazy::new(Default::default);
+
+/// 单个域名的健康状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HostHealth {
+    host: String,
+    healthy: bool,
+    continuous_failures: usize,
+    successes: u64,
+    failures: u64,
+    last_error: Option<String>,
+}
+
+impl HostHealth {
+    /// 获取域名
+    #[inline]
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// 域名是否健康，即最近一次请求是否成功
+    #[inline]
+    pub fn is_healthy(&self) -> bool {
+        self.healthy
+    }
+
+    /// 获取连续失败次数
+    #[inline]
+    pub fn continuous_failures(&self) -> usize {
+        self.continuous_failures
+    }
+
+    /// 获取累计成功次数
+    #[inline]
+    pub fn successes(&self) -> u64 {
+        self.successes
+    }
+
+    /// 获取累计失败次数
+    #[inline]
+    pub fn failures(&self) -> u64 {
+        self.failures
+    }
+
+    /// 获取最近一次失败的错误信息
+    #[inline]
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+}
+
+/// 健康状态快照
+///
+/// 可以通过 `serde_json` 序列化后暴露在应用的 `/healthz` 或 `/metrics` 接口中
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthSnapshot {
+    hosts: Vec<HostHealth>,
+    recent_requests: u64,
+    recent_error_rate: f64,
+    dot_backlog_size: u64,
+    dot_buffered_records: u64,
+    dot_last_uploaded_at: Option<SystemTime>,
+    dot_continuous_upload_failures: u64,
+    cache_hit_rate: Option<f64>,
+    negative_cache_hits: u64,
+    negative_cache_misses: u64,
+    cancelled_sync_requests: u64,
+    slow_requests: u64,
+    divergent_reads: u64,
+}
+
+impl HealthSnapshot {
+    /// 获取所有访问过的域名的健康状态，按域名排序
+    #[inline]
+    pub fn hosts(&self) -> &[HostHealth] {
+        &self.hosts
+    }
+
+    /// 获取最近一分钟内的请求次数
+    #[inline]
+    pub fn recent_requests(&self) -> u64 {
+        self.recent_requests
+    }
+
+    /// 获取最近一分钟内的请求失败率，没有请求时为 0
+    #[inline]
+    pub fn recent_error_rate(&self) -> f64 {
+        self.recent_error_rate
+    }
+
+    /// 获取尚未上传的打点记录文件尺寸，单位为字节
+    #[inline]
+    pub fn dot_backlog_size(&self) -> u64 {
+        self.dot_backlog_size
+    }
+
+    /// 获取当前进程写入打点记录文件后尚未上传成功的记录条数
+    #[inline]
+    pub fn dot_buffered_records(&self) -> u64 {
+        self.dot_buffered_records
+    }
+
+    /// 获取当前进程最近一次成功上传打点记录的时间，尚未成功上传过时为 None
+    #[inline]
+    pub fn dot_last_uploaded_at(&self) -> Option<SystemTime> {
+        self.dot_last_uploaded_at
+    }
+
+    /// 获取打点记录连续上传失败的次数，持续增长说明打点上报链路已经中断
+    #[inline]
+    pub fn dot_continuous_upload_failures(&self) -> u64 {
+        self.dot_continuous_upload_failures
+    }
+
+    /// 获取 IO 域名查询缓存的命中率，尚未查询过时为 None
+    #[inline]
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        self.cache_hit_rate
+    }
+
+    /// 获取对象不存在的缓存命中次数，即未访问服务器直接判定为不存在的次数
+    #[inline]
+    pub fn negative_cache_hits(&self) -> u64 {
+        self.negative_cache_hits
+    }
+
+    /// 获取对象不存在的缓存未命中次数
+    #[inline]
+    pub fn negative_cache_misses(&self) -> u64 {
+        self.negative_cache_misses
+    }
+
+    /// 获取因调用方放弃等待而被中断的同步接口请求次数
+    #[inline]
+    pub fn cancelled_sync_requests(&self) -> u64 {
+        self.cancelled_sync_requests
+    }
+
+    /// 获取耗时超过慢请求阈值的 API 调用次数
+    #[inline]
+    pub fn slow_requests(&self) -> u64 {
+        self.slow_requests
+    }
+
+    /// 获取从第二个域名验证时发现数据不一致的读取次数
+    #[inline]
+    pub fn divergent_reads(&self) -> u64 {
+        self.divergent_reads
+    }
+}
+
+/// 获取当前进程的下载健康状态快照
+pub async fn health_snapshot() -> HealthSnapshot {
+    let dot_backlog_size = match cache_dir_path_of(DOT_FILE_NAME).await {
+        Ok(path) => metadata(path).await.map_or(0, |meta| meta.len()),
+        Err(_) => 0,
+    };
+    HEALTH_STATS
+        .lock()
+        .unwrap()
+        .snapshot(Instant::now(), dot_backlog_size)
+}
+
+pub(super) fn record_host_success(host: &str) {
+    HEALTH_STATS
+        .lock()
+        .unwrap()
+        .record(host, None, Instant::now());
+}
+
+pub(super) fn record_host_failure(host: &str, error: &IoError) {
+    HEALTH_STATS
+        .lock()
+        .unwrap()
+        .record(host, Some(error), Instant::now());
+}
+
+pub(super) fn record_cache_lookup(hit: bool) {
+    let mut stats = HEALTH_STATS.lock().unwrap();
+    if hit {
+        stats.cache_hits += 1;
+    } else {
+        stats.cache_misses += 1;
+    }
+}
+
+pub(super) fn record_negative_cache_lookup(hit: bool) {
+    let mut stats = HEALTH_STATS.lock().unwrap();
+    if hit {
+        stats.negative_cache_hits += 1;
+    } else {
+        stats.negative_cache_misses += 1;
+    }
+}
+
+pub(super) fn record_cancelled_sync_request() {
+    HEALTH_STATS.lock().unwrap().cancelled_sync_requests += 1;
+}
+
+pub(super) fn record_slow_request() {
+    HEALTH_STATS.lock().unwrap().slow_requests += 1;
+}
+
+pub(super) fn record_divergent_read() {
+    HEALTH_STATS.lock().unwrap().divergent_reads += 1;
+}
+
+/// 记录写入打点记录文件的记录条数和写入后的文件尺寸
+pub(crate) fn record_dot_records_flushed(records: usize, backlog_size: u64) {
+    HEALTH_STATS.lock().unwrap().dot_buffered_records += records as u64;
+    emit_event(|| DownloadEvent::DotRecordsFlushed {
+        records,
+        backlog_size,
+    });
+}
+
+/// 记录打点记录的上传结果
+pub(crate) fn record_dot_upload(error: Option<&IoError>) {
+    let mut stats = HEALTH_STATS.lock().unwrap();
+    match error {
+        Some(_) => stats.dot_continuous_upload_failures += 1,
+        None => {
+            stats.dot_continuous_upload_failures = 0;
+            stats.dot_last_uploaded_at = Some(SystemTime::now());
+        }
+    }
+}
+
+/// 记录打点记录文件已被清空，文件中的记录均已上传
+pub(crate) fn record_dot_backlog_cleared() {
+    HEALTH_STATS.lock().unwrap().dot_buffered_records = 0;
+}
+
+#[derive(Debug, Default)]
+struct HealthStats {
+    hosts: HashMap<String, HostHealth>,
+    recent: VecDeque<RecentBucket>,
+    cache_hits: u64,
+    cache_misses: u64,
+    negative_cache_hits: u64,
+    negative_cache_misses: u64,
+    cancelled_sync_requests: u64,
+    slow_requests: u64,
+    divergent_reads: u64,
+    dot_buffered_records: u64,
+    dot_last_uploaded_at: Option<SystemTime>,
+    dot_continuous_upload_failures: u64,
+}
+
+/// 按秒聚合的请求结果
+#[derive(Debug)]
+struct RecentBucket {
+    started_at: Instant,
+    successes: u64,
+    failures: u64,
+}
+
+impl HealthStats {
+    fn record(&mut self, host: &str, error: Option<&IoError>, now: Instant) {
+        let health = self
+            .hosts
+            .entry(host.to_owned())
+            .or_insert_with(|| HostHealth {
+                host: host.to_owned(),
+                healthy: true,
+                continuous_failures: 0,
+                successes: 0,
+                failures: 0,
+                last_error: None,
+            });
+        match error {
+            Some(error) => {
+                health.healthy = false;
+                health.continuous_failures += 1;
+                health.failures += 1;
+                health.last_error = Some(error.to_string());
+            }
+            None => {
+                health.healthy = true;
+                health.continuous_failures = 0;
+                health.successes += 1;
+            }
+        }
+
+        self.prune(now);
+        let expired = self.recent.back().map_or(true, |bucket| {
+            now.duration_since(bucket.started_at) >= Duration::from_secs(1)
+        });
+        if expired {
+            self.recent.push_back(RecentBucket {
+                started_at: now,
+                successes: 0,
+                failures: 0,
+            });
+        }
+        let bucket = self.recent.back_mut().unwrap();
+        if error.is_some() {
+            bucket.failures += 1;
+        } else {
+            bucket.successes += 1;
+        }
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while let Some(bucket) = self.recent.front() {
+            if now.duration_since(bucket.started_at) > RECENT_WINDOW {
+                self.recent.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn snapshot(&mut self, now: Instant, dot_backlog_size: u64) -> HealthSnapshot {
+        self.prune(now);
+        let (successes, failures) =
+            self.recent
+                .iter()
+                .fold((0, 0), |(successes, failures), bucket| {
+                    (successes + bucket.successes, failures + bucket.failures)
+                });
+        let recent_requests = successes + failures;
+        let mut hosts = self.hosts.values().cloned().collect::<Vec<_>>();
+        hosts.sort_by(|a, b| a.host.cmp(&b.host));
+        let cache_lookups = self.cache_hits + self.cache_misses;
+        HealthSnapshot {
+            hosts,
+            recent_requests,
+            recent_error_rate: if recent_requests > 0 {
+                failures as f64 / recent_requests as f64
+            } else {
+                0.0
+            },
+            dot_backlog_size,
+            dot_buffered_records: self.dot_buffered_records,
+            dot_last_uploaded_at: self.dot_last_uploaded_at,
+            dot_continuous_upload_failures: self.dot_continuous_upload_failures,
+            cache_hit_rate: if cache_lookups > 0 {
+                Some(self.cache_hits as f64 / cache_lookups as f64)
+            } else {
+                None
+            },
+            negative_cache_hits: self.negative_cache_hits,
+            negative_cache_misses: self.negative_cache_misses,
+            cancelled_sync_requests: self.cancelled_sync_requests,
+            slow_requests: self.slow_requests,
+            divergent_reads: self.divergent_reads,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind as IoErrorKind;
+
+    #[test]
+    fn test_health_stats() {
+        let mut stats = HealthStats::default();
+        let begin_at = Instant::now();
+        let error = IoError::new(IoErrorKind::TimedOut, "timed out");
+        stats.record("http://io1.example.com", None, begin_at);
+        stats.record("http://io2.example.com", Some(&error), begin_at);
+        stats.record("http://io2.example.com", Some(&error), begin_at);
+        stats.record(
+            "http://io1.example.com",
+            None,
+            begin_at + Duration::from_secs(2),
+        );
+        stats.cache_hits = 3;
+        stats.cache_misses = 1;
+        stats.negative_cache_hits = 2;
+        stats.cancelled_sync_requests = 1;
+        stats.slow_requests = 3;
+        stats.divergent_reads = 1;
+        stats.dot_buffered_records = 5;
+        stats.dot_continuous_upload_failures = 2;
+
+        let snapshot = stats.snapshot(begin_at + Duration::from_secs(2), 10);
+        assert_eq!(snapshot.recent_requests(), 4);
+        assert!((snapshot.recent_error_rate() - 0.5).abs() < f64::EPSILON);
+        assert_eq!(snapshot.cache_hit_rate(), Some(0.75));
+        assert_eq!(snapshot.dot_backlog_size(), 10);
+        assert_eq!(snapshot.negative_cache_hits(), 2);
+        assert_eq!(snapshot.negative_cache_misses(), 0);
+        assert_eq!(snapshot.cancelled_sync_requests(), 1);
+        assert_eq!(snapshot.slow_requests(), 3);
+        assert_eq!(snapshot.divergent_reads(), 1);
+        assert_eq!(snapshot.dot_buffered_records(), 5);
+        assert_eq!(snapshot.dot_last_uploaded_at(), None);
+        assert_eq!(snapshot.dot_continuous_upload_failures(), 2);
+        assert_eq!(snapshot.hosts().len(), 2);
+        assert!(snapshot.hosts()[0].is_healthy());
+        assert_eq!(snapshot.hosts()[0].successes(), 2);
+        assert!(!snapshot.hosts()[1].is_healthy());
+        assert_eq!(snapshot.hosts()[1].continuous_failures(), 2);
+        assert_eq!(snapshot.hosts()[1].last_error(), Some("timed out"));
+
+        let snapshot = stats.snapshot(begin_at + Duration::from_secs(61), 0);
+        assert_eq!(snapshot.recent_requests(), 1);
+        assert!(snapshot.recent_error_rate().abs() < f64::EPSILON);
+
+        let snapshot = stats.snapshot(begin_at + Duration::from_secs(120), 0);
+        assert_eq!(snapshot.recent_requests(), 0);
+        assert_eq!(snapshot.hosts().len(), 2);
+    }
+}
@@ -1,17 +1,33 @@
-use super::dot::Dotter;
-use log::info;
-use rand::{seq::SliceRandom, thread_rng};
+use super::{
+    super::{
+        base::rng::SharedRng,
+        config::{
+            is_connection_failure, normalize_host_url, normalize_host_urls, ConnectionFailureKind,
+            FailoverBackoff,
+        },
+        error_code::ErrorCode,
+        events::{emit_event, DownloadEvent},
+    },
+    dot::{ApiName, DotType, Dotter},
+    health::{record_host_failure, record_host_success},
+    host_stats::persisted_host_stats,
+};
+use log::{info, warn};
 use scc::HashMap;
 use std::{
     cmp::{min, Ordering},
     collections::HashSet,
+    error::Error as StdError,
     fmt::{Debug, Formatter, Result as FormatResult},
     future::Future,
-    io::{Error as IoError, Result as IoResult},
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
     ops::Deref,
     pin::Pin,
     sync::{
-        atomic::{AtomicUsize, Ordering::Relaxed},
+        atomic::{
+            AtomicUsize,
+            Ordering::{Acquire, Relaxed, Release},
+        },
         Arc,
     },
     time::{Duration, Instant},
@@ -20,6 +36,7 @@ use tap::prelude::*;
 use tokio::{
     spawn,
     sync::{Mutex, RwLock},
+    time::sleep,
 };
 
 #[derive(Default, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
@@ -136,11 +153,23 @@ struct HostsUpdater {
     update_option: Option<UpdateOption>,
     index: AtomicUsize,
     current_timeout_power: AtomicUsize,
+    last_update_result: Mutex<Option<UpdateResult>>,
+    update_generation: AtomicUsize,
+    force_https: bool,
+    rng: SharedRng,
 }
 
+/// 最近一次更新域名列表的结果，错误只保留类型和描述，供等待中的调用方复用
+type UpdateResult = Result<bool, (IoErrorKind, String)>;
+
 struct UpdateOption {
     func: UpdateFn,
     interval: Duration,
+    tries: usize,
+    backoff: FailoverBackoff,
+    dotter: Dotter,
+    min_hosts: usize,
+    max_change_ratio: f64,
     last_updated_at: Mutex<Instant>,
 }
 
@@ -149,13 +178,85 @@ impl UpdateOption {
         Self {
             func,
             interval,
+            tries: DEFAULT_UPDATE_TRIES,
+            backoff: default_update_backoff(),
+            dotter: Default::default(),
+            min_hosts: DEFAULT_MIN_HOSTS_ON_UPDATE,
+            max_change_ratio: DEFAULT_MAX_HOST_CHANGE_RATIO,
             last_updated_at: Mutex::new(Instant::now()),
         }
     }
+
+    fn tries(mut self, tries: usize) -> Self {
+        self.tries = tries.max(1);
+        self
+    }
+
+    fn dotter(mut self, dotter: Dotter) -> Self {
+        self.dotter = dotter;
+        self
+    }
+
+    fn min_hosts(mut self, min_hosts: usize) -> Self {
+        self.min_hosts = min_hosts;
+        self
+    }
+
+    fn max_change_ratio(mut self, ratio: f64) -> Self {
+        self.max_change_ratio = ratio;
+        self
+    }
+
+    /// 检查新的域名列表是否可疑，可疑时返回原因
+    ///
+    /// 原有域名列表为空或新的域名数量不少于原有列表时总是接受，
+    /// 以免整体迁移域名的更新被拒绝
+    fn check_new_hosts(&self, old_hosts: &[String], new_hosts: &[String]) -> Option<String> {
+        if old_hosts.is_empty() || new_hosts.len() >= old_hosts.len() {
+            return None;
+        }
+        if new_hosts.len() < self.min_hosts {
+            return Some(format!(
+                "only {} hosts are returned, at least {} hosts are required",
+                new_hosts.len(),
+                self.min_hosts
+            ));
+        }
+        let removed = old_hosts
+            .iter()
+            .filter(|&host| !new_hosts.contains(host))
+            .count();
+        if removed as f64 > old_hosts.len() as f64 * self.max_change_ratio {
+            return Some(format!(
+                "{} of {} hosts are removed, at most {:.0}% is allowed",
+                removed,
+                old_hosts.len(),
+                self.max_change_ratio * 100.0
+            ));
+        }
+        None
+    }
+}
+
+const DEFAULT_UPDATE_TRIES: usize = 3;
+const MAX_SEEDED_TIMEOUT_POWER: usize = 4;
+const UNHEALTHY_SUCCESS_RATE: f64 = 0.5;
+const DEFAULT_MIN_HOSTS_ON_UPDATE: usize = 1;
+const DEFAULT_MAX_HOST_CHANGE_RATIO: f64 = 0.5;
+const DEFAULT_MAX_TIMEOUT: Duration = Duration::from_secs(600);
+
+fn default_update_backoff() -> FailoverBackoff {
+    FailoverBackoff::exponential(Duration::from_millis(100), Duration::from_secs(2)).with_jitter()
 }
 
 impl HostsUpdater {
-    async fn new(hosts: Vec<String>, update_option: Option<UpdateOption>) -> Arc<Self> {
+    async fn new(
+        hosts: Vec<String>,
+        update_option: Option<UpdateOption>,
+        force_https: bool,
+        rng: SharedRng,
+    ) -> Arc<Self> {
+        let hosts = normalize_host_urls(hosts, force_https);
         let hosts_map = HashMap::default();
         for host in &hosts {
             hosts_map
@@ -169,10 +270,23 @@ impl HostsUpdater {
             hosts: RwLock::new(hosts),
             index: AtomicUsize::new(0),
             current_timeout_power: AtomicUsize::new(0),
+            last_update_result: Default::default(),
+            update_generation: AtomicUsize::new(0),
+            force_https,
+            rng,
         })
     }
 
-    async fn set_hosts(&self, mut hosts: Vec<String>) {
+    /// 规范化域名列表，不合法的域名将被忽略，启用强制 HTTPS 时所有域名都将使用 HTTPS 协议
+    fn normalize_hosts(&self, hosts: Vec<String>) -> Vec<String> {
+        normalize_host_urls(hosts, self.force_https)
+    }
+
+    async fn set_hosts(&self, hosts: Vec<String>) {
+        self.replace_hosts(self.normalize_hosts(hosts)).await
+    }
+
+    async fn replace_hosts(&self, mut hosts: Vec<String>) {
         let mut new_hosts_set = HashSet::with_capacity(hosts.len());
         for host in hosts.iter() {
             new_hosts_set.insert(host.to_owned());
@@ -184,20 +298,100 @@ impl HostsUpdater {
         self.hosts_map
             .retain_async(|host, _| new_hosts_set.contains(host))
             .await;
-        hosts.shuffle(&mut thread_rng());
+        self.rng.shuffle(&mut hosts);
         *self.hosts.write().await = hosts;
     }
 
-    async fn update_hosts(&self) -> bool {
-        if let Some(update_option) = &self.update_option {
-            if let Ok(new_hosts) = (update_option.func)().await {
-                if !new_hosts.is_empty() {
-                    self.set_hosts(new_hosts).await;
-                    return true;
+    /// 更新域名列表，同一时刻只有一个调用会真正发起更新，其他并发的调用等待并复用其结果
+    async fn update_hosts(&self) -> IoResult<bool> {
+        let generation = self.update_generation.load(Acquire);
+        let mut last_update_result = self.last_update_result.lock().await;
+        if self.update_generation.load(Acquire) != generation {
+            if let Some(result) = last_update_result.as_ref() {
+                return result
+                    .to_owned()
+                    .map_err(|(kind, message)| IoError::new(kind, message));
+            }
+        }
+        let result = self._update_hosts().await;
+        *last_update_result = Some(
+            result
+                .as_ref()
+                .copied()
+                .map_err(|err| (err.kind(), err.to_string())),
+        );
+        self.update_generation.fetch_add(1, Release);
+        result
+    }
+
+    /// 更新域名列表，失败时按退避策略重试，所有尝试均失败时返回最后一次的错误
+    async fn _update_hosts(&self) -> IoResult<bool> {
+        let update_option = if let Some(update_option) = &self.update_option {
+            update_option
+        } else {
+            return Ok(false);
+        };
+        let begin_at = Instant::now();
+        let mut last_error = None;
+        for tries in 0..update_option.tries {
+            if tries > 0 {
+                sleep(update_option.backoff.delay_with_rng(tries, &self.rng)).await;
+            }
+            match (update_option.func)().await {
+                Ok(new_hosts) => {
+                    let new_hosts = self.normalize_hosts(new_hosts);
+                    update_option
+                        .dotter
+                        .dot(DotType::Sdk, ApiName::UpdateHosts, true, begin_at.elapsed())
+                        .await
+                        .ok();
+                    if new_hosts.is_empty() {
+                        return Ok(false);
+                    }
+                    let rejected_reason =
+                        update_option.check_new_hosts(&self.hosts.read().await, &new_hosts);
+                    if let Some(reason) = rejected_reason {
+                        warn!(
+                            "Reject suspicious hosts update {:?} and keep the previous hosts: {}",
+                            new_hosts, reason
+                        );
+                        update_option
+                            .dotter
+                            .dot(
+                                DotType::Sdk,
+                                ApiName::RejectHostsUpdate,
+                                false,
+                                begin_at.elapsed(),
+                            )
+                            .await
+                            .ok();
+                        return Ok(false);
+                    }
+                    self.replace_hosts(new_hosts).await;
+                    return Ok(true);
+                }
+                Err(err) => {
+                    warn!(
+                        "Failed to update hosts, tries: {}/{}, error: {}",
+                        tries + 1,
+                        update_option.tries,
+                        err
+                    );
+                    last_error = Some(err);
                 }
             }
         }
-        false
+        update_option
+            .dotter
+            .dot(
+                DotType::Sdk,
+                ApiName::UpdateHosts,
+                false,
+                begin_at.elapsed(),
+            )
+            .await
+            .ok();
+        Err(last_error.expect("update tries must be greater than 0"))
     }
 
     fn next_index(updater: &Arc<HostsUpdater>) -> usize {
@@ -221,9 +415,18 @@ impl HostsUpdater {
             if let Some(update_option) = &updater.update_option {
                 let mut last_updated_at = update_option.last_updated_at.lock().await;
                 if last_updated_at.elapsed() >= update_option.interval {
-                    if updater.update_hosts().await {
-                        info!("`host-selector-auto-updater` update hosts successfully");
-                    };
+                    match updater.update_hosts().await {
+                        Ok(true) => {
+                            info!("`host-selector-auto-updater` update hosts successfully")
+                        }
+                        Ok(false) => {}
+                        Err(err) => {
+                            warn!(
+                                "`host-selector-auto-updater` failed to update hosts: {}",
+                                err
+                            )
+                        }
+                    }
                     *last_updated_at = Instant::now();
                 }
             }
@@ -273,8 +476,11 @@ struct HostPunisher {
     should_punish_func: Option<ShouldPunishFn>,
     punish_duration: Duration,
     base_timeout: Duration,
+    max_timeout: Duration,
+    host_base_timeouts: HashMap<String, Duration>,
     max_punished_times: usize,
     max_punished_hosts_percent: u8,
+    connection_failure_kinds: Vec<ConnectionFailureKind>,
 }
 
 impl HostPunisher {
@@ -297,14 +503,33 @@ impl HostPunisher {
         }
     }
 
-    fn timeout(&self, punished_info: &PunishedInfo) -> Duration {
+    /// 域名的基础超时时长，未单独配置时使用全局的基础超时时长
+    fn base_timeout_of(&self, host: &str) -> Duration {
+        self.host_base_timeouts
+            .read(host, |_, base_timeout| *base_timeout)
+            .unwrap_or(self.base_timeout)
+    }
+
+    fn timeout(&self, host: &str, punished_info: &PunishedInfo) -> Duration {
         min(
             // 超时时长有上限，否则可能超过 tokio 极限
-            self.base_timeout * (1 << punished_info.timeout_power),
-            Duration::from_secs(600),
+            self.base_timeout_of(host) * (1 << punished_info.timeout_power),
+            self.max_timeout,
         )
     }
 
+    /// 计算超时时长不低于指定延迟两倍所需的超时倍数
+    fn timeout_power_for(&self, host: &str, latency: Duration) -> usize {
+        let base_timeout = self.base_timeout_of(host);
+        let mut timeout_power = 0;
+        while timeout_power < MAX_SEEDED_TIMEOUT_POWER
+            && base_timeout * (1 << timeout_power) < latency * 2
+        {
+            timeout_power += 1;
+        }
+        timeout_power
+    }
+
     async fn should_punish(&self, error: &IoError) -> bool {
         if let Some(should_punish_func) = &self.should_punish_func {
             should_punish_func(error).await
@@ -320,19 +545,45 @@ impl Debug for HostPunisher {
             .field("should_punish", &self.should_punish_func.is_some())
             .field("punish_duration", &self.punish_duration)
             .field("base_timeout", &self.base_timeout)
+            .field("max_timeout", &self.max_timeout)
+            .field("host_base_timeouts", &self.host_base_timeouts)
             .field("max_punished_times", &self.max_punished_times)
             .field(
                 "max_punished_hosts_percent",
                 &self.max_punished_hosts_percent,
             )
+            .field("connection_failure_kinds", &self.connection_failure_kinds)
             .finish()
     }
 }
 
+/// 运行时人工干预域名选择，用于在故障期间临时禁用或固定域名
+#[derive(Debug, Default)]
+struct HostSteering {
+    banned_until: HashMap<String, Instant>,
+    pinned_hosts: RwLock<Vec<String>>,
+}
+
+impl HostSteering {
+    async fn is_allowed(&self, host: &str) -> bool {
+        let banned = self
+            .banned_until
+            .read_async(host, |_, banned_until| Instant::now() < *banned_until)
+            .await
+            .unwrap_or(false);
+        if banned {
+            return false;
+        }
+        let pinned_hosts = self.pinned_hosts.read().await;
+        pinned_hosts.is_empty() || pinned_hosts.iter().any(|pinned| pinned == host)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(super) struct HostSelector {
     hosts_updater: Arc<HostsUpdater>,
     host_punisher: Arc<HostPunisher>,
+    host_steering: Arc<HostSteering>,
 }
 
 pub(super) struct HostSelectorBuilder {
@@ -340,10 +591,20 @@ pub(super) struct HostSelectorBuilder {
     update_func: Option<UpdateFn>,
     should_punish_func: Option<ShouldPunishFn>,
     update_interval: Duration,
+    update_tries: usize,
+    update_dotter: Dotter,
+    min_hosts_on_update: usize,
+    max_host_change_ratio: f64,
+    use_persisted_stats: bool,
     punish_duration: Duration,
     base_timeout: Duration,
+    max_timeout: Duration,
+    host_base_timeouts: Vec<(String, Duration)>,
     max_punished_times: usize,
     max_punished_hosts_percent: u8,
+    connection_failure_kinds: Vec<ConnectionFailureKind>,
+    force_https: bool,
+    rng: SharedRng,
 }
 
 impl HostSelectorBuilder {
@@ -353,10 +614,20 @@ impl HostSelectorBuilder {
             update_func: None,
             should_punish_func: None,
             update_interval: Duration::from_secs(60),
+            update_tries: DEFAULT_UPDATE_TRIES,
+            update_dotter: Default::default(),
+            min_hosts_on_update: DEFAULT_MIN_HOSTS_ON_UPDATE,
+            max_host_change_ratio: DEFAULT_MAX_HOST_CHANGE_RATIO,
+            use_persisted_stats: false,
             punish_duration: Duration::from_secs(30 * 60),
             base_timeout: Duration::from_millis(3000),
+            max_timeout: DEFAULT_MAX_TIMEOUT,
+            host_base_timeouts: vec![],
             max_punished_times: 5,
             max_punished_hosts_percent: 50,
+            connection_failure_kinds: ConnectionFailureKind::ALL.to_vec(),
+            force_https: false,
+            rng: Default::default(),
         }
     }
 
@@ -373,11 +644,36 @@ impl HostSelectorBuilder {
         self
     }
 
+    pub(super) fn update_tries(mut self, tries: usize) -> Self {
+        self.update_tries = tries;
+        self
+    }
+
+    pub(super) fn update_dotter(mut self, dotter: Dotter) -> Self {
+        self.update_dotter = dotter;
+        self
+    }
+
     pub(super) fn update_interval(mut self, interval: Duration) -> Self {
         self.update_interval = interval;
         self
     }
 
+    pub(super) fn min_hosts_on_update(mut self, min_hosts: usize) -> Self {
+        self.min_hosts_on_update = min_hosts;
+        self
+    }
+
+    pub(super) fn max_host_change_ratio(mut self, ratio: f64) -> Self {
+        self.max_host_change_ratio = ratio;
+        self
+    }
+
+    pub(super) fn use_persisted_stats(mut self, use_persisted_stats: bool) -> Self {
+        self.use_persisted_stats = use_persisted_stats;
+        self
+    }
+
     pub(super) fn punish_duration(mut self, duration: Duration) -> Self {
         self.punish_duration = duration;
         self
@@ -388,6 +684,19 @@ impl HostSelectorBuilder {
         self
     }
 
+    pub(super) fn max_timeout(mut self, timeout: Duration) -> Self {
+        self.max_timeout = timeout;
+        self
+    }
+
+    pub(super) fn host_base_timeouts(
+        mut self,
+        timeouts: impl IntoIterator<Item = (String, Duration)>,
+    ) -> Self {
+        self.host_base_timeouts = timeouts.into_iter().collect();
+        self
+    }
+
     pub(super) fn max_punished_times(mut self, times: usize) -> Self {
         self.max_punished_times = times;
         self
@@ -398,31 +707,73 @@ impl HostSelectorBuilder {
         self
     }
 
+    pub(super) fn connection_failure_kinds(mut self, kinds: Vec<ConnectionFailureKind>) -> Self {
+        self.connection_failure_kinds = kinds;
+        self
+    }
+
+    pub(super) fn force_https(mut self, force_https: bool) -> Self {
+        self.force_https = force_https;
+        self
+    }
+
+    pub(super) fn rng(mut self, rng: SharedRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
     pub(super) async fn build(self) -> HostSelector {
         let auto_update_enabled = self.update_func.is_some();
         let is_hosts_empty = self.hosts.is_empty();
         let update_interval = self.update_interval;
+        let update_tries = self.update_tries;
+        let update_dotter = self.update_dotter;
+        let min_hosts_on_update = self.min_hosts_on_update;
+        let max_host_change_ratio = self.max_host_change_ratio;
         let hosts_updater = HostsUpdater::new(
             self.hosts,
-            self.update_func
-                .map(|f| UpdateOption::new(f, update_interval)),
+            self.update_func.map(|f| {
+                UpdateOption::new(f, update_interval)
+                    .tries(update_tries)
+                    .dotter(update_dotter)
+                    .min_hosts(min_hosts_on_update)
+                    .max_change_ratio(max_host_change_ratio)
+            }),
+            self.force_https,
+            self.rng,
         )
         .await;
 
         if auto_update_enabled && is_hosts_empty {
-            hosts_updater.update_hosts().await;
+            if let Err(err) = hosts_updater.update_hosts().await {
+                warn!("Failed to initialize hosts: {}", err);
+            }
         }
 
-        HostSelector {
+        let host_base_timeouts = HashMap::default();
+        for (host, base_timeout) in self.host_base_timeouts {
+            host_base_timeouts
+                .upsert_async(normalize_host_url(host, self.force_https), base_timeout)
+                .await;
+        }
+        let host_selector = HostSelector {
             hosts_updater,
             host_punisher: Arc::new(HostPunisher {
                 should_punish_func: self.should_punish_func,
                 punish_duration: self.punish_duration,
                 base_timeout: self.base_timeout,
+                max_timeout: self.max_timeout,
+                host_base_timeouts,
                 max_punished_times: self.max_punished_times,
                 max_punished_hosts_percent: self.max_punished_hosts_percent,
+                connection_failure_kinds: self.connection_failure_kinds,
             }),
+            host_steering: Default::default(),
+        };
+        if self.use_persisted_stats {
+            host_selector.seed_from_persisted_stats().await;
         }
+        host_selector
     }
 }
 
@@ -454,6 +805,11 @@ impl HostSelector {
             .collect()
     }
 
+    /// 获取全部域名，包括被冻结的域名
+    pub(super) async fn all_hosts(&self) -> Vec<String> {
+        self.hosts_updater.hosts.read().await.to_owned()
+    }
+
     pub(super) async fn all_hosts_crc32(&self) -> u32 {
         let mut hosts = self.hosts_updater.hosts.read().await.to_owned();
         hosts.sort();
@@ -467,16 +823,15 @@ impl HostSelector {
         hasher.finalize()
     }
 
-    pub(super) async fn update_hosts(&self) -> bool {
-        if self.hosts_updater.update_hosts().await {
+    pub(super) async fn update_hosts(&self) -> IoResult<bool> {
+        let updated = self.hosts_updater.update_hosts().await?;
+        if updated {
             info!("manual update hosts successfully");
             if let Some(update_option) = self.hosts_updater.update_option.as_ref() {
                 *update_option.last_updated_at.lock().await = Instant::now();
             }
-            true
-        } else {
-            false
         }
+        Ok(updated)
     }
 
     pub(super) async fn select_host(&self, tried: &HashSet<String>) -> Option<HostInfo> {
@@ -487,20 +842,31 @@ impl HostSelector {
         }
         let mut chosen_host_info = None;
 
-        let hosts = self.hosts_updater.hosts.read().await;
+        let all_hosts = self.hosts_updater.hosts.read().await;
+        let mut hosts = Vec::with_capacity(all_hosts.len());
+        for host in all_hosts.iter() {
+            if self.host_steering.is_allowed(host).await {
+                hosts.push(host.as_str());
+            }
+        }
+        if hosts.is_empty() {
+            warn!("All hosts are banned or not pinned, ignore the manual steering");
+            hosts = all_hosts.iter().map(|host| host.as_str()).collect();
+        }
         let max_seek_times = self.host_punisher.max_seek_times(hosts.len());
         let mut candidates = Vec::with_capacity(max_seek_times + 1);
         for _ in 0..=max_seek_times {
             let index = HostsUpdater::next_index(&self.hosts_updater);
-            let host = hosts[index % hosts.len()].as_str();
+            let host = hosts[index % hosts.len()];
             if tried.contains(host) {
                 continue;
             } else if let Some(true) = self.hosts_updater.hosts_map.read_async(host, |_, punished_info| {
                 if self.host_punisher.is_punishment_expired(punished_info) {
-                    info!("host {} is selected directly because there is no punishment or punishment is expired, timeout: {:?}", host,self.host_punisher.base_timeout);
+                    let timeout = self.host_punisher.base_timeout_of(host);
+                    info!("host {} is selected directly because there is no punishment or punishment is expired, timeout: {:?}", host, timeout);
                     chosen_host_info = Some(CurrentHostInfo {
                         host,
-                        timeout: self.host_punisher.base_timeout,
+                        timeout,
                         timeout_power: 0,
                     });
                     true
@@ -508,12 +874,12 @@ impl HostSelector {
                     info!(
                         "host {} is selected, timeout: {:?}, timeout power: {:?}",
                         host,
-                        self.host_punisher.timeout(punished_info),
+                        self.host_punisher.timeout(host, punished_info),
                         punished_info.timeout_power,
                     );
                     chosen_host_info = Some(CurrentHostInfo {
                         host,
-                        timeout: self.host_punisher.timeout(punished_info),
+                        timeout: self.host_punisher.timeout(host, punished_info),
                         timeout_power: punished_info.timeout_power,
                     });
                     true
@@ -521,7 +887,7 @@ impl HostSelector {
                     info!(
                         "host {} is unsatisfied, put it into candidates, timeout: {:?}, timeout power: {:?}",
                         host,
-                        self.host_punisher.timeout(punished_info),
+                        self.host_punisher.timeout(host, punished_info),
                         punished_info.timeout_power,
                     );
                     candidates.push(Candidate {
@@ -543,7 +909,7 @@ impl HostSelector {
                     .max()
                     .map(|c| CurrentHostInfo {
                         host: c.host,
-                        timeout: self.host_punisher.timeout(&c.punished_info),
+                        timeout: self.host_punisher.timeout(c.host, &c.punished_info),
                         timeout_power: c.punished_info.timeout_power,
                     })
                     .tap_some(|c| {
@@ -565,10 +931,53 @@ impl HostSelector {
             })
     }
 
-    pub(super) async fn reward(&self, host: &str) {
+    /// 优先选择指定域名，域名不可用、正在尝试或未被允许时返回 None
+    ///
+    /// 用于分片下载时将各个分片有意地分散到不同域名上，遵循域名的惩罚状态
+    pub(super) async fn select_preferred_host(
+        &self,
+        host: &str,
+        tried: &HashSet<String>,
+    ) -> Option<HostInfo> {
+        if tried.contains(host) || !self.host_steering.is_allowed(host).await {
+            return None;
+        }
         self.hosts_updater
+            .hosts_map
+            .read_async(host, |_, punished_info| {
+                if self.host_punisher.is_punishment_expired(punished_info) {
+                    Some(HostInfo {
+                        host: host.to_owned(),
+                        timeout: self.host_punisher.base_timeout_of(host),
+                        timeout_power: 0,
+                    })
+                } else if self.is_satisfied_with(punished_info) {
+                    Some(HostInfo {
+                        host: host.to_owned(),
+                        timeout: self.host_punisher.timeout(host, punished_info),
+                        timeout_power: punished_info.timeout_power,
+                    })
+                } else {
+                    None
+                }
+            })
+            .await
+            .flatten()
+            .tap_some(|chosen| {
+                info!(
+                    "preferred host {} is selected, timeout: {:?}, timeout power: {:?}",
+                    chosen.host, chosen.timeout, chosen.timeout_power,
+                );
+            })
+    }
+
+    pub(super) async fn reward(&self, host: &str) {
+        record_host_success(host);
+        let recovered = self
+            .hosts_updater
             .hosts_map
             .update_async(host, |_, punished_info| {
+                let recovered = punished_info.continuous_punished_times > 0;
                 punished_info.continuous_punished_times = 0;
                 punished_info.failed_to_connect = false;
                 punished_info.timeout_power = punished_info.timeout_power.saturating_sub(1);
@@ -576,8 +985,14 @@ impl HostSelector {
                     "Reward host {}, now timeout_power is {}",
                     host, punished_info.timeout_power
                 );
+                recovered
             })
             .await;
+        if recovered == Some(true) {
+            emit_event(|| DownloadEvent::HostRecovered {
+                host: host.to_owned(),
+            });
+        }
     }
 
     pub(super) async fn punish(&self, host: &str, error: &IoError, dotter: &Dotter) -> bool {
@@ -593,12 +1008,22 @@ impl HostSelector {
 
     pub(super) async fn punish_without_dotter(&self, host: &str, error: &IoError) -> PunishResult {
         if self.host_punisher.should_punish(error).await {
+            record_host_failure(host, error);
+            emit_event(|| DownloadEvent::HostPunished {
+                host: host.to_owned(),
+                error: error.to_string(),
+                code: ErrorCode::of(error),
+            });
+            let failed_to_connect = self.is_connection_failure(error);
             let result = self
                 .hosts_updater
                 .hosts_map
                 .update_async(host, |_, punished_info| {
                     punished_info.continuous_punished_times += 1;
                     punished_info.last_punished_at = OptionalInstantTime::now();
+                    if failed_to_connect {
+                        punished_info.failed_to_connect = true;
+                    }
                     info!(
                     "Punish host {}, now continuous_punished_times is {}, and timeout_power is {}",
                     host, punished_info.continuous_punished_times, punished_info.timeout_power
@@ -624,6 +1049,11 @@ impl HostSelector {
             .await
     }
 
+    /// 错误是否属于需要标记为连接失败的错误类型
+    pub(super) fn is_connection_failure(&self, error: &(dyn StdError + 'static)) -> bool {
+        is_connection_failure(error, &self.host_punisher.connection_failure_kinds)
+    }
+
     pub(super) async fn mark_connection_as_failed(&self, host: &str) {
         self.hosts_updater.mark_connection_as_failed(host).await
     }
@@ -632,6 +1062,77 @@ impl HostSelector {
         self.host_punisher.base_timeout
     }
 
+    /// 设置域名的基础超时时长，为 None 表示使用全局的基础超时时长
+    ///
+    /// 基础超时时长是域名超时时长按倍数增长前的起点，适用于跨区域等延迟天然较高的域名
+    pub(super) async fn set_host_base_timeout(&self, host: &str, base_timeout: Option<Duration>) {
+        if let Some(base_timeout) = base_timeout {
+            info!("Set base timeout of host {} to {:?}", host, base_timeout);
+            self.host_punisher
+                .host_base_timeouts
+                .upsert_async(host.to_owned(), base_timeout)
+                .await;
+        } else {
+            info!("Reset base timeout of host {}", host);
+            self.host_punisher
+                .host_base_timeouts
+                .remove_async(host)
+                .await;
+        }
+    }
+
+    /// 使用进程启动时加载的域名统计数据初始化域名状态
+    ///
+    /// 延迟较高的域名将使用更长的超时时长，成功率较低的域名将被视为已被惩罚，
+    /// 避免重启后的进程通过失败的请求重新学习域名状态
+    async fn seed_from_persisted_stats(&self) {
+        let hosts = self.hosts_updater.hosts.read().await.to_owned();
+        for host in hosts {
+            if let Some(stats) = persisted_host_stats(&host).await {
+                let timeout_power = self.host_punisher.timeout_power_for(&host, stats.latency());
+                let unhealthy = stats.success_rate() < UNHEALTHY_SUCCESS_RATE;
+                self.hosts_updater
+                    .hosts_map
+                    .update_async(&host, |_, punished_info| {
+                        punished_info.timeout_power =
+                            punished_info.timeout_power.max(timeout_power);
+                        if unhealthy {
+                            punished_info.continuous_punished_times = 1;
+                            punished_info.last_punished_at = OptionalInstantTime::now();
+                        }
+                    })
+                    .await;
+                info!(
+                    "Seed host {} from persisted stats, success rate: {:.2}, latency: {:?}, timeout power: {}",
+                    host,
+                    stats.success_rate(),
+                    stats.latency(),
+                    timeout_power
+                );
+            }
+        }
+    }
+
+    /// 在指定时长内不再选择该域名，时长为 0 表示解除禁用
+    pub(super) async fn ban_host(&self, host: &str, duration: Duration) {
+        if duration == Duration::from_secs(0) {
+            info!("Unban host {}", host);
+            self.host_steering.banned_until.remove_async(host).await;
+        } else {
+            info!("Ban host {} for {:?}", host, duration);
+            self.host_steering
+                .banned_until
+                .upsert_async(host.to_owned(), Instant::now() + duration)
+                .await;
+        }
+    }
+
+    /// 仅从指定的域名中选择，为空表示解除固定
+    pub(super) async fn pin_hosts(&self, hosts: Vec<String>) {
+        info!("Pin hosts {:?}", hosts);
+        *self.host_steering.pinned_hosts.write().await = hosts;
+    }
+
     fn is_satisfied_with(&self, punished_info: &PunishedInfo) -> bool {
         self.host_punisher.is_available(punished_info, true)
             && self.hosts_updater.current_timeout_power.load(Relaxed) >= punished_info.timeout_power
@@ -670,6 +1171,7 @@ mod tests {
     use tokio::time::sleep;
 
     use super::*;
+    use futures::future::join_all;
     use std::io::ErrorKind as IoErrorKind;
 
     #[tokio::test]
@@ -695,11 +1197,13 @@ mod tests {
                 }),
                 Duration::from_secs(10),
             )),
+            false,
+            Default::default(),
         )
         .await;
         assert_eq!(hosts_updater.hosts.read().await.len(), 3);
         assert_eq!(hosts_updater.hosts_map.len(), 3);
-        assert!(hosts_updater.update_hosts().await);
+        assert!(hosts_updater.update_hosts().await.unwrap());
         assert_eq!(hosts_updater.hosts.read().await.len(), 4);
         assert_eq!(hosts_updater.hosts_map.len(), 4);
         assert!(hosts_updater.hosts_map.contains_async("http://host4").await);
@@ -707,6 +1211,227 @@ mod tests {
         assert!(!hosts_updater.hosts_map.contains_async("http://host3").await);
     }
 
+    #[tokio::test]
+    async fn test_hosts_updater_retries() {
+        env_logger::try_init().ok();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let update_func = |calls: Arc<AtomicUsize>, failures: usize| -> UpdateFn {
+            Box::new(move || {
+                let calls = calls.to_owned();
+                Box::pin(async move {
+                    if calls.fetch_add(1, Relaxed) < failures {
+                        Err(IoError::new(IoErrorKind::Other, "uc is unavailable"))
+                    } else {
+                        Ok(vec!["http://host2".to_owned()])
+                    }
+                })
+            })
+        };
+
+        let hosts_updater = HostsUpdater::new(
+            vec!["http://host1".to_owned()],
+            Some(
+                UpdateOption::new(update_func(calls.to_owned(), 2), Duration::from_secs(10))
+                    .tries(3),
+            ),
+            false,
+            Default::default(),
+        )
+        .await;
+        assert!(hosts_updater.update_hosts().await.unwrap());
+        assert_eq!(calls.load(Relaxed), 3);
+        assert_eq!(
+            *hosts_updater.hosts.read().await,
+            vec!["http://host2".to_owned()]
+        );
+
+        calls.store(0, Relaxed);
+        let hosts_updater = HostsUpdater::new(
+            vec!["http://host1".to_owned()],
+            Some(
+                UpdateOption::new(update_func(calls.to_owned(), 5), Duration::from_secs(10))
+                    .tries(2),
+            ),
+            false,
+            Default::default(),
+        )
+        .await;
+        let err = hosts_updater.update_hosts().await.unwrap_err();
+        assert_eq!(err.to_string(), "uc is unavailable");
+        assert_eq!(calls.load(Relaxed), 2);
+        assert_eq!(
+            *hosts_updater.hosts.read().await,
+            vec!["http://host1".to_owned()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hosts_updater_coalesces_concurrent_updates() {
+        env_logger::try_init().ok();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let hosts_updater = {
+            let calls = calls.to_owned();
+            HostsUpdater::new(
+                vec!["http://host1".to_owned()],
+                Some(UpdateOption::new(
+                    Box::new(move || {
+                        let calls = calls.to_owned();
+                        Box::pin(async move {
+                            calls.fetch_add(1, Relaxed);
+                            sleep(Duration::from_millis(100)).await;
+                            Ok(vec!["http://host2".to_owned()])
+                        })
+                    }),
+                    Duration::from_secs(10),
+                )),
+                false,
+                Default::default(),
+            )
+            .await
+        };
+        let results = join_all((0..10).map(|_| hosts_updater.update_hosts())).await;
+        assert!(results.into_iter().all(|result| result.unwrap()));
+        assert_eq!(calls.load(Relaxed), 1);
+
+        assert!(hosts_updater.update_hosts().await.unwrap());
+        assert_eq!(calls.load(Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_hosts_updater_rejects_suspicious_update() {
+        env_logger::try_init().ok();
+
+        let old_hosts = vec![
+            "http://host1".to_owned(),
+            "http://host2".to_owned(),
+            "http://host3".to_owned(),
+            "http://host4".to_owned(),
+        ];
+        let new_hosts = Arc::new(std::sync::Mutex::new(vec!["http://host1".to_owned()]));
+        let hosts_updater = HostsUpdater::new(
+            old_hosts.to_owned(),
+            Some(
+                UpdateOption::new(
+                    {
+                        let new_hosts = new_hosts.to_owned();
+                        Box::new(move || {
+                            let new_hosts = new_hosts.lock().unwrap().to_owned();
+                            Box::pin(async move { Ok(new_hosts) })
+                        })
+                    },
+                    Duration::from_secs(10),
+                )
+                .min_hosts(2)
+                .max_change_ratio(0.5),
+            ),
+            false,
+            Default::default(),
+        )
+        .await;
+        assert!(!hosts_updater.update_hosts().await.unwrap());
+        assert_eq!(*hosts_updater.hosts.read().await, old_hosts);
+
+        *new_hosts.lock().unwrap() = vec!["http://host1".to_owned(), "http://host5".to_owned()];
+        assert!(!hosts_updater.update_hosts().await.unwrap());
+        assert_eq!(*hosts_updater.hosts.read().await, old_hosts);
+
+        *new_hosts.lock().unwrap() = vec![
+            "http://host1".to_owned(),
+            "http://host2".to_owned(),
+            "http://host5".to_owned(),
+        ];
+        assert!(hosts_updater.update_hosts().await.unwrap());
+        assert_eq!(hosts_updater.hosts.read().await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_hosts_updater_rejects_drastic_shrink_by_default() {
+        env_logger::try_init().ok();
+
+        let old_hosts = vec![
+            "http://host1".to_owned(),
+            "http://host2".to_owned(),
+            "http://host3".to_owned(),
+            "http://host4".to_owned(),
+        ];
+        let new_hosts = Arc::new(std::sync::Mutex::new(vec!["http://host1".to_owned()]));
+        let hosts_updater = HostsUpdater::new(
+            old_hosts.to_owned(),
+            Some(UpdateOption::new(
+                {
+                    let new_hosts = new_hosts.to_owned();
+                    Box::new(move || {
+                        let new_hosts = new_hosts.lock().unwrap().to_owned();
+                        Box::pin(async move { Ok(new_hosts) })
+                    })
+                },
+                Duration::from_secs(10),
+            )),
+            false,
+            Default::default(),
+        )
+        .await;
+        assert!(!hosts_updater.update_hosts().await.unwrap());
+        assert_eq!(*hosts_updater.hosts.read().await, old_hosts);
+
+        *new_hosts.lock().unwrap() = vec!["http://host1".to_owned(), "http://host2".to_owned()];
+        assert!(hosts_updater.update_hosts().await.unwrap());
+        assert_eq!(hosts_updater.hosts.read().await.len(), 2);
+    }
+
+    #[test]
+    fn test_timeout_power_for_latency() {
+        let host_punisher = HostPunisher {
+            should_punish_func: None,
+            punish_duration: Duration::from_secs(60),
+            base_timeout: Duration::from_millis(100),
+            max_timeout: DEFAULT_MAX_TIMEOUT,
+            host_base_timeouts: Default::default(),
+            max_punished_times: 5,
+            max_punished_hosts_percent: 50,
+            connection_failure_kinds: ConnectionFailureKind::ALL.to_vec(),
+        };
+        host_punisher
+            .host_base_timeouts
+            .insert("http://far".to_owned(), Duration::from_millis(1000))
+            .unwrap();
+        let host = "http://near";
+        assert_eq!(
+            host_punisher.timeout_power_for(host, Duration::from_millis(0)),
+            0
+        );
+        assert_eq!(
+            host_punisher.timeout_power_for(host, Duration::from_millis(50)),
+            0
+        );
+        assert_eq!(
+            host_punisher.timeout_power_for(host, Duration::from_millis(60)),
+            1
+        );
+        assert_eq!(
+            host_punisher.timeout_power_for(host, Duration::from_millis(300)),
+            3
+        );
+        assert_eq!(
+            host_punisher.timeout_power_for(host, Duration::from_secs(60)),
+            MAX_SEEDED_TIMEOUT_POWER
+        );
+        assert_eq!(
+            host_punisher.timeout_power_for("http://far", Duration::from_millis(300)),
+            0
+        );
+        assert_eq!(
+            host_punisher.timeout_power_for("http://far", Duration::from_secs(3)),
+            3
+        );
+        assert_eq!(
+            host_punisher.timeout("http://far", &Default::default()),
+            Duration::from_millis(1000)
+        );
+    }
+
     #[tokio::test]
     async fn test_hosts_update() {
         env_logger::try_init().ok();
@@ -761,6 +1486,8 @@ mod tests {
                 }),
                 Duration::from_millis(500),
             )),
+            false,
+            Default::default(),
         )
         .await;
         HostsUpdater::next_index(&hosts_updater);
@@ -1222,4 +1949,169 @@ mod tests {
             14
         );
     }
+
+    #[tokio::test]
+    async fn test_hosts_selector_steering() {
+        env_logger::try_init().ok();
+
+        let host_selector = HostSelectorBuilder::new(vec![
+            "http://host1".to_owned(),
+            "http://host2".to_owned(),
+            "http://host3".to_owned(),
+        ])
+        .build()
+        .await;
+        let host_selector = &host_selector;
+        let select_host = move || async move {
+            host_selector
+                .select_host(&Default::default())
+                .await
+                .unwrap()
+                .host
+        };
+
+        host_selector
+            .ban_host("http://host2", Duration::from_millis(500))
+            .await;
+        for _ in 0..4 {
+            assert_ne!(select_host().await, "http://host2");
+        }
+
+        host_selector
+            .pin_hosts(vec!["http://host2".to_owned(), "http://host3".to_owned()])
+            .await;
+        for _ in 0..4 {
+            assert_eq!(select_host().await, "http://host3");
+        }
+
+        host_selector
+            .ban_host("http://host3", Duration::from_secs(60))
+            .await;
+        let selected = [
+            select_host().await,
+            select_host().await,
+            select_host().await,
+        ];
+        assert!(selected.contains(&"http://host1".to_owned()));
+        assert!(selected.contains(&"http://host3".to_owned()));
+
+        host_selector
+            .ban_host("http://host3", Duration::from_secs(0))
+            .await;
+        sleep(Duration::from_millis(500)).await;
+        let mut selected = HashSet::new();
+        for _ in 0..4 {
+            selected.insert(select_host().await);
+        }
+        assert_eq!(selected.len(), 2);
+        assert!(!selected.contains("http://host1"));
+
+        host_selector.pin_hosts(Vec::new()).await;
+        let mut selected = HashSet::new();
+        for _ in 0..3 {
+            selected.insert(select_host().await);
+        }
+        assert_eq!(selected.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_hosts_selector_marks_connection_failure_on_punish() {
+        env_logger::try_init().ok();
+
+        let host_selector =
+            HostSelectorBuilder::new(vec!["http://host1".to_owned(), "http://host2".to_owned()])
+                .connection_failure_kinds(vec![ConnectionFailureKind::Connect])
+                .build()
+                .await;
+        let failed_to_connect = |host: &'static str| {
+            host_selector
+                .hosts_updater
+                .hosts_map
+                .read(host, |_, punished_info| punished_info.failed_to_connect)
+        };
+
+        host_selector
+            .punish_without_dotter(
+                "http://host1",
+                &IoError::new(IoErrorKind::ConnectionRefused, "connection refused"),
+            )
+            .await;
+        host_selector
+            .punish_without_dotter(
+                "http://host2",
+                &IoError::new(IoErrorKind::ConnectionReset, "connection reset"),
+            )
+            .await;
+        assert_eq!(failed_to_connect("http://host1"), Some(true));
+        assert_eq!(failed_to_connect("http://host2"), Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_hosts_selector_max_timeout() {
+        env_logger::try_init().ok();
+
+        let host_selector = HostSelectorBuilder::new(vec!["http://host1".to_owned()])
+            .base_timeout(Duration::from_millis(100))
+            .max_timeout(Duration::from_millis(250))
+            .build()
+            .await;
+        host_selector
+            .increase_timeout_power_by("http://host1", 0)
+            .await;
+        assert_eq!(
+            host_selector
+                .select_host(&Default::default())
+                .await
+                .unwrap()
+                .timeout,
+            Duration::from_millis(200)
+        );
+        host_selector
+            .increase_timeout_power_by("http://host1", 1)
+            .await;
+        assert_eq!(
+            host_selector
+                .select_host(&Default::default())
+                .await
+                .unwrap()
+                .timeout,
+            Duration::from_millis(250)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hosts_selector_normalize_hosts() {
+        env_logger::try_init().ok();
+
+        let host_selector = HostSelectorBuilder::new(vec![
+            "http://host1/".to_owned(),
+            "HTTPS://host2:8443".to_owned(),
+            "ftp://host3".to_owned(),
+        ])
+        .host_base_timeouts(vec![(
+            "http://host1/".to_owned(),
+            Duration::from_millis(100),
+        )])
+        .force_https(true)
+        .build()
+        .await;
+        let mut hosts = host_selector.all_hosts().await;
+        hosts.sort();
+        assert_eq!(
+            hosts,
+            vec!["https://host1".to_owned(), "https://host2:8443".to_owned()]
+        );
+        assert_eq!(
+            host_selector.host_punisher.base_timeout_of("https://host1"),
+            Duration::from_millis(100)
+        );
+
+        host_selector
+            .set_hosts(vec!["http://host4//".to_owned(), "http://".to_owned()])
+            .await;
+        assert_eq!(
+            host_selector.all_hosts().await,
+            vec!["https://host4".to_owned()]
+        );
+    }
 }
@@ -0,0 +1,186 @@
+use super::cache_dir::cache_dir_path_of;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_slice as json_from_slice, to_vec as json_to_vec};
+use std::{
+    collections::HashMap,
+    io::Result as IoResult,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    fs::{read, rename as rename_file, write},
+    spawn,
+    sync::{Mutex, OnceCell},
+};
+
+pub(super) const HOST_STATS_FILE_NAME: &str = "host-stats.json";
+pub(super) const HOST_STATS_TEMPFILE_NAME: &str = "host-stats.tmp.json";
+
+const EWMA_ALPHA: f64 = 0.2;
+const HALF_LIFE: Duration = Duration::from_secs(60 * 60);
+const MIN_WEIGHT: f64 = 0.1;
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+static HOST_STATS_STORE: OnceCell<Mutex<HostStatsStore>> = OnceCell::const_new();
+
+/// 单个域名请求成功率和延迟的指数加权移动平均值
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(super) struct HostStats {
+    success_rate: f64,
+    latency_ms: f64,
+    updated_at_s: u64,
+}
+
+impl HostStats {
+    fn new(successful: bool, elapsed: Duration, now_s: u64) -> Self {
+        Self {
+            success_rate: if successful { 1.0 } else { 0.0 },
+            latency_ms: elapsed.as_millis() as f64,
+            updated_at_s: now_s,
+        }
+    }
+
+    pub(super) fn success_rate(&self) -> f64 {
+        self.success_rate
+    }
+
+    pub(super) fn latency(&self) -> Duration {
+        Duration::from_millis(self.latency_ms as u64)
+    }
+
+    fn record(&mut self, successful: bool, elapsed: Duration, now_s: u64) {
+        let success = if successful { 1.0 } else { 0.0 };
+        self.success_rate += EWMA_ALPHA * (success - self.success_rate);
+        self.latency_ms += EWMA_ALPHA * (elapsed.as_millis() as f64 - self.latency_ms);
+        self.updated_at_s = now_s;
+    }
+
+    /// 按统计数据的时长衰减，成功率向 1 回归，延迟向 0 回归，权重过低时返回 None
+    fn decayed(&self, now_s: u64) -> Option<Self> {
+        let age_s = now_s.saturating_sub(self.updated_at_s) as f64;
+        let weight = 0.5f64.powf(age_s / HALF_LIFE.as_secs() as f64);
+        if weight < MIN_WEIGHT {
+            return None;
+        }
+        Some(Self {
+            success_rate: 1.0 - (1.0 - self.success_rate) * weight,
+            latency_ms: self.latency_ms * weight,
+            updated_at_s: self.updated_at_s,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct HostStatsStore {
+    persisted: HashMap<String, HostStats>,
+    current: HashMap<String, HostStats>,
+    saved_at: Option<Instant>,
+}
+
+async fn host_stats_store() -> &'static Mutex<HostStatsStore> {
+    HOST_STATS_STORE
+        .get_or_init(|| async {
+            let persisted = load_host_stats().await.unwrap_or_else(|err| {
+                info!("No persisted host stats is loaded: {}", err);
+                Default::default()
+            });
+            Mutex::new(HostStatsStore {
+                current: persisted.to_owned(),
+                persisted,
+                saved_at: None,
+            })
+        })
+        .await
+}
+
+/// 获取进程启动时从缓存目录加载的域名统计数据，已按时长衰减
+pub(super) async fn persisted_host_stats(host: &str) -> Option<HostStats> {
+    host_stats_store()
+        .await
+        .lock()
+        .await
+        .persisted
+        .get(host)
+        .copied()
+}
+
+/// 记录一次请求的结果，并定期将统计数据保存到缓存目录
+pub(super) async fn record_host_stats(host: &str, successful: bool, elapsed: Duration) {
+    let now_s = unix_secs();
+    let mut store = host_stats_store().await.lock().await;
+    if let Some(stats) = store.current.get_mut(host) {
+        stats.record(successful, elapsed, now_s);
+    } else {
+        store
+            .current
+            .insert(host.to_owned(), HostStats::new(successful, elapsed, now_s));
+    }
+
+    let should_save = store
+        .saved_at
+        .map_or(true, |saved_at| saved_at.elapsed() >= SAVE_INTERVAL);
+    if should_save {
+        store.saved_at = Some(Instant::now());
+        let content = json_to_vec(&store.current);
+        drop(store);
+        spawn(async move {
+            match content {
+                Ok(content) => {
+                    if let Err(err) = save_host_stats(content).await {
+                        warn!("Failed to save host stats: {}", err);
+                    }
+                }
+                Err(err) => warn!("Failed to serialize host stats: {}", err),
+            }
+        });
+    }
+}
+
+async fn load_host_stats() -> IoResult<HashMap<String, HostStats>> {
+    let content = read(cache_dir_path_of(HOST_STATS_FILE_NAME).await?).await?;
+    let stats: HashMap<String, HostStats> = json_from_slice(&content)?;
+    let now_s = unix_secs();
+    Ok(stats
+        .into_iter()
+        .filter_map(|(host, stats)| stats.decayed(now_s).map(|stats| (host, stats)))
+        .collect())
+}
+
+async fn save_host_stats(content: Vec<u8>) -> IoResult<()> {
+    let tempfile_path = cache_dir_path_of(HOST_STATS_TEMPFILE_NAME).await?;
+    write(&tempfile_path, content).await?;
+    rename_file(
+        &tempfile_path,
+        cache_dir_path_of(HOST_STATS_FILE_NAME).await?,
+    )
+    .await
+}
+
+fn unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_stats_ewma_and_decay() {
+        let mut stats = HostStats::new(true, Duration::from_millis(100), 1000);
+        stats.record(false, Duration::from_millis(600), 1000);
+        assert!((stats.success_rate() - 0.8).abs() < 1e-9);
+        assert_eq!(stats.latency(), Duration::from_millis(200));
+
+        let decayed = stats.decayed(1000).unwrap();
+        assert_eq!(decayed, stats);
+
+        let decayed = stats.decayed(1000 + HALF_LIFE.as_secs()).unwrap();
+        assert!((decayed.success_rate() - 0.9).abs() < 1e-9);
+        assert_eq!(decayed.latency(), Duration::from_millis(100));
+
+        assert!(stats.decayed(1000 + HALF_LIFE.as_secs() * 4).is_none());
+    }
+}
@@ -0,0 +1,293 @@
+use super::super::base::download::{
+    destination_busy_error, destination_lock_delay, destination_lock_path, sanitize_file_name,
+    ObjectMetadata,
+};
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+use super::uring::UringWriter;
+use super::{
+    retrier::AsyncRangeReaderWithRangeReader,
+    sharding::shard_state_path,
+    streaming::download_to_writer,
+    temp_files::{write_file_atomically, PartFile},
+};
+use fd_lock::RwLock as FdRwLock;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::OsString,
+    fs::Metadata,
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    fs::{metadata, read as read_file, remove_file, OpenOptions},
+    time::sleep,
+};
+
+/// 完整性元数据，记录目标文件对应的对象版本，保存在目标文件旁的 `.qniu.meta` 文件中
+#[derive(Debug, Serialize, Deserialize)]
+struct IntegrityMetadata {
+    etag: String,
+    size: u64,
+    /// 下载完成的时间，为 UNIX 时间戳，单位为秒
+    downloaded_at: u64,
+    /// 写入完成时目标文件的修改时间，用于确认目标文件就是元数据描述的文件
+    #[serde(default)]
+    modified_at: Option<Duration>,
+    /// 已经写入文件的区域，每个区域为起始位置和长度
+    ranges: Vec<(u64, u64)>,
+}
+
+impl IntegrityMetadata {
+    /// 已经写入文件的区域是否覆盖了整个对象
+    fn is_complete(&self) -> bool {
+        let mut ranges = self.ranges.to_owned();
+        ranges.sort_unstable();
+        let mut covered = 0;
+        for (pos, len) in ranges {
+            if pos > covered {
+                return false;
+            }
+            covered = covered.max(pos.saturating_add(len));
+        }
+        covered >= self.size
+    }
+}
+
+fn integrity_metadata_path(path: &Path) -> PathBuf {
+    let mut metadata_path = OsString::from(path.as_os_str());
+    metadata_path.push(".qniu.meta");
+    metadata_path.into()
+}
+
+fn modified_at(metadata: &Metadata) -> Option<Duration> {
+    metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()
+}
+
+/// 下载对象到指定目录中，返回目标文件路径和写入的字节数
+///
+/// 文件名依次取自 Content-Disposition 响应头、`attname` 和对象名称的最后一级路径，
+/// 并去除其中的路径，无法得到合法的文件名时返回 [`IoErrorKind::InvalidInput`] 错误
+pub(super) async fn download_to_dir(
+    reader: &AsyncRangeReaderWithRangeReader,
+    key: &str,
+    dir: &Path,
+    lock_timeout: Option<Duration>,
+) -> IoResult<(PathBuf, u64)> {
+    let metadata = reader.stat(key).await?;
+    let file_name = match metadata.attachment_file_name() {
+        Some(file_name) => Some(file_name),
+        None => reader
+            .attname()
+            .await
+            .and_then(|attname| sanitize_file_name(&attname)),
+    }
+    .or_else(|| sanitize_file_name(key))
+    .ok_or_else(|| {
+        IoError::new(
+            IoErrorKind::InvalidInput,
+            format!("Cannot determine a file name to download {:?} into", key),
+        )
+    })?;
+    let path = dir.join(file_name);
+    let size = lock_and_download_to_path(reader, key, &path, lock_timeout, Some(&metadata)).await?;
+    Ok((path, size))
+}
+
+/// 下载对象到指定路径的文件中，写入期间对目标文件旁的 `.lock` 文件加排他的建议锁
+///
+/// 数据以有界缓冲区流式写入目标文件旁的 `.part` 临时文件，下载成功后再原子地重命名为目标文件，
+/// 因此下载失败时目标文件保持原样。目标文件在重命名时被替换，所以锁加在不会被替换的 `.lock` 文件上。
+/// 下载成功后目标文件旁的分片下载断点续传状态文件已经失效，将被一并删除
+pub(super) async fn download_to_path(
+    reader: &AsyncRangeReaderWithRangeReader,
+    key: &str,
+    path: &Path,
+    lock_timeout: Option<Duration>,
+) -> IoResult<u64> {
+    lock_and_download_to_path(reader, key, path, lock_timeout, None).await
+}
+
+/// `metadata` 为已经获取的对象元信息，用于设置目标文件的修改时间，为 None 时在需要时获取
+async fn lock_and_download_to_path(
+    reader: &AsyncRangeReaderWithRangeReader,
+    key: &str,
+    path: &Path,
+    lock_timeout: Option<Duration>,
+    metadata: Option<&ObjectMetadata>,
+) -> IoResult<u64> {
+    let lock_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(destination_lock_path(path))
+        .await?;
+    let mut lock_file = FdRwLock::new(lock_file);
+    let deadline = lock_timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        match lock_file.try_write() {
+            Ok(_guard) => {
+                let result = if reader.integrity_metadata() {
+                    download_to_file_with_metadata(reader, key, path, metadata).await
+                } else {
+                    download_to_file(reader, key, path, metadata).await
+                };
+                if result.is_ok() {
+                    remove_file(shard_state_path(path)).await.ok();
+                }
+                return result;
+            }
+            Err(err) if err.kind() == IoErrorKind::WouldBlock => {
+                match destination_lock_delay(deadline) {
+                    Some(delay) => sleep(delay).await,
+                    None => return Err(destination_busy_error(path)),
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+async fn download_to_file(
+    reader: &AsyncRangeReaderWithRangeReader,
+    key: &str,
+    path: &Path,
+    metadata: Option<&ObjectMetadata>,
+) -> IoResult<u64> {
+    let mut part = PartFile::create(path).await?;
+    let size = download_to_part_file(reader, key, &mut part).await?;
+    preserve_last_modified(reader, key, &mut part, metadata).await?;
+    part.persist(path).await?;
+    Ok(size)
+}
+
+/// 下载对象到临时文件中
+///
+/// 启用 `io-uring` 功能时，在 Linux 上通过 io_uring 并发写入临时文件，内核不支持时使用 tokio 的文件写入
+async fn download_to_part_file(
+    reader: &AsyncRangeReaderWithRangeReader,
+    key: &str,
+    part: &mut PartFile,
+) -> IoResult<u64> {
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    {
+        let file = part.file_mut().try_clone().await?.into_std().await;
+        if let Some(mut writer) = UringWriter::new(file) {
+            return download_to_writer(reader, key, &mut writer).await;
+        }
+    }
+    download_to_writer(reader, key, part.file_mut()).await
+}
+
+/// 开启保留修改时间时，将临时文件的修改时间设置为对象的最后修改时间
+async fn preserve_last_modified(
+    reader: &AsyncRangeReaderWithRangeReader,
+    key: &str,
+    part: &mut PartFile,
+    metadata: Option<&ObjectMetadata>,
+) -> IoResult<()> {
+    if !reader.preserve_last_modified() {
+        return Ok(());
+    }
+    let last_modified = match metadata {
+        Some(metadata) => metadata.last_modified(),
+        None => reader.stat(key).await?.last_modified(),
+    };
+    if let Some(last_modified) = last_modified {
+        part.set_modified(last_modified).await?;
+    }
+    Ok(())
+}
+
+/// 下载对象到目标文件中，并在目标文件被替换前写入完整性元数据
+///
+/// 元数据记录的 Etag 和尺寸与对象当前的一致，且目标文件的尺寸和修改时间未被修改时，信任已有的文件并跳过下载。
+/// 下载在会话中进行，以保证元数据记录的 Etag 与写入的数据来自同一版本的对象。
+/// 元数据通过临时文件原子地写入，且记录了临时文件的修改时间，
+/// 因此在元数据写入后、目标文件被替换前中断时，原有的目标文件不会被误认为是完整的。
+/// 开启保留修改时间时，记录的是设置为对象最后修改时间之后的修改时间
+async fn download_to_file_with_metadata(
+    reader: &AsyncRangeReaderWithRangeReader,
+    key: &str,
+    path: &Path,
+    metadata: Option<&ObjectMetadata>,
+) -> IoResult<u64> {
+    let reader = reader.to_owned().with_session();
+    let metadata_path = integrity_metadata_path(path);
+    if let Some(size) = trusted_size(&reader, key, path, &metadata_path).await {
+        info!(
+            "{:?} is unchanged according to {:?}, skip downloading",
+            path, metadata_path
+        );
+        return Ok(size);
+    }
+    match remove_file(&metadata_path).await {
+        Err(err) if err.kind() != IoErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+    let mut part = PartFile::create(path).await?;
+    let size = download_to_part_file(&reader, key, &mut part).await?;
+    preserve_last_modified(&reader, key, &mut part, metadata).await?;
+    if let Some(etag) = reader.session_etag() {
+        let metadata = IntegrityMetadata {
+            etag,
+            size,
+            downloaded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            modified_at: modified_at(&part.file_mut().metadata().await?),
+            ranges: vec![(0, size)],
+        };
+        write_file_atomically(&metadata_path, &serde_json::to_vec(&metadata)?).await?;
+    }
+    if let Err(err) = part.persist(path).await {
+        remove_file(&metadata_path).await.ok();
+        return Err(err);
+    }
+    Ok(size)
+}
+
+async fn trusted_size(
+    reader: &AsyncRangeReaderWithRangeReader,
+    key: &str,
+    path: &Path,
+    metadata_path: &Path,
+) -> Option<u64> {
+    let integrity_metadata: IntegrityMetadata =
+        serde_json::from_slice(&read_file(metadata_path).await.ok()?).ok()?;
+    let file_metadata = metadata(path).await.ok()?;
+    if file_metadata.len() != integrity_metadata.size
+        || modified_at(&file_metadata) != integrity_metadata.modified_at
+        || !integrity_metadata.is_complete()
+    {
+        return None;
+    }
+    let size = reader.file_size(key).await.ok()?;
+    if size == integrity_metadata.size && reader.session_etag()? == integrity_metadata.etag {
+        Some(size)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integrity_metadata_is_complete() {
+        let metadata = |size, ranges| IntegrityMetadata {
+            etag: "\"etag\"".to_owned(),
+            size,
+            downloaded_at: 0,
+            modified_at: None,
+            ranges,
+        };
+        assert!(metadata(10, vec![(0, 10)]).is_complete());
+        assert!(metadata(10, vec![(5, 5), (0, 6)]).is_complete());
+        assert!(metadata(0, vec![]).is_complete());
+        assert!(!metadata(10, vec![(0, 4), (5, 5)]).is_complete());
+        assert!(!metadata(10, vec![(0, 9)]).is_complete());
+    }
+}
@@ -1,7 +1,45 @@
+mod archive;
+pub use archive::{ArchiveFormat, ArchiveProgress, AsyncArchiveDownloader};
+
+mod audit;
+pub use audit::{set_audit_log, AuditLogConfig};
+
+mod cache_control;
 mod cache_dir;
+
+mod diagnose;
+pub use diagnose::{
+    diagnose, diagnose_config, CheckOutcome, DiagnosticReport, HostReport, HostRole,
+};
+
+mod fetcher;
+pub use fetcher::AsyncMultiFetcher;
+
+mod head_cache;
+mod health;
+pub use health::{health_snapshot, HealthSnapshot, HostHealth};
+pub(crate) use health::{
+    record_dot_backlog_cleared, record_dot_records_flushed, record_dot_upload,
+};
+
 mod host_selector;
+mod host_stats;
+mod integrity;
+mod multipart;
+mod negative_cache;
+#[cfg(feature = "fuzzing")]
+pub use multipart::{MultipartError, MultipartParser};
 mod query;
 
+mod overrides;
+pub use overrides::ReadOverrides;
+
+mod preload;
+pub use preload::{PreloadProgress, RangeSpec};
+
+mod stats;
+pub use stats::ReaderStats;
+
 mod req_id;
 pub(crate) use req_id::{get_req_id, REQUEST_ID_HEADER};
 pub use req_id::{set_download_start_time, total_download_duration};
@@ -13,9 +51,49 @@ pub use dot::{
 };
 
 mod download;
+pub(crate) use download::sort_range_parts;
 pub use download::{sign_download_url_with_deadline, sign_download_url_with_lifetime, RangePart};
 
+mod egress;
+pub use egress::{
+    egress_budget, remaining_egress_budget, set_egress_budget, EgressBudget,
+    EgressBudgetExceededError, EgressBudgetPolicy, EgressBudgetRemaining, EgressWindow,
+};
+
+#[cfg(feature = "opendal")]
+mod opendal_adapter;
+#[cfg(feature = "opendal")]
+pub use opendal_adapter::QiniuAccessor;
+
+mod file;
+pub use file::AsyncObjectFile;
+
+mod reader;
+pub use reader::AsyncRangeReader;
+
 mod retrier;
+mod session;
+
+mod sharding;
+pub use sharding::{FileAllocation, ShardedDownloadOptions, ShardedDownloadProgress};
+
+mod streaming;
+
+mod support_bundle;
+pub use support_bundle::{export_support_bundle, export_support_bundle_for_config};
 
 mod sync;
 pub(crate) use sync::{RangeReader, RangeReaderBuilder, RangeReaderHandle};
+pub use sync::{SyncRequestError, SyncRuntimeLoad};
+
+mod temp_files;
+pub use temp_files::{remove_stale_cache_files, remove_stale_download_files};
+
+mod trace;
+pub use trace::{set_trace_mode, TraceMode};
+
+mod transform;
+pub use transform::{StreamTransformer, TransformerChain};
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
@@ -0,0 +1,378 @@
+use super::download::RangePart;
+use std::{
+    convert::TryFrom,
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+};
+use thiserror::Error;
+
+const MAX_BOUNDARY_SIZE: usize = 70;
+const MAX_HEADERS_SIZE: usize = 8 << 10;
+
+/// 解析多范围响应体时发生的错误
+///
+/// 转换为 IO 错误后可以重试，且会惩罚返回该响应体的域名
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MultipartError {
+    /// 分隔符为空或过长
+    #[error("Invalid multipart boundary")]
+    InvalidBoundary,
+    /// 分隔符后出现了非法的字符
+    #[error("Invalid data after multipart boundary")]
+    InvalidDelimiter,
+    /// 分段头部过长
+    #[error("Multipart headers are too large")]
+    HeadersTooLarge,
+    /// 分段头部格式错误
+    #[error("Invalid multipart header: {0}")]
+    InvalidHeader(String),
+    /// 分段头部中没有 Content-Range
+    #[error("Content-Range is missing in multipart headers")]
+    MissingContentRange,
+    /// 分段头部中的 Content-Range 格式错误
+    #[error("Invalid Content-Range in multipart headers: {0}")]
+    InvalidContentRange(String),
+    /// 分段数据长度与 Content-Range 不一致
+    #[error("Multipart body size mismatch: expected {expected}, actual {actual}")]
+    BodySizeMismatch {
+        /// Content-Range 中声明的长度
+        expected: u64,
+        /// 实际的数据长度
+        actual: u64,
+    },
+    /// 响应体在结束分隔符之前结束
+    #[error("Unexpected end of multipart body")]
+    UnexpectedEof,
+}
+
+impl From<MultipartError> for IoError {
+    #[inline]
+    fn from(err: MultipartError) -> Self {
+        IoError::new(IoErrorKind::InvalidInput, err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Preamble,
+    Delimiter,
+    Headers,
+    Body { from: u64, len: u64 },
+    Done,
+}
+
+/// 流式的多范围响应体解析器
+///
+/// 按任意方式切分的数据依次调用 `feed`，最后调用 `finish` 确认响应体完整，对任何输入都不会 panic
+#[derive(Debug)]
+pub struct MultipartParser {
+    delimiter: Vec<u8>,
+    buffer: Vec<u8>,
+    searched: usize,
+    state: State,
+}
+
+impl MultipartParser {
+    /// 根据 Content-Type 中的分隔符创建解析器
+    pub fn new(boundary: &str) -> Result<Self, MultipartError> {
+        if boundary.is_empty() || boundary.len() > MAX_BOUNDARY_SIZE {
+            return Err(MultipartError::InvalidBoundary);
+        }
+        let mut delimiter = b"\r\n--".to_vec();
+        delimiter.extend_from_slice(boundary.as_bytes());
+        Ok(Self {
+            delimiter,
+            // 响应体开头的分隔符前没有换行，预先填充以统一处理
+            buffer: b"\r\n".to_vec(),
+            searched: 0,
+            state: State::Preamble,
+        })
+    }
+
+    /// 输入一段数据，返回已经解析完成的分段
+    pub fn feed(&mut self, data: &[u8]) -> Result<Vec<RangePart>, MultipartError> {
+        if self.state == State::Done {
+            return Ok(Vec::new());
+        }
+        self.buffer.extend_from_slice(data);
+        let mut parts = Vec::new();
+        while self.step(&mut parts)? {}
+        Ok(parts)
+    }
+
+    /// 确认已经读到结束分隔符
+    pub fn finish(&self) -> Result<(), MultipartError> {
+        if self.state == State::Done {
+            Ok(())
+        } else {
+            Err(MultipartError::UnexpectedEof)
+        }
+    }
+
+    /// 推进一次状态，数据不足时返回 false
+    fn step(&mut self, parts: &mut Vec<RangePart>) -> Result<bool, MultipartError> {
+        match self.state {
+            State::Preamble => match self.find_delimiter() {
+                Some(pos) => {
+                    self.consume(pos + self.delimiter.len());
+                    self.state = State::Delimiter;
+                    Ok(true)
+                }
+                None => {
+                    let keep = self.delimiter.len() - 1;
+                    if self.buffer.len() > keep {
+                        self.consume(self.buffer.len() - keep);
+                    }
+                    Ok(false)
+                }
+            },
+            State::Delimiter => {
+                if self.buffer.len() < 2 {
+                    return Ok(false);
+                }
+                if self.buffer.starts_with(b"--") {
+                    self.buffer.clear();
+                    self.state = State::Done;
+                    return Ok(false);
+                }
+                let line_end = match find(&self.buffer, b"\r\n", 0) {
+                    Some(line_end) => line_end,
+                    None if self.buffer.len() > MAX_HEADERS_SIZE => {
+                        return Err(MultipartError::InvalidDelimiter)
+                    }
+                    None => return Ok(false),
+                };
+                if !self.buffer[..line_end]
+                    .iter()
+                    .all(|&b| b == b' ' || b == b'\t')
+                {
+                    return Err(MultipartError::InvalidDelimiter);
+                }
+                self.consume(line_end + 2);
+                self.state = State::Headers;
+                Ok(true)
+            }
+            State::Headers => {
+                let (headers_end, body_start) = if self.buffer.starts_with(b"\r\n") {
+                    (0, 2)
+                } else {
+                    match find(&self.buffer, b"\r\n\r\n", 0) {
+                        Some(pos) => (pos, pos + 4),
+                        None if self.buffer.len() > MAX_HEADERS_SIZE => {
+                            return Err(MultipartError::HeadersTooLarge)
+                        }
+                        None => return Ok(false),
+                    }
+                };
+                if headers_end > MAX_HEADERS_SIZE {
+                    return Err(MultipartError::HeadersTooLarge);
+                }
+                let (from, len) = parse_headers(&self.buffer[..headers_end])?;
+                self.consume(body_start);
+                self.state = State::Body { from, len };
+                Ok(true)
+            }
+            State::Body { from, len } => {
+                // 分段数据可能包含分隔符，因此按 Content-Range 声明的长度截取数据，再要求紧随其后的是分隔符
+                let end = match usize::try_from(len)
+                    .ok()
+                    .and_then(|len| len.checked_add(self.delimiter.len()))
+                {
+                    Some(end) if end <= self.buffer.len() => end,
+                    _ => return Ok(false),
+                };
+                let pos = end - self.delimiter.len();
+                if self.buffer[pos..end] != self.delimiter[..] {
+                    return Err(MultipartError::BodySizeMismatch {
+                        expected: len,
+                        actual: find(&self.buffer, &self.delimiter, 0).unwrap_or(self.buffer.len())
+                            as u64,
+                    });
+                }
+                let data = self.buffer[..pos].to_vec();
+                self.consume(end);
+                self.state = State::Delimiter;
+                parts.push(RangePart {
+                    data,
+                    range: (from, len),
+                    requested: (from, len),
+                });
+                Ok(true)
+            }
+            State::Done => Ok(false),
+        }
+    }
+
+    /// 从上次搜索的位置继续查找分隔符，避免重复扫描已经搜索过的数据
+    fn find_delimiter(&mut self) -> Option<usize> {
+        let start = self
+            .searched
+            .saturating_sub(self.delimiter.len().saturating_sub(1));
+        let found = find(&self.buffer, &self.delimiter, start);
+        self.searched = if found.is_some() {
+            0
+        } else {
+            self.buffer.len()
+        };
+        found
+    }
+
+    fn consume(&mut self, size: usize) {
+        self.buffer.drain(..size.min(self.buffer.len()));
+        self.searched = 0;
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8], start: usize) -> Option<usize> {
+    if needle.is_empty() || start >= haystack.len() {
+        return None;
+    }
+    haystack[start..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|pos| pos + start)
+}
+
+fn parse_headers(headers: &[u8]) -> Result<(u64, u64), MultipartError> {
+    let headers = std::str::from_utf8(headers)
+        .map_err(|_| MultipartError::InvalidHeader(String::from_utf8_lossy(headers).into()))?;
+    let mut content_range = None;
+    for line in headers.split("\r\n").filter(|line| !line.is_empty()) {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| MultipartError::InvalidHeader(line.to_owned()))?;
+        if name.trim().eq_ignore_ascii_case("content-range") {
+            content_range = Some(
+                parse_content_range(value)
+                    .ok_or_else(|| MultipartError::InvalidContentRange(value.trim().to_owned()))?,
+            );
+        }
+    }
+    content_range.ok_or(MultipartError::MissingContentRange)
+}
+
+/// 解析 `bytes <from>-<to>/<total>` 格式的 Content-Range，返回开始偏移量和长度
+fn parse_content_range(value: &str) -> Option<(u64, u64)> {
+    let (range, _) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let (from, to) = range.split_once('-')?;
+    let from: u64 = from.trim().parse().ok()?;
+    let to: u64 = to.trim().parse().ok()?;
+    Some((from, to.checked_sub(from)?.checked_add(1)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    const BODY: &[u8] = b"preamble\r\n--abc\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-4/10\r\n\r\n12345\r\n--abc  \r\ncontent-range: bytes 5-9/10\r\n\r\n67890\r\n--abc--\r\nepilogue";
+
+    #[test]
+    fn test_multipart_parser() -> anyhow::Result<()> {
+        for chunk_size in 1..=BODY.len() {
+            let mut parser = MultipartParser::new("abc")?;
+            let mut parts = Vec::new();
+            for chunk in BODY.chunks(chunk_size) {
+                parts.extend(parser.feed(chunk)?);
+            }
+            parser.finish()?;
+            assert_eq!(parts.len(), 2);
+            assert_eq!(parts[0].data, b"12345");
+            assert_eq!(parts[0].range, (0, 5));
+            assert_eq!(parts[1].data, b"67890");
+            assert_eq!(parts[1].range, (5, 5));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_multipart_parser_with_boundary_in_body() -> anyhow::Result<()> {
+        const BODY: &[u8] =
+            b"--abc\r\nContent-Range: bytes 0-12/13\r\n\r\n1\r\n--abc--\r\n2\r\n--abc--\r\n";
+        for chunk_size in 1..=BODY.len() {
+            let mut parser = MultipartParser::new("abc")?;
+            let mut parts = Vec::new();
+            for chunk in BODY.chunks(chunk_size) {
+                parts.extend(parser.feed(chunk)?);
+            }
+            parser.finish()?;
+            assert_eq!(parts.len(), 1);
+            assert_eq!(parts[0].data, b"1\r\n--abc--\r\n2");
+            assert_eq!(parts[0].range, (0, 13));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_multipart_parser_errors() -> anyhow::Result<()> {
+        assert_eq!(
+            MultipartParser::new("").unwrap_err(),
+            MultipartError::InvalidBoundary
+        );
+
+        let cases: &[(&[u8], MultipartError)] = &[
+            (b"--abc", MultipartError::UnexpectedEof),
+            (b"--abcX\r\n", MultipartError::InvalidDelimiter),
+            (
+                b"--abc\r\nContent-Type\r\n\r\n",
+                MultipartError::InvalidHeader("Content-Type".to_owned()),
+            ),
+            (b"--abc\r\n\r\n12345", MultipartError::MissingContentRange),
+            (
+                b"--abc\r\nContent-Range: bytes 9-0/10\r\n\r\n",
+                MultipartError::InvalidContentRange("bytes 9-0/10".to_owned()),
+            ),
+            (
+                b"--abc\r\nContent-Range: bytes 0-18446744073709551615/10\r\n\r\n",
+                MultipartError::InvalidContentRange("bytes 0-18446744073709551615/10".to_owned()),
+            ),
+            (
+                b"--abc\r\nContent-Range: bytes 0-4/10\r\n\r\n123\r\n--abc--",
+                MultipartError::BodySizeMismatch {
+                    expected: 5,
+                    actual: 3,
+                },
+            ),
+            (
+                b"--abc\r\nContent-Range: bytes 0-4/10\r\n\r\n1234567\r\n--abc--",
+                MultipartError::BodySizeMismatch {
+                    expected: 5,
+                    actual: 7,
+                },
+            ),
+        ];
+        for (body, expected) in cases.iter() {
+            let mut parser = MultipartParser::new("abc")?;
+            let err = parser.feed(body).and_then(|_| parser.finish()).unwrap_err();
+            assert_eq!(&err, expected);
+            assert_eq!(IoError::from(err).kind(), IoErrorKind::InvalidInput);
+        }
+
+        let mut parser = MultipartParser::new("abc")?;
+        let mut headers = b"--abc\r\nX-Header: ".to_vec();
+        headers.resize(MAX_HEADERS_SIZE + 16, b'a');
+        assert_eq!(
+            parser.feed(&headers).unwrap_err(),
+            MultipartError::HeadersTooLarge
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_multipart_parser_random_input() {
+        let mut rng = thread_rng();
+        for _ in 0..1000 {
+            let mut body = BODY.to_vec();
+            for _ in 0..rng.gen_range(1..8) {
+                let pos = rng.gen_range(0..body.len());
+                body[pos] = rng.gen();
+            }
+            let mut parser = MultipartParser::new("abc").unwrap();
+            for chunk in body.chunks(rng.gen_range(1..16)) {
+                if parser.feed(chunk).is_err() {
+                    break;
+                }
+            }
+            parser.finish().ok();
+        }
+    }
+}
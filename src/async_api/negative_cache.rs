@@ -0,0 +1,104 @@
+use super::{cache_control::CacheDirectives, health::record_negative_cache_lookup};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+const MAX_ENTRIES: usize = 4096;
+
+/// 记录不存在的对象，在有效期内直接判定为不存在，不再访问服务器
+#[derive(Debug)]
+pub(super) struct NegativeCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, Instant>>,
+}
+
+impl NegativeCache {
+    pub(super) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Default::default(),
+        }
+    }
+
+    /// 对象是否在有效期内被判定为不存在
+    pub(super) fn contains(&self, key: &str) -> bool {
+        let hit = self.contains_at(key, Instant::now());
+        record_negative_cache_lookup(hit);
+        hit
+    }
+
+    /// 记录对象不存在，有效期受响应的缓存指令限制
+    pub(super) fn insert(&self, key: &str, directives: &CacheDirectives) {
+        if let Some(ttl) = directives.limit_ttl(self.ttl) {
+            self.insert_at(key, ttl, Instant::now())
+        }
+    }
+
+    /// 删除对象不存在的记录，如果记录存在则返回 true
+    pub(super) fn invalidate(&self, key: &str) -> bool {
+        self.entries.lock().unwrap().remove(key).is_some()
+    }
+
+    fn contains_at(&self, key: &str, now: Instant) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(&expires_at) if expires_at > now => true,
+            Some(_) => {
+                entries.remove(key);
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn insert_at(&self, key: &str, ttl: Duration, now: Instant) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ENTRIES && !entries.contains_key(key) {
+            entries.retain(|_, &mut expires_at| expires_at > now);
+            if entries.len() >= MAX_ENTRIES {
+                if let Some(oldest) = entries
+                    .iter()
+                    .min_by_key(|(_, &expires_at)| expires_at)
+                    .map(|(key, _)| key.to_owned())
+                {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key.to_owned(), now + ttl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negative_cache() {
+        let cache = NegativeCache::new(Duration::from_secs(10));
+        let now = Instant::now();
+        assert!(!cache.contains_at("file", now));
+
+        cache.insert_at("file", cache.ttl, now);
+        assert!(cache.contains_at("file", now + Duration::from_secs(5)));
+        assert!(!cache.contains_at("file", now + Duration::from_secs(10)));
+        assert!(!cache.invalidate("file"));
+
+        cache.insert_at("file", cache.ttl, now);
+        assert!(cache.invalidate("file"));
+        assert!(!cache.contains_at("file", now));
+
+        for i in 0..MAX_ENTRIES + 1 {
+            cache.insert_at(
+                &i.to_string(),
+                cache.ttl,
+                now + Duration::from_millis(i as u64),
+            );
+        }
+        assert_eq!(cache.entries.lock().unwrap().len(), MAX_ENTRIES);
+        assert!(!cache.contains_at("0", now));
+        assert!(cache.contains_at(&MAX_ENTRIES.to_string(), now));
+    }
+}
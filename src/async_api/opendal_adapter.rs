@@ -0,0 +1,237 @@
+use super::{
+    super::{
+        base::download::RangeReaderBuilder as BaseRangeReaderBuilder,
+        config::{build_range_reader_builder_from_config, Config},
+    },
+    retrier::AsyncRangeReaderWithRangeReader,
+};
+use async_trait::async_trait;
+use hyper::{header::RANGE, HeaderMap, Method, Uri};
+use opendal::{
+    raw::{
+        build_abs_path, normalize_root, oio, Accessor, AccessorInfo, OpPresign, OpRead, OpStat,
+        PresignOperation, PresignedRequest, RpPresign, RpRead, RpStat,
+    },
+    Capability, EntryMode, Error, ErrorKind, Metadata, Result, Scheme,
+};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+
+/// 基于异步范围下载器实现的 OpenDAL Accessor
+///
+/// 支持 read，stat 和 presign 操作，所有请求都会经过本 SDK 的域名选择和重试逻辑。
+/// 可以通过 `opendal::OperatorBuilder::new(accessor).finish()` 创建 OpenDAL Operator
+#[derive(Debug, Clone)]
+pub struct QiniuAccessor {
+    inner: AsyncRangeReaderWithRangeReader,
+    root: String,
+}
+
+impl QiniuAccessor {
+    pub(crate) fn from_base_builder(builder: BaseRangeReaderBuilder, root: &str) -> Self {
+        Self {
+            inner: AsyncRangeReaderWithRangeReader::from_base_builder(builder).0,
+            root: normalize_root(root),
+        }
+    }
+
+    /// 从配置创建 OpenDAL Accessor
+    /// # Arguments
+    ///
+    /// * `config` - 下载配置
+    /// * `root` - 对象名称前缀，OpenDAL 路径将拼接在其后作为对象名称
+    pub fn from_config(config: &Config, root: &str) -> Self {
+        Self::from_base_builder(
+            build_range_reader_builder_from_config(String::new(), config),
+            root,
+        )
+    }
+
+    fn key_of(&self, path: &str) -> String {
+        build_abs_path(&self.root, path)
+    }
+
+    async fn file_size(&self, key: &str) -> Result<u64> {
+        self.inner.file_size(key).await.map_err(to_opendal_error)
+    }
+}
+
+#[async_trait]
+impl Accessor for QiniuAccessor {
+    type Reader = oio::Cursor;
+    type Writer = ();
+    type Lister = ();
+    type BlockingReader = ();
+    type BlockingWriter = ();
+    type BlockingLister = ();
+
+    fn info(&self) -> AccessorInfo {
+        let mut info = AccessorInfo::default();
+        info.set_scheme(Scheme::Custom("qiniu"))
+            .set_root(&self.root)
+            .set_native_capability(Capability {
+                stat: true,
+                read: true,
+                read_with_range: true,
+                presign: true,
+                presign_read: true,
+                presign_stat: true,
+                ..Default::default()
+            });
+        info
+    }
+
+    async fn stat(&self, path: &str, _args: OpStat) -> Result<RpStat> {
+        if path.ends_with('/') {
+            return Ok(RpStat::new(Metadata::new(EntryMode::DIR)));
+        }
+        let size = self.file_size(&self.key_of(path)).await?;
+        Ok(RpStat::new(
+            Metadata::new(EntryMode::FILE).with_content_length(size),
+        ))
+    }
+
+    async fn read(&self, path: &str, args: OpRead) -> Result<(RpRead, Self::Reader)> {
+        let key = self.key_of(path);
+        let range = args.range();
+        let data = match (range.offset(), range.size()) {
+            (None, None) => self.inner.download(&key).await,
+            (None, Some(size)) => self
+                .inner
+                .read_last_bytes(&key, size)
+                .await
+                .map(|(data, _)| data),
+            (Some(offset), Some(size)) => self.inner.read_at(&key, offset, size).await,
+            (Some(offset), None) => {
+                let total_size = self.file_size(&key).await?;
+                if offset >= total_size {
+                    Ok(Vec::new())
+                } else {
+                    self.inner.read_at(&key, offset, total_size - offset).await
+                }
+            }
+        }
+        .map_err(to_opendal_error)?;
+        Ok((
+            RpRead::new().with_size(Some(data.len() as u64)),
+            oio::Cursor::from(data),
+        ))
+    }
+
+    async fn presign(&self, path: &str, args: OpPresign) -> Result<RpPresign> {
+        let (expire, op) = args.into_parts();
+        let (method, range) = match op {
+            PresignOperation::Stat(_) => (Method::HEAD, None),
+            PresignOperation::Read(args) => (Method::GET, Some(args.range())),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "only presign stat and read are supported",
+                ))
+            }
+        };
+        let url = self
+            .inner
+            .sign_download_url(&self.key_of(path), expire)
+            .await
+            .ok_or_else(|| Error::new(ErrorKind::Unexpected, "no available io host to sign"))?;
+        let uri = url.as_str().parse::<Uri>().map_err(|err| {
+            Error::new(ErrorKind::Unexpected, "invalid signed url").set_source(err)
+        })?;
+        let mut headers = HeaderMap::new();
+        if let Some(range) = range.filter(|range| !range.is_full()) {
+            headers.insert(
+                RANGE,
+                range.to_header().parse().map_err(|err| {
+                    Error::new(ErrorKind::Unexpected, "invalid range").set_source(err)
+                })?,
+            );
+        }
+        Ok(RpPresign::new(PresignedRequest::new(method, uri, headers)))
+    }
+}
+
+fn to_opendal_error(err: IoError) -> Error {
+    let kind = match err.kind() {
+        IoErrorKind::NotFound => ErrorKind::NotFound,
+        IoErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+        _ => ErrorKind::Unexpected,
+    };
+    let temporary = !matches!(err.kind(), IoErrorKind::InvalidData | IoErrorKind::NotFound);
+    let err = Error::new(kind, "qiniu download failed").set_source(err);
+    if temporary {
+        err.set_temporary()
+    } else {
+        err
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::super::Credential, *};
+    use hyper::header::{HeaderValue, CONTENT_LENGTH};
+    use opendal::OperatorBuilder;
+    use text_io::scan as scan_text;
+    use tokio::{spawn, sync::oneshot::channel};
+    use warp::{header, http::StatusCode, path, reply::Response, Filter};
+
+    macro_rules! starts_with_server {
+        ($addr:ident, $routes:ident, $code:block) => {{
+            let (tx, rx) = channel();
+            let ($addr, server) =
+                warp::serve($routes).bind_with_graceful_shutdown(([127, 0, 0, 1], 0), async move {
+                    rx.await.unwrap();
+                });
+            spawn(server);
+            $code;
+            tx.send(()).unwrap();
+        }};
+    }
+
+    #[tokio::test]
+    async fn test_opendal_accessor_stat_and_read() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!("dir" / "file")
+            .and(warp::head())
+            .map(|| {
+                let mut resp = Response::default();
+                resp.headers_mut()
+                    .insert(CONTENT_LENGTH, HeaderValue::from_static("64"));
+                resp
+            })
+            .or(path!("dir" / "file")
+                .and(header::value(RANGE.as_str()))
+                .map(|range: HeaderValue| {
+                    let from: u64;
+                    let to: u64;
+                    scan_text!(range.to_str().unwrap().bytes() => "bytes={}-{}", from, to);
+                    let mut resp = Response::new(vec![from as u8; (to - from + 1) as usize].into());
+                    *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+                    resp
+                }));
+
+        starts_with_server!(io_addr, io_routes, {
+            let accessor = QiniuAccessor::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    String::new(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+                "/dir/",
+            );
+            let op = OperatorBuilder::new(accessor).finish();
+
+            assert_eq!(op.stat("file").await?.content_length(), 64);
+            assert_eq!(op.read_with("file").range(8..24).await?, vec![8u8; 16]);
+
+            let err = op.stat("missing").await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+            assert!(!err.is_temporary());
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use super::super::config::TimeoutsConfig;
+
+/// 下载器的调用参数
+///
+/// 通过 [`crate::AsyncRangeReader::with_overrides`] 或 [`crate::RangeReader::with_overrides`] 修改，
+/// 修改后的下载器与原下载器共享域名选择器和缓存
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOverrides {
+    tries: usize,
+    max_retry_concurrency: u32,
+    timeouts: TimeoutsConfig,
+    max_object_size: Option<u64>,
+    bucket: Option<String>,
+}
+
+impl ReadOverrides {
+    pub(super) fn new(
+        tries: usize,
+        max_retry_concurrency: u32,
+        timeouts: TimeoutsConfig,
+        max_object_size: Option<u64>,
+        bucket: Option<String>,
+    ) -> Self {
+        Self {
+            tries,
+            max_retry_concurrency,
+            timeouts,
+            max_object_size,
+            bucket,
+        }
+    }
+
+    /// 获取最大尝试次数
+    #[inline]
+    pub fn tries(&self) -> usize {
+        self.tries
+    }
+
+    /// 设置最大尝试次数，最小为 1
+    #[inline]
+    pub fn set_tries(&mut self, tries: usize) -> &mut Self {
+        self.tries = tries.max(1);
+        self
+    }
+
+    /// 获取最大并行重试次数
+    #[inline]
+    pub fn max_retry_concurrency(&self) -> u32 {
+        self.max_retry_concurrency
+    }
+
+    /// 设置最大并行重试次数，最小为 1
+    #[inline]
+    pub fn set_max_retry_concurrency(&mut self, max_retry_concurrency: u32) -> &mut Self {
+        self.max_retry_concurrency = max_retry_concurrency.max(1);
+        self
+    }
+
+    /// 获取超时时长配置
+    #[inline]
+    pub fn timeouts(&self) -> &TimeoutsConfig {
+        &self.timeouts
+    }
+
+    /// 设置超时时长配置
+    #[inline]
+    pub fn set_timeouts(&mut self, timeouts: TimeoutsConfig) -> &mut Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// 获取对象尺寸上限
+    #[inline]
+    pub fn max_object_size(&self) -> Option<u64> {
+        self.max_object_size
+    }
+
+    /// 设置对象尺寸上限
+    #[inline]
+    pub fn set_max_object_size(&mut self, max_object_size: Option<u64>) -> &mut Self {
+        self.max_object_size = max_object_size;
+        self
+    }
+
+    /// 获取访问的存储空间，为 None 表示访问构建下载器时指定的存储空间
+    #[inline]
+    pub fn bucket(&self) -> Option<&str> {
+        self.bucket.as_deref()
+    }
+
+    /// 设置访问的存储空间，为 None 表示访问构建下载器时指定的存储空间
+    ///
+    /// 存储空间仅用于生成下载地址和签名，域名列表仍与原下载器共享，
+    /// 因此仅适用于使用 Getfile API 且与原存储空间位于同一区域、使用相同凭证的存储空间。
+    /// 缓存按照存储空间区分，不同存储空间中的同名对象不会相互影响
+    #[inline]
+    pub fn set_bucket(&mut self, bucket: Option<impl Into<String>>) -> &mut Self {
+        self.bucket = bucket.map(|bucket| bucket.into());
+        self
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::VecDeque,
+    io::Result as IoResult,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// 默认的预加载缓存容量
+pub(super) const DEFAULT_PRELOAD_CACHE_SIZE: u64 = 64 << 20;
+
+/// 默认的预加载数据有效期
+pub(super) const DEFAULT_PRELOAD_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// 默认的并行预加载数
+pub(super) const DEFAULT_PRELOAD_CONCURRENCY: usize = 4;
+
+/// 预加载的对象区域
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RangeSpec {
+    /// 完整对象
+    Full,
+    /// 指定区域，由开始偏移量和区域长度组成
+    Range(u64, u64),
+}
+
+/// 预加载进度，每完成一个预加载项产生一次
+#[derive(Debug)]
+pub struct PreloadProgress {
+    /// 对象名称
+    pub key: String,
+    /// 预加载的区域
+    pub range: RangeSpec,
+    /// 预加载结果，成功时为载入缓存的字节数
+    pub result: IoResult<u64>,
+    /// 已经完成的预加载项数，包括失败的项
+    pub finished_items: usize,
+    /// 预加载项总数
+    pub total_items: usize,
+}
+
+/// 预加载的区域数据缓存，由共享配置的下载器共享
+///
+/// 仅缓存通过预加载载入的数据，超过容量时淘汰最早载入的区域。
+/// 缓存的数据不会随对象被覆盖而更新，因此超过有效期的区域将被丢弃，之后的读取重新访问服务器
+#[derive(Debug)]
+pub(super) struct PreloadCache {
+    capacity: u64,
+    ttl: Duration,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug)]
+struct PreloadedRange {
+    key: String,
+    offset: u64,
+    data: Vec<u8>,
+    loaded_at: Instant,
+}
+
+/// 按载入时间排序的预加载区域
+#[derive(Debug, Default)]
+struct Entries {
+    ranges: VecDeque<PreloadedRange>,
+    size: u64,
+}
+
+impl Entries {
+    fn remove_expired(&mut self, ttl: Duration) {
+        while let Some(range) = self.ranges.front() {
+            if range.loaded_at.elapsed() < ttl {
+                break;
+            }
+            self.size -= range.data.len() as u64;
+            self.ranges.pop_front();
+        }
+    }
+}
+
+impl PreloadCache {
+    pub(super) fn new(capacity: u64, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: Default::default(),
+        }
+    }
+
+    /// 读取完整覆盖指定区域且尚未过期的缓存数据
+    pub(super) fn get(&self, key: &str, pos: u64, size: u64) -> Option<Vec<u8>> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove_expired(self.ttl);
+        entries.ranges.iter().rev().find_map(|range| {
+            let end = range.offset + range.data.len() as u64;
+            if range.key == key && range.offset <= pos && pos.saturating_add(size) <= end {
+                let from = (pos - range.offset) as usize;
+                Some(range.data[from..from + size as usize].to_vec())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// 载入区域数据，超过缓存容量的区域将被忽略，返回是否载入成功
+    pub(super) fn insert(&self, key: &str, offset: u64, data: Vec<u8>) -> bool {
+        let size = data.len() as u64;
+        if size == 0 || size > self.capacity {
+            return false;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove_expired(self.ttl);
+        while entries.size + size > self.capacity {
+            match entries.ranges.pop_front() {
+                Some(evicted) => entries.size -= evicted.data.len() as u64,
+                None => break,
+            }
+        }
+        entries.size += size;
+        entries.ranges.push_back(PreloadedRange {
+            key: key.to_owned(),
+            offset,
+            data,
+            loaded_at: Instant::now(),
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preload_cache() {
+        let cache = PreloadCache::new(16, Duration::from_secs(60));
+        assert!(cache.insert("file1", 10, b"0123456789".to_vec()));
+        assert_eq!(cache.get("file1", 12, 3), Some(b"234".to_vec()));
+        assert_eq!(cache.get("file1", 15, 10), None);
+        assert_eq!(cache.get("file2", 12, 3), None);
+        assert!(!cache.insert("file2", 0, vec![0; 17]));
+
+        assert!(cache.insert("file2", 0, b"abcdefgh".to_vec()));
+        assert_eq!(cache.get("file1", 10, 1), None);
+        assert_eq!(cache.get("file2", 0, 8), Some(b"abcdefgh".to_vec()));
+    }
+
+    #[test]
+    fn test_preload_cache_expiry() {
+        let cache = PreloadCache::new(16, Duration::from_millis(50));
+        assert!(cache.insert("file1", 0, b"0123456789".to_vec()));
+        assert_eq!(cache.get("file1", 0, 4), Some(b"0123".to_vec()));
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(cache.get("file1", 0, 4), None);
+        assert_eq!(cache.entries.lock().unwrap().size, 0);
+    }
+}
@@ -1,6 +1,8 @@
 use super::{
+    super::base::region_hosts::{select_io_urls, RegionHosts},
     cache_dir::cache_dir_path_of,
     dot::{ApiName, DotType, Dotter},
+    health::record_cache_lookup,
     host_selector::{HostInfo, HostSelector},
 };
 use futures::TryFutureExt;
@@ -110,17 +112,41 @@ struct ResponseBody {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct RegionResponseBody {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    region: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    idc: Option<String>,
     ttl: u64,
     io: DomainsResponseBody,
     uc: DomainsResponseBody,
 }
 
+impl RegionResponseBody {
+    fn to_region_hosts(&self, use_https: bool) -> RegionHosts {
+        RegionHosts::new(
+            self.region.to_owned(),
+            self.idc.to_owned(),
+            self.io.normalized_domains(use_https),
+            self.uc.normalized_domains(use_https),
+        )
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct DomainsResponseBody {
     domains: Box<[Box<str>]>,
 }
 
-#[derive(Clone)]
+impl DomainsResponseBody {
+    fn normalized_domains(&self, use_https: bool) -> Vec<String> {
+        self.domains
+            .iter()
+            .map(|domain| normalize_domain(domain, use_https))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
 pub(super) struct HostsQuerier {
     uc_selector: HostSelector,
     uc_tries: usize,
@@ -143,22 +169,31 @@ impl HostsQuerier {
         }
     }
 
+    /// 查询 IO 域名，指定了 `preferred_idc` 时优先使用匹配的区域或机房的域名
     pub(super) async fn query_for_io_urls(
         &self,
         ak: &str,
         bucket: &str,
         use_https: bool,
+        preferred_idc: Option<&str>,
     ) -> IoResult<Vec<String>> {
+        let region_hosts = self.query_for_region_hosts(ak, bucket, use_https).await?;
+        Ok(select_io_urls(&region_hosts, preferred_idc))
+    }
+
+    /// 查询按区域分组的域名列表
+    pub(super) async fn query_for_region_hosts(
+        &self,
+        ak: &str,
+        bucket: &str,
+        use_https: bool,
+    ) -> IoResult<Vec<RegionHosts>> {
         let response_body = self.query_for_domains(ak, bucket, use_https).await?;
-        return Ok(response_body
+        Ok(response_body
             .hosts
-            .first()
-            .expect("No host in uc query v4 response body")
-            .io
-            .domains
             .iter()
-            .map(|domain| normalize_domain(domain, use_https))
-            .collect());
+            .map(|region| region.to_region_hosts(use_https))
+            .collect())
     }
 
     async fn query_for_domains(
@@ -176,7 +211,9 @@ impl HostsQuerier {
         let mut modified = false;
         let cache_value = {
             let mut map = cache_map(false).await?.write().await;
-            match map.get(&cache_key) {
+            let cached = map.get(&cache_key);
+            record_cache_lookup(cached.is_some());
+            match cached {
                 Some(cache_value) => cache_value.to_owned(),
                 None => query_for_domains_without_cache(
                     ak,
@@ -499,7 +536,7 @@ fn normalize_domain(domain: &str, use_https: bool) -> String {
 mod tests {
     use super::{
         super::{
-            super::{base::credential::Credential, config::Timeouts},
+            super::{base::credential::Credential, config::HttpClientTimeouts},
             dot::{AsyncDotRecordsMap, DotRecordKey, DotRecords, DOT_FILE_NAME},
         },
         *,
@@ -611,7 +648,7 @@ mod tests {
         };
         starts_with_server!(uc_addr, monitor_addr, uc_routes, monitor_routes, {
             let dotter = Dotter::new(
-                Timeouts::default_async_http_client(),
+                HttpClientTimeouts::default_async_http_client(),
                 get_credential(),
                 BUCKET_NAME.to_owned(),
                 vec!["http://".to_owned() + &monitor_addr.to_string()],
@@ -622,6 +659,7 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
             )
             .await;
             let host_selector =
@@ -632,10 +670,10 @@ mod tests {
                 host_selector,
                 1,
                 dotter,
-                Timeouts::default_async_http_client(),
+                HttpClientTimeouts::default_async_http_client(),
             );
             let io_urls = querier
-                .query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false)
+                .query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false, None)
                 .await?;
             assert_eq!(&io_urls, &["http://iovip.qbox.me".to_owned()]);
             assert_eq!(
@@ -715,7 +753,7 @@ mod tests {
 
         starts_with_server!(uc_addr, monitor_addr, uc_routes, monitor_routes, {
             let dotter = Dotter::new(
-                Timeouts::default_async_http_client(),
+                HttpClientTimeouts::default_async_http_client(),
                 get_credential(),
                 BUCKET_NAME.to_owned(),
                 vec!["http://".to_owned() + &monitor_addr.to_string()],
@@ -726,6 +764,7 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
             )
             .await;
             let host_selector =
@@ -736,17 +775,17 @@ mod tests {
                 host_selector,
                 1,
                 dotter,
-                Timeouts::default_async_http_client(),
+                HttpClientTimeouts::default_async_http_client(),
             );
 
             let mut io_urls = hosts_querier
-                .query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false)
+                .query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false, None)
                 .await?;
             assert_eq!(io_urls, vec!["http://iovip.qbox.me".to_owned()]);
             assert_eq!(uc_called.load(Relaxed), 1);
 
             io_urls = hosts_querier
-                .query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false)
+                .query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false, None)
                 .await?;
             assert_eq!(io_urls, vec!["http://iovip.qbox.me".to_owned()]);
             assert_eq!(uc_called.load(Relaxed), 1);
@@ -754,7 +793,7 @@ mod tests {
             sleep(Duration::from_secs(3)).await;
 
             io_urls = hosts_querier
-                .query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false)
+                .query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false, None)
                 .await?;
             assert_eq!(io_urls, vec!["http://iovip.qbox.me".to_owned()]);
             assert_eq!(uc_called.load(Relaxed), 1);
@@ -765,7 +804,7 @@ mod tests {
             let _ = cache_map(true).await?;
 
             io_urls = hosts_querier
-                .query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false)
+                .query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false, None)
                 .await?;
             assert_eq!(io_urls, vec!["http://iovip.qbox.me".to_owned()]);
             assert_eq!(uc_called.load(Relaxed), 2);
@@ -0,0 +1,1496 @@
+use super::{
+    super::{
+        base::{
+            checksum::ChecksumAlgorithm,
+            download::{
+                destination_busy_error, destination_lock_delay, destination_lock_path,
+                wait_for_object_delay, wait_for_object_timed_out_error, ObjectMetadata,
+                RangeReaderBuilder as BaseRangeReaderBuilder,
+            },
+            region_hosts::RegionHosts,
+        },
+        config::{
+            build_range_reader_builder_from_config, build_range_reader_builder_from_env, Config,
+        },
+        sync_api::check_strict_mode,
+    },
+    file::AsyncObjectFile,
+    integrity::{download_to_dir, download_to_path},
+    overrides::ReadOverrides,
+    preload::{PreloadProgress, RangeSpec},
+    retrier::AsyncRangeReaderWithRangeReader,
+    sharding::{download_shards_to_file, ShardedDownloadOptions, ShardedDownloadProgress},
+    stats::ReaderStats,
+    streaming::{download_to_writer, read_stream},
+    transform::{transform_stream, StreamTransformer, TransformWriter},
+    RangePart,
+};
+use fd_lock::RwLock as FdRwLock;
+use futures::Stream;
+use std::{
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::{fs::OpenOptions, io::AsyncWrite, time::sleep};
+
+/// 异步对象范围下载器
+///
+/// 必须在 tokio 运行时中使用，克隆后的下载器共享域名选择器和打点记录
+#[derive(Debug, Clone)]
+pub struct AsyncRangeReader {
+    inner: AsyncRangeReaderWithRangeReader,
+    key: String,
+    bucket: String,
+    cluster_name: Option<String>,
+}
+
+impl AsyncRangeReader {
+    pub(crate) fn from_base_builder(builder: BaseRangeReaderBuilder) -> Self {
+        let bucket = builder.bucket.to_owned();
+        let cluster_name = builder.cluster_name.to_owned();
+        let (inner, key) = AsyncRangeReaderWithRangeReader::from_base_builder(builder);
+        Self {
+            inner,
+            key,
+            bucket,
+            cluster_name,
+        }
+    }
+
+    /// 从配置创建异步范围下载器
+    /// # Arguments
+    ///
+    /// * `key` - 对象名称
+    /// * `config` - 下载配置
+    pub fn from_config(key: impl Into<String>, config: &Config) -> Self {
+        Self::from_base_builder(build_range_reader_builder_from_config(key.into(), config))
+    }
+
+    /// 从配置创建异步范围下载器，启用严格模式时先检查配置要求
+    ///
+    /// 未启用严格模式时与 [`Self::from_config`] 相同，总是返回成功
+    pub fn try_from_config(key: impl Into<String>, config: &Config) -> IoResult<Self> {
+        let builder = build_range_reader_builder_from_config(key.into(), config);
+        check_strict_mode(&builder)?;
+        Ok(Self::from_base_builder(builder))
+    }
+
+    /// 从环境变量创建异步范围下载器
+    /// # Arguments
+    ///
+    /// * `key` - 对象名称
+    pub fn from_env(key: impl Into<String>) -> Option<Self> {
+        build_range_reader_builder_from_env(key.into(), false).map(Self::from_base_builder)
+    }
+
+    /// 获取对象名称
+    #[inline]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// 获取存储空间名称，通过 [`ReadOverrides::set_bucket`] 修改后返回修改后的存储空间名称
+    #[inline]
+    pub fn bucket(&self) -> &str {
+        self.inner.bucket().unwrap_or(&self.bucket)
+    }
+
+    /// 获取集群名称，仅当从多集群配置创建时返回
+    #[inline]
+    pub fn cluster_name(&self) -> Option<&str> {
+        self.cluster_name.as_deref()
+    }
+
+    /// 开启会话模式，返回新的下载器
+    ///
+    /// 会话中首次成功的请求将记录对象的 Etag，此后的请求均通过 `If-Match` 携带该 Etag，
+    /// 对象在会话期间被覆盖时返回 [`crate::ObjectChangedError`] 错误且不再重试，
+    /// 可以保证多次范围读取的数据来自同一版本的对象。克隆后的下载器共享同一个会话
+    pub fn start_session(&self) -> Self {
+        Self {
+            inner: self.inner.to_owned().with_session(),
+            key: self.key.to_owned(),
+            bucket: self.bucket.to_owned(),
+            cluster_name: self.cluster_name.to_owned(),
+        }
+    }
+
+    /// 获取当前的调用参数
+    pub fn overrides(&self) -> ReadOverrides {
+        self.inner.overrides()
+    }
+
+    /// 修改调用参数，返回新的下载器
+    ///
+    /// 新的下载器与当前下载器共享域名选择器、缓存和会话，仅重试次数、超时时长等调用参数不同，
+    /// 因此同一个下载器可以同时服务于交互式请求和批量任务，而无需重复创建域名选择器
+    pub fn with_overrides(&self, f: impl FnOnce(&mut ReadOverrides)) -> Self {
+        let mut overrides = self.inner.overrides();
+        f(&mut overrides);
+        Self {
+            inner: self.inner.to_owned().with_overrides(overrides),
+            key: self.key.to_owned(),
+            bucket: self.bucket.to_owned(),
+            cluster_name: self.cluster_name.to_owned(),
+        }
+    }
+
+    /// 获取下载器的累计统计信息
+    ///
+    /// 包括各 API 的调用次数、下载的字节数、重试次数、切换域名次数、缓存命中次数和平均耗时，
+    /// 克隆后的下载器共享统计信息
+    pub fn stats(&self) -> ReaderStats {
+        self.inner.stats()
+    }
+
+    /// 清空下载器的累计统计信息
+    pub fn reset_stats(&self) {
+        self.inner.reset_stats()
+    }
+
+    /// 主动更新域名列表
+    ///
+    /// 如果返回为 true 表示更新成功，返回 false 表示没有可用于更新的域名列表，
+    /// 更新失败且重试次数耗尽时返回最后一次的错误
+    pub async fn update_urls(&self) -> IoResult<bool> {
+        self.inner.update_urls().await
+    }
+
+    /// 获取当前可用的 IO 节点的域名
+    pub async fn io_urls(&self) -> Vec<String> {
+        self.inner.io_urls().await
+    }
+
+    /// 获取按区域分组的域名列表
+    ///
+    /// 配置了 UC 服务器时实时查询 UC 服务器，返回结果中保留了区域名称和机房标签，
+    /// 未配置 UC 服务器时仅返回包含当前可用 IO 域名的一个分组
+    pub async fn region_hosts(&self) -> IoResult<Vec<RegionHosts>> {
+        self.inner.region_hosts().await
+    }
+
+    /// 报告外部对 IO 节点的访问失败
+    ///
+    /// 用于让同一进程内的健康检查等其他流量参与域名的惩罚，`host` 应当为 [`Self::io_urls`] 返回的域名之一。
+    /// 如果该域名因此被惩罚，则返回 true
+    pub async fn report_external_failure(&self, host: &str, error: &IoError) -> bool {
+        self.inner.report_external_failure(host, error).await
+    }
+
+    /// 报告外部对 IO 节点的访问成功
+    ///
+    /// 用于让同一进程内的健康检查等其他流量参与域名的恢复，`host` 应当为 [`Self::io_urls`] 返回的域名之一。
+    /// 如果找到了该域名，则返回 true
+    pub async fn report_external_success(&self, host: &str) -> bool {
+        self.inner.report_external_success(host).await
+    }
+
+    /// 在指定时长内不再访问该 IO 节点，时长为 0 表示解除禁用
+    ///
+    /// 用于在故障或维护期间人工排除域名，`host` 应当为 [`Self::io_urls`] 返回的域名之一。
+    /// 如果找到了该域名，则返回 true
+    pub async fn ban_host(&self, host: &str, duration: Duration) -> bool {
+        self.inner.ban_host(host, duration).await
+    }
+
+    /// 覆盖该 IO 节点的基础超时时长，传入 None 表示恢复为全局的基础超时时长
+    ///
+    /// 适用于跨地域等天然延迟较高的域名，`host` 应当为 [`Self::io_urls`] 返回的域名之一。
+    /// 如果找到了该域名，则返回 true
+    pub async fn set_host_base_timeout(&self, host: &str, base_timeout: Option<Duration>) -> bool {
+        self.inner.set_host_base_timeout(host, base_timeout).await
+    }
+
+    /// 仅访问指定的 IO 节点，传入空列表表示解除固定
+    ///
+    /// `hosts` 应当为 [`Self::io_urls`] 返回的域名，如果有域名找不到则不做任何修改并返回 false。
+    /// 如果所有可用域名都被禁用或未被固定，则忽略人工干预，从全部域名中选择
+    pub async fn pin_hosts(&self, hosts: &[String]) -> bool {
+        self.inner.pin_hosts(hosts).await
+    }
+
+    /// 删除当前对象不存在的缓存，如果缓存存在则返回 true
+    ///
+    /// 用于在对象上传后立即访问，而无需等待缓存过期
+    pub async fn invalidate_negative_cache(&self) -> bool {
+        self.inner.invalidate_negative_cache(&self.key).await
+    }
+
+    /// 丢弃 HTTP 客户端及其连接池，后续请求将使用新建的客户端，如果客户端存在则返回 true
+    ///
+    /// 用于网络环境变化（例如切换 VPN）后，避免继续使用指向旧路由的空闲连接。
+    /// 相同超时配置的读取器共享同一个客户端，因此也会受到影响
+    pub async fn purge_connections(&self) -> bool {
+        self.inner.purge_connections().await
+    }
+
+    /// 从指定偏移量开始读取数据到缓冲区中，返回实际读取的字节数
+    pub async fn read_at(&self, pos: u64, buf: &mut [u8]) -> IoResult<usize> {
+        let bytes = self.inner.read_at(&self.key, pos, buf.len() as u64).await?;
+        let size = bytes.len().min(buf.len());
+        buf[..size].copy_from_slice(&bytes[..size]);
+        Ok(size)
+    }
+
+    /// 在后台预加载多个对象的指定区域，返回预加载进度流
+    ///
+    /// 以有限的并行度将区域数据载入预加载缓存，之后读取完整落在已预加载区域内的数据时无需访问服务器，
+    /// 适合在应用能够预知工作集时降低交互延迟。丢弃进度流不会中止预加载
+    /// # Arguments
+    /// * `items` - 预加载项列表，每项由对象名称和区域组成
+    pub fn preload(
+        &self,
+        items: Vec<(String, RangeSpec)>,
+    ) -> impl Stream<Item = PreloadProgress> + Send + Unpin + 'static {
+        self.inner.preload(items)
+    }
+
+    /// 读取文件的多个区域，返回每个区域对应的数据，返回顺序与请求的区域顺序一致
+    /// # Arguments
+    /// * `range` - 区域列表，每个区域有开始偏移量和区域长度组成
+    pub async fn read_multi_ranges(&self, ranges: &[(u64, u64)]) -> IoResult<Vec<RangePart>> {
+        self.inner.read_multi_ranges(&self.key, ranges).await
+    }
+
+    /// 流式计算文件指定区域的校验和，不会将区域数据全部缓存在内存中
+    /// # Arguments
+    /// * `pos` - 区域开始偏移量
+    /// * `len` - 区域长度，超出文件末尾的部分将被忽略
+    /// * `algorithm` - 校验和算法
+    pub async fn checksum_range(
+        &self,
+        pos: u64,
+        len: u64,
+        algorithm: ChecksumAlgorithm,
+    ) -> IoResult<Vec<u8>> {
+        self.inner
+            .checksum_range(&self.key, pos, len, algorithm)
+            .await
+    }
+
+    /// 并发计算文件多个区域的校验和，按区域顺序返回
+    /// # Arguments
+    /// * `ranges` - 区域列表，每个区域有开始偏移量和区域长度组成
+    /// * `algorithm` - 校验和算法
+    /// * `concurrency` - 最大并发数，为 0 时视为 1
+    pub async fn checksum_ranges(
+        &self,
+        ranges: &[(u64, u64)],
+        algorithm: ChecksumAlgorithm,
+        concurrency: usize,
+    ) -> IoResult<Vec<Vec<u8>>> {
+        self.inner
+            .checksum_ranges(&self.key, ranges, algorithm, concurrency)
+            .await
+    }
+
+    /// 判定当前对象是否存在
+    pub async fn exist(&self) -> IoResult<bool> {
+        self.inner.exist(&self.key).await
+    }
+
+    /// 获取当前对象的文件大小
+    pub async fn file_size(&self) -> IoResult<u64> {
+        self.inner.file_size(&self.key).await
+    }
+
+    /// 获取当前对象的元信息，包括尺寸、Etag、MIME 类型、最后修改时间和用户自定义元数据
+    pub async fn stat(&self) -> IoResult<ObjectMetadata> {
+        self.inner.stat(&self.key).await
+    }
+
+    /// 等待当前对象出现，返回对象的文件大小
+    ///
+    /// 以 `poll_interval` 为初始间隔轮询对象是否存在，间隔按指数增长且带有随机抖动，
+    /// 每次轮询前都会删除对象不存在的缓存。超过 `timeout` 时对象仍不存在则返回 [`std::io::ErrorKind::TimedOut`] 错误，
+    /// 丢弃返回的 Future 即可取消等待
+    pub async fn wait_for_object(
+        &self,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> IoResult<u64> {
+        let deadline = Instant::now() + timeout;
+        let rng = self.inner.rng().await;
+        for polls in 1.. {
+            self.invalidate_negative_cache().await;
+            match self.file_size().await {
+                Err(err) if err.kind() == IoErrorKind::NotFound => {}
+                result => return result,
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            sleep(wait_for_object_delay(poll_interval, polls, &rng).min(deadline - now)).await;
+        }
+        Err(wait_for_object_timed_out_error(timeout))
+    }
+
+    /// 下载当前对象到内存缓冲区中
+    pub async fn download(&self) -> IoResult<Vec<u8>> {
+        self.inner.download(&self.key).await
+    }
+
+    /// 分片下载当前对象到内存缓冲区中
+    ///
+    /// 对象被切分为多个分片，各个分片按轮转方式分散到不同的 IO 域名上并发下载，
+    /// 以提高单个对象的下载并行度
+    pub async fn download_sharded(&self, options: &ShardedDownloadOptions) -> IoResult<Vec<u8>> {
+        self.inner.download_sharded(&self.key, options).await
+    }
+
+    /// 下载当前对象到指定输出流中，返回写入的字节数
+    ///
+    /// 对象被分块并发读取，同时写入输出流，已经读取但尚未写入的数据不超过 `download_buffer_size`。
+    /// 输出流写入较慢时将暂停读取，而不会将整个对象缓存在内存中
+    pub async fn download_to(&self, writer: &mut (dyn AsyncWrite + Unpin + Send)) -> IoResult<u64> {
+        download_to_writer(&self.inner, &self.key, writer).await
+    }
+
+    /// 下载当前对象到指定路径的文件中，返回写入的字节数
+    ///
+    /// 写入期间对目标文件旁的 `.lock` 文件加排他的建议锁，避免多个下载任务同时写入同一个文件，该文件在下载完成后保留。
+    /// 目标文件已被锁定时，`lock_timeout` 为 None 则立即返回 [`crate::DestinationBusyError`] 错误，
+    /// 否则最多等待 `lock_timeout` 后再返回该错误。
+    ///
+    /// 数据以有界缓冲区流式写入目标文件旁的 `.part` 临时文件，下载成功后原子地重命名为目标文件，下载失败或被取消时删除临时文件，目标文件保持原样。
+    /// 进程异常退出时遗留的临时文件可以通过 [`crate::remove_stale_download_files`] 清理。
+    ///
+    /// 开启完整性元数据后，在目标文件被替换前原子地写入目标文件旁的 `.qniu.meta` 文件，记录对象的 Etag、尺寸、下载时间、目标文件的修改时间和已经写入的区域，
+    /// 再次下载到同一路径时，如果对象的 Etag 和尺寸均未变化且目标文件完整，则跳过下载直接返回对象尺寸
+    pub async fn download_to_path(
+        &self,
+        path: impl AsRef<Path>,
+        lock_timeout: Option<Duration>,
+    ) -> IoResult<u64> {
+        download_to_path(&self.inner, &self.key, path.as_ref(), lock_timeout).await
+    }
+
+    /// 下载当前对象到指定目录中，返回目标文件路径和写入的字节数
+    ///
+    /// 文件名依次取自 Content-Disposition 响应头（通过 `attname` 指定下载文件名时由服务器返回）、`attname` 和对象名称的最后一级路径，
+    /// 其中的路径将被去除，以免写入指定目录之外，无法得到合法的文件名时返回 [`IoErrorKind::InvalidInput`] 错误。
+    /// 写入方式与 [`Self::download_to_path`] 相同
+    pub async fn download_to_dir(
+        &self,
+        dir: impl AsRef<Path>,
+        lock_timeout: Option<Duration>,
+    ) -> IoResult<(PathBuf, u64)> {
+        download_to_dir(&self.inner, &self.key, dir.as_ref(), lock_timeout).await
+    }
+
+    /// 分片下载当前对象到指定路径的文件中，支持断点续传，返回对象尺寸
+    ///
+    /// 各个分片并发下载，完成后立即写入文件的对应位置，目标文件按 [`ShardedDownloadOptions::file_allocation`] 分配空间。
+    /// 已经完成并落盘的分片原子地记录在目标文件旁的 `.shards` 文件中，下载中断后再次调用将跳过这些分片，
+    /// 全部完成后删除该文件，对象在此期间被覆盖时将重新下载整个对象。`on_progress` 在开始下载前和每完成一个分片后调用，进度包括断点续传前已经存在的分片。
+    /// 目标文件的加锁方式与 [`Self::download_to_path`] 相同
+    pub async fn download_to_path_sharded(
+        &self,
+        path: impl AsRef<Path>,
+        options: &ShardedDownloadOptions,
+        lock_timeout: Option<Duration>,
+        mut on_progress: impl FnMut(&ShardedDownloadProgress) + Send,
+    ) -> IoResult<u64> {
+        let path = path.as_ref();
+        let lock_file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(destination_lock_path(path))
+            .await?;
+        let mut lock_file = FdRwLock::new(lock_file);
+        let deadline = lock_timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            match lock_file.try_write() {
+                Ok(_guard) => {
+                    let mut file = OpenOptions::new()
+                        .write(true)
+                        .create(true)
+                        .truncate(false)
+                        .open(path)
+                        .await?;
+                    return download_shards_to_file(
+                        &self.inner,
+                        &self.key,
+                        path,
+                        &mut file,
+                        options,
+                        &mut on_progress,
+                    )
+                    .await;
+                }
+                Err(err) if err.kind() == IoErrorKind::WouldBlock => {
+                    match destination_lock_delay(deadline) {
+                        Some(delay) => sleep(delay).await,
+                        None => return Err(destination_busy_error(path)),
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// 下载当前对象，经过转换器转换后写入指定输出流中，返回写入的字节数，即转换后的数据尺寸
+    ///
+    /// 与 [`Self::download_to`] 相同，对象被分块读取，每个分块读取后立即经过转换器转换并写入输出流，
+    /// 不会将整个对象缓存在内存中
+    pub async fn download_to_with_transformer(
+        &self,
+        writer: &mut (dyn AsyncWrite + Unpin + Send),
+        transformer: &mut dyn StreamTransformer,
+    ) -> IoResult<u64> {
+        let mut writer = TransformWriter::new(writer, transformer);
+        download_to_writer(&self.inner, &self.key, &mut writer).await?;
+        writer.finish().await
+    }
+
+    /// 以数据流的方式读取当前对象从 `pos` 开始的 `len` 个字节，超出对象尾部的部分将被忽略
+    ///
+    /// 区域被切分为不超过下载缓冲区尺寸（最大 1 MiB）的分块并发读取，数据流按顺序输出各个分块，出现错误后数据流结束
+    pub fn read_at_stream(
+        &self,
+        pos: u64,
+        len: u64,
+    ) -> impl Stream<Item = IoResult<Vec<u8>>> + Send + 'static {
+        read_stream(self.inner.to_owned(), self.key.to_owned(), pos, len)
+    }
+
+    /// 以数据流的方式读取当前对象从 `pos` 开始的 `len` 个字节，每个分块经过转换器转换后输出
+    ///
+    /// 读取方式与 [`Self::read_at_stream`] 相同，数据流结束时输出转换器剩余的数据，转换结果为空的分块不会被输出
+    pub fn read_at_stream_with_transformer(
+        &self,
+        pos: u64,
+        len: u64,
+        transformer: impl StreamTransformer + 'static,
+    ) -> impl Stream<Item = IoResult<Vec<u8>>> + Send + 'static {
+        transform_stream(self.read_at_stream(pos, len), transformer)
+    }
+
+    /// 下载对象的最后指定个字节到缓冲区中，返回实际下载的字节数和整个文件的大小
+    pub async fn read_last_bytes(&self, buf: &mut [u8]) -> IoResult<(u64, u64)> {
+        let (bytes, total_size) = self
+            .inner
+            .read_last_bytes(&self.key, buf.len() as u64)
+            .await?;
+        let size = bytes.len().min(buf.len());
+        buf[..size].copy_from_slice(&bytes[..size]);
+        Ok((size as u64, total_size))
+    }
+
+    /// 以异步对象文件的形式打开当前对象
+    #[inline]
+    pub async fn into_file(self) -> IoResult<AsyncObjectFile> {
+        AsyncObjectFile::open(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            super::{Credential, DestinationBusyError},
+            FileAllocation,
+        },
+        *,
+    };
+    use futures::{StreamExt, TryStreamExt};
+    use hyper::{
+        header::{HeaderValue, CONTENT_LENGTH, ETAG, IF_MATCH, RANGE},
+        StatusCode,
+    };
+    use std::sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed},
+        Arc, Mutex,
+    };
+    use text_io::scan as scan_text;
+    use tokio::{
+        io::{duplex, AsyncReadExt},
+        spawn,
+        sync::oneshot::channel,
+    };
+    use warp::{header, path, reply::Response, Filter};
+
+    macro_rules! starts_with_server {
+        ($addr:ident, $routes:ident, $code:block) => {{
+            let (tx, rx) = channel();
+            let ($addr, server) =
+                warp::serve($routes).bind_with_graceful_shutdown(([127, 0, 0, 1], 0), async move {
+                    rx.await.unwrap();
+                });
+            spawn(server);
+            $code;
+            tx.send(()).unwrap();
+        }};
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_read_at() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes =
+            path!("file")
+                .and(header::value(RANGE.as_str()))
+                .map(|range: HeaderValue| {
+                    let from: u64;
+                    let to: u64;
+                    scan_text!(range.to_str().unwrap().bytes() => "bytes={}-{}", from, to);
+                    Response::new(vec![from as u8; (to - from + 1) as usize].into())
+                });
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            );
+            assert_eq!(downloader.key(), "file");
+
+            let mut buf = [0u8; 16];
+            assert_eq!(downloader.read_at(5, &mut buf).await?, 16);
+            assert_eq!(buf, [5u8; 16]);
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_preload() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let called = Arc::new(AtomicUsize::new(0));
+        let io_routes = {
+            let called = called.to_owned();
+            path!("file")
+                .and(header::value(RANGE.as_str()))
+                .map(move |range: HeaderValue| {
+                    called.fetch_add(1, Relaxed);
+                    let from: u64;
+                    let to: u64;
+                    scan_text!(range.to_str().unwrap().bytes() => "bytes={}-{}", from, to);
+                    Response::new(vec![from as u8; (to - from + 1) as usize].into())
+                })
+        };
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            );
+            let progress = downloader
+                .preload(vec![("file".to_owned(), RangeSpec::Range(0, 32))])
+                .collect::<Vec<_>>()
+                .await;
+            assert_eq!(progress.len(), 1);
+            assert_eq!(progress[0].result.as_ref().ok(), Some(&32));
+            assert_eq!(progress[0].finished_items, 1);
+            assert_eq!(progress[0].total_items, 1);
+            assert_eq!(called.load(Relaxed), 1);
+
+            // 预加载区域内的读取直接使用缓存数据
+            let mut buf = [1u8; 16];
+            assert_eq!(downloader.read_at(5, &mut buf).await?, 16);
+            assert_eq!(buf, [0u8; 16]);
+            assert_eq!(called.load(Relaxed), 1);
+            assert_eq!(downloader.stats().cache_hits(), 1);
+
+            assert_eq!(downloader.read_at(20, &mut buf).await?, 16);
+            assert_eq!(buf, [20u8; 16]);
+            assert_eq!(called.load(Relaxed), 2);
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_download_sharded() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        const DATA: &[u8] = b"0123456789abcdef";
+        let routes = |called: Arc<AtomicUsize>| {
+            path!("file")
+                .and(header::optional(RANGE.as_str()))
+                .map(move |range: Option<String>| match range {
+                    Some(range) => {
+                        called.fetch_add(1, Relaxed);
+                        let from: usize;
+                        let to: usize;
+                        scan_text!(range.bytes() => "bytes={}-{}", from, to);
+                        Response::new(DATA[from..=to].to_vec().into())
+                    }
+                    None => Response::new(DATA.into()),
+                })
+        };
+        let called_1 = Arc::new(AtomicUsize::new(0));
+        let called_2 = Arc::new(AtomicUsize::new(0));
+        let io_routes_1 = routes(called_1.to_owned());
+        let io_routes_2 = routes(called_2.to_owned());
+
+        starts_with_server!(io_addr_1, io_routes_1, {
+            starts_with_server!(io_addr_2, io_routes_2, {
+                let downloader = AsyncRangeReader::from_base_builder(
+                    BaseRangeReaderBuilder::new(
+                        "bucket".to_owned(),
+                        "file".to_owned(),
+                        Credential::new("1234567890", "abcdefghijk"),
+                        vec![
+                            format!("http://{}", io_addr_1),
+                            format!("http://{}", io_addr_2),
+                        ],
+                    )
+                    .use_getfile_api(false)
+                    .normalize_key(true),
+                );
+                let mut options = ShardedDownloadOptions::default();
+                options
+                    .set_shard_size(4)
+                    .set_max_concurrency_per_host(std::num::NonZeroUsize::new(1));
+                assert_eq!(downloader.download_sharded(&options).await?, DATA);
+                // 分片按轮转方式分散到两个域名上
+                assert_eq!(called_1.load(Relaxed), 2);
+                assert_eq!(called_2.load(Relaxed), 2);
+            });
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_download_to_with_slow_writer() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        const DATA: &[u8] = b"0123456789abcdef";
+        let io_routes = path!("file").and(header::optional(RANGE.as_str())).map(
+            move |range: Option<String>| match range {
+                Some(range) => {
+                    let from: usize;
+                    let to: usize;
+                    scan_text!(range.bytes() => "bytes={}-{}", from, to);
+                    let mut resp = Response::new(DATA[from..=to].to_vec().into());
+                    *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+                    resp.headers_mut().insert(
+                        "Content-Range",
+                        HeaderValue::from_str(&format!("bytes {}-{}/{}", from, to, DATA.len()))
+                            .unwrap(),
+                    );
+                    resp
+                }
+                None => Response::new(DATA.into()),
+            },
+        );
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true)
+                .download_buffer_size(4),
+            );
+            // 输出流每次只能容纳 2 个字节，读取端消费缓慢
+            let (mut writer, mut reader) = duplex(2);
+            let reading = spawn(async move {
+                let mut data = Vec::new();
+                let mut buf = [0u8; 2];
+                loop {
+                    let n = reader.read(&mut buf).await?;
+                    if n == 0 {
+                        return Ok::<_, IoError>(data);
+                    }
+                    data.extend_from_slice(&buf[..n]);
+                    sleep(Duration::from_millis(10)).await;
+                }
+            });
+            assert_eq!(downloader.download_to(&mut writer).await?, 16);
+            drop(writer);
+            assert_eq!(reading.await??, DATA);
+
+            let high_water_mark = downloader.stats().buffer_high_water_mark();
+            assert!(high_water_mark > 0 && high_water_mark <= 4);
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_download_to_path_sharded() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        const DATA: &[u8] = b"0123456789abcdef";
+        let called = Arc::new(AtomicUsize::new(0));
+        let broken = Arc::new(AtomicBool::new(true));
+        let io_routes = {
+            let called = called.to_owned();
+            let broken = broken.to_owned();
+            path!("file")
+                .and(header::optional(RANGE.as_str()))
+                .map(move |range: Option<String>| match range {
+                    Some(range) => {
+                        called.fetch_add(1, Relaxed);
+                        let from: usize;
+                        let to: usize;
+                        scan_text!(range.bytes() => "bytes={}-{}", from, to);
+                        if from == 8 && broken.load(Relaxed) {
+                            let mut resp = Response::new(Default::default());
+                            *resp.status_mut() = StatusCode::NOT_FOUND;
+                            resp
+                        } else {
+                            Response::new(DATA[from..=to].to_vec().into())
+                        }
+                    }
+                    None => Response::new(DATA.into()),
+                })
+        };
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            );
+            let dir = tempfile::tempdir()?;
+            let path = dir.path().join("file");
+            let mut options = ShardedDownloadOptions::default();
+            options.set_shard_size(4).set_concurrency(1);
+
+            let mut progresses = Vec::new();
+            downloader
+                .download_to_path_sharded(&path, &options, None, |progress| {
+                    progresses.push(*progress)
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(called.load(Relaxed), 3);
+            assert_eq!(progresses.len(), 3);
+            assert_eq!(progresses[2].finished_shards, 2);
+            assert_eq!(progresses[2].downloaded_bytes, 8);
+            // 未写入的区域保持稀疏
+            assert_eq!(std::fs::metadata(&path)?.len(), 16);
+            assert!(dir.path().join("file.shards").exists());
+
+            broken.store(false, Relaxed);
+            called.store(0, Relaxed);
+            let mut progresses = Vec::new();
+            options.set_file_allocation(FileAllocation::Preallocate);
+            let size = downloader
+                .download_to_path_sharded(&path, &options, None, |progress| {
+                    progresses.push(*progress)
+                })
+                .await?;
+            assert_eq!(size, 16);
+            // 断点续传仅下载缺失的分片
+            assert_eq!(called.load(Relaxed), 2);
+            assert_eq!(progresses[0].finished_shards, 2);
+            assert_eq!(progresses[0].resumed_bytes, 8);
+            assert_eq!(progresses[0].downloaded_bytes, 8);
+            assert_eq!(progresses.last().unwrap().downloaded_bytes, 16);
+            assert_eq!(progresses.last().unwrap().total_shards, 4);
+            assert_eq!(std::fs::read(&path)?, DATA);
+            assert!(!dir.path().join("file.shards").exists());
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_download_to_path_sharded_after_overwritten(
+    ) -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        const DATA: &[u8] = b"0123456789abcdef";
+        let called = Arc::new(AtomicUsize::new(0));
+        let overwritten = Arc::new(AtomicBool::new(false));
+        let if_matches = Arc::new(Mutex::new(Vec::new()));
+        let io_routes = {
+            let called = called.to_owned();
+            let overwritten = overwritten.to_owned();
+            let if_matches = if_matches.to_owned();
+            path!("file")
+                .and(header::optional(RANGE.as_str()))
+                .and(header::optional(IF_MATCH.as_str()))
+                .map(move |range: Option<String>, if_match: Option<String>| {
+                    let overwritten = overwritten.load(Relaxed);
+                    let etag = if overwritten { "\"v2\"" } else { "\"v1\"" };
+                    let mut resp = match range {
+                        Some(range) => {
+                            called.fetch_add(1, Relaxed);
+                            if_matches.lock().unwrap().push(if_match);
+                            let from: usize;
+                            let to: usize;
+                            scan_text!(range.bytes() => "bytes={}-{}", from, to);
+                            if from == 8 && !overwritten {
+                                let mut resp = Response::new(Default::default());
+                                *resp.status_mut() = StatusCode::NOT_FOUND;
+                                resp
+                            } else {
+                                Response::new(DATA[from..=to].to_vec().into())
+                            }
+                        }
+                        None => Response::new(DATA.into()),
+                    };
+                    resp.headers_mut()
+                        .insert(ETAG, HeaderValue::from_static(etag));
+                    resp
+                })
+        };
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            );
+            let dir = tempfile::tempdir()?;
+            let path = dir.path().join("file");
+            let mut options = ShardedDownloadOptions::default();
+            options.set_shard_size(4).set_concurrency(1);
+
+            downloader
+                .download_to_path_sharded(&path, &options, None, |_| {})
+                .await
+                .unwrap_err();
+            assert_eq!(called.load(Relaxed), 3);
+            assert!(dir.path().join("file.shards").exists());
+
+            overwritten.store(true, Relaxed);
+            called.store(0, Relaxed);
+            if_matches.lock().unwrap().clear();
+            let mut progresses = Vec::new();
+            let size = downloader
+                .download_to_path_sharded(&path, &options, None, |progress| {
+                    progresses.push(*progress)
+                })
+                .await?;
+            assert_eq!(size, 16);
+            // 对象已经被覆盖，断点续传状态不再可信，重新下载所有分片
+            assert_eq!(called.load(Relaxed), 4);
+            assert_eq!(progresses[0].resumed_bytes, 0);
+            assert!(if_matches
+                .lock()
+                .unwrap()
+                .iter()
+                .all(|if_match| if_match.as_deref() == Some("\"v2\"")));
+            assert_eq!(std::fs::read(&path)?, DATA);
+            assert!(!dir.path().join("file.shards").exists());
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_with_overrides() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let called = Arc::new(AtomicUsize::new(0));
+        let io_routes = {
+            let called = called.to_owned();
+            path!("file").map(move || {
+                called.fetch_add(1, Relaxed);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })
+        };
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true)
+                .io_tries(3)
+                .max_retry_concurrency(1),
+            );
+            let interactive = downloader.with_overrides(|overrides| {
+                overrides.set_tries(1).set_max_object_size(Some(1024));
+            });
+            assert_eq!(downloader.overrides().tries(), 3);
+            assert_eq!(downloader.overrides().max_object_size(), None);
+            assert_eq!(interactive.overrides().tries(), 1);
+            assert_eq!(interactive.overrides().max_object_size(), Some(1024));
+            assert_eq!(interactive.key(), "file");
+
+            let mut buf = [0u8; 16];
+            interactive.read_at(0, &mut buf).await.unwrap_err();
+            assert_eq!(called.load(Relaxed), 1);
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_with_bucket_override() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let called = Arc::new(AtomicUsize::new(0));
+        let io_routes = {
+            let called = called.to_owned();
+            path!("getfile" / String / String / String).map(
+                move |_access_key: String, bucket: String, _key: String| {
+                    called.fetch_add(1, Relaxed);
+                    if bucket == "bucket" {
+                        let mut resp = Response::new(Default::default());
+                        *resp.status_mut() = StatusCode::NOT_FOUND;
+                        resp
+                    } else {
+                        Response::new(bucket.into())
+                    }
+                },
+            )
+        };
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .normalize_key(true)
+                .negative_cache_ttl(Duration::from_secs(60)),
+            );
+            let other = downloader.with_overrides(|overrides| {
+                overrides.set_bucket(Some("other-bucket"));
+            });
+            assert_eq!(downloader.bucket(), "bucket");
+            assert_eq!(other.bucket(), "other-bucket");
+            assert_eq!(other.overrides().bucket(), Some("other-bucket"));
+
+            assert!(!downloader.exist().await?);
+            assert!(!downloader.exist().await?);
+            assert_eq!(called.load(Relaxed), 1);
+            assert!(other.exist().await?);
+            assert_eq!(other.download().await?, b"other-bucket");
+            assert_eq!(called.load(Relaxed), 3);
+
+            let restored = other.with_overrides(|overrides| {
+                overrides.set_bucket(None::<String>);
+            });
+            assert_eq!(restored.bucket(), "bucket");
+            assert!(!restored.exist().await?);
+            assert_eq!(called.load(Relaxed), 3);
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_download_to_path() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!("file").map(|| Response::new("1234567890".into()));
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            );
+            let dir = tempfile::tempdir()?;
+            let path = dir.path().join("file");
+            std::fs::write(&path, "stale content which is longer")?;
+
+            {
+                let mut locked =
+                    FdRwLock::new(std::fs::File::create(dir.path().join("file.lock"))?);
+                let _guard = locked.write()?;
+                for lock_timeout in [None, Some(Duration::from_millis(100))].iter() {
+                    let err = downloader
+                        .download_to_path(&path, *lock_timeout)
+                        .await
+                        .unwrap_err();
+                    assert_eq!(err.kind(), IoErrorKind::WouldBlock);
+                    assert_eq!(
+                        err.get_ref()
+                            .and_then(|err| err.downcast_ref::<DestinationBusyError>())
+                            .map(|err| err.path()),
+                        Some(path.as_path())
+                    );
+                }
+            }
+
+            std::fs::write(dir.path().join("file.shards"), "{}")?;
+            assert_eq!(downloader.download_to_path(&path, None).await?, 10);
+            assert_eq!(std::fs::read(&path)?, b"1234567890");
+            // 锁文件在下载完成后保留，以保证所有下载任务锁定的是同一个文件
+            assert_eq!(std::fs::read_dir(dir.path())?.count(), 2);
+            assert!(dir.path().join("file.lock").exists());
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_download_to_dir() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let last_modified = || HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT");
+        let io_routes = path!("dir" / "file")
+            .map(move || {
+                let mut resp = Response::new("1234567890".into());
+                resp.headers_mut().insert("Last-Modified", last_modified());
+                resp
+            })
+            .or(path!("attached").map(move || {
+                let mut resp = Response::new("12345".into());
+                resp.headers_mut().insert(
+                    "Content-Disposition",
+                    HeaderValue::from_static("attachment; filename=\"../attached.txt\""),
+                );
+                resp
+            }));
+
+        starts_with_server!(io_addr, io_routes, {
+            let builder = |key: &str| {
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    key.to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true)
+            };
+            let dir = tempfile::tempdir()?;
+
+            let downloader = AsyncRangeReader::from_base_builder(
+                builder("dir/file").preserve_last_modified(true),
+            );
+            let (path, size) = downloader.download_to_dir(dir.path(), None).await?;
+            assert_eq!(path, dir.path().join("file"));
+            assert_eq!(size, 10);
+            assert_eq!(std::fs::read(&path)?, b"1234567890");
+            assert_eq!(
+                std::fs::metadata(&path)?.modified()?,
+                std::time::UNIX_EPOCH + Duration::from_secs(784111777)
+            );
+
+            // 服务器返回的文件名中的路径被去除，不会写入指定目录之外
+            let downloader = AsyncRangeReader::from_base_builder(builder("attached"));
+            let (path, size) = downloader.download_to_dir(dir.path(), None).await?;
+            assert_eq!(path, dir.path().join("attached.txt"));
+            assert_eq!(size, 5);
+            assert_eq!(std::fs::read(&path)?, b"12345");
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_download_to_path_with_bounded_buffer() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        const DATA: &[u8] = b"0123456789abcdef";
+        let io_routes = path!("file").and(header::optional(RANGE.as_str())).map(
+            move |range: Option<String>| match range {
+                Some(range) => {
+                    let from: usize;
+                    let to: usize;
+                    scan_text!(range.bytes() => "bytes={}-{}", from, to);
+                    let mut resp = Response::new(DATA[from..=to].to_vec().into());
+                    *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+                    resp.headers_mut().insert(
+                        "Content-Range",
+                        HeaderValue::from_str(&format!("bytes {}-{}/{}", from, to, DATA.len()))
+                            .unwrap(),
+                    );
+                    resp
+                }
+                None => Response::new(DATA.into()),
+            },
+        );
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true)
+                .download_buffer_size(4),
+            );
+            let dir = tempfile::tempdir()?;
+            let path = dir.path().join("file");
+            assert_eq!(downloader.download_to_path(&path, None).await?, 16);
+            assert_eq!(std::fs::read(&path)?, DATA);
+
+            // 对象分块流式写入临时文件，而不是整体读入内存
+            let high_water_mark = downloader.stats().buffer_high_water_mark();
+            assert!(high_water_mark > 0 && high_water_mark <= 4);
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_stat() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!("file").and(warp::head()).map(|| {
+            let mut resp = Response::default();
+            resp.headers_mut()
+                .insert(CONTENT_LENGTH, HeaderValue::from_static("10"));
+            resp.headers_mut()
+                .insert("ETag", HeaderValue::from_static("\"etag\""));
+            resp.headers_mut().insert(
+                "Last-Modified",
+                HeaderValue::from_static("Thu, 01 Jan 1970 00:00:10 GMT"),
+            );
+            resp.headers_mut()
+                .insert("X-Qn-Meta-Author", HeaderValue::from_static("qiniu"));
+            resp
+        });
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            );
+            let metadata = downloader.stat().await?;
+            assert_eq!(metadata.size(), 10);
+            assert_eq!(metadata.etag(), Some("\"etag\""));
+            assert_eq!(metadata.content_type(), None);
+            assert_eq!(
+                metadata.last_modified(),
+                Some(std::time::UNIX_EPOCH + Duration::from_secs(10))
+            );
+            assert_eq!(
+                metadata.user_metadata().get("author").map(String::as_str),
+                Some("qiniu")
+            );
+            assert_eq!(downloader.file_size().await?, 10);
+        });
+
+        Ok(())
+    }
+
+    struct Uppercase;
+
+    impl StreamTransformer for Uppercase {
+        fn transform(&mut self, input: &[u8]) -> IoResult<Vec<u8>> {
+            Ok(input.to_ascii_uppercase())
+        }
+
+        fn finish(&mut self) -> IoResult<Vec<u8>> {
+            Ok(b"!".to_vec())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_transform_streams() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        const DATA: &[u8] = b"0123456789abcdef";
+        let io_routes = path!("file").and(header::optional(RANGE.as_str())).map(
+            move |range: Option<String>| match range {
+                Some(range) => {
+                    let from: usize;
+                    let to: usize;
+                    scan_text!(range.bytes() => "bytes={}-{}", from, to);
+                    let mut resp = Response::new(DATA[from..=to].to_vec().into());
+                    *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+                    resp.headers_mut().insert(
+                        "Content-Range",
+                        HeaderValue::from_str(&format!("bytes {}-{}/{}", from, to, DATA.len()))
+                            .unwrap(),
+                    );
+                    resp
+                }
+                None => Response::new(DATA.into()),
+            },
+        );
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true)
+                .download_buffer_size(4),
+            );
+
+            let mut output = Vec::new();
+            assert_eq!(
+                downloader
+                    .download_to_with_transformer(&mut output, &mut Uppercase)
+                    .await?,
+                17
+            );
+            assert_eq!(output, b"0123456789ABCDEF!");
+            // 对象分块读取并转换，而不是整体读入内存
+            let high_water_mark = downloader.stats().buffer_high_water_mark();
+            assert!(high_water_mark > 0 && high_water_mark <= 4);
+
+            let chunks = downloader
+                .read_at_stream(6, 100)
+                .try_collect::<Vec<_>>()
+                .await?;
+            assert_eq!(chunks, [b"6789".to_vec(), b"abcd".to_vec(), b"ef".to_vec()]);
+            let chunks = downloader
+                .read_at_stream_with_transformer(8, 6, Uppercase)
+                .try_collect::<Vec<_>>()
+                .await?;
+            assert_eq!(chunks, [b"89AB".to_vec(), b"CD".to_vec(), b"!".to_vec()]);
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_download_to_path_failed() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!("file").map(|| {
+            let mut resp = Response::new("1234567890".into());
+            *resp.status_mut() = StatusCode::NOT_FOUND;
+            resp
+        });
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            );
+            let dir = tempfile::tempdir()?;
+
+            // 下载失败时不留下临时文件，也不创建目标文件
+            let path = dir.path().join("file");
+            downloader.download_to_path(&path, None).await.unwrap_err();
+            assert!(!path.exists());
+            assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+
+            // 下载失败时已经存在的目标文件保持原样
+            std::fs::write(&path, "old content")?;
+            downloader.download_to_path(&path, None).await.unwrap_err();
+            assert_eq!(std::fs::read(&path)?, b"old content");
+            assert_eq!(std::fs::read_dir(dir.path())?.count(), 2);
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_download_to_path_with_metadata() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let downloaded = Arc::new(AtomicUsize::new(0));
+        let version = Arc::new(AtomicUsize::new(1));
+        let io_routes = {
+            let downloaded = downloaded.to_owned();
+            let version = version.to_owned();
+            path!("file")
+                .and(warp::method())
+                .map(move |method: hyper::Method| {
+                    if method == hyper::Method::GET {
+                        downloaded.fetch_add(1, Relaxed);
+                    }
+                    let mut resp = Response::new("1234567890".into());
+                    resp.headers_mut().insert(
+                        "ETag",
+                        HeaderValue::from_str(&format!("\"v{}\"", version.load(Relaxed))).unwrap(),
+                    );
+                    resp
+                })
+        };
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true)
+                .integrity_metadata(true),
+            );
+            let dir = tempfile::tempdir()?;
+            let path = dir.path().join("file");
+            let metadata_path = dir.path().join("file.qniu.meta");
+
+            assert_eq!(downloader.download_to_path(&path, None).await?, 10);
+            assert_eq!(std::fs::read(&path)?, b"1234567890");
+            assert_eq!(downloaded.load(Relaxed), 1);
+            let metadata: serde_json::Value =
+                serde_json::from_slice(&std::fs::read(&metadata_path)?)?;
+            assert_eq!(metadata["etag"], "\"v1\"");
+            assert_eq!(metadata["size"], 10);
+            assert_eq!(metadata["ranges"], serde_json::json!([[0, 10]]));
+
+            // 对象未变化时跳过下载
+            assert_eq!(downloader.download_to_path(&path, None).await?, 10);
+            assert_eq!(downloaded.load(Relaxed), 1);
+
+            // 对象被覆盖后重新下载
+            version.store(2, Relaxed);
+            assert_eq!(downloader.download_to_path(&path, None).await?, 10);
+            assert_eq!(downloaded.load(Relaxed), 2);
+            let metadata: serde_json::Value =
+                serde_json::from_slice(&std::fs::read(&metadata_path)?)?;
+            assert_eq!(metadata["etag"], "\"v2\"");
+
+            // 目标文件被修改后重新下载
+            std::fs::write(&path, "12345")?;
+            assert_eq!(downloader.download_to_path(&path, None).await?, 10);
+            assert_eq!(std::fs::read(&path)?, b"1234567890");
+            assert_eq!(downloaded.load(Relaxed), 3);
+
+            // 目标文件被替换为尺寸相同的其他文件后重新下载
+            sleep(Duration::from_millis(50)).await;
+            std::fs::write(&path, "abcdefghij")?;
+            assert_eq!(downloader.download_to_path(&path, None).await?, 10);
+            assert_eq!(std::fs::read(&path)?, b"1234567890");
+            assert_eq!(downloaded.load(Relaxed), 4);
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_stats() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!("file").map(|| Response::new("1234567890".into()));
+
+        starts_with_server!(io_addr, io_routes, {
+            let downloader = AsyncRangeReader::from_base_builder(
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    "file".to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true),
+            );
+            assert_eq!(downloader.download().await?, b"1234567890");
+            assert_eq!(downloader.download().await?, b"1234567890");
+
+            let stats = downloader.with_overrides(|_| {}).stats();
+            assert_eq!(stats.requests().get("range_reader_download_to"), Some(&2));
+            assert_eq!(stats.total_requests(), 2);
+            assert_eq!(stats.failed_requests(), 0);
+            assert_eq!(stats.downloaded_bytes(), 20);
+            assert_eq!(stats.retries(), 0);
+            assert!(stats.average_latency().is_some());
+
+            downloader.reset_stats();
+            assert_eq!(downloader.stats().total_requests(), 0);
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_range_reader_wait_for_object() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let called = Arc::new(AtomicUsize::new(0));
+        let called_with_head_cache = Arc::new(AtomicUsize::new(0));
+        let appear_later = |key: &'static str, called: Arc<AtomicUsize>| {
+            path(key).and(path::end()).map(move || {
+                let mut resp = Response::new("1234567890".into());
+                if called.fetch_add(1, Relaxed) < 2 {
+                    *resp.status_mut() = StatusCode::NOT_FOUND;
+                }
+                resp
+            })
+        };
+        let missing_routes = path!("missing").map(|| StatusCode::NOT_FOUND);
+        let io_routes = appear_later("file", called.to_owned())
+            .or(appear_later("file2", called_with_head_cache.to_owned()))
+            .or(missing_routes);
+
+        starts_with_server!(io_addr, io_routes, {
+            let builder = |key: &str| {
+                BaseRangeReaderBuilder::new(
+                    "bucket".to_owned(),
+                    key.to_owned(),
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec![format!("http://{}", io_addr)],
+                )
+                .use_getfile_api(false)
+                .normalize_key(true)
+                .negative_cache_ttl(Duration::from_secs(60))
+            };
+            let downloader = |key: &str| AsyncRangeReader::from_base_builder(builder(key));
+
+            let size = downloader("file")
+                .wait_for_object(Duration::from_millis(10), Duration::from_secs(10))
+                .await?;
+            assert_eq!(size, 10);
+            assert_eq!(called.load(Relaxed), 3);
+
+            let size = AsyncRangeReader::from_base_builder(
+                builder("file2").head_cache_window(Duration::from_secs(60)),
+            )
+            .wait_for_object(Duration::from_millis(10), Duration::from_secs(10))
+            .await?;
+            assert_eq!(size, 10);
+            assert_eq!(called_with_head_cache.load(Relaxed), 3);
+
+            let err = downloader("missing")
+                .wait_for_object(Duration::from_millis(10), Duration::from_millis(100))
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), IoErrorKind::TimedOut);
+        });
+
+        Ok(())
+    }
+}
@@ -1,11 +1,40 @@
 use super::{
+    super::{
+        base::{
+            checksum::ChecksumAlgorithm,
+            download::{
+                check_multi_ranges, check_object_size, is_content_encoded_error, ObjectMetadata,
+                RangeReaderBuilder as BaseRangeReaderBuilder,
+            },
+            region_hosts::RegionHosts,
+            rng::SharedRng,
+        },
+        config::{TimeoutApi, TimeoutsConfig},
+        error_code::ErrorCode,
+        events::{emit_event, DownloadEvent},
+        settings::global_settings,
+    },
+    audit::{audit_api_call, range_of as audit_range_of, AuditSlot},
     dot::{ApiName, DotType},
-    download::{AsyncRangeReader, IoResult3, Result3, TriesInfo, TryingHosts},
+    download::{
+        AsyncRangeReader, AsyncRangeReaderBuilder, IoResult3, Result3, TriesInfo, TryingHosts,
+    },
+    egress::reserve_egress_budget,
     host_selector::HostInfo,
+    overrides::ReadOverrides,
+    preload::{PreloadProgress, RangeSpec, DEFAULT_PRELOAD_CONCURRENCY},
+    session::{etag_to_string, ObjectSession},
+    sharding::{ShardHosts, ShardedDownloadOptions},
+    stats::{ReaderStats, StatsRecorder},
+    streaming::DEFAULT_DOWNLOAD_BUFFER_SIZE,
     RangePart,
 };
 use async_trait::async_trait;
-use futures::future::{join_all, select, select_all, Either};
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver},
+    future::{join_all, select, select_all, Either},
+    stream::{iter as stream_iter, StreamExt, TryStreamExt},
+};
 use log::{error, info};
 use std::{
     future::Future,
@@ -16,13 +45,27 @@ use std::{
     task::{Context, Poll},
     time::Duration,
 };
-use tokio::{pin, sync::RwLock, time::sleep_until, time::Instant};
+use tokio::{
+    pin, spawn,
+    sync::RwLock,
+    time::{sleep_until, timeout, Instant},
+};
 
 #[derive(Debug, Clone)]
 pub(super) struct AsyncRangeReaderWithRangeReader {
     inner: AsyncRangeReader,
     max_retry_concurrency: u32,
     total_tries: usize,
+    timeouts: TimeoutsConfig,
+    max_object_size: Option<u64>,
+    max_multi_ranges: Option<usize>,
+    max_multi_ranges_size: Option<u64>,
+    integrity_metadata: bool,
+    preserve_last_modified: bool,
+    download_buffer_size: u64,
+    bucket: Option<String>,
+    session: Option<Arc<ObjectSession>>,
+    stats: Arc<StatsRecorder>,
 }
 
 impl AsyncRangeReaderWithRangeReader {
@@ -35,10 +78,190 @@ impl AsyncRangeReaderWithRangeReader {
             inner: range_reader,
             max_retry_concurrency,
             total_tries,
+            timeouts: Default::default(),
+            max_object_size: None,
+            max_multi_ranges: None,
+            max_multi_ranges_size: None,
+            integrity_metadata: false,
+            preserve_last_modified: false,
+            download_buffer_size: DEFAULT_DOWNLOAD_BUFFER_SIZE,
+            bucket: None,
+            session: None,
+            stats: Default::default(),
         }
     }
 
-    pub(super) async fn update_urls(&self) -> bool {
+    pub(super) fn with_timeouts(mut self, timeouts: TimeoutsConfig) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub(super) fn with_max_object_size(mut self, max_object_size: Option<u64>) -> Self {
+        self.max_object_size = max_object_size;
+        self
+    }
+
+    pub(super) fn with_multi_ranges_limits(
+        mut self,
+        max_multi_ranges: Option<usize>,
+        max_multi_ranges_size: Option<u64>,
+    ) -> Self {
+        self.max_multi_ranges = max_multi_ranges;
+        self.max_multi_ranges_size = max_multi_ranges_size;
+        self
+    }
+
+    pub(super) fn with_integrity_metadata(mut self, integrity_metadata: bool) -> Self {
+        self.integrity_metadata = integrity_metadata;
+        self
+    }
+
+    pub(super) fn with_preserve_last_modified(mut self, preserve_last_modified: bool) -> Self {
+        self.preserve_last_modified = preserve_last_modified;
+        self
+    }
+
+    pub(super) fn with_download_buffer_size(mut self, download_buffer_size: Option<u64>) -> Self {
+        self.download_buffer_size = download_buffer_size.unwrap_or(DEFAULT_DOWNLOAD_BUFFER_SIZE);
+        self
+    }
+
+    pub(super) fn from_base_builder(mut builder: BaseRangeReaderBuilder) -> (Self, String) {
+        let max_retry_concurrency = builder.max_retry_concurrency.unwrap_or(5).max(1);
+        let io_tries = builder.io_tries;
+        let key = take(&mut builder.key);
+        let timeouts = builder.timeouts.to_owned();
+        let max_object_size = builder.max_object_size;
+        let max_multi_ranges = builder.max_multi_ranges;
+        let max_multi_ranges_size = builder.max_multi_ranges_size;
+        let integrity_metadata = builder.integrity_metadata;
+        let preserve_last_modified = builder.preserve_last_modified;
+        let download_buffer_size = builder.download_buffer_size;
+        (
+            Self::new(
+                AsyncRangeReaderBuilder::from(builder).build(),
+                max_retry_concurrency,
+                io_tries,
+            )
+            .with_timeouts(timeouts)
+            .with_max_object_size(max_object_size)
+            .with_multi_ranges_limits(max_multi_ranges, max_multi_ranges_size)
+            .with_integrity_metadata(integrity_metadata)
+            .with_preserve_last_modified(preserve_last_modified)
+            .with_download_buffer_size(download_buffer_size),
+            key,
+        )
+    }
+
+    /// 获取当前的调用参数
+    pub(super) fn overrides(&self) -> ReadOverrides {
+        ReadOverrides::new(
+            self.total_tries,
+            self.max_retry_concurrency,
+            self.timeouts.to_owned(),
+            self.max_object_size,
+            self.bucket.to_owned(),
+        )
+    }
+
+    /// 使用新的调用参数，域名选择器、缓存和会话仍与原下载器共享
+    pub(super) fn with_overrides(mut self, overrides: ReadOverrides) -> Self {
+        self.total_tries = overrides.tries();
+        self.max_retry_concurrency = overrides.max_retry_concurrency();
+        self.max_object_size = overrides.max_object_size();
+        self.timeouts = overrides.timeouts().to_owned();
+        self.bucket = overrides.bucket().map(|bucket| bucket.to_owned());
+        self
+    }
+
+    /// 获取通过调用参数指定的存储空间，未指定时返回 None
+    pub(super) fn bucket(&self) -> Option<&str> {
+        self.bucket.as_deref()
+    }
+
+    /// 开启会话模式，会话状态由克隆后的下载器共享
+    pub(super) fn with_session(mut self) -> Self {
+        self.session = Some(Default::default());
+        self
+    }
+
+    /// 获取会话中记录的 Etag，未开启会话模式或尚未记录时返回 None
+    pub(super) fn session_etag(&self) -> Option<String> {
+        self.session
+            .as_ref()
+            .and_then(|session| session.etag())
+            .map(|etag| etag_to_string(&etag))
+    }
+
+    /// 下载到文件时是否读写完整性元数据
+    pub(super) fn integrity_metadata(&self) -> bool {
+        self.integrity_metadata
+    }
+
+    /// 下载到文件时是否将文件的修改时间设置为对象的最后修改时间
+    pub(super) fn preserve_last_modified(&self) -> bool {
+        self.preserve_last_modified
+    }
+
+    /// 获取通过 `attname` 指定的下载文件名
+    pub(super) async fn attname(&self) -> Option<String> {
+        self.inner.attname().await
+    }
+
+    /// 获取对象尺寸上限
+    pub(super) fn max_object_size(&self) -> Option<u64> {
+        self.max_object_size
+    }
+
+    /// 获取下载到输出流时的缓冲区尺寸
+    pub(super) fn download_buffer_size(&self) -> u64 {
+        self.download_buffer_size
+    }
+
+    /// 记录下载到输出流时缓冲区中尚未写入的字节数
+    pub(super) fn record_buffered_bytes(&self, bytes: u64) {
+        self.stats.record_buffered_bytes(bytes)
+    }
+
+    /// 使用指定的统计信息记录器，用于同步接口为每个下载器单独统计
+    pub(super) fn with_stats(mut self, stats: Arc<StatsRecorder>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// 获取累计的统计信息
+    pub(super) fn stats(&self) -> ReaderStats {
+        self.stats.snapshot()
+    }
+
+    /// 清空累计的统计信息
+    pub(super) fn reset_stats(&self) {
+        self.stats.reset()
+    }
+
+    fn tries_info<'a>(
+        &'a self,
+        have_tried: &'a AtomicUsize,
+        audit_slot: &'a AuditSlot,
+    ) -> TriesInfo<'a> {
+        TriesInfo::new(have_tried, self.total_tries())
+            .with_session(self.session.as_deref())
+            .with_audit_slot(Some(audit_slot))
+            .with_stats(Some(&self.stats))
+            .with_bucket(self.bucket.as_deref())
+    }
+
+    fn total_tries(&self) -> usize {
+        global_settings().io_tries().unwrap_or(self.total_tries)
+    }
+
+    fn max_retry_concurrency(&self) -> u32 {
+        global_settings()
+            .max_retry_concurrency()
+            .unwrap_or(self.max_retry_concurrency)
+    }
+
+    pub(super) async fn update_urls(&self) -> IoResult<bool> {
         self.inner.update_urls().await
     }
 
@@ -46,23 +269,203 @@ impl AsyncRangeReaderWithRangeReader {
         self.inner.io_urls().await
     }
 
+    pub(super) async fn region_hosts(&self) -> IoResult<Vec<RegionHosts>> {
+        self.inner.region_hosts(self.bucket.as_deref()).await
+    }
+
+    pub(super) async fn report_external_failure(&self, host: &str, error: &IoError) -> bool {
+        self.inner.report_external_failure(host, error).await
+    }
+
+    pub(super) async fn report_external_success(&self, host: &str) -> bool {
+        self.inner.report_external_success(host).await
+    }
+
+    pub(super) async fn ban_host(&self, host: &str, duration: Duration) -> bool {
+        self.inner.ban_host(host, duration).await
+    }
+
+    pub(super) async fn set_host_base_timeout(
+        &self,
+        host: &str,
+        base_timeout: Option<Duration>,
+    ) -> bool {
+        self.inner.set_host_base_timeout(host, base_timeout).await
+    }
+
+    pub(super) async fn pin_hosts(&self, hosts: &[String]) -> bool {
+        self.inner.pin_hosts(hosts).await
+    }
+
+    /// 记录 API 调用的审计日志，启用阶段耗时打点时同时记录最后一次请求各阶段的耗时
+    async fn audit_api_call(
+        &self,
+        api: ApiName,
+        key: &str,
+        range: Option<String>,
+        outcome: Result<Option<u64>, &IoError>,
+        slot: &AuditSlot,
+        elapsed: Duration,
+    ) {
+        if let Some(stages) = slot.stages() {
+            self.inner.dot_stage_timings(api, &stages).await.ok();
+        }
+        audit_api_call(api, key, range, outcome, slot, elapsed).await;
+    }
+
+    pub(super) async fn rng(&self) -> SharedRng {
+        self.inner.rng().await
+    }
+
+    pub(super) async fn dot_sync_runtime_load(
+        &self,
+        in_flight_requests: usize,
+        queued_requests: usize,
+    ) -> IoResult<()> {
+        self.inner
+            .dot_sync_runtime_load(in_flight_requests, queued_requests)
+            .await
+    }
+
+    pub(super) async fn invalidate_negative_cache(&self, key: &str) -> bool {
+        self.inner
+            .invalidate_negative_cache(key, self.bucket.as_deref())
+            .await
+    }
+
+    pub(super) async fn purge_connections(&self) -> bool {
+        self.inner.purge_connections().await
+    }
+
+    #[cfg(feature = "opendal")]
+    pub(super) async fn sign_download_url(
+        &self,
+        key: &str,
+        lifetime: Duration,
+    ) -> Option<reqwest::Url> {
+        self.inner
+            .sign_download_url(key, self.bucket.as_deref(), lifetime)
+            .await
+    }
+
     pub(super) async fn read_at(&self, key: &str, pos: u64, size: u64) -> IoResult<Vec<u8>> {
+        self.read_at_on_host(key, pos, size, None).await
+    }
+
+    /// 读取对象的指定区域，首次尝试时优先使用指定域名
+    async fn read_at_on_host(
+        &self,
+        key: &str,
+        pos: u64,
+        size: u64,
+        preferred_host: Option<&str>,
+    ) -> IoResult<Vec<u8>> {
+        if let Some(data) = self
+            .inner
+            .preloaded_range(key, self.bucket.as_deref(), pos, size)
+            .await
+        {
+            self.stats.record_cache_hit();
+            return Ok(data);
+        }
         let have_tried: AtomicUsize = Default::default();
         let trying_hosts: TryingHosts = Default::default();
         let selected_info: SelectedHostInfo = Default::default();
-        self.try_with_timeout(ApiName::RangeReaderReadAt, |async_task_id| {
-            RangeReaderReadAtRetrier::new(
-                pos,
-                size,
-                key,
-                async_task_id,
-                &self.inner,
-                TriesInfo::new(&have_tried, self.total_tries),
-                &trying_hosts,
-                &selected_info,
-            )
-        })
-        .await
+        let audit_slot: AuditSlot = Default::default();
+        let begin_at = Instant::now();
+        let reservation = reserve_egress_budget(size).await?;
+        let result = self
+            .try_with_timeout(ApiName::RangeReaderReadAt, key, |async_task_id| {
+                RangeReaderReadAtRetrier::new(
+                    pos,
+                    size,
+                    key,
+                    async_task_id,
+                    &self.inner,
+                    self.tries_info(&have_tried, &audit_slot)
+                        .with_preferred_host(preferred_host),
+                    &trying_hosts,
+                    &selected_info,
+                )
+            })
+            .await;
+        self.audit_api_call(
+            ApiName::RangeReaderReadAt,
+            key,
+            audit_range_of(&[(pos, size)]),
+            result.as_ref().map(|data| Some(data.len() as u64)),
+            &audit_slot,
+            begin_at.elapsed(),
+        )
+        .await;
+        reservation
+            .settle(result.as_ref().map_or(0, |data| data.len() as u64))
+            .await;
+        let data = result?;
+        self.stats.record_bytes(data.len() as u64);
+        Ok(data)
+    }
+
+    pub(super) async fn checksum_range(
+        &self,
+        key: &str,
+        pos: u64,
+        size: u64,
+        algorithm: ChecksumAlgorithm,
+    ) -> IoResult<Vec<u8>> {
+        let have_tried: AtomicUsize = Default::default();
+        let trying_hosts: TryingHosts = Default::default();
+        let selected_info: SelectedHostInfo = Default::default();
+        let audit_slot: AuditSlot = Default::default();
+        let begin_at = Instant::now();
+        let reservation = reserve_egress_budget(size).await?;
+        let result = self
+            .try_with_timeout(ApiName::RangeReaderChecksumRange, key, |async_task_id| {
+                RangeReaderChecksumRangeRetrier::new(
+                    pos,
+                    size,
+                    algorithm,
+                    key,
+                    async_task_id,
+                    &self.inner,
+                    self.tries_info(&have_tried, &audit_slot),
+                    &trying_hosts,
+                    &selected_info,
+                )
+            })
+            .await;
+        self.audit_api_call(
+            ApiName::RangeReaderChecksumRange,
+            key,
+            audit_range_of(&[(pos, size)]),
+            result.as_ref().map(|_| Some(size)),
+            &audit_slot,
+            begin_at.elapsed(),
+        )
+        .await;
+        reservation
+            .settle(if result.is_ok() { size } else { 0 })
+            .await;
+        let checksum = result?;
+        self.stats.record_bytes(size);
+        Ok(checksum)
+    }
+
+    pub(super) async fn checksum_ranges(
+        &self,
+        key: &str,
+        ranges: &[(u64, u64)],
+        algorithm: ChecksumAlgorithm,
+        concurrency: usize,
+    ) -> IoResult<Vec<Vec<u8>>> {
+        let checksums: Vec<_> = ranges
+            .iter()
+            .map(|&(pos, size)| self.checksum_range(key, pos, size, algorithm))
+            .collect();
+        stream_iter(checksums)
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await
     }
 
     pub(super) async fn read_multi_ranges(
@@ -70,90 +473,326 @@ impl AsyncRangeReaderWithRangeReader {
         key: &str,
         ranges: &[(u64, u64)],
     ) -> IoResult<Vec<RangePart>> {
+        check_multi_ranges(ranges, self.max_multi_ranges, self.max_multi_ranges_size)?;
         let have_tried: AtomicUsize = Default::default();
         let trying_hosts: TryingHosts = Default::default();
         let selected_info: SelectedHostInfo = Default::default();
-        self.try_with_timeout(ApiName::RangeReaderReadMultiRanges, |async_task_id| {
-            RangeReaderReadMultiRangesRetrier::new(
-                ranges,
-                key,
-                async_task_id,
-                &self.inner,
-                TriesInfo::new(&have_tried, self.total_tries),
-                &trying_hosts,
-                &selected_info,
-            )
-        })
-        .await
+        let audit_slot: AuditSlot = Default::default();
+        let begin_at = Instant::now();
+        let reservation = reserve_egress_budget(ranges.iter().map(|&(_, size)| size).sum()).await?;
+        let result = self
+            .try_with_timeout(ApiName::RangeReaderReadMultiRanges, key, |async_task_id| {
+                RangeReaderReadMultiRangesRetrier::new(
+                    ranges,
+                    key,
+                    async_task_id,
+                    &self.inner,
+                    self.tries_info(&have_tried, &audit_slot),
+                    &trying_hosts,
+                    &selected_info,
+                )
+            })
+            .await;
+        let bytes = result
+            .as_ref()
+            .map(|parts| parts.iter().map(|part| part.data.len() as u64).sum());
+        self.audit_api_call(
+            ApiName::RangeReaderReadMultiRanges,
+            key,
+            audit_range_of(ranges),
+            bytes.map(Some),
+            &audit_slot,
+            begin_at.elapsed(),
+        )
+        .await;
+        let bytes = bytes.unwrap_or_default();
+        reservation.settle(bytes).await;
+        let parts = result?;
+        self.stats.record_bytes(bytes);
+        Ok(parts)
     }
 
     pub(super) async fn exist(&self, key: &str) -> IoResult<bool> {
         let have_tried: AtomicUsize = Default::default();
         let trying_hosts: TryingHosts = Default::default();
         let selected_info: SelectedHostInfo = Default::default();
-        self.try_with_timeout(ApiName::RangeReaderExist, |async_task_id| {
-            RangeReaderExistRetrier::new(
-                key,
-                async_task_id,
-                &self.inner,
-                TriesInfo::new(&have_tried, self.total_tries),
-                &trying_hosts,
-                &selected_info,
-            )
-        })
-        .await
+        let audit_slot: AuditSlot = Default::default();
+        let begin_at = Instant::now();
+        let result = self
+            .try_with_timeout(ApiName::RangeReaderExist, key, |async_task_id| {
+                RangeReaderExistRetrier::new(
+                    key,
+                    async_task_id,
+                    &self.inner,
+                    self.tries_info(&have_tried, &audit_slot),
+                    &trying_hosts,
+                    &selected_info,
+                )
+            })
+            .await;
+        self.audit_api_call(
+            ApiName::RangeReaderExist,
+            key,
+            None,
+            result.as_ref().map(|_| None),
+            &audit_slot,
+            begin_at.elapsed(),
+        )
+        .await;
+        result
     }
 
     pub(super) async fn file_size(&self, key: &str) -> IoResult<u64> {
         let have_tried: AtomicUsize = Default::default();
         let trying_hosts: TryingHosts = Default::default();
         let selected_info: SelectedHostInfo = Default::default();
-        self.try_with_timeout(ApiName::RangeReaderFileSize, |async_task_id| {
-            RangeReaderFileSizeRetrier::new(
-                key,
-                async_task_id,
-                &self.inner,
-                TriesInfo::new(&have_tried, self.total_tries),
-                &trying_hosts,
-                &selected_info,
-            )
-        })
-        .await
+        let audit_slot: AuditSlot = Default::default();
+        let begin_at = Instant::now();
+        let result = self
+            .try_with_timeout(ApiName::RangeReaderFileSize, key, |async_task_id| {
+                RangeReaderFileSizeRetrier::new(
+                    key,
+                    async_task_id,
+                    &self.inner,
+                    self.tries_info(&have_tried, &audit_slot),
+                    &trying_hosts,
+                    &selected_info,
+                )
+            })
+            .await;
+        self.audit_api_call(
+            ApiName::RangeReaderFileSize,
+            key,
+            None,
+            result.as_ref().map(|_| None),
+            &audit_slot,
+            begin_at.elapsed(),
+        )
+        .await;
+        result
+    }
+
+    pub(super) async fn stat(&self, key: &str) -> IoResult<ObjectMetadata> {
+        let have_tried: AtomicUsize = Default::default();
+        let trying_hosts: TryingHosts = Default::default();
+        let selected_info: SelectedHostInfo = Default::default();
+        let audit_slot: AuditSlot = Default::default();
+        let begin_at = Instant::now();
+        let result = self
+            .try_with_timeout(ApiName::RangeReaderStat, key, |async_task_id| {
+                RangeReaderStatRetrier::new(
+                    key,
+                    async_task_id,
+                    &self.inner,
+                    self.tries_info(&have_tried, &audit_slot),
+                    &trying_hosts,
+                    &selected_info,
+                )
+            })
+            .await;
+        self.audit_api_call(
+            ApiName::RangeReaderStat,
+            key,
+            None,
+            result.as_ref().map(|_| None),
+            &audit_slot,
+            begin_at.elapsed(),
+        )
+        .await;
+        result
+    }
+
+    /// 在后台并行预加载多个对象区域，返回预加载进度的接收端
+    pub(super) fn preload(
+        &self,
+        items: Vec<(String, RangeSpec)>,
+    ) -> UnboundedReceiver<PreloadProgress> {
+        let (tx, rx) = unbounded();
+        let reader = self.to_owned();
+        spawn(async move {
+            let total_items = items.len();
+            let reader = &reader;
+            let mut results = stream_iter(items)
+                .map(|(key, range)| async move {
+                    let result = reader.preload_range(&key, range).await;
+                    (key, range, result)
+                })
+                .buffer_unordered(DEFAULT_PRELOAD_CONCURRENCY);
+            let mut finished_items = 0;
+            while let Some((key, range, result)) = results.next().await {
+                finished_items += 1;
+                tx.unbounded_send(PreloadProgress {
+                    key,
+                    range,
+                    result,
+                    finished_items,
+                    total_items,
+                })
+                .ok();
+            }
+        });
+        rx
+    }
+
+    async fn preload_range(&self, key: &str, range: RangeSpec) -> IoResult<u64> {
+        let (pos, size) = match range {
+            RangeSpec::Full => (0, self.file_size(key).await?),
+            RangeSpec::Range(pos, size) => (pos, size),
+        };
+        if size == 0 {
+            return Ok(0);
+        } else if self
+            .inner
+            .preloaded_range(key, self.bucket.as_deref(), pos, size)
+            .await
+            .is_some()
+        {
+            return Ok(size);
+        }
+        let data = self.read_at(key, pos, size).await?;
+        let size = data.len() as u64;
+        self.inner
+            .preload_range(key, self.bucket.as_deref(), pos, data)
+            .await;
+        Ok(size)
     }
 
     pub(super) async fn download(&self, key: &str) -> IoResult<Vec<u8>> {
+        if self.max_object_size.is_some() {
+            match self.file_size(key).await {
+                Ok(size) => check_object_size(size, self.max_object_size)?,
+                // 以压缩编码存储的对象无法预先获取尺寸，下载后再检查
+                Err(err) if is_content_encoded_error(&err) => {}
+                Err(err) => return Err(err),
+            }
+        }
         let have_tried: AtomicUsize = Default::default();
         let trying_hosts: TryingHosts = Default::default();
         let selected_info: SelectedHostInfo = Default::default();
-        self.try_with_timeout(ApiName::RangeReaderDownloadTo, |async_task_id| {
-            RangeReaderDownloadRetrier::new(
-                key,
-                async_task_id,
-                &self.inner,
-                TriesInfo::new(&have_tried, self.total_tries),
-                &trying_hosts,
-                &selected_info,
-            )
-        })
-        .await
+        let audit_slot: AuditSlot = Default::default();
+        let begin_at = Instant::now();
+        let reservation = reserve_egress_budget(0).await?;
+        let result = self
+            .try_with_timeout(ApiName::RangeReaderDownloadTo, key, |async_task_id| {
+                RangeReaderDownloadRetrier::new(
+                    key,
+                    async_task_id,
+                    &self.inner,
+                    self.tries_info(&have_tried, &audit_slot),
+                    &trying_hosts,
+                    &selected_info,
+                )
+            })
+            .await;
+        self.audit_api_call(
+            ApiName::RangeReaderDownloadTo,
+            key,
+            None,
+            result.as_ref().map(|data| Some(data.len() as u64)),
+            &audit_slot,
+            begin_at.elapsed(),
+        )
+        .await;
+        reservation
+            .settle(result.as_ref().map_or(0, |data| data.len() as u64))
+            .await;
+        let data = result?;
+        self.stats.record_bytes(data.len() as u64);
+        Ok(data)
+    }
+
+    /// 分片下载对象，将各个分片按轮转方式分散到不同的 IO 域名上并发下载
+    pub(super) async fn download_sharded(
+        &self,
+        key: &str,
+        options: &ShardedDownloadOptions,
+    ) -> IoResult<Vec<u8>> {
+        let size = self.file_size(key).await?;
+        check_object_size(size, self.max_object_size)?;
+        let hosts = self.shard_hosts(options).await;
+        let shards: Vec<_> = options
+            .shards(size)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (pos, len))| self.read_shard(key, &hosts, index, pos, len))
+            .collect();
+        let mut data = Vec::with_capacity(size as usize);
+        let mut shards = stream_iter(shards).buffered(options.concurrency().max(1));
+        while let Some(shard) = shards.next().await {
+            data.extend_from_slice(&shard?);
+        }
+        Ok(data)
+    }
+
+    /// 获取分片下载使用的域名分配器
+    pub(super) async fn shard_hosts(&self, options: &ShardedDownloadOptions) -> ShardHosts {
+        ShardHosts::new(
+            self.inner.sharding_hosts().await,
+            options.max_concurrency_per_host(),
+        )
+    }
+
+    /// 下载第 `index` 个分片，首次尝试时使用域名分配器分配的域名
+    pub(super) async fn read_shard(
+        &self,
+        key: &str,
+        hosts: &ShardHosts,
+        index: usize,
+        pos: u64,
+        len: u64,
+    ) -> IoResult<Vec<u8>> {
+        let (preferred_host, _permit) = hosts.acquire(index).await.unzip();
+        let data = self.read_at_on_host(key, pos, len, preferred_host).await?;
+        if data.len() as u64 != len {
+            return Err(IoError::new(
+                IoErrorKind::UnexpectedEof,
+                format!(
+                    "shard at {} of {} is truncated, expected {} bytes, got {}",
+                    pos,
+                    key,
+                    len,
+                    data.len()
+                ),
+            ));
+        }
+        Ok(data)
     }
 
     pub(super) async fn read_last_bytes(&self, key: &str, size: u64) -> IoResult<(Vec<u8>, u64)> {
         let have_tried: AtomicUsize = Default::default();
         let trying_hosts: TryingHosts = Default::default();
         let selected_info: SelectedHostInfo = Default::default();
-        self.try_with_timeout(ApiName::RangeReaderReadLastBytes, |async_task_id| {
-            RangeReaderReadLastBytesRetrier::new(
-                size,
-                key,
-                async_task_id,
-                &self.inner,
-                TriesInfo::new(&have_tried, self.total_tries),
-                &trying_hosts,
-                &selected_info,
-            )
-        })
-        .await
+        let audit_slot: AuditSlot = Default::default();
+        let begin_at = Instant::now();
+        let reservation = reserve_egress_budget(size).await?;
+        let result = self
+            .try_with_timeout(ApiName::RangeReaderReadLastBytes, key, |async_task_id| {
+                RangeReaderReadLastBytesRetrier::new(
+                    size,
+                    key,
+                    async_task_id,
+                    &self.inner,
+                    self.tries_info(&have_tried, &audit_slot),
+                    &trying_hosts,
+                    &selected_info,
+                )
+            })
+            .await;
+        self.audit_api_call(
+            ApiName::RangeReaderReadLastBytes,
+            key,
+            Some(format!("bytes=-{}", size)),
+            result.as_ref().map(|(data, _)| Some(data.len() as u64)),
+            &audit_slot,
+            begin_at.elapsed(),
+        )
+        .await;
+        reservation
+            .settle(result.as_ref().map_or(0, |(data, _)| data.len() as u64))
+            .await;
+        let (data, total_size) = result?;
+        self.stats.record_bytes(data.len() as u64);
+        Ok((data, total_size))
     }
 
     async fn try_with_timeout<
@@ -163,20 +802,60 @@ impl AsyncRangeReaderWithRangeReader {
     >(
         &self,
         api_name: ApiName,
+        key: &str,
         f: F,
     ) -> IoResult<Output> {
         let begin_at = Instant::now();
-        let result = _try_with_timeout(f, self.max_retry_concurrency).await;
+        emit_event(|| DownloadEvent::DownloadStarted {
+            api: api_name.to_string(),
+            key: key.to_owned(),
+        });
+        let timeouts = timeout_api_of(api_name)
+            .map(|api| self.timeouts.effective(api))
+            .unwrap_or_default();
+        let fut = _try_with_timeout(
+            f,
+            self.max_retry_concurrency(),
+            global_settings().should_log(),
+            timeouts.read(),
+        );
+        let result = if let Some(total) = timeouts.total() {
+            timeout(total, fut).await.unwrap_or_else(|_| {
+                TryResult::Error(IoError::new(
+                    IoErrorKind::TimedOut,
+                    format!("{} is timed out after {:?}", api_name, total),
+                ))
+            })
+        } else {
+            fut.await
+        };
+        let successful = match &result {
+            TryResult::Success(_) => true,
+            TryResult::Error(err) => self.inner.is_object_level_error(err).await,
+            TryResult::AllTimedOut => false,
+        };
         self.inner
-            .dot(
-                DotType::Sdk,
-                api_name,
-                matches!(result, TryResult::Success(_)),
-                begin_at.elapsed(),
-            )
+            .dot(DotType::Sdk, api_name, successful, begin_at.elapsed())
             .await
             .ok();
-        return result.into();
+        let result: IoResult<Output> = result.into();
+        self.stats
+            .record_call(api_name, result.is_ok(), begin_at.elapsed());
+        match &result {
+            Ok(_) => emit_event(|| DownloadEvent::DownloadFinished {
+                api: api_name.to_string(),
+                key: key.to_owned(),
+                elapsed: begin_at.elapsed(),
+            }),
+            Err(err) => emit_event(|| DownloadEvent::DownloadFailed {
+                api: api_name.to_string(),
+                key: key.to_owned(),
+                error: err.to_string(),
+                code: ErrorCode::of(err),
+                elapsed: begin_at.elapsed(),
+            }),
+        }
+        return result;
 
         async fn _try_with_timeout<
             Output,
@@ -185,7 +864,17 @@ impl AsyncRangeReaderWithRangeReader {
         >(
             f: F,
             max: u32,
+            verbose: bool,
+            base_timeout: Option<Duration>,
         ) -> TryResult<Output> {
+            macro_rules! verbose_info {
+                ($($arg:tt)+) => {
+                    if verbose {
+                        info!($($arg)+);
+                    }
+                };
+            }
+
             struct FutWithIdx<
                 Output,
                 T: Future<Output = IoResult3<Output>> + MaybeTimeout + Unpin + Send + Sync,
@@ -222,7 +911,11 @@ impl AsyncRangeReaderWithRangeReader {
             }
 
             let last_fut = FutWithIdx { fut: f(0), idx: 0 };
-            let last_base_timeout = last_fut.base_timeout().await;
+            let last_base_timeout =
+                global_settings().apply_base_timeout_multiplier(match base_timeout {
+                    Some(base_timeout) => base_timeout,
+                    None => last_fut.base_timeout().await,
+                });
             let mut all_futures = vec![last_fut];
             let mut last_error = None;
 
@@ -230,22 +923,24 @@ impl AsyncRangeReaderWithRangeReader {
                 let last_try = i >= max - 1;
                 let fut_timeout = future_timeout(last_base_timeout, i);
                 let until = Instant::now() + fut_timeout;
-                info!("{{{}}} Timeout-try ({:?})", i, fut_timeout);
+                verbose_info!("{{{}}} Timeout-try ({:?})", i, fut_timeout);
                 loop {
                     let timeout = sleep_until(until);
                     pin!(timeout);
                     match select(timeout, select_all(take(&mut all_futures))).await {
                         Either::Left((_, futs)) => {
                             if last_try {
-                                info!(
+                                verbose_info!(
                                     "{{{}}} Try timed out ({:?}), this is the last try",
-                                    i, fut_timeout
+                                    i,
+                                    fut_timeout
                                 );
                                 break 'timeout_loop;
                             } else {
-                                info!(
+                                verbose_info!(
                                     "{{{}}} Try timed out ({:?}), spawn new async task",
-                                    i, fut_timeout
+                                    i,
+                                    fut_timeout
                                 );
                                 let last_fut = f(i + 1);
                                 all_futures = futs.into_inner();
@@ -258,7 +953,7 @@ impl AsyncRangeReaderWithRangeReader {
                         }
                         Either::Right(((got_result, idx, rest_futures), _)) => match got_result {
                             Result3::Ok(output) => {
-                                info!("{{{}/{}}} Try succeed", idx, i);
+                                verbose_info!("{{{}/{}}} Try succeed", idx, i);
                                 punish_all_timed_out_futures(
                                     rest_futures.into_iter().filter(|f| f.idx < idx),
                                 )
@@ -266,7 +961,7 @@ impl AsyncRangeReaderWithRangeReader {
                                 return TryResult::Success(output);
                             }
                             Result3::Err(err) => {
-                                info!("{{{}/{}}} Try error: {:?}", idx, i, err);
+                                verbose_info!("{{{}/{}}} Try error: {:?}", idx, i, err);
                                 punish_all_timed_out_futures(
                                     rest_futures.into_iter().filter(|f| f.idx < idx),
                                 )
@@ -274,13 +969,16 @@ impl AsyncRangeReaderWithRangeReader {
                                 return TryResult::Error(err);
                             }
                             Result3::NoMoreTries(maybe_err) => {
-                                info!(
+                                verbose_info!(
                             "{{{}/{}}} No more tries: {:?}, will wait for the other async tasks",
                             idx, i, maybe_err
                         );
                                 if last_error.is_none() {
                                     last_error = maybe_err;
                                 }
+                                if rest_futures.is_empty() {
+                                    break 'timeout_loop;
+                                }
                                 all_futures = rest_futures;
                             }
                         },
@@ -339,6 +1037,18 @@ impl<T> From<TryResult<T>> for IoResult<T> {
     }
 }
 
+fn timeout_api_of(api_name: ApiName) -> Option<TimeoutApi> {
+    match api_name {
+        ApiName::RangeReaderReadAt | ApiName::RangeReaderChecksumRange => Some(TimeoutApi::ReadAt),
+        ApiName::RangeReaderReadMultiRanges => Some(TimeoutApi::ReadMultiRanges),
+        ApiName::RangeReaderExist => Some(TimeoutApi::Exist),
+        ApiName::RangeReaderFileSize | ApiName::RangeReaderStat => Some(TimeoutApi::FileSize),
+        ApiName::RangeReaderDownloadTo => Some(TimeoutApi::Download),
+        ApiName::RangeReaderReadLastBytes => Some(TimeoutApi::ReadLastBytes),
+        _ => None,
+    }
+}
+
 fn future_timeout(last_base_timeout: Duration, index: u32) -> Duration {
     last_base_timeout * 2u32.pow(index)
 }
@@ -438,6 +1148,61 @@ impl MaybeTimeout for RangeReaderReadAtRetrier<'_> {
     }
 }
 
+struct RangeReaderChecksumRangeRetrier<'a>(RangeReaderRetrier<'a, Vec<u8>>);
+
+impl<'a> RangeReaderChecksumRangeRetrier<'a> {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        pos: u64,
+        size: u64,
+        algorithm: ChecksumAlgorithm,
+        key: &'a str,
+        async_task_id: u32,
+        range_reader: &'a AsyncRangeReader,
+        tries_info: TriesInfo<'a>,
+        trying_hosts: &'a TryingHosts,
+        selected_info: &'a SelectedHostInfo,
+    ) -> Self {
+        Self(RangeReaderRetrier {
+            selected_info,
+            range_reader,
+            future: Box::pin(async move {
+                range_reader
+                    .checksum_range(
+                        pos,
+                        size,
+                        algorithm,
+                        key,
+                        async_task_id,
+                        tries_info,
+                        trying_hosts,
+                        |host| async move { set_selected_info(selected_info, host).await },
+                    )
+                    .await
+            }),
+        })
+    }
+}
+
+impl Future for RangeReaderChecksumRangeRetrier<'_> {
+    type Output = IoResult3<Vec<u8>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx)
+    }
+}
+
+#[async_trait]
+impl MaybeTimeout for RangeReaderChecksumRangeRetrier<'_> {
+    async fn increase_timeout_power_if_timed_out(self) {
+        self.0.increase_timeout_power_if_timed_out().await
+    }
+
+    async fn base_timeout(&self) -> Duration {
+        self.0.base_timeout().await
+    }
+}
+
 struct RangeReaderReadMultiRangesRetrier<'a>(RangeReaderRetrier<'a, Vec<RangePart>>);
 
 impl<'a> RangeReaderReadMultiRangesRetrier<'a> {
@@ -584,6 +1349,54 @@ impl MaybeTimeout for RangeReaderFileSizeRetrier<'_> {
     }
 }
 
+struct RangeReaderStatRetrier<'a>(RangeReaderRetrier<'a, ObjectMetadata>);
+
+impl<'a> RangeReaderStatRetrier<'a> {
+    fn new(
+        key: &'a str,
+        async_task_id: u32,
+        range_reader: &'a AsyncRangeReader,
+        tries_info: TriesInfo<'a>,
+        trying_hosts: &'a TryingHosts,
+        selected_info: &'a SelectedHostInfo,
+    ) -> Self {
+        Self(RangeReaderRetrier {
+            selected_info,
+            range_reader,
+            future: Box::pin(async move {
+                range_reader
+                    .stat(
+                        key,
+                        async_task_id,
+                        tries_info,
+                        trying_hosts,
+                        |host| async move { set_selected_info(selected_info, host).await },
+                    )
+                    .await
+            }),
+        })
+    }
+}
+
+impl Future for RangeReaderStatRetrier<'_> {
+    type Output = IoResult3<ObjectMetadata>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx)
+    }
+}
+
+#[async_trait]
+impl MaybeTimeout for RangeReaderStatRetrier<'_> {
+    async fn increase_timeout_power_if_timed_out(self) {
+        self.0.increase_timeout_power_if_timed_out().await
+    }
+
+    async fn base_timeout(&self) -> Duration {
+        self.0.base_timeout().await
+    }
+}
+
 struct RangeReaderDownloadRetrier<'a>(RangeReaderRetrier<'a, Vec<u8>>);
 
 impl<'a> RangeReaderDownloadRetrier<'a> {
@@ -827,7 +1640,7 @@ mod tests {
                 let counter = counter.to_owned();
                 let retrier_punished_1 = retrier_punished_1.to_owned();
                 let retrier_punished_2 = retrier_punished_2.to_owned();
-                downloader.try_with_timeout(ApiName::IoGetfile, move |count| {
+                downloader.try_with_timeout(ApiName::IoGetfile, "file", move |count| {
                     counter.store(count + 1, Relaxed);
                     let retrier_punished_1 = retrier_punished_1.to_owned();
                     let retrier_punished_2 = retrier_punished_2.to_owned();
@@ -877,7 +1690,7 @@ mod tests {
                 let counter = counter.to_owned();
                 let retrier_punished_1 = retrier_punished_1.to_owned();
                 let retrier_punished_2 = retrier_punished_2.to_owned();
-                downloader.try_with_timeout(ApiName::IoGetfile, move |count| {
+                downloader.try_with_timeout(ApiName::IoGetfile, "file", move |count| {
                     counter.store(count + 1, Relaxed);
                     let retrier_punished_1 = retrier_punished_1.to_owned();
                     let retrier_punished_2 = retrier_punished_2.to_owned();
@@ -920,7 +1733,7 @@ mod tests {
             }
 
             let err = {
-                downloader.try_with_timeout(ApiName::IoGetfile, move |count| {
+                downloader.try_with_timeout(ApiName::IoGetfile, "file", move |count| {
                     assert!(count < 2);
                     FakedRetrier::new(
                         Duration::from_millis(1000),
@@ -0,0 +1,50 @@
+use super::super::base::download::object_changed_error;
+use reqwest::{
+    header::{HeaderValue, ETAG},
+    Response as HttpResponse, StatusCode,
+};
+use std::{io::Result as IoResult, sync::Mutex};
+
+/// 会话状态
+///
+/// 记录会话中首次成功的请求返回的 Etag，此后的请求均通过 `If-Match` 携带该 Etag
+#[derive(Debug, Default)]
+pub(super) struct ObjectSession {
+    etag: Mutex<Option<HeaderValue>>,
+}
+
+impl ObjectSession {
+    pub(super) fn etag(&self) -> Option<HeaderValue> {
+        self.etag.lock().unwrap().to_owned()
+    }
+
+    /// 检查响应是否与会话记录的 Etag 一致
+    ///
+    /// 服务器以 412 状态码拒绝请求，或是返回了不同的 Etag 时，说明对象已经被覆盖
+    pub(super) fn check_response(&self, resp: HttpResponse) -> IoResult<HttpResponse> {
+        let mut expected = self.etag.lock().unwrap();
+        if resp.status() == StatusCode::PRECONDITION_FAILED {
+            if let Some(expected) = expected.as_ref() {
+                return Err(object_changed_error(etag_to_string(expected), None));
+            }
+        } else if resp.status().is_success() {
+            if let Some(actual) = resp.headers().get(ETAG) {
+                match expected.as_ref() {
+                    Some(expected) if expected != actual => {
+                        return Err(object_changed_error(
+                            etag_to_string(expected),
+                            Some(etag_to_string(actual)),
+                        ));
+                    }
+                    Some(_) => {}
+                    None => *expected = Some(actual.to_owned()),
+                }
+            }
+        }
+        Ok(resp)
+    }
+}
+
+pub(super) fn etag_to_string(etag: &HeaderValue) -> String {
+    String::from_utf8_lossy(etag.as_bytes()).into_owned()
+}
@@ -0,0 +1,381 @@
+use super::{
+    retrier::AsyncRangeReaderWithRangeReader,
+    temp_files::{write_file_atomically, SHARD_STATE_FILE_SUFFIX},
+};
+use futures::stream::{iter as stream_iter, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    ffi::OsString,
+    io::{Error as IoError, Result as IoResult, SeekFrom},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    fs::{read as read_file, remove_file, File},
+    io::{AsyncSeekExt, AsyncWriteExt},
+    sync::{Semaphore, SemaphorePermit},
+};
+
+/// 默认的分片尺寸
+const DEFAULT_SHARD_SIZE: u64 = 4 << 20;
+
+/// 默认的分片并发数
+const DEFAULT_SHARD_CONCURRENCY: usize = 4;
+
+/// 预先写入零字节时每次写入的尺寸
+const PREALLOCATE_BUFFER_SIZE: usize = 1 << 20;
+
+/// 分片下载到文件时目标文件的空间分配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FileAllocation {
+    /// 仅设置文件长度，分片乱序写入时尚未写入的区域不占用磁盘空间
+    ///
+    /// 在支持稀疏文件的文件系统上不会预先写入零字节
+    Sparse,
+    /// 下载前预先写入零字节分配全部空间
+    ///
+    /// 适合不支持稀疏文件的文件系统，或需要提前确保磁盘空间充足、减少碎片的场景
+    Preallocate,
+}
+
+impl Default for FileAllocation {
+    #[inline]
+    fn default() -> Self {
+        Self::Sparse
+    }
+}
+
+/// 分片下载到文件的进度，每完成一个分片产生一次，开始下载前也会产生一次
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardedDownloadProgress {
+    /// 已经写入文件的分片数，包括断点续传前已经存在的分片
+    pub finished_shards: usize,
+    /// 分片总数
+    pub total_shards: usize,
+    /// 已经写入文件的字节数，包括断点续传前已经存在的分片
+    pub downloaded_bytes: u64,
+    /// 断点续传前已经存在的字节数
+    pub resumed_bytes: u64,
+    /// 对象总尺寸
+    pub total_bytes: u64,
+}
+
+/// 分片下载参数
+///
+/// 分片下载将对象切分为多个分片，按轮转方式有意地将各个分片分散到不同的 IO 域名上并发下载，
+/// 而非仅在失败时才切换域名。被惩罚的域名会被跳过，单个分片失败时仍按常规方式重试其他域名
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardedDownloadOptions {
+    shard_size: u64,
+    concurrency: usize,
+    max_concurrency_per_host: Option<NonZeroUsize>,
+    file_allocation: FileAllocation,
+}
+
+impl Default for ShardedDownloadOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            shard_size: DEFAULT_SHARD_SIZE,
+            concurrency: DEFAULT_SHARD_CONCURRENCY,
+            max_concurrency_per_host: None,
+            file_allocation: Default::default(),
+        }
+    }
+}
+
+impl ShardedDownloadOptions {
+    /// 获取分片尺寸
+    #[inline]
+    pub fn shard_size(&self) -> u64 {
+        self.shard_size
+    }
+
+    /// 设置分片尺寸，最小为 1
+    #[inline]
+    pub fn set_shard_size(&mut self, shard_size: u64) -> &mut Self {
+        self.shard_size = shard_size.max(1);
+        self
+    }
+
+    /// 获取最大并发数
+    #[inline]
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// 设置最大并发数，最小为 1
+    #[inline]
+    pub fn set_concurrency(&mut self, concurrency: usize) -> &mut Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// 获取单个域名的最大并发数
+    #[inline]
+    pub fn max_concurrency_per_host(&self) -> Option<NonZeroUsize> {
+        self.max_concurrency_per_host
+    }
+
+    /// 设置单个域名的最大并发数，为 None 时不限制
+    ///
+    /// 所有域名均达到并发上限时，分片将等待轮转到的域名空闲后再下载
+    #[inline]
+    pub fn set_max_concurrency_per_host(
+        &mut self,
+        max_concurrency_per_host: Option<NonZeroUsize>,
+    ) -> &mut Self {
+        self.max_concurrency_per_host = max_concurrency_per_host;
+        self
+    }
+
+    /// 获取分片下载到文件时目标文件的空间分配方式
+    #[inline]
+    pub fn file_allocation(&self) -> FileAllocation {
+        self.file_allocation
+    }
+
+    /// 设置分片下载到文件时目标文件的空间分配方式，默认为稀疏文件
+    #[inline]
+    pub fn set_file_allocation(&mut self, file_allocation: FileAllocation) -> &mut Self {
+        self.file_allocation = file_allocation;
+        self
+    }
+
+    /// 将指定长度的对象切分为分片，返回每个分片的开始偏移量和长度
+    pub(super) fn shards(&self, size: u64) -> Vec<(u64, u64)> {
+        let mut shards = Vec::with_capacity((size / self.shard_size + 1) as usize);
+        let mut pos = 0;
+        while pos < size {
+            let len = self.shard_size.min(size - pos);
+            shards.push((pos, len));
+            pos += len;
+        }
+        shards
+    }
+}
+
+/// 分片的域名分配器，按轮转方式为分片分配域名，并限制每个域名的并发数
+#[derive(Debug)]
+pub(super) struct ShardHosts {
+    hosts: Vec<String>,
+    limits: Option<Vec<Semaphore>>,
+}
+
+impl ShardHosts {
+    pub(super) fn new(hosts: Vec<String>, max_concurrency_per_host: Option<NonZeroUsize>) -> Self {
+        let limits = max_concurrency_per_host
+            .map(|limit| hosts.iter().map(|_| Semaphore::new(limit.get())).collect());
+        Self { hosts, limits }
+    }
+
+    /// 为第 `index` 个分片分配域名
+    ///
+    /// 优先使用轮转到的域名，该域名达到并发上限时依次尝试后续域名，
+    /// 所有域名均达到上限时等待轮转到的域名空闲。没有可用域名时返回 None，由常规方式选择域名
+    pub(super) async fn acquire(
+        &self,
+        index: usize,
+    ) -> Option<(&str, Option<SemaphorePermit<'_>>)> {
+        if self.hosts.is_empty() {
+            return None;
+        }
+        let start = index % self.hosts.len();
+        let limits = if let Some(limits) = self.limits.as_ref() {
+            limits
+        } else {
+            return Some((&self.hosts[start], None));
+        };
+        for offset in 0..self.hosts.len() {
+            let i = (start + offset) % self.hosts.len();
+            if let Ok(permit) = limits[i].try_acquire() {
+                return Some((&self.hosts[i], Some(permit)));
+            }
+        }
+        let permit = limits[start].acquire().await.ok();
+        Some((&self.hosts[start], permit))
+    }
+}
+
+/// 断点续传状态，记录已经写入文件的分片，保存在目标文件旁的 `.shards` 文件中
+///
+/// 同时记录对象的 Etag，对象被覆盖后已经写入的分片不再可信
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ShardState {
+    size: u64,
+    shard_size: u64,
+    #[serde(default)]
+    etag: Option<String>,
+    finished: BTreeSet<usize>,
+}
+
+pub(super) fn shard_state_path(path: &Path) -> PathBuf {
+    let mut state_path = OsString::from(path.as_os_str());
+    state_path.push(SHARD_STATE_FILE_SUFFIX);
+    state_path.into()
+}
+
+async fn load_shard_state(
+    state_path: &Path,
+    size: u64,
+    shard_size: u64,
+    etag: Option<&str>,
+    file: &File,
+) -> Option<ShardState> {
+    let state: ShardState = serde_json::from_slice(&read_file(state_path).await.ok()?).ok()?;
+    let file_size = file.metadata().await.ok()?.len();
+    if state.size == size
+        && state.shard_size == shard_size
+        && state.etag.as_deref() == etag
+        && file_size == size
+    {
+        Some(state)
+    } else {
+        None
+    }
+}
+
+async fn allocate(file: &mut File, size: u64, file_allocation: FileAllocation) -> IoResult<()> {
+    file.set_len(0).await?;
+    match file_allocation {
+        FileAllocation::Sparse => file.set_len(size).await?,
+        FileAllocation::Preallocate => {
+            let zeros = vec![0u8; PREALLOCATE_BUFFER_SIZE];
+            file.seek(SeekFrom::Start(0)).await?;
+            let mut rest = size;
+            while rest > 0 {
+                let len = rest.min(zeros.len() as u64) as usize;
+                file.write_all(&zeros[..len]).await?;
+                rest -= len as u64;
+            }
+            file.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+/// 分片下载对象到已经打开的目标文件中，支持断点续传
+///
+/// 分片完成后立即写入文件的对应位置，并将其记录在断点续传状态文件中，
+/// 全部分片完成后删除状态文件。对象尺寸、Etag 或分片尺寸变化时将重新下载整个对象。
+/// 下载在会话中进行，各个分片请求都携带 `If-Match` 头，以保证所有分片来自同一版本的对象
+pub(super) async fn download_shards_to_file(
+    reader: &AsyncRangeReaderWithRangeReader,
+    key: &str,
+    path: &Path,
+    file: &mut File,
+    options: &ShardedDownloadOptions,
+    mut on_progress: impl FnMut(&ShardedDownloadProgress) + Send,
+) -> IoResult<u64> {
+    let reader = &reader.to_owned().with_session();
+    let size = reader.file_size(key).await?;
+    let etag = reader.session_etag();
+    let shards = options.shards(size);
+    let state_path = shard_state_path(path);
+    let loaded = load_shard_state(
+        &state_path,
+        size,
+        options.shard_size(),
+        etag.as_deref(),
+        file,
+    )
+    .await;
+    let mut state = match loaded {
+        Some(state) => state,
+        None => {
+            allocate(file, size, options.file_allocation()).await?;
+            ShardState {
+                size,
+                shard_size: options.shard_size(),
+                etag,
+                finished: Default::default(),
+            }
+        }
+    };
+    let resumed_bytes = state
+        .finished
+        .iter()
+        .filter_map(|&index| shards.get(index))
+        .map(|&(_, len)| len)
+        .sum();
+    let mut progress = ShardedDownloadProgress {
+        finished_shards: state.finished.len(),
+        total_shards: shards.len(),
+        downloaded_bytes: resumed_bytes,
+        resumed_bytes,
+        total_bytes: size,
+    };
+    on_progress(&progress);
+
+    let hosts = reader.shard_hosts(options).await;
+    let pending: Vec<_> = shards
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|(index, _)| !state.finished.contains(index))
+        .collect();
+    let mut downloads = stream_iter(pending)
+        .map(|(index, (pos, len))| {
+            let hosts = &hosts;
+            async move {
+                let data = reader.read_shard(key, hosts, index, pos, len).await?;
+                Ok::<_, IoError>((index, pos, data))
+            }
+        })
+        .buffer_unordered(options.concurrency().max(1));
+    while let Some((index, pos, data)) = downloads.try_next().await? {
+        file.seek(SeekFrom::Start(pos)).await?;
+        file.write_all(&data).await?;
+        file.flush().await?;
+        // 分片数据落盘后才记录为已完成，避免断电后断点续传跳过实际未写入的分片
+        file.sync_data().await?;
+        state.finished.insert(index);
+        write_file_atomically(&state_path, &serde_json::to_vec(&state)?).await?;
+        progress.finished_shards += 1;
+        progress.downloaded_bytes += data.len() as u64;
+        on_progress(&progress);
+    }
+    file.sync_all().await?;
+    remove_file(&state_path).await.ok();
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shards() {
+        let mut options = ShardedDownloadOptions::default();
+        options.set_shard_size(4);
+        assert_eq!(options.shards(10), vec![(0, 4), (4, 4), (8, 2)]);
+        assert_eq!(options.shards(8), vec![(0, 4), (4, 4)]);
+        assert!(options.shards(0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_shard_hosts() {
+        let hosts = ShardHosts::new(
+            vec!["a".to_owned(), "b".to_owned(), "c".to_owned()],
+            NonZeroUsize::new(1),
+        );
+        let (host_0, permit_0) = hosts.acquire(0).await.unwrap();
+        assert_eq!(host_0, "a");
+        let (host_1, _permit_1) = hosts.acquire(1).await.unwrap();
+        assert_eq!(host_1, "b");
+        // 轮转到的域名 a 已达到并发上限，使用下一个空闲的域名
+        let (host_3, _permit_3) = hosts.acquire(3).await.unwrap();
+        assert_eq!(host_3, "c");
+        drop(permit_0);
+        let (host_4, _permit_4) = hosts.acquire(4).await.unwrap();
+        assert_eq!(host_4, "a");
+
+        let unlimited = ShardHosts::new(vec!["a".to_owned(), "b".to_owned()], None);
+        assert_eq!(unlimited.acquire(0).await.unwrap().0, "a");
+        assert_eq!(unlimited.acquire(1).await.unwrap().0, "b");
+        assert_eq!(unlimited.acquire(2).await.unwrap().0, "a");
+        assert!(ShardHosts::new(vec![], None).acquire(0).await.is_none());
+    }
+}
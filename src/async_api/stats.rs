@@ -0,0 +1,165 @@
+use super::dot::ApiName;
+use std::{collections::BTreeMap, convert::TryInto, sync::Mutex, time::Duration};
+
+/// 下载器的累计统计信息
+///
+/// 从下载器创建或上次调用 `reset_stats()` 开始累计，仅统计当前下载器及其通过 `with_overrides()` 创建的下载器，
+/// 与全局的打点数据互为补充
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReaderStats {
+    requests: BTreeMap<String, u64>,
+    failed_requests: u64,
+    downloaded_bytes: u64,
+    retries: u64,
+    failovers: u64,
+    cache_hits: u64,
+    buffer_high_water_mark: u64,
+    total_latency: Duration,
+}
+
+impl ReaderStats {
+    /// 获取各 API 的调用次数，键为 API 名称
+    #[inline]
+    pub fn requests(&self) -> &BTreeMap<String, u64> {
+        &self.requests
+    }
+
+    /// 获取所有 API 的调用总次数
+    #[inline]
+    pub fn total_requests(&self) -> u64 {
+        self.requests.values().sum()
+    }
+
+    /// 获取调用失败的次数
+    #[inline]
+    pub fn failed_requests(&self) -> u64 {
+        self.failed_requests
+    }
+
+    /// 获取下载的字节数
+    #[inline]
+    pub fn downloaded_bytes(&self) -> u64 {
+        self.downloaded_bytes
+    }
+
+    /// 获取重试次数，即除每次调用的首次请求以外的 HTTP 请求次数
+    #[inline]
+    pub fn retries(&self) -> u64 {
+        self.retries
+    }
+
+    /// 获取请求失败后切换到其他域名的次数
+    #[inline]
+    pub fn failovers(&self) -> u64 {
+        self.failovers
+    }
+
+    /// 获取命中本地缓存而无需访问服务器的次数
+    #[inline]
+    pub fn cache_hits(&self) -> u64 {
+        self.cache_hits
+    }
+
+    /// 获取下载到输出流时缓冲区的最高水位，即已经读取但尚未写入输出流的最大字节数
+    ///
+    /// 接近缓冲区尺寸时说明输出流的写入速度跟不上下载速度
+    #[inline]
+    pub fn buffer_high_water_mark(&self) -> u64 {
+        self.buffer_high_water_mark
+    }
+
+    /// 获取 API 调用的平均耗时，尚未调用过 API 时返回 None
+    #[inline]
+    pub fn average_latency(&self) -> Option<Duration> {
+        let total_requests = self.total_requests();
+        if total_requests > 0 {
+            Some(self.total_latency / total_requests.try_into().unwrap_or(u32::MAX))
+        } else {
+            None
+        }
+    }
+}
+
+/// 记录下载器的统计信息，由克隆后的下载器共享
+#[derive(Debug, Default)]
+pub(super) struct StatsRecorder(Mutex<ReaderStats>);
+
+impl StatsRecorder {
+    pub(super) fn record_call(&self, api: ApiName, successful: bool, elapsed: Duration) {
+        let mut stats = self.0.lock().unwrap();
+        *stats.requests.entry(api.to_string()).or_default() += 1;
+        if !successful {
+            stats.failed_requests += 1;
+        }
+        stats.total_latency += elapsed;
+    }
+
+    pub(super) fn record_bytes(&self, bytes: u64) {
+        self.0.lock().unwrap().downloaded_bytes += bytes;
+    }
+
+    pub(super) fn record_retry(&self) {
+        self.0.lock().unwrap().retries += 1;
+    }
+
+    pub(super) fn record_failover(&self) {
+        self.0.lock().unwrap().failovers += 1;
+    }
+
+    pub(super) fn record_cache_hit(&self) {
+        self.0.lock().unwrap().cache_hits += 1;
+    }
+
+    pub(super) fn record_buffered_bytes(&self, bytes: u64) {
+        let mut stats = self.0.lock().unwrap();
+        stats.buffer_high_water_mark = stats.buffer_high_water_mark.max(bytes);
+    }
+
+    pub(super) fn snapshot(&self) -> ReaderStats {
+        self.0.lock().unwrap().to_owned()
+    }
+
+    pub(super) fn reset(&self) {
+        *self.0.lock().unwrap() = Default::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_recorder() {
+        let recorder = StatsRecorder::default();
+        assert_eq!(recorder.snapshot().average_latency(), None);
+
+        recorder.record_call(ApiName::RangeReaderReadAt, true, Duration::from_millis(100));
+        recorder.record_call(
+            ApiName::RangeReaderReadAt,
+            false,
+            Duration::from_millis(300),
+        );
+        recorder.record_call(ApiName::RangeReaderExist, true, Duration::from_millis(200));
+        recorder.record_bytes(1024);
+        recorder.record_retry();
+        recorder.record_failover();
+        recorder.record_cache_hit();
+        recorder.record_buffered_bytes(4096);
+        recorder.record_buffered_bytes(2048);
+
+        let stats = recorder.snapshot();
+        assert_eq!(stats.requests().get("range_reader_read_at"), Some(&2));
+        assert_eq!(stats.requests().get("range_reader_exist"), Some(&1));
+        assert_eq!(stats.total_requests(), 3);
+        assert_eq!(stats.failed_requests(), 1);
+        assert_eq!(stats.downloaded_bytes(), 1024);
+        assert_eq!(stats.retries(), 1);
+        assert_eq!(stats.failovers(), 1);
+        assert_eq!(stats.cache_hits(), 1);
+        assert_eq!(stats.buffer_high_water_mark(), 4096);
+        assert_eq!(stats.average_latency(), Some(Duration::from_millis(200)));
+
+        recorder.reset();
+        assert_eq!(recorder.snapshot(), ReaderStats::default());
+    }
+}
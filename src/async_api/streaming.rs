@@ -0,0 +1,204 @@
+use super::{
+    super::base::download::{check_object_size, is_content_encoded_error},
+    retrier::AsyncRangeReaderWithRangeReader,
+};
+use futures::{
+    channel::mpsc::unbounded,
+    future::try_join,
+    stream::{iter as stream_iter, Stream, StreamExt, TryStreamExt},
+    TryFutureExt,
+};
+use std::{
+    convert::TryInto,
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    sync::atomic::{AtomicU64, Ordering::Relaxed},
+};
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::Semaphore,
+};
+
+/// 默认的下载缓冲区尺寸
+pub(super) const DEFAULT_DOWNLOAD_BUFFER_SIZE: u64 = 8 << 20;
+
+/// 下载缓冲区的最大尺寸
+const MAX_DOWNLOAD_BUFFER_SIZE: u64 = 256 << 20;
+
+/// 每次读取的最大分块尺寸
+const MAX_CHUNK_SIZE: u64 = 1 << 20;
+
+/// 同时读取的最大分块数
+const MAX_CONCURRENT_CHUNKS: usize = 4;
+
+/// 记录已经读取但尚未写入输出流的字节数及其最高水位
+#[derive(Debug, Default)]
+struct BufferGauge {
+    buffered: AtomicU64,
+    high_water_mark: AtomicU64,
+}
+
+impl BufferGauge {
+    fn fill(&self, bytes: u64) {
+        let buffered = self.buffered.fetch_add(bytes, Relaxed) + bytes;
+        self.high_water_mark.fetch_max(buffered, Relaxed);
+    }
+
+    fn drain(&self, bytes: u64) {
+        self.buffered.fetch_sub(bytes, Relaxed);
+    }
+
+    fn high_water_mark(&self) -> u64 {
+        self.high_water_mark.load(Relaxed)
+    }
+}
+
+/// 将对象的全部区域切分为分块
+pub(super) fn chunks(size: u64, chunk_size: u64) -> Vec<(u64, u64)> {
+    let mut chunks = Vec::new();
+    let mut pos = 0;
+    while pos < size {
+        let len = chunk_size.min(size - pos);
+        chunks.push((pos, len));
+        pos += len;
+    }
+    chunks
+}
+
+/// 分块读取对象并写入输出流
+///
+/// 读取和写入并发进行，已经读取但尚未写入的数据不超过缓冲区尺寸，
+/// 缓冲区已满时暂停读取，直到输出流消费了缓冲区中的数据，从而将输出流的背压传递给下载过程。
+/// 以压缩编码存储的对象无法预先获取尺寸，将下载整个对象后再写入
+pub(super) async fn download_to_writer(
+    reader: &AsyncRangeReaderWithRangeReader,
+    key: &str,
+    writer: &mut (dyn AsyncWrite + Unpin + Send),
+) -> IoResult<u64> {
+    let size = match reader.file_size(key).await {
+        Ok(size) => size,
+        Err(err) if is_content_encoded_error(&err) => {
+            let data = reader.download(key).await?;
+            writer.write_all(&data).await?;
+            writer.flush().await?;
+            return Ok(data.len() as u64);
+        }
+        Err(err) => return Err(err),
+    };
+    check_object_size(size, reader.max_object_size())?;
+
+    let buffer_size = reader
+        .download_buffer_size()
+        .clamp(1, MAX_DOWNLOAD_BUFFER_SIZE);
+    let chunk_size = buffer_size.min(MAX_CHUNK_SIZE);
+    let permits = Semaphore::new(buffer_size.try_into().unwrap_or(usize::MAX));
+    let gauge = BufferGauge::default();
+    let (tx, mut rx) = unbounded();
+
+    let producer = {
+        let (permits, gauge) = (&permits, &gauge);
+        async move {
+            let mut downloads = stream_iter(chunks(size, chunk_size))
+                .map(|(pos, len)| async move {
+                    // 信号量不会被关闭，获取许可不会失败
+                    let permit = permits
+                        .acquire_many(len.try_into().unwrap_or(u32::MAX))
+                        .await
+                        .expect("download buffer is never closed");
+                    let data = read_chunk(reader, key, pos, len).await?;
+                    gauge.fill(len);
+                    Ok::<_, IoError>((data, permit))
+                })
+                .buffered(MAX_CONCURRENT_CHUNKS);
+            while let Some(chunk) = downloads.try_next().await? {
+                if tx.unbounded_send(chunk).is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        }
+    };
+    let consumer = async {
+        let mut written = 0;
+        while let Some((data, permit)) = rx.next().await {
+            writer.write_all(&data).await?;
+            written += data.len() as u64;
+            gauge.drain(data.len() as u64);
+            drop(permit);
+        }
+        writer.flush().await?;
+        Ok(written)
+    };
+    let result = try_join(producer, consumer).await;
+    reader.record_buffered_bytes(gauge.high_water_mark());
+    result.map(|((), written)| written)
+}
+
+/// 读取对象的一个分块，数据不足时返回 [`IoErrorKind::UnexpectedEof`] 错误
+pub(super) async fn read_chunk(
+    reader: &AsyncRangeReaderWithRangeReader,
+    key: &str,
+    pos: u64,
+    len: u64,
+) -> IoResult<Vec<u8>> {
+    let data = reader.read_at(key, pos, len).await?;
+    if (data.len() as u64) < len {
+        return Err(IoError::new(
+            IoErrorKind::UnexpectedEof,
+            format!(
+                "Expected {} bytes at {}, got {} bytes",
+                len,
+                pos,
+                data.len()
+            ),
+        ));
+    }
+    Ok(data)
+}
+
+/// 分块读取对象从 `pos` 开始的 `len` 个字节，返回按顺序输出各个分块的数据流
+///
+/// 超出对象尾部的部分将被忽略。分块尺寸不超过下载缓冲区尺寸，最多同时读取 [`MAX_CONCURRENT_CHUNKS`] 个分块
+pub(super) fn read_stream(
+    reader: AsyncRangeReaderWithRangeReader,
+    key: String,
+    pos: u64,
+    len: u64,
+) -> impl Stream<Item = IoResult<Vec<u8>>> + Send + 'static {
+    let chunk_size = reader.download_buffer_size().clamp(1, MAX_CHUNK_SIZE);
+    async move {
+        let size = reader.file_size(&key).await?;
+        check_object_size(size, reader.max_object_size())?;
+        let end = pos.saturating_add(len).min(size);
+        let begin = pos.min(end);
+        Ok(stream_iter(chunks(end - begin, chunk_size))
+            .map(move |(offset, len)| {
+                let (reader, key) = (reader.to_owned(), key.to_owned());
+                async move { read_chunk(&reader, &key, begin + offset, len).await }
+            })
+            .buffered(MAX_CONCURRENT_CHUNKS))
+    }
+    .try_flatten_stream()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks() {
+        assert_eq!(chunks(10, 4), vec![(0, 4), (4, 4), (8, 2)]);
+        assert_eq!(chunks(8, 4), vec![(0, 4), (4, 4)]);
+        assert!(chunks(0, 4).is_empty());
+    }
+
+    #[test]
+    fn test_buffer_gauge() {
+        let gauge = BufferGauge::default();
+        gauge.fill(4);
+        gauge.fill(4);
+        gauge.drain(4);
+        gauge.fill(2);
+        gauge.drain(6);
+        assert_eq!(gauge.high_water_mark(), 8);
+    }
+}
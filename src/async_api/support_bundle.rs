@@ -0,0 +1,140 @@
+use super::{
+    super::{config::Config, events::recent_events},
+    cache_dir::cache_dir_path_of,
+    diagnose::{current_config, diagnose, diagnose_config, DiagnosticReport},
+    dot::DOT_FILE_NAME,
+};
+use serde::Serialize;
+use serde_json::Value as JSONValue;
+use std::{
+    io::{ErrorKind as IoErrorKind, Result as IoResult},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::fs::{read, write};
+
+const REDACTED: &str = "******";
+const SECRET_FIELDS: &[&str] = &["secret_key", "monitor_secret_key"];
+
+#[derive(Debug, Serialize)]
+struct SupportBundle {
+    version: &'static str,
+    generated_at: u64,
+    config: Option<JSONValue>,
+    diagnostics: DiagnosticReport,
+    dot_backlog: DotBacklog,
+    recent_events: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct DotBacklog {
+    path: Option<String>,
+    bytes: u64,
+    records: usize,
+}
+
+/// 导出支持包
+///
+/// 将当前七牛环境配置中 `key` 所在集群的配置（Secret Key 已脱敏），诊断报告，
+/// 打点记录积压情况以及进程内保留的最近事件写入 `path` 指定的 JSON 文件，用于附在工单中。
+/// 保留的事件数量通过 [`crate::set_event_history_capacity`] 设置
+pub async fn export_support_bundle(key: &str, path: impl AsRef<Path>) -> IoResult<()> {
+    let config = current_config(key);
+    let diagnostics = diagnose(key).await;
+    write_support_bundle(config.as_ref(), diagnostics, path.as_ref()).await
+}
+
+/// 为指定的七牛配置导出支持包
+///
+/// 与 [`export_support_bundle`] 相同，但不会解析环境变量指定的配置文件
+pub async fn export_support_bundle_for_config(
+    config: &Config,
+    path: impl AsRef<Path>,
+) -> IoResult<()> {
+    let diagnostics = diagnose_config(config).await;
+    write_support_bundle(Some(config), diagnostics, path.as_ref()).await
+}
+
+async fn write_support_bundle(
+    config: Option<&Config>,
+    diagnostics: DiagnosticReport,
+    path: &Path,
+) -> IoResult<()> {
+    let bundle = SupportBundle {
+        version: env!("CARGO_PKG_VERSION"),
+        generated_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default(),
+        config: config.map(redact_config).transpose()?,
+        diagnostics,
+        dot_backlog: dot_backlog().await?,
+        recent_events: recent_events()
+            .iter()
+            .map(|event| format!("{:?}", event))
+            .collect(),
+    };
+    write(path, serde_json::to_vec_pretty(&bundle)?).await
+}
+
+fn redact_config(config: &Config) -> IoResult<JSONValue> {
+    let mut value = serde_json::to_value(config)?;
+    if let Some(object) = value.as_object_mut() {
+        for &field in SECRET_FIELDS {
+            if let Some(secret) = object.get_mut(field) {
+                if !secret.is_null() {
+                    *secret = JSONValue::from(REDACTED);
+                }
+            }
+        }
+    }
+    Ok(value)
+}
+
+async fn dot_backlog() -> IoResult<DotBacklog> {
+    let path = match cache_dir_path_of(DOT_FILE_NAME).await {
+        Ok(path) => path,
+        Err(_) => return Ok(Default::default()),
+    };
+    let content = match read(&path).await {
+        Ok(content) => content,
+        Err(err) if err.kind() == IoErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err),
+    };
+    Ok(DotBacklog {
+        path: Some(path.display().to_string()),
+        bytes: content.len() as u64,
+        records: content
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .count(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_export_support_bundle_for_config() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let dir = tempdir()?;
+        let path = dir.path().join("bundle.json");
+        let config = Config::builder("ak", "sk", "bucket", Some(vec![]))
+            .monitor_credential(Some("monitor-ak".to_owned()), Some("monitor-sk".to_owned()))
+            .build();
+        export_support_bundle_for_config(&config, &path).await?;
+
+        let bundle: JSONValue = serde_json::from_slice(&read(&path).await?)?;
+        assert_eq!(bundle["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(bundle["config"]["access_key"], "ak");
+        assert_eq!(bundle["config"]["secret_key"], REDACTED);
+        assert_eq!(bundle["config"]["monitor_secret_key"], REDACTED);
+        assert_eq!(bundle["diagnostics"]["config"]["status"], "skipped");
+        assert!(bundle["dot_backlog"]["records"].is_u64());
+        assert!(bundle["recent_events"].is_array());
+        Ok(())
+    }
+}
@@ -1,30 +1,46 @@
 use super::{
     super::{
-        base::download::RangeReaderBuilder as BaseRangeReaderBuilder,
-        config::{with_current_qiniu_config, Config},
+        base::{
+            checksum::ChecksumAlgorithm,
+            download::{ObjectMetadata, RangeReaderBuilder as BaseRangeReaderBuilder},
+            region_hosts::RegionHosts,
+            rng::SharedRng,
+        },
+        config::{build_range_reader_builder_from_config, with_current_qiniu_config, Config},
         sync_api::WriteSeek,
     },
     download::AsyncRangeReaderBuilder,
+    health::record_cancelled_sync_request,
+    integrity::{download_to_dir, download_to_path},
+    overrides::ReadOverrides,
     retrier::AsyncRangeReaderWithRangeReader,
+    sharding::ShardedDownloadOptions,
+    stats::{ReaderStats, StatsRecorder},
     RangePart,
 };
 use futures::{
     future::poll_fn,
-    pin_mut, ready,
+    pin_mut,
     task::{waker, ArcWake},
 };
-use log::{debug, error, trace};
+use log::{debug, error, trace, warn};
 use positioned_io::ReadAt;
 use std::{
     future::Future,
-    io::{Error as IoError, Result as IoResult},
-    sync::Arc,
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed},
+        Arc,
+    },
     task::{Context, Poll},
-    thread::{current as current_thread, park as park_thread},
+    thread::{current as current_thread, park as park_thread, park_timeout as park_thread_timeout},
     thread::{Builder as ThreadBuilder, JoinHandle, Thread},
+    time::{Duration, Instant},
 };
+use thiserror::Error;
 use tokio::{
-    runtime::Builder as TokioRuntimeBuilder,
+    runtime::{Builder as TokioRuntimeBuilder, Runtime as TokioRuntime},
     spawn as spawn_tokio,
     sync::{
         mpsc::{unbounded_channel, UnboundedSender},
@@ -61,9 +77,16 @@ impl From<RangeReaderBuilder> for BaseRangeReaderBuilder {
 
 impl RangeReaderBuilder {
     pub(crate) fn build(mut self) -> RangeReader {
+        let request_timeout = self.0.sync_request_timeout();
+        let worker_threads = self.0.sync_worker_threads();
         RangeReader {
             key: self.0.take_key(),
-            handler: RangeReaderHandle::new(self),
+            bucket: self.0.bucket().to_owned(),
+            cluster_name: self.0.cluster_name().map(|name| name.to_owned()),
+            rng: SharedRng::new(self.0.random_seed()),
+            overrides: None,
+            stats: Default::default(),
+            handler: RangeReaderHandle::new(self, request_timeout, worker_threads),
         }
     }
 
@@ -78,15 +101,7 @@ trait BuildAsyncRangeReader: Send {
 
 impl BuildAsyncRangeReader for RangeReaderBuilder {
     fn build_async_range_reader(self) -> AsyncRangeReaderWithRangeReader {
-        let base = BaseRangeReaderBuilder::from(self);
-        let max_retry_concurrency = base.max_retry_concurrency;
-        let io_tries = base.io_tries;
-        let builder = AsyncRangeReaderBuilder::from(base);
-        AsyncRangeReaderWithRangeReader::new(
-            builder.build(),
-            max_retry_concurrency.unwrap_or(5),
-            io_tries,
-        )
+        AsyncRangeReaderWithRangeReader::from_base_builder(self.into()).0
     }
 }
 
@@ -100,24 +115,184 @@ impl BuildAsyncRangeReader for AsyncRangeReaderWithRangeReader {
 pub(crate) struct RangeReader {
     handler: RangeReaderHandle,
     key: String,
+    bucket: String,
+    cluster_name: Option<String>,
+    rng: SharedRng,
+    overrides: Option<ReadOverrides>,
+    stats: Arc<StatsRecorder>,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct RangeReaderHandle(Arc<RangeReaderHandleInner>);
 
 type OneshotResponse = Sender<Response>;
-type ThreadSender = UnboundedSender<(Request, OneshotResponse)>;
+type ThreadMessage = (
+    Request,
+    Option<ReadOverrides>,
+    Arc<StatsRecorder>,
+    OneshotResponse,
+    Arc<AtomicBool>,
+);
+type ThreadSender = UnboundedSender<ThreadMessage>;
 
 #[derive(Debug)]
 struct RangeReaderHandleInner {
     tx: Option<ThreadSender>,
     thread: Option<JoinHandle<()>>,
+    gauges: Arc<RuntimeGauges>,
+    request_timeout: Option<Duration>,
+    startup_error: Option<String>,
+}
+
+/// 同步接口内部运行时的负载
+///
+/// 峰值从内部运行时创建或上次调用 `reset_sync_runtime_load_peaks()` 开始统计，
+/// 通过同一份配置创建的下载器共享内部运行时，因此也共享负载统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncRuntimeLoad {
+    queued_requests: usize,
+    in_flight_requests: usize,
+    peak_queued_requests: usize,
+    peak_in_flight_requests: usize,
+}
+
+impl SyncRuntimeLoad {
+    /// 获取排队等待内部运行时处理的请求数量
+    #[inline]
+    pub fn queued_requests(&self) -> usize {
+        self.queued_requests
+    }
+
+    /// 获取内部运行时正在处理的请求数量
+    #[inline]
+    pub fn in_flight_requests(&self) -> usize {
+        self.in_flight_requests
+    }
+
+    /// 获取排队等待内部运行时处理的请求数量峰值
+    #[inline]
+    pub fn peak_queued_requests(&self) -> usize {
+        self.peak_queued_requests
+    }
+
+    /// 获取内部运行时正在处理的请求数量峰值
+    #[inline]
+    pub fn peak_in_flight_requests(&self) -> usize {
+        self.peak_in_flight_requests
+    }
+}
+
+#[derive(Debug, Default)]
+struct RuntimeGauges {
+    queued: AtomicUsize,
+    in_flight: AtomicUsize,
+    peak_queued: AtomicUsize,
+    peak_in_flight: AtomicUsize,
+}
+
+impl RuntimeGauges {
+    fn enqueue(&self) {
+        let queued = self.queued.fetch_add(1, Relaxed) + 1;
+        self.peak_queued.fetch_max(queued, Relaxed);
+    }
+
+    fn cancel_enqueue(&self) {
+        self.queued.fetch_sub(1, Relaxed);
+    }
+
+    /// 请求出队并开始处理，返回此时正在处理和排队中的请求数量
+    fn start(&self) -> (usize, usize) {
+        let queued = self.queued.fetch_sub(1, Relaxed) - 1;
+        let in_flight = self.in_flight.fetch_add(1, Relaxed) + 1;
+        self.peak_in_flight.fetch_max(in_flight, Relaxed);
+        (in_flight, queued)
+    }
+
+    fn finish(&self) {
+        self.in_flight.fetch_sub(1, Relaxed);
+    }
+
+    fn load(&self) -> SyncRuntimeLoad {
+        SyncRuntimeLoad {
+            queued_requests: self.queued.load(Relaxed),
+            in_flight_requests: self.in_flight.load(Relaxed),
+            peak_queued_requests: self.peak_queued.load(Relaxed),
+            peak_in_flight_requests: self.peak_in_flight.load(Relaxed),
+        }
+    }
+
+    fn reset_peaks(&self) {
+        self.peak_queued.store(self.queued.load(Relaxed), Relaxed);
+        self.peak_in_flight
+            .store(self.in_flight.load(Relaxed), Relaxed);
+    }
+}
+
+/// 请求处理完成或被取消时减少处理中的请求数量
+struct InFlightGuard(Arc<RuntimeGauges>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+/// 同步接口请求错误
+///
+/// 作为 [`std::io::Error`] 的内部错误返回，可以通过 `get_ref()` 和 `downcast_ref()` 获取
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SyncRequestError {
+    /// 请求在超时前仍未被内部运行时处理
+    #[error("Request is still queued after {timeout:?}, {queue_depth} requests are queued")]
+    Busy {
+        /// 超时时长
+        timeout: Duration,
+        /// 超时时排队中的请求数量
+        queue_depth: usize,
+    },
+    /// 请求已被内部运行时处理，但在超时前仍未完成
+    #[error("Request is timed out after {timeout:?}")]
+    TimedOut {
+        /// 超时时长
+        timeout: Duration,
+    },
+    /// 内部运行时不可用，例如运行时创建失败或运行时线程已经崩溃
+    #[error("Internal error: {message}")]
+    Internal {
+        /// 错误信息
+        message: String,
+    },
 }
 
 #[derive(Debug)]
 enum Request {
     UpdateUrls,
     IoUrls,
+    RegionHosts,
+    ReportExternalFailure {
+        host: String,
+        error: IoError,
+    },
+    ReportExternalSuccess {
+        host: String,
+    },
+    BanHost {
+        host: String,
+        duration: Duration,
+    },
+    SetHostBaseTimeout {
+        host: String,
+        base_timeout: Option<Duration>,
+    },
+    PinHosts {
+        hosts: Vec<String>,
+    },
+    InvalidateNegativeCache {
+        key: String,
+    },
+    PurgeConnections,
+    Overrides,
     ReadAt {
         key: String,
         pos: u64,
@@ -127,15 +302,44 @@ enum Request {
         key: String,
         ranges: Vec<(u64, u64)>,
     },
+    ChecksumRange {
+        key: String,
+        pos: u64,
+        size: u64,
+        algorithm: ChecksumAlgorithm,
+    },
+    ChecksumRanges {
+        key: String,
+        ranges: Vec<(u64, u64)>,
+        algorithm: ChecksumAlgorithm,
+        concurrency: usize,
+    },
     Exist {
         key: String,
     },
     FileSize {
         key: String,
     },
+    Stat {
+        key: String,
+    },
     Download {
         key: String,
     },
+    DownloadSharded {
+        key: String,
+        options: ShardedDownloadOptions,
+    },
+    DownloadToPath {
+        key: String,
+        path: PathBuf,
+        lock_timeout: Option<Duration>,
+    },
+    DownloadToDir {
+        key: String,
+        dir: PathBuf,
+        lock_timeout: Option<Duration>,
+    },
     ReadLastBytes {
         key: String,
         size: u64,
@@ -150,38 +354,44 @@ enum ResponseData {
     Bytes(Vec<u8>),
     BytesWithSize((Vec<u8>, u64)),
     Parts(Vec<RangePart>),
+    Checksums(Vec<Vec<u8>>),
     Bool(bool),
     U64(u64),
+    PathWithSize((PathBuf, u64)),
+    Metadata(Box<ObjectMetadata>),
+    Overrides(Box<ReadOverrides>),
+    RegionHosts(Vec<RegionHosts>),
 }
 
 impl Drop for RangeReaderHandleInner {
     fn drop(&mut self) {
-        let id = self
-            .thread
-            .as_ref()
-            .map(|h| h.thread().id())
-            .expect("thread not dropped yet");
-
-        trace!("closing runtime thread ({:?})", id);
         self.tx.take();
-        trace!("signaled close for runtime thread ({:?})", id);
-        self.thread.take().map(|h| h.join());
-        trace!("closed runtime thread ({:?})", id);
+        if let Some(thread) = self.thread.take() {
+            let id = thread.thread().id();
+            trace!("closing runtime thread ({:?})", id);
+            if thread.join().is_err() {
+                error!("runtime thread ({:?}) panicked", id);
+            }
+            trace!("closed runtime thread ({:?})", id);
+        }
     }
 }
 
 impl RangeReaderHandle {
-    fn new(builder: impl BuildAsyncRangeReader + 'static) -> Self {
-        let (tx, rx) = unbounded_channel::<(Request, OneshotResponse)>();
+    fn new(
+        builder: impl BuildAsyncRangeReader + 'static,
+        request_timeout: Option<Duration>,
+        worker_threads: usize,
+    ) -> Self {
+        let (tx, rx) = unbounded_channel::<ThreadMessage>();
         let (spawn_tx, spawn_rx) = channel::<IoResult<()>>();
+        let gauges = Arc::new(RuntimeGauges::default());
+        let worker_gauges = gauges.to_owned();
 
-        let join_handle = ThreadBuilder::new()
+        let spawned = ThreadBuilder::new()
             .name("qiniu-download-internal-sync-runtime".into())
             .spawn(move || {
-                let rt = match TokioRuntimeBuilder::new_current_thread()
-                    .enable_all()
-                    .build()
-                {
+                let rt = match build_runtime(worker_threads) {
                     Ok(rt) => rt,
                     Err(e) => {
                         if let Err(e) = spawn_tx.send(Err(e)) {
@@ -197,9 +407,27 @@ impl RangeReaderHandle {
                         return;
                     }
                     let mut rx = rx;
-                    while let Some((req, req_tx)) = rx.recv().await {
-                        let req_fut = req.send(range_reader.to_owned());
-                        spawn_tokio(forward(req_fut, req_tx));
+                    while let Some((req, overrides, stats, req_tx, dequeued)) = rx.recv().await {
+                        dequeued.store(true, Relaxed);
+                        let (in_flight, queued) = worker_gauges.start();
+                        let range_reader = match overrides {
+                            Some(overrides) => range_reader.to_owned().with_overrides(overrides),
+                            None => range_reader.to_owned(),
+                        }
+                        .with_stats(stats);
+                        let dotter = range_reader.to_owned();
+                        let in_flight_guard = InFlightGuard(worker_gauges.to_owned());
+                        let req_fut = async move {
+                            let _in_flight_guard = in_flight_guard;
+                            req.send(range_reader).await
+                        };
+                        spawn_tokio(async move {
+                            forward(req_fut, req_tx).await;
+                            if let Err(err) = dotter.dot_sync_runtime_load(in_flight, queued).await
+                            {
+                                warn!("Failed to dot sync runtime load: {}", err);
+                            }
+                        });
                     }
 
                     debug!("({:?}) Receiver is shutdown", current_thread().id());
@@ -209,33 +437,111 @@ impl RangeReaderHandle {
                 trace!("({:?}) end runtime::block_on", current_thread().id());
                 drop(rt);
                 trace!("({:?}) finished", current_thread().id());
-            })
-            .expect("Failed to spawn thread");
+            });
+        let join_handle = match spawned {
+            Ok(join_handle) => join_handle,
+            Err(err) => {
+                error!("Failed to spawn runtime thread: {}", err);
+                return Self::unavailable(None, format!("failed to spawn runtime thread: {}", err));
+            }
+        };
 
-        match block_on(spawn_rx) {
+        match block_on(spawn_rx, None).expect("no deadline is set") {
             Ok(Ok(())) => Self(Arc::new(RangeReaderHandleInner {
                 tx: Some(tx),
                 thread: Some(join_handle),
+                gauges,
+                request_timeout,
+                startup_error: None,
             })),
-            Ok(Err(err)) => runtime_create_error(err),
-            Err(_) => event_loop_panicked(),
+            Ok(Err(err)) => {
+                error!("Failed to create tokio runtime: {}", err);
+                Self::unavailable(
+                    Some(join_handle),
+                    format!("tokio runtime creation error: {}", err),
+                )
+            }
+            Err(_) => {
+                error!("Event loop thread panicked during startup");
+                Self::unavailable(Some(join_handle), "event loop thread panicked".to_owned())
+            }
         }
     }
 
-    fn execute_request(&self, request: Request) -> Response {
+    fn unavailable(thread: Option<JoinHandle<()>>, message: String) -> Self {
+        Self(Arc::new(RangeReaderHandleInner {
+            tx: None,
+            thread,
+            gauges: Default::default(),
+            request_timeout: None,
+            startup_error: Some(message),
+        }))
+    }
+
+    fn execute_request(
+        &self,
+        request: Request,
+        overrides: Option<ReadOverrides>,
+        stats: Arc<StatsRecorder>,
+    ) -> Response {
+        let sender = match self.0.tx.as_ref() {
+            Some(sender) => sender,
+            None => {
+                return Err(internal_error(
+                    self.0
+                        .startup_error
+                        .to_owned()
+                        .unwrap_or_else(|| "runtime thread exited early".to_owned()),
+                ))
+            }
+        };
         let (tx, rx) = channel();
-        self.0
-            .tx
-            .as_ref()
-            .expect("core thread exited early")
-            .send((request, tx))
-            .expect("core thread panicked");
+        let dequeued = Arc::new(AtomicBool::new(false));
+        self.0.gauges.enqueue();
+        if sender
+            .send((request, overrides, stats, tx, dequeued.to_owned()))
+            .is_err()
+        {
+            self.0.gauges.cancel_enqueue();
+            return Err(event_loop_panicked());
+        }
 
-        match block_on(async move { rx.await.map_err::<IoError, _>(|_| event_loop_panicked()) }) {
-            Ok(result) => result,
-            Err(err) => Err(err),
+        let deadline = self
+            .0
+            .request_timeout
+            .map(|timeout| Instant::now() + timeout);
+        match block_on(
+            async move { rx.await.map_err(|_| event_loop_panicked()) },
+            deadline,
+        ) {
+            Some(Ok(result)) => result,
+            Some(Err(err)) => Err(err),
+            None => {
+                let timeout = self.0.request_timeout.unwrap_or_default();
+                let err = if dequeued.load(Relaxed) {
+                    SyncRequestError::TimedOut { timeout }
+                } else {
+                    SyncRequestError::Busy {
+                        timeout,
+                        queue_depth: self.queue_depth(),
+                    }
+                };
+                Err(IoError::new(IoErrorKind::TimedOut, err))
+            }
         }
     }
+
+    pub(crate) fn queue_depth(&self) -> usize {
+        self.0.gauges.queued.load(Relaxed)
+    }
+
+    pub(crate) fn load(&self) -> SyncRuntimeLoad {
+        self.0.gauges.load()
+    }
+
+    pub(crate) fn reset_load_peaks(&self) {
+        self.0.gauges.reset_peaks()
+    }
 }
 
 impl RangeReader {
@@ -247,32 +553,237 @@ impl RangeReader {
         with_current_qiniu_config(|config| {
             config.and_then(|config| {
                 config.with_key(&key.to_owned(), |config| {
-                    config.get_or_init_async_range_reader_inner(move || {
-                        let max_retry_concurrency = config.max_retry_concurrency().unwrap_or(5);
-                        let total_retries = config.retry().unwrap_or(10);
-                        RangeReaderHandle::new(AsyncRangeReaderWithRangeReader::new(
-                            AsyncRangeReaderBuilder::from_config(String::new(), config).build(),
-                            max_retry_concurrency,
-                            total_retries,
-                        ))
-                    })
+                    let handler = config.get_or_init_async_range_reader_inner(move || {
+                        let (range_reader, _) = AsyncRangeReaderWithRangeReader::from_base_builder(
+                            build_range_reader_builder_from_config(String::new(), config),
+                        );
+                        RangeReaderHandle::new(
+                            range_reader,
+                            config.sync_request_timeout(),
+                            config.sync_worker_threads().unwrap_or(1),
+                        )
+                    });
+                    (
+                        handler,
+                        config.bucket().to_owned(),
+                        config.cluster_name().map(|name| name.to_owned()),
+                        SharedRng::new(config.random_seed()),
+                    )
                 })
             })
         })
-        .map(|handler| Self { handler, key })
+        .map(|(handler, bucket, cluster_name, rng)| Self {
+            handler,
+            key,
+            bucket,
+            cluster_name,
+            rng,
+            overrides: None,
+            stats: Default::default(),
+        })
+    }
+
+    pub(crate) fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn bucket(&self) -> &str {
+        self.overrides
+            .as_ref()
+            .and_then(|overrides| overrides.bucket())
+            .unwrap_or(&self.bucket)
+    }
+
+    pub(crate) fn cluster_name(&self) -> Option<&str> {
+        self.cluster_name.as_deref()
+    }
+
+    pub(crate) fn rng(&self) -> &SharedRng {
+        &self.rng
+    }
+
+    pub(crate) fn overrides(&self) -> IoResult<ReadOverrides> {
+        if let Some(overrides) = &self.overrides {
+            return Ok(overrides.to_owned());
+        }
+        match self.execute(Request::Overrides) {
+            Ok(ResponseData::Overrides(overrides)) => Ok(*overrides),
+            Ok(data) => Err(unexpected_response(data)),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// 与当前下载器共享内部运行时，请求发送给运行时后再使用新的调用参数
+    pub(crate) fn with_overrides(&self, f: impl FnOnce(&mut ReadOverrides)) -> IoResult<Self> {
+        let mut overrides = self.overrides()?;
+        f(&mut overrides);
+        Ok(Self {
+            overrides: Some(overrides),
+            ..self.to_owned()
+        })
+    }
+
+    /// 统计信息记录在当前下载器中，共享同一个运行时的其他下载器不受影响
+    pub(crate) fn stats(&self) -> ReaderStats {
+        self.stats.snapshot()
     }
 
-    pub(crate) fn update_urls(&self) -> bool {
+    pub(crate) fn reset_stats(&self) {
+        self.stats.reset()
+    }
+
+    pub(crate) fn update_urls(&self) -> IoResult<bool> {
         match self.execute(Request::UpdateUrls) {
-            Ok(ResponseData::Bool(b)) => b,
-            response => unexpected_response(response),
+            Ok(ResponseData::Bool(b)) => Ok(b),
+            Ok(data) => Err(unexpected_response(data)),
+            Err(err) => Err(err),
         }
     }
 
+    pub(crate) fn queue_depth(&self) -> usize {
+        self.handler.queue_depth()
+    }
+
+    pub(crate) fn runtime_load(&self) -> SyncRuntimeLoad {
+        self.handler.load()
+    }
+
+    pub(crate) fn reset_runtime_load_peaks(&self) {
+        self.handler.reset_load_peaks()
+    }
+
     pub(crate) fn io_urls(&self) -> Vec<String> {
         match self.execute(Request::IoUrls) {
             Ok(ResponseData::Strings(urls)) => urls,
-            response => unexpected_response(response),
+            Ok(data) => {
+                unexpected_response(data);
+                Default::default()
+            }
+            Err(err) => {
+                error!("Failed to get io urls: {}", err);
+                Default::default()
+            }
+        }
+    }
+
+    pub(crate) fn region_hosts(&self) -> IoResult<Vec<RegionHosts>> {
+        match self.execute(Request::RegionHosts) {
+            Ok(ResponseData::RegionHosts(region_hosts)) => Ok(region_hosts),
+            Ok(data) => Err(unexpected_response(data)),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub(crate) fn report_external_failure(&self, host: &str, error: IoError) -> bool {
+        match self.execute(Request::ReportExternalFailure {
+            host: host.to_owned(),
+            error,
+        }) {
+            Ok(ResponseData::Bool(b)) => b,
+            Ok(data) => {
+                unexpected_response(data);
+                false
+            }
+            Err(err) => {
+                error!("Failed to report external failure: {}", err);
+                false
+            }
+        }
+    }
+
+    pub(crate) fn report_external_success(&self, host: &str) -> bool {
+        match self.execute(Request::ReportExternalSuccess {
+            host: host.to_owned(),
+        }) {
+            Ok(ResponseData::Bool(b)) => b,
+            Ok(data) => {
+                unexpected_response(data);
+                false
+            }
+            Err(err) => {
+                error!("Failed to report external success: {}", err);
+                false
+            }
+        }
+    }
+
+    pub(crate) fn ban_host(&self, host: &str, duration: Duration) -> bool {
+        match self.execute(Request::BanHost {
+            host: host.to_owned(),
+            duration,
+        }) {
+            Ok(ResponseData::Bool(b)) => b,
+            Ok(data) => {
+                unexpected_response(data);
+                false
+            }
+            Err(err) => {
+                error!("Failed to ban host: {}", err);
+                false
+            }
+        }
+    }
+
+    pub(crate) fn set_host_base_timeout(&self, host: &str, base_timeout: Option<Duration>) -> bool {
+        match self.execute(Request::SetHostBaseTimeout {
+            host: host.to_owned(),
+            base_timeout,
+        }) {
+            Ok(ResponseData::Bool(b)) => b,
+            Ok(data) => {
+                unexpected_response(data);
+                false
+            }
+            Err(err) => {
+                error!("Failed to set host base timeout: {}", err);
+                false
+            }
+        }
+    }
+
+    pub(crate) fn pin_hosts(&self, hosts: &[String]) -> bool {
+        match self.execute(Request::PinHosts {
+            hosts: hosts.to_owned(),
+        }) {
+            Ok(ResponseData::Bool(b)) => b,
+            Ok(data) => {
+                unexpected_response(data);
+                false
+            }
+            Err(err) => {
+                error!("Failed to pin hosts: {}", err);
+                false
+            }
+        }
+    }
+
+    pub(crate) fn invalidate_negative_cache(&self) -> bool {
+        match self.execute(Request::InvalidateNegativeCache {
+            key: self.key.to_owned(),
+        }) {
+            Ok(ResponseData::Bool(b)) => b,
+            Ok(data) => {
+                unexpected_response(data);
+                false
+            }
+            Err(err) => {
+                error!("Failed to invalidate negative cache: {}", err);
+                false
+            }
+        }
+    }
+
+    pub(crate) fn purge_connections(&self) -> bool {
+        match self.execute(Request::PurgeConnections) {
+            Ok(ResponseData::Bool(b)) => b,
+            Ok(data) => {
+                unexpected_response(data);
+                false
+            }
+            Err(err) => {
+                error!("Failed to purge connections: {}", err);
+                false
+            }
         }
     }
 
@@ -282,8 +793,44 @@ impl RangeReader {
             ranges: ranges.to_vec(),
         }) {
             Ok(ResponseData::Parts(parts)) => Ok(parts),
+            Ok(data) => Err(unexpected_response(data)),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub(crate) fn checksum_range(
+        &self,
+        pos: u64,
+        size: u64,
+        algorithm: ChecksumAlgorithm,
+    ) -> IoResult<Vec<u8>> {
+        match self.execute(Request::ChecksumRange {
+            key: self.key.to_owned(),
+            pos,
+            size,
+            algorithm,
+        }) {
+            Ok(ResponseData::Bytes(checksum)) => Ok(checksum),
+            Ok(data) => Err(unexpected_response(data)),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub(crate) fn checksum_ranges(
+        &self,
+        ranges: &[(u64, u64)],
+        algorithm: ChecksumAlgorithm,
+        concurrency: usize,
+    ) -> IoResult<Vec<Vec<u8>>> {
+        match self.execute(Request::ChecksumRanges {
+            key: self.key.to_owned(),
+            ranges: ranges.to_vec(),
+            algorithm,
+            concurrency,
+        }) {
+            Ok(ResponseData::Checksums(checksums)) => Ok(checksums),
+            Ok(data) => Err(unexpected_response(data)),
             Err(err) => Err(err),
-            response => unexpected_response(response),
         }
     }
 
@@ -292,8 +839,8 @@ impl RangeReader {
             key: self.key.to_owned(),
         }) {
             Ok(ResponseData::Bool(existed)) => Ok(existed),
+            Ok(data) => Err(unexpected_response(data)),
             Err(err) => Err(err),
-            response => unexpected_response(response),
         }
     }
 
@@ -302,8 +849,18 @@ impl RangeReader {
             key: self.key.to_owned(),
         }) {
             Ok(ResponseData::U64(size)) => Ok(size),
+            Ok(data) => Err(unexpected_response(data)),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub(crate) fn stat(&self) -> IoResult<ObjectMetadata> {
+        match self.execute(Request::Stat {
+            key: self.key.to_owned(),
+        }) {
+            Ok(ResponseData::Metadata(metadata)) => Ok(*metadata),
+            Ok(data) => Err(unexpected_response(data)),
             Err(err) => Err(err),
-            response => unexpected_response(response),
         }
     }
 
@@ -312,8 +869,51 @@ impl RangeReader {
             key: self.key.to_owned(),
         }) {
             Ok(ResponseData::Bytes(bytes)) => Ok(bytes),
+            Ok(data) => Err(unexpected_response(data)),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub(crate) fn download_sharded(&self, options: &ShardedDownloadOptions) -> IoResult<Vec<u8>> {
+        match self.execute(Request::DownloadSharded {
+            key: self.key.to_owned(),
+            options: options.to_owned(),
+        }) {
+            Ok(ResponseData::Bytes(bytes)) => Ok(bytes),
+            Ok(data) => Err(unexpected_response(data)),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub(crate) fn download_to_path(
+        &self,
+        path: &Path,
+        lock_timeout: Option<Duration>,
+    ) -> IoResult<u64> {
+        match self.execute(Request::DownloadToPath {
+            key: self.key.to_owned(),
+            path: path.to_owned(),
+            lock_timeout,
+        }) {
+            Ok(ResponseData::U64(size)) => Ok(size),
+            Ok(data) => Err(unexpected_response(data)),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub(crate) fn download_to_dir(
+        &self,
+        dir: &Path,
+        lock_timeout: Option<Duration>,
+    ) -> IoResult<(PathBuf, u64)> {
+        match self.execute(Request::DownloadToDir {
+            key: self.key.to_owned(),
+            dir: dir.to_owned(),
+            lock_timeout,
+        }) {
+            Ok(ResponseData::PathWithSize(path_with_size)) => Ok(path_with_size),
+            Ok(data) => Err(unexpected_response(data)),
             Err(err) => Err(err),
-            response => unexpected_response(response),
         }
     }
 
@@ -329,16 +929,18 @@ impl RangeReader {
             size: buf.len() as u64,
         }) {
             Ok(ResponseData::BytesWithSize((bytes, total_size))) => {
-                buf[..bytes.len()].copy_from_slice(&bytes);
-                Ok((bytes.len() as u64, total_size))
+                let size = bytes.len().min(buf.len());
+                buf[..size].copy_from_slice(&bytes[..size]);
+                Ok((size as u64, total_size))
             }
+            Ok(data) => Err(unexpected_response(data)),
             Err(err) => Err(err),
-            response => unexpected_response(response),
         }
     }
 
     fn execute(&self, request: Request) -> Response {
-        self.handler.execute_request(request)
+        self.handler
+            .execute_request(request, self.overrides.to_owned(), self.stats.to_owned())
     }
 }
 
@@ -350,11 +952,12 @@ impl ReadAt for RangeReader {
             key: self.key.to_owned(),
         }) {
             Ok(ResponseData::Bytes(bytes)) => {
-                buf.copy_from_slice(&bytes);
-                Ok(bytes.len())
+                let size = bytes.len().min(buf.len());
+                buf[..size].copy_from_slice(&bytes[..size]);
+                Ok(size)
             }
+            Ok(data) => Err(unexpected_response(data)),
             Err(err) => Err(err),
-            response => unexpected_response(response),
         }
     }
 }
@@ -362,8 +965,36 @@ impl ReadAt for RangeReader {
 impl Request {
     async fn send(self, range_reader: AsyncRangeReaderWithRangeReader) -> Response {
         match self {
-            Self::UpdateUrls => Ok(ResponseData::Bool(range_reader.update_urls().await)),
+            Self::UpdateUrls => range_reader.update_urls().await.map(ResponseData::Bool),
             Self::IoUrls => Ok(ResponseData::Strings(range_reader.io_urls().await)),
+            Self::RegionHosts => range_reader
+                .region_hosts()
+                .await
+                .map(ResponseData::RegionHosts),
+            Self::ReportExternalFailure { host, error } => Ok(ResponseData::Bool(
+                range_reader.report_external_failure(&host, &error).await,
+            )),
+            Self::ReportExternalSuccess { host } => Ok(ResponseData::Bool(
+                range_reader.report_external_success(&host).await,
+            )),
+            Self::BanHost { host, duration } => Ok(ResponseData::Bool(
+                range_reader.ban_host(&host, duration).await,
+            )),
+            Self::SetHostBaseTimeout { host, base_timeout } => Ok(ResponseData::Bool(
+                range_reader
+                    .set_host_base_timeout(&host, base_timeout)
+                    .await,
+            )),
+            Self::PinHosts { hosts } => {
+                Ok(ResponseData::Bool(range_reader.pin_hosts(&hosts).await))
+            }
+            Self::InvalidateNegativeCache { key } => Ok(ResponseData::Bool(
+                range_reader.invalidate_negative_cache(&key).await,
+            )),
+            Self::PurgeConnections => {
+                Ok(ResponseData::Bool(range_reader.purge_connections().await))
+            }
+            Self::Overrides => Ok(ResponseData::Overrides(Box::new(range_reader.overrides()))),
             Self::ReadAt { key, pos, size } => range_reader
                 .read_at(&key, pos, size)
                 .await
@@ -372,9 +1003,49 @@ impl Request {
                 .read_multi_ranges(&key, &ranges)
                 .await
                 .map(ResponseData::Parts),
+            Self::ChecksumRange {
+                key,
+                pos,
+                size,
+                algorithm,
+            } => range_reader
+                .checksum_range(&key, pos, size, algorithm)
+                .await
+                .map(ResponseData::Bytes),
+            Self::ChecksumRanges {
+                key,
+                ranges,
+                algorithm,
+                concurrency,
+            } => range_reader
+                .checksum_ranges(&key, &ranges, algorithm, concurrency)
+                .await
+                .map(ResponseData::Checksums),
             Self::Exist { key } => range_reader.exist(&key).await.map(ResponseData::Bool),
             Self::FileSize { key } => range_reader.file_size(&key).await.map(ResponseData::U64),
+            Self::Stat { key } => range_reader
+                .stat(&key)
+                .await
+                .map(|metadata| ResponseData::Metadata(Box::new(metadata))),
             Self::Download { key } => range_reader.download(&key).await.map(ResponseData::Bytes),
+            Self::DownloadSharded { key, options } => range_reader
+                .download_sharded(&key, &options)
+                .await
+                .map(ResponseData::Bytes),
+            Self::DownloadToPath {
+                key,
+                path,
+                lock_timeout,
+            } => download_to_path(&range_reader, &key, &path, lock_timeout)
+                .await
+                .map(ResponseData::U64),
+            Self::DownloadToDir {
+                key,
+                dir,
+                lock_timeout,
+            } => download_to_dir(&range_reader, &key, &dir, lock_timeout)
+                .await
+                .map(ResponseData::PathWithSize),
             Self::ReadLastBytes { key, size } => range_reader
                 .read_last_bytes(&key, size)
                 .await
@@ -386,23 +1057,26 @@ impl Request {
 async fn forward(fut: impl Future<Output = Response>, mut tx: OneshotResponse) {
     pin_mut!(fut);
 
-    let result = poll_fn(|cx| match fut.as_mut().poll(cx) {
-        Poll::Ready(result) => Poll::Ready(Some(result)),
-        Poll::Pending => {
-            ready!(tx.poll_closed(cx));
-            Poll::Ready(None)
+    // 先检查调用方是否已经放弃等待，避免为排队中超时的请求发起 HTTP 请求
+    // 请求被取消时丢弃 Future，进行中的 HTTP 请求随之中断
+    let result = poll_fn(|cx| {
+        if tx.poll_closed(cx).is_ready() {
+            return Poll::Ready(None);
         }
+        fut.as_mut().poll(cx).map(Some)
     })
     .await;
 
     if let Some(result) = result {
         let _ = tx.send(result);
+    } else {
+        debug!("Request is cancelled by caller");
+        record_cancelled_sync_request();
     }
-    // else request is canceled
 }
 
 #[track_caller]
-fn block_on<F: Future>(fut: F) -> F::Output {
+fn block_on<F: Future>(fut: F, deadline: Option<Instant>) -> Option<F::Output> {
     enter();
     let waker = waker(Arc::new(ThreadWaker(current_thread())));
     let mut cx = Context::from_waker(&waker);
@@ -411,12 +1085,20 @@ fn block_on<F: Future>(fut: F) -> F::Output {
 
     loop {
         match fut.as_mut().poll(&mut cx) {
-            Poll::Ready(v) => return v,
+            Poll::Ready(v) => return Some(v),
             Poll::Pending => {}
         };
 
         debug!("({:?}) park", current_thread().id());
-        park_thread();
+        if let Some(deadline) = deadline {
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            park_thread_timeout(deadline - now);
+        } else {
+            park_thread();
+        }
     }
 
     struct ThreadWaker(Thread);
@@ -444,35 +1126,47 @@ fn block_on<F: Future>(fut: F) -> F::Output {
     }
 }
 
-#[cold]
-#[inline(never)]
-#[track_caller]
-fn event_loop_panicked() -> ! {
-    // The only possible reason there would be a Canceled error
-    // is if the thread running the event loop panicked. We could return
-    // an Err here, like a BrokenPipe, but the Client is not
-    // recoverable. Additionally, the panic in the other thread
-    // is not normal, and should likely be propagated.
-    panic!("event loop thread panicked");
+fn build_runtime(worker_threads: usize) -> IoResult<TokioRuntime> {
+    if worker_threads > 1 {
+        TokioRuntimeBuilder::new_multi_thread()
+            .worker_threads(worker_threads)
+            .thread_name("qiniu-download-internal-sync-worker")
+            .enable_all()
+            .build()
+    } else {
+        TokioRuntimeBuilder::new_current_thread()
+            .enable_all()
+            .build()
+    }
+}
+
+fn internal_error(message: String) -> IoError {
+    IoError::new(IoErrorKind::Other, SyncRequestError::Internal { message })
 }
 
 #[cold]
 #[inline(never)]
-#[track_caller]
-fn runtime_create_error(err: IoError) -> ! {
-    panic!("tokio runtime creation error: {}", err);
+fn event_loop_panicked() -> IoError {
+    // The only possible reason there would be a Canceled error
+    // is if the thread running the event loop panicked. The caller
+    // gets an Err instead of a panic, so the process can keep serving
+    // and rebuild the reader later.
+    error!("event loop thread panicked");
+    internal_error("event loop thread panicked".to_owned())
 }
 
 #[cold]
 #[inline(never)]
 #[track_caller]
-fn unexpected_response(response: Response) -> ! {
-    panic!("unexpected response: {:?}", response);
+fn unexpected_response(data: ResponseData) -> IoError {
+    error!("unexpected response: {:?}", data);
+    internal_error(format!("unexpected response: {:?}", data))
 }
 
 #[cfg(test)]
 mod tests {
     use super::{super::super::Credential, *};
+    use futures::future::pending;
     use hyper::{
         header::{HeaderValue, CONTENT_RANGE, CONTENT_TYPE, RANGE},
         StatusCode,
@@ -484,7 +1178,10 @@ mod tests {
         time::Duration,
     };
     use text_io::scan as scan_text;
-    use tokio::task::{spawn, spawn_blocking};
+    use tokio::{
+        task::{spawn, spawn_blocking},
+        time::{sleep, timeout},
+    };
     use warp::{header, path, reply::Response, Filter};
 
     macro_rules! starts_with_server {
@@ -563,6 +1260,268 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_synced_read_at_straddling_eof() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes =
+            path!("file")
+                .and(header::value(RANGE.as_str()))
+                .map(|range: HeaderValue| {
+                    assert_eq!(range.to_str().unwrap(), "bytes=5-14");
+                    let mut resp = Response::new("67890".into());
+                    *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+                    resp.headers_mut()
+                        .insert(CONTENT_RANGE, "bytes 5-9/10".parse().unwrap());
+                    resp
+                });
+
+        starts_with_server!(io_addr, io_routes, {
+            spawn_blocking(move || {
+                let io_urls = vec![format!("http://{}", io_addr)];
+
+                let downloader = RangeReaderBuilder::from(
+                    BaseRangeReaderBuilder::new(
+                        "bucket".to_owned(),
+                        "file".to_owned(),
+                        get_credential(),
+                        io_urls,
+                    )
+                    .use_getfile_api(false)
+                    .normalize_key(true),
+                )
+                .build();
+
+                let mut buf = [0u8; 10];
+                assert_eq!(downloader.read_at(5, &mut buf).unwrap(), 5);
+                assert_eq!(&buf[..5], b"67890");
+                assert_eq!(&buf[5..], &[0u8; 5]);
+            })
+            .await?;
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_synced_read_beyond_4_gib() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        const FOUR_GIB: u64 = 1 << 32;
+        let io_routes =
+            path!("file")
+                .and(header::value(RANGE.as_str()))
+                .map(|range: HeaderValue| {
+                    let mut resp = if range.to_str().unwrap().contains(',') {
+                        assert_eq!(
+                            range.to_str().unwrap(),
+                            "bytes=4294967296-4294967300,4294967301-4294967305"
+                        );
+                        let mut response_body = Multipart::new();
+                        response_body.add_stream(
+                            "",
+                            Cursor::new(b"12345"),
+                            None,
+                            None,
+                            Some("bytes 4294967296-4294967300/4294967306"),
+                        );
+                        response_body.add_stream(
+                            "",
+                            Cursor::new(b"67890"),
+                            None,
+                            None,
+                            Some("bytes 4294967301-4294967305/4294967306"),
+                        );
+                        let mut fields = response_body.prepare().unwrap();
+                        let mut buffer = Vec::new();
+                        fields.read_to_end(&mut buffer).unwrap();
+                        let mut resp = Response::new(buffer.into());
+                        resp.headers_mut().insert(
+                            CONTENT_TYPE,
+                            ("multipart/form-data; boundary=".to_owned() + fields.boundary())
+                                .parse()
+                                .unwrap(),
+                        );
+                        resp
+                    } else {
+                        assert_eq!(range.to_str().unwrap(), "bytes=4294967301-4294967310");
+                        let mut resp = Response::new("67890".into());
+                        resp.headers_mut().insert(
+                            CONTENT_RANGE,
+                            "bytes 4294967301-4294967305/4294967306".parse().unwrap(),
+                        );
+                        resp
+                    };
+                    *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+                    resp
+                });
+
+        starts_with_server!(io_addr, io_routes, {
+            spawn_blocking(move || {
+                let io_urls = vec![format!("http://{}", io_addr)];
+
+                let downloader = RangeReaderBuilder::from(
+                    BaseRangeReaderBuilder::new(
+                        "bucket".to_owned(),
+                        "file".to_owned(),
+                        get_credential(),
+                        io_urls,
+                    )
+                    .use_getfile_api(false)
+                    .normalize_key(true),
+                )
+                .build();
+
+                let mut buf = [0u8; 10];
+                assert_eq!(downloader.read_at(FOUR_GIB + 5, &mut buf).unwrap(), 5);
+                assert_eq!(&buf[..5], b"67890");
+
+                let mut parts = downloader
+                    .read_multi_ranges(&[(FOUR_GIB, 5), (FOUR_GIB + 5, 5)])
+                    .unwrap();
+                parts.sort_by_key(|part| part.range);
+                assert_eq!(parts.len(), 2);
+                assert_eq!(&parts[0].data, b"12345");
+                assert_eq!(parts[0].range, (FOUR_GIB, 5));
+                assert_eq!(&parts[1].data, b"67890");
+                assert_eq!(parts[1].range, (FOUR_GIB + 5, 5));
+            })
+            .await?;
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_synced_request_timeout() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!("file").then(|| async {
+            sleep(Duration::from_secs(2)).await;
+            Response::new("1234567890".into())
+        });
+
+        starts_with_server!(io_addr, io_routes, {
+            spawn_blocking(move || {
+                let io_urls = vec![format!("http://{}", io_addr)];
+
+                let downloader = RangeReaderBuilder::from(
+                    BaseRangeReaderBuilder::new(
+                        "bucket".to_owned(),
+                        "file".to_owned(),
+                        get_credential(),
+                        io_urls,
+                    )
+                    .use_getfile_api(false)
+                    .normalize_key(true)
+                    .sync_request_timeout(Duration::from_millis(200)),
+                )
+                .build();
+
+                let err = downloader.download().unwrap_err();
+                assert_eq!(err.kind(), IoErrorKind::TimedOut);
+                assert_eq!(
+                    err.get_ref()
+                        .and_then(|err| err.downcast_ref::<SyncRequestError>()),
+                    Some(&SyncRequestError::TimedOut {
+                        timeout: Duration::from_millis(200)
+                    })
+                );
+                assert_eq!(downloader.queue_depth(), 0);
+            })
+            .await?;
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_synced_runtime_load() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!("file").then(|| async {
+            sleep(Duration::from_millis(500)).await;
+            Response::new("1234567890".into())
+        });
+
+        starts_with_server!(io_addr, io_routes, {
+            spawn_blocking(move || {
+                let io_urls = vec![format!("http://{}", io_addr)];
+
+                let downloader = RangeReaderBuilder::from(
+                    BaseRangeReaderBuilder::new(
+                        "bucket".to_owned(),
+                        "file".to_owned(),
+                        get_credential(),
+                        io_urls,
+                    )
+                    .use_getfile_api(false)
+                    .normalize_key(true),
+                )
+                .build();
+                assert_eq!(downloader.runtime_load(), SyncRuntimeLoad::default());
+
+                let threads = (0..4)
+                    .map(|_| {
+                        let downloader = downloader.to_owned();
+                        spawn_thread(move || downloader.download())
+                    })
+                    .collect::<Vec<_>>();
+                for thread in threads {
+                    assert_eq!(thread.join().unwrap().unwrap(), b"1234567890");
+                }
+
+                let load = downloader.runtime_load();
+                assert_eq!(load.queued_requests(), 0);
+                assert_eq!(load.in_flight_requests(), 0);
+                assert!(load.peak_queued_requests() >= 1);
+                assert_eq!(load.peak_in_flight_requests(), 4);
+
+                downloader.reset_runtime_load_peaks();
+                assert_eq!(downloader.runtime_load(), SyncRuntimeLoad::default());
+            })
+            .await?;
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_synced_runtime_unavailable() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        struct PanickedBuilder;
+
+        impl BuildAsyncRangeReader for PanickedBuilder {
+            fn build_async_range_reader(self) -> AsyncRangeReaderWithRangeReader {
+                panic!("failed to build range reader");
+            }
+        }
+
+        let downloader = RangeReader {
+            handler: RangeReaderHandle::new(PanickedBuilder, None, 1),
+            key: "file".to_owned(),
+            bucket: "bucket".to_owned(),
+            cluster_name: None,
+            rng: Default::default(),
+            overrides: None,
+            stats: Default::default(),
+        };
+        let err = downloader.download().unwrap_err();
+        assert_eq!(err.kind(), IoErrorKind::Other);
+        assert!(matches!(
+            err.get_ref()
+                .and_then(|err| err.downcast_ref::<SyncRequestError>()),
+            Some(SyncRequestError::Internal { .. })
+        ));
+        assert!(downloader.exist().is_err());
+        assert!(downloader.io_urls().is_empty());
+        assert!(downloader.update_urls().is_err());
+        assert_eq!(downloader.queue_depth(), 0);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_synced_read_last_bytes() -> anyhow::Result<()> {
         env_logger::try_init().ok();
@@ -639,6 +1598,69 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_synced_download_with_worker_pool() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = { path!("file").map(|| Response::new("1234567890".into())) };
+        starts_with_server!(io_addr, io_routes, {
+            spawn_blocking(move || {
+                let io_urls = vec![format!("http://{}", io_addr)];
+                let downloader = RangeReaderBuilder::from(
+                    BaseRangeReaderBuilder::new(
+                        "bucket".to_owned(),
+                        "file".to_owned(),
+                        get_credential(),
+                        io_urls,
+                    )
+                    .use_getfile_api(false)
+                    .normalize_key(true)
+                    .sync_worker_threads(4),
+                )
+                .build();
+
+                let threads = (0..16)
+                    .map(|_| {
+                        let downloader = downloader.to_owned();
+                        spawn_thread(move || downloader.download())
+                    })
+                    .collect::<Vec<_>>();
+                for thread in threads {
+                    assert_eq!(&thread.join().unwrap().unwrap(), b"1234567890");
+                }
+            })
+            .await?;
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_forward_cancelled_request() -> anyhow::Result<()> {
+        let polled = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = channel();
+        drop(rx);
+        {
+            let polled = polled.to_owned();
+            forward(
+                async move {
+                    polled.store(true, Relaxed);
+                    Ok(ResponseData::Bool(true))
+                },
+                tx,
+            )
+            .await;
+        }
+        assert!(!polled.load(Relaxed));
+
+        let (tx, rx) = channel();
+        let handle = spawn(forward(pending(), tx));
+        sleep(Duration::from_millis(100)).await;
+        drop(rx);
+        timeout(Duration::from_secs(1), handle).await??;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_synced_read_multi_ranges() -> anyhow::Result<()> {
         env_logger::try_init().ok();
@@ -695,10 +1717,11 @@ mod tests {
                 let ranges = [(0, 5), (5, 5)];
                 let parts = downloader.read_multi_ranges(&ranges).unwrap();
                 assert_eq!(parts.len(), 2);
-                assert_eq!(&parts.get(1).unwrap().data, b"12345");
-                assert_eq!(parts.get(1).unwrap().range, (0, 5));
-                assert_eq!(&parts.first().unwrap().data, b"67890");
-                assert_eq!(parts.first().unwrap().range, (5, 5));
+                assert_eq!(&parts[0].data, b"12345");
+                assert_eq!(parts[0].range, (0, 5));
+                assert_eq!(&parts[1].data, b"67890");
+                assert_eq!(parts[1].range, (5, 5));
+                assert_eq!(parts[1].requested, (5, 5));
             })
             .await?;
         });
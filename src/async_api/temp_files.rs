@@ -0,0 +1,331 @@
+use super::{
+    cache_dir::cache_dir_path_of, diagnose::DIAGNOSE_FILE_NAME,
+    host_stats::HOST_STATS_TEMPFILE_NAME, query::CACHE_TEMPFILE_NAME,
+};
+use filetime::{set_file_mtime, FileTime};
+use log::{info, warn};
+use std::{
+    ffi::OsString,
+    fs::remove_file as remove_file_sync,
+    io::{ErrorKind as IoErrorKind, Result as IoResult},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering::Relaxed},
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    fs::{metadata, read_dir, remove_file, rename, File, OpenOptions},
+    io::AsyncWriteExt,
+    task::spawn_blocking,
+};
+
+/// 下载临时文件的后缀
+const PART_FILE_SUFFIX: &str = ".part";
+
+/// 断点续传状态文件的后缀
+pub(super) const SHARD_STATE_FILE_SUFFIX: &str = ".shards";
+
+/// 缓存目录中写入完成后即被重命名或删除的临时文件
+const CACHE_TEMP_FILE_NAMES: [&str; 3] = [
+    CACHE_TEMPFILE_NAME,
+    HOST_STATS_TEMPFILE_NAME,
+    DIAGNOSE_FILE_NAME,
+];
+
+static PART_FILE_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+/// 下载临时文件，数据写入完成后原子地重命名为目标文件
+///
+/// 临时文件与目标文件位于同一目录，名称为目标文件名加上进程 ID、序号和 `.part` 后缀，
+/// 以保证并发写入同一目标文件的下载任务不会写入同一个临时文件。
+/// 在重命名前被丢弃时（包括下载失败和 Future 被取消）删除临时文件
+#[derive(Debug)]
+pub(super) struct PartFile {
+    file: File,
+    path: PathBuf,
+    persisted: bool,
+}
+
+impl PartFile {
+    pub(super) async fn create(target_path: &Path) -> IoResult<Self> {
+        let mut path = OsString::from(target_path.as_os_str());
+        path.push(format!(
+            ".{}-{}{}",
+            process::id(),
+            PART_FILE_SEQ.fetch_add(1, Relaxed),
+            PART_FILE_SUFFIX
+        ));
+        let path = PathBuf::from(path);
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await?;
+        Ok(Self {
+            file,
+            path,
+            persisted: false,
+        })
+    }
+
+    #[inline]
+    pub(super) fn file_mut(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// 设置临时文件的修改时间，重命名后即为目标文件的修改时间
+    ///
+    /// 设置前先写出已经缓冲的数据，以免之后的写入再次更新修改时间
+    pub(super) async fn set_modified(&mut self, modified: SystemTime) -> IoResult<()> {
+        self.file.flush().await?;
+        let path = self.path.to_owned();
+        spawn_blocking(move || set_file_mtime(path, FileTime::from_system_time(modified))).await?
+    }
+
+    /// 将临时文件落盘后重命名为目标文件
+    pub(super) async fn persist(mut self, target_path: &Path) -> IoResult<()> {
+        self.file.sync_all().await?;
+        rename(&self.path, target_path).await?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for PartFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            if let Err(err) = remove_file_sync(&self.path) {
+                if err.kind() != IoErrorKind::NotFound {
+                    warn!("Failed to remove part file {:?}: {}", self.path, err);
+                }
+            }
+        }
+    }
+}
+
+/// 通过临时文件原子地写入指定文件，写入中断时原有文件保持原样
+pub(super) async fn write_file_atomically(path: &Path, contents: &[u8]) -> IoResult<()> {
+    let mut part = PartFile::create(path).await?;
+    part.file_mut().write_all(contents).await?;
+    part.persist(path).await
+}
+
+/// 文件名是否为 [`PartFile`] 创建的下载临时文件，即 `<name>.<pid>-<seq>.part`
+fn is_part_file_name(file_name: &str) -> bool {
+    let is_part_file_name = || {
+        let (name, id) = file_name.strip_suffix(PART_FILE_SUFFIX)?.rsplit_once('.')?;
+        let (pid, seq) = id.split_once('-')?;
+        let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        Some(!name.is_empty() && is_number(pid) && is_number(seq))
+    };
+    is_part_file_name().unwrap_or(false)
+}
+
+/// 清理指定目录中过期的下载临时文件
+///
+/// 删除目录中（不包括子目录）修改时间早于 `ttl` 之前的 `<name>.<pid>-<seq>.part` 下载临时文件，
+/// 以及与下载目标文件成对出现的 `<name>.shards` 断点续传状态文件，返回删除的文件数。
+/// 其他文件即使后缀相同也不会被删除。进程崩溃或被强制终止时遗留的这些文件不会被自动删除，
+/// 可以定期对下载目标目录调用该函数清理。断点续传状态文件被删除后，对应的分片下载将从头开始
+pub async fn remove_stale_download_files(dir: impl AsRef<Path>, ttl: Duration) -> IoResult<usize> {
+    let dir = dir.as_ref();
+    let mut entries = read_dir(dir).await?;
+    let mut candidates = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name();
+        let is_temp_file = match file_name.to_str() {
+            Some(file_name) if is_part_file_name(file_name) => true,
+            Some(file_name) => match file_name.strip_suffix(SHARD_STATE_FILE_SUFFIX) {
+                Some(target_name) if !target_name.is_empty() => {
+                    metadata(dir.join(target_name)).await.is_ok()
+                }
+                _ => false,
+            },
+            None => false,
+        };
+        if is_temp_file {
+            candidates.push(entry.path());
+        }
+    }
+    let removed = remove_stale_files(candidates, ttl).await?;
+    if removed > 0 {
+        info!("Removed {} stale download files from {:?}", removed, dir);
+    }
+    Ok(removed)
+}
+
+/// 清理缓存目录中过期的临时文件
+///
+/// 删除缓存目录中修改时间早于 `ttl` 之前的写入缓存时使用的临时文件和 `.part` 临时文件，返回删除的文件数。
+/// 这些文件在写入完成后即被重命名或删除，仅在进程崩溃或被强制终止时遗留
+pub async fn remove_stale_cache_files(ttl: Duration) -> IoResult<usize> {
+    remove_stale_cache_files_in(&cache_dir_path_of("").await?, ttl).await
+}
+
+async fn remove_stale_cache_files_in(dir: &Path, ttl: Duration) -> IoResult<usize> {
+    let mut entries = read_dir(dir).await?;
+    let mut candidates = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name();
+        let is_temp_file = file_name.to_str().is_some_and(|file_name| {
+            is_part_file_name(file_name) || CACHE_TEMP_FILE_NAMES.contains(&file_name)
+        });
+        if is_temp_file {
+            candidates.push(entry.path());
+        }
+    }
+    let removed = remove_stale_files(candidates, ttl).await?;
+    if removed > 0 {
+        info!("Removed {} stale cache files from {:?}", removed, dir);
+    }
+    Ok(removed)
+}
+
+async fn remove_stale_files(paths: Vec<PathBuf>, ttl: Duration) -> IoResult<usize> {
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for path in paths {
+        let metadata = match metadata(&path).await {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == IoErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        let is_stale = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age >= ttl);
+        if is_stale {
+            match remove_file(&path).await {
+                Ok(()) => removed += 1,
+                Err(err) if err.kind() == IoErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[tokio::test]
+    async fn test_part_file() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let target_path = dir.path().join("file");
+
+        let mut part = PartFile::create(&target_path).await?;
+        part.file_mut().write_all(b"12345").await?;
+        let part_path = part.path.to_owned();
+        assert!(part_path.exists());
+        assert!(is_part_file_name(
+            part_path.file_name().unwrap().to_str().unwrap()
+        ));
+        drop(part);
+        assert!(!part_path.exists());
+        assert!(!target_path.exists());
+
+        let mut part = PartFile::create(&target_path).await?;
+        part.file_mut().write_all(b"12345").await?;
+        let part_path = part.path.to_owned();
+        let modified = UNIX_EPOCH + Duration::from_secs(784111777);
+        part.set_modified(modified).await?;
+        part.persist(&target_path).await?;
+        assert!(!part_path.exists());
+        assert_eq!(std::fs::read(&target_path)?, b"12345");
+        assert_eq!(std::fs::metadata(&target_path)?.modified()?, modified);
+
+        write_file_atomically(&target_path, b"67890").await?;
+        assert_eq!(std::fs::read(&target_path)?, b"67890");
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_part_file_name() {
+        assert!(is_part_file_name("file.1-0.part"));
+        assert!(is_part_file_name("file.tar.gz.12345-67.part"));
+        assert!(!is_part_file_name("file.part"));
+        assert!(!is_part_file_name(".1-0.part"));
+        assert!(!is_part_file_name("file.1-.part"));
+        assert!(!is_part_file_name("file.a-0.part"));
+        assert!(!is_part_file_name("file.1-0.partial"));
+    }
+
+    #[tokio::test]
+    async fn test_remove_stale_download_files() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        for file_name in [
+            "file",
+            "file.1-0.part",
+            "file.shards",
+            "file.qniu.meta",
+            "file.lock",
+            "orphan.shards",
+            "user.part",
+        ]
+        .iter()
+        {
+            std::fs::write(dir.path().join(file_name), b"")?;
+        }
+        std::fs::create_dir(dir.path().join("dir.1-0.part"))?;
+
+        assert_eq!(
+            remove_stale_download_files(dir.path(), Duration::from_secs(3600)).await?,
+            0
+        );
+        assert_eq!(
+            remove_stale_download_files(dir.path(), Duration::from_secs(0)).await?,
+            2
+        );
+        assert!(!dir.path().join("file.1-0.part").exists());
+        assert!(!dir.path().join("file.shards").exists());
+        for file_name in [
+            "file",
+            "file.qniu.meta",
+            "file.lock",
+            "orphan.shards",
+            "user.part",
+            "dir.1-0.part",
+        ]
+        .iter()
+        {
+            assert!(dir.path().join(file_name).exists());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remove_stale_cache_files() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        for file_name in [
+            "query-cache.json",
+            "query-cache.tmp.json",
+            "host-stats.tmp.json",
+            "dot-file",
+            "dot-file.1-0.part",
+        ]
+        .iter()
+        {
+            std::fs::write(dir.path().join(file_name), b"")?;
+        }
+
+        assert_eq!(
+            remove_stale_cache_files_in(dir.path(), Duration::from_secs(3600)).await?,
+            0
+        );
+        assert_eq!(
+            remove_stale_cache_files_in(dir.path(), Duration::from_secs(0)).await?,
+            3
+        );
+        assert!(dir.path().join("query-cache.json").exists());
+        assert!(dir.path().join("dot-file").exists());
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,405 @@
+use super::super::base::{
+    base64::{decode_urlsafe, urlsafe},
+    download::user_metadata_of,
+};
+use hyper::http::Response as RawHttpResponse;
+use log::warn;
+use once_cell::sync::Lazy;
+use reqwest::{
+    header::{HeaderName, HeaderValue, AUTHORIZATION, RANGE, SET_COOKIE},
+    Error as ReqwestError, Method, RequestBuilder as HttpRequestBuilder, Response as HttpResponse,
+    Url,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str as json_from_str, to_string as json_to_string};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{create_dir_all, read_to_string, File, OpenOptions},
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult, Write},
+    path::PathBuf,
+    sync::Mutex,
+    time::Instant,
+};
+
+const TRACE_FILE_NAME: &str = "trace.jsonl";
+
+static TRACE_STATE: Lazy<Mutex<Option<TraceState>>> = Lazy::new(Default::default);
+
+/// 请求跟踪模式，用于在本地复现下载问题
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TraceMode {
+    /// 录制模式，将脱敏后的请求和响应元数据追加写入指定目录
+    Record {
+        /// 录制数据所在目录
+        dir: PathBuf,
+        /// 录制响应体的最大尺寸，超过该尺寸的响应体不会被录制，为 0 表示不录制响应体
+        max_body_size: u64,
+    },
+    /// 回放模式，从指定目录读取录制的数据响应请求，不会访问网络
+    ///
+    /// 相同请求方法、路径和 `Range` 的请求按录制的顺序依次回放，
+    /// 未录制响应体的响应将以录制的尺寸填充零字节
+    Replay {
+        /// 录制数据所在目录
+        dir: PathBuf,
+    },
+}
+
+impl TraceMode {
+    /// 创建不录制响应体的录制模式
+    #[inline]
+    pub fn record(dir: impl Into<PathBuf>) -> Self {
+        Self::record_with_bodies(dir, 0)
+    }
+
+    /// 创建录制不超过指定尺寸的响应体的录制模式
+    #[inline]
+    pub fn record_with_bodies(dir: impl Into<PathBuf>, max_body_size: u64) -> Self {
+        Self::Record {
+            dir: dir.into(),
+            max_body_size,
+        }
+    }
+
+    /// 创建回放模式
+    #[inline]
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        Self::Replay { dir: dir.into() }
+    }
+}
+
+/// 设置请求跟踪模式，设置为 None 表示关闭
+///
+/// 录制模式下将创建录制目录，回放模式下将立即读取录制的数据
+pub fn set_trace_mode(mode: Option<TraceMode>) -> IoResult<()> {
+    let state = match mode {
+        Some(TraceMode::Record { dir, max_body_size }) => {
+            create_dir_all(&dir)?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(dir.join(TRACE_FILE_NAME))?;
+            Some(TraceState::Record {
+                file,
+                max_body_size,
+            })
+        }
+        Some(TraceMode::Replay { dir }) => {
+            let mut entries: HashMap<TraceKey, VecDeque<TraceEntry>> = Default::default();
+            for line in read_to_string(dir.join(TRACE_FILE_NAME))?.lines() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let entry: TraceEntry = json_from_str(line)?;
+                entries.entry(entry.key()).or_default().push_back(entry);
+            }
+            Some(TraceState::Replay { entries })
+        }
+        None => None,
+    };
+    *TRACE_STATE.lock().unwrap() = state;
+    Ok(())
+}
+
+#[derive(Debug)]
+enum TraceState {
+    Record {
+        file: File,
+        max_body_size: u64,
+    },
+    Replay {
+        entries: HashMap<TraceKey, VecDeque<TraceEntry>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TraceKey {
+    method: String,
+    path: String,
+    range: Option<String>,
+}
+
+/// 录制的一次请求，URL 中的查询参数和敏感的请求头不会被录制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TraceEntry {
+    method: String,
+    host: String,
+    path: String,
+    range: Option<String>,
+    #[serde(default)]
+    status: Option<u16>,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+    /// 解码后的 `x-qn-meta-*` 用户自定义元数据，非 ASCII 的值不会出现在 `headers` 中
+    #[serde(default)]
+    user_metadata: HashMap<String, String>,
+    #[serde(default)]
+    body_size: Option<u64>,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+    /// 录制时测量的各阶段耗时，回放时不使用
+    #[serde(default)]
+    timings: Option<TraceTimings>,
+}
+
+/// 录制的请求各阶段的耗时
+///
+/// 选择域名、解析响应体和写入缓存的耗时记录在审计日志中
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct TraceTimings {
+    /// 从发出请求到收到响应头或发生错误的耗时
+    ttfb_ms: u64,
+    /// 接收响应体的耗时，仅在录制了响应体时记录
+    #[serde(default)]
+    transfer_ms: Option<u64>,
+}
+
+impl TraceEntry {
+    fn new(method: &Method, url: &Url, range: Option<&HeaderValue>) -> Self {
+        Self {
+            method: method.to_string(),
+            host: url.origin().ascii_serialization(),
+            path: url.path().to_owned(),
+            range: range
+                .and_then(|range| range.to_str().ok())
+                .map(ToOwned::to_owned),
+            status: None,
+            headers: Default::default(),
+            user_metadata: Default::default(),
+            body_size: None,
+            body: None,
+            error: None,
+            timings: None,
+        }
+    }
+
+    fn key(&self) -> TraceKey {
+        TraceKey {
+            method: self.method.to_owned(),
+            path: self.path.to_owned(),
+            range: self.range.to_owned(),
+        }
+    }
+
+    fn into_response(self, url: Url) -> IoResult<HttpResponse> {
+        if let Some(error) = self.error {
+            return Err(IoError::new(IoErrorKind::ConnectionAborted, error));
+        }
+        let body = match (&self.body, self.body_size) {
+            (Some(body), _) => {
+                decode_urlsafe(body).map_err(|err| IoError::new(IoErrorKind::InvalidData, err))?
+            }
+            (None, Some(body_size)) if self.method != Method::HEAD.as_str() => {
+                vec![0; body_size as usize]
+            }
+            _ => Vec::new(),
+        };
+        let mut builder = RawHttpResponse::builder().status(self.status.unwrap_or(200));
+        for (name, value) in self.headers.iter() {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        let mut resp = builder
+            .body(body)
+            .map_err(|err| IoError::new(IoErrorKind::InvalidData, err))?;
+        resp.extensions_mut().insert(TracedUrl(url));
+        Ok(resp.into())
+    }
+}
+
+/// 录制或回放生成的响应中无法设置原始 URL，因此通过扩展字段保存
+#[derive(Debug, Clone)]
+struct TracedUrl(Url);
+
+/// 获取响应对应的 URL，对于录制或回放生成的响应，返回原始请求的 URL
+pub(super) fn response_url(resp: &HttpResponse) -> &Url {
+    resp.extensions()
+        .get::<TracedUrl>()
+        .map_or_else(|| resp.url(), |url| &url.0)
+}
+
+/// 发送请求时发生的错误
+#[derive(Debug)]
+pub(super) enum SendError {
+    /// 实际发送请求时发生的错误
+    Reqwest(ReqwestError),
+    /// 回放录制的错误或找不到录制的响应
+    Replayed(IoError),
+}
+
+/// 发送请求，根据当前的请求跟踪模式录制或回放请求
+pub(super) async fn send_request(
+    request_builder: HttpRequestBuilder,
+) -> Result<HttpResponse, SendError> {
+    let max_body_size = match &*TRACE_STATE.lock().unwrap() {
+        None => None,
+        Some(TraceState::Record { max_body_size, .. }) => Some(Some(*max_body_size)),
+        Some(TraceState::Replay { .. }) => Some(None),
+    };
+    let max_body_size = match max_body_size {
+        None => return request_builder.send().await.map_err(SendError::Reqwest),
+        Some(None) => return replay(request_builder).map_err(SendError::Replayed),
+        Some(Some(max_body_size)) => max_body_size,
+    };
+
+    let request = request_builder
+        .try_clone()
+        .ok_or_else(|| SendError::Replayed(unclonable_request_error()))?
+        .build()
+        .map_err(SendError::Reqwest)?;
+    let mut entry = TraceEntry::new(
+        request.method(),
+        request.url(),
+        request.headers().get(RANGE),
+    );
+    let begin_at = Instant::now();
+    let resp = request_builder.send().await;
+    let mut timings = TraceTimings {
+        ttfb_ms: begin_at.elapsed().as_millis() as u64,
+        transfer_ms: None,
+    };
+    entry.timings = Some(timings);
+    let resp = match resp {
+        Ok(resp) => resp,
+        Err(err) => {
+            entry.error = Some(err.to_string());
+            write_entry(&entry);
+            return Err(SendError::Reqwest(err));
+        }
+    };
+    entry.status = Some(resp.status().as_u16());
+    entry.headers = resp
+        .headers()
+        .iter()
+        .filter(|(name, _)| is_recordable_header(name))
+        .filter_map(|(name, value)| {
+            value
+                .to_str()
+                .ok()
+                .map(|value| (name.to_string(), value.to_owned()))
+        })
+        .collect();
+    entry.user_metadata = user_metadata_of(resp.headers());
+    entry.body_size = resp.content_length();
+    let resp = match entry.body_size {
+        Some(body_size) if body_size > 0 && body_size <= max_body_size => {
+            let url = resp.url().to_owned();
+            let transfer_begin_at = Instant::now();
+            let body = resp.bytes().await.map_err(SendError::Reqwest)?;
+            timings.transfer_ms = Some(transfer_begin_at.elapsed().as_millis() as u64);
+            entry.timings = Some(timings);
+            entry.body = Some(urlsafe(&body));
+            write_entry(&entry);
+            entry.into_response(url).map_err(SendError::Replayed)?
+        }
+        _ => {
+            write_entry(&entry);
+            resp
+        }
+    };
+    Ok(resp)
+}
+
+fn replay(request_builder: HttpRequestBuilder) -> IoResult<HttpResponse> {
+    let request = request_builder
+        .build()
+        .map_err(|err| IoError::new(IoErrorKind::InvalidInput, err))?;
+    let entry = TraceEntry::new(
+        request.method(),
+        request.url(),
+        request.headers().get(RANGE),
+    );
+    let recorded = match &mut *TRACE_STATE.lock().unwrap() {
+        Some(TraceState::Replay { entries }) => entries
+            .get_mut(&entry.key())
+            .and_then(|entries| entries.pop_front()),
+        _ => None,
+    };
+    recorded
+        .ok_or_else(|| {
+            IoError::new(
+                IoErrorKind::Other,
+                format!(
+                    "No recorded response for {} {} (range: {:?})",
+                    entry.method, entry.path, entry.range
+                ),
+            )
+        })?
+        .into_response(request.url().to_owned())
+}
+
+fn write_entry(entry: &TraceEntry) {
+    if let Some(TraceState::Record { file, .. }) = &mut *TRACE_STATE.lock().unwrap() {
+        let result = json_to_string(entry)
+            .map_err(IoError::from)
+            .and_then(|mut line| {
+                line.push('\n');
+                file.write_all(line.as_bytes())
+            });
+        if let Err(err) = result {
+            warn!("Failed to write trace entry: {}", err);
+        }
+    }
+}
+
+fn is_recordable_header(name: &HeaderName) -> bool {
+    name != AUTHORIZATION && name != SET_COOKIE
+}
+
+fn unclonable_request_error() -> IoError {
+    IoError::new(IoErrorKind::InvalidInput, "Request can't be traced")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replay_trace_entry() -> anyhow::Result<()> {
+        let url = Url::parse("http://io.example.com/file?e=1&token=secret")?;
+        let mut entry = TraceEntry::new(
+            &Method::GET,
+            &url,
+            Some(&HeaderValue::from_static("bytes=0-4")),
+        );
+        assert_eq!(entry.host, "http://io.example.com");
+        assert_eq!(entry.path, "/file");
+        entry.status = Some(206);
+        entry.headers = vec![("content-range".to_owned(), "bytes 0-4/10".to_owned())];
+        entry.user_metadata = vec![("city".to_owned(), "上海".to_owned())]
+            .into_iter()
+            .collect();
+        entry.body_size = Some(5);
+        entry.body = Some(urlsafe(b"hello"));
+
+        entry.timings = Some(TraceTimings {
+            ttfb_ms: 12,
+            transfer_ms: Some(3),
+        });
+
+        let line = json_to_string(&entry)?;
+        assert!(!line.contains("secret"));
+        assert!(line.contains(r#""timings":{"ttfb_ms":12,"transfer_ms":3}"#));
+        let entry: TraceEntry = json_from_str(&line)?;
+        assert_eq!(
+            entry.user_metadata.get("city").map(String::as_str),
+            Some("上海")
+        );
+        let resp = entry.to_owned().into_response(url.to_owned())?;
+        assert_eq!(resp.status().as_u16(), 206);
+        assert_eq!(response_url(&resp), &url);
+        assert_eq!(resp.bytes().await?.as_ref(), b"hello");
+
+        let mut entry = entry;
+        entry.body = None;
+        let resp = entry.to_owned().into_response(url.to_owned())?;
+        assert_eq!(resp.bytes().await?.as_ref(), &[0u8; 5]);
+
+        entry.error = Some("connection reset".to_owned());
+        let err = entry.into_response(url).unwrap_err();
+        assert_eq!(err.kind(), IoErrorKind::ConnectionAborted);
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+use futures::{
+    ready,
+    stream::{unfold, Stream, StreamExt},
+};
+use std::{
+    fmt::{self, Debug},
+    io::{ErrorKind as IoErrorKind, Result as IoResult},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// 下载数据流转换器
+///
+/// 在下载数据写入输出流前单次遍历地对数据进行转换，例如解压缩、解密或去除格式头等。
+/// 转换器可以保存状态，每个数据流使用独立的转换器实例
+pub trait StreamTransformer: Send {
+    /// 转换一段输入数据，返回转换后的数据，可以返回空数据表示需要更多输入
+    fn transform(&mut self, input: &[u8]) -> IoResult<Vec<u8>>;
+
+    /// 数据流结束时调用，返回尚未输出的剩余数据
+    fn finish(&mut self) -> IoResult<Vec<u8>> {
+        Ok(Vec::new())
+    }
+}
+
+impl<T: StreamTransformer + ?Sized> StreamTransformer for &mut T {
+    #[inline]
+    fn transform(&mut self, input: &[u8]) -> IoResult<Vec<u8>> {
+        (**self).transform(input)
+    }
+
+    #[inline]
+    fn finish(&mut self) -> IoResult<Vec<u8>> {
+        (**self).finish()
+    }
+}
+
+/// 按顺序组合的多个数据流转换器
+///
+/// 前一个转换器的输出作为后一个转换器的输入
+#[derive(Default)]
+pub struct TransformerChain {
+    transformers: Vec<Box<dyn StreamTransformer>>,
+}
+
+impl TransformerChain {
+    /// 创建空的转换器链，不对数据做任何转换
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// 在转换器链的末尾追加转换器
+    #[inline]
+    pub fn then(mut self, transformer: impl StreamTransformer + 'static) -> Self {
+        self.transformers.push(Box::new(transformer));
+        self
+    }
+
+    /// 转换器链是否为空
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.transformers.is_empty()
+    }
+}
+
+impl StreamTransformer for TransformerChain {
+    fn transform(&mut self, input: &[u8]) -> IoResult<Vec<u8>> {
+        let mut data = input.to_vec();
+        for transformer in self.transformers.iter_mut() {
+            data = transformer.transform(&data)?;
+        }
+        Ok(data)
+    }
+
+    fn finish(&mut self) -> IoResult<Vec<u8>> {
+        let mut data = Vec::new();
+        for transformer in self.transformers.iter_mut() {
+            let mut output = if data.is_empty() {
+                Vec::new()
+            } else {
+                transformer.transform(&data)?
+            };
+            output.extend(transformer.finish()?);
+            data = output;
+        }
+        Ok(data)
+    }
+}
+
+impl Debug for TransformerChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransformerChain")
+            .field("transformers", &self.transformers.len())
+            .finish()
+    }
+}
+
+/// 为每个数据流创建独立的转换器
+#[derive(Clone)]
+pub(super) struct TransformerFactory(Arc<dyn Fn() -> TransformerChain + Send + Sync>);
+
+impl TransformerFactory {
+    pub(super) fn new(factory: impl Fn() -> TransformerChain + Send + Sync + 'static) -> Self {
+        Self(Arc::new(factory))
+    }
+
+    pub(super) fn make(&self) -> TransformerChain {
+        (self.0)()
+    }
+}
+
+impl Debug for TransformerFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TransformerFactory").finish()
+    }
+}
+
+/// 对数据流中的每个分块依次执行转换，数据流结束时输出转换器剩余的数据
+///
+/// 转换结果为空的分块不会被输出，出现错误后数据流结束
+pub(super) fn transform_stream<'a>(
+    stream: impl Stream<Item = IoResult<Vec<u8>>> + Send + 'a,
+    transformer: impl StreamTransformer + 'a,
+) -> impl Stream<Item = IoResult<Vec<u8>>> + Send + 'a {
+    unfold(
+        (Box::pin(stream), Some(transformer)),
+        |(mut stream, mut transformer)| async move {
+            loop {
+                let current = transformer.as_mut()?;
+                let output = match stream.next().await {
+                    Some(Ok(input)) => current.transform(&input),
+                    Some(Err(err)) => Err(err),
+                    None => {
+                        let output = current.finish();
+                        transformer = None;
+                        output
+                    }
+                };
+                match output {
+                    Ok(output) if output.is_empty() => continue,
+                    Ok(output) => return Some((Ok(output), (stream, transformer))),
+                    Err(err) => return Some((Err(err), (stream, None))),
+                }
+            }
+        },
+    )
+}
+
+/// 将写入的数据经过转换器转换后写入内部输出流
+///
+/// 每次写入的数据立即被转换，转换后的数据在下一次写入或 flush 时写入内部输出流，因此最多缓存一次转换的结果。
+/// 写入完成后需要调用 [`Self::finish`] 输出转换器剩余的数据
+pub(super) struct TransformWriter<'a> {
+    inner: &'a mut (dyn AsyncWrite + Unpin + Send),
+    transformer: &'a mut dyn StreamTransformer,
+    pending: Vec<u8>,
+    pending_pos: usize,
+    written: u64,
+}
+
+impl<'a> TransformWriter<'a> {
+    pub(super) fn new(
+        inner: &'a mut (dyn AsyncWrite + Unpin + Send),
+        transformer: &'a mut dyn StreamTransformer,
+    ) -> Self {
+        Self {
+            inner,
+            transformer,
+            pending: Vec::new(),
+            pending_pos: 0,
+            written: 0,
+        }
+    }
+
+    /// 输出转换器剩余的数据，返回写入内部输出流的字节数
+    pub(super) async fn finish(mut self) -> IoResult<u64> {
+        self.flush().await?;
+        let rest = self.transformer.finish()?;
+        self.inner.write_all(&rest).await?;
+        self.inner.flush().await?;
+        Ok(self.written + rest.len() as u64)
+    }
+
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        while self.pending_pos < self.pending.len() {
+            let n = ready!(
+                Pin::new(&mut *self.inner).poll_write(cx, &self.pending[self.pending_pos..])
+            )?;
+            if n == 0 {
+                return Poll::Ready(Err(IoErrorKind::WriteZero.into()));
+            }
+            self.pending_pos += n;
+            self.written += n as u64;
+        }
+        self.pending.clear();
+        self.pending_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for TransformWriter<'_> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        this.pending = this.transformer.transform(buf)?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut *this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut *this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 将数据按指定长度分组后逆序输出，用于验证有状态的转换器
+    struct ReverseChunks {
+        chunk_size: usize,
+        buffer: Vec<u8>,
+    }
+
+    impl StreamTransformer for ReverseChunks {
+        fn transform(&mut self, input: &[u8]) -> IoResult<Vec<u8>> {
+            self.buffer.extend_from_slice(input);
+            let complete = self.buffer.len() / self.chunk_size * self.chunk_size;
+            let mut output: Vec<u8> = self.buffer.drain(..complete).collect();
+            output
+                .chunks_mut(self.chunk_size)
+                .for_each(|chunk| chunk.reverse());
+            Ok(output)
+        }
+
+        fn finish(&mut self) -> IoResult<Vec<u8>> {
+            let mut output = std::mem::take(&mut self.buffer);
+            output.reverse();
+            Ok(output)
+        }
+    }
+
+    struct Uppercase;
+
+    impl StreamTransformer for Uppercase {
+        fn transform(&mut self, input: &[u8]) -> IoResult<Vec<u8>> {
+            Ok(input.to_ascii_uppercase())
+        }
+    }
+
+    #[test]
+    fn test_transformer_chain() -> anyhow::Result<()> {
+        let mut chain = TransformerChain::new()
+            .then(ReverseChunks {
+                chunk_size: 3,
+                buffer: Vec::new(),
+            })
+            .then(Uppercase);
+        assert_eq!(chain.transform(b"ab")?, b"");
+        assert_eq!(chain.transform(b"cdefg")?, b"CBAFED");
+        assert_eq!(chain.finish()?, b"G");
+
+        assert!(TransformerChain::new().is_empty());
+        Ok(())
+    }
+
+    fn reverse_chunks_chain() -> TransformerChain {
+        TransformerChain::new()
+            .then(ReverseChunks {
+                chunk_size: 3,
+                buffer: Vec::new(),
+            })
+            .then(Uppercase)
+    }
+
+    #[tokio::test]
+    async fn test_transform_stream() -> anyhow::Result<()> {
+        let input = futures::stream::iter(vec![
+            Ok(b"ab".to_vec()),
+            Ok(b"cdefg".to_vec()),
+            Ok(b"h".to_vec()),
+        ]);
+        let output = transform_stream(input, reverse_chunks_chain())
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<IoResult<Vec<_>>>()?;
+        assert_eq!(output, [b"CBAFED".to_vec(), b"HG".to_vec()]);
+
+        let input = futures::stream::iter(vec![
+            Ok(b"abc".to_vec()),
+            Err(IoErrorKind::UnexpectedEof.into()),
+            Ok(b"def".to_vec()),
+        ]);
+        let output = transform_stream(input, reverse_chunks_chain())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0].as_ref().unwrap(), b"CBA");
+        assert_eq!(
+            output[1].as_ref().unwrap_err().kind(),
+            IoErrorKind::UnexpectedEof
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transform_writer() -> anyhow::Result<()> {
+        let mut transformer = reverse_chunks_chain();
+        let mut output = Vec::new();
+        let mut writer = TransformWriter::new(&mut output, &mut transformer);
+        writer.write_all(b"ab").await?;
+        writer.write_all(b"cdefg").await?;
+        assert_eq!(writer.finish().await?, 7);
+        assert_eq!(output, b"CBAFEDG");
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+use io_uring::{opcode, squeue::Entry, types::Fd, IoUring, Probe};
+use log::{info, warn};
+use std::{
+    collections::HashMap,
+    fs::File as StdFile,
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    mem::forget,
+    os::unix::io::AsRawFd,
+    pin::Pin,
+    sync::{
+        mpsc::{channel, Receiver, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+    thread,
+};
+use tokio::io::AsyncWrite;
+
+/// 提交队列的长度，即同时进行的最大写入操作数
+const QUEUE_DEPTH: u32 = 32;
+
+/// 已经提交但尚未完成的写入数据的上限，超过时暂停接受新的写入
+const MAX_IN_FLIGHT_BYTES: u64 = 64 << 20;
+
+/// 单次写入操作的最大尺寸
+const MAX_WRITE_SIZE: usize = 8 << 20;
+
+#[derive(Debug, Default)]
+struct WriterState {
+    in_flight: u64,
+    error: Option<IoError>,
+    waker: Option<Waker>,
+}
+
+impl WriterState {
+    fn complete(&mut self, len: usize, result: IoResult<()>) {
+        self.in_flight -= len as u64;
+        if let Err(err) = result {
+            self.error.get_or_insert(err);
+        }
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// 复制错误，以便在之后的每次写入中都返回同一个错误
+fn copy_error(err: &IoError) -> IoError {
+    match err.raw_os_error() {
+        Some(code) => IoError::from_raw_os_error(code),
+        None => IoError::new(err.kind(), err.to_string()),
+    }
+}
+
+/// 通过 io_uring 写入文件的输出流，从文件开头顺序写入
+///
+/// 写入的数据被复制后交给后台线程，由其通过 io_uring 在各自的偏移量上并发写入文件，
+/// 已提交但尚未完成的数据超过上限时暂停接受新的写入。flush 在所有已提交的写入完成后返回，
+/// 写入过程中出现的错误将在之后的 write 或 flush 中返回
+#[derive(Debug)]
+pub(super) struct UringWriter {
+    offset: u64,
+    jobs: Option<Sender<(u64, Vec<u8>)>>,
+    state: Arc<Mutex<WriterState>>,
+}
+
+impl UringWriter {
+    /// 创建写入指定文件的输出流，后台线程持有该文件，在所有写入完成后关闭
+    ///
+    /// 内核不支持 io_uring（包括被 seccomp 禁用）或不支持 `IORING_OP_WRITE`（Linux 5.6 之前）时返回 None，
+    /// 此时应使用普通的文件写入
+    pub(super) fn new(file: StdFile) -> Option<Self> {
+        let ring = match IoUring::new(QUEUE_DEPTH) {
+            Ok(ring) => ring,
+            Err(err) => {
+                info!(
+                    "io_uring is unavailable, fall back to tokio file writes: {}",
+                    err
+                );
+                return None;
+            }
+        };
+        let mut probe = Probe::new();
+        if ring.submitter().register_probe(&mut probe).is_err()
+            || !probe.is_supported(opcode::Write::CODE)
+        {
+            info!("io_uring does not support writes, fall back to tokio file writes");
+            return None;
+        }
+        let (tx, rx) = channel();
+        let state = Arc::new(Mutex::new(WriterState::default()));
+        {
+            let state = state.to_owned();
+            thread::Builder::new()
+                .name("qiniu-download-uring".to_owned())
+                .spawn(move || run_ring(ring, file, rx, &state))
+                .map_err(|err| warn!("Failed to spawn io_uring thread: {}", err))
+                .ok()?;
+        }
+        Some(Self {
+            offset: 0,
+            jobs: Some(tx),
+            state,
+        })
+    }
+}
+
+impl AsyncWrite for UringWriter {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        let this = self.get_mut();
+        let mut state = this.state.lock().unwrap();
+        if let Some(err) = &state.error {
+            return Poll::Ready(Err(copy_error(err)));
+        }
+        if state.in_flight >= MAX_IN_FLIGHT_BYTES {
+            state.waker = Some(cx.waker().to_owned());
+            return Poll::Pending;
+        }
+        let buf = &buf[..buf.len().min(MAX_WRITE_SIZE)];
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let jobs = match &this.jobs {
+            Some(jobs) => jobs,
+            None => return Poll::Ready(Err(IoErrorKind::BrokenPipe.into())),
+        };
+        // 持有锁时增加计数，以免后台线程在计数前完成写入
+        state.in_flight += buf.len() as u64;
+        if jobs.send((this.offset, buf.to_vec())).is_err() {
+            state.in_flight -= buf.len() as u64;
+            return Poll::Ready(Err(IoError::new(
+                IoErrorKind::BrokenPipe,
+                "io_uring thread exited",
+            )));
+        }
+        this.offset += buf.len() as u64;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(err) = &state.error {
+            Poll::Ready(Err(copy_error(err)))
+        } else if state.in_flight == 0 {
+            Poll::Ready(Ok(()))
+        } else {
+            state.waker = Some(cx.waker().to_owned());
+            Poll::Pending
+        }
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let result = futures::ready!(self.as_mut().poll_flush(cx));
+        self.jobs = None;
+        Poll::Ready(result)
+    }
+}
+
+/// 提交中的写入操作
+struct PendingWrite {
+    offset: u64,
+    data: Vec<u8>,
+    written: usize,
+}
+
+impl PendingWrite {
+    fn entry(&self, fd: Fd, id: u64) -> Entry {
+        let remaining = &self.data[self.written..];
+        opcode::Write::new(fd, remaining.as_ptr(), remaining.len() as u32)
+            .offset(self.offset + self.written as u64)
+            .build()
+            .user_data(id)
+    }
+}
+
+/// 后台线程的主循环，在输出流被丢弃且所有写入完成后退出
+fn run_ring(
+    mut ring: IoUring,
+    file: StdFile,
+    jobs: Receiver<(u64, Vec<u8>)>,
+    state: &Mutex<WriterState>,
+) {
+    let fd = Fd(file.as_raw_fd());
+    let mut pending = HashMap::<u64, PendingWrite>::new();
+    let mut next_id = 0u64;
+    let mut closed = false;
+    loop {
+        while !closed && pending.len() < QUEUE_DEPTH as usize {
+            // 没有进行中的写入时阻塞等待新的写入
+            let job = if pending.is_empty() {
+                jobs.recv().map_err(|_| TryRecvError::Disconnected)
+            } else {
+                jobs.try_recv()
+            };
+            match job {
+                Ok((offset, data)) => {
+                    let write = PendingWrite {
+                        offset,
+                        data,
+                        written: 0,
+                    };
+                    push(&mut ring, &write.entry(fd, next_id));
+                    pending.insert(next_id, write);
+                    next_id += 1;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => closed = true,
+            }
+        }
+        if pending.is_empty() {
+            return;
+        }
+        match ring.submit_and_wait(1) {
+            Ok(_) => {}
+            Err(err) if err.kind() == IoErrorKind::Interrupted => continue,
+            Err(err) => {
+                warn!("Failed to submit io_uring writes: {}", err);
+                let mut state = state.lock().unwrap();
+                for write in pending.values() {
+                    state.complete(write.data.len(), Err(copy_error(&err)));
+                }
+                // 已提交的写入可能仍在进行，因此不能释放其缓冲区和文件
+                forget(pending);
+                forget(file);
+                return;
+            }
+        }
+        let completions = ring
+            .completion()
+            .map(|cqe| (cqe.user_data(), cqe.result()))
+            .collect::<Vec<_>>();
+        for (id, result) in completions {
+            let write = match pending.get_mut(&id) {
+                Some(write) => write,
+                None => continue,
+            };
+            let result = match result {
+                result if result < 0 => Err(IoError::from_raw_os_error(-result)),
+                0 => Err(IoErrorKind::WriteZero.into()),
+                written => {
+                    write.written += written as usize;
+                    Ok(())
+                }
+            };
+            if result.is_ok() && write.written < write.data.len() {
+                // 写入不完整时继续写入剩余的数据
+                push(&mut ring, &write.entry(fd, id));
+            } else if let Some(write) = pending.remove(&id) {
+                state.lock().unwrap().complete(write.data.len(), result);
+            }
+        }
+    }
+}
+
+fn push(ring: &mut IoUring, entry: &Entry) {
+    // 进行中的写入不超过提交队列的长度，因此提交队列不会已满。
+    // 写入的缓冲区由 pending 持有，文件由后台线程持有，在写入完成前都不会被释放
+    unsafe { ring.submission().push(entry) }.expect("io_uring submission queue is never full");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_uring_writer() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("file");
+        let mut writer = match UringWriter::new(StdFile::create(&path)?) {
+            Some(writer) => writer,
+            None => return Ok(()),
+        };
+        let data = (0..(3 * MAX_WRITE_SIZE + 100))
+            .map(|i| i as u8)
+            .collect::<Vec<_>>();
+        for chunk in data.chunks(1 << 20) {
+            writer.write_all(chunk).await?;
+        }
+        writer.write_all(&data[..10]).await?;
+        writer.flush().await?;
+        drop(writer);
+
+        let written = std::fs::read(&path)?;
+        assert_eq!(written.len(), data.len() + 10);
+        assert_eq!(&written[..data.len()], data.as_slice());
+        assert_eq!(&written[data.len()..], &data[..10]);
+        Ok(())
+    }
+}
@@ -1,3 +1,7 @@
 pub(crate) fn urlsafe(data: &[u8]) -> String {
     base64::encode_config(data, base64::URL_SAFE)
 }
+
+pub(crate) fn decode_urlsafe(data: impl AsRef<[u8]>) -> Result<Vec<u8>, base64::DecodeError> {
+    base64::decode_config(data, base64::URL_SAFE)
+}
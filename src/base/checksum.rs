@@ -0,0 +1,80 @@
+use crc32fast::Hasher as Crc32;
+use md5::{Digest, Md5};
+use sha2::Sha256;
+
+/// 校验和算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ChecksumAlgorithm {
+    /// CRC32，结果为 4 字节的大端序整数
+    Crc32,
+    /// MD5，结果为 16 字节
+    Md5,
+    /// SHA-256，结果为 32 字节
+    Sha256,
+}
+
+/// 流式计算校验和，不需要缓存全部数据
+#[derive(Debug, Clone)]
+pub(crate) enum RangeHasher {
+    Crc32(Crc32),
+    Md5(Md5),
+    Sha256(Sha256),
+}
+
+impl RangeHasher {
+    pub(crate) fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            ChecksumAlgorithm::Crc32 => Self::Crc32(Crc32::new()),
+            ChecksumAlgorithm::Md5 => Self::Md5(Md5::new()),
+            ChecksumAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+        }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Crc32(hasher) => hasher.update(data),
+            Self::Md5(hasher) => hasher.update(data),
+            Self::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    pub(crate) fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Crc32(hasher) => hasher.finalize().to_be_bytes().to_vec(),
+            Self::Md5(hasher) => hasher.finalize().to_vec(),
+            Self::Sha256(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_hasher() {
+        let hash = |algorithm| {
+            let mut hasher = RangeHasher::new(algorithm);
+            hasher.update(b"hello ");
+            hasher.update(b"world");
+            hasher.finalize()
+        };
+        assert_eq!(hash(ChecksumAlgorithm::Crc32), [0x0d, 0x4a, 0x11, 0x85]);
+        assert_eq!(
+            hash(ChecksumAlgorithm::Md5),
+            [
+                0x5e, 0xb6, 0x3b, 0xbb, 0xe0, 0x1e, 0xee, 0xd0, 0x93, 0xcb, 0x22, 0xbb, 0x8f, 0x5a,
+                0xcd, 0xc3
+            ]
+        );
+        assert_eq!(
+            hash(ChecksumAlgorithm::Sha256),
+            [
+                0xb9, 0x4d, 0x27, 0xb9, 0x93, 0x4d, 0x3e, 0x08, 0xa5, 0x2e, 0x52, 0xd7, 0xda, 0x7d,
+                0xab, 0xfa, 0xc4, 0x84, 0xef, 0xe3, 0x7a, 0x53, 0x80, 0xee, 0x90, 0x88, 0xf7, 0xac,
+                0xe2, 0xef, 0xcd, 0xe9
+            ]
+        );
+    }
+}
@@ -1,8 +1,16 @@
 use hmac::{Hmac, Mac};
+use reqwest::{
+    header::{HeaderMap, CONTENT_TYPE},
+    Method, Url,
+};
 use sha1::Sha1;
 
 use super::base64;
 
+const FORM_MIME: &str = "application/x-www-form-urlencoded";
+const OCTET_STREAM_MIME: &str = "application/octet-stream";
+const X_QINIU_PREFIX: &str = "X-Qiniu-";
+
 /// 七牛凭证，用于设置 Access Key 和 Secret Key 以访问私有空间的七牛对象
 #[derive(Debug, Clone)]
 pub struct Credential {
@@ -36,6 +44,76 @@ impl Credential {
         self.sign(encoded_data.as_bytes()) + ":" + &encoded_data
     }
 
+    /// 为 HTTP 请求签名，同时生成 QBox 和 Qiniu 两种 Authorization 头
+    ///
+    /// 可以用于调用七牛的其他管理接口
+    pub fn sign_request(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> RequestAuthorization {
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty());
+        RequestAuthorization {
+            qbox: self.qbox_authorization(url, content_type, body),
+            qiniu: self.qiniu_authorization(method, url, content_type, headers, body),
+        }
+    }
+
+    fn qbox_authorization(&self, url: &Url, content_type: Option<&str>, body: &[u8]) -> String {
+        let mut data = path_with_query(url).into_bytes();
+        data.push(b'\n');
+        if content_type == Some(FORM_MIME) {
+            data.extend_from_slice(body);
+        }
+        "QBox ".to_owned() + &self.sign(&data)
+    }
+
+    fn qiniu_authorization(
+        &self,
+        method: &Method,
+        url: &Url,
+        content_type: Option<&str>,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> String {
+        let mut data = format!("{} {}", method.as_str(), path_with_query(url));
+        data.push_str("\nHost: ");
+        data.push_str(url.host_str().unwrap_or_default());
+        if let Some(port) = url.port() {
+            data.push_str(&format!(":{}", port));
+        }
+        if let Some(content_type) = content_type {
+            data.push_str("\nContent-Type: ");
+            data.push_str(content_type);
+        }
+        let mut x_qiniu_headers: Vec<_> = headers
+            .iter()
+            .map(|(name, value)| (canonical_header_name(name.as_str()), value))
+            .filter(|(name, _)| {
+                name.len() > X_QINIU_PREFIX.len() && name.starts_with(X_QINIU_PREFIX)
+            })
+            .filter_map(|(name, value)| value.to_str().ok().map(|value| (name, value)))
+            .collect();
+        x_qiniu_headers.sort();
+        for (name, value) in x_qiniu_headers {
+            data.push('\n');
+            data.push_str(&name);
+            data.push_str(": ");
+            data.push_str(value);
+        }
+        data.push_str("\n\n");
+        let mut data = data.into_bytes();
+        if matches!(content_type, Some(content_type) if content_type != OCTET_STREAM_MIME) {
+            data.extend_from_slice(body);
+        }
+        "Qiniu ".to_owned() + &self.sign(&data)
+    }
+
     fn base64_hmac_digest(&self, data: &[u8]) -> String {
         let mut hmac = Hmac::<Sha1>::new_from_slice(self.secret_key.as_bytes()).unwrap();
         hmac.update(data);
@@ -43,6 +121,51 @@ impl Credential {
     }
 }
 
+/// HTTP 请求的 Authorization 头
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestAuthorization {
+    qbox: String,
+    qiniu: String,
+}
+
+impl RequestAuthorization {
+    /// 获取 QBox 签名的 Authorization 头，形如 `QBox <AccessKey>:<Signature>`
+    #[inline]
+    pub fn qbox(&self) -> &str {
+        &self.qbox
+    }
+
+    /// 获取 Qiniu 签名的 Authorization 头，形如 `Qiniu <AccessKey>:<Signature>`
+    #[inline]
+    pub fn qiniu(&self) -> &str {
+        &self.qiniu
+    }
+}
+
+fn path_with_query(url: &Url) -> String {
+    let mut path = url.path().to_owned();
+    if let Some(query) = url.query().filter(|query| !query.is_empty()) {
+        path.push('?');
+        path.push_str(query);
+    }
+    path
+}
+
+fn canonical_header_name(name: &str) -> String {
+    name.split('-')
+        .map(|segment| {
+            let mut chars = segment.chars();
+            chars
+                .next()
+                .map(|first| {
+                    first.to_ascii_uppercase().to_string() + &chars.as_str().to_ascii_lowercase()
+                })
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -84,4 +207,45 @@ mod tests {
             .for_each(|thread| thread.join().unwrap());
         Ok(())
     }
+
+    #[test]
+    fn test_sign_request() -> Result<(), Box<dyn Error>> {
+        let credential = Credential::new("abcdefghklmnopq", "1234567890");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, FORM_MIME.parse()?);
+        headers.insert("x-qiniu-cxx", "1".parse()?);
+        headers.insert("X-Qiniu-bbb", "2".parse()?);
+        headers.insert("x-qiniu-", "ignored".parse()?);
+        let authorization = credential.sign_request(
+            &Method::POST,
+            &Url::parse("http://rs.qiniu.com:8888/move/bucket?x=1")?,
+            &headers,
+            b"a=b&c=d",
+        );
+        assert_eq!(
+            authorization.qbox(),
+            "QBox abcdefghklmnopq:TL50o4g-1Dl7FSXwDxFlU_i1k6c="
+        );
+        assert_eq!(
+            authorization.qiniu(),
+            "Qiniu abcdefghklmnopq:txxLPm1DMh3kGXSAWdUGsjwj19k="
+        );
+
+        let authorization = credential.sign_request(
+            &Method::GET,
+            &Url::parse("https://uc.qbox.me/v4/query?ak=1")?,
+            &HeaderMap::new(),
+            b"",
+        );
+        assert_eq!(
+            authorization.qbox(),
+            "QBox abcdefghklmnopq:_f0_ED2Z3GZ9OKHmGkUvEPEXP4g="
+        );
+        assert_eq!(
+            authorization.qiniu(),
+            "Qiniu abcdefghklmnopq:7MKA88ohBzZuRXdiE-33bqHA1-o="
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,96 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering::Relaxed};
+
+const MAX_SAMPLE_RATE: u32 = 1 << 16;
+const MAX_ESCALATION: u32 = 10;
+
+/// 打点采样器
+///
+/// 每 `sample_rate` 次 API 调用仅记录一次，记录时的调用次数乘以采样率，保证汇总后的次数基本准确。
+/// 设置了 `escalation_threshold` 后，打点缓存文件超过该尺寸时采样率会自动翻倍，
+/// 缓存文件回落到阈值的一半以下后恢复为设置的采样率
+#[derive(Debug)]
+pub(crate) struct DotSampler {
+    sample_rate: u32,
+    escalation_threshold: Option<u64>,
+    escalation: AtomicU32,
+    counter: AtomicU64,
+}
+
+impl DotSampler {
+    pub(crate) fn new(sample_rate: Option<u32>, escalation_threshold: Option<u64>) -> Self {
+        Self {
+            sample_rate: sample_rate.unwrap_or(1).clamp(1, MAX_SAMPLE_RATE),
+            escalation_threshold: escalation_threshold.filter(|&threshold| threshold > 0),
+            escalation: AtomicU32::new(0),
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    /// 判断本次调用是否需要记录，需要记录时返回记录的调用次数
+    pub(crate) fn sample(&self) -> Option<usize> {
+        let rate = self.effective_rate();
+        if rate <= 1 || self.counter.fetch_add(1, Relaxed) % u64::from(rate) == 0 {
+            Some(rate as usize)
+        } else {
+            None
+        }
+    }
+
+    /// 当前实际生效的采样率
+    pub(crate) fn effective_rate(&self) -> u32 {
+        self.sample_rate
+            .saturating_mul(1 << self.escalation.load(Relaxed))
+            .min(MAX_SAMPLE_RATE)
+    }
+
+    /// 是否会根据打点缓存文件的尺寸自动提高采样率
+    #[inline]
+    pub(crate) fn escalates(&self) -> bool {
+        self.escalation_threshold.is_some()
+    }
+
+    /// 根据打点缓存文件的尺寸调整采样率
+    pub(crate) fn observe_buffer_size(&self, buffer_size: u64) {
+        if let Some(threshold) = self.escalation_threshold {
+            if buffer_size > threshold {
+                self.escalation
+                    .fetch_update(Relaxed, Relaxed, |escalation| {
+                        Some((escalation + 1).min(MAX_ESCALATION))
+                    })
+                    .ok();
+            } else if buffer_size <= threshold / 2 {
+                self.escalation.store(0, Relaxed);
+            }
+        }
+    }
+}
+
+impl Default for DotSampler {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot_sampler() {
+        let sampler = DotSampler::default();
+        assert!((0..10).all(|_| sampler.sample() == Some(1)));
+
+        let sampler = DotSampler::new(Some(4), Some(1024));
+        let sampled: Vec<_> = (0..16).filter_map(|_| sampler.sample()).collect();
+        assert_eq!(sampled, vec![4; 4]);
+
+        sampler.observe_buffer_size(2048);
+        assert_eq!(sampler.effective_rate(), 8);
+        sampler.observe_buffer_size(2048);
+        assert_eq!(sampler.effective_rate(), 16);
+        sampler.observe_buffer_size(800);
+        assert_eq!(sampler.effective_rate(), 16);
+        sampler.observe_buffer_size(512);
+        assert_eq!(sampler.effective_rate(), 4);
+    }
+}
@@ -1,22 +1,881 @@
-use super::credential::Credential;
-use std::time::Duration;
+use super::{
+    super::config::{
+        ConnectionFailureKind, FailoverBackoff, KeyRewriteRule, RangeRequestEncoding,
+        RedirectPolicy, TimeoutsConfig,
+    },
+    credential::Credential,
+    rng::SharedRng,
+};
+use log::warn;
+use reqwest::{
+    header::{
+        HeaderMap, AGE, CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_ENCODING, CONTENT_LENGTH,
+        CONTENT_TYPE, ETAG, EXPIRES, LAST_MODIFIED,
+    },
+    Error as ReqwestError, Url,
+};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    error::Error as StdError,
+    ffi::OsString,
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+use thiserror::Error;
+
+/// 对象尺寸超过上限
+///
+/// 作为 [`std::io::Error`] 的内部错误返回，可以通过 `get_ref()` 和 `downcast_ref()` 获取
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Object size {size} exceeds the limit {max_object_size}")]
+pub struct ObjectTooLargeError {
+    size: u64,
+    max_object_size: u64,
+}
+
+impl ObjectTooLargeError {
+    /// 获取对象尺寸
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// 获取对象尺寸上限
+    #[inline]
+    pub fn max_object_size(&self) -> u64 {
+        self.max_object_size
+    }
+}
+
+/// `read_multi_ranges` 请求的区域超过限制
+///
+/// 作为 [`std::io::Error`] 的内部错误返回，可以通过 `get_ref()` 和 `downcast_ref()` 获取
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("{ranges} ranges with total size {total_size} exceed the limits of read_multi_ranges, read them in smaller batches or stream the object with chunks instead")]
+pub struct MultiRangesLimitError {
+    ranges: usize,
+    total_size: u64,
+    max_ranges: Option<usize>,
+    max_total_size: Option<u64>,
+}
+
+impl MultiRangesLimitError {
+    /// 获取请求的区域数量
+    #[inline]
+    pub fn ranges(&self) -> usize {
+        self.ranges
+    }
+
+    /// 获取请求的区域总长度
+    #[inline]
+    pub fn total_size(&self) -> u64 {
+        self.total_size
+    }
+
+    /// 获取区域数量上限
+    #[inline]
+    pub fn max_ranges(&self) -> Option<usize> {
+        self.max_ranges
+    }
+
+    /// 获取区域总长度上限
+    #[inline]
+    pub fn max_total_size(&self) -> Option<u64> {
+        self.max_total_size
+    }
+}
+
+/// 服务器返回了非预期的状态码
+///
+/// 作为 [`std::io::Error`] 的内部错误返回，可以通过 `get_ref()` 和 `downcast_ref()` 获取
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Unexpected status code {status_code}")]
+pub struct UnexpectedStatusCodeError {
+    status_code: u16,
+    final_host: Option<String>,
+}
+
+impl UnexpectedStatusCodeError {
+    /// 获取状态码
+    #[inline]
+    pub fn status_code(&self) -> u16 {
+        self.status_code
+    }
+
+    /// 获取实际返回该状态码的域名，跟随重定向后可能与请求的域名不同
+    #[inline]
+    pub fn final_host(&self) -> Option<&str> {
+        self.final_host.as_deref()
+    }
+}
+
+/// 会话期间对象被覆盖
+///
+/// 仅在会话模式下返回，作为 [`std::io::Error`] 的内部错误返回，可以通过 `get_ref()` 和 `downcast_ref()` 获取
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Object is changed during the session, expected etag {expected_etag}")]
+pub struct ObjectChangedError {
+    expected_etag: String,
+    actual_etag: Option<String>,
+}
+
+impl ObjectChangedError {
+    /// 获取会话开始时记录的 Etag
+    #[inline]
+    pub fn expected_etag(&self) -> &str {
+        &self.expected_etag
+    }
+
+    /// 获取服务器返回的 Etag，服务器以 412 状态码拒绝请求时为 None
+    #[inline]
+    pub fn actual_etag(&self) -> Option<&str> {
+        self.actual_etag.as_deref()
+    }
+}
+
+pub(crate) fn object_changed_error(expected_etag: String, actual_etag: Option<String>) -> IoError {
+    IoError::new(
+        IoErrorKind::InvalidData,
+        ObjectChangedError {
+            expected_etag,
+            actual_etag,
+        },
+    )
+}
+
+fn is_object_changed_error(error: &IoError) -> bool {
+    error
+        .get_ref()
+        .is_some_and(|err| err.is::<ObjectChangedError>())
+}
+
+/// 对象以压缩编码存储，不支持范围读取
+///
+/// 对象的响应带有 Content-Encoding 头时，范围读取的偏移量对应压缩后的数据而不是原始数据，因此直接返回该错误，
+/// 可以启用 `decompress_content_encoding` 后下载整个对象。
+/// 作为 [`std::io::Error`] 的内部错误返回，可以通过 `get_ref()` 和 `downcast_ref()` 获取
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Object is stored with Content-Encoding {content_encoding}, range reads are not supported, download the whole object instead")]
+pub struct ContentEncodedError {
+    content_encoding: String,
+}
+
+impl ContentEncodedError {
+    /// 获取对象的 Content-Encoding
+    #[inline]
+    pub fn content_encoding(&self) -> &str {
+        &self.content_encoding
+    }
+}
+
+pub(crate) fn content_encoded_error(content_encoding: &str) -> IoError {
+    IoError::new(
+        IoErrorKind::InvalidData,
+        ContentEncodedError {
+            content_encoding: content_encoding.to_owned(),
+        },
+    )
+}
+
+pub(crate) fn is_content_encoded_error(error: &IoError) -> bool {
+    error
+        .get_ref()
+        .is_some_and(|err| err.is::<ContentEncodedError>())
+}
+
+/// 获取响应的 Content-Encoding，未压缩编码时返回 None
+pub(crate) fn content_encoding_of(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .filter(|value| !value.is_empty() && value != "identity")
+}
+
+/// 协议错误类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ProtocolErrorKind {
+    /// 响应头尺寸超过上限
+    HeaderTooLarge,
+    /// 响应头的值不合法
+    InvalidHeader,
+    /// 无法解析的响应
+    MalformedResponse,
+}
+
+/// 服务器返回的响应不符合 HTTP 协议或超过了响应头的限制
+///
+/// 该错误将惩罚返回响应的域名，作为 [`std::io::Error`] 的内部错误返回，可以通过 `get_ref()` 和 `downcast_ref()` 获取
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Protocol error ({kind:?}): {message}")]
+pub struct ProtocolError {
+    kind: ProtocolErrorKind,
+    message: String,
+}
+
+impl ProtocolError {
+    /// 获取协议错误类型
+    #[inline]
+    pub fn kind(&self) -> ProtocolErrorKind {
+        self.kind
+    }
+
+    /// 获取错误描述
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+fn protocol_error(kind: ProtocolErrorKind, message: String) -> IoError {
+    IoError::new(IoErrorKind::Other, ProtocolError { kind, message })
+}
+
+/// 如果请求因为无法解析响应而失败，则转换为协议错误
+pub(crate) fn protocol_error_of(error: &ReqwestError) -> Option<IoError> {
+    let mut source = error.source();
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<hyper::Error>() {
+            if err.is_parse_too_large() {
+                return Some(protocol_error(
+                    ProtocolErrorKind::HeaderTooLarge,
+                    err.to_string(),
+                ));
+            } else if err.is_parse() || err.is_parse_status() {
+                return Some(protocol_error(
+                    ProtocolErrorKind::MalformedResponse,
+                    err.to_string(),
+                ));
+            }
+        }
+        source = err.source();
+    }
+    None
+}
+
+/// 校验响应头的尺寸，以及严格模式下响应头的值是否均为可见 ASCII 字符，Content-Length 是否一致
+pub(crate) fn check_response_headers(
+    headers: &HeaderMap,
+    max_header_size: Option<usize>,
+    strict: bool,
+) -> IoResult<()> {
+    if let Some(max_header_size) = max_header_size {
+        // 按照 `name: value\r\n` 的格式计算
+        let header_size: usize = headers
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len() + 4)
+            .sum();
+        if header_size > max_header_size {
+            return Err(protocol_error(
+                ProtocolErrorKind::HeaderTooLarge,
+                format!(
+                    "Response header size {} exceeds the limit {}",
+                    header_size, max_header_size
+                ),
+            ));
+        }
+    }
+    if strict {
+        if let Some((name, _)) = headers.iter().find(|(_, value)| value.to_str().is_err()) {
+            return Err(protocol_error(
+                ProtocolErrorKind::InvalidHeader,
+                format!("Response header {} contains invalid characters", name),
+            ));
+        }
+        let mut content_lengths = headers.get_all(CONTENT_LENGTH).iter();
+        if let Some(first) = content_lengths.next() {
+            if content_lengths.any(|value| value != first) {
+                return Err(protocol_error(
+                    ProtocolErrorKind::InvalidHeader,
+                    "Response contains conflicting Content-Length headers".to_owned(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+const MAX_POLL_INTERVAL_FACTOR: u32 = 16;
+
+/// 等待对象出现时第 `polls` 次轮询后的等待时长，`polls` 从 1 开始计数
+///
+/// 等待时长从轮询间隔开始按指数增长，最长为轮询间隔的 16 倍，实际的等待时长在其一半到全部之间随机选取
+pub(crate) fn wait_for_object_delay(
+    poll_interval: Duration,
+    polls: usize,
+    rng: &SharedRng,
+) -> Duration {
+    let max_delay = FailoverBackoff::exponential(
+        poll_interval,
+        poll_interval.saturating_mul(MAX_POLL_INTERVAL_FACTOR),
+    )
+    .max_delay(polls);
+    let min_delay = max_delay / 2;
+    if min_delay.is_zero() {
+        max_delay
+    } else {
+        rng.gen_duration(min_delay..=max_delay)
+    }
+}
+
+pub(crate) fn wait_for_object_timed_out_error(timeout: Duration) -> IoError {
+    IoError::new(
+        IoErrorKind::TimedOut,
+        format!("Object still does not exist after {:?}", timeout),
+    )
+}
+
+/// 下载目标文件正在被其他下载任务写入
+///
+/// 由 `download_to_path` 返回，作为 [`std::io::Error`] 的内部错误返回，可以通过 `get_ref()` 和 `downcast_ref()` 获取
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Destination {path:?} is being written by another download")]
+pub struct DestinationBusyError {
+    path: PathBuf,
+}
+
+impl DestinationBusyError {
+    /// 获取下载目标文件路径
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// 严格模式下无法满足的配置要求
+///
+/// 由严格模式下的 `try_build()` 或 `try_from_config()` 返回，作为 [`std::io::Error`] 的内部错误返回，可以通过 `get_ref()` 和 `downcast_ref()` 获取
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StrictModeError {
+    /// 未配置监控域名，打点数据无法上传
+    #[error("monitor_urls is empty, dot records can not be uploaded")]
+    EmptyMonitorUrls,
+    /// 缓存目录不可写，打点数据无法缓存
+    #[error("Cache directory is not writable: {reason}")]
+    CacheDirNotWritable {
+        /// 错误原因
+        reason: String,
+    },
+}
+
+pub(crate) fn strict_mode_error(err: StrictModeError) -> IoError {
+    IoError::new(IoErrorKind::InvalidInput, err)
+}
+
+pub(crate) fn destination_busy_error(path: &Path) -> IoError {
+    IoError::new(
+        IoErrorKind::WouldBlock,
+        DestinationBusyError {
+            path: path.to_owned(),
+        },
+    )
+}
+
+const DESTINATION_LOCK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 下载目标文件对应的锁文件路径
+///
+/// 目标文件可能被重命名覆盖，因此建议锁加在目标文件旁的 `.lock` 文件上，该文件创建后不会被删除
+pub(crate) fn destination_lock_path(path: &Path) -> PathBuf {
+    let mut lock_path = OsString::from(path.as_os_str());
+    lock_path.push(".lock");
+    lock_path.into()
+}
+
+/// 等待下载目标文件解锁时下一次尝试前的等待时长，超过截止时间则返回 None
+pub(crate) fn destination_lock_delay(deadline: Option<Instant>) -> Option<Duration> {
+    let remaining = deadline?.checked_duration_since(Instant::now())?;
+    if remaining.is_zero() {
+        None
+    } else {
+        Some(remaining.min(DESTINATION_LOCK_POLL_INTERVAL))
+    }
+}
+
+const USER_METADATA_PREFIX: &str = "x-qn-meta-";
+
+/// 解析 `x-qn-meta-*` 响应头中的用户自定义元数据
+///
+/// 响应头名称不区分大小写，因此元数据名称统一为小写。
+/// 值可以是 UTF-8 编码或百分号编码的，无法解码为 UTF-8 的值将被忽略
+pub(crate) fn user_metadata_of(headers: &HeaderMap) -> HashMap<String, String> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let name = name.as_str().strip_prefix(USER_METADATA_PREFIX)?;
+            if name.is_empty() {
+                return None;
+            }
+            Some((name.to_owned(), percent_decode(value.as_bytes())?))
+        })
+        .collect()
+}
+
+/// 对象元信息
+///
+/// 由 `stat()` 返回，解析自对象 HEAD 请求的响应头
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObjectMetadata {
+    size: u64,
+    etag: Option<String>,
+    content_type: Option<String>,
+    last_modified: Option<SystemTime>,
+    user_metadata: HashMap<String, String>,
+    cache_control: Option<String>,
+    expires: Option<SystemTime>,
+    age: Option<Duration>,
+    content_disposition: Option<String>,
+}
+
+impl ObjectMetadata {
+    pub(crate) fn from_headers(size: u64, headers: &HeaderMap) -> Self {
+        let header_str = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let cache_control = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Vec<_>>();
+        Self {
+            size,
+            etag: headers
+                .get(ETAG)
+                .map(|etag| String::from_utf8_lossy(etag.as_bytes()).into_owned()),
+            content_type: header_str(CONTENT_TYPE).map(|value| value.to_owned()),
+            last_modified: header_str(LAST_MODIFIED)
+                .and_then(|value| httpdate::parse_http_date(value.trim()).ok()),
+            user_metadata: user_metadata_of(headers),
+            cache_control: Some(cache_control.join(", ")).filter(|value| !value.is_empty()),
+            expires: header_str(EXPIRES)
+                .and_then(|value| httpdate::parse_http_date(value.trim()).ok()),
+            age: header_str(AGE)
+                .and_then(|value| value.trim().parse().ok())
+                .map(Duration::from_secs),
+            content_disposition: header_str(CONTENT_DISPOSITION).map(|value| value.to_owned()),
+        }
+    }
+
+    /// 获取对象尺寸
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// 获取对象的 Etag，与响应头中的值相同，包括两侧的引号
+    #[inline]
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+
+    /// 获取对象的 MIME 类型
+    #[inline]
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// 获取对象的最后修改时间
+    #[inline]
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        self.last_modified
+    }
+
+    /// 获取 `x-qn-meta-*` 响应头中的用户自定义元数据，名称统一为小写
+    #[inline]
+    pub fn user_metadata(&self) -> &HashMap<String, String> {
+        &self.user_metadata
+    }
+
+    /// 获取 Cache-Control 响应头，多个响应头以 `, ` 连接
+    #[inline]
+    pub fn cache_control(&self) -> Option<&str> {
+        self.cache_control.as_deref()
+    }
+
+    /// 获取 Expires 响应头表示的过期时间，无法解析时为 None
+    #[inline]
+    pub fn expires(&self) -> Option<SystemTime> {
+        self.expires
+    }
+
+    /// 获取 Age 响应头，即响应在上游缓存中已经保存的时长
+    #[inline]
+    pub fn age(&self) -> Option<Duration> {
+        self.age
+    }
+
+    /// 获取 Content-Disposition 响应头，通过 `attname` 指定下载文件名时由服务器返回
+    #[inline]
+    pub fn content_disposition(&self) -> Option<&str> {
+        self.content_disposition.as_deref()
+    }
+
+    /// 获取 Content-Disposition 响应头中指定的文件名，已去除其中的路径
+    pub(crate) fn attachment_file_name(&self) -> Option<String> {
+        self.content_disposition
+            .as_deref()
+            .and_then(file_name_of_content_disposition)
+            .and_then(|file_name| sanitize_file_name(&file_name))
+    }
+}
+
+/// 解析 Content-Disposition 中的文件名，`filename*` 优先于 `filename`
+fn file_name_of_content_disposition(value: &str) -> Option<String> {
+    let mut file_name = None;
+    for param in split_header_params(value).into_iter().skip(1) {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => continue,
+        };
+        if name.eq_ignore_ascii_case("filename*") {
+            // RFC 5987 格式，即 `<字符集>'<语言>'<百分号编码的值>`，仅支持 UTF-8 字符集
+            let mut parts = value.splitn(3, '\'');
+            if let (Some(charset), Some(_), Some(encoded)) =
+                (parts.next(), parts.next(), parts.next())
+            {
+                if charset.eq_ignore_ascii_case("utf-8") {
+                    if let Some(decoded) = percent_decode(encoded.as_bytes()) {
+                        return Some(decoded);
+                    }
+                }
+            }
+        } else if name.eq_ignore_ascii_case("filename") && file_name.is_none() {
+            file_name = Some(unquote(value));
+        }
+    }
+    file_name
+}
+
+/// 按照 `;` 分割响应头中的参数，引号中的 `;` 不作为分隔符
+fn split_header_params(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let (mut begin, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if quoted && c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            quoted = !quoted;
+        } else if c == ';' && !quoted {
+            params.push(&value[begin..i]);
+            begin = i + 1;
+        }
+    }
+    params.push(&value[begin..]);
+    params
+}
+
+/// 去除引号字符串两侧的引号及其中的转义符
+fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Some(quoted) => {
+            let mut unquoted = String::with_capacity(quoted.len());
+            let mut chars = quoted.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => unquoted.extend(chars.next()),
+                    c => unquoted.push(c),
+                }
+            }
+            unquoted
+        }
+        None => value.to_owned(),
+    }
+}
+
+/// 将服务器返回的文件名转换为可以安全地在目录中创建的文件名
+///
+/// 仅保留最后一级路径，去除控制字符，结果为空、`.` 或 `..` 时返回 None
+pub(crate) fn sanitize_file_name(file_name: &str) -> Option<String> {
+    let file_name = file_name
+        .rsplit(['/', '\\', ':'])
+        .next()?
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>();
+    let file_name = file_name.trim();
+    if file_name.is_empty() || file_name == "." || file_name == ".." {
+        None
+    } else {
+        Some(file_name.to_owned())
+    }
+}
+
+/// 解码百分号编码，不合法的百分号编码保留原样
+fn percent_decode(bytes: &[u8]) -> Option<String> {
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = |b: u8| (b as char).to_digit(16);
+            if let (Some(high), Some(low)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                decoded.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// 表示对象不存在的状态码，其中 612 为七牛自定义的状态码
+const NOT_FOUND_STATUS_CODES: [u16; 2] = [404, 612];
+
+#[inline]
+pub(crate) fn is_not_found_status_code(status_code: u16) -> bool {
+    NOT_FOUND_STATUS_CODES.contains(&status_code)
+}
+
+pub(crate) fn unexpected_status_code_error(
+    status_code: u16,
+    final_host: Option<String>,
+) -> IoError {
+    let error_kind = if is_not_found_status_code(status_code) {
+        IoErrorKind::NotFound
+    } else if (400..500).contains(&status_code) {
+        IoErrorKind::InvalidData
+    } else {
+        IoErrorKind::Other
+    };
+    IoError::new(
+        error_kind,
+        UnexpectedStatusCodeError {
+            status_code,
+            final_host,
+        },
+    )
+}
+
+fn unexpected_status_code_of(error: &IoError) -> Option<&UnexpectedStatusCodeError> {
+    error
+        .get_ref()
+        .and_then(|err| err.downcast_ref::<UnexpectedStatusCodeError>())
+}
+
+pub(crate) fn status_code_of(error: &IoError) -> Option<u16> {
+    unexpected_status_code_of(error).map(|err| err.status_code())
+}
+
+/// 获取实际返回错误的域名，仅当该域名与请求的域名不同时返回，即请求被重定向到了其他域名
+pub(crate) fn redirected_host_of<'a>(error: &'a IoError, requested_host: &str) -> Option<&'a str> {
+    unexpected_status_code_of(error)
+        .and_then(|err| err.final_host())
+        .filter(|&final_host| final_host != requested_host)
+}
+
+/// 判断错误是否为对象级错误
+///
+/// 对象级错误说明域名本身工作正常，因此不应该惩罚域名，也不应该记录为失败的打点。
+/// 除对象不存在、会话期间对象被覆盖和对象以压缩编码存储以外，还可以通过 `object_level_status_codes` 指定其他状态码
+pub(crate) fn is_object_level_error(error: &IoError, object_level_status_codes: &[u16]) -> bool {
+    if is_object_changed_error(error) || is_content_encoded_error(error) {
+        return true;
+    }
+    status_code_of(error).is_some_and(|status_code| {
+        is_not_found_status_code(status_code) || object_level_status_codes.contains(&status_code)
+    })
+}
+
+/// 判断是否应该惩罚域名并切换域名重试
+///
+/// 对象级错误均不重试，未配置可重试的状态码时，除客户端错误以外的错误均可重试
+pub(crate) fn should_retry(
+    error: &IoError,
+    retryable_status_codes: Option<&[u16]>,
+    object_level_status_codes: &[u16],
+) -> bool {
+    if is_object_level_error(error, object_level_status_codes) {
+        return false;
+    }
+    match (status_code_of(error), retryable_status_codes) {
+        (Some(status_code), Some(retryable_status_codes)) => {
+            retryable_status_codes.contains(&status_code)
+        }
+        _ => error.kind() != IoErrorKind::InvalidData,
+    }
+}
+
+pub(crate) fn check_body_size(actual: u64, expected: u64) -> IoResult<()> {
+    if actual < expected {
+        Err(IoError::new(
+            IoErrorKind::UnexpectedEof,
+            format!(
+                "Response body is shorter than expected: {} < {}",
+                actual, expected
+            ),
+        ))
+    } else if actual > expected {
+        Err(IoError::new(
+            IoErrorKind::Other,
+            format!(
+                "Response body is longer than expected: {} > {}",
+                actual, expected
+            ),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// 计算指定区域在完整响应体中的下标范围，超出响应体的部分将被截断，区域为空时返回 None
+///
+/// 偏移量和长度均以 u64 计算，避免在 32 位平台上截断 4 GiB 以外的偏移量
+pub(crate) fn range_in_body(body_len: usize, from: u64, len: u64) -> Option<Range<usize>> {
+    let body_len = body_len as u64;
+    let from = from.min(body_len);
+    let len = len.min(body_len - from);
+    if len > 0 {
+        Some(from as usize..(from + len) as usize)
+    } else {
+        None
+    }
+}
+
+/// 根据区域长度计算缓冲区的初始容量，超出 usize 的表示范围时不预先分配
+#[inline]
+pub(crate) fn capacity_hint(len: u64) -> usize {
+    usize::try_from(len).unwrap_or_default()
+}
+
+pub(crate) fn check_object_size(size: u64, max_object_size: Option<u64>) -> IoResult<()> {
+    match max_object_size {
+        Some(max_object_size) if size > max_object_size => Err(IoError::new(
+            IoErrorKind::Other,
+            ObjectTooLargeError {
+                size,
+                max_object_size,
+            },
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// 检查 `read_multi_ranges` 请求的区域数量和总长度，避免一次缓存过多数据
+pub(crate) fn check_multi_ranges(
+    ranges: &[(u64, u64)],
+    max_ranges: Option<usize>,
+    max_total_size: Option<u64>,
+) -> IoResult<()> {
+    let total_size = ranges
+        .iter()
+        .fold(0u64, |total, &(_, size)| total.saturating_add(size));
+    if max_ranges.is_some_and(|max_ranges| ranges.len() > max_ranges)
+        || max_total_size.is_some_and(|max_total_size| total_size > max_total_size)
+    {
+        Err(IoError::new(
+            IoErrorKind::InvalidInput,
+            MultiRangesLimitError {
+                ranges: ranges.len(),
+                total_size,
+                max_ranges,
+                max_total_size,
+            },
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DownloadUrlOptions {
+    pub(crate) path_prefix: Option<String>,
+    pub(crate) style: Option<String>,
+    pub(crate) fop: Option<String>,
+    pub(crate) attname: Option<String>,
+    pub(crate) traffic_tag: Option<String>,
+}
+
+const TRAFFIC_TAG_PARAM: &str = "traffictag";
+const MAX_TRAFFIC_TAG_LEN: usize = 64;
+
+/// 流量标签仅允许使用字母、数字、`-`、`_` 和 `.`，且长度不超过 64
+fn is_valid_traffic_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag.len() <= MAX_TRAFFIC_TAG_LEN
+        && tag
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.')
+}
+
+impl DownloadUrlOptions {
+    /// 在域名之后拼接路径前缀，作为下载地址的基础地址
+    pub(crate) fn base_url(&self, host: &str) -> String {
+        if let Some(path_prefix) = &self.path_prefix {
+            format!("{}{}", host.trim_end_matches('/'), path_prefix)
+        } else {
+            host.to_owned()
+        }
+    }
+
+    pub(crate) fn apply_to(&self, mut url: String) -> String {
+        if let Some(style) = &self.style {
+            url.push_str(style);
+        }
+        if let Some(fop) = &self.fop {
+            url.push(if url.contains('?') { '&' } else { '?' });
+            url.push_str(fop);
+        }
+        if let Some(attname) = &self.attname {
+            if let Ok(mut parsed) = Url::parse(&url) {
+                parsed.query_pairs_mut().append_pair("attname", attname);
+                url = parsed.into();
+            }
+        }
+        if let Some(traffic_tag) = &self.traffic_tag {
+            url.push(if url.contains('?') { '&' } else { '?' });
+            url.push_str(TRAFFIC_TAG_PARAM);
+            url.push('=');
+            url.push_str(traffic_tag);
+        }
+        url
+    }
+}
 
 #[derive(Debug, Clone)]
 pub(crate) struct RangeReaderBuilder {
     pub(crate) credential: Credential,
     pub(crate) bucket: String,
     pub(crate) key: String,
+    pub(crate) cluster_name: Option<String>,
     pub(crate) io_urls: Vec<String>,
+    pub(crate) cdn_urls: Vec<String>,
     pub(crate) uc_urls: Vec<String>,
     pub(crate) monitor_urls: Vec<String>,
     pub(crate) io_tries: usize,
     pub(crate) uc_tries: usize,
+    pub(crate) update_hosts_tries: usize,
     pub(crate) update_interval: Option<Duration>,
+    pub(crate) force_update_interval: Option<Duration>,
+    pub(crate) negative_cache_ttl: Option<Duration>,
+    pub(crate) head_cache_window: Option<Duration>,
+    pub(crate) preload_cache_size: Option<u64>,
+    pub(crate) preload_cache_ttl: Option<Duration>,
+    pub(crate) respect_cache_control: bool,
+    pub(crate) force_https: bool,
+    pub(crate) random_seed: Option<u64>,
+    pub(crate) verify_with_second_host: f64,
+    pub(crate) preferred_idc: Option<String>,
     pub(crate) punish_duration: Option<Duration>,
     pub(crate) base_timeout: Option<Duration>,
+    pub(crate) max_timeout: Option<Duration>,
+    pub(crate) host_base_timeouts: HashMap<String, Duration>,
     pub(crate) dial_timeout: Option<Duration>,
+    pub(crate) dns_cache_min_ttl: Option<Duration>,
+    pub(crate) dns_cache_max_ttl: Option<Duration>,
+    pub(crate) redirect_policy: Option<RedirectPolicy>,
     pub(crate) max_punished_times: Option<usize>,
     pub(crate) max_punished_hosts_percent: Option<u8>,
+    pub(crate) min_hosts_on_update: Option<usize>,
+    pub(crate) max_host_change_ratio: Option<f64>,
     pub(crate) use_getfile_api: bool,
     pub(crate) normalize_key: bool,
     pub(crate) private_url_lifetime: Option<Duration>,
@@ -24,7 +883,38 @@ pub(crate) struct RangeReaderBuilder {
     pub(crate) dot_tries: Option<usize>,
     pub(crate) dot_interval: Option<Duration>,
     pub(crate) max_dot_buffer_size: Option<u64>,
+    pub(crate) dot_credential: Option<Credential>,
+    pub(crate) dot_bucket: Option<String>,
+    pub(crate) dot_token_lifetime: Option<Duration>,
+    pub(crate) dot_sample_rate: Option<u32>,
+    pub(crate) dot_sampling_threshold: Option<u64>,
+    pub(crate) dot_latency_buckets_ms: Option<Vec<u64>>,
+    pub(crate) dot_sync_runtime_load: bool,
+    pub(crate) dot_stage_timings: bool,
     pub(crate) max_retry_concurrency: Option<u32>,
+    pub(crate) url_options: DownloadUrlOptions,
+    pub(crate) timeouts: TimeoutsConfig,
+    pub(crate) max_object_size: Option<u64>,
+    pub(crate) strict_body_size: bool,
+    pub(crate) max_response_header_size: Option<usize>,
+    pub(crate) strict_header_validation: bool,
+    pub(crate) disable_read_multi_ranges: bool,
+    pub(crate) range_request_encoding: RangeRequestEncoding,
+    pub(crate) integrity_metadata: bool,
+    pub(crate) preserve_last_modified: bool,
+    pub(crate) download_buffer_size: Option<u64>,
+    pub(crate) max_multi_ranges: Option<usize>,
+    pub(crate) max_multi_ranges_size: Option<u64>,
+    pub(crate) disable_download: bool,
+    pub(crate) decompress_content_encoding: bool,
+    pub(crate) strict: bool,
+    pub(crate) failover_backoff: FailoverBackoff,
+    pub(crate) connection_failure_kinds: Vec<ConnectionFailureKind>,
+    pub(crate) retryable_status_codes: Option<Vec<u16>>,
+    pub(crate) object_level_status_codes: Vec<u16>,
+    pub(crate) key_rewrite_rules: Vec<KeyRewriteRule>,
+    pub(crate) sync_request_timeout: Option<Duration>,
+    pub(crate) sync_worker_threads: usize,
 }
 
 impl RangeReaderBuilder {
@@ -37,18 +927,38 @@ impl RangeReaderBuilder {
         RangeReaderBuilder {
             bucket,
             key,
+            cluster_name: None,
             credential,
             io_urls,
+            cdn_urls: vec![],
             uc_urls: vec![],
             monitor_urls: vec![],
             io_tries: 10,
             uc_tries: 10,
+            update_hosts_tries: 3,
             update_interval: None,
+            force_update_interval: None,
+            negative_cache_ttl: None,
+            head_cache_window: None,
+            preload_cache_size: None,
+            preload_cache_ttl: None,
+            respect_cache_control: false,
+            force_https: false,
+            random_seed: None,
+            verify_with_second_host: 0.0,
+            preferred_idc: None,
             punish_duration: None,
             base_timeout: None,
+            max_timeout: None,
+            host_base_timeouts: Default::default(),
             dial_timeout: None,
+            dns_cache_min_ttl: None,
+            dns_cache_max_ttl: None,
+            redirect_policy: None,
             max_punished_times: None,
             max_punished_hosts_percent: None,
+            min_hosts_on_update: None,
+            max_host_change_ratio: None,
             use_getfile_api: true,
             normalize_key: false,
             private_url_lifetime: None,
@@ -56,10 +966,51 @@ impl RangeReaderBuilder {
             dot_tries: None,
             dot_interval: None,
             max_dot_buffer_size: None,
+            dot_credential: None,
+            dot_bucket: None,
+            dot_token_lifetime: None,
+            dot_sample_rate: None,
+            dot_sampling_threshold: None,
+            dot_latency_buckets_ms: None,
+            dot_sync_runtime_load: false,
+            dot_stage_timings: false,
             max_retry_concurrency: None,
+            url_options: Default::default(),
+            timeouts: Default::default(),
+            max_object_size: None,
+            strict_body_size: false,
+            max_response_header_size: None,
+            strict_header_validation: false,
+            disable_read_multi_ranges: false,
+            range_request_encoding: Default::default(),
+            integrity_metadata: false,
+            preserve_last_modified: false,
+            download_buffer_size: None,
+            max_multi_ranges: None,
+            max_multi_ranges_size: None,
+            disable_download: false,
+            decompress_content_encoding: false,
+            strict: false,
+            failover_backoff: Default::default(),
+            connection_failure_kinds: ConnectionFailureKind::ALL.to_vec(),
+            retryable_status_codes: None,
+            object_level_status_codes: vec![],
+            key_rewrite_rules: vec![],
+            sync_request_timeout: None,
+            sync_worker_threads: 1,
         }
     }
 
+    pub(crate) fn cluster_name(mut self, cluster_name: String) -> Self {
+        self.cluster_name = Some(cluster_name);
+        self
+    }
+
+    pub(crate) fn cdn_urls(mut self, urls: Vec<String>) -> Self {
+        self.cdn_urls = urls;
+        self
+    }
+
     pub(crate) fn uc_urls(mut self, urls: Vec<String>) -> Self {
         self.uc_urls = urls;
         self
@@ -80,6 +1031,11 @@ impl RangeReaderBuilder {
         self
     }
 
+    pub(crate) fn update_hosts_tries(mut self, tries: usize) -> Self {
+        self.update_hosts_tries = tries;
+        self
+    }
+
     pub(crate) fn dot_tries(mut self, tries: usize) -> Self {
         self.dot_tries = Some(tries);
         self
@@ -90,6 +1046,36 @@ impl RangeReaderBuilder {
         self
     }
 
+    pub(crate) fn force_update_interval(mut self, interval: Duration) -> Self {
+        self.force_update_interval = Some(interval);
+        self
+    }
+
+    pub(crate) fn negative_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_cache_ttl = Some(ttl);
+        self
+    }
+
+    pub(crate) fn head_cache_window(mut self, window: Duration) -> Self {
+        self.head_cache_window = Some(window);
+        self
+    }
+
+    pub(crate) fn preload_cache_size(mut self, size: u64) -> Self {
+        self.preload_cache_size = Some(size);
+        self
+    }
+
+    pub(crate) fn preload_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.preload_cache_ttl = Some(ttl);
+        self
+    }
+
+    pub(crate) fn respect_cache_control(mut self, respect_cache_control: bool) -> Self {
+        self.respect_cache_control = respect_cache_control;
+        self
+    }
+
     pub(crate) fn punish_duration(mut self, duration: Duration) -> Self {
         self.punish_duration = Some(duration);
         self
@@ -100,11 +1086,36 @@ impl RangeReaderBuilder {
         self
     }
 
+    pub(crate) fn max_timeout(mut self, timeout: Duration) -> Self {
+        self.max_timeout = Some(timeout);
+        self
+    }
+
+    pub(crate) fn host_base_timeouts(mut self, timeouts: HashMap<String, Duration>) -> Self {
+        self.host_base_timeouts = timeouts;
+        self
+    }
+
     pub(crate) fn connect_timeout(mut self, timeout: Duration) -> Self {
         self.dial_timeout = Some(timeout);
         self
     }
 
+    pub(crate) fn dns_cache_min_ttl(mut self, ttl: Duration) -> Self {
+        self.dns_cache_min_ttl = Some(ttl);
+        self
+    }
+
+    pub(crate) fn dns_cache_max_ttl(mut self, ttl: Duration) -> Self {
+        self.dns_cache_max_ttl = Some(ttl);
+        self
+    }
+
+    pub(crate) fn redirect_policy(mut self, redirect_policy: RedirectPolicy) -> Self {
+        self.redirect_policy = Some(redirect_policy);
+        self
+    }
+
     pub(crate) fn max_punished_times(mut self, max_times: usize) -> Self {
         self.max_punished_times = Some(max_times);
         self
@@ -115,6 +1126,16 @@ impl RangeReaderBuilder {
         self
     }
 
+    pub(crate) fn min_hosts_on_update(mut self, min_hosts: usize) -> Self {
+        self.min_hosts_on_update = Some(min_hosts);
+        self
+    }
+
+    pub(crate) fn max_host_change_ratio(mut self, ratio: f64) -> Self {
+        self.max_host_change_ratio = Some(ratio);
+        self
+    }
+
     pub(crate) fn use_getfile_api(mut self, use_getfile_api: bool) -> Self {
         self.use_getfile_api = use_getfile_api;
         self
@@ -140,6 +1161,46 @@ impl RangeReaderBuilder {
         self
     }
 
+    pub(crate) fn dot_credential(mut self, credential: Credential) -> Self {
+        self.dot_credential = Some(credential);
+        self
+    }
+
+    pub(crate) fn dot_bucket(mut self, bucket: String) -> Self {
+        self.dot_bucket = Some(bucket);
+        self
+    }
+
+    pub(crate) fn dot_token_lifetime(mut self, lifetime: Duration) -> Self {
+        self.dot_token_lifetime = Some(lifetime);
+        self
+    }
+
+    pub(crate) fn dot_sample_rate(mut self, sample_rate: u32) -> Self {
+        self.dot_sample_rate = Some(sample_rate);
+        self
+    }
+
+    pub(crate) fn dot_sampling_threshold(mut self, threshold: u64) -> Self {
+        self.dot_sampling_threshold = Some(threshold);
+        self
+    }
+
+    pub(crate) fn dot_latency_buckets_ms(mut self, buckets_ms: Vec<u64>) -> Self {
+        self.dot_latency_buckets_ms = Some(buckets_ms);
+        self
+    }
+
+    pub(crate) fn dot_sync_runtime_load(mut self, dot_sync_runtime_load: bool) -> Self {
+        self.dot_sync_runtime_load = dot_sync_runtime_load;
+        self
+    }
+
+    pub(crate) fn dot_stage_timings(mut self, dot_stage_timings: bool) -> Self {
+        self.dot_stage_timings = dot_stage_timings;
+        self
+    }
+
     pub(crate) fn max_retry_concurrency(mut self, max_retry_concurrency: u32) -> Self {
         self.max_retry_concurrency = Some(max_retry_concurrency);
         self
@@ -149,4 +1210,499 @@ impl RangeReaderBuilder {
         self.use_https = use_https;
         self
     }
+
+    pub(crate) fn force_https(mut self, force_https: bool) -> Self {
+        self.force_https = force_https;
+        self
+    }
+
+    pub(crate) fn random_seed(mut self, seed: u64) -> Self {
+        self.random_seed = Some(seed);
+        self
+    }
+
+    pub(crate) fn verify_with_second_host(mut self, probability: f64) -> Self {
+        self.verify_with_second_host = probability;
+        self
+    }
+
+    pub(crate) fn preferred_idc(mut self, preferred_idc: String) -> Self {
+        self.preferred_idc = Some(preferred_idc);
+        self
+    }
+
+    pub(crate) fn timeouts(mut self, timeouts: TimeoutsConfig) -> Self {
+        if let Some(read) = timeouts.defaults().read() {
+            self.base_timeout = Some(read);
+        }
+        if let Some(connect) = timeouts.defaults().connect() {
+            self.dial_timeout = Some(connect);
+        }
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub(crate) fn max_object_size(mut self, max_object_size: u64) -> Self {
+        self.max_object_size = Some(max_object_size);
+        self
+    }
+
+    pub(crate) fn strict_body_size(mut self, strict_body_size: bool) -> Self {
+        self.strict_body_size = strict_body_size;
+        self
+    }
+
+    pub(crate) fn max_response_header_size(mut self, max_response_header_size: usize) -> Self {
+        self.max_response_header_size = Some(max_response_header_size);
+        self
+    }
+
+    pub(crate) fn strict_header_validation(mut self, strict_header_validation: bool) -> Self {
+        self.strict_header_validation = strict_header_validation;
+        self
+    }
+
+    pub(crate) fn disable_read_multi_ranges(mut self, disable_read_multi_ranges: bool) -> Self {
+        self.disable_read_multi_ranges = disable_read_multi_ranges;
+        self
+    }
+
+    pub(crate) fn range_request_encoding(
+        mut self,
+        range_request_encoding: RangeRequestEncoding,
+    ) -> Self {
+        self.range_request_encoding = range_request_encoding;
+        self
+    }
+
+    pub(crate) fn integrity_metadata(mut self, integrity_metadata: bool) -> Self {
+        self.integrity_metadata = integrity_metadata;
+        self
+    }
+
+    pub(crate) fn preserve_last_modified(mut self, preserve_last_modified: bool) -> Self {
+        self.preserve_last_modified = preserve_last_modified;
+        self
+    }
+
+    pub(crate) fn download_buffer_size(mut self, download_buffer_size: u64) -> Self {
+        self.download_buffer_size = Some(download_buffer_size);
+        self
+    }
+
+    pub(crate) fn max_multi_ranges(mut self, max_multi_ranges: usize) -> Self {
+        self.max_multi_ranges = Some(max_multi_ranges);
+        self
+    }
+
+    pub(crate) fn max_multi_ranges_size(mut self, max_multi_ranges_size: u64) -> Self {
+        self.max_multi_ranges_size = Some(max_multi_ranges_size);
+        self
+    }
+
+    pub(crate) fn disable_download(mut self, disable_download: bool) -> Self {
+        self.disable_download = disable_download;
+        self
+    }
+
+    pub(crate) fn decompress_content_encoding(mut self, decompress_content_encoding: bool) -> Self {
+        self.decompress_content_encoding = decompress_content_encoding;
+        self
+    }
+
+    pub(crate) fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub(crate) fn failover_backoff(mut self, failover_backoff: FailoverBackoff) -> Self {
+        self.failover_backoff = failover_backoff;
+        self
+    }
+
+    pub(crate) fn connection_failure_kinds(
+        mut self,
+        connection_failure_kinds: Vec<ConnectionFailureKind>,
+    ) -> Self {
+        self.connection_failure_kinds = connection_failure_kinds;
+        self
+    }
+
+    pub(crate) fn retryable_status_codes(mut self, retryable_status_codes: Vec<u16>) -> Self {
+        self.retryable_status_codes = Some(retryable_status_codes);
+        self
+    }
+
+    pub(crate) fn object_level_status_codes(mut self, object_level_status_codes: Vec<u16>) -> Self {
+        self.object_level_status_codes = object_level_status_codes;
+        self
+    }
+
+    pub(crate) fn key_rewrite_rules(mut self, key_rewrite_rules: Vec<KeyRewriteRule>) -> Self {
+        self.key_rewrite_rules = key_rewrite_rules;
+        self
+    }
+
+    pub(crate) fn sync_request_timeout(mut self, timeout: Duration) -> Self {
+        self.sync_request_timeout = Some(timeout);
+        self
+    }
+
+    pub(crate) fn sync_worker_threads(mut self, worker_threads: usize) -> Self {
+        self.sync_worker_threads = worker_threads;
+        self
+    }
+
+    pub(crate) fn path_prefix(mut self, path_prefix: String) -> Self {
+        let path_prefix = path_prefix.trim_matches('/');
+        self.url_options.path_prefix = if path_prefix.is_empty() {
+            None
+        } else {
+            Some(format!("/{}", path_prefix))
+        };
+        self
+    }
+
+    pub(crate) fn style(mut self, separator: char, style: String) -> Self {
+        self.url_options.style = Some(format!("{}{}", separator, style));
+        self
+    }
+
+    pub(crate) fn fop(mut self, fop: String) -> Self {
+        self.url_options.fop = Some(fop);
+        self
+    }
+
+    pub(crate) fn attname(mut self, attname: String) -> Self {
+        self.url_options.attname = Some(attname);
+        self
+    }
+
+    pub(crate) fn traffic_tag(mut self, traffic_tag: String) -> Self {
+        if is_valid_traffic_tag(&traffic_tag) {
+            self.url_options.traffic_tag = Some(traffic_tag);
+        } else {
+            warn!("Invalid traffic tag {:?} is ignored", traffic_tag);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_check_response_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-reqid", HeaderValue::from_static("abc"));
+        assert!(check_response_headers(&headers, None, true).is_ok());
+        assert!(check_response_headers(&headers, Some(14), true).is_ok());
+        let err = check_response_headers(&headers, Some(13), false).unwrap_err();
+        assert_eq!(
+            err.get_ref()
+                .and_then(|err| err.downcast_ref::<ProtocolError>())
+                .map(|err| err.kind()),
+            Some(ProtocolErrorKind::HeaderTooLarge)
+        );
+
+        headers.insert("x-bad", HeaderValue::from_bytes(b"caf\xe9").unwrap());
+        assert!(check_response_headers(&headers, None, false).is_ok());
+        let err = check_response_headers(&headers, None, true).unwrap_err();
+        assert_eq!(
+            err.get_ref()
+                .and_then(|err| err.downcast_ref::<ProtocolError>())
+                .map(|err| err.kind()),
+            Some(ProtocolErrorKind::InvalidHeader)
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.append(CONTENT_LENGTH, HeaderValue::from_static("5"));
+        headers.append(CONTENT_LENGTH, HeaderValue::from_static("5"));
+        assert!(check_response_headers(&headers, None, true).is_ok());
+        headers.append(CONTENT_LENGTH, HeaderValue::from_static("6"));
+        assert!(check_response_headers(&headers, None, true).is_err());
+    }
+
+    #[test]
+    fn test_check_multi_ranges() {
+        let ranges = [(0, 100), (200, 100), (400, 100)];
+        assert!(check_multi_ranges(&ranges, None, None).is_ok());
+        assert!(check_multi_ranges(&ranges, Some(3), Some(300)).is_ok());
+
+        let err = check_multi_ranges(&ranges, Some(2), None).unwrap_err();
+        assert_eq!(err.kind(), IoErrorKind::InvalidInput);
+        let err = err
+            .get_ref()
+            .and_then(|err| err.downcast_ref::<MultiRangesLimitError>())
+            .unwrap();
+        assert_eq!(err.ranges(), 3);
+        assert_eq!(err.total_size(), 300);
+        assert_eq!(err.max_ranges(), Some(2));
+        assert_eq!(err.max_total_size(), None);
+
+        let err = check_multi_ranges(&ranges, None, Some(299)).unwrap_err();
+        assert_eq!(
+            err.get_ref()
+                .and_then(|err| err.downcast_ref::<MultiRangesLimitError>())
+                .and_then(|err| err.max_total_size()),
+            Some(299)
+        );
+    }
+
+    #[test]
+    fn test_wait_for_object_delay() {
+        let poll_interval = Duration::from_millis(100);
+        for (polls, max_delay) in [(1, 100), (2, 200), (5, 1600), (10, 1600)] {
+            let delay = wait_for_object_delay(poll_interval, polls, &Default::default());
+            assert!(delay >= Duration::from_millis(max_delay / 2));
+            assert!(delay <= Duration::from_millis(max_delay));
+        }
+    }
+
+    #[test]
+    fn test_user_metadata_of() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Qn-Meta-Author", HeaderValue::from_static("qiniu"));
+        headers.insert(
+            "x-qn-meta-title",
+            HeaderValue::from_static("%E4%B8%83%E7%89%9B%20100%"),
+        );
+        headers.insert(
+            "x-qn-meta-city",
+            HeaderValue::from_bytes("上海".as_bytes()).unwrap(),
+        );
+        headers.insert("x-qn-meta-bad", HeaderValue::from_bytes(b"\xff").unwrap());
+        headers.insert("x-qn-meta-", HeaderValue::from_static("empty"));
+        headers.insert("x-reqid", HeaderValue::from_static("abc"));
+
+        let metadata = user_metadata_of(&headers);
+        assert_eq!(metadata.len(), 3);
+        assert_eq!(metadata.get("author").map(String::as_str), Some("qiniu"));
+        assert_eq!(metadata.get("title").map(String::as_str), Some("七牛 100%"));
+        assert_eq!(metadata.get("city").map(String::as_str), Some("上海"));
+    }
+
+    #[test]
+    fn test_object_metadata_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("\"etag\""));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("image/png"));
+        headers.insert(
+            LAST_MODIFIED,
+            HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT"),
+        );
+        headers.insert("X-Qn-Meta-Author", HeaderValue::from_static("qiniu"));
+        headers.append(CACHE_CONTROL, HeaderValue::from_static("public"));
+        headers.append(CACHE_CONTROL, HeaderValue::from_static("max-age=60"));
+        headers.insert(
+            EXPIRES,
+            HeaderValue::from_static("Sun, 06 Nov 1994 08:50:37 GMT"),
+        );
+        headers.insert(AGE, HeaderValue::from_static("10"));
+
+        let metadata = ObjectMetadata::from_headers(10, &headers);
+        assert_eq!(metadata.size(), 10);
+        assert_eq!(metadata.etag(), Some("\"etag\""));
+        assert_eq!(metadata.content_type(), Some("image/png"));
+        assert_eq!(
+            metadata.last_modified(),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(784111777))
+        );
+        assert_eq!(
+            metadata.user_metadata().get("author").map(String::as_str),
+            Some("qiniu")
+        );
+        assert_eq!(metadata.cache_control(), Some("public, max-age=60"));
+        assert_eq!(
+            metadata.expires(),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(784111837))
+        );
+        assert_eq!(metadata.age(), Some(Duration::from_secs(10)));
+        assert_eq!(metadata.content_disposition(), None);
+        assert_eq!(metadata.attachment_file_name(), None);
+
+        let metadata = ObjectMetadata::from_headers(0, &HeaderMap::new());
+        assert_eq!(metadata, ObjectMetadata::default());
+    }
+
+    #[test]
+    fn test_attachment_file_name() {
+        let file_name_of = |content_disposition: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                CONTENT_DISPOSITION,
+                HeaderValue::from_static(content_disposition),
+            );
+            ObjectMetadata::from_headers(0, &headers).attachment_file_name()
+        };
+        assert_eq!(
+            file_name_of("attachment; filename=\"a.jpg\"").as_deref(),
+            Some("a.jpg")
+        );
+        assert_eq!(
+            file_name_of("attachment; filename=a.jpg").as_deref(),
+            Some("a.jpg")
+        );
+        assert_eq!(
+            file_name_of("attachment; filename=\"a;\\\"b\\\".jpg\"; size=1").as_deref(),
+            Some("a;\"b\".jpg")
+        );
+        assert_eq!(
+            file_name_of(
+                "attachment; filename=\"a.jpg\"; filename*=UTF-8''%E5%9B%BE%E7%89%87%201.jpg"
+            )
+            .as_deref(),
+            Some("图片 1.jpg")
+        );
+        assert_eq!(
+            file_name_of("attachment; filename=\"../../etc/passwd\"").as_deref(),
+            Some("passwd")
+        );
+        assert_eq!(
+            file_name_of("attachment; filename=\"C:\\\\Windows\\\\a.exe\"").as_deref(),
+            Some("a.exe")
+        );
+        assert_eq!(file_name_of("attachment; filename=\"..\""), None);
+        assert_eq!(file_name_of("attachment; filename=\"dir/\""), None);
+        assert_eq!(file_name_of("attachment"), None);
+        assert_eq!(file_name_of("inline; name=\"a.jpg\""), None);
+    }
+
+    #[test]
+    fn test_download_url_options() {
+        let url = "http://io.example.com/dir/file.jpg".to_owned();
+        assert_eq!(DownloadUrlOptions::default().apply_to(url.to_owned()), url);
+
+        let options = DownloadUrlOptions {
+            path_prefix: None,
+            style: Some("-thumb".to_owned()),
+            fop: Some("imageView2/1/w/200/h/200".to_owned()),
+            attname: Some("图片 1.jpg".to_owned()),
+            traffic_tag: None,
+        };
+        assert_eq!(
+            options.apply_to(url.to_owned()),
+            "http://io.example.com/dir/file.jpg-thumb?imageView2/1/w/200/h/200&attname=%E5%9B%BE%E7%89%87+1.jpg"
+        );
+
+        let options = DownloadUrlOptions {
+            traffic_tag: Some("team-a_1.0".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.apply_to(url),
+            "http://io.example.com/dir/file.jpg?traffictag=team-a_1.0"
+        );
+        assert!(!is_valid_traffic_tag(""));
+        assert!(!is_valid_traffic_tag("team a"));
+        assert!(!is_valid_traffic_tag("标签"));
+        assert!(!is_valid_traffic_tag(&"a".repeat(MAX_TRAFFIC_TAG_LEN + 1)));
+
+        let options = DownloadUrlOptions {
+            path_prefix: Some("/internal".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.base_url("http://io.example.com/"),
+            "http://io.example.com/internal"
+        );
+        assert_eq!(
+            DownloadUrlOptions::default().base_url("http://io.example.com"),
+            "http://io.example.com"
+        );
+    }
+
+    #[test]
+    fn test_should_retry() {
+        let bad_gateway = unexpected_status_code_error(502, None);
+        let forbidden = unexpected_status_code_error(403, None);
+        let custom = unexpected_status_code_error(599, None);
+        let timed_out = IoError::new(IoErrorKind::TimedOut, "timed out");
+
+        assert!(should_retry(&bad_gateway, None, &[]));
+        assert!(!should_retry(&forbidden, None, &[]));
+        assert!(should_retry(&custom, None, &[]));
+        assert!(should_retry(&timed_out, None, &[]));
+
+        let retryable_status_codes = [502, 503, 504, 429];
+        assert!(should_retry(
+            &bad_gateway,
+            Some(&retryable_status_codes),
+            &[]
+        ));
+        assert!(!should_retry(&custom, Some(&retryable_status_codes), &[]));
+        assert!(should_retry(
+            &unexpected_status_code_error(429, None),
+            Some(&retryable_status_codes),
+            &[]
+        ));
+        assert!(should_retry(&timed_out, Some(&retryable_status_codes), &[]));
+        assert!(!should_retry(&forbidden, Some(&[]), &[]));
+        assert!(!should_retry(
+            &bad_gateway,
+            Some(&retryable_status_codes),
+            &[502]
+        ));
+    }
+
+    #[test]
+    fn test_object_level_error() {
+        for status_code in [404, 612] {
+            let err = unexpected_status_code_error(status_code, None);
+            assert_eq!(err.kind(), IoErrorKind::NotFound);
+            assert!(is_object_level_error(&err, &[]));
+            assert!(!should_retry(&err, None, &[]));
+            assert!(!should_retry(&err, Some(&[404, 612]), &[]));
+        }
+
+        let gone = unexpected_status_code_error(410, None);
+        assert_eq!(gone.kind(), IoErrorKind::InvalidData);
+        assert!(!is_object_level_error(&gone, &[]));
+        assert!(is_object_level_error(&gone, &[410]));
+        assert!(!is_object_level_error(
+            &IoError::new(IoErrorKind::NotFound, "no address"),
+            &[]
+        ));
+
+        let changed = object_changed_error("\"etag1\"".to_owned(), Some("\"etag2\"".to_owned()));
+        assert!(is_object_level_error(&changed, &[]));
+        assert!(!should_retry(&changed, Some(&[412]), &[]));
+        let changed = changed
+            .get_ref()
+            .and_then(|err| err.downcast_ref::<ObjectChangedError>())
+            .unwrap();
+        assert_eq!(changed.expected_etag(), "\"etag1\"");
+        assert_eq!(changed.actual_etag(), Some("\"etag2\""));
+    }
+
+    #[test]
+    fn test_range_in_body() {
+        const FOUR_GIB: u64 = 1 << 32;
+        assert_eq!(range_in_body(10, 5, 3), Some(5..8));
+        assert_eq!(range_in_body(10, 5, 10), Some(5..10));
+        assert_eq!(range_in_body(10, 10, 1), None);
+        assert_eq!(range_in_body(10, FOUR_GIB + 5, 3), None);
+        assert_eq!(range_in_body(10, 5, FOUR_GIB + 3), Some(5..10));
+        assert_eq!(range_in_body(10, u64::MAX, u64::MAX), None);
+        assert_eq!(capacity_hint(1024), 1024);
+    }
+
+    #[test]
+    fn test_redirected_host_of() {
+        let err = unexpected_status_code_error(502, Some("http://edge.example.com".to_owned()));
+        assert_eq!(
+            redirected_host_of(&err, "http://io.example.com"),
+            Some("http://edge.example.com")
+        );
+        assert_eq!(redirected_host_of(&err, "http://edge.example.com"), None);
+        assert_eq!(
+            redirected_host_of(
+                &unexpected_status_code_error(502, None),
+                "http://io.example.com"
+            ),
+            None
+        );
+    }
 }
@@ -0,0 +1,505 @@
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult};
+
+const MAX_BITS: usize = 15;
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTHS_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const FLAG_HCRC: u8 = 1 << 1;
+const FLAG_EXTRA: u8 = 1 << 2;
+const FLAG_NAME: u8 = 1 << 3;
+const FLAG_COMMENT: u8 = 1 << 4;
+
+/// 解压 gzip 格式的数据，支持多个成员拼接的数据
+///
+/// 解压后的数据超过 `max_size` 时返回错误，避免解压炸弹耗尽内存
+pub fn decompress(data: &[u8], max_size: Option<u64>) -> IoResult<Vec<u8>> {
+    let mut output = Vec::with_capacity(data.len().saturating_mul(2));
+    let mut reader = BitReader::new(data);
+    loop {
+        let member_start = output.len();
+        read_header(&mut reader)?;
+        inflate(&mut reader, &mut output, max_size)?;
+        reader.align_to_byte();
+        let crc32 = reader.read_u32_le()?;
+        let size = reader.read_u32_le()?;
+        let member = &output[member_start..];
+        if crc32fast::hash(member) != crc32 {
+            return Err(invalid_data("gzip crc32 mismatch"));
+        } else if member.len() as u32 != size {
+            return Err(invalid_data("gzip size mismatch"));
+        }
+        if reader.is_empty() {
+            return Ok(output);
+        }
+    }
+}
+
+fn read_header(reader: &mut BitReader) -> IoResult<()> {
+    let header = reader.read_bytes(10)?;
+    if header[..3] != [0x1f, 0x8b, 8] {
+        return Err(invalid_data("invalid gzip header"));
+    }
+    let flags = header[3];
+    if flags & FLAG_EXTRA != 0 {
+        let len = reader.read_u16_le()?;
+        reader.read_bytes(len.into())?;
+    }
+    if flags & FLAG_NAME != 0 {
+        reader.skip_zero_terminated()?;
+    }
+    if flags & FLAG_COMMENT != 0 {
+        reader.skip_zero_terminated()?;
+    }
+    if flags & FLAG_HCRC != 0 {
+        reader.read_u16_le()?;
+    }
+    Ok(())
+}
+
+fn inflate(reader: &mut BitReader, output: &mut Vec<u8>, max_size: Option<u64>) -> IoResult<()> {
+    loop {
+        let is_last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align_to_byte();
+                let len = reader.read_u16_le()?;
+                if reader.read_u16_le()? != !len {
+                    return Err(invalid_data("invalid stored block length"));
+                }
+                output.extend_from_slice(reader.read_bytes(len.into())?);
+            }
+            1 => {
+                let mut lengths = [0u8; 288 + 30];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..288].fill(8);
+                lengths[288..].fill(5);
+                let literals = Huffman::new(&lengths[..288])?;
+                let distances = Huffman::new(&lengths[288..])?;
+                inflate_block(reader, output, &literals, &distances, max_size)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_tables(reader)?;
+                inflate_block(reader, output, &literals, &distances, max_size)?;
+            }
+            _ => return Err(invalid_data("invalid deflate block type")),
+        }
+        check_size(output, max_size)?;
+        if is_last {
+            return Ok(());
+        }
+    }
+}
+
+fn read_dynamic_tables(reader: &mut BitReader) -> IoResult<(Huffman, Huffman)> {
+    let literals_count = reader.bits(5)? as usize + 257;
+    let distances_count = reader.bits(5)? as usize + 1;
+    let code_lengths_count = reader.bits(4)? as usize + 4;
+    if literals_count > 286 || distances_count > 30 {
+        return Err(invalid_data("too many deflate codes"));
+    }
+    let mut code_lengths = [0u8; 19];
+    for &index in CODE_LENGTHS_ORDER.iter().take(code_lengths_count) {
+        code_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; literals_count + distances_count];
+    let mut index = 0;
+    while index < lengths.len() {
+        let (value, repeat) = match code_lengths.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 if index > 0 => (lengths[index - 1], reader.bits(2)? as usize + 3),
+            17 => (0, reader.bits(3)? as usize + 3),
+            18 => (0, reader.bits(7)? as usize + 11),
+            _ => return Err(invalid_data("invalid deflate code lengths")),
+        };
+        if index + repeat > lengths.len() {
+            return Err(invalid_data("invalid deflate code lengths"));
+        }
+        lengths[index..index + repeat].fill(value);
+        index += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(invalid_data("missing deflate end of block code"));
+    }
+    Ok((
+        Huffman::new(&lengths[..literals_count])?,
+        Huffman::new(&lengths[literals_count..])?,
+    ))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+    max_size: Option<u64>,
+) -> IoResult<()> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        if symbol < 256 {
+            output.push(symbol as u8);
+            continue;
+        } else if symbol == 256 {
+            return Ok(());
+        }
+        let symbol = symbol - 257;
+        if symbol >= LENGTH_BASES.len() {
+            return Err(invalid_data("invalid deflate length code"));
+        }
+        let len =
+            LENGTH_BASES[symbol] as usize + reader.bits(LENGTH_EXTRA_BITS[symbol].into())? as usize;
+        let symbol = distances.decode(reader)? as usize;
+        if symbol >= DISTANCE_BASES.len() {
+            return Err(invalid_data("invalid deflate distance code"));
+        }
+        let distance = DISTANCE_BASES[symbol] as usize
+            + reader.bits(DISTANCE_EXTRA_BITS[symbol].into())? as usize;
+        if distance > output.len() {
+            return Err(invalid_data("deflate distance too far back"));
+        }
+        let start = output.len() - distance;
+        for offset in 0..len {
+            output.push(output[start + offset]);
+        }
+        check_size(output, max_size)?;
+    }
+}
+
+fn check_size(output: &[u8], max_size: Option<u64>) -> IoResult<()> {
+    if max_size.is_some_and(|max_size| output.len() as u64 > max_size) {
+        Err(invalid_data("decompressed data exceeds the size limit"))
+    } else {
+        Ok(())
+    }
+}
+
+fn invalid_data(message: &str) -> IoError {
+    IoError::new(IoErrorKind::InvalidData, message)
+}
+
+/// 范式 Huffman 编码表，按编码长度记录符号
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> IoResult<Self> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for len in 1..=MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                let offset = &mut offsets[usize::from(len)];
+                symbols[usize::from(*offset)] = symbol as u16;
+                *offset += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> IoResult<u16> {
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid_data("invalid deflate huffman code"))
+    }
+}
+
+/// 按照 deflate 的位序从低位到高位读取数据
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u64,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            bit_buf: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bits(&mut self, count: u32) -> IoResult<u32> {
+        while self.bit_count < count {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| IoError::from(IoErrorKind::UnexpectedEof))?;
+            self.bit_buf |= u64::from(byte) << self.bit_count;
+            self.bit_count += 8;
+            self.pos += 1;
+        }
+        let value = (self.bit_buf & ((1 << count) - 1)) as u32;
+        self.bit_buf >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    /// 丢弃当前字节中尚未读取的位，读取时只会多取不足一个字节的位
+    fn align_to_byte(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+
+    fn read_bytes(&mut self, len: usize) -> IoResult<&'a [u8]> {
+        let data = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| IoError::from(IoErrorKind::UnexpectedEof))?;
+        self.pos += len;
+        Ok(data)
+    }
+
+    fn read_u16_le(&mut self) -> IoResult<u16> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32_le(&mut self) -> IoResult<u32> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn skip_zero_terminated(&mut self) -> IoResult<()> {
+        while self.read_bytes(1)?[0] != 0 {}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXED: &[u8] = &[
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9, 0xc9,
+        0x57, 0xc8, 0x40, 0x27, 0xb9, 0x00, 0x00, 0x88, 0x59, 0x0b, 0x18, 0x00, 0x00, 0x00,
+    ];
+
+    const DYNAMIC: &[u8] = &[
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcd, 0x8a, 0x41, 0x0a, 0x80,
+        0x30, 0x0c, 0x04, 0xbf, 0xb2, 0xff, 0xf0, 0x35, 0xc1, 0xae, 0xb4, 0x50, 0xd3, 0xd2, 0x04,
+        0xb4, 0xbf, 0x37, 0xf5, 0xe2, 0x17, 0x84, 0x3d, 0xcc, 0x0e, 0xe3, 0x99, 0x18, 0x52, 0x14,
+        0x31, 0xeb, 0x0b, 0xcc, 0x65, 0x1a, 0xce, 0xc0, 0x3a, 0x97, 0xf5, 0x28, 0x7a, 0x8d, 0xbb,
+        0xbd, 0xf8, 0x93, 0x98, 0x9a, 0xd0, 0x8e, 0xcf, 0xc2, 0x79, 0x3b, 0x8a, 0x61, 0x50, 0xf6,
+        0xcc, 0x84, 0xcc, 0x41, 0x68, 0xbb, 0x20, 0x51, 0x46, 0xa6, 0x0f, 0xc0, 0xe9, 0xbc, 0x92,
+        0xea, 0x00, 0x00, 0x00,
+    ];
+
+    const STORED: &[u8] = &[
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x03, 0x01, 0x11, 0x00, 0xee, 0xff,
+        0x73, 0x74, 0x6f, 0x72, 0x65, 0x64, 0x20, 0x62, 0x6c, 0x6f, 0x63, 0x6b, 0x20, 0x64, 0x61,
+        0x74, 0x61, 0xdf, 0x65, 0xa0, 0xa5, 0x11, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_decompress() -> anyhow::Result<()> {
+        assert_eq!(decompress(FIXED, None)?, b"hello hello hello hello\n");
+
+        let mut expected = b"the rain in spain stays mainly in the plain; ".repeat(4);
+        expected.extend_from_slice(b"the end of the plain text is reached here now and then");
+        assert_eq!(decompress(DYNAMIC, None)?, expected);
+        assert_eq!(decompress(STORED, None)?, b"stored block data");
+
+        let mut concatenated = FIXED.to_vec();
+        concatenated.extend_from_slice(FIXED);
+        assert_eq!(
+            decompress(&concatenated, None)?,
+            b"hello hello hello hello\n".repeat(2)
+        );
+
+        assert_eq!(
+            decompress(FIXED, Some(10)).unwrap_err().kind(),
+            IoErrorKind::InvalidData
+        );
+        let mut corrupted = FIXED.to_vec();
+        corrupted[FIXED.len() - 8] ^= 0xff;
+        assert!(decompress(&corrupted, None).is_err());
+        assert!(decompress(&FIXED[..20], None).is_err());
+        Ok(())
+    }
+
+    /// 按 LSB 优先的顺序写入 deflate 数据
+    struct BitWriter {
+        bytes: Vec<u8>,
+        /// 最后一个字节中已经写入的位数，为 0 时写入下一位前需要追加新的字节
+        bit_pos: u32,
+    }
+
+    impl BitWriter {
+        fn bits(&mut self, value: u32, count: u32) {
+            for i in 0..count {
+                if self.bit_pos == 0 {
+                    self.bytes.push(0);
+                }
+                *self.bytes.last_mut().unwrap() |= (((value >> i) & 1) as u8) << self.bit_pos;
+                self.bit_pos = (self.bit_pos + 1) % 8;
+            }
+        }
+
+        /// Huffman 编码按 MSB 优先的顺序写入
+        fn huffman(&mut self, code: u32, len: u32) {
+            for i in (0..len).rev() {
+                self.bits(code >> i, 1);
+            }
+        }
+
+        fn align_to_byte(&mut self) {
+            self.bit_pos = 0;
+        }
+    }
+
+    /// 构造一个 gzip 成员：以存储块写入 `window`，再以固定 Huffman 块写入一组 (长度, 距离) 的回溯引用
+    fn gzip_with_back_references(window: &[u8], references: &[(u32, u32)]) -> Vec<u8> {
+        let mut writer = BitWriter {
+            bytes: vec![0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff],
+            bit_pos: 0,
+        };
+        if !window.is_empty() {
+            writer.bits(0, 1);
+            writer.bits(0, 2);
+            writer.align_to_byte();
+            let len = window.len() as u16;
+            writer.bytes.extend_from_slice(&len.to_le_bytes());
+            writer.bytes.extend_from_slice(&(!len).to_le_bytes());
+            writer.bytes.extend_from_slice(window);
+            writer.align_to_byte();
+        }
+        writer.bits(1, 1);
+        writer.bits(1, 2);
+        for &(len, distance) in references {
+            let symbol = LENGTH_BASES
+                .iter()
+                .rposition(|&base| u32::from(base) <= len)
+                .unwrap();
+            // 长度编码 257..=279 为 7 位，280..=287 为 8 位
+            if symbol < 23 {
+                writer.huffman(symbol as u32 + 1, 7);
+            } else {
+                writer.huffman(0b1100_0000 + symbol as u32 - 23, 8);
+            }
+            writer.bits(
+                len - u32::from(LENGTH_BASES[symbol]),
+                LENGTH_EXTRA_BITS[symbol].into(),
+            );
+            let symbol = DISTANCE_BASES
+                .iter()
+                .rposition(|&base| u32::from(base) <= distance)
+                .unwrap();
+            writer.huffman(symbol as u32, 5);
+            writer.bits(
+                distance - u32::from(DISTANCE_BASES[symbol]),
+                DISTANCE_EXTRA_BITS[symbol].into(),
+            );
+        }
+        writer.huffman(0, 7);
+        writer.align_to_byte();
+
+        let mut expected = window.to_vec();
+        for &(len, distance) in references {
+            let start = expected.len().saturating_sub(distance as usize);
+            for offset in 0..len as usize {
+                expected.push(expected.get(start + offset).copied().unwrap_or_default());
+            }
+        }
+        writer
+            .bytes
+            .extend_from_slice(&crc32fast::hash(&expected).to_le_bytes());
+        writer
+            .bytes
+            .extend_from_slice(&(expected.len() as u32).to_le_bytes());
+        writer.bytes
+    }
+
+    #[test]
+    fn test_decompress_with_back_references() -> anyhow::Result<()> {
+        let mut seed = 0x2545_f491u32;
+        let window: Vec<u8> = (0..32768)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) as u8
+            })
+            .collect();
+
+        // 回溯整个 32 KiB 窗口，复制长度超过距离的重叠引用，以及各种长度和距离的额外位
+        let references = [
+            (258, 32768),
+            (258, 1),
+            (3, 4),
+            (10, 3),
+            (130, 24577),
+            (227, 300),
+        ];
+        let data = gzip_with_back_references(&window, &references);
+        let output = decompress(&data, None)?;
+        assert_eq!(output.len(), 32768 + 258 * 2 + 3 + 10 + 130 + 227);
+        assert_eq!(&output[..32768], window.as_slice());
+        assert_eq!(&output[32768..32768 + 258], &window[..258]);
+        assert!(output[32768 + 258..32768 + 258 * 2]
+            .iter()
+            .all(|&byte| byte == window[257]));
+        assert_eq!(
+            decompress(&data, Some(output.len() as u64 - 1))
+                .unwrap_err()
+                .kind(),
+            IoErrorKind::InvalidData
+        );
+
+        // 回溯距离超过已经解压的数据
+        let data = gzip_with_back_references(&window[..100], &[(3, 101)]);
+        assert_eq!(
+            decompress(&data, None).unwrap_err().kind(),
+            IoErrorKind::InvalidData
+        );
+        let data = gzip_with_back_references(&[], &[(3, 1)]);
+        assert_eq!(
+            decompress(&data, None).unwrap_err().kind(),
+            IoErrorKind::InvalidData
+        );
+        Ok(())
+    }
+}
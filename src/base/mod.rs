@@ -1,5 +1,10 @@
 pub(crate) mod base64;
+pub(crate) mod checksum;
 pub(crate) mod credential;
+pub(crate) mod dot_sampler;
 pub(crate) mod download;
+pub(crate) mod gzip;
+pub(crate) mod region_hosts;
+pub(crate) mod rng;
 pub(crate) mod upload_policy;
 pub(crate) mod upload_token;
@@ -0,0 +1,135 @@
+/// 按区域分组的域名列表
+///
+/// 由 UC 查询结果得到，保留了区域和机房等拓扑信息。未配置 UC 服务器时只有一个分组，包含当前所有的 IO 域名
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegionHosts {
+    region: Option<String>,
+    idc: Option<String>,
+    io_urls: Vec<String>,
+    uc_urls: Vec<String>,
+}
+
+impl RegionHosts {
+    pub(crate) fn new(
+        region: Option<String>,
+        idc: Option<String>,
+        io_urls: Vec<String>,
+        uc_urls: Vec<String>,
+    ) -> Self {
+        Self {
+            region,
+            idc,
+            io_urls,
+            uc_urls,
+        }
+    }
+
+    /// 获取区域名称，例如 `z0`
+    #[inline]
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    /// 获取机房标签
+    #[inline]
+    pub fn idc(&self) -> Option<&str> {
+        self.idc.as_deref()
+    }
+
+    /// 获取该区域的 IO 域名
+    #[inline]
+    pub fn io_urls(&self) -> &[String] {
+        &self.io_urls
+    }
+
+    /// 获取该区域的 UC 域名
+    #[inline]
+    pub fn uc_urls(&self) -> &[String] {
+        &self.uc_urls
+    }
+
+    /// 区域名称或机房标签是否与指定标签相同
+    #[inline]
+    pub fn matches(&self, label: &str) -> bool {
+        self.region() == Some(label) || self.idc() == Some(label)
+    }
+}
+
+/// 从分组后的域名列表中选出 IO 域名
+///
+/// 指定了 `preferred_idc` 且存在匹配的分组时，合并所有匹配分组的 IO 域名，否则使用第一个分组的 IO 域名
+pub(crate) fn select_io_urls(groups: &[RegionHosts], preferred_idc: Option<&str>) -> Vec<String> {
+    if let Some(preferred_idc) = preferred_idc {
+        let mut io_urls = Vec::new();
+        for io_url in groups
+            .iter()
+            .filter(|group| group.matches(preferred_idc))
+            .flat_map(|group| group.io_urls())
+        {
+            if !io_urls.contains(io_url) {
+                io_urls.push(io_url.to_owned());
+            }
+        }
+        if !io_urls.is_empty() {
+            return io_urls;
+        }
+    }
+    groups
+        .first()
+        .map(|group| group.io_urls().to_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_io_urls() {
+        let groups = vec![
+            RegionHosts::new(
+                Some("z0".to_owned()),
+                Some("idc-a".to_owned()),
+                vec!["http://io1".to_owned(), "http://io2".to_owned()],
+                vec!["http://uc1".to_owned()],
+            ),
+            RegionHosts::new(
+                Some("z0".to_owned()),
+                Some("idc-b".to_owned()),
+                vec!["http://io2".to_owned(), "http://io3".to_owned()],
+                vec![],
+            ),
+            RegionHosts::new(
+                Some("z1".to_owned()),
+                None,
+                vec!["http://io4".to_owned()],
+                vec![],
+            ),
+        ];
+        assert_eq!(
+            select_io_urls(&groups, None),
+            vec!["http://io1".to_owned(), "http://io2".to_owned()]
+        );
+        assert_eq!(
+            select_io_urls(&groups, Some("idc-b")),
+            vec!["http://io2".to_owned(), "http://io3".to_owned()]
+        );
+        assert_eq!(
+            select_io_urls(&groups, Some("z0")),
+            vec![
+                "http://io1".to_owned(),
+                "http://io2".to_owned(),
+                "http://io3".to_owned()
+            ]
+        );
+        assert_eq!(
+            select_io_urls(&groups, Some("z1")),
+            vec!["http://io4".to_owned()]
+        );
+        assert_eq!(
+            select_io_urls(&groups, Some("idc-c")),
+            vec!["http://io1".to_owned(), "http://io2".to_owned()]
+        );
+        assert!(select_io_urls(&[], Some("idc-a")).is_empty());
+    }
+}
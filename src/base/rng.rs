@@ -0,0 +1,96 @@
+use rand::{rngs::StdRng, seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use std::{
+    fmt::{Debug, Formatter, Result as FormatResult},
+    ops::RangeInclusive,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// 可注入的随机数生成器，用于域名乱序、重试抖动等随机行为
+///
+/// 指定种子时使用由克隆后的实例共享的确定性随机数生成器，使相同的调用序列产生相同的结果，
+/// 未指定种子时使用线程本地的随机数生成器
+#[derive(Clone, Default)]
+pub(crate) struct SharedRng(Option<Arc<Mutex<StdRng>>>);
+
+impl SharedRng {
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        Self(seed.map(|seed| Arc::new(Mutex::new(StdRng::seed_from_u64(seed)))))
+    }
+
+    /// 随机打乱切片中的元素
+    pub(crate) fn shuffle<T>(&self, slice: &mut [T]) {
+        match &self.0 {
+            Some(rng) => slice.shuffle(&mut *rng.lock().unwrap()),
+            None => slice.shuffle(&mut thread_rng()),
+        }
+    }
+
+    /// 以指定概率返回 true，概率不在 0 到 1 之间时取最接近的边界值
+    pub(crate) fn gen_bool(&self, probability: f64) -> bool {
+        let probability = if probability.is_nan() {
+            0.0
+        } else {
+            probability.clamp(0.0, 1.0)
+        };
+        match &self.0 {
+            Some(rng) => rng.lock().unwrap().gen_bool(probability),
+            None => thread_rng().gen_bool(probability),
+        }
+    }
+
+    /// 在指定区间内随机选取时长
+    pub(crate) fn gen_duration(&self, range: RangeInclusive<Duration>) -> Duration {
+        match &self.0 {
+            Some(rng) => rng.lock().unwrap().gen_range(range),
+            None => thread_rng().gen_range(range),
+        }
+    }
+}
+
+impl Debug for SharedRng {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        f.debug_tuple("SharedRng")
+            .field(&if self.0.is_some() {
+                "seeded"
+            } else {
+                "entropy"
+            })
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_rng() {
+        let shuffled = |rng: &SharedRng| {
+            let mut items: Vec<_> = (0..16).collect();
+            rng.shuffle(&mut items);
+            items
+        };
+        let delays = |rng: &SharedRng| {
+            (0..8)
+                .map(|_| rng.gen_duration(Duration::from_millis(0)..=Duration::from_secs(1)))
+                .collect::<Vec<_>>()
+        };
+
+        let (rng1, rng2) = (SharedRng::new(Some(42)), SharedRng::new(Some(42)));
+        assert_eq!(shuffled(&rng1), shuffled(&rng2));
+        assert_eq!(delays(&rng1), delays(&rng2));
+
+        let cloned = rng1.to_owned();
+        assert_eq!(shuffled(&cloned), shuffled(&rng2));
+        assert_ne!(shuffled(&rng1), shuffled(&cloned));
+
+        assert!(rng1.gen_bool(1.5));
+        assert!(!rng1.gen_bool(-1.0));
+        assert!(!rng1.gen_bool(f64::NAN));
+
+        let mut items = shuffled(&SharedRng::default());
+        items.sort_unstable();
+        assert_eq!(items, (0..16).collect::<Vec<_>>());
+    }
+}
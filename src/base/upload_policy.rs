@@ -1,23 +1,92 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use serde_json::{json, Value as JSONValue};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Result as JSONResult, Value as JSONValue};
 
-pub(crate) struct UploadPolicy {
+/// 上传策略
+///
+/// 上传凭证中携带的 JSON 对象，`scope` 为 `<bucket>` 或 `<bucket>:<key>`，
+/// `deadline` 为凭证有效截止时间的 UNIX 时间戳，其余字段原样保留
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UploadPolicy {
     value: JSONValue,
 }
 
 impl UploadPolicy {
-    pub(crate) fn new_for_bucket(bucket: String, deadline: SystemTime) -> Self {
-        let timestamp = deadline
-            .duration_since(UNIX_EPOCH)
-            .map(|t| t.as_secs())
-            .unwrap_or_else(|_| u64::MAX);
+    /// 创建允许上传到整个存储空间的上传策略
+    pub fn new_for_bucket(bucket: String, deadline: SystemTime) -> Self {
         Self {
-            value: json!({"scope": bucket, "deadline": timestamp }),
+            value: json!({"scope": bucket, "deadline": to_timestamp(deadline) }),
         }
     }
 
-    pub(crate) fn to_json(&self) -> String {
+    /// 创建仅允许上传指定对象的上传策略
+    pub fn new_for_object(bucket: &str, key: &str, deadline: SystemTime) -> Self {
+        let scope = format!("{}:{}", bucket, key);
+        Self {
+            value: json!({"scope": scope, "deadline": to_timestamp(deadline) }),
+        }
+    }
+
+    /// 从 JSON 字符串解析上传策略
+    pub fn from_json(json: &str) -> JSONResult<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// 将上传策略序列化为 JSON 字符串
+    pub fn to_json(&self) -> String {
         serde_json::to_string(&self.value).unwrap()
     }
+
+    /// 获取上传策略的 JSON 对象
+    #[inline]
+    pub fn as_json_value(&self) -> &JSONValue {
+        &self.value
+    }
+
+    /// 获取上传策略中的指定字段
+    #[inline]
+    pub fn get(&self, field: &str) -> Option<&JSONValue> {
+        self.value.get(field)
+    }
+
+    /// 获取 `scope` 字段
+    #[inline]
+    pub fn scope(&self) -> Option<&str> {
+        self.get("scope").and_then(|scope| scope.as_str())
+    }
+
+    /// 获取 `scope` 中的存储空间
+    pub fn bucket(&self) -> Option<&str> {
+        self.scope()
+            .map(|scope| scope.split_once(':').map_or(scope, |(bucket, _)| bucket))
+    }
+
+    /// 获取 `scope` 中的对象名称，仅允许上传到整个存储空间时返回 `None`
+    pub fn key(&self) -> Option<&str> {
+        self.scope()
+            .and_then(|scope| scope.split_once(':'))
+            .map(|(_, key)| key)
+    }
+
+    /// 获取凭证有效截止时间
+    pub fn deadline(&self) -> Option<SystemTime> {
+        self.get("deadline")
+            .and_then(|deadline| deadline.as_u64())
+            .and_then(|deadline| UNIX_EPOCH.checked_add(Duration::from_secs(deadline)))
+    }
+
+    /// 凭证是否已经过期，没有 `deadline` 字段的上传策略视为已过期
+    pub fn is_expired(&self) -> bool {
+        self.deadline()
+            .map_or(true, |deadline| deadline <= SystemTime::now())
+    }
+}
+
+fn to_timestamp(deadline: SystemTime) -> u64 {
+    deadline
+        .duration_since(UNIX_EPOCH)
+        .map(|t| t.as_secs())
+        .unwrap_or_else(|_| u64::MAX)
 }
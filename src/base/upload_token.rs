@@ -1,6 +1,166 @@
-use super::{credential::Credential, upload_policy::UploadPolicy};
+use super::{base64, credential::Credential, upload_policy::UploadPolicy};
+use thiserror::Error;
 
-pub(crate) fn sign_upload_token(credential: &Credential, policy: &UploadPolicy) -> String {
+/// 使用七牛凭证为上传策略签发上传凭证
+pub fn sign_upload_token(credential: &Credential, policy: &UploadPolicy) -> String {
     let serialized_policy = policy.to_json();
     credential.sign_with_data(serialized_policy.as_bytes())
 }
+
+/// 解析上传凭证
+///
+/// 仅解析凭证的格式，需要调用 [`ParsedUploadToken::verify`] 校验签名和有效期
+pub fn parse_upload_token(token: &str) -> Result<ParsedUploadToken, UploadTokenError> {
+    let mut parts = token.splitn(3, ':');
+    let (access_key, signature, encoded_policy) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(access_key), Some(signature), Some(encoded_policy))
+            if !access_key.is_empty() && !signature.is_empty() =>
+        {
+            (access_key, signature, encoded_policy)
+        }
+        _ => return Err(UploadTokenError::InvalidFormat),
+    };
+    let policy = base64::decode_urlsafe(encoded_policy)?;
+    let policy = serde_json::from_slice(&policy)?;
+    Ok(ParsedUploadToken {
+        access_key: access_key.to_owned(),
+        signature: signature.to_owned(),
+        encoded_policy: encoded_policy.to_owned(),
+        policy,
+    })
+}
+
+/// 解析后的上传凭证
+#[derive(Clone, Debug)]
+pub struct ParsedUploadToken {
+    access_key: String,
+    signature: String,
+    encoded_policy: String,
+    policy: UploadPolicy,
+}
+
+impl ParsedUploadToken {
+    /// 获取签发凭证所用的 Access Key
+    #[inline]
+    pub fn access_key(&self) -> &str {
+        &self.access_key
+    }
+
+    /// 获取上传策略
+    #[inline]
+    pub fn policy(&self) -> &UploadPolicy {
+        &self.policy
+    }
+
+    /// 获取上传策略的所有权
+    #[inline]
+    pub fn into_policy(self) -> UploadPolicy {
+        self.policy
+    }
+
+    /// 使用七牛凭证校验上传凭证的签名和有效期
+    pub fn verify(&self, credential: &Credential) -> Result<(), UploadTokenError> {
+        if credential.access_key() != self.access_key {
+            return Err(UploadTokenError::AccessKeyMismatch);
+        }
+        let expected = credential.sign(self.encoded_policy.as_bytes());
+        let actual = format!("{}:{}", self.access_key, self.signature);
+        if !constant_time_eq(expected.as_bytes(), actual.as_bytes()) {
+            return Err(UploadTokenError::SignatureMismatch);
+        }
+        if self.policy.is_expired() {
+            return Err(UploadTokenError::Expired);
+        }
+        Ok(())
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 上传凭证解析或校验错误
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum UploadTokenError {
+    /// 上传凭证不是 `<AccessKey>:<Signature>:<EncodedPolicy>` 格式
+    #[error("Upload token is not in `<AccessKey>:<Signature>:<EncodedPolicy>` format")]
+    InvalidFormat,
+
+    /// 上传策略不是合法的 URL 安全的 Base64 编码
+    #[error("Upload policy is not valid urlsafe base64: {0}")]
+    InvalidBase64(#[from] ::base64::DecodeError),
+
+    /// 上传策略不是合法的 JSON
+    #[error("Upload policy is not valid json: {0}")]
+    InvalidPolicy(#[from] serde_json::Error),
+
+    /// 上传凭证的 Access Key 与校验所用凭证不一致
+    #[error("Upload token is signed by another access key")]
+    AccessKeyMismatch,
+
+    /// 上传凭证的签名不正确
+    #[error("Upload token signature mismatch")]
+    SignatureMismatch,
+
+    /// 上传凭证已经过期
+    #[error("Upload token is expired")]
+    Expired,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_parse_upload_token() -> anyhow::Result<()> {
+        let credential = Credential::new("abcdefghklmnopq", "1234567890");
+        let policy = UploadPolicy::new_for_object(
+            "test-bucket",
+            "test:key",
+            SystemTime::now() + Duration::from_secs(3600),
+        );
+        let token = sign_upload_token(&credential, &policy);
+
+        let parsed = parse_upload_token(&token)?;
+        assert_eq!(parsed.access_key(), "abcdefghklmnopq");
+        assert_eq!(parsed.policy(), &policy);
+        assert_eq!(parsed.policy().bucket(), Some("test-bucket"));
+        assert_eq!(parsed.policy().key(), Some("test:key"));
+        parsed.verify(&credential)?;
+
+        assert!(matches!(
+            parsed.verify(&Credential::new("abcdefghklmnopq", "0987654321")),
+            Err(UploadTokenError::SignatureMismatch)
+        ));
+        assert!(matches!(
+            parsed.verify(&Credential::new("another", "1234567890")),
+            Err(UploadTokenError::AccessKeyMismatch)
+        ));
+
+        let expired = sign_upload_token(
+            &credential,
+            &UploadPolicy::new_for_bucket(
+                "test-bucket".to_owned(),
+                SystemTime::now() - Duration::from_secs(1),
+            ),
+        );
+        let parsed = parse_upload_token(&expired)?;
+        assert_eq!(parsed.policy().key(), None);
+        assert!(matches!(
+            parsed.verify(&credential),
+            Err(UploadTokenError::Expired)
+        ));
+
+        assert!(matches!(
+            parse_upload_token("invalid"),
+            Err(UploadTokenError::InvalidFormat)
+        ));
+        assert!(matches!(
+            parse_upload_token("ak:sign:!!!"),
+            Err(UploadTokenError::InvalidBase64(_))
+        ));
+        Ok(())
+    }
+}
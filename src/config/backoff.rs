@@ -0,0 +1,157 @@
+use super::super::base::rng::SharedRng;
+use serde::{Deserialize, Serialize};
+use std::{convert::TryInto, time::Duration};
+
+/// 切换域名重试前的等待策略
+///
+/// 对应配置文件中的 `[failover_backoff]` 表，通过 `kind` 字段选择策略，例如
+///
+/// ```toml
+/// [failover_backoff]
+/// kind = "exponential"
+/// base_ms = 100
+/// max_ms = 2000
+/// jitter = true
+/// ```
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum FailoverBackoff {
+    /// 不等待，立即切换域名重试
+    #[default]
+    None,
+    /// 每次切换域名前等待固定的时长
+    Fixed {
+        /// 等待时长，单位为毫秒
+        delay_ms: u64,
+        /// 是否在 0 到等待时长之间随机选取实际的等待时长
+        #[serde(default)]
+        jitter: bool,
+    },
+    /// 每次切换域名前等待的时长按指数增长
+    Exponential {
+        /// 第一次切换域名前的等待时长，单位为毫秒
+        base_ms: u64,
+        /// 等待时长的上限，单位为毫秒
+        max_ms: u64,
+        /// 是否在 0 到等待时长之间随机选取实际的等待时长
+        #[serde(default)]
+        jitter: bool,
+    },
+}
+
+impl FailoverBackoff {
+    /// 创建固定等待时长的策略
+    #[inline]
+    pub fn fixed(delay: Duration) -> Self {
+        Self::Fixed {
+            delay_ms: to_millis(delay),
+            jitter: false,
+        }
+    }
+
+    /// 创建等待时长按指数增长的策略
+    #[inline]
+    pub fn exponential(base: Duration, max: Duration) -> Self {
+        Self::Exponential {
+            base_ms: to_millis(base),
+            max_ms: to_millis(max),
+            jitter: false,
+        }
+    }
+
+    /// 在 0 到等待时长之间随机选取实际的等待时长
+    #[inline]
+    pub fn with_jitter(mut self) -> Self {
+        match &mut self {
+            Self::None => {}
+            Self::Fixed { jitter, .. } | Self::Exponential { jitter, .. } => *jitter = true,
+        }
+        self
+    }
+
+    /// 获取第 `failures` 次切换域名前的最大等待时长，`failures` 从 1 开始计数
+    pub fn max_delay(&self, failures: usize) -> Duration {
+        let delay_ms = match *self {
+            Self::None => 0,
+            Self::Fixed { delay_ms, .. } => delay_ms,
+            Self::Exponential {
+                base_ms, max_ms, ..
+            } => {
+                let power: u32 = failures.saturating_sub(1).try_into().unwrap_or(u32::MAX);
+                base_ms
+                    .saturating_mul(2u64.checked_pow(power).unwrap_or(u64::MAX))
+                    .min(max_ms)
+            }
+        };
+        Duration::from_millis(delay_ms)
+    }
+
+    /// 获取第 `failures` 次切换域名前实际的等待时长，`failures` 从 1 开始计数
+    pub fn delay(&self, failures: usize) -> Duration {
+        self.delay_with_rng(failures, &Default::default())
+    }
+
+    /// 使用指定的随机数生成器计算第 `failures` 次切换域名前实际的等待时长
+    pub(crate) fn delay_with_rng(&self, failures: usize, rng: &SharedRng) -> Duration {
+        let max_delay = self.max_delay(failures);
+        match *self {
+            Self::Fixed { jitter: true, .. } | Self::Exponential { jitter: true, .. }
+                if max_delay > Duration::from_millis(0) =>
+            {
+                rng.gen_duration(Duration::from_millis(0)..=max_delay)
+            }
+            _ => max_delay,
+        }
+    }
+}
+
+fn to_millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failover_backoff() -> anyhow::Result<()> {
+        assert_eq!(
+            FailoverBackoff::default().delay(3),
+            Duration::from_millis(0)
+        );
+
+        let fixed = FailoverBackoff::fixed(Duration::from_millis(100));
+        assert_eq!(fixed.delay(1), Duration::from_millis(100));
+        assert_eq!(fixed.delay(5), Duration::from_millis(100));
+
+        let exponential =
+            FailoverBackoff::exponential(Duration::from_millis(100), Duration::from_secs(1));
+        assert_eq!(exponential.delay(1), Duration::from_millis(100));
+        assert_eq!(exponential.delay(2), Duration::from_millis(200));
+        assert_eq!(exponential.delay(4), Duration::from_millis(800));
+        assert_eq!(exponential.delay(5), Duration::from_secs(1));
+        assert_eq!(exponential.delay(usize::MAX), Duration::from_secs(1));
+
+        let jittered = exponential.with_jitter();
+        for failures in 1..10 {
+            assert!(jittered.delay(failures) <= jittered.max_delay(failures));
+        }
+
+        #[derive(Deserialize)]
+        struct Wrapper {
+            failover_backoff: FailoverBackoff,
+        }
+        let wrapper: Wrapper = toml::from_str(
+            r#"
+            [failover_backoff]
+            kind = "exponential"
+            base_ms = 100
+            max_ms = 1000
+            jitter = true
+            "#,
+        )?;
+        assert_eq!(wrapper.failover_backoff, jittered);
+        Ok(())
+    }
+}
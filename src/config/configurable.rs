@@ -1,7 +1,7 @@
 use super::{
     multi_clusters::MultipleClustersConfig,
     single_cluster::{Config, SingleClusterConfig},
-    ClustersConfigParseError, Timeouts,
+    ClustersConfigParseError, HttpClientTimeouts,
 };
 use std::{
     collections::HashSet,
@@ -106,7 +106,7 @@ impl Configurable {
     }
 
     #[inline]
-    pub(super) fn timeouts_set(&self) -> HashSet<Timeouts> {
+    pub(super) fn timeouts_set(&self) -> HashSet<HttpClientTimeouts> {
         match &self.0 {
             ConfigurableInner::Single(single) => single.timeouts_set(),
             ConfigurableInner::Multi(multi) => multi.timeouts_set(),
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error as StdError,
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+};
+
+/// 视为连接失败的错误类型
+///
+/// 连接失败的域名在选择域名时将被优先排除，对应配置文件中的 `connection_failure_kinds` 字段，例如
+///
+/// ```toml
+/// connection_failure_kinds = ["dns", "connect"]
+/// ```
+///
+/// 默认三种错误均视为连接失败
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ConnectionFailureKind {
+    /// 域名解析失败
+    Dns,
+    /// 建立 TCP 连接失败
+    Connect,
+    /// TLS 握手失败
+    Tls,
+}
+
+impl ConnectionFailureKind {
+    /// 默认视为连接失败的错误类型
+    pub(crate) const ALL: [Self; 3] = [Self::Dns, Self::Connect, Self::Tls];
+
+    /// 判断错误的连接失败类型，不属于连接失败时返回 None
+    ///
+    /// 依次检查错误链，仅建立连接阶段的错误属于连接失败，再根据错误信息区分域名解析和 TLS 握手失败
+    pub(crate) fn of(error: &(dyn StdError + 'static)) -> Option<Self> {
+        let mut is_connect = false;
+        let mut messages = Vec::new();
+        let mut source = Some(error);
+        while let Some(err) = source {
+            if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+                is_connect |= err.is_connect();
+            } else if let Some(err) = err.downcast_ref::<hyper::Error>() {
+                is_connect |= err.is_connect();
+            } else if let Some(err) = err.downcast_ref::<IoError>() {
+                // 自定义的 IoError 在错误链中会跳过内部错误本身，需要单独检查
+                is_connect |= matches!(
+                    err.kind(),
+                    IoErrorKind::ConnectionRefused | IoErrorKind::AddrNotAvailable
+                ) || err
+                    .get_ref()
+                    .and_then(|err| err.downcast_ref::<reqwest::Error>())
+                    .is_some_and(|err| err.is_connect());
+            }
+            messages.push(err.to_string().to_ascii_lowercase());
+            source = err.source();
+        }
+        if !is_connect {
+            return None;
+        }
+        let contains = |patterns: &[&str]| {
+            messages
+                .iter()
+                .any(|message| patterns.iter().any(|pattern| message.contains(pattern)))
+        };
+        if contains(&[
+            "dns error",
+            "failed to lookup address",
+            "name or service not known",
+            "no record found",
+        ]) {
+            Some(Self::Dns)
+        } else if contains(&["tls", "ssl", "certificate", "handshake"]) {
+            Some(Self::Tls)
+        } else {
+            Some(Self::Connect)
+        }
+    }
+}
+
+/// 错误是否属于指定类型的连接失败
+pub(crate) fn is_connection_failure(
+    error: &(dyn StdError + 'static),
+    kinds: &[ConnectionFailureKind],
+) -> bool {
+    ConnectionFailureKind::of(error).is_some_and(|kind| kinds.contains(&kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt;
+
+    #[derive(Debug)]
+    struct Wrapped(&'static str, IoError);
+
+    impl fmt::Display for Wrapped {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    impl StdError for Wrapped {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.1)
+        }
+    }
+
+    #[test]
+    fn test_connection_failure_kind_of() {
+        let refused = IoError::new(IoErrorKind::ConnectionRefused, "connection refused");
+        assert_eq!(
+            ConnectionFailureKind::of(&refused),
+            Some(ConnectionFailureKind::Connect)
+        );
+        assert_eq!(
+            ConnectionFailureKind::of(&Wrapped("dns error", refused)),
+            Some(ConnectionFailureKind::Dns)
+        );
+        assert_eq!(
+            ConnectionFailureKind::of(&Wrapped(
+                "error trying to connect",
+                IoError::new(IoErrorKind::AddrNotAvailable, "tls handshake eof"),
+            )),
+            Some(ConnectionFailureKind::Tls)
+        );
+        assert_eq!(
+            ConnectionFailureKind::of(&IoError::new(IoErrorKind::Other, "dns error")),
+            None
+        );
+        assert!(!is_connection_failure(
+            &IoError::new(IoErrorKind::ConnectionRefused, "connection refused"),
+            &[ConnectionFailureKind::Dns],
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_connection_failure_kind_of_reqwest_error() -> anyhow::Result<()> {
+        let err = reqwest::Client::new()
+            .get("http://127.0.0.1:1")
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(
+            ConnectionFailureKind::of(&err),
+            Some(ConnectionFailureKind::Connect)
+        );
+        assert!(is_connection_failure(
+            &IoError::new(IoErrorKind::ConnectionAborted, err),
+            &ConnectionFailureKind::ALL,
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+use dashmap::DashMap;
+use hyper::client::connect::dns::Name;
+use log::debug;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use std::{
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+const DEFAULT_MAX_TTL: Duration = Duration::from_secs(3600);
+
+/// DNS 缓存的有效期范围
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct DnsCacheTtl {
+    min: Duration,
+    max: Duration,
+}
+
+impl DnsCacheTtl {
+    /// 两者均未设置时返回 None，表示不启用 DNS 缓存
+    pub(crate) fn new(min: Option<Duration>, max: Option<Duration>) -> Option<Self> {
+        let min = min.filter(|&ttl| ttl > Duration::from_secs(0));
+        let max = max.filter(|&ttl| ttl > Duration::from_secs(0));
+        if min.is_none() && max.is_none() {
+            return None;
+        }
+        let min = min.unwrap_or_default();
+        let max = max.unwrap_or(DEFAULT_MAX_TTL).max(min);
+        Some(Self { min, max })
+    }
+
+    fn limit(&self, ttl: Duration) -> Duration {
+        ttl.max(self.min).min(self.max)
+    }
+}
+
+#[derive(Clone)]
+struct CachedAddrs {
+    addrs: Arc<[SocketAddr]>,
+    expires_at: Instant,
+}
+
+/// 带缓存的 DNS 解析器
+///
+/// 启用 `hickory-dns` 功能时使用 hickory-resolver 解析并遵循记录自身的 TTL，
+/// 否则使用系统解析器，由于无法得知记录的 TTL，缓存将保留最短有效期
+#[derive(Clone)]
+pub(crate) struct CachedResolver(Arc<CachedResolverInner>);
+
+struct CachedResolverInner {
+    ttl: DnsCacheTtl,
+    cache: DashMap<String, CachedAddrs>,
+    #[cfg(feature = "hickory-dns")]
+    resolver: hickory_resolver::TokioAsyncResolver,
+}
+
+impl CachedResolver {
+    pub(crate) fn new(ttl: DnsCacheTtl) -> Self {
+        Self(Arc::new(CachedResolverInner {
+            ttl,
+            cache: Default::default(),
+            #[cfg(feature = "hickory-dns")]
+            resolver: hickory_resolver::TokioAsyncResolver::tokio_from_system_conf()
+                .unwrap_or_else(|err| {
+                    log::warn!(
+                        "Failed to load system DNS config, use default config instead: {}",
+                        err
+                    );
+                    hickory_resolver::TokioAsyncResolver::tokio(
+                        Default::default(),
+                        Default::default(),
+                    )
+                }),
+        }))
+    }
+
+    fn cached(&self, host: &str) -> Option<Arc<[SocketAddr]>> {
+        let entry = self.0.cache.get(host)?;
+        if entry.expires_at > Instant::now() {
+            Some(entry.addrs.to_owned())
+        } else {
+            None
+        }
+    }
+
+    async fn resolve_and_cache(self, host: String) -> Result<Arc<[SocketAddr]>, IoError> {
+        if let Some(addrs) = self.cached(&host) {
+            return Ok(addrs);
+        }
+        let (addrs, ttl) = self.lookup(&host).await?;
+        if addrs.is_empty() {
+            return Err(IoError::new(
+                IoErrorKind::NotFound,
+                format!("No address is resolved for {}", host),
+            ));
+        }
+        let ttl = self.0.ttl.limit(ttl);
+        debug!("Resolved {} to {:?}, cached for {:?}", host, addrs, ttl);
+        let addrs: Arc<[SocketAddr]> = addrs.into();
+        if ttl > Duration::from_secs(0) {
+            self.0.cache.insert(
+                host,
+                CachedAddrs {
+                    addrs: addrs.to_owned(),
+                    expires_at: Instant::now() + ttl,
+                },
+            );
+        }
+        Ok(addrs)
+    }
+
+    #[cfg(feature = "hickory-dns")]
+    async fn lookup(&self, host: &str) -> Result<(Vec<SocketAddr>, Duration), IoError> {
+        let lookup = self
+            .0
+            .resolver
+            .lookup_ip(host)
+            .await
+            .map_err(|err| IoError::new(IoErrorKind::Other, err))?;
+        let ttl = lookup
+            .valid_until()
+            .saturating_duration_since(Instant::now());
+        let addrs = lookup.iter().map(|ip| SocketAddr::new(ip, 0)).collect();
+        Ok((addrs, ttl))
+    }
+
+    #[cfg(not(feature = "hickory-dns"))]
+    async fn lookup(&self, host: &str) -> Result<(Vec<SocketAddr>, Duration), IoError> {
+        let addrs = tokio::net::lookup_host((host, 0)).await?.collect();
+        Ok((addrs, Duration::from_secs(0)))
+    }
+}
+
+impl Resolve for CachedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.to_owned();
+        let host = name.as_str().to_owned();
+        Box::pin(async move {
+            let addrs = resolver.resolve_and_cache(host).await?;
+            let addrs: Addrs = Box::new(Vec::from(&*addrs).into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dns_cache_ttl() {
+        assert_eq!(DnsCacheTtl::new(None, None), None);
+        assert_eq!(
+            DnsCacheTtl::new(Some(Duration::from_secs(0)), Some(Duration::from_secs(0))),
+            None
+        );
+
+        let ttl = DnsCacheTtl::new(Some(Duration::from_secs(30)), None).unwrap();
+        assert_eq!(ttl.limit(Duration::from_secs(1)), Duration::from_secs(30));
+        assert_eq!(ttl.limit(Duration::from_secs(86400)), DEFAULT_MAX_TTL);
+
+        let ttl =
+            DnsCacheTtl::new(Some(Duration::from_secs(60)), Some(Duration::from_secs(10))).unwrap();
+        assert_eq!(ttl.limit(Duration::from_secs(0)), Duration::from_secs(60));
+        assert_eq!(ttl.limit(Duration::from_secs(600)), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn test_cached_resolver() -> anyhow::Result<()> {
+        let resolver = CachedResolver::new(
+            DnsCacheTtl::new(Some(Duration::from_secs(60)), Some(Duration::from_secs(60))).unwrap(),
+        );
+        let addrs = resolver
+            .to_owned()
+            .resolve_and_cache("localhost".to_owned())
+            .await?;
+        assert!(!addrs.is_empty());
+        assert_eq!(resolver.cached("localhost"), Some(addrs));
+        assert_eq!(resolver.cached("127.0.0.2"), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use log::warn;
+use reqwest::Url;
+use std::{
+    fmt::{Display, Formatter, Result as FormatResult},
+    str::FromStr,
+};
+use thiserror::Error;
+
+/// 经过解析和规范化的域名 URL
+///
+/// 域名 URL 只能包含协议和域名（可以带端口号），例如 `http://iovip.qbox.me` 或 `https://127.0.0.1:8080`。
+/// 解析时忽略首尾的空白字符和末尾的 `/`，协议名称不区分大小写
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct HostUrl {
+    https: bool,
+    authority: String,
+}
+
+impl HostUrl {
+    /// 解析域名 URL
+    pub fn parse(url: &str) -> Result<Self, HostUrlError> {
+        let trimmed = url.trim();
+        if trimmed.is_empty() {
+            return Err(HostUrlError::Empty);
+        }
+        let (scheme, rest) = trimmed
+            .split_once("://")
+            .ok_or_else(|| HostUrlError::MissingScheme(url.to_owned()))?;
+        let https = if scheme.eq_ignore_ascii_case("https") {
+            true
+        } else if scheme.eq_ignore_ascii_case("http") {
+            false
+        } else {
+            return Err(HostUrlError::UnsupportedScheme(url.to_owned()));
+        };
+        let authority = rest.trim_end_matches('/');
+        if authority.contains(['/', '?', '#']) {
+            return Err(HostUrlError::UnexpectedPath(url.to_owned()));
+        }
+        let parsed = Url::parse(&format!("{}://{}", scheme, authority)).map_err(|err| {
+            HostUrlError::InvalidAuthority {
+                url: url.to_owned(),
+                reason: err.to_string(),
+            }
+        })?;
+        if authority.is_empty() || parsed.host_str().map_or(true, |host| host.is_empty()) {
+            return Err(HostUrlError::InvalidAuthority {
+                url: url.to_owned(),
+                reason: "empty host".to_owned(),
+            });
+        }
+        Ok(Self {
+            https,
+            authority: authority.to_owned(),
+        })
+    }
+
+    /// 是否使用 HTTPS 协议
+    #[inline]
+    pub fn is_https(&self) -> bool {
+        self.https
+    }
+
+    /// 获取域名，如果有端口号则包括端口号
+    #[inline]
+    pub fn authority(&self) -> &str {
+        &self.authority
+    }
+
+    /// 将协议升级为 HTTPS
+    #[inline]
+    pub fn upgrade_to_https(mut self) -> Self {
+        self.https = true;
+        self
+    }
+}
+
+impl Display for HostUrl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        let scheme = if self.https { "https" } else { "http" };
+        write!(f, "{}://{}", scheme, self.authority)
+    }
+}
+
+impl FromStr for HostUrl {
+    type Err = HostUrlError;
+
+    #[inline]
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        Self::parse(url)
+    }
+}
+
+impl From<HostUrl> for String {
+    #[inline]
+    fn from(url: HostUrl) -> Self {
+        url.to_string()
+    }
+}
+
+/// 域名 URL 解析错误
+#[derive(Error, Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum HostUrlError {
+    /// 域名 URL 为空
+    #[error("Host url is empty")]
+    Empty,
+
+    /// 域名 URL 缺少协议，例如 `iovip.qbox.me`
+    #[error("Host url {0:?} has no scheme, http:// or https:// is required")]
+    MissingScheme(String),
+
+    /// 域名 URL 的协议既不是 HTTP 也不是 HTTPS
+    #[error("Host url {0:?} has unsupported scheme, only http:// and https:// are allowed")]
+    UnsupportedScheme(String),
+
+    /// 域名 URL 包含路径、查询参数或片段，路径前缀应该通过 `path_prefix` 设置
+    #[error("Host url {0:?} should not contain path, query or fragment")]
+    UnexpectedPath(String),
+
+    /// 域名 URL 中的域名或端口号不合法
+    #[error("Host url {url:?} has invalid host: {reason}")]
+    InvalidAuthority {
+        /// 域名 URL
+        url: String,
+        /// 不合法的原因
+        reason: String,
+    },
+}
+
+fn normalize(url: HostUrl, force_https: bool) -> String {
+    if force_https {
+        url.upgrade_to_https().into()
+    } else {
+        url.into()
+    }
+}
+
+/// 解析并规范化域名 URL 列表，任意一个域名 URL 不合法时返回错误
+pub(crate) fn parse_host_urls(
+    urls: &[String],
+    force_https: bool,
+) -> Result<Vec<String>, HostUrlError> {
+    urls.iter()
+        .map(|url| HostUrl::parse(url).map(|url| normalize(url, force_https)))
+        .collect()
+}
+
+/// 规范化域名 URL 列表，不合法的域名 URL 将被忽略并输出警告日志
+///
+/// 缺少协议的域名保持原样，访问时将根据是否使用 HTTPS 协议补全
+pub(crate) fn normalize_host_urls(urls: Vec<String>, force_https: bool) -> Vec<String> {
+    urls.into_iter()
+        .filter_map(|url| match HostUrl::parse(&url) {
+            Ok(url) => Some(normalize(url, force_https)),
+            Err(HostUrlError::MissingScheme(_)) => Some(url),
+            Err(err) => {
+                warn!("Ignore invalid host url: {}", err);
+                None
+            }
+        })
+        .collect()
+}
+
+/// 规范化单个域名 URL，不合法的域名 URL 保持原样，用于规范化以域名 URL 为键的配置
+pub(crate) fn normalize_host_url(url: String, force_https: bool) -> String {
+    match HostUrl::parse(&url) {
+        Ok(url) => normalize(url, force_https),
+        Err(_) => url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_host_url() {
+        let url = HostUrl::parse(" HTTP://iovip.qbox.me/ ").unwrap();
+        assert!(!url.is_https());
+        assert_eq!(url.authority(), "iovip.qbox.me");
+        assert_eq!(url.to_string(), "http://iovip.qbox.me");
+        assert_eq!(url.upgrade_to_https().to_string(), "https://iovip.qbox.me");
+        assert_eq!(
+            "https://127.0.0.1:8080"
+                .parse::<HostUrl>()
+                .unwrap()
+                .to_string(),
+            "https://127.0.0.1:8080"
+        );
+
+        assert_eq!(HostUrl::parse("  "), Err(HostUrlError::Empty));
+        assert_eq!(
+            HostUrl::parse("iovip.qbox.me"),
+            Err(HostUrlError::MissingScheme("iovip.qbox.me".to_owned()))
+        );
+        assert_eq!(
+            HostUrl::parse("ftp://iovip.qbox.me"),
+            Err(HostUrlError::UnsupportedScheme(
+                "ftp://iovip.qbox.me".to_owned()
+            ))
+        );
+        assert_eq!(
+            HostUrl::parse("http://iovip.qbox.me/prefix"),
+            Err(HostUrlError::UnexpectedPath(
+                "http://iovip.qbox.me/prefix".to_owned()
+            ))
+        );
+        assert!(matches!(
+            HostUrl::parse("http://iovip.qbox.me:port"),
+            Err(HostUrlError::InvalidAuthority { .. })
+        ));
+        assert!(matches!(
+            HostUrl::parse("http://"),
+            Err(HostUrlError::InvalidAuthority { .. })
+        ));
+    }
+
+    #[test]
+    fn test_normalize_host_urls() {
+        let urls = vec![
+            "http://host1/".to_owned(),
+            "host2".to_owned(),
+            "https://host3".to_owned(),
+        ];
+        assert_eq!(
+            parse_host_urls(&urls, false),
+            Err(HostUrlError::MissingScheme("host2".to_owned()))
+        );
+        assert_eq!(
+            normalize_host_urls(urls.to_owned(), false),
+            vec![
+                "http://host1".to_owned(),
+                "host2".to_owned(),
+                "https://host3".to_owned()
+            ]
+        );
+        assert_eq!(
+            normalize_host_urls(
+                vec!["http://host1".to_owned(), "ftp://host2".to_owned()],
+                true
+            ),
+            vec!["https://host1".to_owned()]
+        );
+        assert_eq!(
+            normalize_host_url("host2".to_owned(), true),
+            "host2".to_owned()
+        );
+    }
+}
@@ -1,20 +1,26 @@
-use super::SingleClusterConfig;
+use super::{
+    dns_cache::{CachedResolver, DnsCacheTtl},
+    RedirectPolicy, SingleClusterConfig,
+};
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
 use reqwest::{blocking::Client as HttpClient, Client as AsyncHttpClient};
 use std::{collections::HashSet, sync::Arc, time::Duration};
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub(crate) struct Timeouts {
+pub(crate) struct HttpClientTimeouts {
     base_timeout: Duration,
     dial_timeout: Duration,
+    dns_cache: Option<DnsCacheTtl>,
+    redirect_policy: RedirectPolicy,
 }
 
-static HTTP_CLIENTS: Lazy<DashMap<Timeouts, Arc<HttpClient>>> = Lazy::new(Default::default);
-static ASYNC_HTTP_CLIENTS: Lazy<DashMap<Timeouts, Arc<AsyncHttpClient>>> =
+static HTTP_CLIENTS: Lazy<DashMap<HttpClientTimeouts, Arc<HttpClient>>> =
+    Lazy::new(Default::default);
+static ASYNC_HTTP_CLIENTS: Lazy<DashMap<HttpClientTimeouts, Arc<AsyncHttpClient>>> =
     Lazy::new(Default::default);
 
-impl Timeouts {
+impl HttpClientTimeouts {
     #[cfg(test)]
     pub(crate) fn default_http_client() -> Arc<HttpClient> {
         Self::new(None, None).http_client()
@@ -33,63 +39,103 @@ impl Timeouts {
             dial_timeout: dial_timeout
                 .filter(|&value| value > Duration::from_millis(0))
                 .unwrap_or_else(|| Duration::from_millis(50)),
+            dns_cache: None,
+            redirect_policy: Default::default(),
         }
     }
 
+    /// 设置 DNS 缓存的最短和最长有效期，两者均未设置时不启用 DNS 缓存
+    ///
+    /// 由于阻塞客户端无法替换 DNS 解析器，DNS 缓存仅对异步客户端生效
+    pub(crate) fn with_dns_cache(mut self, min: Option<Duration>, max: Option<Duration>) -> Self {
+        self.dns_cache = DnsCacheTtl::new(min, max);
+        self
+    }
+
+    pub(crate) fn with_redirect_policy(mut self, redirect_policy: Option<RedirectPolicy>) -> Self {
+        self.redirect_policy = redirect_policy.unwrap_or_default();
+        self
+    }
+
     pub(crate) fn http_client(&self) -> Arc<HttpClient> {
         return HTTP_CLIENTS
             .entry(self.to_owned())
             .or_insert_with(|| build_http_client(self))
             .to_owned();
 
-        fn build_http_client(timeouts: &Timeouts) -> Arc<HttpClient> {
+        fn build_http_client(timeouts: &HttpClientTimeouts) -> Arc<HttpClient> {
             const USER_AGENT: &str =
                 concat!("QiniuRustDownload/", env!("CARGO_PKG_VERSION"), "/sync");
-            Arc::new(
-                HttpClient::builder()
-                    .user_agent(USER_AGENT)
-                    .connect_timeout(timeouts.dial_timeout)
-                    .timeout(timeouts.base_timeout)
-                    .pool_max_idle_per_host(5)
-                    .connection_verbose(true)
-                    .build()
-                    .expect("Failed to build Reqwest Client"),
-            )
+            let builder = HttpClient::builder()
+                .user_agent(USER_AGENT)
+                .connect_timeout(timeouts.dial_timeout)
+                .timeout(timeouts.base_timeout)
+                .redirect(timeouts.redirect_policy.to_reqwest_policy())
+                .pool_max_idle_per_host(5)
+                .connection_verbose(true);
+            #[cfg(feature = "rustls-tls")]
+            let builder = builder.use_rustls_tls();
+            Arc::new(builder.build().expect("Failed to build Reqwest Client"))
         }
     }
 
+    /// 丢弃当前超时配置对应的异步客户端，下次获取时将重新创建，如果客户端存在则返回 true
+    pub(crate) fn purge_async_http_client(&self) -> bool {
+        ASYNC_HTTP_CLIENTS.remove(self).is_some()
+    }
+
     pub(crate) fn async_http_client(&self) -> Arc<AsyncHttpClient> {
+        if let Some(client) = ASYNC_HTTP_CLIENTS.get(self) {
+            return client.to_owned();
+        }
         return ASYNC_HTTP_CLIENTS
             .entry(self.to_owned())
             .or_insert_with(|| build_http_client(self))
             .to_owned();
 
-        fn build_http_client(timeouts: &Timeouts) -> Arc<AsyncHttpClient> {
+        fn build_http_client(timeouts: &HttpClientTimeouts) -> Arc<AsyncHttpClient> {
             const USER_AGENT: &str =
                 concat!("QiniuRustDownload/", env!("CARGO_PKG_VERSION"), "/async");
-            Arc::new(
-                AsyncHttpClient::builder()
-                    .user_agent(USER_AGENT)
-                    .connect_timeout(timeouts.dial_timeout)
-                    .pool_max_idle_per_host(5)
-                    .connection_verbose(true)
-                    .build()
-                    .expect("Failed to build Reqwest Client"),
-            )
+            let mut builder = AsyncHttpClient::builder()
+                .user_agent(USER_AGENT)
+                .connect_timeout(timeouts.dial_timeout)
+                .redirect(timeouts.redirect_policy.to_reqwest_policy())
+                .pool_max_idle_per_host(5)
+                .connection_verbose(true);
+            if let Some(ttl) = timeouts.dns_cache {
+                builder = builder.dns_resolver(Arc::new(CachedResolver::new(ttl)));
+            }
+            #[cfg(feature = "rustls-tls")]
+            let builder = builder.use_rustls_tls();
+            Arc::new(builder.build().expect("Failed to build Reqwest Client"))
         }
     }
 }
 
-impl<'a> From<&'a SingleClusterConfig> for Timeouts {
+impl<'a> From<&'a SingleClusterConfig> for HttpClientTimeouts {
     fn from(config: &'a SingleClusterConfig) -> Self {
-        Self::new(config.base_timeout(), config.connect_timeout())
+        let defaults = config
+            .timeouts()
+            .map(|timeouts| *timeouts.defaults())
+            .unwrap_or_default();
+        Self::new(
+            defaults.read().or_else(|| config.base_timeout()),
+            defaults.connect().or_else(|| config.connect_timeout()),
+        )
+        .with_dns_cache(config.dns_cache_min_ttl(), config.dns_cache_max_ttl())
+        .with_redirect_policy(config.redirect_policy())
     }
 }
 
-pub(super) fn ensure_http_clients(set: &HashSet<Timeouts>) {
+pub(super) fn ensure_http_clients(set: &HashSet<HttpClientTimeouts>) {
     HTTP_CLIENTS.retain(|key, _| set.contains(key))
 }
 
+pub(super) fn purge_http_clients() {
+    HTTP_CLIENTS.clear();
+    ASYNC_HTTP_CLIENTS.clear();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,15 +145,20 @@ mod tests {
         env_logger::try_init().ok();
 
         let c1 =
-            Timeouts::new(Some(Duration::from_secs(1)), Some(Duration::from_secs(1))).http_client();
+            HttpClientTimeouts::new(Some(Duration::from_secs(1)), Some(Duration::from_secs(1)))
+                .http_client();
         let c2 =
-            Timeouts::new(Some(Duration::from_secs(1)), Some(Duration::from_secs(1))).http_client();
+            HttpClientTimeouts::new(Some(Duration::from_secs(1)), Some(Duration::from_secs(1)))
+                .http_client();
         let c3 =
-            Timeouts::new(Some(Duration::from_secs(1)), Some(Duration::from_secs(2))).http_client();
+            HttpClientTimeouts::new(Some(Duration::from_secs(1)), Some(Duration::from_secs(2)))
+                .http_client();
         let c4 =
-            Timeouts::new(Some(Duration::from_secs(2)), Some(Duration::from_secs(1))).http_client();
+            HttpClientTimeouts::new(Some(Duration::from_secs(2)), Some(Duration::from_secs(1)))
+                .http_client();
         let c5 =
-            Timeouts::new(Some(Duration::from_secs(2)), Some(Duration::from_secs(2))).http_client();
+            HttpClientTimeouts::new(Some(Duration::from_secs(2)), Some(Duration::from_secs(2)))
+                .http_client();
 
         assert_eq!(3, Arc::strong_count(&c1));
         assert_eq!(0, Arc::weak_count(&c1));
@@ -120,4 +171,19 @@ mod tests {
         assert_eq!(2, Arc::strong_count(&c5));
         assert_eq!(0, Arc::weak_count(&c5));
     }
+
+    #[test]
+    fn test_purge_async_http_client() {
+        env_logger::try_init().ok();
+
+        let timeouts =
+            HttpClientTimeouts::new(Some(Duration::from_secs(3)), Some(Duration::from_secs(3)));
+        let c1 = timeouts.async_http_client();
+        assert!(Arc::ptr_eq(&c1, &timeouts.async_http_client()));
+        assert!(timeouts.purge_async_http_client());
+        assert!(!timeouts.purge_async_http_client());
+        let c2 = timeouts.async_http_client();
+        assert!(!Arc::ptr_eq(&c1, &c2));
+        assert_eq!(1, Arc::strong_count(&c1));
+    }
 }
@@ -0,0 +1,81 @@
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// 对象名称前缀改写规则
+///
+/// 对应配置文件中的 `[[key_rewrite_rules]]` 表数组，例如
+///
+/// ```toml
+/// [[key_rewrite_rules]]
+/// prefix = "logs/"
+/// replacement = "v2/logs/"
+/// ```
+///
+/// 应用请求 `logs/x` 时将实际下载 `v2/logs/x`。改写在构造下载 URL 前进行，对所有 API 生效，
+/// 改写后的对象名称会出现在请求跟踪录制的路径和调试日志中
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+pub struct KeyRewriteRule {
+    prefix: String,
+    replacement: String,
+}
+
+impl KeyRewriteRule {
+    /// 创建前缀改写规则，将以 `prefix` 开头的对象名称的前缀替换为 `replacement`
+    #[inline]
+    pub fn new(prefix: impl Into<String>, replacement: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            replacement: replacement.into(),
+        }
+    }
+
+    /// 获取匹配的对象名称前缀
+    #[inline]
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// 获取替换后的对象名称前缀
+    #[inline]
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+}
+
+/// 按顺序查找第一条匹配的规则改写对象名称，没有匹配的规则时返回原对象名称
+pub(crate) fn rewrite_key<'a>(rules: &[KeyRewriteRule], key: &'a str) -> Cow<'a, str> {
+    for rule in rules.iter() {
+        if let Some(rest) = key.strip_prefix(rule.prefix.as_str()) {
+            let rewritten = rule.replacement.to_owned() + rest;
+            debug!(
+                "key {} is rewritten to {} by prefix {:?}",
+                key, rewritten, rule.prefix
+            );
+            return Cow::Owned(rewritten);
+        }
+    }
+    Cow::Borrowed(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_key() {
+        let rules = vec![
+            KeyRewriteRule::new("logs/archive/", "cold/logs/"),
+            KeyRewriteRule::new("logs/", "v2/logs/"),
+            KeyRewriteRule::new("/", ""),
+        ];
+        assert_eq!(rewrite_key(&rules, "logs/x"), "v2/logs/x");
+        assert_eq!(rewrite_key(&rules, "logs/archive/x"), "cold/logs/x");
+        assert_eq!(rewrite_key(&rules, "/data/x"), "data/x");
+        assert!(matches!(
+            rewrite_key(&rules, "data/x"),
+            Cow::Borrowed("data/x")
+        ));
+        assert_eq!(rewrite_key(&[], "logs/x"), "logs/x");
+    }
+}
@@ -1,22 +1,46 @@
+mod backoff;
 mod configurable;
+mod connection_failure;
+mod dns_cache;
+mod host_url;
 mod http_client;
+mod key_rewrite;
 mod multi_clusters;
+mod range_encoding;
+mod redirect;
 mod single_cluster;
 mod static_vars;
+mod timeouts;
 mod watcher;
 
+pub use backoff::FailoverBackoff;
 pub use configurable::Configurable;
-use http_client::ensure_http_clients;
-pub(crate) use http_client::Timeouts;
+pub(crate) use connection_failure::is_connection_failure;
+pub use connection_failure::ConnectionFailureKind;
+pub(crate) use host_url::{normalize_host_url, normalize_host_urls, parse_host_urls};
+pub use host_url::{HostUrl, HostUrlError};
+pub(crate) use http_client::HttpClientTimeouts;
+use http_client::{ensure_http_clients, purge_http_clients};
+pub(crate) use key_rewrite::rewrite_key;
+pub use key_rewrite::KeyRewriteRule;
 pub use multi_clusters::{
     MultipleClustersConfig, MultipleClustersConfigBuilder, MultipleClustersConfigParseError,
 };
+pub use range_encoding::RangeRequestEncoding;
+pub use redirect::RedirectPolicy;
 pub use single_cluster::{Config, ConfigBuilder, SingleClusterConfig, SingleClusterConfigBuilder};
+pub use timeouts::{TimeoutApi, Timeouts, TimeoutsConfig};
 
 use super::base::{credential::Credential, download::RangeReaderBuilder as BaseRangeReaderBuilder};
 use log::{error, info, warn};
 use static_vars::qiniu_config;
-use std::{env, fs, sync::RwLock, time::Duration};
+use std::{
+    env, fs,
+    io::{Error as IOError, ErrorKind as IOErrorKind},
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::Duration,
+};
 use tap::prelude::*;
 use thiserror::Error;
 use watcher::{ensure_watches, unwatch_all};
@@ -68,6 +92,14 @@ pub fn set_qiniu_multi_clusters_config(config: MultipleClustersConfig) {
     set_config_and_reload(config.into(), false)
 }
 
+/// 丢弃所有 HTTP 客户端及其连接池，后续请求将使用新建的客户端
+///
+/// 用于网络环境变化（例如切换 VPN）后，避免继续使用指向旧路由的空闲连接。
+/// 正在进行的请求不受影响，最大并行重试次数为 0 时使用的同步实现不受影响
+pub fn purge_connections() {
+    purge_http_clients()
+}
+
 const QINIU_ENV: &str = "QINIU";
 const QINIU_MULTI_ENV: &str = "QINIU_MULTI_CLUSTER";
 const QINIU_DISABLE_CONFIG_HOT_RELOADING_ENV: &str = "QINIU_DISABLE_CONFIG_HOT_RELOADING";
@@ -108,6 +140,32 @@ fn load_config() -> Option<Configurable> {
     }
 }
 
+/// 重新读取并解析环境变量指定的配置文件，成功时返回配置文件路径，失败时返回错误信息
+pub(crate) fn check_config_file() -> Result<PathBuf, String> {
+    let (path, multi) = env::var_os(QINIU_MULTI_ENV)
+        .map(|path| (PathBuf::from(path), true))
+        .or_else(|| env::var_os(QINIU_ENV).map(|path| (PathBuf::from(path), false)))
+        .ok_or_else(|| "QINIU or QINIU_MULTI_CLUSTER Env IS NOT ENABLED".to_owned())?;
+    if !matches!(
+        path.extension().and_then(|s| s.to_str()),
+        Some("toml") | Some("json")
+    ) {
+        return Err(format!(
+            "Qiniu config file ({:?}) must be a .toml or .json file",
+            path
+        ));
+    }
+    let bytes = fs::read(&path)
+        .map_err(|err| format!("Qiniu config file ({:?}) cannot be open: {}", path, err))?;
+    Configurable::parse(&path, &bytes, multi).map_err(|err| {
+        format!(
+            "Qiniu config file ({:?}) cannot be deserialized: {}",
+            path, err
+        )
+    })?;
+    Ok(path)
+}
+
 fn init_config() -> RwLock<Option<Configurable>> {
     RwLock::new(load_config().tap(|config| ensure_watches_for(config.as_ref())))
 }
@@ -162,6 +220,26 @@ pub enum ClustersConfigParseError {
     /// 七牛配置信息 TOML 解析错误
     #[error("Parse config as toml error: {0}")]
     TOMLError(#[from] toml::de::Error),
+
+    /// 七牛配置信息中的域名 URL 不合法
+    #[error("Invalid host url in config: {0}")]
+    HostUrlError(#[from] HostUrlError),
+
+    /// 七牛配置信息文件类型不受支持，错误类型为 [`std::io::ErrorKind::InvalidInput`]
+    #[error("I/O error: {0}")]
+    IOError(#[from] IOError),
+}
+
+impl ClustersConfigParseError {
+    pub(super) fn unsupported_extension(path: &Path) -> Self {
+        Self::IOError(IOError::new(
+            IOErrorKind::InvalidInput,
+            format!(
+                "Qiniu config file ({:?}) must be a .toml or .json file",
+                path
+            ),
+        ))
+    }
 }
 
 pub(super) fn build_range_reader_builder_from_config(
@@ -178,6 +256,12 @@ pub(super) fn build_range_reader_builder_from_config(
             .unwrap_or_default(),
     );
 
+    if let Some(cdn_urls) = config.cdn_urls() {
+        if !cdn_urls.is_empty() {
+            builder = builder.cdn_urls(cdn_urls.to_owned());
+        }
+    }
+
     if let Some(uc_urls) = config.uc_urls() {
         if !uc_urls.is_empty() {
             builder = builder.uc_urls(uc_urls.to_owned());
@@ -196,6 +280,71 @@ pub(super) fn build_range_reader_builder_from_config(
         }
     }
 
+    if let Some(update_hosts_retry) = config.update_hosts_retry() {
+        if update_hosts_retry > 0 {
+            builder = builder.update_hosts_tries(update_hosts_retry);
+        }
+    }
+
+    if let Some(min_hosts_on_update) = config.min_hosts_on_update() {
+        if min_hosts_on_update > 0 {
+            builder = builder.min_hosts_on_update(min_hosts_on_update);
+        }
+    }
+
+    if let Some(max_host_change_ratio) = config.max_host_change_ratio() {
+        if max_host_change_ratio > 0.0 {
+            builder = builder.max_host_change_ratio(max_host_change_ratio);
+        }
+    }
+
+    if let Some(negative_cache_ttl) = config.negative_cache_ttl() {
+        if negative_cache_ttl > Duration::from_secs(0) {
+            builder = builder.negative_cache_ttl(negative_cache_ttl);
+        }
+    }
+
+    if let Some(head_cache_window) = config.head_cache_window() {
+        if head_cache_window > Duration::from_millis(0) {
+            builder = builder.head_cache_window(head_cache_window);
+        }
+    }
+
+    if let Some(preload_cache_size) = config.preload_cache_size() {
+        builder = builder.preload_cache_size(preload_cache_size);
+    }
+
+    if let Some(preload_cache_ttl) = config.preload_cache_ttl() {
+        if preload_cache_ttl > Duration::from_secs(0) {
+            builder = builder.preload_cache_ttl(preload_cache_ttl);
+        }
+    }
+
+    if let Some(force_https) = config.force_https() {
+        builder = builder.force_https(force_https);
+    }
+
+    if let Some(random_seed) = config.random_seed() {
+        builder = builder.random_seed(random_seed);
+    }
+
+    if let Some(verify_with_second_host_rate) = config.verify_with_second_host_rate() {
+        if verify_with_second_host_rate > 0 {
+            builder =
+                builder.verify_with_second_host(1.0 / f64::from(verify_with_second_host_rate));
+        }
+    }
+
+    if let Some(preferred_idc) = config.preferred_idc() {
+        if !preferred_idc.is_empty() {
+            builder = builder.preferred_idc(preferred_idc.to_owned());
+        }
+    }
+
+    if let Some(respect_cache_control) = config.respect_cache_control() {
+        builder = builder.respect_cache_control(respect_cache_control);
+    }
+
     if let Some(punish_time) = config.punish_time() {
         if punish_time > Duration::from_secs(0) {
             builder = builder.punish_duration(punish_time);
@@ -208,12 +357,139 @@ pub(super) fn build_range_reader_builder_from_config(
         }
     }
 
+    if let Some(max_timeout) = config.max_timeout() {
+        if max_timeout > Duration::from_millis(0) {
+            builder = builder.max_timeout(max_timeout);
+        }
+    }
+
+    if let Some(host_base_timeouts) = config.host_base_timeouts() {
+        builder = builder.host_base_timeouts(
+            host_base_timeouts
+                .into_iter()
+                .filter(|(_, timeout)| *timeout > Duration::from_millis(0))
+                .collect(),
+        );
+    }
+
     if let Some(connect_timeout) = config.connect_timeout() {
         if connect_timeout > Duration::from_millis(0) {
             builder = builder.connect_timeout(connect_timeout);
         }
     }
 
+    if let Some(dns_cache_min_ttl) = config.dns_cache_min_ttl() {
+        if dns_cache_min_ttl > Duration::from_secs(0) {
+            builder = builder.dns_cache_min_ttl(dns_cache_min_ttl);
+        }
+    }
+
+    if let Some(dns_cache_max_ttl) = config.dns_cache_max_ttl() {
+        if dns_cache_max_ttl > Duration::from_secs(0) {
+            builder = builder.dns_cache_max_ttl(dns_cache_max_ttl);
+        }
+    }
+
+    if let Some(cluster_name) = config.cluster_name() {
+        builder = builder.cluster_name(cluster_name.to_owned());
+    }
+
+    if let Some(redirect_policy) = config.redirect_policy() {
+        builder = builder.redirect_policy(redirect_policy);
+    }
+
+    if let Some(timeouts) = config.timeouts() {
+        builder = builder.timeouts(timeouts.to_owned());
+    }
+
+    if let Some(max_object_size) = config.max_object_size() {
+        builder = builder.max_object_size(max_object_size);
+    }
+
+    if let Some(strict_body_size) = config.strict_body_size() {
+        builder = builder.strict_body_size(strict_body_size);
+    }
+
+    if let Some(max_response_header_size) = config.max_response_header_size() {
+        if max_response_header_size > 0 {
+            builder = builder.max_response_header_size(max_response_header_size);
+        }
+    }
+
+    if let Some(strict_header_validation) = config.strict_header_validation() {
+        builder = builder.strict_header_validation(strict_header_validation);
+    }
+
+    if let Some(disable_read_multi_ranges) = config.disable_read_multi_ranges() {
+        builder = builder.disable_read_multi_ranges(disable_read_multi_ranges);
+    }
+
+    if let Some(range_request_encoding) = config.range_request_encoding() {
+        builder = builder.range_request_encoding(range_request_encoding);
+    }
+
+    if let Some(integrity_metadata) = config.integrity_metadata() {
+        builder = builder.integrity_metadata(integrity_metadata);
+    }
+
+    if let Some(preserve_last_modified) = config.preserve_last_modified() {
+        builder = builder.preserve_last_modified(preserve_last_modified);
+    }
+
+    if let Some(download_buffer_size) = config.download_buffer_size() {
+        builder = builder.download_buffer_size(download_buffer_size);
+    }
+
+    if let Some(max_multi_ranges) = config.max_multi_ranges() {
+        builder = builder.max_multi_ranges(max_multi_ranges);
+    }
+
+    if let Some(max_multi_ranges_size) = config.max_multi_ranges_size() {
+        builder = builder.max_multi_ranges_size(max_multi_ranges_size);
+    }
+
+    if let Some(disable_download) = config.disable_download() {
+        builder = builder.disable_download(disable_download);
+    }
+
+    if let Some(decompress_content_encoding) = config.decompress_content_encoding() {
+        builder = builder.decompress_content_encoding(decompress_content_encoding);
+    }
+
+    if let Some(strict) = config.strict() {
+        builder = builder.strict(strict);
+    }
+
+    if let Some(failover_backoff) = config.failover_backoff() {
+        builder = builder.failover_backoff(failover_backoff);
+    }
+
+    if let Some(connection_failure_kinds) = config.connection_failure_kinds() {
+        builder = builder.connection_failure_kinds(connection_failure_kinds.to_owned());
+    }
+
+    if let Some(retryable_status_codes) = config.retryable_status_codes() {
+        builder = builder.retryable_status_codes(retryable_status_codes.to_owned());
+    }
+
+    if let Some(object_level_status_codes) = config.object_level_status_codes() {
+        builder = builder.object_level_status_codes(object_level_status_codes.to_owned());
+    }
+
+    if let Some(key_rewrite_rules) = config.key_rewrite_rules() {
+        builder = builder.key_rewrite_rules(key_rewrite_rules.to_owned());
+    }
+
+    if let Some(sync_worker_threads) = config.sync_worker_threads() {
+        builder = builder.sync_worker_threads(sync_worker_threads);
+    }
+
+    if let Some(sync_request_timeout) = config.sync_request_timeout() {
+        if sync_request_timeout > Duration::from_millis(0) {
+            builder = builder.sync_request_timeout(sync_request_timeout);
+        }
+    }
+
     if let Some(dot_interval) = config.dot_interval() {
         if dot_interval > Duration::from_secs(0) {
             builder = builder.dot_interval(dot_interval);
@@ -226,6 +502,50 @@ pub(super) fn build_range_reader_builder_from_config(
         }
     }
 
+    if let (Some(monitor_access_key), Some(monitor_secret_key)) =
+        (config.monitor_access_key(), config.monitor_secret_key())
+    {
+        builder = builder.dot_credential(Credential::new(monitor_access_key, monitor_secret_key));
+    }
+
+    if let Some(monitor_bucket) = config.monitor_bucket() {
+        if !monitor_bucket.is_empty() {
+            builder = builder.dot_bucket(monitor_bucket.to_owned());
+        }
+    }
+
+    if let Some(dot_token_lifetime) = config.dot_token_lifetime() {
+        if dot_token_lifetime > Duration::from_secs(0) {
+            builder = builder.dot_token_lifetime(dot_token_lifetime);
+        }
+    }
+
+    if let Some(dot_sample_rate) = config.dot_sample_rate() {
+        if dot_sample_rate > 0 {
+            builder = builder.dot_sample_rate(dot_sample_rate);
+        }
+    }
+
+    if let Some(dot_sampling_threshold) = config.dot_sampling_threshold() {
+        if dot_sampling_threshold > 0 {
+            builder = builder.dot_sampling_threshold(dot_sampling_threshold);
+        }
+    }
+
+    if let Some(dot_latency_buckets_ms) = config.dot_latency_buckets_ms() {
+        if !dot_latency_buckets_ms.is_empty() {
+            builder = builder.dot_latency_buckets_ms(dot_latency_buckets_ms.to_owned());
+        }
+    }
+
+    if let Some(dot_sync_runtime_load) = config.dot_sync_runtime_load() {
+        builder = builder.dot_sync_runtime_load(dot_sync_runtime_load);
+    }
+
+    if let Some(dot_stage_timings) = config.dot_stage_timings() {
+        builder = builder.dot_stage_timings(dot_stage_timings);
+    }
+
     if let Some(max_retry_concurrency) = config.max_retry_concurrency() {
         builder = builder.max_retry_concurrency(max_retry_concurrency);
     }
@@ -238,6 +558,12 @@ pub(super) fn build_range_reader_builder_from_config(
         builder = builder.use_getfile_api(use_getfile_api);
     }
 
+    if let Some(path_prefix) = config.path_prefix() {
+        if !path_prefix.is_empty() {
+            builder = builder.path_prefix(path_prefix.to_owned());
+        }
+    }
+
     if let Some(normalize_key) = config.normalize_key() {
         builder = builder.normalize_key(normalize_key);
     }
@@ -270,10 +596,11 @@ mod tests {
         ffi::OsStr,
         fs::{remove_file, rename, OpenOptions},
         io::Write,
-        path::PathBuf,
+        path::{Path, PathBuf},
         thread::sleep,
     };
     use tempfile::{tempdir, Builder as TempFileBuilder};
+    use warp::{http::HeaderValue, path, reply::Response, Filter};
     use watcher::{watch_dirs_count, watch_files_count};
 
     #[test]
@@ -448,6 +775,283 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_build_range_reader_builder_with_monitor_config() -> Result<()> {
+        let config: Config = toml::from_str(
+            r#"
+            access_key = "test-ak-1"
+            secret_key = "test-sk-1"
+            bucket = "test-bucket-1"
+            io_urls = ["http://io1.com"]
+            cdn_urls = ["http://cdn1.com"]
+            monitor_urls = ["http://monitor1.com"]
+            monitor_access_key = "monitor-ak-1"
+            monitor_secret_key = "monitor-sk-1"
+            monitor_bucket = "monitor-bucket-1"
+            dot_token_lifetime_s = 300
+            dot_sample_rate = 100
+            dot_sampling_threshold = 4096
+            dot_latency_buckets_ms = [100, 500, 1000]
+            dot_sync_runtime_load = true
+            dot_stage_timings = true
+            retryable_status_codes = [502, 503, 504]
+            connection_failure_kinds = ["dns", "connect"]
+            object_level_status_codes = [410]
+            update_hosts_retry = 5
+            min_hosts_on_update = 2
+            max_host_change_ratio = 0.8
+            path_prefix = "/internal/"
+            disable_read_multi_ranges = true
+            range_request_encoding = "query_param"
+            integrity_metadata = true
+            preserve_last_modified = true
+            download_buffer_size = 1048576
+            max_multi_ranges = 100
+            max_multi_ranges_size = 67108864
+            disable_download = true
+            decompress_content_encoding = true
+            strict = true
+            negative_cache_ttl_s = 60
+            head_cache_window_ms = 500
+            preload_cache_size = 1048576
+            preload_cache_ttl_s = 600
+            respect_cache_control = true
+            force_https = true
+            random_seed = 42
+            preferred_idc = "idc-a"
+            verify_with_second_host_rate = 4
+            max_timeout_ms = 30000
+            max_response_header_size = 16384
+            strict_header_validation = true
+
+            [redirect_policy]
+            kind = "same_host"
+            max_redirects = 3
+
+            [host_base_timeouts_ms]
+            "http://io1.com" = 6000
+
+            [[key_rewrite_rules]]
+            prefix = "logs/"
+            replacement = "v2/logs/"
+            "#,
+        )?;
+        let builder = build_range_reader_builder_from_config("key".to_owned(), &config);
+        assert_eq!(builder.credential.access_key(), "test-ak-1");
+        assert_eq!(
+            builder.dot_credential.as_ref().map(|c| c.access_key()),
+            Some("monitor-ak-1")
+        );
+        assert_eq!(builder.dot_bucket.as_deref(), Some("monitor-bucket-1"));
+        assert_eq!(builder.dot_token_lifetime, Some(Duration::from_secs(300)));
+        assert_eq!(builder.dot_sample_rate, Some(100));
+        assert_eq!(builder.dot_sampling_threshold, Some(4096));
+        assert_eq!(builder.dot_latency_buckets_ms, Some(vec![100, 500, 1000]));
+        assert!(builder.dot_sync_runtime_load);
+        assert!(builder.dot_stage_timings);
+        assert_eq!(builder.retryable_status_codes, Some(vec![502, 503, 504]));
+        assert_eq!(
+            builder.connection_failure_kinds,
+            vec![ConnectionFailureKind::Dns, ConnectionFailureKind::Connect]
+        );
+        assert_eq!(builder.object_level_status_codes, vec![410]);
+        assert_eq!(builder.cdn_urls, vec!["http://cdn1.com".to_owned()]);
+        assert_eq!(builder.update_hosts_tries, 5);
+        assert_eq!(builder.min_hosts_on_update, Some(2));
+        assert_eq!(builder.max_host_change_ratio, Some(0.8));
+        assert_eq!(
+            builder.url_options.path_prefix.as_deref(),
+            Some("/internal")
+        );
+        assert!(builder.disable_read_multi_ranges);
+        assert_eq!(
+            builder.range_request_encoding,
+            RangeRequestEncoding::QueryParam
+        );
+        assert!(builder.integrity_metadata);
+        assert!(builder.preserve_last_modified);
+        assert_eq!(builder.download_buffer_size, Some(1 << 20));
+        assert_eq!(builder.max_multi_ranges, Some(100));
+        assert_eq!(builder.max_multi_ranges_size, Some(67108864));
+        assert_eq!(
+            builder.host_base_timeouts.get("http://io1.com"),
+            Some(&Duration::from_millis(6000))
+        );
+        assert!(builder.disable_download);
+        assert!(builder.decompress_content_encoding);
+        assert!(builder.strict);
+        assert_eq!(builder.negative_cache_ttl, Some(Duration::from_secs(60)));
+        assert_eq!(builder.head_cache_window, Some(Duration::from_millis(500)));
+        assert_eq!(builder.preload_cache_size, Some(1048576));
+        assert_eq!(builder.preload_cache_ttl, Some(Duration::from_secs(600)));
+        assert!(builder.respect_cache_control);
+        assert!(builder.force_https);
+        assert_eq!(builder.random_seed, Some(42));
+        assert_eq!(builder.preferred_idc.as_deref(), Some("idc-a"));
+        assert!((builder.verify_with_second_host - 0.25).abs() < f64::EPSILON);
+        assert_eq!(builder.max_timeout, Some(Duration::from_secs(30)));
+        assert_eq!(builder.max_response_header_size, Some(16384));
+        assert!(builder.strict_header_validation);
+        assert_eq!(builder.redirect_policy, Some(RedirectPolicy::same_host(3)));
+        assert_eq!(
+            builder.key_rewrite_rules,
+            vec![KeyRewriteRule::new("logs/", "v2/logs/")]
+        );
+
+        let builder = build_range_reader_builder_from_config(
+            "key".to_owned(),
+            &Config::builder("test-ak-1", "test-sk-1", "test-bucket-1", None).build(),
+        );
+        assert!(builder.dot_credential.is_none());
+        assert!(builder.dot_bucket.is_none());
+        assert!(builder.dot_token_lifetime.is_none());
+        assert!(builder.dot_sample_rate.is_none());
+        assert!(builder.retryable_status_codes.is_none());
+        assert!(builder.redirect_policy.is_none());
+        assert!(builder.key_rewrite_rules.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_range_reader_builder_with_cluster_name() -> Result<()> {
+        let config = MultipleClustersConfig::builder()
+            .add_cluster(
+                "/node1",
+                ConfigBuilder::new("test-ak-1", "test-sk-1", "test-bucket-1", None).build(),
+            )
+            .build();
+        let builder = config
+            .with_key("/node1/file", |config| {
+                build_range_reader_builder_from_config("/node1/file".to_owned(), config)
+            })
+            .unwrap();
+        assert_eq!(builder.bucket, "test-bucket-1");
+        assert_eq!(builder.cluster_name.as_deref(), Some("/node1"));
+
+        let builder = build_range_reader_builder_from_config(
+            "file".to_owned(),
+            &ConfigBuilder::new("test-ak-1", "test-sk-1", "test-bucket-1", None).build(),
+        );
+        assert!(builder.cluster_name.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_config_with_invalid_host_urls() -> Result<()> {
+        let path = Path::new("config.toml");
+        let config = Config::parse(
+            path,
+            br#"
+            access_key = "test-ak"
+            secret_key = "test-sk"
+            bucket = "test-bucket"
+            io_urls = ["http://io1.com/", "https://io2.com"]
+            "#,
+        )?;
+        assert_eq!(config.io_urls().map(|urls| urls.len()), Some(2));
+
+        let err = Config::parse(
+            path,
+            br#"
+            access_key = "test-ak"
+            secret_key = "test-sk"
+            bucket = "test-bucket"
+            io_urls = ["http://io1.com"]
+            uc_urls = ["uc.qbox.me"]
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ClustersConfigParseError::HostUrlError(HostUrlError::MissingScheme(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_config_with_unsupported_extension() -> Result<()> {
+        for err in [
+            Config::parse(Path::new("config.yaml"), b"").unwrap_err(),
+            Config::parse_with_defaults(Path::new("config"), b"", &Default::default()).unwrap_err(),
+            MultipleClustersConfig::parse(Path::new("config.ini"), b"").unwrap_err(),
+        ] {
+            assert!(matches!(
+                err,
+                ClustersConfigParseError::IOError(ref err) if err.kind() == IOErrorKind::InvalidInput
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_clusters_config_defaults() -> Result<()> {
+        env_logger::try_init().ok();
+
+        let tempdir = tempdir()?;
+        let cluster_path_1 = tempdir.path().join("cluster1.toml");
+        fs::write(
+            &cluster_path_1,
+            r#"
+            bucket = "test-bucket-1"
+            io_urls = ["http://io-11.com"]
+            retry = 3
+
+            [timeouts]
+            read_ms = 1000
+            "#,
+        )?;
+        let cluster_path_2 = tempdir.path().join("cluster2.json");
+        fs::write(
+            &cluster_path_2,
+            r#"{"access_key": "test-ak-2", "bucket": "test-bucket-2", "io_urls": null}"#,
+        )?;
+        let multi_config_path = tempdir.path().join("all.toml");
+        let multi_config_content = format!(
+            r#"
+            "/node1" = {:?}
+            "/node2" = {:?}
+
+            [defaults]
+            access_key = "test-ak"
+            secret_key = "test-sk"
+            io_urls = ["http://io-default.com"]
+            retry = 5
+
+            [defaults.timeouts]
+            connect_ms = 500
+            read_ms = 5000
+            "#,
+            cluster_path_1, cluster_path_2
+        );
+        let multi_config =
+            MultipleClustersConfig::parse(&multi_config_path, multi_config_content.as_bytes())?;
+
+        multi_config
+            .with_key("/node1/file", |config| {
+                assert_eq!(config.access_key(), "test-ak");
+                assert_eq!(config.secret_key(), "test-sk");
+                assert_eq!(config.bucket(), "test-bucket-1");
+                assert_eq!(config.io_urls(), Some(&["http://io-11.com".to_owned()][..]));
+                assert_eq!(config.retry(), Some(3));
+                let timeouts = config.timeouts().unwrap().defaults();
+                assert_eq!(timeouts.connect(), Some(Duration::from_millis(500)));
+                assert_eq!(timeouts.read(), Some(Duration::from_millis(1000)));
+                assert_eq!(config.original_path(), Some(cluster_path_1.as_path()));
+                assert_eq!(config.cluster_name(), Some("/node1"));
+            })
+            .unwrap();
+        multi_config
+            .with_key("/node2/file", |config| {
+                assert_eq!(config.access_key(), "test-ak-2");
+                assert_eq!(config.secret_key(), "test-sk");
+                assert_eq!(config.bucket(), "test-bucket-2");
+                assert_eq!(config.io_urls(), None);
+                assert_eq!(config.retry(), Some(5));
+            })
+            .unwrap();
+        Ok(())
+    }
+
     #[test]
     fn test_load_multi_clusters_config() -> Result<()> {
         env_logger::try_init().ok();
@@ -496,9 +1100,9 @@ mod tests {
                 .tempfile_in(tempdir.path())?;
             tempfile.write_all(&toml::to_vec(&config)?)?;
             tempfile.flush()?;
-            env::set_var(QINIU_MULTI_ENV, tempfile.path().as_os_str());
             tempfile.into_temp_path()
         };
+        let _multi_env_guard = QiniuMultiEnvGuard::new(tempfile_path.as_os_str());
         let _env_guard = QiniuEnvGuard::new(tempfile_path.as_os_str());
 
         with_current_qiniu_config_mut(|config| {
@@ -909,6 +1513,53 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_range_reader_from_env_with_integrity_metadata() -> Result<()> {
+        env_logger::try_init().ok();
+        let _defer = ResetFinally;
+
+        let runtime = tokio::runtime::Runtime::new()?;
+        let io_routes = path!("file").map(|| {
+            let mut resp = Response::new("1234567890".into());
+            resp.headers_mut()
+                .insert("ETag", HeaderValue::from_static("\"v1\""));
+            resp
+        });
+        let (io_addr, server) = {
+            let _guard = runtime.enter();
+            warp::serve(io_routes).bind_ephemeral(([127, 0, 0, 1], 0))
+        };
+        runtime.spawn(server);
+
+        let config = ConfigBuilder::new(
+            "test-ak-1",
+            "test-sk-1",
+            "test-bucket-1",
+            Some(vec![format!("http://{}", io_addr)]),
+        )
+        .use_getfile_api(Some(false))
+        .normalize_key(Some(true))
+        .integrity_metadata(Some(true))
+        .build();
+        let tempfile_path = {
+            let mut tempfile = TempFileBuilder::new().suffix(".toml").tempfile()?;
+            tempfile.write_all(&toml::to_vec(&config)?)?;
+            tempfile.flush()?;
+            tempfile.into_temp_path()
+        };
+        let _env_guard = QiniuEnvGuard::new(tempfile_path.as_os_str());
+
+        let downloader = RangeReader::from_env("file".to_owned()).unwrap();
+        assert!(downloader.is_async());
+        let dir = tempdir()?;
+        let path = dir.path().join("file");
+        assert_eq!(downloader.download_to_path(&path, None)?, 10);
+        assert_eq!(fs::read(&path)?, b"1234567890");
+        assert!(dir.path().join("file.qniu.meta").exists());
+
+        Ok(())
+    }
+
     #[test]
     fn test_range_reader_from_multi_clusters_config() -> Result<()> {
         env_logger::try_init().ok();
@@ -1,6 +1,7 @@
-use super::{single_cluster::Config, ClustersConfigParseError, Timeouts};
+use super::{single_cluster::Config, ClustersConfigParseError, HttpClientTimeouts};
 use once_cell::sync::Lazy;
 use serde::Deserialize;
+use serde_json::Value as JsonValue;
 use std::{
     collections::{HashMap, HashSet},
     convert::TryFrom,
@@ -21,8 +22,11 @@ static DEFAULT_CONFIG_SELECT_CALLBACK: Lazy<SelectConfigFn> =
     Lazy::new(|| Arc::new(default_select_config));
 
 /// 多集群七牛配置信息
+///
+/// 配置文件中每个集群名称对应一个单集群配置文件的路径，
+/// 可以通过 `defaults` 表设置所有集群共用的配置，单集群配置文件中设置的字段将覆盖其中的值
 #[derive(Clone, Deserialize)]
-#[serde(try_from = "HashMap<String, PathBuf>")]
+#[serde(try_from = "RawMultipleClustersConfig")]
 pub struct MultipleClustersConfig {
     configs: HashMap<String, Config>,
     original_path: Option<PathBuf>,
@@ -67,7 +71,7 @@ impl MultipleClustersConfig {
         match path.extension().and_then(|s| s.to_str()) {
             Some("toml") => toml::from_slice(bytes).map_err(|err| err.into()),
             Some("json") => serde_json::from_slice(bytes).map_err(|err| err.into()),
-            _ => panic!("QINIU env can only support to be given .toml or .json file"),
+            _ => Err(ClustersConfigParseError::unsupported_extension(path)),
         }
         .tap_ok_mut(|config: &mut Self| {
             config.original_path = Some(path.to_owned());
@@ -82,33 +86,53 @@ impl MultipleClustersConfig {
             .unwrap_or_default();
         paths.extend(
             self.configs
-                .iter()
-                .filter_map(|(_, config)| config.original_path())
+                .values()
+                .filter_map(|config| config.original_path())
                 .map(|path| path.to_owned()),
         );
         paths
     }
 
-    pub(super) fn timeouts_set(&self) -> HashSet<Timeouts> {
-        self.configs.values().map(Timeouts::from).collect()
+    pub(super) fn timeouts_set(&self) -> HashSet<HttpClientTimeouts> {
+        self.configs
+            .values()
+            .map(HttpClientTimeouts::from)
+            .collect()
     }
 }
 
-impl TryFrom<HashMap<String, PathBuf>> for MultipleClustersConfig {
+#[derive(Deserialize)]
+struct RawMultipleClustersConfig {
+    #[serde(default)]
+    defaults: Option<JsonValue>,
+    #[serde(flatten)]
+    configs: HashMap<String, PathBuf>,
+}
+
+impl TryFrom<RawMultipleClustersConfig> for MultipleClustersConfig {
     type Error = MultipleClustersConfigParseError;
 
-    fn try_from(configs: HashMap<String, PathBuf>) -> Result<Self, Self::Error> {
+    fn try_from(raw: RawMultipleClustersConfig) -> Result<Self, Self::Error> {
+        let defaults = raw.defaults;
         Ok(Self {
-            configs: configs
+            configs: raw
+                .configs
                 .into_iter()
                 .map(|(name, path)| {
                     fs::read(&path)
                         .map_err(MultipleClustersConfigParseError::from)
                         .and_then(|bytes| {
-                            Config::parse(&path, &bytes)
-                                .map_err(MultipleClustersConfigParseError::from)
+                            if let Some(defaults) = defaults.as_ref() {
+                                Config::parse_with_defaults(&path, &bytes, defaults)
+                            } else {
+                                Config::parse(&path, &bytes)
+                            }
+                            .map_err(MultipleClustersConfigParseError::from)
+                        })
+                        .map(|mut config| {
+                            config.set_cluster_name(Some(name.to_owned()));
+                            (name, config)
                         })
-                        .map(|config| (name, config))
                 })
                 .collect::<Result<_, _>>()?,
             original_path: None,
@@ -174,8 +198,10 @@ impl MultipleClustersConfigBuilder {
 
     /// 增加集群配置
     #[inline]
-    pub fn add_cluster(mut self, name: impl Into<String>, config: Config) -> Self {
-        self.0.configs.insert(name.into(), config);
+    pub fn add_cluster(mut self, name: impl Into<String>, mut config: Config) -> Self {
+        let name = name.into();
+        config.set_cluster_name(Some(name.to_owned()));
+        self.0.configs.insert(name, config);
         self
     }
 
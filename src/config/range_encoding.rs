@@ -0,0 +1,147 @@
+use reqwest::{blocking::RequestBuilder as SyncRequestBuilder, Method, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+const RANGE_QUERY_PARAM: &str = "range";
+
+/// 范围请求的编码方式
+///
+/// 对应配置文件中的 `range_request_encoding` 字段，例如
+///
+/// ```toml
+/// range_request_encoding = "json_body"
+/// ```
+///
+/// 部分私有化部署的网关不支持 `Range` 请求头，可以改为通过查询参数或请求体传递范围
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RangeRequestEncoding {
+    /// 通过 `Range` 请求头传递范围，例如 `Range: bytes=0-4,5-9`
+    Header,
+    /// 通过 `range` 查询参数传递范围，例如 `?range=bytes%3D0-4%2C5-9`
+    ///
+    /// 查询参数将在下载 URL 签名后追加，网关需要在校验签名前移除该参数
+    QueryParam,
+    /// 使用 POST 方法，通过 JSON 请求体传递范围，例如 `{"ranges":["0-4","5-9"]}`
+    ///
+    /// 每个范围的格式与 `Range` 请求头中的相同，因此可以是 `5-` 或 `-10` 这样的开放范围
+    JsonBody,
+}
+
+impl Default for RangeRequestEncoding {
+    #[inline]
+    fn default() -> Self {
+        Self::Header
+    }
+}
+
+impl RangeRequestEncoding {
+    /// 携带范围的请求使用的 HTTP 方法
+    pub(crate) fn method(self) -> Method {
+        match self {
+            Self::JsonBody => Method::POST,
+            _ => Method::GET,
+        }
+    }
+
+    /// 将 `bytes=` 格式的范围编码到请求中
+    pub(crate) fn encode<B: RangeRequestBuilder>(self, builder: B, range: &str) -> B {
+        match self {
+            Self::Header => builder.range_header(range),
+            Self::QueryParam => builder.range_query(range),
+            Self::JsonBody => builder.range_json_body(&RangeRequestBody::new(range)),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct RangeRequestBody<'a> {
+    ranges: Vec<&'a str>,
+}
+
+impl<'a> RangeRequestBody<'a> {
+    fn new(range: &'a str) -> Self {
+        Self {
+            ranges: range.trim_start_matches("bytes=").split(',').collect(),
+        }
+    }
+}
+
+/// 可以携带范围的 HTTP 请求构建器，同时适用于异步和同步的 reqwest 客户端
+pub(crate) trait RangeRequestBuilder: Sized {
+    fn range_header(self, range: &str) -> Self;
+    fn range_query(self, range: &str) -> Self;
+    fn range_json_body(self, body: &RangeRequestBody<'_>) -> Self;
+}
+
+impl RangeRequestBuilder for RequestBuilder {
+    #[inline]
+    fn range_header(self, range: &str) -> Self {
+        self.header(reqwest::header::RANGE, range)
+    }
+
+    #[inline]
+    fn range_query(self, range: &str) -> Self {
+        self.query(&[(RANGE_QUERY_PARAM, range)])
+    }
+
+    #[inline]
+    fn range_json_body(self, body: &RangeRequestBody<'_>) -> Self {
+        self.json(body)
+    }
+}
+
+impl RangeRequestBuilder for SyncRequestBuilder {
+    #[inline]
+    fn range_header(self, range: &str) -> Self {
+        self.header(reqwest::header::RANGE, range)
+    }
+
+    #[inline]
+    fn range_query(self, range: &str) -> Self {
+        self.query(&[(RANGE_QUERY_PARAM, range)])
+    }
+
+    #[inline]
+    fn range_json_body(self, body: &RangeRequestBody<'_>) -> Self {
+        self.json(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::{header::RANGE, Client};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_range_request_encoding() {
+        let client = Client::new();
+        let encode = |encoding: RangeRequestEncoding| {
+            encoding
+                .encode(
+                    client.request(encoding.method(), "http://io.example.com/key"),
+                    "bytes=0-4,5-",
+                )
+                .build()
+                .unwrap()
+        };
+
+        let request = encode(RangeRequestEncoding::Header);
+        assert_eq!(request.method(), Method::GET);
+        assert_eq!(request.headers().get(RANGE).unwrap(), "bytes=0-4,5-");
+        assert!(request.body().is_none());
+
+        let request = encode(RangeRequestEncoding::QueryParam);
+        assert_eq!(request.method(), Method::GET);
+        assert!(request.headers().get(RANGE).is_none());
+        assert_eq!(request.url().query(), Some("range=bytes%3D0-4%2C5-"));
+
+        let request = encode(RangeRequestEncoding::JsonBody);
+        assert_eq!(request.method(), Method::POST);
+        assert!(request.headers().get(RANGE).is_none());
+        let body: Value =
+            serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(body, json!({"ranges": ["0-4", "5-"]}));
+    }
+}
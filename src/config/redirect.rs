@@ -0,0 +1,108 @@
+use reqwest::redirect::Policy;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// 下载请求的重定向策略
+///
+/// 对应配置文件中的 `[redirect_policy]` 表，通过 `kind` 字段选择策略，例如
+///
+/// ```toml
+/// [redirect_policy]
+/// kind = "same_host"
+/// max_redirects = 3
+/// ```
+///
+/// 跟随重定向时将保留 `Range` 等请求头，私有空间的下载凭证位于 URL 中，由重定向地址决定是否携带
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RedirectPolicy {
+    /// 不跟随重定向，直接将重定向响应作为非预期的状态码处理
+    None,
+    /// 最多跟随指定次数的重定向
+    Limited {
+        /// 最大重定向次数
+        max_redirects: usize,
+    },
+    /// 仅跟随重定向到同一域名的重定向，且最多跟随指定次数
+    SameHost {
+        /// 最大重定向次数
+        max_redirects: usize,
+    },
+}
+
+impl Default for RedirectPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::Limited {
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+        }
+    }
+}
+
+impl RedirectPolicy {
+    /// 创建最多跟随指定次数重定向的策略
+    #[inline]
+    pub fn limited(max_redirects: usize) -> Self {
+        Self::Limited { max_redirects }
+    }
+
+    /// 创建仅跟随同一域名内重定向的策略
+    #[inline]
+    pub fn same_host(max_redirects: usize) -> Self {
+        Self::SameHost { max_redirects }
+    }
+
+    pub(crate) fn to_reqwest_policy(self) -> Policy {
+        match self {
+            Self::None => Policy::none(),
+            Self::Limited { max_redirects } => Policy::limited(max_redirects),
+            Self::SameHost { max_redirects } => Policy::custom(move |attempt| {
+                if attempt.previous().len() > max_redirects {
+                    return attempt.error("too many redirects");
+                }
+                let same_host = attempt
+                    .previous()
+                    .first()
+                    .map_or(true, |first| first.origin() == attempt.url().origin());
+                if same_host {
+                    attempt.follow()
+                } else {
+                    attempt.stop()
+                }
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect_policy_from_toml() -> anyhow::Result<()> {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            redirect_policy: RedirectPolicy,
+        }
+        let wrapper: Wrapper = toml::from_str(
+            r#"
+            [redirect_policy]
+            kind = "same_host"
+            max_redirects = 3
+            "#,
+        )?;
+        assert_eq!(wrapper.redirect_policy, RedirectPolicy::same_host(3));
+
+        let wrapper: Wrapper = toml::from_str(
+            r#"
+            [redirect_policy]
+            kind = "none"
+            "#,
+        )?;
+        assert_eq!(wrapper.redirect_policy, RedirectPolicy::None);
+        assert_eq!(RedirectPolicy::default(), RedirectPolicy::limited(10));
+        Ok(())
+    }
+}
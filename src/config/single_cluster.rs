@@ -1,11 +1,14 @@
 use super::{
     super::{async_api::RangeReaderHandle as AsyncRangeReaderHandle, sync_api::RangeReaderInner},
-    ClustersConfigParseError, Timeouts,
+    parse_host_urls, ClustersConfigParseError, ConnectionFailureKind, FailoverBackoff,
+    HostUrlError, HttpClientTimeouts, KeyRewriteRule, RangeRequestEncoding, RedirectPolicy,
+    TimeoutsConfig,
 };
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     convert::TryInto,
     path::{Path, PathBuf},
     sync::Arc,
@@ -26,6 +29,9 @@ pub struct Config {
     #[serde(alias = "io_hosts")]
     io_urls: Option<Vec<String>>,
 
+    #[serde(alias = "cdn_hosts")]
+    cdn_urls: Option<Vec<String>>,
+
     #[serde(alias = "uc_hosts")]
     uc_urls: Option<Vec<String>>,
 
@@ -33,15 +39,64 @@ pub struct Config {
     monitor_urls: Option<Vec<String>>,
 
     sim: Option<bool>,
+    path_prefix: Option<String>,
     normalize_key: Option<bool>,
     private: Option<bool>,
     retry: Option<usize>,
+    update_hosts_retry: Option<usize>,
+    min_hosts_on_update: Option<usize>,
+    max_host_change_ratio: Option<Ratio>,
     dot_interval_s: Option<u64>,
     max_dot_buffer_size: Option<u64>,
+    monitor_access_key: Option<String>,
+    monitor_secret_key: Option<String>,
+    monitor_bucket: Option<String>,
+    dot_token_lifetime_s: Option<u64>,
+    dot_sample_rate: Option<u32>,
+    dot_sampling_threshold: Option<u64>,
+    dot_latency_buckets_ms: Option<Vec<u64>>,
+    dot_sync_runtime_load: Option<bool>,
+    dot_stage_timings: Option<bool>,
     punish_time_s: Option<u64>,
+    negative_cache_ttl_s: Option<u64>,
+    head_cache_window_ms: Option<u64>,
+    preload_cache_size: Option<u64>,
+    preload_cache_ttl_s: Option<u64>,
+    respect_cache_control: Option<bool>,
+    force_https: Option<bool>,
+    random_seed: Option<u64>,
+    verify_with_second_host_rate: Option<u32>,
+    preferred_idc: Option<String>,
     base_timeout_ms: Option<u64>,
+    max_timeout_ms: Option<u64>,
+    host_base_timeouts_ms: Option<HashMap<String, u64>>,
     dial_timeout_ms: Option<u64>,
+    dns_cache_min_ttl_s: Option<u64>,
+    dns_cache_max_ttl_s: Option<u64>,
+    redirect_policy: Option<RedirectPolicy>,
     max_retry_concurrency: Option<u32>,
+    timeouts: Option<TimeoutsConfig>,
+    max_object_size: Option<u64>,
+    strict_body_size: Option<bool>,
+    max_response_header_size: Option<usize>,
+    strict_header_validation: Option<bool>,
+    disable_read_multi_ranges: Option<bool>,
+    range_request_encoding: Option<RangeRequestEncoding>,
+    integrity_metadata: Option<bool>,
+    preserve_last_modified: Option<bool>,
+    download_buffer_size: Option<u64>,
+    max_multi_ranges: Option<usize>,
+    max_multi_ranges_size: Option<u64>,
+    disable_download: Option<bool>,
+    decompress_content_encoding: Option<bool>,
+    strict: Option<bool>,
+    failover_backoff: Option<FailoverBackoff>,
+    connection_failure_kinds: Option<Vec<ConnectionFailureKind>>,
+    retryable_status_codes: Option<Vec<u16>>,
+    object_level_status_codes: Option<Vec<u16>>,
+    key_rewrite_rules: Option<Vec<KeyRewriteRule>>,
+    sync_request_timeout_ms: Option<u64>,
+    sync_worker_threads: Option<usize>,
 
     #[serde(skip)]
     extra: Extra,
@@ -67,15 +122,56 @@ impl Config {
 
     pub(super) fn parse(path: &Path, bytes: &[u8]) -> Result<Self, ClustersConfigParseError> {
         match path.extension().and_then(|s| s.to_str()) {
-            Some("toml") => toml::from_slice(bytes).map_err(|err| err.into()),
-            Some("json") => serde_json::from_slice(bytes).map_err(|err| err.into()),
-            _ => panic!("QINIU env can only support to be given .toml or .json file"),
+            Some("toml") => toml::from_slice(bytes).map_err(ClustersConfigParseError::from),
+            Some("json") => serde_json::from_slice(bytes).map_err(ClustersConfigParseError::from),
+            _ => Err(ClustersConfigParseError::unsupported_extension(path)),
         }
+        .and_then(|config: Self| {
+            config.validate_host_urls()?;
+            Ok(config)
+        })
         .tap_ok_mut(|config: &mut Self| {
             config.extra.original_path = Some(path.to_owned());
         })
     }
 
+    /// 解析配置文件，配置文件中未设置的字段继承 `defaults` 中的值
+    ///
+    /// 表逐个字段递归合并，其他类型的值（包括数组）由配置文件中的值整体覆盖
+    pub(super) fn parse_with_defaults(
+        path: &Path,
+        bytes: &[u8],
+        defaults: &JsonValue,
+    ) -> Result<Self, ClustersConfigParseError> {
+        let overrides: JsonValue = match path.extension().and_then(|s| s.to_str()) {
+            Some("toml") => toml::from_slice(bytes)?,
+            Some("json") => serde_json::from_slice(bytes)?,
+            _ => return Err(ClustersConfigParseError::unsupported_extension(path)),
+        };
+        let mut merged = defaults.to_owned();
+        merge_config_value(&mut merged, overrides);
+        let mut config: Self = serde_json::from_value(merged)?;
+        config.validate_host_urls()?;
+        config.extra.original_path = Some(path.to_owned());
+        Ok(config)
+    }
+
+    /// 检查配置中的所有域名 URL 是否合法
+    pub(super) fn validate_host_urls(&self) -> Result<(), HostUrlError> {
+        for urls in [
+            &self.io_urls,
+            &self.cdn_urls,
+            &self.uc_urls,
+            &self.monitor_urls,
+        ]
+        .iter()
+        .filter_map(|urls| urls.as_ref())
+        {
+            parse_host_urls(urls, false)?;
+        }
+        Ok(())
+    }
+
     /// 获取七牛 Access Key
     #[inline]
     pub fn access_key(&self) -> &str {
@@ -132,6 +228,23 @@ impl Config {
         self
     }
 
+    /// 获取 CDN 域名 URL 列表
+    #[inline]
+    pub fn cdn_urls(&self) -> Option<&[String]> {
+        self.cdn_urls.as_ref().map(|urls| urls.as_ref())
+    }
+
+    /// 设置 CDN 域名 URL 列表
+    ///
+    /// 设置后将优先通过 CDN 下载，CDN 返回错误或过期内容时再回源到 IO 服务器。
+    /// CDN 域名与 IO 服务器域名分别惩罚，并以 `cdn_getfile` 单独打点。最大并行重试次数为 0 时不支持
+    #[inline]
+    pub fn set_cdn_urls(&mut self, cdn_urls: Option<impl Into<Vec<String>>>) -> &mut Self {
+        self.cdn_urls = cdn_urls.map(|urls| urls.into());
+        self.uninit_range_reader_inner();
+        self
+    }
+
     /// 获取 UC 服务器 URL 列表
     #[inline]
     pub fn uc_urls(&self) -> Option<&[String]> {
@@ -174,6 +287,20 @@ impl Config {
         self
     }
 
+    /// 获取下载地址的路径前缀
+    #[inline]
+    pub fn path_prefix(&self) -> Option<&str> {
+        self.path_prefix.as_deref()
+    }
+
+    /// 设置下载地址的路径前缀
+    #[inline]
+    pub fn set_path_prefix(&mut self, path_prefix: Option<String>) -> &mut Self {
+        self.path_prefix = path_prefix;
+        self.uninit_range_reader_inner();
+        self
+    }
+
     /// 是否对 key 进行格式化
     #[inline]
     pub fn normalize_key(&self) -> Option<bool> {
@@ -216,6 +343,48 @@ impl Config {
         self
     }
 
+    /// 获取更新 IO 域名列表的重试次数
+    #[inline]
+    pub fn update_hosts_retry(&self) -> Option<usize> {
+        self.update_hosts_retry
+    }
+
+    /// 设置更新 IO 域名列表的重试次数
+    #[inline]
+    pub fn set_update_hosts_retry(&mut self, update_hosts_retry: Option<usize>) -> &mut Self {
+        self.update_hosts_retry = update_hosts_retry;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取更新 IO 域名列表时要求的最少域名数量
+    #[inline]
+    pub fn min_hosts_on_update(&self) -> Option<usize> {
+        self.min_hosts_on_update
+    }
+
+    /// 设置更新 IO 域名列表时要求的最少域名数量
+    #[inline]
+    pub fn set_min_hosts_on_update(&mut self, min_hosts_on_update: Option<usize>) -> &mut Self {
+        self.min_hosts_on_update = min_hosts_on_update;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取更新 IO 域名列表时允许移除的域名最大比例
+    #[inline]
+    pub fn max_host_change_ratio(&self) -> Option<f64> {
+        self.max_host_change_ratio.map(|ratio| ratio.0)
+    }
+
+    /// 设置更新 IO 域名列表时允许移除的域名最大比例
+    #[inline]
+    pub fn set_max_host_change_ratio(&mut self, max_host_change_ratio: Option<f64>) -> &mut Self {
+        self.max_host_change_ratio = max_host_change_ratio.map(Ratio);
+        self.uninit_range_reader_inner();
+        self
+    }
+
     /// 获取打点记录上传频率
     #[inline]
     pub fn dot_interval(&self) -> Option<Duration> {
@@ -244,279 +413,1448 @@ impl Config {
         self
     }
 
-    /// 获取域名访问失败后的惩罚时长
+    /// 获取上传打点记录所用的 Access Key
     #[inline]
-    pub fn punish_time(&self) -> Option<Duration> {
-        self.punish_time_s.map(Duration::from_secs)
+    pub fn monitor_access_key(&self) -> Option<&str> {
+        self.monitor_access_key.as_deref()
     }
 
-    /// 设置域名访问失败后的惩罚时长
+    /// 获取上传打点记录所用的 Secret Key
     #[inline]
-    pub fn set_punish_time(&mut self, punish_time: Option<Duration>) -> &mut Self {
-        self.punish_time_s = punish_time.map(|d| d.as_secs());
+    pub fn monitor_secret_key(&self) -> Option<&str> {
+        self.monitor_secret_key.as_deref()
+    }
+
+    /// 设置上传打点记录所用的凭证，不设置则使用下载所用的凭证
+    #[inline]
+    pub fn set_monitor_credential(
+        &mut self,
+        monitor_access_key: Option<impl Into<String>>,
+        monitor_secret_key: Option<impl Into<String>>,
+    ) -> &mut Self {
+        self.monitor_access_key = monitor_access_key.map(|ak| ak.into());
+        self.monitor_secret_key = monitor_secret_key.map(|sk| sk.into());
         self.uninit_range_reader_inner();
         self
     }
 
-    /// 获取域名访问的基础超时时长
+    /// 获取上传打点记录的存储空间
     #[inline]
-    pub fn base_timeout(&self) -> Option<Duration> {
-        self.base_timeout_ms.map(Duration::from_millis)
+    pub fn monitor_bucket(&self) -> Option<&str> {
+        self.monitor_bucket.as_deref()
     }
 
-    /// 设置域名访问失败后的惩罚时长
+    /// 设置上传打点记录的存储空间，不设置则使用下载所用的存储空间
     #[inline]
-    pub fn set_base_timeout(&mut self, base_timeout: Option<Duration>) -> &mut Self {
-        self.base_timeout_ms = base_timeout.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
+    pub fn set_monitor_bucket(&mut self, monitor_bucket: Option<impl Into<String>>) -> &mut Self {
+        self.monitor_bucket = monitor_bucket.map(|bucket| bucket.into());
         self.uninit_range_reader_inner();
         self
     }
 
-    /// 获取域名连接的超时时长
+    /// 获取上传打点记录所用的上传凭证有效期
     #[inline]
-    pub fn connect_timeout(&self) -> Option<Duration> {
-        self.dial_timeout_ms.map(Duration::from_millis)
+    pub fn dot_token_lifetime(&self) -> Option<Duration> {
+        self.dot_token_lifetime_s.map(Duration::from_secs)
     }
 
-    /// 设置域名连接的超时时长
+    /// 设置上传打点记录所用的上传凭证有效期
     #[inline]
-    pub fn set_connect_timeout(&mut self, connect_timeout: Option<Duration>) -> &mut Self {
-        self.dial_timeout_ms =
-            connect_timeout.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
+    pub fn set_dot_token_lifetime(&mut self, dot_token_lifetime: Option<Duration>) -> &mut Self {
+        self.dot_token_lifetime_s = dot_token_lifetime.map(|d| d.as_secs());
         self.uninit_range_reader_inner();
         self
     }
 
-    /// 获取最大并行重试次数
+    /// 获取打点采样率
     #[inline]
-    pub fn max_retry_concurrency(&self) -> Option<u32> {
-        self.max_retry_concurrency
+    pub fn dot_sample_rate(&self) -> Option<u32> {
+        self.dot_sample_rate
     }
 
-    /// 设置最大并行重试次数，如果设置为 Some(0) 则表示禁止并行重试功能
+    /// 设置打点采样率，每 `dot_sample_rate` 次 API 调用仅记录一次
     #[inline]
-    pub fn set_max_retry_concurrency(&mut self, max_retry_concurrency: Option<u32>) -> &mut Self {
-        self.max_retry_concurrency = max_retry_concurrency;
+    pub fn set_dot_sample_rate(&mut self, dot_sample_rate: Option<u32>) -> &mut Self {
+        self.dot_sample_rate = dot_sample_rate;
         self.uninit_range_reader_inner();
         self
     }
 
-    pub(super) fn original_path(&self) -> Option<&Path> {
-        self.extra.original_path.as_ref().map(|p| p.as_ref())
+    /// 获取自动提高打点采样率的本地缓存文件尺寸阈值
+    #[inline]
+    pub fn dot_sampling_threshold(&self) -> Option<u64> {
+        self.dot_sampling_threshold
     }
 
-    #[allow(dead_code)]
-    pub(super) fn original_path_mut(&mut self) -> &mut Option<PathBuf> {
-        &mut self.extra.original_path
+    /// 设置自动提高打点采样率的本地缓存文件尺寸阈值
+    ///
+    /// 缓存文件超过该尺寸时采样率自动翻倍，回落到阈值的一半以下后恢复
+    #[inline]
+    pub fn set_dot_sampling_threshold(&mut self, dot_sampling_threshold: Option<u64>) -> &mut Self {
+        self.dot_sampling_threshold = dot_sampling_threshold;
+        self.uninit_range_reader_inner();
+        self
     }
 
-    pub(super) fn config_paths(&self) -> Vec<PathBuf> {
-        self.extra
-            .original_path
-            .as_ref()
-            .map(|path| vec![path.to_owned()])
-            .unwrap_or_default()
+    /// 获取打点记录中耗时直方图的分桶边界，单位为毫秒
+    #[inline]
+    pub fn dot_latency_buckets_ms(&self) -> Option<&[u64]> {
+        self.dot_latency_buckets_ms.as_deref()
     }
 
-    pub(super) fn timeouts_set(&self) -> HashSet<Timeouts> {
-        let mut set = HashSet::with_capacity(1);
-        set.insert(Timeouts::from(self));
-        set
+    /// 设置打点记录中耗时直方图的分桶边界，单位为毫秒
+    ///
+    /// 设置后打点记录将包含耗时直方图，需要监控服务支持该格式
+    #[inline]
+    pub fn set_dot_latency_buckets_ms(
+        &mut self,
+        dot_latency_buckets_ms: Option<Vec<u64>>,
+    ) -> &mut Self {
+        self.dot_latency_buckets_ms = dot_latency_buckets_ms;
+        self.uninit_range_reader_inner();
+        self
     }
 
-    pub(crate) fn get_or_init_range_reader_inner(
-        &self,
-        f: impl FnOnce() -> Arc<RangeReaderInner>,
-    ) -> Arc<RangeReaderInner> {
-        self.extra.range_reader_inner.get_or_init(f).to_owned()
+    /// 是否记录同步接口内部运行时的负载打点
+    #[inline]
+    pub fn dot_sync_runtime_load(&self) -> Option<bool> {
+        self.dot_sync_runtime_load
     }
 
-    pub(crate) fn get_or_init_async_range_reader_inner(
-        &self,
-        f: impl FnOnce() -> AsyncRangeReaderHandle,
-    ) -> AsyncRangeReaderHandle {
-        self.extra
-            .async_range_reader_inner
-            .get_or_init(f)
-            .to_owned()
+    /// 设置是否记录同步接口内部运行时的负载打点
+    ///
+    /// 启用后打点记录将包含同步接口内部运行时的请求并发数和排队请求数峰值，需要监控服务支持该格式
+    #[inline]
+    pub fn set_dot_sync_runtime_load(&mut self, dot_sync_runtime_load: Option<bool>) -> &mut Self {
+        self.dot_sync_runtime_load = dot_sync_runtime_load;
+        self.uninit_range_reader_inner();
+        self
     }
 
-    fn uninit_range_reader_inner(&mut self) {
-        self.extra.range_reader_inner.take();
-        self.extra.async_range_reader_inner.take();
+    /// 是否记录 API 调用各阶段耗时的打点
+    #[inline]
+    pub fn dot_stage_timings(&self) -> Option<bool> {
+        self.dot_stage_timings
     }
-}
 
-/// 七牛单集群配置信息构建器
-#[derive(Debug)]
-pub struct ConfigBuilder(Config);
+    /// 设置是否记录 API 调用各阶段耗时的打点
+    ///
+    /// 启用后打点记录将包含每个 API 最后一次请求的选择域名、首字节、传输、解析和写缓存的平均耗时，需要监控服务支持该格式
+    #[inline]
+    pub fn set_dot_stage_timings(&mut self, dot_stage_timings: Option<bool>) -> &mut Self {
+        self.dot_stage_timings = dot_stage_timings;
+        self.uninit_range_reader_inner();
+        self
+    }
 
-/// 七牛单集群配置信息构建器
-pub type SingleClusterConfigBuilder = ConfigBuilder;
+    /// 获取域名访问失败后的惩罚时长
+    #[inline]
+    pub fn punish_time(&self) -> Option<Duration> {
+        self.punish_time_s.map(Duration::from_secs)
+    }
 
-impl ConfigBuilder {
-    /// 创建七牛配置信息构建器
-    pub fn new(
-        access_key: impl Into<String>,
-        secret_key: impl Into<String>,
-        bucket: impl Into<String>,
-        io_urls: Option<Vec<String>>,
-    ) -> Self {
-        Self(Config {
-            access_key: access_key.into(),
-            secret_key: secret_key.into(),
-            bucket: bucket.into(),
-            io_urls,
-            ..Default::default()
-        })
+    /// 设置域名访问失败后的惩罚时长
+    #[inline]
+    pub fn set_punish_time(&mut self, punish_time: Option<Duration>) -> &mut Self {
+        self.punish_time_s = punish_time.map(|d| d.as_secs());
+        self.uninit_range_reader_inner();
+        self
     }
 
-    /// 构建七牛配置信息
+    /// 获取对象不存在的缓存有效期
     #[inline]
-    pub fn build(self) -> Config {
-        self.0
+    pub fn negative_cache_ttl(&self) -> Option<Duration> {
+        self.negative_cache_ttl_s.map(Duration::from_secs)
     }
 
-    /// 配置七牛 Access Key
+    /// 设置对象不存在的缓存有效期
     #[inline]
-    pub fn access_key(mut self, access_key: impl Into<String>) -> Self {
-        self.0.access_key = access_key.into();
+    pub fn set_negative_cache_ttl(&mut self, negative_cache_ttl: Option<Duration>) -> &mut Self {
+        self.negative_cache_ttl_s = negative_cache_ttl.map(|d| d.as_secs());
+        self.uninit_range_reader_inner();
         self
     }
 
-    /// 配置七牛 Secret Key
+    /// 获取复用 HEAD 请求结果的时间窗口
     #[inline]
-    pub fn secret_key(mut self, secret_key: impl Into<String>) -> Self {
-        self.0.secret_key = secret_key.into();
-        self
+    pub fn head_cache_window(&self) -> Option<Duration> {
+        self.head_cache_window_ms.map(Duration::from_millis)
     }
 
-    /// 配置七牛存储空间
+    /// 设置复用 HEAD 请求结果的时间窗口
     #[inline]
-    pub fn bucket(mut self, bucket: impl Into<String>) -> Self {
-        self.0.bucket = bucket.into();
+    pub fn set_head_cache_window(&mut self, head_cache_window: Option<Duration>) -> &mut Self {
+        self.head_cache_window_ms =
+            head_cache_window.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
+        self.uninit_range_reader_inner();
         self
     }
 
-    /// 配置 IO 服务器 URL 列表
+    /// 获取预加载缓存容量
     #[inline]
-    pub fn io_urls(mut self, io_urls: Option<Vec<String>>) -> Self {
-        self.0.io_urls = io_urls;
-        self
+    pub fn preload_cache_size(&self) -> Option<u64> {
+        self.preload_cache_size
     }
 
-    /// 配置 UC 服务器域名列表
+    /// 设置预加载缓存容量
     #[inline]
-    pub fn uc_urls(mut self, uc_urls: Option<Vec<String>>) -> Self {
-        self.0.uc_urls = uc_urls;
+    pub fn set_preload_cache_size(&mut self, preload_cache_size: Option<u64>) -> &mut Self {
+        self.preload_cache_size = preload_cache_size;
+        self.uninit_range_reader_inner();
         self
     }
 
-    /// 配置监控服务器域名列表，如果不配置或配置为空，则不会启用打点功能
+    /// 获取预加载数据的有效期
     #[inline]
-    pub fn monitor_urls(mut self, monitor_urls: Option<Vec<String>>) -> Self {
-        self.0.monitor_urls = monitor_urls;
-        self
+    pub fn preload_cache_ttl(&self) -> Option<Duration> {
+        self.preload_cache_ttl_s.map(Duration::from_secs)
     }
 
-    /// 是否使用 Getfile API，默认为 true
+    /// 设置预加载数据的有效期
     #[inline]
-    pub fn use_getfile_api(mut self, use_getfile_api: Option<bool>) -> Self {
-        self.0.sim = use_getfile_api.map(|b| !b);
+    pub fn set_preload_cache_ttl(&mut self, preload_cache_ttl: Option<Duration>) -> &mut Self {
+        self.preload_cache_ttl_s = preload_cache_ttl.map(|d| d.as_secs());
+        self.uninit_range_reader_inner();
         self
     }
 
-    /// 是否对 key 进行格式化，默认为 false
+    /// 是否遵循响应中的缓存指令
     #[inline]
-    pub fn normalize_key(mut self, normalize_key: Option<bool>) -> Self {
-        self.0.normalize_key = normalize_key;
-        self
+    pub fn respect_cache_control(&self) -> Option<bool> {
+        self.respect_cache_control
     }
 
-    /// 是否使用私有存储空间，默认不使用
+    /// 设置是否遵循响应中的缓存指令
     #[inline]
-    pub fn private(mut self, private: Option<bool>) -> Self {
-        self.0.private = private;
+    pub fn set_respect_cache_control(&mut self, respect_cache_control: Option<bool>) -> &mut Self {
+        self.respect_cache_control = respect_cache_control;
+        self.uninit_range_reader_inner();
         self
     }
 
-    /// 配置 IO 和 UC 服务器访问重试次数，默认为 10
+    /// 是否强制使用 HTTPS 协议访问所有域名
     #[inline]
-    pub fn retry(mut self, retry: Option<usize>) -> Self {
-        self.0.retry = retry;
-        self
+    pub fn force_https(&self) -> Option<bool> {
+        self.force_https
     }
 
-    /// 配置域名访问失败后的惩罚时长，默认为 30 分钟
+    /// 设置是否强制使用 HTTPS 协议访问所有域名
     #[inline]
-    pub fn punish_duration(mut self, punish_duration: Option<Duration>) -> Self {
-        self.0.punish_time_s = punish_duration.map(|d| d.as_secs());
+    pub fn set_force_https(&mut self, force_https: Option<bool>) -> &mut Self {
+        self.force_https = force_https;
+        self.uninit_range_reader_inner();
         self
     }
 
-    /// 配置域名访问的基础超时时长，默认为 3000 毫秒
+    /// 获取随机数种子
     #[inline]
-    pub fn base_timeout(mut self, base_timeout: Option<Duration>) -> Self {
-        self.0.base_timeout_ms = base_timeout.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
-        self
+    pub fn random_seed(&self) -> Option<u64> {
+        self.random_seed
     }
 
-    /// 配置域名连接的超时时长，默认为 50 毫秒
+    /// 设置随机数种子
     #[inline]
-    pub fn connect_timeout(mut self, connect_timeout: Option<Duration>) -> Self {
-        self.0.dial_timeout_ms =
-            connect_timeout.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
+    pub fn set_random_seed(&mut self, random_seed: Option<u64>) -> &mut Self {
+        self.random_seed = random_seed;
+        self.uninit_range_reader_inner();
         self
     }
 
-    /// 配置最大并行重试次数，默认为 5，如果设置为 Some(0) 则表示禁止并行重试功能
+    /// 获取从第二个域名验证读取结果的采样率
     #[inline]
-    pub fn max_retry_concurrency(mut self, max_retry_concurrency: Option<u32>) -> Self {
-        self.0.max_retry_concurrency = max_retry_concurrency;
-        self
+    pub fn verify_with_second_host_rate(&self) -> Option<u32> {
+        self.verify_with_second_host_rate
     }
 
-    /// 设置打点记录上传频率，默认为 10 秒
+    /// 设置从第二个域名验证读取结果的采样率，每 `verify_with_second_host_rate` 次读取验证一次
     #[inline]
-    pub fn dot_interval(mut self, dot_interval: Option<Duration>) -> Self {
-        self.0.dot_interval_s = dot_interval.map(|d| d.as_secs());
+    pub fn set_verify_with_second_host_rate(
+        &mut self,
+        verify_with_second_host_rate: Option<u32>,
+    ) -> &mut Self {
+        self.verify_with_second_host_rate = verify_with_second_host_rate;
+        self.uninit_range_reader_inner();
         self
     }
 
-    /// 设置打点记录本地缓存文件尺寸上限，默认为 1 MB
+    /// 获取优先使用的区域或机房标签
     #[inline]
-    pub fn max_dot_buffer_size(mut self, max_dot_buffer_size: Option<u64>) -> Self {
-        self.0.max_dot_buffer_size = max_dot_buffer_size;
-        self
+    pub fn preferred_idc(&self) -> Option<&str> {
+        self.preferred_idc.as_deref()
     }
 
+    /// 设置优先使用的区域或机房标签
     #[inline]
-    #[cfg(test)]
-    pub(super) fn original_path(mut self, original_path: Option<PathBuf>) -> Self {
-        self.0.extra.original_path = original_path;
+    pub fn set_preferred_idc(&mut self, preferred_idc: Option<String>) -> &mut Self {
+        self.preferred_idc = preferred_idc;
+        self.uninit_range_reader_inner();
         self
     }
-}
 
-impl From<Config> for ConfigBuilder {
+    /// 获取域名访问的基础超时时长
     #[inline]
-    fn from(config: Config) -> Self {
-        ConfigBuilder(config)
+    pub fn base_timeout(&self) -> Option<Duration> {
+        self.base_timeout_ms.map(Duration::from_millis)
     }
-}
 
-#[derive(Default, Clone, Debug)]
-struct Extra {
-    original_path: Option<PathBuf>,
-    range_reader_inner: OnceCell<Arc<RangeReaderInner>>,
-    async_range_reader_inner: OnceCell<AsyncRangeReaderHandle>,
-}
+    /// 设置域名访问失败后的惩罚时长
+    #[inline]
+    pub fn set_base_timeout(&mut self, base_timeout: Option<Duration>) -> &mut Self {
+        self.base_timeout_ms = base_timeout.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
+        self.uninit_range_reader_inner();
+        self
+    }
 
-impl PartialEq for Extra {
+    /// 获取域名访问的超时时长上限
     #[inline]
-    fn eq(&self, _: &Self) -> bool {
-        true
+    pub fn max_timeout(&self) -> Option<Duration> {
+        self.max_timeout_ms.map(Duration::from_millis)
     }
-}
 
-impl Eq for Extra {}
+    /// 设置域名访问的超时时长上限
+    ///
+    /// 超时时长随域名的惩罚次数翻倍增长，但不会超过该上限
+    #[inline]
+    pub fn set_max_timeout(&mut self, max_timeout: Option<Duration>) -> &mut Self {
+        self.max_timeout_ms = max_timeout.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取按域名覆盖的基础超时时长
+    #[inline]
+    pub fn host_base_timeouts(&self) -> Option<HashMap<String, Duration>> {
+        self.host_base_timeouts_ms.as_ref().map(|timeouts| {
+            timeouts
+                .iter()
+                .map(|(host, &ms)| (host.to_owned(), Duration::from_millis(ms)))
+                .collect()
+        })
+    }
+
+    /// 设置按域名覆盖的基础超时时长
+    #[inline]
+    pub fn set_host_base_timeouts(
+        &mut self,
+        host_base_timeouts: Option<HashMap<String, Duration>>,
+    ) -> &mut Self {
+        self.host_base_timeouts_ms = host_base_timeouts_to_ms(host_base_timeouts);
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取域名连接的超时时长
+    #[inline]
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.dial_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// 设置域名连接的超时时长
+    #[inline]
+    pub fn set_connect_timeout(&mut self, connect_timeout: Option<Duration>) -> &mut Self {
+        self.dial_timeout_ms =
+            connect_timeout.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取 DNS 缓存的最短有效期
+    #[inline]
+    pub fn dns_cache_min_ttl(&self) -> Option<Duration> {
+        self.dns_cache_min_ttl_s.map(Duration::from_secs)
+    }
+
+    /// 设置 DNS 缓存的最短有效期
+    ///
+    /// 解析记录的 TTL 短于该值时，仍将缓存该值的时长
+    #[inline]
+    pub fn set_dns_cache_min_ttl(&mut self, dns_cache_min_ttl: Option<Duration>) -> &mut Self {
+        self.dns_cache_min_ttl_s = dns_cache_min_ttl.map(|d| d.as_secs());
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取 DNS 缓存的最长有效期
+    #[inline]
+    pub fn dns_cache_max_ttl(&self) -> Option<Duration> {
+        self.dns_cache_max_ttl_s.map(Duration::from_secs)
+    }
+
+    /// 设置 DNS 缓存的最长有效期
+    ///
+    /// 解析记录的 TTL 长于该值时，仅缓存该值的时长
+    #[inline]
+    pub fn set_dns_cache_max_ttl(&mut self, dns_cache_max_ttl: Option<Duration>) -> &mut Self {
+        self.dns_cache_max_ttl_s = dns_cache_max_ttl.map(|d| d.as_secs());
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取下载请求的重定向策略
+    #[inline]
+    pub fn redirect_policy(&self) -> Option<RedirectPolicy> {
+        self.redirect_policy
+    }
+
+    /// 设置下载请求的重定向策略
+    #[inline]
+    pub fn set_redirect_policy(&mut self, redirect_policy: Option<RedirectPolicy>) -> &mut Self {
+        self.redirect_policy = redirect_policy;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取最大并行重试次数
+    #[inline]
+    pub fn max_retry_concurrency(&self) -> Option<u32> {
+        self.max_retry_concurrency
+    }
+
+    /// 设置最大并行重试次数，如果设置为 Some(0) 则表示禁止并行重试功能
+    #[inline]
+    pub fn set_max_retry_concurrency(&mut self, max_retry_concurrency: Option<u32>) -> &mut Self {
+        self.max_retry_concurrency = max_retry_concurrency;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取超时时长配置表
+    #[inline]
+    pub fn timeouts(&self) -> Option<&TimeoutsConfig> {
+        self.timeouts.as_ref()
+    }
+
+    /// 设置超时时长配置表，其中的默认配置将覆盖基础超时时长和连接超时时长
+    #[inline]
+    pub fn set_timeouts(&mut self, timeouts: Option<TimeoutsConfig>) -> &mut Self {
+        self.timeouts = timeouts;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取允许下载的对象尺寸上限
+    #[inline]
+    pub fn max_object_size(&self) -> Option<u64> {
+        self.max_object_size
+    }
+
+    /// 设置允许下载的对象尺寸上限，`download` 和 `download_to` 下载超过该尺寸的对象时将直接返回错误
+    #[inline]
+    pub fn set_max_object_size(&mut self, max_object_size: Option<u64>) -> &mut Self {
+        self.max_object_size = max_object_size;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 是否严格校验响应体尺寸
+    #[inline]
+    pub fn strict_body_size(&self) -> Option<bool> {
+        self.strict_body_size
+    }
+
+    /// 设置是否严格校验响应体尺寸
+    #[inline]
+    pub fn set_strict_body_size(&mut self, strict_body_size: Option<bool>) -> &mut Self {
+        self.strict_body_size = strict_body_size;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取响应头尺寸上限
+    #[inline]
+    pub fn max_response_header_size(&self) -> Option<usize> {
+        self.max_response_header_size
+    }
+
+    /// 设置响应头尺寸上限
+    #[inline]
+    pub fn set_max_response_header_size(
+        &mut self,
+        max_response_header_size: Option<usize>,
+    ) -> &mut Self {
+        self.max_response_header_size = max_response_header_size;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 是否严格校验响应头
+    #[inline]
+    pub fn strict_header_validation(&self) -> Option<bool> {
+        self.strict_header_validation
+    }
+
+    /// 设置是否严格校验响应头
+    #[inline]
+    pub fn set_strict_header_validation(
+        &mut self,
+        strict_header_validation: Option<bool>,
+    ) -> &mut Self {
+        self.strict_header_validation = strict_header_validation;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 是否禁用多范围读取 API
+    #[inline]
+    pub fn disable_read_multi_ranges(&self) -> Option<bool> {
+        self.disable_read_multi_ranges
+    }
+
+    /// 设置是否禁用多范围读取 API
+    #[inline]
+    pub fn set_disable_read_multi_ranges(
+        &mut self,
+        disable_read_multi_ranges: Option<bool>,
+    ) -> &mut Self {
+        self.disable_read_multi_ranges = disable_read_multi_ranges;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取范围请求的编码方式
+    #[inline]
+    pub fn range_request_encoding(&self) -> Option<RangeRequestEncoding> {
+        self.range_request_encoding
+    }
+
+    /// 设置范围请求的编码方式
+    #[inline]
+    pub fn set_range_request_encoding(
+        &mut self,
+        range_request_encoding: Option<RangeRequestEncoding>,
+    ) -> &mut Self {
+        self.range_request_encoding = range_request_encoding;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 下载到文件时是否读写完整性元数据
+    #[inline]
+    pub fn integrity_metadata(&self) -> Option<bool> {
+        self.integrity_metadata
+    }
+
+    /// 设置下载到文件时是否读写完整性元数据
+    #[inline]
+    pub fn set_integrity_metadata(&mut self, integrity_metadata: Option<bool>) -> &mut Self {
+        self.integrity_metadata = integrity_metadata;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 下载到文件时是否将文件的修改时间设置为对象的最后修改时间
+    #[inline]
+    pub fn preserve_last_modified(&self) -> Option<bool> {
+        self.preserve_last_modified
+    }
+
+    /// 设置下载到文件时是否将文件的修改时间设置为对象的最后修改时间
+    #[inline]
+    pub fn set_preserve_last_modified(
+        &mut self,
+        preserve_last_modified: Option<bool>,
+    ) -> &mut Self {
+        self.preserve_last_modified = preserve_last_modified;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取下载到输出流时的缓冲区尺寸
+    #[inline]
+    pub fn download_buffer_size(&self) -> Option<u64> {
+        self.download_buffer_size
+    }
+
+    /// 设置下载到输出流时的缓冲区尺寸
+    #[inline]
+    pub fn set_download_buffer_size(&mut self, download_buffer_size: Option<u64>) -> &mut Self {
+        self.download_buffer_size = download_buffer_size;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取多范围读取的区域数量上限
+    #[inline]
+    pub fn max_multi_ranges(&self) -> Option<usize> {
+        self.max_multi_ranges
+    }
+
+    /// 设置多范围读取的区域数量上限，`read_multi_ranges` 请求的区域数量超过上限时将直接返回错误
+    #[inline]
+    pub fn set_max_multi_ranges(&mut self, max_multi_ranges: Option<usize>) -> &mut Self {
+        self.max_multi_ranges = max_multi_ranges;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取多范围读取的区域总长度上限
+    #[inline]
+    pub fn max_multi_ranges_size(&self) -> Option<u64> {
+        self.max_multi_ranges_size
+    }
+
+    /// 设置多范围读取的区域总长度上限，`read_multi_ranges` 请求的区域总长度超过上限时将直接返回错误
+    #[inline]
+    pub fn set_max_multi_ranges_size(&mut self, max_multi_ranges_size: Option<u64>) -> &mut Self {
+        self.max_multi_ranges_size = max_multi_ranges_size;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 是否禁用下载整个文件的 API
+    #[inline]
+    pub fn disable_download(&self) -> Option<bool> {
+        self.disable_download
+    }
+
+    /// 设置是否禁用下载整个文件的 API
+    #[inline]
+    pub fn set_disable_download(&mut self, disable_download: Option<bool>) -> &mut Self {
+        self.disable_download = disable_download;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 是否解压以压缩编码存储的对象
+    #[inline]
+    pub fn decompress_content_encoding(&self) -> Option<bool> {
+        self.decompress_content_encoding
+    }
+
+    /// 设置是否解压以压缩编码存储的对象
+    #[inline]
+    pub fn set_decompress_content_encoding(
+        &mut self,
+        decompress_content_encoding: Option<bool>,
+    ) -> &mut Self {
+        self.decompress_content_encoding = decompress_content_encoding;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 是否启用严格模式
+    #[inline]
+    pub fn strict(&self) -> Option<bool> {
+        self.strict
+    }
+
+    /// 设置是否启用严格模式
+    #[inline]
+    pub fn set_strict(&mut self, strict: Option<bool>) -> &mut Self {
+        self.strict = strict;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取切换域名重试前的等待策略
+    #[inline]
+    pub fn failover_backoff(&self) -> Option<FailoverBackoff> {
+        self.failover_backoff
+    }
+
+    /// 设置切换域名重试前的等待策略
+    #[inline]
+    pub fn set_failover_backoff(&mut self, failover_backoff: Option<FailoverBackoff>) -> &mut Self {
+        self.failover_backoff = failover_backoff;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取视为连接失败的错误类型
+    #[inline]
+    pub fn connection_failure_kinds(&self) -> Option<&[ConnectionFailureKind]> {
+        self.connection_failure_kinds.as_deref()
+    }
+
+    /// 设置视为连接失败的错误类型
+    ///
+    /// 连接失败的域名在选择域名时将被优先排除，未设置时域名解析、建立连接和 TLS 握手失败均视为连接失败
+    #[inline]
+    pub fn set_connection_failure_kinds(
+        &mut self,
+        connection_failure_kinds: Option<Vec<ConnectionFailureKind>>,
+    ) -> &mut Self {
+        self.connection_failure_kinds = connection_failure_kinds;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取可以切换域名重试的状态码
+    #[inline]
+    pub fn retryable_status_codes(&self) -> Option<&[u16]> {
+        self.retryable_status_codes.as_deref()
+    }
+
+    /// 设置可以切换域名重试的状态码
+    ///
+    /// 设置后，仅返回这些状态码时才会惩罚域名并切换域名重试，
+    /// 未设置时除 4xx 以外的状态码均会重试
+    #[inline]
+    pub fn set_retryable_status_codes(
+        &mut self,
+        retryable_status_codes: Option<Vec<u16>>,
+    ) -> &mut Self {
+        self.retryable_status_codes = retryable_status_codes;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取视为对象级错误的状态码
+    #[inline]
+    pub fn object_level_status_codes(&self) -> Option<&[u16]> {
+        self.object_level_status_codes.as_deref()
+    }
+
+    /// 设置视为对象级错误的状态码
+    ///
+    /// 对象级错误将直接返回，既不惩罚域名，也不记录为失败的打点。
+    /// 404 和 612 总是视为对象不存在，无需设置
+    #[inline]
+    pub fn set_object_level_status_codes(
+        &mut self,
+        object_level_status_codes: Option<Vec<u16>>,
+    ) -> &mut Self {
+        self.object_level_status_codes = object_level_status_codes;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取对象名称前缀改写规则
+    #[inline]
+    pub fn key_rewrite_rules(&self) -> Option<&[KeyRewriteRule]> {
+        self.key_rewrite_rules.as_deref()
+    }
+
+    /// 设置对象名称前缀改写规则
+    ///
+    /// 构造下载 URL 前按顺序查找第一条匹配的规则改写对象名称，对所有 API 生效
+    #[inline]
+    pub fn set_key_rewrite_rules(
+        &mut self,
+        key_rewrite_rules: Option<Vec<KeyRewriteRule>>,
+    ) -> &mut Self {
+        self.key_rewrite_rules = key_rewrite_rules;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取同步接口等待内部运行时处理请求的超时时长
+    #[inline]
+    pub fn sync_request_timeout(&self) -> Option<Duration> {
+        self.sync_request_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// 设置同步接口等待内部运行时处理请求的超时时长
+    #[inline]
+    pub fn set_sync_request_timeout(
+        &mut self,
+        sync_request_timeout: Option<Duration>,
+    ) -> &mut Self {
+        self.sync_request_timeout_ms =
+            sync_request_timeout.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取同步接口内部运行时的工作线程数
+    #[inline]
+    pub fn sync_worker_threads(&self) -> Option<usize> {
+        self.sync_worker_threads
+    }
+
+    /// 设置同步接口内部运行时的工作线程数
+    #[inline]
+    pub fn set_sync_worker_threads(&mut self, sync_worker_threads: Option<usize>) -> &mut Self {
+        self.sync_worker_threads = sync_worker_threads;
+        self.uninit_range_reader_inner();
+        self
+    }
+
+    /// 获取集群名称，仅当该配置属于多集群配置时返回
+    #[inline]
+    pub fn cluster_name(&self) -> Option<&str> {
+        self.extra.cluster_name.as_deref()
+    }
+
+    pub(super) fn set_cluster_name(&mut self, cluster_name: Option<String>) {
+        self.extra.cluster_name = cluster_name;
+    }
+
+    pub(super) fn original_path(&self) -> Option<&Path> {
+        self.extra.original_path.as_ref().map(|p| p.as_ref())
+    }
+
+    #[allow(dead_code)]
+    pub(super) fn original_path_mut(&mut self) -> &mut Option<PathBuf> {
+        &mut self.extra.original_path
+    }
+
+    pub(super) fn config_paths(&self) -> Vec<PathBuf> {
+        self.extra
+            .original_path
+            .as_ref()
+            .map(|path| vec![path.to_owned()])
+            .unwrap_or_default()
+    }
+
+    pub(super) fn timeouts_set(&self) -> HashSet<HttpClientTimeouts> {
+        let mut set = HashSet::with_capacity(1);
+        set.insert(HttpClientTimeouts::from(self));
+        set
+    }
+
+    pub(crate) fn get_or_init_range_reader_inner(
+        &self,
+        f: impl FnOnce() -> Arc<RangeReaderInner>,
+    ) -> Arc<RangeReaderInner> {
+        self.extra.range_reader_inner.get_or_init(f).to_owned()
+    }
+
+    pub(crate) fn get_or_init_async_range_reader_inner(
+        &self,
+        f: impl FnOnce() -> AsyncRangeReaderHandle,
+    ) -> AsyncRangeReaderHandle {
+        self.extra
+            .async_range_reader_inner
+            .get_or_init(f)
+            .to_owned()
+    }
+
+    fn uninit_range_reader_inner(&mut self) {
+        self.extra.range_reader_inner.take();
+        self.extra.async_range_reader_inner.take();
+    }
+}
+
+/// 七牛单集群配置信息构建器
+#[derive(Debug)]
+pub struct ConfigBuilder(Config);
+
+/// 七牛单集群配置信息构建器
+pub type SingleClusterConfigBuilder = ConfigBuilder;
+
+impl ConfigBuilder {
+    /// 创建七牛配置信息构建器
+    pub fn new(
+        access_key: impl Into<String>,
+        secret_key: impl Into<String>,
+        bucket: impl Into<String>,
+        io_urls: Option<Vec<String>>,
+    ) -> Self {
+        Self(Config {
+            access_key: access_key.into(),
+            secret_key: secret_key.into(),
+            bucket: bucket.into(),
+            io_urls,
+            ..Default::default()
+        })
+    }
+
+    /// 构建七牛配置信息
+    #[inline]
+    pub fn build(self) -> Config {
+        self.0
+    }
+
+    /// 配置七牛 Access Key
+    #[inline]
+    pub fn access_key(mut self, access_key: impl Into<String>) -> Self {
+        self.0.access_key = access_key.into();
+        self
+    }
+
+    /// 配置七牛 Secret Key
+    #[inline]
+    pub fn secret_key(mut self, secret_key: impl Into<String>) -> Self {
+        self.0.secret_key = secret_key.into();
+        self
+    }
+
+    /// 配置七牛存储空间
+    #[inline]
+    pub fn bucket(mut self, bucket: impl Into<String>) -> Self {
+        self.0.bucket = bucket.into();
+        self
+    }
+
+    /// 配置 IO 服务器 URL 列表
+    #[inline]
+    pub fn io_urls(mut self, io_urls: Option<Vec<String>>) -> Self {
+        self.0.io_urls = io_urls;
+        self
+    }
+
+    /// 配置 CDN 域名列表，配置后将优先通过 CDN 下载，失败时再回源到 IO 服务器
+    #[inline]
+    pub fn cdn_urls(mut self, cdn_urls: Option<Vec<String>>) -> Self {
+        self.0.cdn_urls = cdn_urls;
+        self
+    }
+
+    /// 配置 UC 服务器域名列表
+    #[inline]
+    pub fn uc_urls(mut self, uc_urls: Option<Vec<String>>) -> Self {
+        self.0.uc_urls = uc_urls;
+        self
+    }
+
+    /// 配置监控服务器域名列表，如果不配置或配置为空，则不会启用打点功能
+    #[inline]
+    pub fn monitor_urls(mut self, monitor_urls: Option<Vec<String>>) -> Self {
+        self.0.monitor_urls = monitor_urls;
+        self
+    }
+
+    /// 是否使用 Getfile API，默认为 true
+    #[inline]
+    pub fn use_getfile_api(mut self, use_getfile_api: Option<bool>) -> Self {
+        self.0.sim = use_getfile_api.map(|b| !b);
+        self
+    }
+
+    /// 配置下载地址的路径前缀，将插入在域名和 Getfile API 路径或对象名称之间
+    ///
+    /// 例如配置为 `/internal` 时，下载地址将形如 `http://io.example.com/internal/getfile/...`
+    #[inline]
+    pub fn path_prefix(mut self, path_prefix: Option<String>) -> Self {
+        self.0.path_prefix = path_prefix;
+        self
+    }
+
+    /// 是否对 key 进行格式化，默认为 false
+    #[inline]
+    pub fn normalize_key(mut self, normalize_key: Option<bool>) -> Self {
+        self.0.normalize_key = normalize_key;
+        self
+    }
+
+    /// 是否使用私有存储空间，默认不使用
+    #[inline]
+    pub fn private(mut self, private: Option<bool>) -> Self {
+        self.0.private = private;
+        self
+    }
+
+    /// 配置 IO 和 UC 服务器访问重试次数，默认为 10
+    #[inline]
+    pub fn retry(mut self, retry: Option<usize>) -> Self {
+        self.0.retry = retry;
+        self
+    }
+
+    /// 配置更新 IO 域名列表的重试次数，默认为 3
+    #[inline]
+    pub fn update_hosts_retry(mut self, update_hosts_retry: Option<usize>) -> Self {
+        self.0.update_hosts_retry = update_hosts_retry;
+        self
+    }
+
+    /// 配置更新 IO 域名列表时要求的最少域名数量，默认为 1
+    ///
+    /// UC 返回的域名数量少于该值且少于当前域名数量时，本次更新将被拒绝
+    #[inline]
+    pub fn min_hosts_on_update(mut self, min_hosts_on_update: Option<usize>) -> Self {
+        self.0.min_hosts_on_update = min_hosts_on_update;
+        self
+    }
+
+    /// 配置更新 IO 域名列表时允许移除的域名最大比例，取值范围为 0 到 1，默认为 0.5
+    ///
+    /// UC 返回的域名数量少于当前域名数量，且其中移除的当前域名比例大于该值时，本次更新将被拒绝
+    #[inline]
+    pub fn max_host_change_ratio(mut self, max_host_change_ratio: Option<f64>) -> Self {
+        self.0.max_host_change_ratio = max_host_change_ratio.map(Ratio);
+        self
+    }
+
+    /// 配置域名访问失败后的惩罚时长，默认为 30 分钟
+    #[inline]
+    pub fn punish_duration(mut self, punish_duration: Option<Duration>) -> Self {
+        self.0.punish_time_s = punish_duration.map(|d| d.as_secs());
+        self
+    }
+
+    /// 配置对象不存在的缓存有效期，默认不缓存
+    ///
+    /// 启用后，判定为不存在的对象在有效期内将直接返回不存在，不再访问服务器
+    #[inline]
+    pub fn negative_cache_ttl(mut self, negative_cache_ttl: Option<Duration>) -> Self {
+        self.0.negative_cache_ttl_s = negative_cache_ttl.map(|d| d.as_secs());
+        self
+    }
+
+    /// 配置复用 HEAD 请求结果的时间窗口，默认不复用
+    ///
+    /// 启用后，时间窗口内对同一对象相继调用判定对象是否存在、获取文件大小和获取对象元信息时只发出一次 HEAD 请求
+    #[inline]
+    pub fn head_cache_window(mut self, head_cache_window: Option<Duration>) -> Self {
+        self.0.head_cache_window_ms =
+            head_cache_window.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
+        self
+    }
+
+    /// 配置预加载缓存容量，单位为字节，默认为 64 MiB，为 0 时禁用预加载缓存
+    ///
+    /// 通过 `preload()` 预加载的区域数据将缓存在内存中，之后完整落在缓存区域内的读取无需访问服务器
+    #[inline]
+    pub fn preload_cache_size(mut self, preload_cache_size: Option<u64>) -> Self {
+        self.0.preload_cache_size = preload_cache_size;
+        self
+    }
+
+    /// 配置预加载数据的有效期，默认为 5 分钟
+    ///
+    /// 超过有效期的预加载数据将不再用于读取，以免对象被覆盖后长期读到旧数据
+    #[inline]
+    pub fn preload_cache_ttl(mut self, preload_cache_ttl: Option<Duration>) -> Self {
+        self.0.preload_cache_ttl_s = preload_cache_ttl.map(|d| d.as_secs());
+        self
+    }
+
+    /// 配置是否遵循响应中的 Cache-Control、Expires 和 Age 头，默认不遵循
+    ///
+    /// 启用后，本地缓存的有效期不会超过响应允许的有效期，响应禁止缓存时不再缓存
+    #[inline]
+    pub fn respect_cache_control(mut self, respect_cache_control: Option<bool>) -> Self {
+        self.0.respect_cache_control = respect_cache_control;
+        self
+    }
+
+    /// 配置是否强制使用 HTTPS 协议访问所有域名，默认不强制
+    ///
+    /// 启用后，配置中和更新得到的 `http://` 域名都将被升级为 `https://` 域名
+    #[inline]
+    pub fn force_https(mut self, force_https: Option<bool>) -> Self {
+        self.0.force_https = force_https;
+        self
+    }
+
+    /// 配置随机数种子，默认使用系统熵源
+    ///
+    /// 设置后域名乱序、重试等待抖动等随机行为将可以复现，适用于集成测试
+    #[inline]
+    pub fn random_seed(mut self, random_seed: Option<u64>) -> Self {
+        self.0.random_seed = random_seed;
+        self
+    }
+
+    /// 配置从第二个域名验证读取结果的采样率，每 `verify_with_second_host_rate` 次读取验证一次，默认不验证
+    ///
+    /// 验证时从另一个 IO 域名再次读取相同区域并比较校验和，仅适用于异步实现
+    #[inline]
+    pub fn verify_with_second_host_rate(
+        mut self,
+        verify_with_second_host_rate: Option<u32>,
+    ) -> Self {
+        self.0.verify_with_second_host_rate = verify_with_second_host_rate;
+        self
+    }
+
+    /// 配置优先使用的区域或机房标签，默认使用 UC 查询结果中的第一个区域
+    ///
+    /// 从 UC 服务器更新 IO 域名时仅使用区域名称或机房标签与之相同的域名，没有匹配的区域时使用第一个区域
+    #[inline]
+    pub fn preferred_idc(mut self, preferred_idc: Option<String>) -> Self {
+        self.0.preferred_idc = preferred_idc;
+        self
+    }
+
+    /// 配置域名访问的基础超时时长，默认为 3000 毫秒
+    #[inline]
+    pub fn base_timeout(mut self, base_timeout: Option<Duration>) -> Self {
+        self.0.base_timeout_ms = base_timeout.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
+        self
+    }
+
+    /// 配置域名访问的超时时长上限，默认为 600 秒
+    #[inline]
+    pub fn max_timeout(mut self, max_timeout: Option<Duration>) -> Self {
+        self.0.max_timeout_ms = max_timeout.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
+        self
+    }
+
+    /// 配置按域名覆盖的基础超时时长，键为 IO 域名的 URL，默认不覆盖
+    #[inline]
+    pub fn host_base_timeouts(
+        mut self,
+        host_base_timeouts: Option<HashMap<String, Duration>>,
+    ) -> Self {
+        self.0.host_base_timeouts_ms = host_base_timeouts_to_ms(host_base_timeouts);
+        self
+    }
+
+    /// 配置域名连接的超时时长，默认为 50 毫秒
+    #[inline]
+    pub fn connect_timeout(mut self, connect_timeout: Option<Duration>) -> Self {
+        self.0.dial_timeout_ms =
+            connect_timeout.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
+        self
+    }
+
+    /// 配置 DNS 缓存的最短有效期，默认不启用 DNS 缓存
+    #[inline]
+    pub fn dns_cache_min_ttl(mut self, dns_cache_min_ttl: Option<Duration>) -> Self {
+        self.0.dns_cache_min_ttl_s = dns_cache_min_ttl.map(|d| d.as_secs());
+        self
+    }
+
+    /// 配置 DNS 缓存的最长有效期，仅设置最短有效期时默认为 1 小时
+    #[inline]
+    pub fn dns_cache_max_ttl(mut self, dns_cache_max_ttl: Option<Duration>) -> Self {
+        self.0.dns_cache_max_ttl_s = dns_cache_max_ttl.map(|d| d.as_secs());
+        self
+    }
+
+    /// 配置下载请求的重定向策略，默认最多跟随 10 次重定向
+    #[inline]
+    pub fn redirect_policy(mut self, redirect_policy: Option<RedirectPolicy>) -> Self {
+        self.0.redirect_policy = redirect_policy;
+        self
+    }
+
+    /// 配置最大并行重试次数，默认为 5，如果设置为 Some(0) 则表示禁止并行重试功能
+    #[inline]
+    pub fn max_retry_concurrency(mut self, max_retry_concurrency: Option<u32>) -> Self {
+        self.0.max_retry_concurrency = max_retry_concurrency;
+        self
+    }
+
+    /// 配置超时时长配置表，其中的默认配置将覆盖基础超时时长和连接超时时长
+    #[inline]
+    pub fn timeouts(mut self, timeouts: Option<TimeoutsConfig>) -> Self {
+        self.0.timeouts = timeouts;
+        self
+    }
+
+    /// 配置允许下载的对象尺寸上限，默认不限制
+    #[inline]
+    pub fn max_object_size(mut self, max_object_size: Option<u64>) -> Self {
+        self.0.max_object_size = max_object_size;
+        self
+    }
+
+    /// 是否严格校验响应体尺寸，默认为 false
+    ///
+    /// 启用后，范围读取的响应体尺寸与 Content-Length 或请求的范围尺寸不一致时将惩罚该域名并重试，
+    /// 否则将截断过长的响应体
+    #[inline]
+    pub fn strict_body_size(mut self, strict_body_size: Option<bool>) -> Self {
+        self.0.strict_body_size = strict_body_size;
+        self
+    }
+
+    /// 配置响应头尺寸上限，默认不限制
+    ///
+    /// 响应头超过上限时将返回 [`crate::ProtocolError`] 错误，并惩罚该域名
+    #[inline]
+    pub fn max_response_header_size(mut self, max_response_header_size: Option<usize>) -> Self {
+        self.0.max_response_header_size = max_response_header_size;
+        self
+    }
+
+    /// 是否严格校验响应头，默认为 false
+    ///
+    /// 启用后，响应头的值包含非可见 ASCII 字符或 Content-Length 不一致时将返回 [`crate::ProtocolError`] 错误，并惩罚该域名
+    #[inline]
+    pub fn strict_header_validation(mut self, strict_header_validation: Option<bool>) -> Self {
+        self.0.strict_header_validation = strict_header_validation;
+        self
+    }
+
+    /// 是否禁用多范围读取 API，默认为 false
+    ///
+    /// 禁用后，多范围读取将依次对每个范围发起单范围读取请求，适用于不支持多范围请求的网关
+    #[inline]
+    pub fn disable_read_multi_ranges(mut self, disable_read_multi_ranges: Option<bool>) -> Self {
+        self.0.disable_read_multi_ranges = disable_read_multi_ranges;
+        self
+    }
+
+    /// 配置范围请求的编码方式，默认通过 `Range` 请求头传递范围
+    ///
+    /// 适用于要求通过查询参数或 POST 请求体传递范围的网关
+    #[inline]
+    pub fn range_request_encoding(
+        mut self,
+        range_request_encoding: Option<RangeRequestEncoding>,
+    ) -> Self {
+        self.0.range_request_encoding = range_request_encoding;
+        self
+    }
+
+    /// 配置下载到文件时是否读写完整性元数据，默认为 false
+    ///
+    /// 开启后下载到文件时将在目标文件旁写入 `.qniu.meta` 文件，对象未变化时再次下载到同一路径将跳过下载
+    #[inline]
+    pub fn integrity_metadata(mut self, integrity_metadata: Option<bool>) -> Self {
+        self.0.integrity_metadata = integrity_metadata;
+        self
+    }
+
+    /// 配置下载到文件时是否将文件的修改时间设置为对象的最后修改时间，默认为 false
+    ///
+    /// 开启后可以通过比较文件的修改时间判断本地文件是否需要同步
+    #[inline]
+    pub fn preserve_last_modified(mut self, preserve_last_modified: Option<bool>) -> Self {
+        self.0.preserve_last_modified = preserve_last_modified;
+        self
+    }
+
+    /// 配置下载到输出流时的缓冲区尺寸，单位为字节，默认为 8 MiB
+    ///
+    /// 已经读取但尚未写入输出流的数据不超过缓冲区尺寸，输出流写入较慢时将暂停读取
+    #[inline]
+    pub fn download_buffer_size(mut self, download_buffer_size: Option<u64>) -> Self {
+        self.0.download_buffer_size = download_buffer_size;
+        self
+    }
+
+    /// 配置多范围读取的区域数量上限，默认不限制
+    ///
+    /// 多范围读取的所有区域都将缓存在内存中，因此需要限制单次请求的区域数量
+    #[inline]
+    pub fn max_multi_ranges(mut self, max_multi_ranges: Option<usize>) -> Self {
+        self.0.max_multi_ranges = max_multi_ranges;
+        self
+    }
+
+    /// 配置多范围读取的区域总长度上限，默认不限制
+    #[inline]
+    pub fn max_multi_ranges_size(mut self, max_multi_ranges_size: Option<u64>) -> Self {
+        self.0.max_multi_ranges_size = max_multi_ranges_size;
+        self
+    }
+
+    /// 是否禁用下载整个文件的 API，默认为 false
+    ///
+    /// 禁用后，下载整个文件将先获取文件尺寸，再分块发起范围读取请求
+    #[inline]
+    pub fn disable_download(mut self, disable_download: Option<bool>) -> Self {
+        self.0.disable_download = disable_download;
+        self
+    }
+
+    /// 是否解压以压缩编码存储的对象，默认为 false
+    ///
+    /// 以压缩编码存储的对象总是不支持范围读取，启用后下载整个对象时将解压 gzip 编码的数据
+    #[inline]
+    pub fn decompress_content_encoding(
+        mut self,
+        decompress_content_encoding: Option<bool>,
+    ) -> Self {
+        self.0.decompress_content_encoding = decompress_content_encoding;
+        self
+    }
+
+    /// 是否启用严格模式，默认为 false
+    ///
+    /// 启用后，监控域名为空、缓存目录不可写等会导致打点数据丢失的问题不再被静默忽略，
+    /// 而是由 `try_from_config()` 返回错误
+    #[inline]
+    pub fn strict(mut self, strict: Option<bool>) -> Self {
+        self.0.strict = strict;
+        self
+    }
+
+    /// 配置切换域名重试前的等待策略，默认立即切换
+    #[inline]
+    pub fn failover_backoff(mut self, failover_backoff: Option<FailoverBackoff>) -> Self {
+        self.0.failover_backoff = failover_backoff;
+        self
+    }
+
+    /// 配置视为连接失败的错误类型，默认域名解析、建立连接和 TLS 握手失败均视为连接失败
+    #[inline]
+    pub fn connection_failure_kinds(
+        mut self,
+        connection_failure_kinds: Option<Vec<ConnectionFailureKind>>,
+    ) -> Self {
+        self.0.connection_failure_kinds = connection_failure_kinds;
+        self
+    }
+
+    /// 配置可以切换域名重试的状态码，默认除 4xx 以外的状态码均会重试
+    #[inline]
+    pub fn retryable_status_codes(mut self, retryable_status_codes: Option<Vec<u16>>) -> Self {
+        self.0.retryable_status_codes = retryable_status_codes;
+        self
+    }
+
+    /// 配置视为对象级错误的状态码，404 和 612 总是视为对象不存在
+    #[inline]
+    pub fn object_level_status_codes(
+        mut self,
+        object_level_status_codes: Option<Vec<u16>>,
+    ) -> Self {
+        self.0.object_level_status_codes = object_level_status_codes;
+        self
+    }
+
+    /// 配置对象名称前缀改写规则，默认不改写
+    #[inline]
+    pub fn key_rewrite_rules(mut self, key_rewrite_rules: Option<Vec<KeyRewriteRule>>) -> Self {
+        self.0.key_rewrite_rules = key_rewrite_rules;
+        self
+    }
+
+    /// 配置同步接口内部运行时的工作线程数，默认为 1
+    #[inline]
+    pub fn sync_worker_threads(mut self, sync_worker_threads: Option<usize>) -> Self {
+        self.0.sync_worker_threads = sync_worker_threads;
+        self
+    }
+
+    /// 配置同步接口等待内部运行时处理请求的超时时长，默认无限等待
+    #[inline]
+    pub fn sync_request_timeout(mut self, sync_request_timeout: Option<Duration>) -> Self {
+        self.0.sync_request_timeout_ms =
+            sync_request_timeout.map(|d| d.as_millis().try_into().unwrap_or(u64::MAX));
+        self
+    }
+
+    /// 设置打点记录上传频率，默认为 10 秒
+    #[inline]
+    pub fn dot_interval(mut self, dot_interval: Option<Duration>) -> Self {
+        self.0.dot_interval_s = dot_interval.map(|d| d.as_secs());
+        self
+    }
+
+    /// 设置打点记录本地缓存文件尺寸上限，默认为 1 MB
+    #[inline]
+    pub fn max_dot_buffer_size(mut self, max_dot_buffer_size: Option<u64>) -> Self {
+        self.0.max_dot_buffer_size = max_dot_buffer_size;
+        self
+    }
+
+    /// 设置上传打点记录所用的凭证，默认使用下载所用的凭证
+    #[inline]
+    pub fn monitor_credential(
+        mut self,
+        monitor_access_key: Option<String>,
+        monitor_secret_key: Option<String>,
+    ) -> Self {
+        self.0.monitor_access_key = monitor_access_key;
+        self.0.monitor_secret_key = monitor_secret_key;
+        self
+    }
+
+    /// 设置上传打点记录的存储空间，默认使用下载所用的存储空间
+    #[inline]
+    pub fn monitor_bucket(mut self, monitor_bucket: Option<String>) -> Self {
+        self.0.monitor_bucket = monitor_bucket;
+        self
+    }
+
+    /// 设置上传打点记录所用的上传凭证有效期，默认为 30 秒
+    #[inline]
+    pub fn dot_token_lifetime(mut self, dot_token_lifetime: Option<Duration>) -> Self {
+        self.0.dot_token_lifetime_s = dot_token_lifetime.map(|d| d.as_secs());
+        self
+    }
+
+    /// 设置打点采样率，默认为 1，即记录每次 API 调用
+    #[inline]
+    pub fn dot_sample_rate(mut self, dot_sample_rate: Option<u32>) -> Self {
+        self.0.dot_sample_rate = dot_sample_rate;
+        self
+    }
+
+    /// 设置自动提高打点采样率的本地缓存文件尺寸阈值，默认不自动提高
+    #[inline]
+    pub fn dot_sampling_threshold(mut self, dot_sampling_threshold: Option<u64>) -> Self {
+        self.0.dot_sampling_threshold = dot_sampling_threshold;
+        self
+    }
+
+    /// 设置打点记录中耗时直方图的分桶边界，单位为毫秒，默认不记录直方图
+    #[inline]
+    pub fn dot_latency_buckets_ms(mut self, dot_latency_buckets_ms: Option<Vec<u64>>) -> Self {
+        self.0.dot_latency_buckets_ms = dot_latency_buckets_ms;
+        self
+    }
+
+    /// 设置是否记录同步接口内部运行时的负载打点，默认不记录
+    #[inline]
+    pub fn dot_sync_runtime_load(mut self, dot_sync_runtime_load: Option<bool>) -> Self {
+        self.0.dot_sync_runtime_load = dot_sync_runtime_load;
+        self
+    }
+
+    /// 设置是否记录 API 调用各阶段耗时的打点，默认不记录
+    #[inline]
+    pub fn dot_stage_timings(mut self, dot_stage_timings: Option<bool>) -> Self {
+        self.0.dot_stage_timings = dot_stage_timings;
+        self
+    }
+
+    #[inline]
+    #[cfg(test)]
+    pub(super) fn original_path(mut self, original_path: Option<PathBuf>) -> Self {
+        self.0.extra.original_path = original_path;
+        self
+    }
+}
+
+impl From<Config> for ConfigBuilder {
+    #[inline]
+    fn from(config: Config) -> Self {
+        ConfigBuilder(config)
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+struct Extra {
+    original_path: Option<PathBuf>,
+    cluster_name: Option<String>,
+    range_reader_inner: OnceCell<Arc<RangeReaderInner>>,
+    async_range_reader_inner: OnceCell<AsyncRangeReaderHandle>,
+}
+
+impl PartialEq for Extra {
+    #[inline]
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Extra {}
+
+/// 配置中的比例值，按位比较以便配置信息实现 [`Eq`]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+#[serde(transparent)]
+struct Ratio(f64);
+
+impl PartialEq for Ratio {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Ratio {}
+
+fn merge_config_value(base: &mut JsonValue, overrides: JsonValue) {
+    match (base, overrides) {
+        (JsonValue::Object(base), JsonValue::Object(overrides)) => {
+            for (key, value) in overrides {
+                if let Some(base_value) = base.get_mut(&key) {
+                    merge_config_value(base_value, value);
+                } else {
+                    base.insert(key, value);
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+fn host_base_timeouts_to_ms(
+    host_base_timeouts: Option<HashMap<String, Duration>>,
+) -> Option<HashMap<String, u64>> {
+    host_base_timeouts.map(|timeouts| {
+        timeouts
+            .into_iter()
+            .map(|(host, d)| (host, d.as_millis().try_into().unwrap_or(u64::MAX)))
+            .collect()
+    })
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use std::{convert::TryInto, time::Duration};
+
+/// 超时时长配置
+///
+/// 所有字段均为可选，未设置的字段将使用上一级的配置
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug, Default)]
+pub struct Timeouts {
+    connect_ms: Option<u64>,
+    read_ms: Option<u64>,
+    total_ms: Option<u64>,
+}
+
+impl Timeouts {
+    /// 获取域名连接的超时时长
+    #[inline]
+    pub fn connect(&self) -> Option<Duration> {
+        self.connect_ms.map(Duration::from_millis)
+    }
+
+    /// 设置域名连接的超时时长
+    ///
+    /// 连接超时时长作用于 HTTP 客户端，因此仅在默认配置中有效
+    #[inline]
+    pub fn set_connect(&mut self, connect: Option<Duration>) -> &mut Self {
+        self.connect_ms = connect.map(to_millis);
+        self
+    }
+
+    /// 获取单次请求的基础超时时长
+    #[inline]
+    pub fn read(&self) -> Option<Duration> {
+        self.read_ms.map(Duration::from_millis)
+    }
+
+    /// 设置单次请求的基础超时时长
+    ///
+    /// 单次请求超时后，将在此基础上增加超时时长进行重试
+    #[inline]
+    pub fn set_read(&mut self, read: Option<Duration>) -> &mut Self {
+        self.read_ms = read.map(to_millis);
+        self
+    }
+
+    /// 获取 API 调用的总超时时长
+    #[inline]
+    pub fn total(&self) -> Option<Duration> {
+        self.total_ms.map(Duration::from_millis)
+    }
+
+    /// 设置 API 调用的总超时时长，包含所有重试所花费的时间
+    #[inline]
+    pub fn set_total(&mut self, total: Option<Duration>) -> &mut Self {
+        self.total_ms = total.map(to_millis);
+        self
+    }
+
+    fn or(self, fallback: Self) -> Self {
+        Self {
+            connect_ms: self.connect_ms.or(fallback.connect_ms),
+            read_ms: self.read_ms.or(fallback.read_ms),
+            total_ms: self.total_ms.or(fallback.total_ms),
+        }
+    }
+}
+
+/// 可以单独配置超时时长的 API
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[non_exhaustive]
+pub enum TimeoutApi {
+    /// `read_at`
+    ReadAt,
+    /// `read_multi_ranges`
+    ReadMultiRanges,
+    /// `exist`
+    Exist,
+    /// `file_size`
+    FileSize,
+    /// `download` 和 `download_to`
+    Download,
+    /// `read_last_bytes`
+    ReadLastBytes,
+}
+
+/// 超时时长配置表
+///
+/// 对应配置文件中的 `[timeouts]` 表，表中的字段为默认配置，
+/// 每个 API 可以在 `[timeouts.<api>]` 子表中覆盖默认配置，例如 `[timeouts.download]`
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug, Default)]
+pub struct TimeoutsConfig {
+    #[serde(flatten)]
+    defaults: Timeouts,
+    read_at: Option<Timeouts>,
+    read_multi_ranges: Option<Timeouts>,
+    exist: Option<Timeouts>,
+    file_size: Option<Timeouts>,
+    download: Option<Timeouts>,
+    read_last_bytes: Option<Timeouts>,
+}
+
+impl TimeoutsConfig {
+    /// 获取默认超时时长配置
+    #[inline]
+    pub fn defaults(&self) -> &Timeouts {
+        &self.defaults
+    }
+
+    /// 设置默认超时时长配置
+    #[inline]
+    pub fn set_defaults(&mut self, defaults: Timeouts) -> &mut Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// 获取指定 API 覆盖的超时时长配置
+    #[inline]
+    pub fn api(&self, api: TimeoutApi) -> Option<&Timeouts> {
+        self.api_slot(api).as_ref()
+    }
+
+    /// 设置指定 API 覆盖的超时时长配置
+    #[inline]
+    pub fn set_api(&mut self, api: TimeoutApi, timeouts: Option<Timeouts>) -> &mut Self {
+        *self.api_slot_mut(api) = timeouts;
+        self
+    }
+
+    /// 获取指定 API 实际生效的超时时长配置
+    pub fn effective(&self, api: TimeoutApi) -> Timeouts {
+        self.api(api)
+            .map(|timeouts| timeouts.or(self.defaults))
+            .unwrap_or(self.defaults)
+    }
+
+    fn api_slot(&self, api: TimeoutApi) -> &Option<Timeouts> {
+        match api {
+            TimeoutApi::ReadAt => &self.read_at,
+            TimeoutApi::ReadMultiRanges => &self.read_multi_ranges,
+            TimeoutApi::Exist => &self.exist,
+            TimeoutApi::FileSize => &self.file_size,
+            TimeoutApi::Download => &self.download,
+            TimeoutApi::ReadLastBytes => &self.read_last_bytes,
+        }
+    }
+
+    fn api_slot_mut(&mut self, api: TimeoutApi) -> &mut Option<Timeouts> {
+        match api {
+            TimeoutApi::ReadAt => &mut self.read_at,
+            TimeoutApi::ReadMultiRanges => &mut self.read_multi_ranges,
+            TimeoutApi::Exist => &mut self.exist,
+            TimeoutApi::FileSize => &mut self.file_size,
+            TimeoutApi::Download => &mut self.download,
+            TimeoutApi::ReadLastBytes => &mut self.read_last_bytes,
+        }
+    }
+}
+
+fn to_millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeouts_config_from_toml() -> anyhow::Result<()> {
+        let config: TimeoutsConfig = toml::from_str(
+            r#"
+            connect_ms = 100
+            read_ms = 3000
+            [download]
+            total_ms = 600000
+            [exist]
+            read_ms = 500
+            total_ms = 1000
+            "#,
+        )?;
+        assert_eq!(
+            config.defaults().connect(),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(config.defaults().total(), None);
+
+        let download = config.effective(TimeoutApi::Download);
+        assert_eq!(download.read(), Some(Duration::from_secs(3)));
+        assert_eq!(download.total(), Some(Duration::from_secs(600)));
+
+        let exist = config.effective(TimeoutApi::Exist);
+        assert_eq!(exist.read(), Some(Duration::from_millis(500)));
+        assert_eq!(exist.total(), Some(Duration::from_secs(1)));
+
+        assert_eq!(config.effective(TimeoutApi::ReadAt), *config.defaults());
+        Ok(())
+    }
+}
@@ -143,15 +143,11 @@ fn _start_watcher() -> RecommendedWatcher {
         loop {
             match rx.recv() {
                 Ok(event) => match &event {
-                    DebouncedEvent::Create(ref path) => {
-                        if WATCHED_FILES.contains(path) {
-                            event_received(&event);
-                        }
+                    DebouncedEvent::Create(ref path) if WATCHED_FILES.contains(path) => {
+                        event_received(&event);
                     }
-                    DebouncedEvent::Write(ref path) => {
-                        if WATCHED_FILES.contains(path) {
-                            event_received(&event);
-                        }
+                    DebouncedEvent::Write(ref path) if WATCHED_FILES.contains(path) => {
+                        event_received(&event);
                     }
                     DebouncedEvent::Error(err, _) => {
                         error!(
@@ -1,18 +1,44 @@
+#[cfg(feature = "opendal")]
+use super::async_api::QiniuAccessor;
 use super::{
     async_api::{
+        AsyncArchiveDownloader, AsyncMultiFetcher, AsyncRangeReader as PublicAsyncRangeReader,
         RangePart, RangeReader as AsyncRangeReader, RangeReaderBuilder as AsyncRangeReaderBuilder,
+        ReadOverrides, ReaderStats, ShardedDownloadOptions, StreamTransformer, SyncRuntimeLoad,
+    },
+    base::{
+        checksum::{ChecksumAlgorithm, RangeHasher},
+        credential::Credential,
+        download::{
+            destination_busy_error, destination_lock_delay, destination_lock_path,
+            wait_for_object_delay, wait_for_object_timed_out_error, ObjectMetadata,
+            RangeReaderBuilder as BaseRangeReaderBuilder,
+        },
+        region_hosts::RegionHosts,
     },
-    base::{credential::Credential, download::RangeReaderBuilder as BaseRangeReaderBuilder},
     config::{
         build_range_reader_builder_from_config, build_range_reader_builder_from_env,
-        with_current_qiniu_config, Config,
+        with_current_qiniu_config, Config, ConnectionFailureKind, FailoverBackoff, KeyRewriteRule,
+        RangeRequestEncoding, RedirectPolicy, TimeoutsConfig,
     },
     sync_api::{
-        RangeReader as SyncRangeReader, RangeReaderBuilder as SyncRangeReaderBuilder, WriteSeek,
+        check_strict_mode, RangeReader as SyncRangeReader,
+        RangeReaderBuilder as SyncRangeReaderBuilder, WriteSeek,
     },
 };
+use fd_lock::RwLock as FdRwLock;
 use positioned_io::ReadAt;
-use std::{io::Result as IoResult, time::Duration};
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult, Write},
+    path::{Path, PathBuf},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+/// 下载并转换对象时每次读取的分块尺寸
+const TRANSFORM_CHUNK_SIZE: usize = 1 << 20;
 
 #[derive(Debug)]
 /// 对象范围下载构建器
@@ -26,7 +52,6 @@ impl RangeReaderBuilder {
     /// * `key` - 对象名称
     /// * `credential` - 存储空间所在账户的凭证
     /// * `io_urls` - 七牛 IO 服务器 URL 列表
-
     pub fn new(
         bucket: impl Into<String>,
         key: impl Into<String>,
@@ -41,64 +66,137 @@ impl RangeReaderBuilder {
         ))
     }
 
-    /// 设置七牛 UC 服务器 URL 列表
+    /// 设置 CDN 域名 URL 列表
+    ///
+    /// 设置后将优先通过 CDN 下载，CDN 返回错误或过期内容时再回源到 IO 服务器，最大并行重试次数为 0 时不支持
+    pub fn cdn_urls(self, urls: Vec<String>) -> Self {
+        self.with_inner(|b| b.cdn_urls(urls))
+    }
 
+    /// 设置七牛 UC 服务器 URL 列表
     pub fn uc_urls(self, urls: Vec<String>) -> Self {
         self.with_inner(|b| b.uc_urls(urls))
     }
 
     /// 设置七牛监控服务器 URL 列表
-
     pub fn monitor_urls(self, urls: Vec<String>) -> Self {
         self.with_inner(|b| b.monitor_urls(urls))
     }
 
     /// 设置对象下载最大尝试次数
-
     pub fn io_tries(self, tries: usize) -> Self {
         self.with_inner(|b| b.io_tries(tries))
     }
 
     /// 设置 UC 查询的最大尝试次数
-
     pub fn uc_tries(self, tries: usize) -> Self {
         self.with_inner(|b| b.uc_tries(tries))
     }
 
-    /// 设置打点记录上传的最大尝试次数
+    /// 设置更新 IO 域名列表的最大尝试次数，默认为 3
+    pub fn update_hosts_tries(self, tries: usize) -> Self {
+        self.with_inner(|b| b.update_hosts_tries(tries))
+    }
 
+    /// 设置打点记录上传的最大尝试次数
     pub fn dot_tries(self, tries: usize) -> Self {
         self.with_inner(|b| b.dot_tries(tries))
     }
 
     /// 设置 UC 查询的频率
-
     pub fn update_interval(self, interval: Duration) -> Self {
         self.with_inner(|b| b.update_interval(interval))
     }
 
-    /// 设置域名访问失败后的惩罚时长
+    /// 设置所有尝试均失败时强制更新域名列表的最小间隔，默认为 30 秒
+    pub fn force_update_interval(self, interval: Duration) -> Self {
+        self.with_inner(|b| b.force_update_interval(interval))
+    }
+
+    /// 设置对象不存在的缓存有效期，默认不缓存
+    ///
+    /// 启用后，判定为不存在的对象在有效期内将直接返回不存在，不再访问服务器
+    pub fn negative_cache_ttl(self, ttl: Duration) -> Self {
+        self.with_inner(|b| b.negative_cache_ttl(ttl))
+    }
+
+    /// 设置复用 HEAD 请求结果的时间窗口，默认不复用
+    ///
+    /// 启用后，时间窗口内对同一对象相继调用判定对象是否存在、获取文件大小和获取对象元信息时只发出一次 HEAD 请求
+    pub fn head_cache_window(self, window: Duration) -> Self {
+        self.with_inner(|b| b.head_cache_window(window))
+    }
+
+    /// 设置预加载缓存容量，单位为字节，默认为 64 MiB，为 0 时禁用预加载缓存
+    pub fn preload_cache_size(self, size: u64) -> Self {
+        self.with_inner(|b| b.preload_cache_size(size))
+    }
 
+    /// 设置预加载数据的有效期，默认为 5 分钟
+    ///
+    /// 超过有效期的预加载数据将不再用于读取，以免对象被覆盖后长期读到旧数据
+    pub fn preload_cache_ttl(self, ttl: Duration) -> Self {
+        self.with_inner(|b| b.preload_cache_ttl(ttl))
+    }
+
+    /// 设置是否遵循响应中的 Cache-Control、Expires 和 Age 头，默认不遵循
+    ///
+    /// 启用后，对象不存在的缓存和 HEAD 请求结果的缓存不会超过响应允许的有效期，响应禁止缓存时不再缓存
+    pub fn respect_cache_control(self, respect_cache_control: bool) -> Self {
+        self.with_inner(|b| b.respect_cache_control(respect_cache_control))
+    }
+
+    /// 设置域名访问失败后的惩罚时长
     pub fn punish_duration(self, duration: Duration) -> Self {
         self.with_inner(|b| b.punish_duration(duration))
     }
 
     /// 设置域名访问的基础超时时长
-
     pub fn base_timeout(self, timeout: Duration) -> Self {
         self.with_inner(|b| b.base_timeout(timeout))
     }
 
-    /// 设置域名访问的连接时长
+    /// 设置域名访问的超时时长上限，默认为 600 秒
+    ///
+    /// 超时时长随域名的惩罚次数翻倍增长，但不会超过该上限。
+    /// 下载大区域的批处理任务可以适当调高，交互式服务可以调低以便尽快失败
+    pub fn max_timeout(self, timeout: Duration) -> Self {
+        self.with_inner(|b| b.max_timeout(timeout))
+    }
 
+    /// 设置按域名覆盖的基础超时时长，键为 IO 域名的 URL
+    ///
+    /// 适用于跨地域等天然延迟较高的域名，这些域名的超时时长以覆盖值为起点计算
+    pub fn host_base_timeouts(self, timeouts: HashMap<String, Duration>) -> Self {
+        self.with_inner(|b| b.host_base_timeouts(timeouts))
+    }
+
+    /// 设置域名访问的连接时长
     pub fn connect_timeout(self, timeout: Duration) -> Self {
         self.with_inner(|b| b.connect_timeout(timeout))
     }
 
+    /// 设置 DNS 缓存的最短有效期，默认不启用 DNS 缓存
+    ///
+    /// 解析记录的 TTL 短于该值时，仍将缓存该值的时长。
+    /// 启用 `hickory-dns` 功能时遵循解析记录自身的 TTL，否则缓存最短有效期
+    pub fn dns_cache_min_ttl(self, ttl: Duration) -> Self {
+        self.with_inner(|b| b.dns_cache_min_ttl(ttl))
+    }
+
+    /// 设置 DNS 缓存的最长有效期，仅设置最短有效期时默认为 1 小时
+    pub fn dns_cache_max_ttl(self, ttl: Duration) -> Self {
+        self.with_inner(|b| b.dns_cache_max_ttl(ttl))
+    }
+
+    /// 设置下载请求的重定向策略，默认最多跟随 10 次重定向
+    pub fn redirect_policy(self, redirect_policy: RedirectPolicy) -> Self {
+        self.with_inner(|b| b.redirect_policy(redirect_policy))
+    }
+
     /// 设置失败域名的最大重试次数
     ///
     /// 一旦一个域名的被惩罚次数超过限制，则域名选择器不会选择该域名，除非被惩罚的域名比例超过上限，或惩罚时长超过指定时长
-
     pub fn max_punished_times(self, max_times: usize) -> Self {
         self.with_inner(|b| b.max_punished_times(max_times))
     }
@@ -106,52 +204,333 @@ impl RangeReaderBuilder {
     /// 设置被惩罚的域名最大比例
     ///
     /// 域名选择器在搜索域名时，一旦被跳过的域名比例大于该值，则下一个域名将被选中，不管该域名是否也被惩罚。一旦该域名成功，则惩罚将立刻被取消
-
     pub fn max_punished_hosts_percent(self, percent: u8) -> Self {
         self.with_inner(|b| b.max_punished_hosts_percent(percent))
     }
 
-    /// 设置是否使用 getfile API 下载
+    /// 设置更新域名列表时要求的最少域名数量，默认为 1
+    ///
+    /// UC 返回的域名数量少于该值且少于当前域名数量时，本次更新将被拒绝，继续使用当前的域名列表
+    pub fn min_hosts_on_update(self, min_hosts: usize) -> Self {
+        self.with_inner(|b| b.min_hosts_on_update(min_hosts))
+    }
 
+    /// 设置更新域名列表时允许移除的域名最大比例，取值范围为 0 到 1，默认为 0.5
+    ///
+    /// UC 返回的域名数量少于当前域名数量，且其中移除的当前域名比例大于该值时，本次更新将被拒绝，继续使用当前的域名列表。
+    /// 设置为 1 将不再检查移除的域名比例
+    pub fn max_host_change_ratio(self, ratio: f64) -> Self {
+        self.with_inner(|b| b.max_host_change_ratio(ratio))
+    }
+
+    /// 设置是否使用 getfile API 下载
     pub fn use_getfile_api(self, use_getfile_api: bool) -> Self {
         self.with_inner(|b| b.use_getfile_api(use_getfile_api))
     }
 
     /// 设置是否对 key 进行格式化
-
     pub fn normalize_key(self, normalize_key: bool) -> Self {
         self.with_inner(|b| b.normalize_key(normalize_key))
     }
 
     /// 设置私有空间下载 URL 有效期，如果为 None，则使用公开空间下载 URL
-
     pub fn private_url_lifetime(self, private_url_lifetime: Option<Duration>) -> Self {
         self.with_inner(|b| b.private_url_lifetime(private_url_lifetime))
     }
 
     /// 设置打点记录上传频率
-
     pub fn dot_interval(self, dot_interval: Duration) -> Self {
         self.with_inner(|b| b.dot_interval(dot_interval))
     }
 
     /// 设置打点记录本地缓存文件尺寸上限
-
     pub fn max_dot_buffer_size(self, max_dot_buffer_size: u64) -> Self {
         self.with_inner(|b| b.max_dot_buffer_size(max_dot_buffer_size))
     }
 
+    /// 设置上传打点记录所用的凭证，默认使用下载所用的凭证
+    pub fn dot_credential(self, credential: Credential) -> Self {
+        self.with_inner(|b| b.dot_credential(credential))
+    }
+
+    /// 设置上传打点记录的存储空间，默认使用下载所用的存储空间
+    pub fn dot_bucket(self, bucket: impl Into<String>) -> Self {
+        self.with_inner(|b| b.dot_bucket(bucket.into()))
+    }
+
+    /// 设置上传打点记录所用的上传凭证有效期，默认为 30 秒
+    pub fn dot_token_lifetime(self, lifetime: Duration) -> Self {
+        self.with_inner(|b| b.dot_token_lifetime(lifetime))
+    }
+
+    /// 设置打点采样率，每 `sample_rate` 次 API 调用仅记录一次，默认为 1，即记录每次调用
+    pub fn dot_sample_rate(self, sample_rate: u32) -> Self {
+        self.with_inner(|b| b.dot_sample_rate(sample_rate))
+    }
+
+    /// 设置打点记录本地缓存文件超过多大时自动提高采样率，默认不自动提高
+    pub fn dot_sampling_threshold(self, threshold: u64) -> Self {
+        self.with_inner(|b| b.dot_sampling_threshold(threshold))
+    }
+
+    /// 设置打点记录中耗时直方图的分桶边界，单位为毫秒，默认不记录直方图
+    ///
+    /// 设置后打点记录将包含各个桶的调用次数，监控服务可以据此统计 P95 和 P99 耗时
+    pub fn dot_latency_buckets_ms(self, buckets_ms: Vec<u64>) -> Self {
+        self.with_inner(|b| b.dot_latency_buckets_ms(buckets_ms))
+    }
+
+    /// 设置是否记录同步接口内部运行时的负载打点，默认不记录
+    ///
+    /// 启用后打点记录将包含内部运行时的请求并发数和排队请求数峰值，
+    /// 可以据此判断单线程的内部运行时是否已经成为瓶颈。仅对基于异步实现的同步接口生效
+    pub fn dot_sync_runtime_load(self, dot_sync_runtime_load: bool) -> Self {
+        self.with_inner(|b| b.dot_sync_runtime_load(dot_sync_runtime_load))
+    }
+
+    /// 设置是否记录 API 调用各阶段耗时的打点，默认不记录
+    ///
+    /// 启用后打点记录将包含每个 API 最后一次请求的选择域名、首字节、传输、解析和写缓存的平均耗时，
+    /// 可以据此判断耗时主要花费在哪个阶段
+    pub fn dot_stage_timings(self, dot_stage_timings: bool) -> Self {
+        self.with_inner(|b| b.dot_stage_timings(dot_stage_timings))
+    }
+
     /// 设置最大并行重试次数，如果设置为 0 则表示禁止并行重试功能
     pub fn max_retry_concurrency(self, max_retry_concurrency: u32) -> Self {
         self.with_inner(|b| b.max_retry_concurrency(max_retry_concurrency))
     }
 
-    /// 设置是否使用 HTTPS 协议来访问 IO 服务器
+    /// 设置超时时长配置表
+    ///
+    /// 其中的默认配置将覆盖基础超时时长和连接超时时长，每个 API 还可以单独设置单次请求和总超时时长
+    pub fn timeouts(self, timeouts: TimeoutsConfig) -> Self {
+        self.with_inner(|b| b.timeouts(timeouts))
+    }
+
+    /// 设置允许下载的对象尺寸上限
+    ///
+    /// `download` 和 `download_to` 将首先获取对象尺寸，超过上限时返回 [`crate::ObjectTooLargeError`] 错误
+    pub fn max_object_size(self, max_object_size: u64) -> Self {
+        self.with_inner(|b| b.max_object_size(max_object_size))
+    }
+
+    /// 设置是否严格校验响应体尺寸
+    ///
+    /// 启用后，范围读取的响应体尺寸与 Content-Length 或请求的范围尺寸不一致时将惩罚该域名并重试，
+    /// 否则将截断过长的响应体
+    pub fn strict_body_size(self, strict_body_size: bool) -> Self {
+        self.with_inner(|b| b.strict_body_size(strict_body_size))
+    }
+
+    /// 设置响应头尺寸上限
+    ///
+    /// 响应头超过上限时将返回 [`crate::ProtocolError`] 错误，并惩罚该域名
+    pub fn max_response_header_size(self, max_response_header_size: usize) -> Self {
+        self.with_inner(|b| b.max_response_header_size(max_response_header_size))
+    }
+
+    /// 设置是否严格校验响应头
+    ///
+    /// 启用后，响应头的值包含非可见 ASCII 字符或 Content-Length 不一致时将返回 [`crate::ProtocolError`] 错误，并惩罚该域名
+    pub fn strict_header_validation(self, strict_header_validation: bool) -> Self {
+        self.with_inner(|b| b.strict_header_validation(strict_header_validation))
+    }
+
+    /// 设置是否禁用多范围读取 API
+    ///
+    /// 禁用后，多范围读取将依次对每个范围发起单范围读取请求
+    pub fn disable_read_multi_ranges(self, disable_read_multi_ranges: bool) -> Self {
+        self.with_inner(|b| b.disable_read_multi_ranges(disable_read_multi_ranges))
+    }
+
+    /// 设置范围请求的编码方式，默认通过 `Range` 请求头传递范围
+    ///
+    /// 部分网关要求通过查询参数或 POST 请求的 JSON 请求体传递范围
+    pub fn range_request_encoding(self, range_request_encoding: RangeRequestEncoding) -> Self {
+        self.with_inner(|b| b.range_request_encoding(range_request_encoding))
+    }
 
+    /// 设置下载到文件时是否读写完整性元数据，默认为 false
+    ///
+    /// 开启后 [`RangeReader::download_to_path`] 将在目标文件旁写入 `.qniu.meta` 文件，
+    /// 对象未变化时再次下载到同一路径将跳过下载
+    pub fn integrity_metadata(self, integrity_metadata: bool) -> Self {
+        self.with_inner(|b| b.integrity_metadata(integrity_metadata))
+    }
+
+    /// 设置下载到文件时是否将文件的修改时间设置为对象的最后修改时间，默认为 false
+    ///
+    /// 开启后 [`RangeReader::download_to_path`] 和 [`RangeReader::download_to_dir`] 将在重命名为目标文件前设置其修改时间，
+    /// 对象没有返回 Last-Modified 时保持为写入时间，旧版同步实现不支持该选项
+    pub fn preserve_last_modified(self, preserve_last_modified: bool) -> Self {
+        self.with_inner(|b| b.preserve_last_modified(preserve_last_modified))
+    }
+
+    /// 设置下载到输出流时的缓冲区尺寸，单位为字节，默认为 8 MiB，最大为 256 MiB
+    ///
+    /// 对象将被分块并发读取，已经读取但尚未写入输出流的数据不超过缓冲区尺寸。
+    /// 输出流写入较慢时将暂停读取，直到缓冲区中的数据被写入，缓冲区的最高水位记录在 [`crate::ReaderStats`] 中。
+    /// 仅适用于异步实现的 `download_to()`
+    pub fn download_buffer_size(self, download_buffer_size: u64) -> Self {
+        self.with_inner(|b| b.download_buffer_size(download_buffer_size))
+    }
+
+    /// 设置多范围读取的区域数量上限
+    ///
+    /// `read_multi_ranges` 请求的区域数量超过上限时返回 [`crate::MultiRangesLimitError`] 错误
+    pub fn max_multi_ranges(self, max_multi_ranges: usize) -> Self {
+        self.with_inner(|b| b.max_multi_ranges(max_multi_ranges))
+    }
+
+    /// 设置多范围读取的区域总长度上限
+    ///
+    /// `read_multi_ranges` 请求的区域总长度超过上限时返回 [`crate::MultiRangesLimitError`] 错误
+    pub fn max_multi_ranges_size(self, max_multi_ranges_size: u64) -> Self {
+        self.with_inner(|b| b.max_multi_ranges_size(max_multi_ranges_size))
+    }
+
+    /// 设置是否禁用下载整个文件的 API
+    ///
+    /// 禁用后，下载整个文件将先获取文件尺寸，再分块发起范围读取请求
+    pub fn disable_download(self, disable_download: bool) -> Self {
+        self.with_inner(|b| b.disable_download(disable_download))
+    }
+
+    /// 设置是否解压以压缩编码存储的对象
+    ///
+    /// 以 Content-Encoding 压缩编码存储的对象不支持范围读取，将返回 [`crate::ContentEncodedError`] 错误。
+    /// 启用后，下载整个对象时将解压 gzip 编码的数据，解压后的尺寸受 `max_object_size` 限制
+    pub fn decompress_content_encoding(self, decompress_content_encoding: bool) -> Self {
+        self.with_inner(|b| b.decompress_content_encoding(decompress_content_encoding))
+    }
+
+    /// 设置是否启用严格模式
+    ///
+    /// 启用后，监控域名为空、缓存目录不可写等会导致打点数据丢失的问题不再被静默忽略，
+    /// 而是由 [`Self::try_build`] 返回 [`crate::StrictModeError`] 错误
+    pub fn strict(self, strict: bool) -> Self {
+        self.with_inner(|b| b.strict(strict))
+    }
+
+    /// 设置切换域名重试前的等待策略，默认立即切换
+    pub fn failover_backoff(self, failover_backoff: FailoverBackoff) -> Self {
+        self.with_inner(|b| b.failover_backoff(failover_backoff))
+    }
+
+    /// 设置视为连接失败的错误类型
+    ///
+    /// 连接失败的域名在选择域名时将被优先排除，默认域名解析、建立连接和 TLS 握手失败均视为连接失败
+    pub fn connection_failure_kinds(
+        self,
+        connection_failure_kinds: Vec<ConnectionFailureKind>,
+    ) -> Self {
+        self.with_inner(|b| b.connection_failure_kinds(connection_failure_kinds))
+    }
+
+    /// 设置可以切换域名重试的状态码
+    ///
+    /// 设置后，仅返回这些状态码时才会惩罚域名并切换域名重试，默认除 4xx 以外的状态码均会重试
+    pub fn retryable_status_codes(self, retryable_status_codes: Vec<u16>) -> Self {
+        self.with_inner(|b| b.retryable_status_codes(retryable_status_codes))
+    }
+
+    /// 设置视为对象级错误的状态码
+    ///
+    /// 对象级错误将直接返回，既不惩罚域名，也不记录为失败的打点。404 和 612 总是视为对象不存在
+    pub fn object_level_status_codes(self, object_level_status_codes: Vec<u16>) -> Self {
+        self.with_inner(|b| b.object_level_status_codes(object_level_status_codes))
+    }
+
+    /// 设置对象名称前缀改写规则
+    ///
+    /// 构造下载 URL 前按顺序查找第一条匹配的规则改写对象名称，对所有 API 生效
+    pub fn key_rewrite_rules(self, key_rewrite_rules: Vec<KeyRewriteRule>) -> Self {
+        self.with_inner(|b| b.key_rewrite_rules(key_rewrite_rules))
+    }
+
+    /// 设置同步接口等待内部运行时处理请求的超时时长，默认无限等待
+    ///
+    /// 超时后将返回包含 [`crate::SyncRequestError`] 的错误
+    pub fn sync_request_timeout(self, timeout: Duration) -> Self {
+        self.with_inner(|b| b.sync_request_timeout(timeout))
+    }
+
+    /// 设置同步接口内部运行时的工作线程数，默认为 1
+    ///
+    /// 大于 1 时将使用多线程运行时，适用于响应体处理占用较多 CPU 的场景
+    pub fn sync_worker_threads(self, worker_threads: usize) -> Self {
+        self.with_inner(|b| b.sync_worker_threads(worker_threads))
+    }
+
+    /// 设置是否使用 HTTPS 协议来访问 IO 服务器
     pub fn use_https(self, use_https: bool) -> Self {
         self.with_inner(|b| b.use_https(use_https))
     }
 
+    /// 设置是否强制使用 HTTPS 协议访问所有域名
+    ///
+    /// 启用后，设置的和更新得到的 `http://` 域名都将被升级为 `https://` 域名，默认不强制
+    pub fn force_https(self, force_https: bool) -> Self {
+        self.with_inner(|b| b.force_https(force_https))
+    }
+
+    /// 设置随机数种子
+    ///
+    /// 设置后域名乱序、重试等待抖动和轮询等待抖动都将使用由该种子初始化的随机数生成器，
+    /// 相同的调用序列将产生相同的随机行为，适用于需要结果可复现的集成测试。默认使用系统熵源
+    pub fn random_seed(self, seed: u64) -> Self {
+        self.with_inner(|b| b.random_seed(seed))
+    }
+
+    /// 设置从第二个域名验证读取结果的概率，取值范围为 0 到 1，默认为 0，即不验证
+    ///
+    /// 被抽中的 `read_at()` 调用成功后，将从另一个 IO 域名再次读取相同区域并比较校验和，
+    /// 不一致时输出警告日志、发出 [`crate::DownloadEvent::HostDivergence`] 事件并记录打点，
+    /// 返回的仍是首次读取的数据。验证请求会增加额外的流量，仅建议在审计场景中启用。仅适用于异步实现
+    pub fn verify_with_second_host(self, probability: f64) -> Self {
+        self.with_inner(|b| b.verify_with_second_host(probability))
+    }
+
+    /// 设置优先使用的区域或机房标签
+    ///
+    /// 从 UC 服务器更新 IO 域名时，仅使用区域名称或机房标签与之相同的分组中的域名，
+    /// 没有匹配的分组时使用第一个分组的域名。默认使用第一个分组
+    pub fn preferred_idc(self, idc: impl Into<String>) -> Self {
+        self.with_inner(|b| b.preferred_idc(idc.into()))
+    }
+
+    /// 设置下载地址的路径前缀，将插入在域名和 Getfile API 路径或对象名称之间
+    pub fn path_prefix(self, path_prefix: impl Into<String>) -> Self {
+        self.with_inner(|b| b.path_prefix(path_prefix.into()))
+    }
+
+    /// 设置图片样式，样式名称将通过分隔符拼接在对象名称之后
+    /// # Arguments
+    ///
+    /// * `separator` - 样式分隔符，例如 `-` 或 `!`
+    /// * `style` - 样式名称
+    pub fn style(self, separator: char, style: impl Into<String>) -> Self {
+        self.with_inner(|b| b.style(separator, style.into()))
+    }
+
+    /// 设置数据处理指令，例如 `imageView2/1/w/200/h/200`，多个指令可以用 `|` 连接
+    pub fn fop(self, fop: impl Into<String>) -> Self {
+        self.with_inner(|b| b.fop(fop.into()))
+    }
+
+    /// 设置下载时的附件名称
+    pub fn attname(self, attname: impl Into<String>) -> Self {
+        self.with_inner(|b| b.attname(attname.into()))
+    }
+
+    /// 设置流量标签，带有标签的下载流量将单独计费
+    ///
+    /// 标签仅允许使用字母、数字、`-`、`_` 和 `.`，且长度不超过 64，不合法的标签将被忽略
+    pub fn traffic_tag(self, traffic_tag: impl Into<String>) -> Self {
+        self.with_inner(|b| b.traffic_tag(traffic_tag.into()))
+    }
+
     fn with_inner(
         mut self,
         f: impl FnOnce(BaseRangeReaderBuilder) -> BaseRangeReaderBuilder,
@@ -167,18 +546,54 @@ impl RangeReaderBuilder {
                 SyncRangeReaderBuilder::from(self.0).build(),
             ))
         } else {
-            RangeReader(RangeReaderImpl::Async(
+            RangeReader(RangeReaderImpl::Async(Box::new(
                 AsyncRangeReaderBuilder::from(self.0).build(),
-            ))
+            )))
         }
     }
 
+    /// 构建范围下载器，启用严格模式时先检查配置要求
+    ///
+    /// 未启用严格模式时与 [`Self::build`] 相同，总是返回成功
+    pub fn try_build(self) -> IoResult<RangeReader> {
+        check_strict_mode(&self.0)?;
+        Ok(self.build())
+    }
+
+    /// 构建异步范围下载器
+    ///
+    /// 异步范围下载器必须在 tokio 运行时中使用，最大并行重试次数为 0 时将被视为 1
+    pub fn build_async(self) -> PublicAsyncRangeReader {
+        PublicAsyncRangeReader::from_base_builder(self.0)
+    }
+
+    /// 构建多对象归档下载器
+    ///
+    /// 构建器中设置的对象名称将被忽略，归档的对象名称在下载时指定
+    pub fn build_archive_downloader(self) -> AsyncArchiveDownloader {
+        AsyncArchiveDownloader::from_base_builder(self.0)
+    }
+
+    /// 构建多对象并发下载器
+    ///
+    /// 构建器中设置的对象名称将被忽略，对象名称在下载时指定
+    pub fn build_multi_fetcher(self) -> AsyncMultiFetcher {
+        AsyncMultiFetcher::from_base_builder(self.0)
+    }
+
+    /// 构建 OpenDAL Accessor
+    ///
+    /// 构建器中设置的对象名称将被忽略，OpenDAL 路径拼接在 `root` 之后作为对象名称
+    #[cfg(feature = "opendal")]
+    pub fn build_opendal_accessor(self, root: &str) -> QiniuAccessor {
+        QiniuAccessor::from_base_builder(self.0, root)
+    }
+
     /// 从配置创建范围下载构建器
     /// # Arguments
     ///
     /// * `key` - 对象名称
     /// * `config` - 下载配置
-
     pub fn from_config(key: impl Into<String>, config: &Config) -> Self {
         Self(build_range_reader_builder_from_config(key.into(), config))
     }
@@ -187,7 +602,6 @@ impl RangeReaderBuilder {
     /// # Arguments
     ///
     /// * `key` - 对象名称
-
     pub fn from_env(key: impl Into<String>) -> Option<Self> {
         build_range_reader_builder_from_env(key.into(), false).map(Self)
     }
@@ -200,12 +614,11 @@ pub struct RangeReader(RangeReaderImpl);
 #[derive(Debug)]
 enum RangeReaderImpl {
     Sync(SyncRangeReader),
-    Async(AsyncRangeReader),
+    Async(Box<AsyncRangeReader>),
 }
 
 impl RangeReader {
     /// 创建范围下载构建器
-
     pub fn builder(
         bucket: impl Into<String>,
         key: impl Into<String>,
@@ -227,13 +640,26 @@ impl RangeReader {
                 config,
             )))
         } else {
-            Self(RangeReaderImpl::Async(AsyncRangeReader::from_config(
-                key.into(),
-                config,
+            Self(RangeReaderImpl::Async(Box::new(
+                AsyncRangeReader::from_config(key.into(), config),
             )))
         }
     }
 
+    /// 从配置创建范围下载器，启用严格模式时先检查配置要求
+    ///
+    /// 未启用严格模式时与 [`Self::from_config`] 相同，总是返回成功
+    pub fn try_from_config(key: impl Into<String>, config: &Config) -> IoResult<Self> {
+        let key = key.into();
+        if config.strict() == Some(true) {
+            check_strict_mode(&build_range_reader_builder_from_config(
+                key.to_owned(),
+                config,
+            ))?;
+        }
+        Ok(Self::from_config(key, config))
+    }
+
     /// 从环境变量创建范围下载器
     /// # Arguments
     ///
@@ -249,6 +675,7 @@ impl RangeReader {
                             .map(Self)
                     } else {
                         AsyncRangeReader::from_env(key)
+                            .map(Box::new)
                             .map(RangeReaderImpl::Async)
                             .map(Self)
                     }
@@ -258,12 +685,37 @@ impl RangeReader {
         .flatten()
     }
 
+    /// 获取对象名称
+    pub fn key(&self) -> &str {
+        match &self.0 {
+            RangeReaderImpl::Sync(range_reader) => range_reader.key(),
+            RangeReaderImpl::Async(range_reader) => range_reader.key(),
+        }
+    }
+
+    /// 获取存储空间名称
+    pub fn bucket(&self) -> &str {
+        match &self.0 {
+            RangeReaderImpl::Sync(range_reader) => range_reader.bucket(),
+            RangeReaderImpl::Async(range_reader) => range_reader.bucket(),
+        }
+    }
+
+    /// 获取集群名称，仅当从多集群配置创建时返回
+    pub fn cluster_name(&self) -> Option<&str> {
+        match &self.0 {
+            RangeReaderImpl::Sync(range_reader) => range_reader.cluster_name(),
+            RangeReaderImpl::Async(range_reader) => range_reader.cluster_name(),
+        }
+    }
+
     /// 主动更新域名列表
     ///
-    /// 如果返回为 true 表示更新成功，否则返回 false
-    pub fn update_urls(&self) -> bool {
+    /// 如果返回为 true 表示更新成功，返回 false 表示没有可用于更新的域名列表，
+    /// 更新失败且重试次数耗尽时返回最后一次的错误
+    pub fn update_urls(&self) -> IoResult<bool> {
         match &self.0 {
-            RangeReaderImpl::Sync(range_reader) => range_reader.update_urls(),
+            RangeReaderImpl::Sync(range_reader) => Ok(range_reader.update_urls()),
             RangeReaderImpl::Async(range_reader) => range_reader.update_urls(),
         }
     }
@@ -276,7 +728,174 @@ impl RangeReader {
         }
     }
 
-    /// 读取文件的多个区域，返回每个区域对应的数据
+    /// 获取按区域分组的域名列表
+    ///
+    /// 配置了 UC 服务器时实时查询 UC 服务器，返回结果中保留了区域名称和机房标签，
+    /// 未配置 UC 服务器时仅返回包含当前可用 IO 域名的一个分组
+    pub fn region_hosts(&self) -> IoResult<Vec<RegionHosts>> {
+        match &self.0 {
+            RangeReaderImpl::Sync(range_reader) => range_reader.region_hosts(),
+            RangeReaderImpl::Async(range_reader) => range_reader.region_hosts(),
+        }
+    }
+
+    /// 报告外部对 IO 节点的访问失败
+    ///
+    /// 用于让同一进程内的健康检查等其他流量参与域名的惩罚，与下载器共享同一份域名健康状态。
+    /// `host` 应当为 [`Self::io_urls`] 返回的域名之一，如果该域名因此被惩罚，则返回 true
+    pub fn report_external_failure(&self, host: &str, error: IoError) -> bool {
+        match &self.0 {
+            RangeReaderImpl::Sync(range_reader) => {
+                range_reader.report_external_failure(host, &error)
+            }
+            RangeReaderImpl::Async(range_reader) => {
+                range_reader.report_external_failure(host, error)
+            }
+        }
+    }
+
+    /// 报告外部对 IO 节点的访问成功
+    ///
+    /// 用于让同一进程内的健康检查等其他流量参与域名的恢复，与下载器共享同一份域名健康状态。
+    /// `host` 应当为 [`Self::io_urls`] 返回的域名之一，如果找到了该域名，则返回 true
+    pub fn report_external_success(&self, host: &str) -> bool {
+        match &self.0 {
+            RangeReaderImpl::Sync(range_reader) => range_reader.report_external_success(host),
+            RangeReaderImpl::Async(range_reader) => range_reader.report_external_success(host),
+        }
+    }
+
+    /// 在指定时长内不再访问该 IO 节点，时长为 0 表示解除禁用
+    ///
+    /// 用于在故障或维护期间人工排除域名，无需修改配置或重启进程。
+    /// `host` 应当为 [`Self::io_urls`] 返回的域名之一，如果找到了该域名，则返回 true。
+    /// 最大并行重试次数为 0 时使用的同步实现不支持该功能，总是返回 false
+    pub fn ban_host(&self, host: &str, duration: Duration) -> bool {
+        match &self.0 {
+            RangeReaderImpl::Sync(_) => false,
+            RangeReaderImpl::Async(range_reader) => range_reader.ban_host(host, duration),
+        }
+    }
+
+    /// 覆盖该 IO 节点的基础超时时长，传入 None 表示恢复为全局的基础超时时长
+    ///
+    /// 适用于跨地域等天然延迟较高的域名，超时时长的倍增以覆盖值为起点。
+    /// `host` 应当为 [`Self::io_urls`] 返回的域名之一，如果找到了该域名，则返回 true。
+    /// 最大并行重试次数为 0 时使用的同步实现不支持该功能，总是返回 false
+    pub fn set_host_base_timeout(&self, host: &str, base_timeout: Option<Duration>) -> bool {
+        match &self.0 {
+            RangeReaderImpl::Sync(_) => false,
+            RangeReaderImpl::Async(range_reader) => {
+                range_reader.set_host_base_timeout(host, base_timeout)
+            }
+        }
+    }
+
+    /// 仅访问指定的 IO 节点，传入空列表表示解除固定
+    ///
+    /// `hosts` 应当为 [`Self::io_urls`] 返回的域名，如果有域名找不到则不做任何修改并返回 false。
+    /// 如果所有可用域名都被禁用或未被固定，则忽略人工干预，从全部域名中选择。
+    /// 最大并行重试次数为 0 时使用的同步实现不支持该功能，总是返回 false
+    pub fn pin_hosts(&self, hosts: &[String]) -> bool {
+        match &self.0 {
+            RangeReaderImpl::Sync(_) => false,
+            RangeReaderImpl::Async(range_reader) => range_reader.pin_hosts(hosts),
+        }
+    }
+
+    /// 删除当前对象不存在的缓存，如果缓存存在则返回 true
+    ///
+    /// 用于在对象上传后立即访问，而无需等待缓存过期。
+    /// 最大并行重试次数为 0 时使用的同步实现不支持该功能，总是返回 false
+    pub fn invalidate_negative_cache(&self) -> bool {
+        match &self.0 {
+            RangeReaderImpl::Sync(_) => false,
+            RangeReaderImpl::Async(range_reader) => range_reader.invalidate_negative_cache(),
+        }
+    }
+
+    /// 丢弃 HTTP 客户端及其连接池，后续请求将使用新建的客户端，如果客户端存在则返回 true
+    ///
+    /// 用于网络环境变化（例如切换 VPN）后，避免继续使用指向旧路由的空闲连接。
+    /// 相同超时配置的读取器共享同一个客户端，因此也会受到影响，如需丢弃所有客户端请使用 [`crate::purge_connections`]。
+    /// 最大并行重试次数为 0 时使用的同步实现不支持该功能，总是返回 false
+    pub fn purge_connections(&self) -> bool {
+        match &self.0 {
+            RangeReaderImpl::Sync(_) => false,
+            RangeReaderImpl::Async(range_reader) => range_reader.purge_connections(),
+        }
+    }
+
+    /// 修改调用参数，返回新的下载器
+    ///
+    /// 新的下载器与当前下载器共享内部运行时、域名选择器和缓存，仅重试次数、超时时长等调用参数不同，
+    /// 因此同一个下载器可以同时服务于交互式请求和批量任务。
+    /// 最大并行重试次数为 0 时使用的同步实现不支持该功能，总是返回 [`std::io::ErrorKind::Unsupported`] 错误
+    pub fn with_overrides(&self, f: impl FnOnce(&mut ReadOverrides)) -> IoResult<Self> {
+        match &self.0 {
+            RangeReaderImpl::Sync(_) => Err(IoError::new(
+                IoErrorKind::Unsupported,
+                "overrides are not supported when max_retry_concurrency is 0",
+            )),
+            RangeReaderImpl::Async(range_reader) => range_reader
+                .with_overrides(f)
+                .map(Box::new)
+                .map(RangeReaderImpl::Async)
+                .map(Self),
+        }
+    }
+
+    /// 获取下载器的累计统计信息
+    ///
+    /// 包括各 API 的调用次数、下载的字节数、重试次数、切换域名次数、缓存命中次数和平均耗时，
+    /// 克隆后的下载器共享统计信息。
+    /// 最大并行重试次数为 0 时使用的同步实现不支持该功能，总是返回空的统计信息
+    pub fn stats(&self) -> ReaderStats {
+        match &self.0 {
+            RangeReaderImpl::Sync(_) => Default::default(),
+            RangeReaderImpl::Async(range_reader) => range_reader.stats(),
+        }
+    }
+
+    /// 清空下载器的累计统计信息
+    pub fn reset_stats(&self) {
+        if let RangeReaderImpl::Async(range_reader) = &self.0 {
+            range_reader.reset_stats()
+        }
+    }
+
+    /// 获取同步接口中排队等待内部运行时处理的请求数量
+    pub fn queued_requests(&self) -> usize {
+        match &self.0 {
+            RangeReaderImpl::Sync(_) => 0,
+            RangeReaderImpl::Async(range_reader) => range_reader.queue_depth(),
+        }
+    }
+
+    /// 获取同步接口中内部运行时正在处理的请求数量
+    pub fn in_flight_requests(&self) -> usize {
+        self.sync_runtime_load().in_flight_requests()
+    }
+
+    /// 获取同步接口内部运行时的负载，包括当前和峰值的排队请求数和处理中请求数
+    ///
+    /// 当排队请求数持续较高时，说明内部运行时已经成为瓶颈，可以通过 `sync_worker_threads()` 增加工作线程，
+    /// 或者改用异步接口。不基于异步实现的同步接口总是返回零值
+    pub fn sync_runtime_load(&self) -> SyncRuntimeLoad {
+        match &self.0 {
+            RangeReaderImpl::Sync(_) => Default::default(),
+            RangeReaderImpl::Async(range_reader) => range_reader.runtime_load(),
+        }
+    }
+
+    /// 将同步接口内部运行时的负载峰值重置为当前值
+    pub fn reset_sync_runtime_load_peaks(&self) {
+        if let RangeReaderImpl::Async(range_reader) = &self.0 {
+            range_reader.reset_runtime_load_peaks()
+        }
+    }
+
+    /// 读取文件的多个区域，返回每个区域对应的数据，返回顺序与请求的区域顺序一致
     /// # Arguments
     /// * `range` - 区域列表，每个区域有开始偏移量和区域长度组成
     pub fn read_multi_ranges(&self, ranges: &[(u64, u64)]) -> IoResult<Vec<RangePart>> {
@@ -286,6 +905,51 @@ impl RangeReader {
         }
     }
 
+    /// 流式计算文件指定区域的校验和，不会将区域数据全部缓存在内存中
+    /// # Arguments
+    /// * `pos` - 区域开始偏移量
+    /// * `len` - 区域长度，超出文件末尾的部分将被忽略
+    /// * `algorithm` - 校验和算法
+    pub fn checksum_range(
+        &self,
+        pos: u64,
+        len: u64,
+        algorithm: ChecksumAlgorithm,
+    ) -> IoResult<Vec<u8>> {
+        match &self.0 {
+            RangeReaderImpl::Sync(range_reader) => {
+                checksum_range_by_chunks(range_reader, pos, len, algorithm)
+            }
+            RangeReaderImpl::Async(range_reader) => {
+                range_reader.checksum_range(pos, len, algorithm)
+            }
+        }
+    }
+
+    /// 并发计算文件多个区域的校验和，按区域顺序返回
+    ///
+    /// 旧版同步实现不支持并发，将依次计算每个区域的校验和
+    /// # Arguments
+    /// * `ranges` - 区域列表，每个区域有开始偏移量和区域长度组成
+    /// * `algorithm` - 校验和算法
+    /// * `concurrency` - 最大并发数，为 0 时视为 1
+    pub fn checksum_ranges(
+        &self,
+        ranges: &[(u64, u64)],
+        algorithm: ChecksumAlgorithm,
+        concurrency: usize,
+    ) -> IoResult<Vec<Vec<u8>>> {
+        match &self.0 {
+            RangeReaderImpl::Sync(range_reader) => ranges
+                .iter()
+                .map(|&(pos, len)| checksum_range_by_chunks(range_reader, pos, len, algorithm))
+                .collect(),
+            RangeReaderImpl::Async(range_reader) => {
+                range_reader.checksum_ranges(ranges, algorithm, concurrency)
+            }
+        }
+    }
+
     /// 判定当前对象是否存在
     pub fn exist(&self) -> IoResult<bool> {
         match &self.0 {
@@ -302,6 +966,44 @@ impl RangeReader {
         }
     }
 
+    /// 获取当前对象的元信息，包括尺寸、Etag、MIME 类型、最后修改时间和用户自定义元数据
+    ///
+    /// 最大并行重试次数为 0 时使用的同步实现不支持该功能，总是返回 [`std::io::ErrorKind::Unsupported`] 错误
+    pub fn stat(&self) -> IoResult<ObjectMetadata> {
+        match &self.0 {
+            RangeReaderImpl::Sync(_) => Err(IoError::new(
+                IoErrorKind::Unsupported,
+                "stat is not supported when max_retry_concurrency is 0",
+            )),
+            RangeReaderImpl::Async(range_reader) => range_reader.stat(),
+        }
+    }
+
+    /// 等待当前对象出现，返回对象的文件大小
+    ///
+    /// 以 `poll_interval` 为初始间隔轮询对象是否存在，间隔按指数增长且带有随机抖动，
+    /// 每次轮询前都会删除对象不存在的缓存。超过 `timeout` 时对象仍不存在则返回 [`std::io::ErrorKind::TimedOut`] 错误
+    pub fn wait_for_object(&self, poll_interval: Duration, timeout: Duration) -> IoResult<u64> {
+        let deadline = Instant::now() + timeout;
+        let rng = match &self.0 {
+            RangeReaderImpl::Sync(range_reader) => range_reader.rng(),
+            RangeReaderImpl::Async(range_reader) => range_reader.rng(),
+        };
+        for polls in 1.. {
+            self.invalidate_negative_cache();
+            match self.file_size() {
+                Err(err) if err.kind() == IoErrorKind::NotFound => {}
+                result => return result,
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            sleep(wait_for_object_delay(poll_interval, polls, rng).min(deadline - now));
+        }
+        Err(wait_for_object_timed_out_error(timeout))
+    }
+
     /// 下载当前对象到内存缓冲区中
     pub fn download(&self) -> IoResult<Vec<u8>> {
         match &self.0 {
@@ -310,6 +1012,16 @@ impl RangeReader {
         }
     }
 
+    /// 分片下载当前对象到内存缓冲区中
+    ///
+    /// 各个分片按轮转方式分散到不同的 IO 域名上并发下载，旧版同步实现不支持分片，将直接下载整个对象
+    pub fn download_sharded(&self, options: &ShardedDownloadOptions) -> IoResult<Vec<u8>> {
+        match &self.0 {
+            RangeReaderImpl::Sync(range_reader) => range_reader.download(),
+            RangeReaderImpl::Async(range_reader) => range_reader.download_sharded(options),
+        }
+    }
+
     /// 下载当前对象到指定输出流中
     pub fn download_to(&self, writer: &mut dyn WriteSeek) -> IoResult<u64> {
         match &self.0 {
@@ -318,6 +1030,95 @@ impl RangeReader {
         }
     }
 
+    /// 下载当前对象，经过转换器转换后写入指定输出流中，返回写入的字节数，即转换后的数据尺寸
+    ///
+    /// 对象按 [`Self::chunks`] 的方式分块顺序读取，每个分块读取后立即经过转换器转换并写入输出流，不会将整个对象缓存在内存中
+    pub fn download_to_with_transformer(
+        &self,
+        writer: &mut dyn Write,
+        transformer: &mut dyn StreamTransformer,
+    ) -> IoResult<u64> {
+        let mut written = 0;
+        for chunk in self.chunks(TRANSFORM_CHUNK_SIZE) {
+            let output = transformer.transform(&chunk?)?;
+            writer.write_all(&output)?;
+            written += output.len() as u64;
+        }
+        let output = transformer.finish()?;
+        writer.write_all(&output)?;
+        writer.flush()?;
+        Ok(written + output.len() as u64)
+    }
+
+    /// 下载当前对象到指定路径的文件中，返回写入的字节数
+    ///
+    /// 写入期间对目标文件旁的 `.lock` 文件加排他的建议锁，避免多个下载任务同时写入同一个文件，该文件在下载完成后保留。
+    /// 目标文件已被锁定时，`lock_timeout` 为 None 则立即返回 [`crate::DestinationBusyError`] 错误，
+    /// 否则最多等待 `lock_timeout` 后再返回该错误。
+    ///
+    /// 数据先写入目标文件旁的 `.part` 临时文件，下载成功后原子地重命名为目标文件，下载失败时目标文件保持原样，
+    /// 旧版同步实现直接写入目标文件。
+    ///
+    /// 开启完整性元数据后，对象未变化时再次下载到同一路径将跳过下载，旧版同步实现不支持完整性元数据
+    pub fn download_to_path(
+        &self,
+        path: impl AsRef<Path>,
+        lock_timeout: Option<Duration>,
+    ) -> IoResult<u64> {
+        let path = path.as_ref();
+        if let RangeReaderImpl::Async(range_reader) = &self.0 {
+            return range_reader.download_to_path(path, lock_timeout);
+        }
+        let lock_file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(destination_lock_path(path))?;
+        let mut lock_file = FdRwLock::new(lock_file);
+        let deadline = lock_timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            match lock_file.try_write() {
+                Ok(_guard) => {
+                    let mut file = OpenOptions::new()
+                        .write(true)
+                        .create(true)
+                        .truncate(true)
+                        .open(path)?;
+                    return self.download_to(&mut file);
+                }
+                Err(err) if err.kind() == IoErrorKind::WouldBlock => {
+                    match destination_lock_delay(deadline) {
+                        Some(delay) => sleep(delay),
+                        None => return Err(destination_busy_error(path)),
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// 下载当前对象到指定目录中，返回目标文件路径和写入的字节数
+    ///
+    /// 文件名依次取自 Content-Disposition 响应头（通过 `attname` 指定下载文件名时由服务器返回）、`attname` 和对象名称的最后一级路径，
+    /// 其中的路径将被去除，无法得到合法的文件名时返回 [`std::io::ErrorKind::InvalidInput`] 错误。写入方式与 [`Self::download_to_path`] 相同。
+    ///
+    /// 最大并行重试次数为 0 时使用的同步实现不支持该功能，总是返回 [`std::io::ErrorKind::Unsupported`] 错误
+    pub fn download_to_dir(
+        &self,
+        dir: impl AsRef<Path>,
+        lock_timeout: Option<Duration>,
+    ) -> IoResult<(PathBuf, u64)> {
+        match &self.0 {
+            RangeReaderImpl::Sync(_) => Err(IoError::new(
+                IoErrorKind::Unsupported,
+                "download_to_dir is not supported when max_retry_concurrency is 0",
+            )),
+            RangeReaderImpl::Async(range_reader) => {
+                range_reader.download_to_dir(dir.as_ref(), lock_timeout)
+            }
+        }
+    }
+
     /// 下载对象的最后指定个字节到缓冲区中，返回实际下载的字节数和整个文件的大小
     pub fn read_last_bytes(&self, buf: &mut [u8]) -> IoResult<(u64, u64)> {
         match &self.0 {
@@ -326,6 +1127,19 @@ impl RangeReader {
         }
     }
 
+    /// 按固定大小的分块顺序读取当前对象
+    ///
+    /// 每个分块单独下载，失败时按照下载器的配置重试或切换域名，一旦出错迭代器将不再返回分块。分块大小为 0 时将被视为 1
+    pub fn chunks(&self, chunk_size: usize) -> impl Iterator<Item = IoResult<Vec<u8>>> + '_ {
+        Chunks {
+            reader: self,
+            chunk_size: chunk_size.max(1),
+            pos: 0,
+            file_size: None,
+            done: false,
+        }
+    }
+
     #[cfg(test)]
     pub(crate) fn is_async(&self) -> bool {
         matches!(&self.0, RangeReaderImpl::Async(_))
@@ -340,3 +1154,374 @@ impl ReadAt for RangeReader {
         }
     }
 }
+
+const CHECKSUM_CHUNK_SIZE: u64 = 4 << 20;
+
+fn checksum_range_by_chunks(
+    reader: &impl ReadAt,
+    pos: u64,
+    len: u64,
+    algorithm: ChecksumAlgorithm,
+) -> IoResult<Vec<u8>> {
+    let mut hasher = RangeHasher::new(algorithm);
+    let mut buf = vec![0u8; CHECKSUM_CHUNK_SIZE.min(len) as usize];
+    let mut hashed = 0u64;
+    while hashed < len {
+        let chunk_size = (len - hashed).min(CHECKSUM_CHUNK_SIZE) as usize;
+        let size = reader.read_at(pos + hashed, &mut buf[..chunk_size])?;
+        if size == 0 {
+            break;
+        }
+        hasher.update(&buf[..size]);
+        hashed += size as u64;
+    }
+    Ok(hasher.finalize())
+}
+
+struct Chunks<'r> {
+    reader: &'r RangeReader,
+    chunk_size: usize,
+    pos: u64,
+    file_size: Option<u64>,
+    done: bool,
+}
+
+impl Chunks<'_> {
+    fn next_chunk(&mut self) -> IoResult<Option<Vec<u8>>> {
+        let file_size = match self.file_size {
+            Some(file_size) => file_size,
+            None => *self.file_size.insert(self.reader.file_size()?),
+        };
+        if self.pos >= file_size {
+            return Ok(None);
+        }
+        let size = (file_size - self.pos).min(self.chunk_size as u64) as usize;
+        let mut buf = vec![0u8; size];
+        let mut have_read = 0;
+        while have_read < size {
+            let n = self
+                .reader
+                .read_at(self.pos + have_read as u64, &mut buf[have_read..])?;
+            if n == 0 {
+                return Err(IoError::new(
+                    IoErrorKind::UnexpectedEof,
+                    "object is shorter than expected",
+                ));
+            }
+            have_read += n;
+        }
+        self.pos += size as u64;
+        Ok(Some(buf))
+    }
+}
+
+impl Iterator for Chunks<'_> {
+    type Item = IoResult<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.next_chunk();
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+        result.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{ObjectTooLargeError, StrictModeError},
+        *,
+    };
+    use hyper::header::{HeaderValue, CONTENT_LENGTH, RANGE};
+    use text_io::scan as scan_text;
+    use tokio::{spawn, sync::oneshot::channel, task::spawn_blocking};
+    use warp::{header, http::StatusCode, path, reply::Response, Filter};
+
+    macro_rules! starts_with_server {
+        ($addr:ident, $routes:ident, $code:block) => {{
+            let (tx, rx) = channel();
+            let ($addr, server) =
+                warp::serve($routes).bind_with_graceful_shutdown(([127, 0, 0, 1], 0), async move {
+                    rx.await.unwrap();
+                });
+            spawn(server);
+            $code;
+            tx.send(()).unwrap();
+        }};
+    }
+
+    fn data() -> Vec<u8> {
+        (0..100u8).collect()
+    }
+
+    #[tokio::test]
+    async fn test_range_reader_chunks() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!("file")
+            .and(warp::head())
+            .map(|| {
+                let mut resp = Response::default();
+                resp.headers_mut()
+                    .insert(CONTENT_LENGTH, HeaderValue::from_static("100"));
+                resp
+            })
+            .or(path!("file")
+                .and(header::value(RANGE.as_str()))
+                .map(|range: HeaderValue| {
+                    let from: usize;
+                    let to: usize;
+                    scan_text!(range.to_str().unwrap().bytes() => "bytes={}-{}", from, to);
+                    let mut resp = Response::new(data()[from..=to.min(99)].to_vec().into());
+                    *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+                    resp
+                }));
+
+        starts_with_server!(io_addr, io_routes, {
+            let io_urls = vec![format!("http://{}", io_addr)];
+            spawn_blocking(move || {
+                let downloader = RangeReader::builder(
+                    "bucket",
+                    "file",
+                    Credential::new("1234567890", "abcdefghijk"),
+                    io_urls,
+                )
+                .use_getfile_api(false)
+                .normalize_key(true)
+                .build();
+                let chunks = downloader.chunks(30).collect::<IoResult<Vec<_>>>().unwrap();
+                assert_eq!(
+                    chunks.iter().map(|chunk| chunk.len()).collect::<Vec<_>>(),
+                    [30, 30, 30, 10]
+                );
+                assert_eq!(chunks.concat(), data());
+
+                struct Reverse(Vec<u8>);
+
+                impl StreamTransformer for Reverse {
+                    fn transform(&mut self, input: &[u8]) -> IoResult<Vec<u8>> {
+                        self.0.extend_from_slice(input);
+                        Ok(Vec::new())
+                    }
+
+                    fn finish(&mut self) -> IoResult<Vec<u8>> {
+                        self.0.reverse();
+                        Ok(std::mem::take(&mut self.0))
+                    }
+                }
+
+                let mut output = Vec::new();
+                assert_eq!(
+                    downloader
+                        .download_to_with_transformer(&mut output, &mut Reverse(Vec::new()))
+                        .unwrap(),
+                    100
+                );
+                let mut reversed = data();
+                reversed.reverse();
+                assert_eq!(output, reversed);
+            })
+            .await?;
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_range_reader_report_external_health() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        spawn_blocking(|| {
+            let downloader = RangeReader::builder(
+                "bucket",
+                "file",
+                Credential::new("1234567890", "abcdefghijk"),
+                vec!["http://io1.com".to_owned(), "io2.com".to_owned()],
+            )
+            .build();
+            let error = || IoError::new(IoErrorKind::ConnectionRefused, "health check failed");
+            assert!(downloader.report_external_failure("http://io1.com", error()));
+            assert!(downloader.report_external_failure("http://io2.com", error()));
+            assert!(!downloader.report_external_failure("http://io3.com", error()));
+            assert!(!downloader.report_external_failure(
+                "http://io1.com",
+                IoError::new(IoErrorKind::InvalidData, "bad response"),
+            ));
+            assert!(downloader.report_external_success("http://io1.com"));
+            assert!(!downloader.report_external_success("http://io3.com"));
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_range_reader_ban_and_pin_hosts() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        spawn_blocking(|| {
+            let downloader = RangeReader::builder(
+                "bucket",
+                "file",
+                Credential::new("1234567890", "abcdefghijk"),
+                vec!["http://io1.com".to_owned(), "io2.com".to_owned()],
+            )
+            .build();
+            assert!(downloader.ban_host("http://io2.com", Duration::from_secs(60)));
+            assert!(!downloader.ban_host("http://io3.com", Duration::from_secs(60)));
+            assert!(downloader.ban_host("http://io2.com", Duration::from_secs(0)));
+            assert!(downloader.pin_hosts(&["http://io1.com".to_owned()]));
+            assert!(
+                !downloader.pin_hosts(&["http://io1.com".to_owned(), "http://io3.com".to_owned()])
+            );
+            assert!(downloader.pin_hosts(&[]));
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_range_reader_try_build_in_strict_mode() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        spawn_blocking(|| {
+            let builder = || {
+                RangeReader::builder(
+                    "bucket",
+                    "file",
+                    Credential::new("1234567890", "abcdefghijk"),
+                    vec!["http://io1.com".to_owned()],
+                )
+            };
+            assert!(builder().try_build().is_ok());
+
+            let err = builder().strict(true).try_build().unwrap_err();
+            assert_eq!(err.kind(), IoErrorKind::InvalidInput);
+            assert_eq!(
+                err.get_ref()
+                    .and_then(|err| err.downcast_ref::<StrictModeError>()),
+                Some(&StrictModeError::EmptyMonitorUrls)
+            );
+
+            assert!(builder()
+                .strict(true)
+                .monitor_urls(vec!["http://monitor1.com".to_owned()])
+                .try_build()
+                .is_ok());
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_range_reader_max_object_size() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!("file")
+            .and(warp::head())
+            .map(|| {
+                let mut resp = Response::default();
+                resp.headers_mut()
+                    .insert(CONTENT_LENGTH, HeaderValue::from_static("100"));
+                resp
+            })
+            .or(path!("file").map(|| Response::new(data().into())));
+
+        starts_with_server!(io_addr, io_routes, {
+            let io_urls = vec![format!("http://{}", io_addr)];
+            spawn_blocking(move || {
+                let builder = || {
+                    RangeReader::builder(
+                        "bucket",
+                        "file",
+                        Credential::new("1234567890", "abcdefghijk"),
+                        io_urls.to_owned(),
+                    )
+                    .use_getfile_api(false)
+                    .normalize_key(true)
+                };
+
+                let err = builder()
+                    .max_object_size(99)
+                    .build()
+                    .download()
+                    .unwrap_err();
+                let err = err
+                    .get_ref()
+                    .and_then(|err| err.downcast_ref::<ObjectTooLargeError>())
+                    .unwrap();
+                assert_eq!(err.size(), 100);
+                assert_eq!(err.max_object_size(), 99);
+
+                assert_eq!(
+                    builder().max_object_size(100).build().download().unwrap(),
+                    data()
+                );
+            })
+            .await?;
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_range_reader_stat() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let io_routes = path!("file").and(warp::head()).map(|| {
+            let mut resp = Response::default();
+            resp.headers_mut()
+                .insert(CONTENT_LENGTH, HeaderValue::from_static("100"));
+            resp.headers_mut()
+                .insert("ETag", HeaderValue::from_static("\"etag\""));
+            resp.headers_mut()
+                .insert("Content-Type", HeaderValue::from_static("text/plain"));
+            resp.headers_mut().insert(
+                "X-Qn-Meta-Title",
+                HeaderValue::from_static("%E4%B8%83%E7%89%9B"),
+            );
+            resp
+        });
+
+        starts_with_server!(io_addr, io_routes, {
+            let io_urls = vec![format!("http://{}", io_addr)];
+            spawn_blocking(move || {
+                let builder = || {
+                    RangeReader::builder(
+                        "bucket",
+                        "file",
+                        Credential::new("1234567890", "abcdefghijk"),
+                        io_urls.to_owned(),
+                    )
+                    .use_getfile_api(false)
+                    .normalize_key(true)
+                };
+
+                let metadata = builder().build().stat().unwrap();
+                assert_eq!(metadata.size(), 100);
+                assert_eq!(metadata.etag(), Some("\"etag\""));
+                assert_eq!(metadata.content_type(), Some("text/plain"));
+                assert_eq!(
+                    metadata.user_metadata().get("title").map(String::as_str),
+                    Some("七牛")
+                );
+
+                let err = builder()
+                    .max_retry_concurrency(0)
+                    .build()
+                    .stat()
+                    .unwrap_err();
+                assert_eq!(err.kind(), IoErrorKind::Unsupported);
+            })
+            .await?;
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use super::{
+    async_api::{EgressBudgetExceededError, SyncRequestError},
+    base::download::{
+        is_not_found_status_code, ContentEncodedError, DestinationBusyError, MultiRangesLimitError,
+        ObjectChangedError, ObjectTooLargeError, ProtocolError, StrictModeError,
+        UnexpectedStatusCodeError,
+    },
+};
+use std::{
+    fmt,
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+};
+
+/// 稳定的错误码
+///
+/// 错误码不随错误信息的措辞变化，下载失败的日志和 [`crate::DownloadEvent`] 中均会携带错误码，
+/// 日志采集和告警系统应该匹配错误码而不是错误信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// 对象不存在，错误码为 `not_found`
+    NotFound,
+    /// 对象尺寸超过上限，错误码为 `object_too_large`
+    ObjectTooLarge,
+    /// 会话期间对象被覆盖，错误码为 `object_changed`
+    ObjectChanged,
+    /// 对象以压缩编码存储，不支持范围读取，错误码为 `content_encoded`
+    ContentEncoded,
+    /// 服务器返回了非预期的状态码，错误码为 `unexpected_status_code`
+    UnexpectedStatusCode,
+    /// 服务器响应不符合 HTTP 协议，错误码为 `protocol_error`
+    ProtocolError,
+    /// 下载目标文件正被其他任务写入，错误码为 `destination_busy`
+    DestinationBusy,
+    /// 多范围读取的区域超过限制，错误码为 `multi_ranges_limit`
+    MultiRangesLimit,
+    /// 超出出口流量预算，错误码为 `egress_budget_exceeded`
+    EgressBudgetExceeded,
+    /// 不满足严格模式的配置要求，错误码为 `strict_mode`
+    StrictMode,
+    /// 同步接口的请求排队超时，错误码为 `sync_busy`
+    SyncBusy,
+    /// 请求超时，错误码为 `timed_out`
+    TimedOut,
+    /// 连接失败或连接中断，错误码为 `connection_failed`
+    ConnectionFailed,
+    /// 参数错误，错误码为 `invalid_input`
+    InvalidInput,
+    /// 内部错误，错误码为 `internal`
+    Internal,
+    /// 其他错误，错误码为 `unknown`
+    Unknown,
+}
+
+impl ErrorCode {
+    /// 获取错误的错误码
+    ///
+    /// 优先根据内部错误的类型判断，无法判断时再根据 [`std::io::ErrorKind`] 判断
+    pub fn of(error: &IoError) -> Self {
+        if let Some(inner) = error.get_ref() {
+            if let Some(err) = inner.downcast_ref::<UnexpectedStatusCodeError>() {
+                return if is_not_found_status_code(err.status_code()) {
+                    Self::NotFound
+                } else {
+                    Self::UnexpectedStatusCode
+                };
+            } else if let Some(err) = inner.downcast_ref::<SyncRequestError>() {
+                return match err {
+                    SyncRequestError::Busy { .. } => Self::SyncBusy,
+                    SyncRequestError::TimedOut { .. } => Self::TimedOut,
+                    _ => Self::Internal,
+                };
+            } else if inner.is::<ObjectTooLargeError>() {
+                return Self::ObjectTooLarge;
+            } else if inner.is::<ObjectChangedError>() {
+                return Self::ObjectChanged;
+            } else if inner.is::<ContentEncodedError>() {
+                return Self::ContentEncoded;
+            } else if inner.is::<ProtocolError>() {
+                return Self::ProtocolError;
+            } else if inner.is::<DestinationBusyError>() {
+                return Self::DestinationBusy;
+            } else if inner.is::<MultiRangesLimitError>() {
+                return Self::MultiRangesLimit;
+            } else if inner.is::<EgressBudgetExceededError>() {
+                return Self::EgressBudgetExceeded;
+            } else if inner.is::<StrictModeError>() {
+                return Self::StrictMode;
+            }
+        }
+        match error.kind() {
+            IoErrorKind::NotFound => Self::NotFound,
+            IoErrorKind::TimedOut => Self::TimedOut,
+            IoErrorKind::ConnectionAborted
+            | IoErrorKind::ConnectionRefused
+            | IoErrorKind::ConnectionReset
+            | IoErrorKind::NotConnected
+            | IoErrorKind::BrokenPipe
+            | IoErrorKind::UnexpectedEof => Self::ConnectionFailed,
+            IoErrorKind::InvalidInput => Self::InvalidInput,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// 获取字符串形式的错误码
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::NotFound => "not_found",
+            Self::ObjectTooLarge => "object_too_large",
+            Self::ObjectChanged => "object_changed",
+            Self::ContentEncoded => "content_encoded",
+            Self::UnexpectedStatusCode => "unexpected_status_code",
+            Self::ProtocolError => "protocol_error",
+            Self::DestinationBusy => "destination_busy",
+            Self::MultiRangesLimit => "multi_ranges_limit",
+            Self::EgressBudgetExceeded => "egress_budget_exceeded",
+            Self::StrictMode => "strict_mode",
+            Self::SyncBusy => "sync_busy",
+            Self::TimedOut => "timed_out",
+            Self::ConnectionFailed => "connection_failed",
+            Self::InvalidInput => "invalid_input",
+            Self::Internal => "internal",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::base::download::unexpected_status_code_error, *};
+    use std::time::Duration;
+
+    #[test]
+    fn test_error_code_of() {
+        assert_eq!(
+            ErrorCode::of(&unexpected_status_code_error(404, None)),
+            ErrorCode::NotFound
+        );
+        assert_eq!(
+            ErrorCode::of(&unexpected_status_code_error(502, None)),
+            ErrorCode::UnexpectedStatusCode
+        );
+        assert_eq!(
+            ErrorCode::of(&IoError::new(
+                IoErrorKind::TimedOut,
+                SyncRequestError::Busy {
+                    timeout: Duration::from_secs(1),
+                    queue_depth: 10,
+                },
+            )),
+            ErrorCode::SyncBusy
+        );
+        assert_eq!(
+            ErrorCode::of(&IoError::new(IoErrorKind::TimedOut, "timed out")),
+            ErrorCode::TimedOut
+        );
+        assert_eq!(
+            ErrorCode::of(&IoError::new(IoErrorKind::ConnectionReset, "reset")),
+            ErrorCode::ConnectionFailed
+        );
+        assert_eq!(
+            ErrorCode::of(&IoError::new(IoErrorKind::Other, "other")),
+            ErrorCode::Unknown
+        );
+        assert_eq!(ErrorCode::ObjectTooLarge.to_string(), "object_too_large");
+    }
+}
@@ -0,0 +1,209 @@
+use super::error_code::ErrorCode;
+use once_cell::sync::Lazy;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Mutex,
+    },
+    time::Duration,
+};
+use tokio::sync::broadcast::{channel, Receiver, Sender};
+
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+static EVENT_SENDER: Lazy<Sender<DownloadEvent>> = Lazy::new(|| channel(EVENT_CHANNEL_CAPACITY).0);
+static EVENT_HISTORY_CAPACITY: AtomicUsize = AtomicUsize::new(0);
+static EVENT_HISTORY: Lazy<Mutex<VecDeque<DownloadEvent>>> = Lazy::new(Default::default);
+
+/// 下载器生命周期事件
+///
+/// 通过 [`subscribe_events`] 订阅，可以用于在应用自己的监控面板中展示下载器的运行状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DownloadEvent {
+    /// 域名被惩罚
+    HostPunished {
+        /// 被惩罚的域名
+        host: String,
+        /// 导致惩罚的错误信息
+        error: String,
+        /// 导致惩罚的错误码
+        code: ErrorCode,
+    },
+    /// 被惩罚的域名重新访问成功
+    HostRecovered {
+        /// 恢复的域名
+        host: String,
+    },
+    /// 请求失败，将切换域名重试
+    RetryScheduled {
+        /// API 名称
+        api: String,
+        /// 失败的域名
+        host: String,
+        /// 已经尝试的次数
+        tries: usize,
+    },
+    /// API 调用开始
+    DownloadStarted {
+        /// API 名称
+        api: String,
+        /// 对象名称
+        key: String,
+    },
+    /// API 调用成功
+    DownloadFinished {
+        /// API 名称
+        api: String,
+        /// 对象名称
+        key: String,
+        /// API 调用耗时
+        elapsed: Duration,
+    },
+    /// API 调用失败
+    DownloadFailed {
+        /// API 名称
+        api: String,
+        /// 对象名称
+        key: String,
+        /// 错误信息
+        error: String,
+        /// 错误码
+        code: ErrorCode,
+        /// API 调用耗时
+        elapsed: Duration,
+    },
+    /// API 调用耗时超过慢请求阈值
+    SlowRequest {
+        /// API 名称
+        api: String,
+        /// 对象名称
+        key: String,
+        /// 请求的区域，格式与 HTTP Range 请求头相同
+        range: Option<String>,
+        /// 最后一次请求的域名
+        host: Option<String>,
+        /// 发出的请求次数
+        tries: usize,
+        /// API 调用耗时
+        elapsed: Duration,
+    },
+    /// 从两个域名读取的相同区域数据不一致
+    HostDivergence {
+        /// 对象名称
+        key: String,
+        /// 读取的区域，格式与 HTTP Range 请求头相同
+        range: String,
+        /// 首次读取使用的域名
+        first_host: String,
+        /// 验证读取使用的域名
+        second_host: String,
+        /// 首次读取数据的 CRC32 校验和
+        first_crc32: u32,
+        /// 验证读取数据的 CRC32 校验和
+        second_crc32: u32,
+    },
+    /// 打点记录被写入打点记录文件，等待上传
+    DotRecordsFlushed {
+        /// 写入的记录条数
+        records: usize,
+        /// 写入后打点记录文件的尺寸，单位为字节
+        backlog_size: u64,
+    },
+    /// 打点记录上传成功
+    DotUploadSucceeded,
+    /// 打点记录上传失败
+    DotUploadFailed {
+        /// 错误信息
+        error: String,
+    },
+}
+
+/// 订阅下载器生命周期事件
+///
+/// 事件在整个进程内的所有下载器之间共享，订阅者处理过慢时将丢失最早的事件。
+/// 没有任何订阅者时不会产生事件
+#[inline]
+pub fn subscribe_events() -> Receiver<DownloadEvent> {
+    EVENT_SENDER.subscribe()
+}
+
+/// 设置进程内保留的最近事件数量
+///
+/// 默认为 0，即不保留任何事件。保留的事件可以通过 [`recent_events`] 获取，也会被写入支持包中
+pub fn set_event_history_capacity(capacity: usize) {
+    EVENT_HISTORY_CAPACITY.store(capacity, Relaxed);
+    let mut history = EVENT_HISTORY.lock().unwrap();
+    while history.len() > capacity {
+        history.pop_front();
+    }
+}
+
+/// 获取进程内保留的最近事件，按发生的先后顺序排列
+pub fn recent_events() -> Vec<DownloadEvent> {
+    EVENT_HISTORY.lock().unwrap().iter().cloned().collect()
+}
+
+pub(crate) fn emit_event(f: impl FnOnce() -> DownloadEvent) {
+    let has_receivers = EVENT_SENDER.receiver_count() > 0;
+    let history_capacity = EVENT_HISTORY_CAPACITY.load(Relaxed);
+    if !has_receivers && history_capacity == 0 {
+        return;
+    }
+    let event = f();
+    if history_capacity > 0 {
+        let mut history = EVENT_HISTORY.lock().unwrap();
+        while history.len() >= history_capacity {
+            history.pop_front();
+        }
+        history.push_back(event.to_owned());
+    }
+    if has_receivers {
+        EVENT_SENDER.send(event).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    #[test]
+    fn test_subscribe_events() {
+        let mut receiver = subscribe_events();
+        emit_event(|| DownloadEvent::HostRecovered {
+            host: "http://host1".to_owned(),
+        });
+        emit_event(|| DownloadEvent::DotUploadSucceeded);
+        let mut events = Vec::new();
+        loop {
+            match receiver.try_recv() {
+                Ok(event) => events.push(event),
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        assert!(events.contains(&DownloadEvent::HostRecovered {
+            host: "http://host1".to_owned(),
+        }));
+        assert!(events.contains(&DownloadEvent::DotUploadSucceeded));
+    }
+
+    #[test]
+    fn test_event_history() {
+        set_event_history_capacity(2);
+        for host in ["http://host1", "http://host2", "http://host3"] {
+            emit_event(|| DownloadEvent::HostRecovered {
+                host: host.to_owned(),
+            });
+        }
+        let events = recent_events();
+        set_event_history_capacity(0);
+        assert!(events.len() <= 2);
+        assert!(!events.contains(&DownloadEvent::HostRecovered {
+            host: "http://host1".to_owned(),
+        }));
+        assert!(recent_events().is_empty());
+    }
+}
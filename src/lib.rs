@@ -21,25 +21,84 @@
 //! ## 七牛下载 SDK
 //!
 //! 负责下载完整或部分七牛对象
+//!
+//! ## TLS 后端
+//!
+//! 默认启用 `native-tls` 功能，使用系统的 TLS 实现（Linux 上为 OpenSSL）。
+//! 启用 `rustls-tls` 功能后，所有内部 HTTP 客户端都将改用 rustls，如需彻底移除对 OpenSSL 的依赖，请同时禁用默认功能
+//!
+//! ## io_uring
+//!
+//! 在 Linux 上启用 `io-uring` 功能后，下载到文件时将通过 io_uring 并发写入文件，以减少高速存储设备上的写入瓶颈。
+//! 内核不支持 io_uring（Linux 5.6 之前或被 seccomp 禁用）时自动改用 tokio 的文件写入，其他平台上该功能没有作用
+//!
+//! ## 集成测试
+//!
+//! 启用 `test-backend` 功能后可以使用 `TestBackend` 模拟 IO 服务器，为每个请求指定延迟、状态码、截断或断开连接等行为，
+//! 用于在下游项目中编写确定的集成测试
 
 mod async_api;
 mod base;
 mod config;
 mod download;
+mod error_code;
+mod events;
+#[cfg(feature = "python")]
+mod python;
+mod reader;
+mod settings;
 mod sync_api;
+#[cfg(feature = "test-backend")]
+mod test_backend;
 
+#[cfg(feature = "opendal")]
+pub use async_api::QiniuAccessor;
 pub use async_api::{
-    disable_dot_uploading, disable_dotting, enable_dot_uploading, enable_dotting,
-    is_dot_uploading_disabled, is_dotting_disabled, set_download_start_time,
-    sign_download_url_with_deadline, sign_download_url_with_lifetime, total_download_duration,
-    RangePart,
+    diagnose, diagnose_config, disable_dot_uploading, disable_dotting, egress_budget,
+    enable_dot_uploading, enable_dotting, export_support_bundle, export_support_bundle_for_config,
+    health_snapshot, is_dot_uploading_disabled, is_dotting_disabled, remaining_egress_budget,
+    remove_stale_cache_files, remove_stale_download_files, set_audit_log, set_download_start_time,
+    set_egress_budget, set_trace_mode, sign_download_url_with_deadline,
+    sign_download_url_with_lifetime, total_download_duration, ArchiveFormat, ArchiveProgress,
+    AsyncArchiveDownloader, AsyncMultiFetcher, AsyncObjectFile, AsyncRangeReader, AuditLogConfig,
+    CheckOutcome, DiagnosticReport, EgressBudget, EgressBudgetExceededError, EgressBudgetPolicy,
+    EgressBudgetRemaining, EgressWindow, FileAllocation, HealthSnapshot, HostHealth, HostReport,
+    HostRole, PreloadProgress, RangePart, RangeSpec, ReadOverrides, ReaderStats,
+    ShardedDownloadOptions, ShardedDownloadProgress, StreamTransformer, SyncRequestError,
+    SyncRuntimeLoad, TraceMode, TransformerChain,
+};
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub use async_api::{MultipartError, MultipartParser};
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub use base::gzip::decompress as gzip_decompress;
+pub use base::{
+    checksum::ChecksumAlgorithm,
+    credential::{Credential, RequestAuthorization},
+    download::{
+        ContentEncodedError, DestinationBusyError, MultiRangesLimitError, ObjectChangedError,
+        ObjectMetadata, ObjectTooLargeError, ProtocolError, ProtocolErrorKind, StrictModeError,
+        UnexpectedStatusCodeError,
+    },
+    region_hosts::RegionHosts,
+    upload_policy::UploadPolicy,
+    upload_token::{parse_upload_token, sign_upload_token, ParsedUploadToken, UploadTokenError},
 };
-pub use base::credential::Credential;
 pub use config::{
-    is_qiniu_enabled, set_qiniu_config, set_qiniu_multi_clusters_config,
+    is_qiniu_enabled, purge_connections, set_qiniu_config, set_qiniu_multi_clusters_config,
     set_qiniu_single_cluster_config, with_current_qiniu_config, with_current_qiniu_config_mut,
-    ClustersConfigParseError, Config, ConfigBuilder, Configurable, MultipleClustersConfig,
-    MultipleClustersConfigBuilder, MultipleClustersConfigParseError, SingleClusterConfig,
-    SingleClusterConfigBuilder,
+    ClustersConfigParseError, Config, ConfigBuilder, Configurable, ConnectionFailureKind,
+    FailoverBackoff, HostUrl, HostUrlError, KeyRewriteRule, MultipleClustersConfig,
+    MultipleClustersConfigBuilder, MultipleClustersConfigParseError, RangeRequestEncoding,
+    RedirectPolicy, SingleClusterConfig, SingleClusterConfigBuilder, TimeoutApi, Timeouts,
+    TimeoutsConfig,
 };
 pub use download::{RangeReader, RangeReaderBuilder};
+pub use error_code::ErrorCode;
+pub use events::{recent_events, set_event_history_capacity, subscribe_events, DownloadEvent};
+pub use reader::{AsyncObjectReader, ObjectReader};
+pub use settings::{global_settings, GlobalSettings};
+pub use sync_api::WriteSeek;
+#[cfg(feature = "test-backend")]
+pub use test_backend::{TestBackend, TestBehavior};
@@ -0,0 +1,159 @@
+// pyo3 0.22 的 #[pymethods] 展开代码会触发该 lint
+#![allow(clippy::useless_conversion)]
+
+use super::{Credential, RangeReader};
+use positioned_io::ReadAt;
+use pyo3::{prelude::*, types::PyBytes};
+use std::{fs::File, time::Duration};
+
+/// Python 中的对象范围下载器
+///
+/// 所有阻塞调用在执行期间都会释放 GIL，因此可以在多个 Python 线程中并发使用
+#[pyclass(name = "RangeReader", module = "qiniu_download")]
+struct PyRangeReader(RangeReader);
+
+#[pymethods]
+impl PyRangeReader {
+    #[new]
+    #[pyo3(signature = (
+        bucket,
+        key,
+        access_key,
+        secret_key,
+        io_urls,
+        uc_urls = None,
+        monitor_urls = None,
+        io_tries = None,
+        base_timeout_ms = None,
+        connect_timeout_ms = None,
+        private_url_lifetime_s = None,
+        max_retry_concurrency = None,
+        use_https = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        bucket: String,
+        key: String,
+        access_key: String,
+        secret_key: String,
+        io_urls: Vec<String>,
+        uc_urls: Option<Vec<String>>,
+        monitor_urls: Option<Vec<String>>,
+        io_tries: Option<usize>,
+        base_timeout_ms: Option<u64>,
+        connect_timeout_ms: Option<u64>,
+        private_url_lifetime_s: Option<u64>,
+        max_retry_concurrency: Option<u32>,
+        use_https: Option<bool>,
+    ) -> Self {
+        let mut builder = RangeReader::builder(
+            bucket,
+            key,
+            Credential::new(access_key, secret_key),
+            io_urls,
+        )
+        .private_url_lifetime(private_url_lifetime_s.map(Duration::from_secs));
+        if let Some(uc_urls) = uc_urls {
+            builder = builder.uc_urls(uc_urls);
+        }
+        if let Some(monitor_urls) = monitor_urls {
+            builder = builder.monitor_urls(monitor_urls);
+        }
+        if let Some(io_tries) = io_tries {
+            builder = builder.io_tries(io_tries);
+        }
+        if let Some(base_timeout_ms) = base_timeout_ms {
+            builder = builder.base_timeout(Duration::from_millis(base_timeout_ms));
+        }
+        if let Some(connect_timeout_ms) = connect_timeout_ms {
+            builder = builder.connect_timeout(Duration::from_millis(connect_timeout_ms));
+        }
+        if let Some(max_retry_concurrency) = max_retry_concurrency {
+            builder = builder.max_retry_concurrency(max_retry_concurrency);
+        }
+        if let Some(use_https) = use_https {
+            builder = builder.use_https(use_https);
+        }
+        Self(builder.build())
+    }
+
+    #[staticmethod]
+    fn from_env(key: String) -> Option<Self> {
+        RangeReader::from_env(key).map(Self)
+    }
+
+    fn update_urls(&self, py: Python<'_>) -> PyResult<bool> {
+        Ok(py.allow_threads(|| self.0.update_urls())?)
+    }
+
+    fn io_urls(&self, py: Python<'_>) -> Vec<String> {
+        py.allow_threads(|| self.0.io_urls())
+    }
+
+    fn read_at(&self, py: Python<'_>, pos: u64, size: usize) -> PyResult<Py<PyBytes>> {
+        let buf = py.allow_threads(|| {
+            let mut buf = vec![0u8; size];
+            let have_read = self.0.read_at(pos, &mut buf)?;
+            buf.truncate(have_read);
+            Ok::<_, PyErr>(buf)
+        })?;
+        Ok(PyBytes::new_bound(py, &buf).unbind())
+    }
+
+    fn read_multi_ranges(
+        &self,
+        py: Python<'_>,
+        ranges: Vec<(u64, u64)>,
+    ) -> PyResult<Vec<(u64, u64, Py<PyBytes>)>> {
+        let parts = py.allow_threads(|| self.0.read_multi_ranges(&ranges))?;
+        Ok(parts
+            .into_iter()
+            .map(|part| {
+                (
+                    part.range.0,
+                    part.range.1,
+                    PyBytes::new_bound(py, &part.data).unbind(),
+                )
+            })
+            .collect())
+    }
+
+    fn exist(&self, py: Python<'_>) -> PyResult<bool> {
+        Ok(py.allow_threads(|| self.0.exist())?)
+    }
+
+    fn file_size(&self, py: Python<'_>) -> PyResult<u64> {
+        Ok(py.allow_threads(|| self.0.file_size())?)
+    }
+
+    fn download(&self, py: Python<'_>) -> PyResult<Py<PyBytes>> {
+        let data = py.allow_threads(|| self.0.download())?;
+        Ok(PyBytes::new_bound(py, &data).unbind())
+    }
+
+    fn download_to_file(&self, py: Python<'_>, path: String) -> PyResult<u64> {
+        Ok(py.allow_threads(|| {
+            let mut file = File::create(path)?;
+            self.0.download_to(&mut file)
+        })?)
+    }
+
+    fn read_last_bytes(&self, py: Python<'_>, size: usize) -> PyResult<(Py<PyBytes>, u64)> {
+        let (buf, total_size) = py.allow_threads(|| {
+            let mut buf = vec![0u8; size];
+            let (have_read, total_size) = self.0.read_last_bytes(&mut buf)?;
+            buf.truncate(have_read as usize);
+            Ok::<_, PyErr>((buf, total_size))
+        })?;
+        Ok((PyBytes::new_bound(py, &buf).unbind(), total_size))
+    }
+}
+
+/// Python 模块入口
+///
+/// 需要以 cdylib 形式编译，并启用 `python` 功能
+#[pymodule]
+fn qiniu_download(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyRangeReader>()?;
+    Ok(())
+}
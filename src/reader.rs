@@ -0,0 +1,149 @@
+use super::{async_api::AsyncRangeReader, download::RangeReader, sync_api::WriteSeek};
+use async_trait::async_trait;
+use positioned_io::ReadAt;
+use std::io::Result as IoResult;
+use tokio::io::AsyncWrite;
+
+/// 对象读取器
+///
+/// 对同步下载器的抽象，可以作为 trait object 使用，便于在测试中替换实现。
+///
+/// 同步和异步读取器无法合并为一个 trait，也无法由其中一个自动实现另一个：
+/// `max_retry_concurrency` 为 0 时 [`RangeReader`] 使用阻塞式的实现，没有可供等待的异步接口；
+/// 而在异步代码中阻塞等待 [`AsyncRangeReader`] 会占用运行时的工作线程，在当前线程运行时中甚至会导致死锁。
+/// 异步代码请使用 [`AsyncObjectReader`]
+pub trait ObjectReader: Send + Sync {
+    /// 从指定偏移量开始读取数据到缓冲区中，返回实际读取的字节数
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> IoResult<usize>;
+
+    /// 获取对象的文件大小
+    fn file_size(&self) -> IoResult<u64>;
+
+    /// 下载对象到指定输出流中，返回下载的字节数
+    fn download_to(&self, writer: &mut dyn WriteSeek) -> IoResult<u64>;
+}
+
+/// 异步对象读取器
+///
+/// 对异步下载器的抽象，可以作为 trait object 使用，便于在测试中替换实现。
+/// 与 [`ObjectReader`] 分开定义的原因见其文档
+#[async_trait]
+pub trait AsyncObjectReader: Send + Sync {
+    /// 从指定偏移量开始读取数据到缓冲区中，返回实际读取的字节数
+    async fn read_at(&self, pos: u64, buf: &mut [u8]) -> IoResult<usize>;
+
+    /// 获取对象的文件大小
+    async fn file_size(&self) -> IoResult<u64>;
+
+    /// 下载对象到指定输出流中，返回下载的字节数
+    async fn download_to(&self, writer: &mut (dyn AsyncWrite + Unpin + Send)) -> IoResult<u64>;
+}
+
+impl ObjectReader for RangeReader {
+    #[inline]
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> IoResult<usize> {
+        ReadAt::read_at(self, pos, buf)
+    }
+
+    #[inline]
+    fn file_size(&self) -> IoResult<u64> {
+        RangeReader::file_size(self)
+    }
+
+    #[inline]
+    fn download_to(&self, writer: &mut dyn WriteSeek) -> IoResult<u64> {
+        RangeReader::download_to(self, writer)
+    }
+}
+
+#[async_trait]
+impl AsyncObjectReader for AsyncRangeReader {
+    #[inline]
+    async fn read_at(&self, pos: u64, buf: &mut [u8]) -> IoResult<usize> {
+        AsyncRangeReader::read_at(self, pos, buf).await
+    }
+
+    #[inline]
+    async fn file_size(&self) -> IoResult<u64> {
+        AsyncRangeReader::file_size(self).await
+    }
+
+    #[inline]
+    async fn download_to(&self, writer: &mut (dyn AsyncWrite + Unpin + Send)) -> IoResult<u64> {
+        AsyncRangeReader::download_to(self, writer).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Error as IoError, ErrorKind as IoErrorKind};
+
+    struct MemoryReader(Vec<u8>);
+
+    impl ObjectReader for MemoryReader {
+        fn read_at(&self, pos: u64, buf: &mut [u8]) -> IoResult<usize> {
+            let data = self.0.get(pos as usize..).unwrap_or_default();
+            let size = data.len().min(buf.len());
+            buf[..size].copy_from_slice(&data[..size]);
+            Ok(size)
+        }
+
+        fn file_size(&self) -> IoResult<u64> {
+            Ok(self.0.len() as u64)
+        }
+
+        fn download_to(&self, writer: &mut dyn WriteSeek) -> IoResult<u64> {
+            writer.write_all(&self.0)?;
+            Ok(self.0.len() as u64)
+        }
+    }
+
+    #[async_trait]
+    impl AsyncObjectReader for MemoryReader {
+        async fn read_at(&self, pos: u64, buf: &mut [u8]) -> IoResult<usize> {
+            ObjectReader::read_at(self, pos, buf)
+        }
+
+        async fn file_size(&self) -> IoResult<u64> {
+            ObjectReader::file_size(self)
+        }
+
+        async fn download_to(
+            &self,
+            _writer: &mut (dyn AsyncWrite + Unpin + Send),
+        ) -> IoResult<u64> {
+            Err(IoError::new(IoErrorKind::Unsupported, "not supported"))
+        }
+    }
+
+    fn read_tail(reader: &dyn ObjectReader, size: u64) -> IoResult<Vec<u8>> {
+        let file_size = reader.file_size()?;
+        let mut buf = vec![0u8; size as usize];
+        let have_read = reader.read_at(file_size.saturating_sub(size), &mut buf)?;
+        buf.truncate(have_read);
+        Ok(buf)
+    }
+
+    #[test]
+    fn test_object_reader_as_trait_object() -> IoResult<()> {
+        let reader = MemoryReader(b"hello world".to_vec());
+        assert_eq!(read_tail(&reader, 5)?, b"world");
+
+        let mut cursor = Cursor::new(Vec::new());
+        assert_eq!(ObjectReader::download_to(&reader, &mut cursor)?, 11);
+        assert_eq!(cursor.into_inner(), b"hello world");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_object_reader_as_trait_object() -> IoResult<()> {
+        let reader: Box<dyn AsyncObjectReader> = Box::new(MemoryReader(b"hello world".to_vec()));
+        assert_eq!(reader.file_size().await?, 11);
+
+        let mut buf = [0u8; 8];
+        assert_eq!(reader.read_at(6, &mut buf).await?, 5);
+        assert_eq!(&buf[..5], b"world");
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use super::async_api::{disable_dotting, enable_dotting, is_dotting_disabled};
+use std::{
+    convert::TryFrom,
+    sync::atomic::{
+        AtomicU32, AtomicU64, AtomicUsize,
+        Ordering::{Relaxed, SeqCst},
+    },
+    time::Duration,
+};
+
+static GLOBAL_SETTINGS: GlobalSettings = GlobalSettings::new();
+
+/// 获取全局运行时设置
+///
+/// 所有设置项均可在运行时随时修改，修改后对后续发起的请求立即生效
+#[inline]
+pub fn global_settings() -> &'static GlobalSettings {
+    &GLOBAL_SETTINGS
+}
+
+/// 全局运行时设置
+///
+/// 通过 [`global_settings`] 获取，所有设置项均为原子变量，可以在多线程中安全修改
+#[derive(Debug)]
+pub struct GlobalSettings {
+    io_tries: AtomicUsize,
+    base_timeout_multiplier: AtomicU64,
+    max_retry_concurrency: AtomicU32,
+    log_sampling: AtomicU32,
+    log_counter: AtomicU64,
+    slow_request_threshold_ms: AtomicU64,
+}
+
+const DEFAULT_BASE_TIMEOUT_MULTIPLIER: u64 = 0x3FF0_0000_0000_0000; // 1.0f64.to_bits()
+
+impl GlobalSettings {
+    const fn new() -> Self {
+        Self {
+            io_tries: AtomicUsize::new(0),
+            base_timeout_multiplier: AtomicU64::new(DEFAULT_BASE_TIMEOUT_MULTIPLIER),
+            max_retry_concurrency: AtomicU32::new(0),
+            log_sampling: AtomicU32::new(1),
+            log_counter: AtomicU64::new(0),
+            slow_request_threshold_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// 打点功能是否启用
+    #[inline]
+    pub fn is_dotting_enabled(&self) -> bool {
+        !is_dotting_disabled()
+    }
+
+    /// 启用或禁止打点功能
+    #[inline]
+    pub fn set_dotting_enabled(&self, enabled: bool) -> &Self {
+        if enabled {
+            enable_dotting();
+        } else {
+            disable_dotting();
+        }
+        self
+    }
+
+    /// 获取覆盖的 IO 重试次数
+    ///
+    /// 返回 None 表示使用构建下载器时设置的重试次数
+    #[inline]
+    pub fn io_tries(&self) -> Option<usize> {
+        match self.io_tries.load(Relaxed) {
+            0 => None,
+            tries => Some(tries),
+        }
+    }
+
+    /// 设置覆盖的 IO 重试次数
+    ///
+    /// 设置为 None 或 0 表示使用构建下载器时设置的重试次数
+    #[inline]
+    pub fn set_io_tries(&self, tries: Option<usize>) -> &Self {
+        self.io_tries.store(tries.unwrap_or(0), Relaxed);
+        self
+    }
+
+    /// 获取基础超时时长倍数
+    #[inline]
+    pub fn base_timeout_multiplier(&self) -> f64 {
+        f64::from_bits(self.base_timeout_multiplier.load(Relaxed))
+    }
+
+    /// 设置基础超时时长倍数
+    ///
+    /// 并发重试时每个异步任务的超时时长将乘以该倍数，默认为 1.0，非正数或非有限值将被忽略
+    #[inline]
+    pub fn set_base_timeout_multiplier(&self, multiplier: f64) -> &Self {
+        if multiplier.is_finite() && multiplier > 0.0 {
+            self.base_timeout_multiplier
+                .store(multiplier.to_bits(), Relaxed);
+        }
+        self
+    }
+
+    /// 获取覆盖的最大并发重试数
+    ///
+    /// 返回 None 表示使用构建下载器时设置的最大并发重试数
+    #[inline]
+    pub fn max_retry_concurrency(&self) -> Option<u32> {
+        match self.max_retry_concurrency.load(Relaxed) {
+            0 => None,
+            concurrency => Some(concurrency),
+        }
+    }
+
+    /// 设置覆盖的最大并发重试数
+    ///
+    /// 设置为 None 或 0 表示使用构建下载器时设置的最大并发重试数。
+    /// 该设置无法将异步下载器切换为同步下载器，反之亦然
+    #[inline]
+    pub fn set_max_retry_concurrency(&self, concurrency: Option<u32>) -> &Self {
+        self.max_retry_concurrency
+            .store(concurrency.unwrap_or(0), Relaxed);
+        self
+    }
+
+    /// 获取日志采样率，即每 N 次调用输出一次详细日志
+    #[inline]
+    pub fn log_sampling(&self) -> u32 {
+        self.log_sampling.load(Relaxed)
+    }
+
+    /// 设置日志采样率，即每 N 次调用输出一次详细日志
+    ///
+    /// 默认为 1，即每次调用都输出详细日志，设置为 0 将被视为 1
+    #[inline]
+    pub fn set_log_sampling(&self, sampling: u32) -> &Self {
+        self.log_sampling.store(sampling.max(1), Relaxed);
+        self
+    }
+
+    /// 获取慢请求阈值
+    ///
+    /// 返回 None 表示未启用慢请求日志
+    #[inline]
+    pub fn slow_request_threshold(&self) -> Option<Duration> {
+        match self.slow_request_threshold_ms.load(Relaxed) {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+
+    /// 设置慢请求阈值
+    ///
+    /// 耗时超过该阈值的 API 调用将以 JSON 格式输出一条警告日志，包括对象名称、区域、域名、请求次数和各阶段的耗时，
+    /// 同时累加健康状态快照中的慢请求计数并产生 [`crate::DownloadEvent::SlowRequest`] 事件。
+    /// 慢请求日志使用独立的日志目标 `qiniu_download::slow_request`，可以不受其他日志级别的影响单独开启。
+    /// 设置为 None 或 0 表示关闭慢请求日志，默认关闭
+    #[inline]
+    pub fn set_slow_request_threshold(&self, threshold: Option<Duration>) -> &Self {
+        let ms = threshold.map_or(0, |threshold| {
+            u64::try_from(threshold.as_millis()).unwrap_or(u64::MAX)
+        });
+        self.slow_request_threshold_ms.store(ms, Relaxed);
+        self
+    }
+
+    pub(crate) fn apply_base_timeout_multiplier(&self, timeout: Duration) -> Duration {
+        let multiplier = self.base_timeout_multiplier();
+        if (multiplier - 1.0).abs() < f64::EPSILON {
+            timeout
+        } else {
+            Duration::from_secs_f64(timeout.as_secs_f64() * multiplier)
+        }
+    }
+
+    pub(crate) fn should_log(&self) -> bool {
+        let sampling = u64::from(self.log_sampling());
+        sampling <= 1
+            || self.log_counter.fetch_update(SeqCst, SeqCst, |count| {
+                Some(if count + 1 >= sampling { 0 } else { count + 1 })
+            }) == Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_settings() {
+        let settings = GlobalSettings::new();
+        assert_eq!(settings.io_tries(), None);
+        assert_eq!(settings.max_retry_concurrency(), None);
+        assert_eq!(settings.base_timeout_multiplier(), 1.0);
+        assert_eq!(settings.log_sampling(), 1);
+        assert_eq!(settings.slow_request_threshold(), None);
+
+        settings
+            .set_io_tries(Some(3))
+            .set_max_retry_concurrency(Some(2))
+            .set_base_timeout_multiplier(2.5)
+            .set_log_sampling(0)
+            .set_slow_request_threshold(Some(Duration::from_millis(500)));
+        assert_eq!(settings.io_tries(), Some(3));
+        assert_eq!(settings.max_retry_concurrency(), Some(2));
+        assert_eq!(settings.base_timeout_multiplier(), 2.5);
+        assert_eq!(settings.log_sampling(), 1);
+        assert_eq!(
+            settings.slow_request_threshold(),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            settings.apply_base_timeout_multiplier(Duration::from_millis(100)),
+            Duration::from_millis(250)
+        );
+
+        settings
+            .set_base_timeout_multiplier(-1.0)
+            .set_base_timeout_multiplier(f64::NAN);
+        assert_eq!(settings.base_timeout_multiplier(), 2.5);
+
+        settings
+            .set_io_tries(None)
+            .set_max_retry_concurrency(Some(0))
+            .set_slow_request_threshold(Some(Duration::from_millis(0)));
+        assert_eq!(settings.io_tries(), None);
+        assert_eq!(settings.max_retry_concurrency(), None);
+        assert_eq!(settings.slow_request_threshold(), None);
+    }
+
+    #[test]
+    fn test_log_sampling() {
+        let settings = GlobalSettings::new();
+        assert!((0..10).all(|_| settings.should_log()));
+
+        settings.set_log_sampling(4);
+        assert_eq!((0..40).filter(|_| settings.should_log()).count(), 10);
+    }
+}
@@ -1,9 +1,19 @@
 use super::{
     super::{
-        async_api::{is_dot_uploading_disabled, is_dotting_disabled},
+        async_api::{
+            is_dot_uploading_disabled, is_dotting_disabled, record_dot_backlog_cleared,
+            record_dot_records_flushed, record_dot_upload,
+        },
         base::{
-            credential::Credential, upload_policy::UploadPolicy, upload_token::sign_upload_token,
+            credential::Credential,
+            dot_sampler::DotSampler,
+            download::{
+                strict_mode_error, RangeReaderBuilder as BaseRangeReaderBuilder, StrictModeError,
+            },
+            upload_policy::UploadPolicy,
+            upload_token::sign_upload_token,
         },
+        events::{emit_event, DownloadEvent},
     },
     cache_dir::cache_dir_path_of,
     host_selector::{HostSelector, PunishResult},
@@ -24,7 +34,10 @@ use std::{
         SeekFrom, Write,
     },
     ops::Deref,
-    sync::{Arc, Mutex},
+    sync::{
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
     thread::Builder as ThreadBuilder,
     time::{Duration, Instant, SystemTime},
 };
@@ -79,12 +92,21 @@ impl fmt::Display for ApiName {
 #[derive(Clone, Debug, Default)]
 pub(super) struct Dotter {
     inner: Option<Arc<DotterInner>>,
+    sampler: Arc<DotSampler>,
+    _ticker: Option<Arc<DotterTicker>>,
+}
+
+/// 后台定时上传打点记录的线程，最后一个 [`Dotter`] 被释放时退出
+#[derive(Debug)]
+struct DotterTicker {
+    _sender: Mutex<Sender<()>>,
 }
 
 #[derive(Debug)]
 struct DotterInner {
     credential: Credential,
     bucket: String,
+    token_lifetime: Duration,
     monitor_selector: HostSelector,
     buffered_records: DotRecordsDashMap,
     buffered_file: Mutex<FdRwLock<File>>,
@@ -97,6 +119,26 @@ struct DotterInner {
 
 pub(super) const DOT_FILE_NAME: &str = "dot-file";
 
+/// 检查严格模式下的配置要求，未启用严格模式时总是返回成功
+///
+/// 打点缓存文件由同步和异步实现共用，因此两种实现都可以在构建前使用该检查
+pub(crate) fn check_strict_mode(builder: &BaseRangeReaderBuilder) -> IOResult<()> {
+    if !builder.strict {
+        return Ok(());
+    }
+    if builder.monitor_urls.is_empty() {
+        return Err(strict_mode_error(StrictModeError::EmptyMonitorUrls));
+    }
+    cache_dir_path_of(DOT_FILE_NAME)
+        .and_then(|path| OpenOptions::new().create(true).append(true).open(path))
+        .map_err(|err| {
+            strict_mode_error(StrictModeError::CacheDirNotWritable {
+                reason: err.to_string(),
+            })
+        })?;
+    Ok(())
+}
+
 impl Dotter {
     #[allow(clippy::too_many_arguments)]
     pub(super) fn new(
@@ -111,6 +153,7 @@ impl Dotter {
         max_punished_times: Option<usize>,
         max_punished_hosts_percent: Option<u8>,
         base_timeout: Option<Duration>,
+        token_lifetime: Option<Duration>,
     ) -> Dotter {
         if !monitor_urls.is_empty() {
             if let Ok(buffered_file_path) = cache_dir_path_of(DOT_FILE_NAME) {
@@ -125,24 +168,33 @@ impl Dotter {
                         .max_punished_hosts_percent(max_punished_hosts_percent.unwrap_or(50))
                         .base_timeout(base_timeout.unwrap_or_else(|| Duration::from_secs(1)))
                         .build();
+                    let inner = Arc::new(DotterInner {
+                        credential,
+                        bucket,
+                        token_lifetime: token_lifetime.unwrap_or_else(|| Duration::from_secs(30)),
+                        monitor_selector,
+                        http_client,
+                        buffered_records: Default::default(),
+                        buffered_file: Mutex::new(FdRwLock::new(buffer_file)),
+                        interval: interval.unwrap_or_else(|| Duration::from_secs(10)),
+                        uploaded_at: Instant::now(),
+                        max_buffer_size: max_buffer_size.unwrap_or(1 << 20),
+                        tries: tries.unwrap_or(10),
+                    });
                     return Self {
-                        inner: Some(Arc::new(DotterInner {
-                            credential,
-                            bucket,
-                            monitor_selector,
-                            http_client,
-                            buffered_records: Default::default(),
-                            buffered_file: Mutex::new(FdRwLock::new(buffer_file)),
-                            interval: interval.unwrap_or_else(|| Duration::from_secs(10)),
-                            uploaded_at: Instant::now(),
-                            max_buffer_size: max_buffer_size.unwrap_or(1 << 20),
-                            tries: tries.unwrap_or(10),
-                        })),
+                        _ticker: DotterTicker::spawn(&inner),
+                        inner: Some(inner),
+                        sampler: Default::default(),
                     };
                 }
             }
         }
-        Self { inner: None }
+        Default::default()
+    }
+
+    pub(super) fn with_sampler(mut self, sampler: DotSampler) -> Self {
+        self.sampler = Arc::new(sampler);
+        self
     }
 
     pub(super) fn dot(
@@ -155,9 +207,17 @@ impl Dotter {
         if is_dotting_disabled() {
             debug!("dotting is disabled")
         } else if let Some(inner) = self.inner.as_ref() {
-            inner.fast_dot(dot_type, api_name, successful, elapsed_duration);
+            let count = match self.sampler.sample() {
+                Some(count) => count,
+                None => return Ok(()),
+            };
+            inner.fast_dot(dot_type, api_name, successful, elapsed_duration, count);
             inner.lock_buffered_file(|buffered_file| {
                 inner.flush_to_file(buffered_file)?;
+                if self.sampler.escalates() {
+                    self.sampler
+                        .observe_buffer_size(buffered_file.metadata()?.len());
+                }
                 if inner.is_time_to_upload(buffered_file)? {
                     self.async_upload();
                 }
@@ -206,6 +266,38 @@ impl Dotter {
     }
 }
 
+impl DotterTicker {
+    fn spawn(inner: &Arc<DotterInner>) -> Option<Arc<Self>> {
+        let interval = inner.interval;
+        if interval == Duration::from_millis(0) {
+            return None;
+        }
+        let (tx, rx) = channel::<()>();
+        let inner = Arc::downgrade(inner);
+        ThreadBuilder::new()
+            .name("dots-ticker".into())
+            .spawn(move || {
+                // 发送端被释放时 recv_timeout 返回 Disconnected，线程随之退出
+                while let Err(RecvTimeoutError::Timeout) = rx.recv_timeout(interval) {
+                    match inner.upgrade() {
+                        Some(inner) => {
+                            inner
+                                .upload_pending()
+                                .tap_err(|err| warn!("failed to upload pending dots: {:?}", err))
+                                .ok();
+                        }
+                        None => break,
+                    }
+                }
+            })
+            .tap_err(|err| warn!("failed to start thread `dots-ticker`: {:?}", err))
+            .ok()?;
+        Some(Arc::new(Self {
+            _sender: Mutex::new(tx),
+        }))
+    }
+}
+
 impl DotterInner {
     fn fast_dot(
         &self,
@@ -213,12 +305,13 @@ impl DotterInner {
         api_name: ApiName,
         successful: bool,
         elapsed_duration: Duration,
+        count: usize,
     ) {
         let record = if successful {
             DotRecord::new(
                 dot_type,
                 api_name,
-                1,
+                count,
                 Default::default(),
                 elapsed_duration.as_millis(),
                 Default::default(),
@@ -228,7 +321,7 @@ impl DotterInner {
                 dot_type,
                 api_name,
                 Default::default(),
-                1,
+                count,
                 Default::default(),
                 elapsed_duration.as_millis(),
             )
@@ -242,8 +335,17 @@ impl DotterInner {
     }
 
     fn flush_to_file(&self, buffered_file: &mut File) -> IOResult<()> {
-        self.buffered_records
-            .retain(|_, r| write_to_file(r, buffered_file).is_err());
+        let mut records = 0;
+        self.buffered_records.retain(|_, r| {
+            let failed = write_to_file(r, buffered_file).is_err();
+            if !failed {
+                records += 1;
+            }
+            failed
+        });
+        if records > 0 {
+            record_dot_records_flushed(records, buffered_file.metadata()?.len());
+        }
 
         return Ok(());
 
@@ -271,6 +373,19 @@ impl DotterInner {
         Ok(result)
     }
 
+    fn upload_pending(&self) -> IOResult<()> {
+        if is_dotting_disabled() || is_dot_uploading_disabled() {
+            return Ok(());
+        }
+        self.lock_buffered_file(|buffered_file| {
+            self.flush_to_file(buffered_file)?;
+            if buffered_file.metadata()?.len() > 0 {
+                self.sync_upload()?;
+            }
+            Ok(())
+        })
+    }
+
     fn sync_upload(&self) -> IOResult<()> {
         let mut buffered_file = OpenOptions::new()
             .read(true)
@@ -283,7 +398,7 @@ impl DotterInner {
                 &self.credential,
                 &UploadPolicy::new_for_bucket(
                     self.bucket.to_owned(),
-                    SystemTime::now() + Duration::from_secs(30),
+                    SystemTime::now() + self.token_lifetime,
                 ),
             );
             let begin_at = Instant::now();
@@ -316,13 +431,25 @@ impl DotterInner {
                         ApiName::MonitorV1Stat,
                         result.is_ok(),
                         begin_at.elapsed(),
+                        1,
                     );
                 })
-                .tap_ok(|_| info!("upload dots succeed"))
-                .tap_err(|err| warn!("failed to upload dots: {:?}", err))?;
+                .tap_ok(|_| {
+                    info!("upload dots succeed");
+                    record_dot_upload(None);
+                    emit_event(|| DownloadEvent::DotUploadSucceeded);
+                })
+                .tap_err(|err| {
+                    warn!("failed to upload dots: {:?}", err);
+                    record_dot_upload(Some(err));
+                    emit_event(|| DownloadEvent::DotUploadFailed {
+                        error: err.to_string(),
+                    });
+                })?;
             Ok(())
         })?;
         buffered_file.set_len(0)?;
+        record_dot_backlog_cleared();
         Ok(())
     }
 
@@ -499,7 +626,6 @@ impl DotRecord {
     }
 
     #[cfg(test)]
-
     pub(super) fn dot_type(&self) -> Option<DotType> {
         match self {
             Self::APICalls(record) => Some(record.dot_type),
@@ -508,7 +634,6 @@ impl DotRecord {
     }
 
     #[cfg(test)]
-
     pub(super) fn api_name(&self) -> Option<ApiName> {
         match self {
             Self::APICalls(record) => Some(record.api_name),
@@ -517,7 +642,6 @@ impl DotRecord {
     }
 
     #[cfg(test)]
-
     pub(super) fn success_count(&self) -> Option<usize> {
         match self {
             Self::APICalls(record) => Some(record.success_count),
@@ -526,7 +650,6 @@ impl DotRecord {
     }
 
     #[cfg(test)]
-
     pub(super) fn success_avg_elapsed_duration_ms(&self) -> Option<u128> {
         match self {
             Self::APICalls(record) => Some(record.success_avg_elapsed_duration),
@@ -535,7 +658,6 @@ impl DotRecord {
     }
 
     #[cfg(test)]
-
     pub(super) fn failed_count(&self) -> Option<usize> {
         match self {
             Self::APICalls(record) => Some(record.failed_count),
@@ -544,7 +666,6 @@ impl DotRecord {
     }
 
     #[cfg(test)]
-
     pub(super) fn failed_avg_elapsed_duration_ms(&self) -> Option<u128> {
         match self {
             Self::APICalls(record) => Some(record.failed_avg_elapsed_duration),
@@ -553,7 +674,6 @@ impl DotRecord {
     }
 
     #[cfg(test)]
-
     pub(super) fn punished_count(&self) -> Option<usize> {
         match self {
             Self::PunishedCount(record) => Some(record.punished_count),
@@ -586,7 +706,6 @@ pub(super) struct DotRecords {
 
 impl DotRecords {
     #[cfg(test)]
-
     pub(super) fn records(&self) -> &[DotRecord] {
         self.records.as_ref()
     }
@@ -727,7 +846,7 @@ impl Deref for DotRecordsDashMap {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Timeouts;
+    use crate::config::HttpClientTimeouts;
     use futures::channel::oneshot::channel;
     use rayon::ThreadPoolBuilder;
     use std::{
@@ -805,7 +924,7 @@ mod tests {
         starts_with_server!(addr, routes, {
             spawn_blocking(move || {
                 let dotter = Dotter::new(
-                    Timeouts::default_http_client(),
+                    HttpClientTimeouts::default_http_client(),
                     get_credential(),
                     BUCKET_NAME.to_owned(),
                     vec![],
@@ -816,6 +935,7 @@ mod tests {
                     None,
                     None,
                     None,
+                    None,
                 );
                 assert!(dotter.inner.is_none());
                 dotter
@@ -831,7 +951,7 @@ mod tests {
 
                 let urls = vec!["http://".to_owned() + &addr.to_string()];
                 let dotter = Dotter::new(
-                    Timeouts::default_http_client(),
+                    HttpClientTimeouts::default_http_client(),
                     get_credential(),
                     BUCKET_NAME.to_owned(),
                     urls,
@@ -842,6 +962,7 @@ mod tests {
                     None,
                     None,
                     None,
+                    None,
                 );
                 assert!(dotter.inner.is_some());
 
@@ -891,7 +1012,7 @@ mod tests {
             ];
             spawn_blocking(move || {
                 let dotter = Dotter::new(
-                    Timeouts::default_http_client(),
+                    HttpClientTimeouts::default_http_client(),
                     get_credential(),
                     BUCKET_NAME.to_owned(),
                     urls,
@@ -902,6 +1023,7 @@ mod tests {
                     None,
                     None,
                     None,
+                    None,
                 );
 
                 let thread_pool = ThreadPoolBuilder::new().num_threads(10).build().unwrap();
@@ -1127,7 +1249,7 @@ mod tests {
             let urls = vec!["http://".to_owned() + &addr.to_string()];
             spawn_blocking(move || {
                 let dotter = Dotter::new(
-                    Timeouts::default_http_client(),
+                    HttpClientTimeouts::default_http_client(),
                     get_credential(),
                     BUCKET_NAME.to_owned(),
                     urls,
@@ -1138,6 +1260,7 @@ mod tests {
                     None,
                     None,
                     None,
+                    None,
                 );
 
                 let thread_pool = ThreadPoolBuilder::new().num_threads(10).build().unwrap();
@@ -1,10 +1,28 @@
 use super::{
     super::{
-        async_api::{sign_download_url_with_lifetime, RangePart},
-        base::{credential::Credential, download::RangeReaderBuilder as BaseRangeReaderBuilder},
+        async_api::{sign_download_url_with_lifetime, sort_range_parts, RangePart},
+        base::{
+            credential::Credential,
+            dot_sampler::DotSampler,
+            download::{
+                capacity_hint, check_body_size, check_multi_ranges, check_object_size,
+                content_encoded_error, content_encoding_of, is_content_encoded_error,
+                is_not_found_status_code, is_object_level_error, range_in_body, redirected_host_of,
+                should_retry, unexpected_status_code_error, DownloadUrlOptions,
+                RangeReaderBuilder as BaseRangeReaderBuilder,
+            },
+            gzip,
+            region_hosts::RegionHosts,
+            rng::SharedRng,
+        },
         config::{
-            build_range_reader_builder_from_config, with_current_qiniu_config, Config, Timeouts,
+            build_range_reader_builder_from_config, is_connection_failure, rewrite_key,
+            with_current_qiniu_config, Config, ConnectionFailureKind, FailoverBackoff,
+            HttpClientTimeouts, KeyRewriteRule, RangeRequestEncoding, TimeoutApi, TimeoutsConfig,
         },
+        error_code::ErrorCode,
+        events::{emit_event, DownloadEvent},
+        settings::global_settings,
     },
     dot::{ApiName, DotType, Dotter},
     host_selector::{HostSelector, HostSelectorBuilder},
@@ -18,10 +36,11 @@ use reqwest::{
     blocking::{
         Client as HTTPClient, RequestBuilder as HTTPRequestBuilder, Response as HTTPResponse,
     },
-    header::{HeaderValue, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE},
+    header::{HeaderValue, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE},
     Error as ReqwestError, Method, StatusCode, Url,
 };
 use std::{
+    cmp::min,
     io::{
         copy as io_copy, Cursor, Error as IOError, ErrorKind as IOErrorKind, Read,
         Result as IOResult, Seek, SeekFrom, Write,
@@ -43,6 +62,7 @@ pub(crate) struct RangeReader {
 #[derive(Debug)]
 pub(crate) struct RangeReaderInner {
     io_selector: HostSelector,
+    io_querier: Option<HostsQuerier>,
     dotter: Dotter,
     credential: Credential,
     http_client: Arc<HTTPClient>,
@@ -50,8 +70,22 @@ pub(crate) struct RangeReaderInner {
     tries: usize,
     use_getfile_api: bool,
     normalize_key: bool,
+    key_rewrite_rules: Vec<KeyRewriteRule>,
     use_https: bool,
     private_url_lifetime: Option<Duration>,
+    url_options: DownloadUrlOptions,
+    timeouts: TimeoutsConfig,
+    max_object_size: Option<u64>,
+    max_multi_ranges: Option<usize>,
+    max_multi_ranges_size: Option<u64>,
+    range_request_encoding: RangeRequestEncoding,
+    strict_body_size: bool,
+    decompress_content_encoding: bool,
+    failover_backoff: FailoverBackoff,
+    object_level_status_codes: Vec<u16>,
+    connection_failure_kinds: Vec<ConnectionFailureKind>,
+    cluster_name: Option<String>,
+    rng: SharedRng,
 }
 
 #[derive(Debug)]
@@ -77,11 +111,20 @@ impl RangeReaderBuilder {
 
     fn build_inner_and_key(self) -> (Arc<RangeReaderInner>, String) {
         let builder = self.0;
-        let http_client = Timeouts::new(builder.base_timeout, builder.dial_timeout).http_client();
+        let http_client = HttpClientTimeouts::new(builder.base_timeout, builder.dial_timeout)
+            .with_dns_cache(builder.dns_cache_min_ttl, builder.dns_cache_max_ttl)
+            .with_redirect_policy(builder.redirect_policy)
+            .http_client();
         let dotter = Dotter::new(
             http_client.to_owned(),
-            builder.credential.to_owned(),
-            builder.bucket.to_owned(),
+            builder
+                .dot_credential
+                .clone()
+                .unwrap_or_else(|| builder.credential.to_owned()),
+            builder
+                .dot_bucket
+                .clone()
+                .unwrap_or_else(|| builder.bucket.to_owned()),
             builder.monitor_urls,
             builder.dot_interval,
             builder.max_dot_buffer_size,
@@ -90,14 +133,23 @@ impl RangeReaderBuilder {
             builder.max_punished_times,
             builder.max_punished_hosts_percent,
             builder.base_timeout,
-        );
+            builder.dot_token_lifetime,
+        )
+        .with_sampler(DotSampler::new(
+            builder.dot_sample_rate,
+            builder.dot_sampling_threshold,
+        ));
 
+        let rng = SharedRng::new(builder.random_seed);
         let params = HostSelectorParams {
             update_interval: builder.update_interval,
             punish_duration: builder.punish_duration,
             max_punished_times: builder.max_punished_times,
             max_punished_hosts_percent: builder.max_punished_hosts_percent,
             base_timeout: builder.base_timeout,
+            max_timeout: builder.max_timeout,
+            force_https: builder.force_https,
+            rng: rng.to_owned(),
         };
 
         let io_querier = if builder.uc_urls.is_empty() {
@@ -112,16 +164,20 @@ impl RangeReaderBuilder {
         };
         let io_selector = make_io_selector(
             builder.io_urls,
-            io_querier,
+            io_querier.to_owned(),
             builder.credential.access_key().to_owned(),
             builder.bucket.to_owned(),
             builder.use_https,
+            builder.preferred_idc.to_owned(),
+            builder.retryable_status_codes,
+            builder.object_level_status_codes.to_owned(),
             &params,
         );
 
         return (
             Arc::new(RangeReaderInner {
                 io_selector,
+                io_querier,
                 dotter,
                 http_client,
                 credential: builder.credential,
@@ -129,8 +185,22 @@ impl RangeReaderBuilder {
                 tries: builder.io_tries,
                 use_getfile_api: builder.use_getfile_api,
                 normalize_key: builder.normalize_key,
+                key_rewrite_rules: builder.key_rewrite_rules,
                 use_https: builder.use_https,
                 private_url_lifetime: builder.private_url_lifetime,
+                url_options: builder.url_options,
+                timeouts: builder.timeouts,
+                max_object_size: builder.max_object_size,
+                max_multi_ranges: builder.max_multi_ranges,
+                max_multi_ranges_size: builder.max_multi_ranges_size,
+                range_request_encoding: builder.range_request_encoding,
+                strict_body_size: builder.strict_body_size,
+                decompress_content_encoding: builder.decompress_content_encoding,
+                failover_backoff: builder.failover_backoff,
+                object_level_status_codes: builder.object_level_status_codes,
+                connection_failure_kinds: builder.connection_failure_kinds,
+                cluster_name: builder.cluster_name,
+                rng,
             }),
             builder.key,
         );
@@ -142,6 +212,9 @@ impl RangeReaderBuilder {
             max_punished_times: Option<usize>,
             max_punished_hosts_percent: Option<u8>,
             base_timeout: Option<Duration>,
+            max_timeout: Option<Duration>,
+            force_https: bool,
+            rng: SharedRng,
         }
 
         impl HostSelectorParams {
@@ -161,7 +234,12 @@ impl RangeReaderBuilder {
                 if let Some(base_timeout) = self.base_timeout {
                     builder = builder.base_timeout(base_timeout);
                 }
+                if let Some(max_timeout) = self.max_timeout {
+                    builder = builder.max_timeout(max_timeout);
+                }
                 builder
+                    .force_https(self.force_https)
+                    .rng(self.rng.to_owned())
             }
         }
 
@@ -172,24 +250,37 @@ impl RangeReaderBuilder {
             params.set_builder(HostSelector::builder(uc_urls)).build()
         }
 
+        #[allow(clippy::too_many_arguments)]
         fn make_io_selector(
             io_urls: Vec<String>,
             io_querier: Option<HostsQuerier>,
             access_key: String,
             bucket: String,
             use_https: bool,
+            preferred_idc: Option<String>,
+            retryable_status_codes: Option<Vec<u16>>,
+            object_level_status_codes: Vec<u16>,
             params: &HostSelectorParams,
         ) -> HostSelector {
             let builder = HostSelector::builder(io_urls)
                 .update_callback(Some(Box::new(move || -> IOResult<Vec<String>> {
                     if let Some(io_querier) = &io_querier {
-                        io_querier.query_for_io_urls(&access_key, &bucket, use_https)
+                        io_querier.query_for_io_urls(
+                            &access_key,
+                            &bucket,
+                            use_https,
+                            preferred_idc.as_deref(),
+                        )
                     } else {
                         Ok(vec![])
                     }
                 })))
-                .should_punish_callback(Some(Box::new(|error| {
-                    !matches!(error.kind(), IOErrorKind::InvalidData)
+                .should_punish_callback(Some(Box::new(move |error| {
+                    should_retry(
+                        error,
+                        retryable_status_codes.as_deref(),
+                        &object_level_status_codes,
+                    )
                 })));
             params.set_builder(builder).build()
         }
@@ -220,29 +311,83 @@ impl RangeReader {
         .map(|inner| Self { inner, key })
     }
 
+    pub(crate) fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn bucket(&self) -> &str {
+        &self.inner.bucket
+    }
+
+    pub(crate) fn cluster_name(&self) -> Option<&str> {
+        self.inner.cluster_name.as_deref()
+    }
+
+    pub(crate) fn rng(&self) -> &SharedRng {
+        &self.inner.rng
+    }
+
     pub(crate) fn update_urls(&self) -> bool {
         self.inner.io_selector.update_hosts()
     }
 
     pub(crate) fn io_urls(&self) -> Vec<String> {
-        return self
-            .inner
+        self.inner
             .io_selector
             .hosts()
             .iter()
             .map(|host| normalize_host(host, self.inner.use_https))
-            .collect();
+            .collect()
+    }
 
-        fn normalize_host(host: &str, use_https: bool) -> String {
-            if host.contains("://") {
-                host.to_string()
-            } else if use_https {
-                "https://".to_owned() + host
-            } else {
-                "http://".to_owned() + host
-            }
+    pub(crate) fn region_hosts(&self) -> IOResult<Vec<RegionHosts>> {
+        if let Some(io_querier) = &self.inner.io_querier {
+            io_querier.query_for_region_hosts(
+                self.inner.credential.access_key(),
+                &self.inner.bucket,
+                self.inner.use_https,
+            )
+        } else {
+            Ok(vec![RegionHosts::new(None, None, self.io_urls(), vec![])])
+        }
+    }
+
+    pub(crate) fn report_external_failure(&self, url: &str, error: &IOError) -> bool {
+        if let Some(host) = self.find_io_host(url) {
+            self.inner
+                .io_selector
+                .punish(&host, error, &self.inner.dotter)
+        } else {
+            false
         }
     }
+
+    pub(crate) fn report_external_success(&self, url: &str) -> bool {
+        if let Some(host) = self.find_io_host(url) {
+            self.inner.io_selector.reward(&host);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn find_io_host(&self, url: &str) -> Option<String> {
+        self.inner
+            .io_selector
+            .hosts()
+            .into_iter()
+            .find(|host| host == url || normalize_host(host, self.inner.use_https) == url)
+    }
+}
+
+fn normalize_host(host: &str, use_https: bool) -> String {
+    if host.contains("://") {
+        host.to_string()
+    } else if use_https {
+        "https://".to_owned() + host
+    } else {
+        "http://".to_owned() + host
+    }
 }
 
 impl ReadAt for RangeReader {
@@ -253,10 +398,11 @@ impl ReadAt for RangeReader {
         }
         let mut cursor = Cursor::new(buf);
         let range = format!("bytes={}-{}", pos, pos + size - 1);
+        let encoding = self.inner.range_request_encoding;
         let begin_at = Instant::now();
 
         self.with_retries(
-            Method::GET,
+            encoding.method(),
             ApiName::RangeReaderReadAt,
             |tries, request_builder, req_id, download_url, chosen_host, timeout_power| {
                 debug!(
@@ -265,8 +411,8 @@ impl ReadAt for RangeReader {
                 );
                 let begin_at = Instant::now();
 
-                let result = request_builder
-                    .header(RANGE, &range)
+                let result = encoding
+                    .encode(request_builder, &range)
                     .send()
                     .tap_err(|err| self.punish_if_needed(chosen_host, timeout_power, err))
                     .map_err(|err| IOError::new(IOErrorKind::ConnectionAborted, err))
@@ -275,7 +421,23 @@ impl ReadAt for RangeReader {
                         if code != StatusCode::PARTIAL_CONTENT && code != StatusCode::OK {
                             return Err(unexpected_status_code(&resp));
                         }
+                        reject_content_encoded(&resp)?;
                         let content_length = parse_content_length(&resp);
+                        if self.inner.strict_body_size {
+                            if content_length > size {
+                                check_body_size(content_length, size)?;
+                            }
+                            let mut body = Vec::new();
+                            self.wrap_reader(
+                                resp.take(content_length.saturating_add(1)),
+                                chosen_host,
+                                timeout_power,
+                            )
+                            .read_to_end(&mut body)
+                            .map_err(|err| IOError::new(IOErrorKind::BrokenPipe, err))?;
+                            check_body_size(body.len() as u64, content_length)?;
+                            return io_copy(&mut body.as_slice(), &mut cursor);
+                        }
                         let max_size = content_length.min(size);
                         io_copy(
                             &mut self.wrap_reader(resp.take(max_size), chosen_host, timeout_power),
@@ -298,16 +460,16 @@ impl ReadAt for RangeReader {
                     })
                     .tap_err(|err| {
                         warn!(
-                            "[{}] read_at error url: {}, range: {}, error: {}, req_id: {:?}, elapsed: {:?}",
-                            tries, download_url, range, err, req_id, begin_at.elapsed(),
+                            "[{}] read_at error url: {}, range: {}, error: {}, code: {}, req_id: {:?}, elapsed: {:?}",
+                            tries, download_url, range, err, ErrorCode::of(err), req_id, begin_at.elapsed(),
                         );
                         cursor.set_position(0);
                     })
             },
             |err, download_url| {
                 error!(
-                    "final failed read_at url = {}, error: {:?}, elapsed: {:?}",
-                    download_url, err, begin_at.elapsed(),
+                    "final failed read_at url = {}, error: {:?}, code: {}, elapsed: {:?}",
+                    download_url, err, ErrorCode::of(err), begin_at.elapsed(),
                 );
             },
         )
@@ -316,11 +478,17 @@ impl ReadAt for RangeReader {
 
 impl RangeReader {
     pub(crate) fn read_multi_ranges(&self, ranges: &[(u64, u64)]) -> IOResult<Vec<RangePart>> {
+        check_multi_ranges(
+            ranges,
+            self.inner.max_multi_ranges,
+            self.inner.max_multi_ranges_size,
+        )?;
         let range_header_value = format!("bytes={}", generate_range_header(ranges));
+        let encoding = self.inner.range_request_encoding;
         let begin_at = Instant::now();
 
         return self.with_retries(
-            Method::GET,
+            encoding.method(),
             ApiName::RangeReaderReadMultiRanges,
             |tries, http_request_builder, req_id, download_url, chosen_host, timeout_power| {
                 debug!(
@@ -328,12 +496,13 @@ impl RangeReader {
                     tries, download_url, req_id,
                 );
                 let begin_at = Instant::now();
-                let result = http_request_builder
-                    .header(RANGE, &range_header_value)
+                let result = encoding
+                    .encode(http_request_builder, &range_header_value)
                     .send()
                     .tap_err(|err| self.punish_if_needed(chosen_host, timeout_power, err))
                     .map_err(|err| IOError::new(IOErrorKind::ConnectionAborted, err))
                     .and_then(|resp| {
+                        reject_content_encoded(&resp)?;
                         let mut parts = Vec::with_capacity(ranges.len());
                         match resp.status() {
                             StatusCode::OK => {
@@ -342,12 +511,11 @@ impl RangeReader {
                                     .read_to_end(&mut body)
                                     .map_err(|err| IOError::new(IOErrorKind::BrokenPipe, err))?;
                                 for &(from, len) in ranges.iter() {
-                                    let from = (from as usize).min(body.len());
-                                    let len = (len as usize).min(body.len() - from);
-                                    if len > 0 {
+                                    if let Some(range) = range_in_body(body.len(), from, len) {
                                         parts.push(RangePart {
-                                            data: body[from..(from + len)].to_vec(),
-                                            range: (from as u64, len as u64),
+                                            range: (range.start as u64, range.len() as u64),
+                                            data: body[range].to_vec(),
+                                            requested: (from, len),
                                         });
                                     }
                                 }
@@ -399,7 +567,7 @@ impl RangeReader {
                                                     )
                                                 })?;
                                                 let len = to - from + 1;
-                                                let mut data = Vec::with_capacity(len as usize);
+                                                let mut data = Vec::with_capacity(capacity_hint(len));
                                                 field.data.read_to_end(&mut data).map_err(
                                                     |err| {
                                                         IOError::new(IOErrorKind::BrokenPipe, err)
@@ -408,6 +576,7 @@ impl RangeReader {
                                                 parts.push(RangePart {
                                                     data,
                                                     range: (from, len),
+                                                    requested: (from, len),
                                                 });
                                             }
                                             Ok(None) => break,
@@ -438,12 +607,13 @@ impl RangeReader {
                                             )
                                         })?;
                                     let len = to - from + 1;
-                                    let mut data = Vec::with_capacity(len as usize);
+                                    let mut data = Vec::with_capacity(capacity_hint(len));
                                     self.wrap_reader(resp, chosen_host, timeout_power)
                                         .read_to_end(&mut data)?;
                                     parts.push(RangePart {
                                         data,
                                         range: (from, len),
+                                        requested: (from, len),
                                     });
                                 }
                             }
@@ -452,7 +622,7 @@ impl RangeReader {
                             }
                         }
 
-                        Ok(parts)
+                        Ok(sort_range_parts(ranges, parts))
                     });
                 result
                     .tap_ok(|_| {
@@ -463,15 +633,15 @@ impl RangeReader {
                     })
                     .tap_err(|err| {
                         warn!(
-                            "[{}] read_multi_ranges error url: {}, error: {}, req_id: {:?}, elapsed: {:?}",
-                            tries, download_url, err, req_id, begin_at.elapsed(),
+                            "[{}] read_multi_ranges error url: {}, error: {}, code: {}, req_id: {:?}, elapsed: {:?}",
+                            tries, download_url, err, ErrorCode::of(err), req_id, begin_at.elapsed(),
                         );
                     })
             },
             |err, download_url| {
                 error!(
-                    "final failed read_multi_ranges url = {}, error: {:?}, elapsed: {:?}",
-                    download_url, err, begin_at.elapsed(),
+                    "final failed read_multi_ranges url = {}, error: {:?}, code: {}, elapsed: {:?}",
+                    download_url, err, ErrorCode::of(err), begin_at.elapsed(),
                 );
             },
         );
@@ -517,7 +687,7 @@ impl RangeReader {
                     .map_err(|err| IOError::new(IOErrorKind::ConnectionAborted, err))
                     .and_then(|resp| match resp.status() {
                         StatusCode::OK => Ok(true),
-                        StatusCode::NOT_FOUND => Ok(false),
+                        status if is_not_found_status_code(status.as_u16()) => Ok(false),
                         _ => Err(unexpected_status_code(&resp)),
                     });
                 result
@@ -532,10 +702,11 @@ impl RangeReader {
                     })
                     .tap_err(|err| {
                         warn!(
-                            "[{}] exist error url: {}, error: {}, req_id: {:?}, elapsed: {:?}",
+                            "[{}] exist error url: {}, error: {}, code: {}, req_id: {:?}, elapsed: {:?}",
                             tries,
                             download_url,
                             err,
+                            ErrorCode::of(err),
                             req_id,
                             begin_at.elapsed(),
                         );
@@ -543,9 +714,10 @@ impl RangeReader {
             },
             |err, download_url| {
                 error!(
-                    "final failed exist url = {}, error: {:?}, elapsed: {:?}",
+                    "final failed exist url = {}, error: {:?}, code: {}, elapsed: {:?}",
                     download_url,
                     err,
+                    ErrorCode::of(err),
                     begin_at.elapsed(),
                 );
             },
@@ -569,6 +741,7 @@ impl RangeReader {
                     .map_err(|err| IOError::new(IOErrorKind::Other, err))
                     .and_then(|resp| {
                         if resp.status() == StatusCode::OK {
+                            reject_content_encoded(&resp)?;
                             Ok(parse_content_length(&resp))
                         } else {
                             Err(unexpected_status_code(&resp))
@@ -586,10 +759,11 @@ impl RangeReader {
                     })
                     .tap_err(|err| {
                         warn!(
-                            "[{}] file_size error url: {}, error: {}, req_id: {:?}, elapsed: {:?}",
+                            "[{}] file_size error url: {}, error: {}, code: {}, req_id: {:?}, elapsed: {:?}",
                             tries,
                             download_url,
                             err,
+                            ErrorCode::of(err),
                             req_id,
                             begin_at.elapsed(),
                         );
@@ -597,9 +771,10 @@ impl RangeReader {
             },
             |err, download_url| {
                 error!(
-                    "final failed file_size url = {}, error: {:?}, elapsed: {:?}",
+                    "final failed file_size url = {}, error: {:?}, code: {}, elapsed: {:?}",
                     download_url,
                     err,
+                    ErrorCode::of(err),
                     begin_at.elapsed(),
                 );
             },
@@ -613,12 +788,21 @@ impl RangeReader {
     }
 
     pub(crate) fn download_to(&self, writer: &mut dyn WriteSeek) -> IOResult<u64> {
+        if self.inner.max_object_size.is_some() {
+            match self.file_size() {
+                Ok(size) => check_object_size(size, self.inner.max_object_size)?,
+                // 以压缩编码存储的对象无法预先获取尺寸，解压时再检查
+                Err(err) if is_content_encoded_error(&err) => {}
+                Err(err) => return Err(err),
+            }
+        }
         let init_start_from = writer.seek(SeekFrom::End(0))?;
         let mut start_from = init_start_from;
+        let encoding = self.inner.range_request_encoding;
         let begin_at = Instant::now();
 
         self.with_retries(
-            Method::GET,
+            encoding.method(),
             ApiName::RangeReaderDownloadTo,
             |tries, mut request_builder, req_id, download_url, chosen_host, timeout_power| {
                 debug!(
@@ -628,7 +812,7 @@ impl RangeReader {
                 let begin_at = Instant::now();
                 if start_from > 0 {
                     request_builder =
-                        request_builder.header(RANGE, format!("bytes={}-", start_from));
+                        encoding.encode(request_builder, &format!("bytes={}-", start_from));
                 }
                 let result = request_builder
                     .send()
@@ -641,6 +825,14 @@ impl RangeReader {
                             && resp.status() != StatusCode::PARTIAL_CONTENT
                         {
                             Err(unexpected_status_code(&resp))
+                        } else if let Some(content_encoding) = content_encoding_of(resp.headers())
+                            .filter(|_| self.inner.decompress_content_encoding)
+                        {
+                            self.decompress_to(
+                                self.wrap_reader(resp, chosen_host, timeout_power),
+                                &content_encoding,
+                                writer,
+                            )
                         } else {
                             io_copy(
                                 &mut self.wrap_reader(resp, chosen_host, timeout_power),
@@ -661,15 +853,15 @@ impl RangeReader {
                     })
                     .tap_err(|err| {
                         warn!(
-                            "[{}] download error url: {}, start_from: {}, error: {}, req_id: {:?}, elapsed: {:?}",
-                            tries, download_url, origin_start_from, err, req_id, begin_at.elapsed(),
+                            "[{}] download error url: {}, start_from: {}, error: {}, code: {}, req_id: {:?}, elapsed: {:?}",
+                            tries, download_url, origin_start_from, err, ErrorCode::of(err), req_id, begin_at.elapsed(),
                         );
                     })
             },
             |err, download_url| {
                 error!(
-                    "final failed download url = {}, start_from: {}, error: {:?}, elapsed: {:?}",
-                    download_url, init_start_from, err, begin_at.elapsed(),
+                    "final failed download url = {}, start_from: {}, error: {:?}, code: {}, elapsed: {:?}",
+                    download_url, init_start_from, err, ErrorCode::of(err), begin_at.elapsed(),
                 );
             },
         )
@@ -680,10 +872,11 @@ impl RangeReader {
         let size = buf.len() as u64;
         let mut cursor = Cursor::new(buf);
         let range = format!("bytes=-{}", size);
+        let encoding = self.inner.range_request_encoding;
         let begin_at = Instant::now();
 
         self.with_retries(
-            Method::GET,
+            encoding.method(),
             ApiName::RangeReaderReadLastBytes,
             |tries, request_builder, req_id, download_url, chosen_host, timeout_power| {
                 debug!(
@@ -691,8 +884,8 @@ impl RangeReader {
                     tries, download_url, req_id, size
                 );
                 let begin_at = Instant::now();
-                let result = request_builder
-                    .header(RANGE, &range)
+                let result = encoding
+                    .encode(request_builder, &range)
                     .send()
                     .tap_err(|err| self.punish_if_needed(chosen_host, timeout_power, err))
                     .map_err(|err| IOError::new(IOErrorKind::ConnectionAborted, err))
@@ -700,6 +893,7 @@ impl RangeReader {
                         if resp.status() != StatusCode::PARTIAL_CONTENT {
                             return Err(unexpected_status_code(&resp));
                         }
+                        reject_content_encoded(&resp)?;
                         let content_range = resp
                             .headers()
                             .get(CONTENT_RANGE)
@@ -726,21 +920,40 @@ impl RangeReader {
                     })
                     .tap_err(|err| {
                         warn!(
-                            "[{}] download error url: {}, len: {}, error: {}, req_id: {:?}, elapsed: {:?}",
-                            tries, download_url, size, err, req_id, begin_at.elapsed(),
+                            "[{}] download error url: {}, len: {}, error: {}, code: {}, req_id: {:?}, elapsed: {:?}",
+                            tries, download_url, size, err, ErrorCode::of(err), req_id, begin_at.elapsed(),
                         );
                         cursor.set_position(0);
                     })
             },
             |err, download_url| {
                 error!(
-                    "final failed read_last_bytes url = {}, len: {}, error: {:?}, elapsed: {:?}",
-                    download_url, size, err, begin_at.elapsed(),
+                    "final failed read_last_bytes url = {}, len: {}, error: {:?}, code: {}, elapsed: {:?}",
+                    download_url, size, err, ErrorCode::of(err), begin_at.elapsed(),
                 );
             },
         )
     }
 
+    /// 读取整个压缩编码的响应体，解压后写入输出流，仅支持 gzip 编码
+    fn decompress_to(
+        &self,
+        mut reader: impl Read,
+        content_encoding: &str,
+        writer: &mut dyn WriteSeek,
+    ) -> IOResult<u64> {
+        if content_encoding != "gzip" {
+            return Err(content_encoded_error(content_encoding));
+        }
+        let mut body = Vec::new();
+        reader
+            .read_to_end(&mut body)
+            .map_err(|err| IOError::new(IOErrorKind::BrokenPipe, err))?;
+        let data = gzip::decompress(&body, self.inner.max_object_size)?;
+        writer.write_all(&data)?;
+        Ok(data.len() as u64)
+    }
+
     fn wrap_reader<'a, R: 'a + Read>(
         &'a self,
         source: R,
@@ -768,22 +981,64 @@ impl RangeReader {
     ) -> IOResult<T> {
         let begin_at = SystemTime::now();
         let begin_at_instant = Instant::now();
-        assert!(self.inner.tries > 0);
+        let total_tries = global_settings().io_tries().unwrap_or(self.inner.tries);
+        assert!(total_tries > 0);
+        let timeouts = timeout_api_of(api_name)
+            .map(|api| self.inner.timeouts.effective(api))
+            .unwrap_or_default();
+        emit_event(|| DownloadEvent::DownloadStarted {
+            api: api_name.to_string(),
+            key: self.key.to_owned(),
+        });
 
-        for tries in 0..self.inner.tries {
+        for tries in 0..total_tries {
             sleep_before_retry(tries);
-            let last_try = self.inner.tries - tries <= 1;
+            let last_try = total_tries - tries <= 1;
 
             let chosen_io_info = self.inner.io_selector.select_host();
+            let mut request_timeout = timeouts
+                .read()
+                .map(|read| {
+                    min(
+                        read * (1 << chosen_io_info.timeout_power),
+                        self.inner.io_selector.max_timeout(),
+                    )
+                })
+                .unwrap_or(chosen_io_info.timeout);
+            if let Some(total) = timeouts.total() {
+                match total.checked_sub(begin_at_instant.elapsed()) {
+                    Some(remaining) if remaining > Duration::from_millis(0) => {
+                        request_timeout = min(request_timeout, remaining);
+                    }
+                    _ => {
+                        let err = IOError::new(
+                            IOErrorKind::TimedOut,
+                            format!("{} is timed out after {:?}", api_name, total),
+                        );
+                        self.inner
+                            .dotter
+                            .dot(DotType::Sdk, api_name, false, begin_at_instant.elapsed())
+                            .ok();
+                        emit_event(|| DownloadEvent::DownloadFailed {
+                            api: api_name.to_string(),
+                            key: self.key.to_owned(),
+                            error: err.to_string(),
+                            code: ErrorCode::of(&err),
+                            elapsed: begin_at_instant.elapsed(),
+                        });
+                        return Err(err);
+                    }
+                }
+            }
             let download_url = sign_download_url_if_needed(
-                &make_download_url(
-                    &chosen_io_info.host,
+                &self.inner.url_options.apply_to(make_download_url(
+                    &self.inner.url_options.base_url(&chosen_io_info.host),
                     self.inner.credential.access_key(),
                     &self.inner.bucket,
-                    &self.key,
+                    &rewrite_key(&self.inner.key_rewrite_rules, &self.key),
                     self.inner.use_getfile_api,
                     self.inner.normalize_key,
-                ),
+                )),
                 self.inner.private_url_lifetime,
                 &self.inner.credential,
             );
@@ -794,7 +1049,7 @@ impl RangeReader {
                 .http_client
                 .request(method.to_owned(), download_url.to_owned())
                 .header(REQUEST_ID_HEADER, req_id.to_owned())
-                .timeout(chosen_io_info.timeout);
+                .timeout(request_timeout);
             match for_each_url(
                 tries,
                 request_builder,
@@ -809,6 +1064,11 @@ impl RangeReader {
                         .dotter
                         .dot(DotType::Sdk, api_name, true, begin_at_instant.elapsed())
                         .ok();
+                    emit_event(|| DownloadEvent::DownloadFinished {
+                        api: api_name.to_string(),
+                        key: self.key.to_owned(),
+                        elapsed: begin_at_instant.elapsed(),
+                    });
                     self.inner
                         .dotter
                         .dot(
@@ -820,7 +1080,37 @@ impl RangeReader {
                         .ok();
                     return Ok(result);
                 }
+                Err(err) if is_object_level_error(&err, &self.inner.object_level_status_codes) => {
+                    self.inner.io_selector.reward(&chosen_io_info.host);
+                    self.inner
+                        .dotter
+                        .dot(DotType::Sdk, api_name, true, begin_at_instant.elapsed())
+                        .ok();
+                    emit_event(|| DownloadEvent::DownloadFailed {
+                        api: api_name.to_string(),
+                        key: self.key.to_owned(),
+                        error: err.to_string(),
+                        code: ErrorCode::of(&err),
+                        elapsed: begin_at_instant.elapsed(),
+                    });
+                    self.inner
+                        .dotter
+                        .dot(
+                            DotType::Http,
+                            ApiName::IoGetfile,
+                            true,
+                            request_begin_at_instant.elapsed(),
+                        )
+                        .ok();
+                    return Err(err);
+                }
                 Err(err) => {
+                    if let Some(final_host) = redirected_host_of(&err, &chosen_io_info.host) {
+                        warn!(
+                            "{} is redirected to {}, which responds error: {}",
+                            chosen_io_info.host, final_host, err
+                        );
+                    }
                     let punished = self.inner.io_selector.punish(
                         &chosen_io_info.host,
                         &err,
@@ -841,8 +1131,25 @@ impl RangeReader {
                             .dotter
                             .dot(DotType::Sdk, api_name, false, begin_at_instant.elapsed())
                             .ok();
+                        emit_event(|| DownloadEvent::DownloadFailed {
+                            api: api_name.to_string(),
+                            key: self.key.to_owned(),
+                            error: err.to_string(),
+                            code: ErrorCode::of(&err),
+                            elapsed: begin_at_instant.elapsed(),
+                        });
                         return Err(err);
                     }
+                    emit_event(|| DownloadEvent::RetryScheduled {
+                        api: api_name.to_string(),
+                        host: chosen_io_info.host.to_owned(),
+                        tries: tries + 1,
+                    });
+                    sleep(
+                        self.inner
+                            .failover_backoff
+                            .delay_with_rng(tries + 1, &self.inner.rng),
+                    );
                 }
             }
         }
@@ -897,6 +1204,18 @@ impl RangeReader {
                 sleep(Duration::from_secs(tries as u64));
             }
         }
+
+        fn timeout_api_of(api_name: ApiName) -> Option<TimeoutApi> {
+            match api_name {
+                ApiName::RangeReaderReadAt => Some(TimeoutApi::ReadAt),
+                ApiName::RangeReaderReadMultiRanges => Some(TimeoutApi::ReadMultiRanges),
+                ApiName::RangeReaderExist => Some(TimeoutApi::Exist),
+                ApiName::RangeReaderFileSize => Some(TimeoutApi::FileSize),
+                ApiName::RangeReaderDownloadTo => Some(TimeoutApi::Download),
+                ApiName::RangeReaderReadLastBytes => Some(TimeoutApi::ReadLastBytes),
+                _ => None,
+            }
+        }
     }
 
     fn punish_if_needed(&self, host: &str, timeout_power: usize, err: &ReqwestError) {
@@ -904,29 +1223,33 @@ impl RangeReader {
             self.inner
                 .io_selector
                 .increase_timeout_power_by(host, timeout_power)
-        } else if err.is_connect() {
+        } else if is_connection_failure(err, &self.inner.connection_failure_kinds) {
             self.inner.io_selector.mark_connection_as_failed(host)
         }
     }
 }
 
+/// 可写入并可定位的输出流
 pub trait WriteSeek: Write + Seek {}
 impl<T: Write + Seek> WriteSeek for T {}
 
 #[cold]
 #[inline(never)]
 fn unexpected_status_code(resp: &HTTPResponse) -> IOError {
-    let error_kind = if resp.status().is_client_error() {
-        IOErrorKind::InvalidData
-    } else {
-        IOErrorKind::Other
-    };
-    IOError::new(
-        error_kind,
-        format!("Unexpected status code {}", resp.status().as_u16()),
+    unexpected_status_code_error(
+        resp.status().as_u16(),
+        Some(resp.url().origin().ascii_serialization()),
     )
 }
 
+/// 以压缩编码存储的对象不支持范围读取和获取尺寸，直接返回 [`crate::ContentEncodedError`] 错误
+fn reject_content_encoded(resp: &HTTPResponse) -> IOResult<()> {
+    match content_encoding_of(resp.headers()) {
+        Some(content_encoding) => Err(content_encoded_error(&content_encoding)),
+        None => Ok(()),
+    }
+}
+
 fn parse_content_length(resp: &HTTPResponse) -> u64 {
     resp.headers()
         .get(CONTENT_LENGTH)
@@ -969,7 +1292,10 @@ mod tests {
     };
     use warp::{
         header,
-        http::{header::AUTHORIZATION, HeaderValue, StatusCode},
+        http::{
+            header::{AUTHORIZATION, RANGE},
+            HeaderValue, StatusCode,
+        },
         hyper::Body,
         path,
         reply::Response,
@@ -1619,10 +1945,10 @@ mod tests {
                 let ranges = [(0, 5), (5, 5)];
                 let parts = downloader.read_multi_ranges(&ranges).unwrap();
                 assert_eq!(parts.len(), 2);
-                assert_eq!(&parts.get(1).unwrap().data, b"12345");
-                assert_eq!(parts.get(1).unwrap().range, (0, 5));
-                assert_eq!(&parts.first().unwrap().data, b"67890");
-                assert_eq!(parts.first().unwrap().range, (5, 5));
+                assert_eq!(&parts[0].data, b"12345");
+                assert_eq!(parts[0].range, (0, 5));
+                assert_eq!(&parts[1].data, b"67890");
+                assert_eq!(parts[1].range, (5, 5));
             })
             .await?;
         });
@@ -1788,10 +2114,10 @@ mod tests {
                 let ranges = [(0, 5), (5, 5)];
                 let parts = downloader.read_multi_ranges(&ranges).unwrap();
                 assert_eq!(parts.len(), 2);
-                assert_eq!(&parts.get(1).unwrap().data, b"12345");
-                assert_eq!(parts.get(1).unwrap().range, (0, 5));
-                assert_eq!(&parts.first().unwrap().data, b"6");
-                assert_eq!(parts.first().unwrap().range, (5, 1));
+                assert_eq!(&parts[0].data, b"12345");
+                assert_eq!(parts[0].range, (0, 5));
+                assert_eq!(&parts[1].data, b"6");
+                assert_eq!(parts[1].range, (5, 1));
             })
             .await?;
         });
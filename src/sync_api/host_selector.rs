@@ -1,7 +1,14 @@
-use super::dot::Dotter;
+use super::{
+    super::{
+        base::rng::SharedRng,
+        config::normalize_host_urls,
+        error_code::ErrorCode,
+        events::{emit_event, DownloadEvent},
+    },
+    dot::Dotter,
+};
 use dashmap::DashMap;
 use log::{info, warn};
-use rand::{seq::SliceRandom, thread_rng};
 use reqwest::Error as ReqwestError;
 use std::{
     cmp::{min, Ordering},
@@ -18,6 +25,8 @@ use std::{
 };
 use tap::prelude::*;
 
+const DEFAULT_MAX_TIMEOUT: Duration = Duration::from_secs(600);
+
 #[derive(Default, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 struct OptionalInstantTime(Option<Instant>);
 
@@ -127,6 +136,8 @@ struct HostsUpdater {
     update_option: Option<UpdateOption>,
     index: AtomicUsize,
     current_timeout_power: AtomicUsize,
+    force_https: bool,
+    rng: SharedRng,
 }
 
 struct UpdateOption {
@@ -146,7 +157,13 @@ impl UpdateOption {
 }
 
 impl HostsUpdater {
-    fn new(hosts: Vec<String>, update_option: Option<UpdateOption>) -> Arc<Self> {
+    fn new(
+        hosts: Vec<String>,
+        update_option: Option<UpdateOption>,
+        force_https: bool,
+        rng: SharedRng,
+    ) -> Arc<Self> {
+        let hosts = normalize_host_urls(hosts, force_https);
         Arc::new(Self {
             hosts_map: hosts
                 .iter()
@@ -156,10 +173,13 @@ impl HostsUpdater {
             update_option,
             index: AtomicUsize::new(0),
             current_timeout_power: AtomicUsize::new(0),
+            force_https,
+            rng,
         })
     }
 
-    fn set_hosts(&self, mut hosts: Vec<String>) {
+    fn set_hosts(&self, hosts: Vec<String>) {
+        let mut hosts = normalize_host_urls(hosts, self.force_https);
         let mut new_hosts_set = HashSet::with_capacity(hosts.len());
         for host in hosts.iter() {
             new_hosts_set.insert(host.to_owned());
@@ -167,7 +187,7 @@ impl HostsUpdater {
         }
         self.hosts_map
             .retain(|host, _| new_hosts_set.contains(host));
-        hosts.shuffle(&mut thread_rng());
+        self.rng.shuffle(&mut hosts);
         *self.hosts.write().unwrap() = hosts;
     }
 
@@ -254,6 +274,7 @@ struct HostPunisher {
     should_punish_func: Option<ShouldPunishFn>,
     punish_duration: Duration,
     base_timeout: Duration,
+    max_timeout: Duration,
     max_punished_times: usize,
     max_punished_hosts_percent: u8,
 }
@@ -282,7 +303,7 @@ impl HostPunisher {
         min(
             // 超时时长有上限，否则可能超过 tokio 极限
             self.base_timeout * (1 << punished_info.timeout_power),
-            Duration::from_secs(600),
+            self.max_timeout,
         )
     }
 
@@ -301,6 +322,7 @@ impl Debug for HostPunisher {
             .field("should_punish", &self.should_punish_func.is_some())
             .field("punish_duration", &self.punish_duration)
             .field("base_timeout", &self.base_timeout)
+            .field("max_timeout", &self.max_timeout)
             .field("max_punished_times", &self.max_punished_times)
             .field(
                 "max_punished_hosts_percent",
@@ -323,8 +345,11 @@ pub(super) struct HostSelectorBuilder {
     update_interval: Duration,
     punish_duration: Duration,
     base_timeout: Duration,
+    max_timeout: Duration,
     max_punished_times: usize,
     max_punished_hosts_percent: u8,
+    force_https: bool,
+    rng: SharedRng,
 }
 
 impl HostSelectorBuilder {
@@ -336,8 +361,11 @@ impl HostSelectorBuilder {
             update_interval: Duration::from_secs(60),
             punish_duration: Duration::from_secs(30 * 60),
             base_timeout: Duration::from_millis(3000),
+            max_timeout: DEFAULT_MAX_TIMEOUT,
             max_punished_times: 5,
             max_punished_hosts_percent: 50,
+            force_https: false,
+            rng: Default::default(),
         }
     }
 
@@ -369,6 +397,11 @@ impl HostSelectorBuilder {
         self
     }
 
+    pub(super) fn max_timeout(mut self, timeout: Duration) -> Self {
+        self.max_timeout = timeout;
+        self
+    }
+
     pub(super) fn max_punished_times(mut self, times: usize) -> Self {
         self.max_punished_times = times;
         self
@@ -379,6 +412,16 @@ impl HostSelectorBuilder {
         self
     }
 
+    pub(super) fn force_https(mut self, force_https: bool) -> Self {
+        self.force_https = force_https;
+        self
+    }
+
+    pub(super) fn rng(mut self, rng: SharedRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
     pub(super) fn build(self) -> HostSelector {
         let auto_update_enabled = self.update_func.is_some();
         let is_hosts_empty = self.hosts.is_empty();
@@ -387,6 +430,8 @@ impl HostSelectorBuilder {
             self.hosts,
             self.update_func
                 .map(|f| UpdateOption::new(f, update_interval)),
+            self.force_https,
+            self.rng,
         );
 
         if auto_update_enabled && is_hosts_empty {
@@ -399,6 +444,7 @@ impl HostSelectorBuilder {
                 should_punish_func: self.should_punish_func,
                 punish_duration: self.punish_duration,
                 base_timeout: self.base_timeout,
+                max_timeout: self.max_timeout,
                 max_punished_times: self.max_punished_times,
                 max_punished_hosts_percent: self.max_punished_hosts_percent,
             }),
@@ -422,6 +468,10 @@ impl HostSelector {
         self.hosts_updater.set_hosts(hosts)
     }
 
+    pub(super) fn max_timeout(&self) -> Duration {
+        self.host_punisher.max_timeout
+    }
+
     pub(super) fn hosts(&self) -> Vec<String> {
         self.hosts_updater
             .hosts
@@ -555,6 +605,7 @@ impl HostSelector {
 
     pub(super) fn reward(&self, host: &str) {
         if let Some(mut punished_info) = self.hosts_updater.hosts_map.get_mut(host) {
+            let recovered = punished_info.continuous_punished_times > 0;
             punished_info.continuous_punished_times = 0;
             punished_info.failed_to_connect = false;
             punished_info.timeout_power = punished_info.timeout_power.saturating_sub(1);
@@ -562,6 +613,12 @@ impl HostSelector {
                 "Reward host {}, now timeout_power is {}",
                 host, punished_info.timeout_power
             );
+            drop(punished_info);
+            if recovered {
+                emit_event(|| DownloadEvent::HostRecovered {
+                    host: host.to_owned(),
+                });
+            }
         }
     }
 
@@ -578,6 +635,11 @@ impl HostSelector {
 
     pub(super) fn punish_without_dotter(&self, host: &str, error: &IOError) -> PunishResult {
         if self.host_punisher.should_punish(error) {
+            emit_event(|| DownloadEvent::HostPunished {
+                host: host.to_owned(),
+                error: error.to_string(),
+                code: ErrorCode::of(error),
+            });
             if let Some(mut punished_info) = self.hosts_updater.hosts_map.get_mut(host) {
                 punished_info.continuous_punished_times += 1;
                 punished_info.last_punished_at = OptionalInstantTime::now();
@@ -696,6 +758,8 @@ mod tests {
                 }),
                 Duration::from_secs(10),
             )),
+            false,
+            Default::default(),
         );
         assert_eq!(hosts_updater.hosts.read().unwrap().len(), 3);
         assert_eq!(hosts_updater.hosts_map.len(), 3);
@@ -751,6 +815,8 @@ mod tests {
                 }),
                 Duration::from_millis(500),
             )),
+            false,
+            Default::default(),
         );
         HostsUpdater::next_index(&hosts_updater);
         assert_eq!(hosts_updater.hosts.read().unwrap().len(), 3);
@@ -1,8 +1,10 @@
 mod cache_dir;
 mod dot;
+pub(crate) use dot::check_strict_mode;
 mod host_selector;
 mod query;
 mod req_id;
 
 mod download;
-pub(crate) use download::{RangeReader, RangeReaderBuilder, RangeReaderInner, WriteSeek};
+pub use download::WriteSeek;
+pub(crate) use download::{RangeReader, RangeReaderBuilder, RangeReaderInner};
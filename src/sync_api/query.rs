@@ -1,4 +1,5 @@
 use super::{
+    super::base::region_hosts::{select_io_urls, RegionHosts},
     cache_dir::cache_dir_path_of,
     dot::{ApiName, DotType, Dotter},
     host_selector::HostSelector,
@@ -103,23 +104,47 @@ struct ResponseBody {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct RegionResponseBody {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    region: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    idc: Option<String>,
     ttl: u64,
     io: DomainsResponseBody,
     uc: DomainsResponseBody,
 }
 
+impl RegionResponseBody {
+    fn to_region_hosts(&self, use_https: bool) -> RegionHosts {
+        RegionHosts::new(
+            self.region.to_owned(),
+            self.idc.to_owned(),
+            self.io.normalized_domains(use_https),
+            self.uc.normalized_domains(use_https),
+        )
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct DomainsResponseBody {
     domains: Box<[Box<str>]>,
 }
 
+impl DomainsResponseBody {
+    fn normalized_domains(&self, use_https: bool) -> Vec<String> {
+        self.domains
+            .iter()
+            .map(|domain| normalize_domain(domain, use_https))
+            .collect()
+    }
+}
+
 static CACHE_MAP: Lazy<DashMap<CacheKey, CacheValue>> = Lazy::new(Default::default);
 static CACHE_FILE_LOCK: Lazy<Mutex<()>> = Lazy::new(Default::default);
 static CACHE_INIT: Lazy<()> = Lazy::new(|| {
     load_cache().ok();
 });
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub(super) struct HostsQuerier {
     uc_selector: HostSelector,
     uc_tries: usize,
@@ -142,23 +167,32 @@ impl HostsQuerier {
         }
     }
 
+    /// 查询 IO 域名，指定了 `preferred_idc` 时优先使用匹配的区域或机房的域名
     pub(super) fn query_for_io_urls(
         &self,
         ak: &str,
         bucket: &str,
         use_https: bool,
+        preferred_idc: Option<&str>,
     ) -> IOResult<Vec<String>> {
+        let region_hosts = self.query_for_region_hosts(ak, bucket, use_https)?;
+        Ok(select_io_urls(&region_hosts, preferred_idc))
+    }
+
+    /// 查询按区域分组的域名列表
+    pub(super) fn query_for_region_hosts(
+        &self,
+        ak: &str,
+        bucket: &str,
+        use_https: bool,
+    ) -> IOResult<Vec<RegionHosts>> {
         Lazy::force(&CACHE_INIT);
 
         Ok(self
             .query_for_domains(ak, bucket, use_https)?
             .hosts
-            .first()
-            .expect("No host in uc query v4 response body")
-            .io
-            .domains
             .iter()
-            .map(|domain| normalize_domain(domain, use_https))
+            .map(|region| region.to_region_hosts(use_https))
             .collect())
     }
 
@@ -440,7 +474,7 @@ fn normalize_domain(domain: &str, use_https: bool) -> String {
 mod tests {
     use super::{
         super::{
-            super::{base::credential::Credential, config::Timeouts},
+            super::{base::credential::Credential, config::HttpClientTimeouts},
             dot::{DotRecordKey, DotRecords, DotRecordsDashMap, DOT_FILE_NAME},
         },
         *,
@@ -558,7 +592,7 @@ mod tests {
         starts_with_server!(uc_addr, monitor_addr, uc_routes, monitor_routes, {
             spawn_blocking(move || -> IOResult<()> {
                 let dotter = Dotter::new(
-                    Timeouts::default_http_client(),
+                    HttpClientTimeouts::default_http_client(),
                     get_credential(),
                     BUCKET_NAME.to_owned(),
                     vec!["http://".to_owned() + &monitor_addr.to_string()],
@@ -569,13 +603,18 @@ mod tests {
                     None,
                     None,
                     None,
+                    None,
                 );
                 let host_selector =
                     HostSelector::builder(vec!["http://".to_owned() + &uc_addr.to_string()])
                         .build();
-                let querier =
-                    HostsQuerier::new(host_selector, 1, dotter, Timeouts::default_http_client());
-                let io_urls = querier.query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false)?;
+                let querier = HostsQuerier::new(
+                    host_selector,
+                    1,
+                    dotter,
+                    HttpClientTimeouts::default_http_client(),
+                );
+                let io_urls = querier.query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false, None)?;
                 assert_eq!(&io_urls, &["http://iovip.qbox.me".to_owned()]);
                 assert_eq!(
                     &querier.uc_selector.hosts(),
@@ -644,7 +683,7 @@ mod tests {
         starts_with_server!(uc_addr, monitor_addr, uc_routes, monitor_routes, {
             spawn_blocking(move || -> IOResult<()> {
                 let dotter = Dotter::new(
-                    Timeouts::default_http_client(),
+                    HttpClientTimeouts::default_http_client(),
                     get_credential(),
                     BUCKET_NAME.to_owned(),
                     vec!["http://".to_owned() + &monitor_addr.to_string()],
@@ -655,24 +694,29 @@ mod tests {
                     None,
                     None,
                     None,
+                    None,
                 );
                 let host_selector =
                     HostSelector::builder(vec!["http://".to_owned() + &uc_addr.to_string()])
                         .build();
-                let hosts_querier =
-                    HostsQuerier::new(host_selector, 1, dotter, Timeouts::default_http_client());
+                let hosts_querier = HostsQuerier::new(
+                    host_selector,
+                    1,
+                    dotter,
+                    HttpClientTimeouts::default_http_client(),
+                );
                 let mut io_urls =
-                    hosts_querier.query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false)?;
+                    hosts_querier.query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false, None)?;
                 assert_eq!(io_urls, vec!["http://iovip.qbox.me".to_owned()]);
                 assert_eq!(uc_called.load(Relaxed), 1);
 
-                io_urls = hosts_querier.query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false)?;
+                io_urls = hosts_querier.query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false, None)?;
                 assert_eq!(io_urls, vec!["http://iovip.qbox.me".to_owned()]);
                 assert_eq!(uc_called.load(Relaxed), 1);
 
                 sleep(Duration::from_secs(3));
 
-                io_urls = hosts_querier.query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false)?;
+                io_urls = hosts_querier.query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false, None)?;
                 assert_eq!(io_urls, vec!["http://iovip.qbox.me".to_owned()]);
                 assert_eq!(uc_called.load(Relaxed), 1);
 
@@ -682,7 +726,7 @@ mod tests {
                 CACHE_MAP.clear();
                 load_cache().ok();
 
-                io_urls = hosts_querier.query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false)?;
+                io_urls = hosts_querier.query_for_io_urls(ACCESS_KEY, BUCKET_NAME, false, None)?;
                 assert_eq!(io_urls, vec!["http://iovip.qbox.me".to_owned()]);
                 assert_eq!(uc_called.load(Relaxed), 2);
 
@@ -0,0 +1,315 @@
+use hyper::{
+    header::{HeaderValue, CONTENT_LENGTH, CONTENT_RANGE, RANGE},
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    spawn,
+    sync::oneshot::{channel, Sender},
+    time::sleep,
+};
+
+/// 测试服务器对单个请求的行为
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TestBehavior {
+    /// 正常响应
+    Normal,
+    /// 等待指定时长后正常响应
+    Delay(Duration),
+    /// 返回指定的状态码，响应体为空
+    Status(u16),
+    /// 返回完整的响应头，但仅发送响应体的前若干字节后断开连接
+    Truncate(usize),
+    /// 不返回响应，直接断开连接
+    Drop,
+}
+
+/// 用于集成测试的模拟 IO 服务器
+///
+/// 在本地随机端口上提供对象下载，支持 HEAD 请求和单个范围的 Range 请求，多个范围的 Range 请求将返回完整对象。
+/// 对象名称即为 URL 路径去掉开头的 `/`，不做 URL 解码。
+///
+/// 通过 [`Self::push_behaviors`] 依次指定之后每个请求的行为，用于构造确定的故障序列，未指定行为的请求正常响应。
+/// 需要在 Tokio 运行时中创建，该结构体被释放时服务器关闭
+#[derive(Debug)]
+pub struct TestBackend {
+    addr: SocketAddr,
+    state: Arc<State>,
+    shutdown: Option<Sender<()>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    objects: Mutex<HashMap<String, Vec<u8>>>,
+    behaviors: Mutex<VecDeque<TestBehavior>>,
+    requests: AtomicUsize,
+}
+
+enum ByteRange {
+    Whole,
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+impl TestBackend {
+    /// 在本地随机端口上启动测试服务器
+    pub async fn start() -> IoResult<Self> {
+        let state = Arc::new(State::default());
+        let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .map_err(|err| IoError::new(IoErrorKind::AddrNotAvailable, err))?
+            .serve(make_service_fn({
+                let state = state.to_owned();
+                move |_| {
+                    let state = state.to_owned();
+                    async move {
+                        Ok::<_, Infallible>(service_fn(move |req| handle(state.to_owned(), req)))
+                    }
+                }
+            }));
+        let addr = server.local_addr();
+        let (tx, rx) = channel();
+        spawn(server.with_graceful_shutdown(async move {
+            rx.await.ok();
+        }));
+        Ok(Self {
+            addr,
+            state,
+            shutdown: Some(tx),
+        })
+    }
+
+    /// 获取服务器地址
+    #[inline]
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 获取服务器 URL，可以直接作为 IO 域名使用
+    #[inline]
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// 上传对象，已存在的对象将被覆盖
+    pub fn put_object(&self, key: impl Into<String>, data: impl Into<Vec<u8>>) {
+        self.state
+            .objects
+            .lock()
+            .unwrap()
+            .insert(key.into(), data.into());
+    }
+
+    /// 删除对象，如果对象存在则返回 true
+    pub fn remove_object(&self, key: &str) -> bool {
+        self.state.objects.lock().unwrap().remove(key).is_some()
+    }
+
+    /// 追加之后请求的行为，每个请求按顺序消耗一个行为
+    pub fn push_behaviors(&self, behaviors: impl IntoIterator<Item = TestBehavior>) {
+        self.state.behaviors.lock().unwrap().extend(behaviors);
+    }
+
+    /// 清空尚未消耗的行为
+    pub fn clear_behaviors(&self) {
+        self.state.behaviors.lock().unwrap().clear();
+    }
+
+    /// 获取服务器收到的请求数量
+    #[inline]
+    pub fn requests(&self) -> usize {
+        self.state.requests.load(Relaxed)
+    }
+}
+
+impl Drop for TestBackend {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+async fn handle(state: Arc<State>, req: Request<Body>) -> IoResult<Response<Body>> {
+    state.requests.fetch_add(1, Relaxed);
+    let behavior = state
+        .behaviors
+        .lock()
+        .unwrap()
+        .pop_front()
+        .unwrap_or(TestBehavior::Normal);
+    match behavior {
+        TestBehavior::Normal => Ok(respond(&state, &req).map(Body::from)),
+        TestBehavior::Delay(delay) => {
+            sleep(delay).await;
+            Ok(respond(&state, &req).map(Body::from))
+        }
+        TestBehavior::Status(status) => {
+            let mut resp = Response::new(Body::empty());
+            *resp.status_mut() = StatusCode::from_u16(status)
+                .map_err(|err| IoError::new(IoErrorKind::InvalidInput, err))?;
+            Ok(resp)
+        }
+        TestBehavior::Truncate(size) => {
+            let (mut parts, mut data) = respond(&state, &req).into_parts();
+            parts
+                .headers
+                .insert(CONTENT_LENGTH, HeaderValue::from(data.len()));
+            data.truncate(size);
+            let (mut sender, body) = Body::channel();
+            spawn(async move {
+                sender.send_data(data.into()).await.ok();
+                sender.abort();
+            });
+            Ok(Response::from_parts(parts, body))
+        }
+        TestBehavior::Drop => Err(IoError::new(
+            IoErrorKind::ConnectionAborted,
+            "connection is dropped by test backend",
+        )),
+    }
+}
+
+fn respond(state: &State, req: &Request<Body>) -> Response<Vec<u8>> {
+    let key = req.uri().path().trim_start_matches('/');
+    let data = match state.objects.lock().unwrap().get(key) {
+        Some(data) => data.to_owned(),
+        None => return status_response(StatusCode::NOT_FOUND),
+    };
+    let size = data.len() as u64;
+    let range = req
+        .headers()
+        .get(RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(ByteRange::Whole, |value| parse_range(value, size));
+    match range {
+        ByteRange::Whole => Response::new(data),
+        ByteRange::Partial(from, to) => {
+            let mut resp = Response::new(data[from as usize..=to as usize].to_vec());
+            *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+            resp.headers_mut().insert(
+                CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes {}-{}/{}", from, to, size)).unwrap(),
+            );
+            resp
+        }
+        ByteRange::Unsatisfiable => {
+            let mut resp = status_response(StatusCode::RANGE_NOT_SATISFIABLE);
+            resp.headers_mut().insert(
+                CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes */{}", size)).unwrap(),
+            );
+            resp
+        }
+    }
+}
+
+fn status_response(status: StatusCode) -> Response<Vec<u8>> {
+    let mut resp = Response::new(Vec::new());
+    *resp.status_mut() = status;
+    resp
+}
+
+/// 解析单个范围的 Range 头，无法解析或包含多个范围时视为请求完整对象
+fn parse_range(value: &str, size: u64) -> ByteRange {
+    let spec = match value.trim().strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec,
+        _ => return ByteRange::Whole,
+    };
+    let (from, to) = match spec.split_once('-') {
+        Some((from, to)) => (from.trim(), to.trim()),
+        None => return ByteRange::Whole,
+    };
+    match (from.parse::<u64>(), to.parse::<u64>()) {
+        (Err(_), Ok(suffix)) if from.is_empty() => {
+            if suffix > 0 && size > 0 {
+                ByteRange::Partial(size.saturating_sub(suffix), size - 1)
+            } else {
+                ByteRange::Unsatisfiable
+            }
+        }
+        (Ok(from), Err(_)) if to.is_empty() => {
+            if from < size {
+                ByteRange::Partial(from, size - 1)
+            } else {
+                ByteRange::Unsatisfiable
+            }
+        }
+        (Ok(from), Ok(to)) if from <= to => {
+            if from < size {
+                ByteRange::Partial(from, to.min(size - 1))
+            } else {
+                ByteRange::Unsatisfiable
+            }
+        }
+        _ => ByteRange::Whole,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            async_api::AsyncRangeReader,
+            base::download::RangeReaderBuilder as BaseRangeReaderBuilder, Credential,
+        },
+        *,
+    };
+
+    #[tokio::test]
+    async fn test_test_backend() -> anyhow::Result<()> {
+        env_logger::try_init().ok();
+
+        let backend = TestBackend::start().await?;
+        backend.put_object("file", "1234567890");
+        let client = reqwest::Client::new();
+        let url = format!("{}/file", backend.url());
+
+        let resp = client.get(&url).header(RANGE, "bytes=2-4").send().await?;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.bytes().await?.as_ref(), b"345");
+        let resp = client.get(&url).header(RANGE, "bytes=20-").send().await?;
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        backend.push_behaviors([
+            TestBehavior::Status(503),
+            TestBehavior::Drop,
+            TestBehavior::Truncate(3),
+        ]);
+        assert_eq!(
+            client.get(&url).send().await?.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert!(client.get(&url).send().await.is_err());
+        assert!(client.get(&url).send().await?.bytes().await.is_err());
+        assert_eq!(backend.requests(), 5);
+
+        backend.push_behaviors([TestBehavior::Status(503), TestBehavior::Drop]);
+        let downloader = AsyncRangeReader::from_base_builder(
+            BaseRangeReaderBuilder::new(
+                "bucket".to_owned(),
+                "file".to_owned(),
+                Credential::new("1234567890", "abcdefghijk"),
+                vec![backend.url()],
+            )
+            .io_tries(5)
+            .use_getfile_api(false)
+            .normalize_key(true),
+        );
+        assert_eq!(downloader.download().await?, b"1234567890");
+        assert!(backend.requests() >= 8);
+
+        Ok(())
+    }
+}